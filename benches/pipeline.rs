@@ -0,0 +1,103 @@
+//! Measure each stage of handling a path request across randomised worlds of
+//! 10x10, 50x50 and 100x100 sectors generated by the `bench_fixtures` module.
+//! Each request travels the same number of sectors diagonally across the
+//! centre of the world so the results show how the size of a world affects
+//! a typical request
+//!
+
+use bevy::utils::Duration;
+use bevy_flowfield_tiles_plugin::{bench_fixtures::*, prelude::*};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Number of sectors along each side of the benchmarked worlds
+const SIZES: [u32; 3] = [10, 50, 100];
+/// Seed used for every world so results are comparable between runs
+const SEED: u64 = 7;
+/// Chance of any FieldCell being impassable
+const IMPASSABLE_RATIO: f32 = 0.1;
+/// Number of sectors travelled along each axis by the benchmarked request
+const REQUEST_SPAN: u32 = 5;
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+	let mut group = c.benchmark_group("pipeline");
+	group.significance_level(0.1).sample_size(10);
+	for size in SIZES {
+		let bundle = random_bundle(size, size, SEED, IMPASSABLE_RATIO);
+		let start = (size - REQUEST_SPAN) / 2;
+		let request = sector_to_sector_request(
+			SectorID::new(start, start),
+			SectorID::new(start + REQUEST_SPAN, start + REQUEST_SPAN - 1),
+		);
+		let route = find_bench_route(&bundle, &request).unwrap();
+		let int_builder = build_bench_integration(&bundle, &route);
+		group.bench_with_input(
+			BenchmarkId::new("graph_build", size),
+			&bundle,
+			|b, bundle| {
+				b.iter(|| {
+					PortalGraph::new(
+						black_box(&bundle.sector_portals),
+						black_box(&bundle.sector_cost_fields),
+						black_box(&bundle.map_dimensions),
+					)
+				})
+			},
+		);
+		// a sector in the middle of the world has all of its boundaries and
+		// those of its neighbours rebuilt
+		let sector_id = SectorID::new(size / 2, size / 2);
+		group.bench_with_input(
+			BenchmarkId::new("portal_recalculation", size),
+			&bundle,
+			|b, bundle| {
+				b.iter_batched(
+					|| (bundle.sector_portals.clone(), bundle.portal_graph.clone()),
+					|(mut portals, mut graph)| {
+						portals.update_portals(
+							sector_id,
+							&bundle.sector_cost_fields,
+							&bundle.map_dimensions,
+						);
+						graph.update_graph(
+							sector_id,
+							&portals,
+							&bundle.sector_cost_fields,
+							&bundle.map_dimensions,
+						);
+					},
+					criterion::BatchSize::LargeInput,
+				)
+			},
+		);
+		group.bench_with_input(
+			BenchmarkId::new("integration_build", size),
+			&bundle,
+			|b, bundle| b.iter(|| build_bench_integration(black_box(bundle), black_box(&route))),
+		);
+		group.bench_with_input(
+			BenchmarkId::new("flow_build", size),
+			&int_builder,
+			|b, int_builder| {
+				b.iter(|| {
+					let mut cache = FlowFieldCache::default();
+					cache.insert_flow_fields(black_box(int_builder), Duration::default());
+				})
+			},
+		);
+		group.bench_with_input(
+			BenchmarkId::new("full_request", size),
+			&bundle,
+			|b, bundle| {
+				b.iter_batched(
+					|| bundle.clone(),
+					|mut bundle| bundle.prewarm(black_box(&[request])),
+					criterion::BatchSize::LargeInput,
+				)
+			},
+		);
+	}
+	group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
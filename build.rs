@@ -0,0 +1,12 @@
+//! Defines the cfg aliases shared across the crate
+//!
+
+use cfg_aliases::cfg_aliases;
+
+/// Rayon's thread pool isn't available on wasm, there the `parallel` feature
+/// falls back to iterating serially
+fn main() {
+	cfg_aliases! {
+		use_rayon: { all(feature = "parallel", not(target_arch = "wasm32")) },
+	}
+}
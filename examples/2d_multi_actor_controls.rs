@@ -18,7 +18,7 @@ fn main() {
 		))
 		.insert_resource(SubstepCount(30))
 		.insert_resource(Gravity(Vec2::ZERO))
-		.add_plugins(FlowFieldTilesPlugin)
+		.add_plugins(FlowFieldTilesPlugin::default())
 		.add_systems(
 			Startup,
 			(setup_visualisation, setup_navigation, create_wall_colliders),
@@ -27,7 +27,7 @@ fn main() {
 		))
 		.insert_resource(SubstepCount(6))
 		.insert_resource(Gravity(Vec2::ZERO))
-		.add_plugins(FlowFieldTilesPlugin)
+		.add_plugins(FlowFieldTilesPlugin::default())
 		.add_systems(Startup, (setup, create_wall_colliders, create_counters))
 		.add_systems(PreUpdate, click_update_cost)
 		// .insert_resource(Time::<Fixed>::from_seconds(0.1))
@@ -22,7 +22,7 @@ fn main() {
 		.insert_resource(Time::<Fixed>::from_duration(Duration::from_secs_f32(
 			ACTOR_TIMESTEP,
 		)))
-		.add_plugins(FlowFieldTilesPlugin)
+		.add_plugins(FlowFieldTilesPlugin::default())
 		.add_systems(Startup, (setup_visualisation, setup_navigation))
 		.add_systems(Update, (user_input, actor_update_route))
 		.add_systems(FixedUpdate, (actor_steering, apply_velocity).chain())
@@ -7,7 +7,7 @@ use bevy_flowfield_tiles_plugin::prelude::*;
 fn main() {
 	App::new()
 		.add_plugins(DefaultPlugins)
-		.add_plugins(FlowFieldTilesPlugin)
+		.add_plugins(FlowFieldTilesPlugin::default())
 		.add_systems(Startup, (setup,))
 		.add_systems(
 			Update,
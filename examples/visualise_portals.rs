@@ -12,7 +12,7 @@ use bevy_flowfield_tiles_plugin::prelude::*;
 fn main() {
 	App::new()
 		.add_plugins(DefaultPlugins)
-		.add_plugins(FlowFieldTilesPlugin)
+		.add_plugins(FlowFieldTilesPlugin::default())
 		.add_systems(Startup, (setup_visualisation, create_counter))
 		.add_systems(Update, (update_sprites, click_update_cost, update_counter))
 		.run();
@@ -0,0 +1,236 @@
+//! Helpers for generating large randomised worlds so the performance of the
+//! pathing pipeline can be measured. They back the crate's own criterion
+//! benches and can be used to profile map sizes and layouts of your own, e.g
+//! `random_bundle(100, 100, 7, 0.1)` creates a world of `100x100` sectors
+//! where roughly a tenth of the [FieldCell]s are impassable.
+//!
+//! The same `seed` always produces the same world so that results can be
+//! compared between runs
+//!
+
+use crate::prelude::*;
+use bevy::utils::Duration;
+
+/// The `sector_resolution` of worlds created by [random_bundle]
+pub const BENCH_SECTOR_RESOLUTION: u32 = 10;
+/// The `actor_size` of worlds created by [random_bundle]
+pub const BENCH_ACTOR_SIZE: f32 = 0.5;
+
+/// Get the [FieldCell] of each sector which [random_cost_fields] keeps
+/// passable
+fn sector_centre() -> FieldCell {
+	FieldCell::new(FIELD_RESOLUTION / 2, FIELD_RESOLUTION / 2)
+}
+
+/// Small xorshift generator so fixtures are reproducible without pulling in
+/// a random number crate
+struct FixtureRng(u64);
+
+impl FixtureRng {
+	/// Create a generator from a `seed`, a seed of `0` would only ever produce
+	/// `0` so it's swapped for a constant
+	fn new(seed: u64) -> Self {
+		FixtureRng(if seed == 0 {
+			0x9E37_79B9_7F4A_7C15
+		} else {
+			seed
+		})
+	}
+	/// Get the next value of the sequence
+	fn next_u64(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+	/// Get a value within `0.0..1.0`
+	fn next_f32(&mut self) -> f32 {
+		(self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+	}
+}
+
+/// Create [SectorCostFields] of random costs where each [FieldCell] has an
+/// `impassable_ratio` chance of being impassable and is otherwise given a
+/// cost between [Cost::PASSABLE] and [Cost::ROUGH]. The corner [FieldCell]s
+/// of the map and the centre [FieldCell] of each sector are always passable
+/// so they can be used as the source and target of a request, see
+/// [corner_to_corner_request] and [sector_to_sector_request]
+pub fn random_cost_fields(
+	map_dimensions: &MapDimensions,
+	seed: u64,
+	impassable_ratio: f32,
+) -> SectorCostFields {
+	let mut rng = FixtureRng::new(seed);
+	let mut cost_fields = SectorCostFields::new(map_dimensions);
+	let corners = map_corners(map_dimensions);
+	let sector_ids: Vec<SectorID> = cost_fields.get_baseline().keys().cloned().collect();
+	for sector_id in sector_ids {
+		let Some(mut guard) = cost_fields.edit(sector_id, map_dimensions) else {
+			continue;
+		};
+		for column in 0..FIELD_RESOLUTION {
+			for row in 0..FIELD_RESOLUTION {
+				let field_cell = FieldCell::new(column, row);
+				let cost = if rng.next_f32() < impassable_ratio {
+					Cost::IMPASSABLE
+				} else {
+					let range = (Cost::ROUGH.get() - Cost::PASSABLE.get()) as u64 + 1;
+					Cost::new(Cost::PASSABLE.get() + (rng.next_u64() % range) as u8)
+				};
+				if field_cell == sector_centre() || corners.contains(&(sector_id, field_cell)) {
+					guard.set_field_cell_value(field_cell, Cost::PASSABLE);
+				} else {
+					guard.set_field_cell_value(field_cell, cost);
+				}
+			}
+		}
+	}
+	// the fields are new rather than edited so the plugin has nothing to
+	// rebuild, instead rescale them together now that every sector is set
+	cost_fields.take_edited_sectors();
+	cost_fields.scale_all_costfields(map_dimensions);
+	cost_fields
+}
+
+/// Get the corner [FieldCell]s of the map, ordered top-left, top-right,
+/// bottom-right, bottom-left
+fn map_corners(map_dimensions: &MapDimensions) -> [(SectorID, FieldCell); 4] {
+	let grid = map_dimensions.get_sector_grid();
+	let last_column = grid.get_columns().saturating_sub(1);
+	let last_row = grid.get_rows().saturating_sub(1);
+	let end = FIELD_RESOLUTION - 1;
+	[
+		(SectorID::new(0, 0), FieldCell::new(0, 0)),
+		(SectorID::new(last_column, 0), FieldCell::new(end, 0)),
+		(
+			SectorID::new(last_column, last_row),
+			FieldCell::new(end, end),
+		),
+		(SectorID::new(0, last_row), FieldCell::new(0, end)),
+	]
+}
+
+/// Create a [FlowFieldTilesBundle] of `sector_columns` by `sector_rows`
+/// sectors with [SectorCostFields] from [random_cost_fields] and the
+/// portals and graph built from them
+pub fn random_bundle(
+	sector_columns: u32,
+	sector_rows: u32,
+	seed: u64,
+	impassable_ratio: f32,
+) -> FlowFieldTilesBundle {
+	let mut bundle = FlowFieldTilesBundle::new(
+		sector_columns * BENCH_SECTOR_RESOLUTION,
+		sector_rows * BENCH_SECTOR_RESOLUTION,
+		BENCH_SECTOR_RESOLUTION,
+		BENCH_ACTOR_SIZE,
+	);
+	let map_dimensions = bundle.map_dimensions;
+	let cost_fields = random_cost_fields(&map_dimensions, seed, impassable_ratio);
+	let mut portals = SectorPortals::new(
+		map_dimensions.get_length(),
+		map_dimensions.get_depth(),
+		map_dimensions.get_sector_resolution(),
+	);
+	for sector_id in cost_fields.get_scaled().keys() {
+		portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+	}
+	bundle.portal_graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
+	bundle.sector_portals = portals;
+	bundle.sector_cost_fields = cost_fields;
+	bundle
+}
+
+/// Create a request from the top-right corner of the map to the bottom-left
+/// corner, the longest journey an actor can make across the map
+pub fn corner_to_corner_request(map_dimensions: &MapDimensions) -> RouteMetadata {
+	let [_, source, _, target] = map_corners(map_dimensions);
+	RouteMetadata::new(source.0, source.1, target.0, target.1, Duration::default())
+}
+
+/// Create a request between the centre [FieldCell]s of two sectors
+pub fn sector_to_sector_request(source: SectorID, target: SectorID) -> RouteMetadata {
+	RouteMetadata::new(
+		source,
+		sector_centre(),
+		target,
+		sector_centre(),
+		Duration::default(),
+	)
+}
+
+/// Find the [Route] of a `request` within the `bundle`, ordered from the
+/// actor to the goal. Returns [None] if the goal can't be reached
+pub fn find_bench_route(bundle: &FlowFieldTilesBundle, request: &RouteMetadata) -> Option<Route> {
+	find_route_with_fallback(
+		&bundle.portal_graph,
+		&bundle.sector_portals,
+		&bundle.sector_cost_fields,
+		&bundle.map_dimensions,
+		bundle.route_cost_model.get(),
+		request,
+	)
+	.ok()
+	.map(|(_, route)| route)
+}
+
+/// Run the portal expansion, line of sight and integrated cost passes of a
+/// `route` (ordered from the actor to the goal) so that its [FlowField]s
+/// can be built with [FlowFieldCache::insert_flow_fields]
+pub fn build_bench_integration(bundle: &FlowFieldTilesBundle, route: &Route) -> IntegrationBuilder {
+	let mut route_from_goal = route.clone();
+	route_from_goal.get_mut().reverse();
+	let mut int_builder = IntegrationBuilder::new(route_from_goal, &bundle.sector_cost_fields)
+		.with_connectivity(bundle.connectivity);
+	int_builder.expand_field_portals(
+		&bundle.sector_portals,
+		&bundle.sector_cost_fields,
+		&bundle.map_dimensions,
+	);
+	int_builder.set_expanded_portals();
+	int_builder.calculate_los();
+	int_builder.set_los_pass();
+	int_builder.build_integrated_cost(&bundle.sector_cost_fields);
+	int_builder.set_cost_pass();
+	int_builder
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn random_fields_are_seeded() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let a = random_cost_fields(&map_dimensions, 3, 0.2);
+		let b = random_cost_fields(&map_dimensions, 3, 0.2);
+		let c = random_cost_fields(&map_dimensions, 4, 0.2);
+		let sector_id = SectorID::new(1, 1);
+		let field =
+			|fields: &SectorCostFields| *fields.get_baseline().get(&sector_id).unwrap().get();
+		assert_eq!(field(&a), field(&b));
+		assert_ne!(field(&a), field(&c));
+		for (sector_id, field_cell) in map_corners(&map_dimensions) {
+			let cost = a
+				.get_baseline()
+				.get(&sector_id)
+				.unwrap()
+				.get_field_cell_value(field_cell);
+			assert_eq!(1, cost);
+		}
+	}
+	#[test]
+	fn random_bundle_routes_corner_to_corner() {
+		let mut bundle = random_bundle(10, 10, 7, 0.1);
+		let request = corner_to_corner_request(&bundle.map_dimensions);
+		assert_eq!(SectorID::new(9, 0), request.get_source_sector());
+		assert_eq!(SectorID::new(0, 9), request.get_target_sector());
+		let route = find_bench_route(&bundle, &request).unwrap();
+		let int_builder = build_bench_integration(&bundle, &route);
+		assert_eq!(
+			route.get().len(),
+			int_builder.get_integration_fields().len()
+		);
+		bundle.prewarm(&[request]);
+		assert!(bundle.ready(&request));
+	}
+}
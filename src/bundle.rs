@@ -7,7 +7,7 @@ use bevy::prelude::*;
 
 /// Defines all required components for generating [FlowField] Tiles
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
-#[derive(Bundle)]
+#[derive(Bundle, Clone)]
 pub struct FlowFieldTilesBundle {
 	/// [CostField]s of all sectors
 	pub sector_cost_fields: SectorCostFields,
@@ -19,8 +19,57 @@ pub struct FlowFieldTilesBundle {
 	pub map_dimensions: MapDimensions,
 	/// Cache of overarching portal-portal routes
 	pub route_cache: RouteCache,
+	/// Routes waiting to be promoted into the [RouteCache]
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub route_queue: RouteQueue,
 	/// Cache of [FlowField]s that can be queried in a steering pipeline
 	pub flow_field_cache: FlowFieldCache,
+	/// Routes whose [FlowField]s are being built before they're inserted
+	/// into the [FlowFieldCache]
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub flow_field_queue: FlowFieldQueue,
+	/// How routes through the [PortalGraph] are scored
+	#[cfg_attr(feature = "serde", serde(skip))]
+	pub route_cost_model: RouteCostModel,
+	/// Slots around shared goals assigned to actors
+	#[cfg_attr(feature = "serde", serde(skip))]
+	pub arrival_slots: ArrivalSlots,
+	/// Temporary cost changes which are reverted when they expire
+	#[cfg_attr(feature = "serde", serde(skip))]
+	pub cost_modifiers: CostModifiers,
+	/// Cost changes applied speculatively until they're confirmed or
+	/// reverted
+	#[cfg_attr(feature = "serde", serde(skip))]
+	pub speculative_edits: SpeculativeEdits,
+	/// Cache of [FlowField]s directing actors away from dangers
+	#[cfg_attr(feature = "serde", serde(skip))]
+	pub flee_field_cache: FleeFieldCache,
+	/// Looped series of goals whose routes and [FlowField]s are kept cached
+	#[cfg_attr(feature = "serde", serde(skip))]
+	pub patrol_routes: PatrolRoutes,
+	/// Which neighbouring field cells actors may move between
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub connectivity: Connectivity,
+	/// The plane of the world the bundle is laid out on
+	#[cfg(any(feature = "2d", feature = "3d"))]
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub coordinate_system: CoordinateSystem,
+}
+
+/// Relates an actor (or anything else issuing path requests and cost
+/// updates) to the entity of the [FlowFieldTilesBundle] it navigates within.
+/// When several bundles exist, such as separate dungeon instances, the
+/// referenced entity can be supplied to events with `with_nav_world` and used
+/// to query the caches of that bundle alone
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct NavWorld(pub Entity);
+
+impl NavWorld {
+	/// Get the entity of the [FlowFieldTilesBundle]
+	pub fn get(&self) -> Entity {
+		self.0
+	}
 }
 
 impl FlowFieldTilesBundle {
@@ -48,6 +97,14 @@ impl FlowFieldTilesBundle {
 	pub fn get_route_cache_mut(&mut self) -> &mut RouteCache {
 		&mut self.route_cache
 	}
+	/// Get a reference to the [RouteQueue]
+	pub fn get_route_queue(&self) -> &RouteQueue {
+		&self.route_queue
+	}
+	/// Get a reference to the [FlowFieldQueue]
+	pub fn get_flowfield_queue(&self) -> &FlowFieldQueue {
+		&self.flow_field_queue
+	}
 	/// Get a reference to the [FlowFieldCache]
 	pub fn get_flowfield_cache(&self) -> &FlowFieldCache {
 		&self.flow_field_cache
@@ -56,28 +113,246 @@ impl FlowFieldTilesBundle {
 	pub fn get_flowfield_cache_mut(&mut self) -> &mut FlowFieldCache {
 		&mut self.flow_field_cache
 	}
-	/// Create a new instance of [FlowFieldTilesBundle] based on map dimensions
-	pub fn new(map_length: u32, map_depth: u32, sector_resolution: u32, actor_size: f32) -> Self {
-		let map_dimensions =
-			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
-		let cost_fields = SectorCostFields::new(&map_dimensions);
-		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
-		// update default portals for cost fields
-		for sector_id in cost_fields.get_scaled().keys() {
-			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
-		}
-		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
-		let route_cache = RouteCache::default();
-		let cache = FlowFieldCache::default();
+	/// Get a reference to the [ArrivalSlots]
+	pub fn get_arrival_slots(&self) -> &ArrivalSlots {
+		&self.arrival_slots
+	}
+	/// Get a reference to the [CostModifiers]
+	pub fn get_cost_modifiers(&self) -> &CostModifiers {
+		&self.cost_modifiers
+	}
+	/// Get a reference to the [SpeculativeEdits]
+	pub fn get_speculative_edits(&self) -> &SpeculativeEdits {
+		&self.speculative_edits
+	}
+	/// Get a reference to the [FleeFieldCache]
+	pub fn get_flee_field_cache(&self) -> &FleeFieldCache {
+		&self.flee_field_cache
+	}
+	/// Get a reference to the [PatrolRoutes]
+	pub fn get_patrol_routes(&self) -> &PatrolRoutes {
+		&self.patrol_routes
+	}
+	/// Get a mutable reference to the [PatrolRoutes] so that patrols can be
+	/// registered before the bundle is spawned
+	pub fn get_patrol_routes_mut(&mut self) -> &mut PatrolRoutes {
+		&mut self.patrol_routes
+	}
+	/// Get a reference to the [RouteCostModel]
+	pub fn get_route_cost_model(&self) -> &RouteCostModel {
+		&self.route_cost_model
+	}
+	/// Score routes through the [PortalGraph] with a custom [PortalCostModel]
+	pub fn with_route_cost_model(mut self, model: impl PortalCostModel + 'static) -> Self {
+		self.route_cost_model = RouteCostModel::new(model);
+		self
+	}
+	/// Produce a [UserFlagField] alongside every [FlowField] the bundle
+	/// generates with a custom [FlowFlagHook]
+	pub fn with_flow_flag_hook(mut self, hook: impl FlowFlagHook + 'static) -> Self {
+		self.flow_field_cache.set_flag_hook(hook);
+		self
+	}
+	/// Get the [Connectivity] of actors navigating the bundle
+	pub fn get_connectivity(&self) -> Connectivity {
+		self.connectivity
+	}
+	/// Restrict actors to moving between field cells with the given
+	/// [Connectivity], such as [Connectivity::FourWay] for board-game style
+	/// movement
+	pub fn with_connectivity(mut self, connectivity: Connectivity) -> Self {
+		self.connectivity = connectivity;
+		self
+	}
+	/// Get the [CoordinateSystem] the bundle is laid out on
+	#[cfg(any(feature = "2d", feature = "3d"))]
+	pub fn get_coordinate_system(&self) -> CoordinateSystem {
+		self.coordinate_system
+	}
+	/// Lay the bundle out on a [CoordinateSystem] other than the default, so
+	/// that with both the `2d` and `3d` features enabled requests from entity
+	/// translations are converted appropriately for each bundle
+	#[cfg(any(feature = "2d", feature = "3d"))]
+	pub fn with_coordinate_system(mut self, coordinate_system: CoordinateSystem) -> Self {
+		self.coordinate_system = coordinate_system;
+		self
+	}
+	/// Take a snapshot of the navigation state, this can be stored by a
+	/// rollback scheme and later reinstated with [FlowFieldTilesBundle::restore_state]
+	pub fn clone_state(&self) -> Self {
+		self.clone()
+	}
+	/// Reinstate the navigation state from a snapshot previously taken with
+	/// [FlowFieldTilesBundle::clone_state]
+	pub fn restore_state(&mut self, state: &FlowFieldTilesBundle) {
+		self.clone_from(state);
+	}
+	/// Synchronously calculate the routes and [FlowField]s for a series of
+	/// requests so that they are cached before they're needed, for instance
+	/// during a loading screen when the spawn and goal positions of a map are
+	/// known in advance
+	pub fn prewarm(&mut self, requests: &[RouteMetadata]) {
+		for metadata in requests {
+			let Ok((goal, route)) = find_route_with_fallback(
+				&self.portal_graph,
+				&self.sector_portals,
+				&self.sector_cost_fields,
+				&self.map_dimensions,
+				self.route_cost_model.get(),
+				metadata,
+			) else {
+				warn!(
+					"Unable to prewarm route from {:?} to {:?}",
+					(
+						metadata.get_source_sector(),
+						metadata.get_source_field_cell()
+					),
+					(metadata.get_target_sector(), metadata.get_target_goal())
+				);
+				continue;
+			};
+			let mut metadata = *metadata;
+			metadata.set_resolved_target(goal.0, goal.1);
+			let mut route_from_goal = route.clone();
+			route_from_goal.get_mut().reverse();
+			self.route_cache.insert_route_with_metadata(metadata, route);
+			let mut int_builder =
+				IntegrationBuilder::new(route_from_goal, &self.sector_cost_fields)
+					.with_connectivity(self.connectivity)
+					.with_goal_seeds(metadata.get_goal_seeds());
+			if let Some(faction) = metadata.get_faction() {
+				int_builder = int_builder.with_faction(faction);
+			}
+			int_builder.skip_fields_within_stopping_distance(&metadata, &self.map_dimensions);
+			int_builder.expand_field_portals(
+				&self.sector_portals,
+				&self.sector_cost_fields,
+				&self.map_dimensions,
+			);
+			int_builder.set_expanded_portals();
+			self.flow_field_cache
+				.share_cached_fields_into(&mut int_builder);
+			int_builder.calculate_los();
+			int_builder.set_los_pass();
+			int_builder.build_integrated_cost(&self.sector_cost_fields);
+			int_builder.set_cost_pass();
+			self.flow_field_cache
+				.insert_flow_fields(&int_builder, metadata.get_time_generated());
+		}
+	}
+	/// Whether the route and all [FlowField]s of a request have been built
+	/// and cached
+	pub fn ready(&self, request: &RouteMetadata) -> bool {
+		self.route_cache
+			.get_route_from_metadata(request)
+			.is_some_and(|(_, route)| {
+				self.flow_field_cache
+					.is_route_built_for(request, &route, &self.map_dimensions)
+			})
+	}
+	/// Create a new instance of [FlowFieldTilesBundle] from [SectorCostFields]
+	/// which have already been built for the `map_dimensions`, the portals and
+	/// [PortalGraph] are derived from them
+	pub fn from_sector_cost_fields(
+		map_dimensions: MapDimensions,
+		cost_fields: SectorCostFields,
+	) -> Self {
+		FlowFieldTilesBundle::from_sector_cost_fields_with_progress(
+			map_dimensions,
+			cost_fields,
+			|_, _| {},
+		)
+	}
+	/// As with [FlowFieldTilesBundle::from_sector_cost_fields] but `progress`
+	/// is called with the [BundleBuildStage] being worked on and the fraction
+	/// of it completed so far
+	pub fn from_sector_cost_fields_with_progress(
+		map_dimensions: MapDimensions,
+		cost_fields: SectorCostFields,
+		progress: impl FnMut(BundleBuildStage, f32),
+	) -> Self {
+		FlowFieldTilesBundle::from_sector_cost_fields_with_boundary_distance(
+			map_dimensions,
+			cost_fields,
+			SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE,
+			progress,
+		)
+	}
+	/// As with [FlowFieldTilesBundle::from_sector_cost_fields_with_progress]
+	/// but the [PortalGraph] is built with the `boundary_distance`, see
+	/// [PortalGraph::new_with_boundary_distance]
+	pub(crate) fn from_sector_cost_fields_with_boundary_distance(
+		map_dimensions: MapDimensions,
+		cost_fields: SectorCostFields,
+		boundary_distance: i32,
+		mut progress: impl FnMut(BundleBuildStage, f32),
+	) -> Self {
+		let mut portals = SectorPortals::new(
+			map_dimensions.get_length(),
+			map_dimensions.get_depth(),
+			map_dimensions.get_sector_resolution(),
+		);
+		// a single sector has no neighbours to build portals or a graph for
+		let graph = if map_dimensions.is_single_sector() {
+			let mut graph = PortalGraph::default();
+			graph.set_boundary_distance(boundary_distance);
+			graph
+		} else {
+			// update default portals for cost fields
+			let sector_count = cost_fields.get_scaled().len();
+			for (i, sector_id) in cost_fields.get_scaled().keys().enumerate() {
+				portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+				progress(
+					BundleBuildStage::Portals,
+					(i + 1) as f32 / sector_count as f32,
+				);
+			}
+			progress(BundleBuildStage::PortalGraph, 0.0);
+			PortalGraph::new_with_boundary_distance(
+				&portals,
+				&cost_fields,
+				&map_dimensions,
+				boundary_distance,
+			)
+		};
+		progress(BundleBuildStage::PortalGraph, 1.0);
+		FlowFieldTilesBundle::from_parts(map_dimensions, cost_fields, portals, graph)
+	}
+	/// Assemble a [FlowFieldTilesBundle] from fields, portals and a graph
+	/// which have already been built, the caches start empty
+	pub(crate) fn from_parts(
+		map_dimensions: MapDimensions,
+		cost_fields: SectorCostFields,
+		portals: SectorPortals,
+		graph: PortalGraph,
+	) -> Self {
 		FlowFieldTilesBundle {
 			sector_cost_fields: cost_fields,
 			sector_portals: portals,
 			portal_graph: graph,
 			map_dimensions,
-			route_cache,
-			flow_field_cache: cache,
+			route_cache: RouteCache::default(),
+			route_queue: RouteQueue::default(),
+			flow_field_cache: FlowFieldCache::default(),
+			flow_field_queue: FlowFieldQueue::default(),
+			route_cost_model: RouteCostModel::default(),
+			arrival_slots: ArrivalSlots::default(),
+			cost_modifiers: CostModifiers::default(),
+			speculative_edits: SpeculativeEdits::default(),
+			flee_field_cache: FleeFieldCache::default(),
+			patrol_routes: PatrolRoutes::default(),
+			connectivity: Connectivity::default(),
+			#[cfg(any(feature = "2d", feature = "3d"))]
+			coordinate_system: CoordinateSystem::default(),
 		}
 	}
+	/// Create a new instance of [FlowFieldTilesBundle] based on map dimensions
+	pub fn new(map_length: u32, map_depth: u32, sector_resolution: u32, actor_size: f32) -> Self {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		FlowFieldTilesBundle::from_sector_cost_fields(map_dimensions, cost_fields)
+	}
 	/// Create a new instance of [FlowFieldTilesBundle] based on map dimensions where the [SectorCostFields] are derived from a `.ron` file
 	#[cfg(feature = "ron")]
 	pub fn from_ron(
@@ -90,27 +365,38 @@ impl FlowFieldTilesBundle {
 		let map_dimensions =
 			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
 		let cost_fields = SectorCostFields::from_ron(path.to_string(), &map_dimensions);
+		FlowFieldTilesBundle::from_ron_cost_fields(map_dimensions, cost_fields)
+	}
+	/// As with [FlowFieldTilesBundle::from_ron] but the [SectorCostFields] are
+	/// deserialized from the contents of a `.ron` file, for platforms without
+	/// a filesystem such as the web
+	#[cfg(feature = "ron")]
+	pub fn from_ron_str(
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		data: &str,
+	) -> Self {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields = SectorCostFields::from_ron_str(data, &map_dimensions);
+		FlowFieldTilesBundle::from_ron_cost_fields(map_dimensions, cost_fields)
+	}
+	/// Initialise a bundle from deserialized [SectorCostFields], panicking if
+	/// they don't cover every sector of the map
+	#[cfg(feature = "ron")]
+	fn from_ron_cost_fields(map_dimensions: MapDimensions, cost_fields: SectorCostFields) -> Self {
+		let map_length = map_dimensions.get_length();
+		let map_depth = map_dimensions.get_depth();
+		let sector_resolution = map_dimensions.get_sector_resolution();
+		let sector_count = cost_fields.get_baseline().len() + cost_fields.get_void_sectors().len();
 		if ((map_length * map_depth) / (sector_resolution * sector_resolution)) as usize
-			!= cost_fields.get_baseline().len()
+			!= sector_count
 		{
-			panic!("Map size ({}, {}) with resolution {} produces ({}x{}) sectors. Ron file only produces {} sectors", map_length, map_depth, sector_resolution, map_length/sector_resolution, map_depth/sector_resolution, cost_fields.get_baseline().len());
-		}
-		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
-		// update default portals for cost fields
-		for sector_id in cost_fields.get_scaled().keys() {
-			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
-		}
-		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
-		let route_cache = RouteCache::default();
-		let cache = FlowFieldCache::default();
-		FlowFieldTilesBundle {
-			sector_cost_fields: cost_fields,
-			sector_portals: portals,
-			portal_graph: graph,
-			map_dimensions,
-			route_cache,
-			flow_field_cache: cache,
+			panic!("Map size ({}, {}) with resolution {} produces ({}x{}) sectors. Ron file only produces {} sectors", map_length, map_depth, sector_resolution, map_length/sector_resolution, map_depth/sector_resolution, sector_count);
 		}
+		FlowFieldTilesBundle::from_sector_cost_fields(map_dimensions, cost_fields)
 	}
 	/// Create a new instance of [FlowFieldTilesBundle] from a directory containing CSV [CostField] files
 	#[cfg(not(tarpaulin_include))]
@@ -125,22 +411,24 @@ impl FlowFieldTilesBundle {
 		let map_dimensions =
 			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
 		let cost_fields = SectorCostFields::from_csv_dir(&map_dimensions, directory.to_string());
-		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
-		// update default portals for cost fields
-		for sector_id in cost_fields.get_scaled().keys() {
-			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
-		}
-		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
-		let route_cache = RouteCache::default();
-		let cache = FlowFieldCache::default();
-		FlowFieldTilesBundle {
-			sector_cost_fields: cost_fields,
-			sector_portals: portals,
-			portal_graph: graph,
-			map_dimensions,
-			route_cache,
-			flow_field_cache: cache,
-		}
+		FlowFieldTilesBundle::from_sector_cost_fields(map_dimensions, cost_fields)
+	}
+	/// Create a new instance of [FlowFieldTilesBundle] from the CSV
+	/// [CostField] of each sector read from any source, see
+	/// [SectorCostFields::from_csv_readers]
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "csv")]
+	pub fn from_csv_readers<R: std::io::Read>(
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		readers: impl IntoIterator<Item = (SectorID, R)>,
+	) -> Self {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields = SectorCostFields::from_csv_readers(&map_dimensions, readers);
+		FlowFieldTilesBundle::from_sector_cost_fields(map_dimensions, cost_fields)
 	}
 	/// From a greyscale heightmap image initialise a bundle where the
 	/// [CostField]s are derived from the pixel values of the image
@@ -152,26 +440,54 @@ impl FlowFieldTilesBundle {
 		sector_resolution: u32,
 		actor_size: f32,
 		file_path: &str,
+	) -> Self {
+		FlowFieldTilesBundle::from_heightmap_with_progress(
+			map_length,
+			map_depth,
+			sector_resolution,
+			actor_size,
+			file_path,
+			|_| {},
+		)
+	}
+	/// As with [FlowFieldTilesBundle::from_heightmap] but the image is
+	/// decoded from the bytes of an image file, see
+	/// [SectorCostFields::from_image_bytes]
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "heightmap")]
+	pub fn from_image_bytes(
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		bytes: &[u8],
 	) -> Self {
 		let map_dimensions =
 			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
-		let cost_fields = SectorCostFields::from_heightmap(&map_dimensions, file_path.to_string());
-		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
-		// update default portals for cost fields
-		for sector_id in cost_fields.get_scaled().keys() {
-			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
-		}
-		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
-		let route_cache = RouteCache::default();
-		let cache = FlowFieldCache::default();
-		FlowFieldTilesBundle {
-			sector_cost_fields: cost_fields,
-			sector_portals: portals,
-			portal_graph: graph,
-			map_dimensions,
-			route_cache,
-			flow_field_cache: cache,
-		}
+		let cost_fields = SectorCostFields::from_image_bytes(&map_dimensions, bytes);
+		FlowFieldTilesBundle::from_sector_cost_fields(map_dimensions, cost_fields)
+	}
+	/// As with [FlowFieldTilesBundle::from_heightmap] but `progress` is
+	/// called with the fraction of the [CostField]s built so far, see
+	/// [SectorCostFields::from_heightmap_with_progress]
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "heightmap")]
+	pub fn from_heightmap_with_progress(
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		file_path: &str,
+		progress: impl FnMut(f32),
+	) -> Self {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields = SectorCostFields::from_heightmap_with_progress(
+			&map_dimensions,
+			file_path.to_string(),
+			progress,
+		);
+		FlowFieldTilesBundle::from_sector_cost_fields(map_dimensions, cost_fields)
 	}
 	/// From a list of 2d meshes and their translation initialise a bundle. The vertex points of the meshes must be within the `map_length` and `map_depth` of the world.
 	///
@@ -189,41 +505,545 @@ impl FlowFieldTilesBundle {
 		internal_cost: u8,
 		external_cost: u8,
 	) -> Self {
-		let map_dimensions =
-			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
-		let cost_fields = SectorCostFields::from_bevy_2d_meshes(
+		FlowFieldTilesBundle::from_bevy_2d_meshes_with_progress(
+			meshes,
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size),
+			internal_cost,
+			external_cost,
+			|_| {},
+		)
+	}
+	/// As with [FlowFieldTilesBundle::from_bevy_2d_meshes] but `progress` is
+	/// called with the fraction of the [CostField]s built so far, see
+	/// [SectorCostFields::from_bevy_2d_meshes_with_progress]
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "2d")]
+	pub fn from_bevy_2d_meshes_with_progress(
+		meshes: Vec<(&Mesh, Vec2)>,
+		map_dimensions: MapDimensions,
+		internal_cost: u8,
+		external_cost: u8,
+		progress: impl FnMut(f32),
+	) -> Self {
+		let cost_fields = SectorCostFields::from_bevy_2d_meshes_with_progress(
 			&map_dimensions,
 			&meshes,
 			internal_cost,
 			external_cost,
+			progress,
 		);
-		let mut portals = SectorPortals::new(
-			map_dimensions.get_length(),
-			map_dimensions.get_depth(),
-			sector_resolution,
+		FlowFieldTilesBundle::from_sector_cost_fields(map_dimensions, cost_fields)
+	}
+	/// As with [FlowFieldTilesBundle::from_bevy_2d_meshes] but each
+	/// [FieldCell] is supersampled to find how much of it is covered by the
+	/// meshes, partially covered cells are given an interpolated cost rather
+	/// than being treated as external to the meshes. See [MeshSupersampling]
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "2d")]
+	pub fn from_bevy_2d_meshes_supersampled(
+		meshes: Vec<(&Mesh, Vec2)>,
+		map_dimensions: MapDimensions,
+		internal_cost: u8,
+		external_cost: u8,
+		supersampling: MeshSupersampling,
+	) -> Self {
+		let cost_fields = SectorCostFields::from_bevy_2d_meshes_supersampled(
+			&map_dimensions,
+			&meshes,
+			internal_cost,
+			external_cost,
+			supersampling,
 		);
-		// update default portals for cost fields
-		for sector_id in cost_fields.get_scaled().keys() {
-			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+		FlowFieldTilesBundle::from_sector_cost_fields(map_dimensions, cost_fields)
+	}
+}
+
+impl FlowFieldTilesBundle {
+	/// Begin describing a [FlowFieldTilesBundle] with a
+	/// [FlowFieldTilesBundleBuilder], rather than panicking on bad input the
+	/// builder reports every problem it finds at once
+	pub fn builder() -> FlowFieldTilesBundleBuilder {
+		FlowFieldTilesBundleBuilder::default()
+	}
+	/// Spawn an entity whose [FlowFieldTilesBundle] is built by the `builder`
+	/// within a task of the `AsyncComputeTaskPool` rather than stalling a
+	/// frame. The plugin publishes an [EventBundleBuildProgress] as the
+	/// build advances and inserts the components into the returned entity
+	/// once it's done, see [BundleBuildTask]
+	pub fn spawn_async(commands: &mut Commands, builder: FlowFieldTilesBundleBuilder) -> Entity {
+		commands.spawn(BundleBuildTask::new(builder)).id()
+	}
+}
+
+/// Where the [CostField]s of a [FlowFieldTilesBundleBuilder] come from
+#[derive(Clone, Default)]
+enum CostSource {
+	/// Every [FieldCell] has a cost of `1`
+	#[default]
+	Uniform,
+	/// Fields which have already been built
+	CostFields(SectorCostFields),
+	/// Path to a `.ron` file of [SectorCostFields]
+	#[cfg(feature = "ron")]
+	Ron(String),
+	/// Contents of a `.ron` file of [SectorCostFields]
+	#[cfg(feature = "ron")]
+	RonStr(String),
+	/// Path to a directory of CSV [CostField] files
+	#[cfg(feature = "csv")]
+	Csv(String),
+	/// Contents of the CSV [CostField] file of each sector
+	#[cfg(feature = "csv")]
+	CsvData(Vec<(SectorID, Vec<u8>)>),
+	/// Path to a greyscale heightmap image
+	#[cfg(feature = "heightmap")]
+	Heightmap(String),
+	/// Bytes of a greyscale heightmap image file
+	#[cfg(feature = "heightmap")]
+	HeightmapBytes(Vec<u8>),
+	/// 2d meshes and their translation with the cost of cells inside and
+	/// outside of them
+	#[cfg(feature = "2d")]
+	Meshes2d {
+		/// Meshes and their translation
+		meshes: Vec<(Mesh, Vec2)>,
+		/// Cost of cells within a mesh
+		internal_cost: u8,
+		/// Cost of cells outside of every mesh
+		external_cost: u8,
+	},
+}
+
+/// Describes a [FlowFieldTilesBundle] with fluent options, the bundle is
+/// only created by [FlowFieldTilesBundleBuilder::build] once every option
+/// has been validated. The builder owns all of its inputs so it can be moved
+/// to another thread, such as a task of the `AsyncComputeTaskPool`, to load
+/// a map without stalling a frame
+///
+/// ```rust,no_run
+/// use bevy_flowfield_tiles_plugin::prelude::*;
+///
+/// let bundle = FlowFieldTilesBundle::builder()
+///     .with_dimensions(1920, 1920)
+///     .with_sector_resolution(640)
+///     .with_actor_size(16.0)
+///     .with_connectivity(Connectivity::FourWay)
+///     .build();
+/// match bundle {
+///     Ok(bundle) => { /* spawn the bundle */ }
+///     Err(report) => eprintln!("{}", report),
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct FlowFieldTilesBundleBuilder {
+	/// `(length, depth)` of the map
+	dimensions: Option<(u32, u32)>,
+	/// Size of each sector
+	sector_resolution: Option<u32>,
+	/// Size of the actors navigating the map
+	actor_size: Option<f32>,
+	/// Where the [CostField]s come from
+	cost_source: CostSource,
+	/// Which neighbouring field cells actors may move between
+	connectivity: Connectivity,
+	/// How routes through the [PortalGraph] are scored
+	route_cost_model: Option<RouteCostModel>,
+	/// Longest boundary window represented by a single portal
+	max_portal_window: Option<usize>,
+	/// Distance of moving between portals either side of a sector boundary
+	portal_boundary_distance: Option<i32>,
+	/// The plane of the world the bundle is laid out on
+	#[cfg(any(feature = "2d", feature = "3d"))]
+	coordinate_system: CoordinateSystem,
+}
+
+impl FlowFieldTilesBundleBuilder {
+	/// Set the `length` and `depth` of the map, see [MapDimensions::new]
+	pub fn with_dimensions(mut self, length: u32, depth: u32) -> Self {
+		self.dimensions = Some((length, depth));
+		self
+	}
+	/// Set the size of each sector, the dimensions of the map must be
+	/// multiples of it
+	pub fn with_sector_resolution(mut self, sector_resolution: u32) -> Self {
+		self.sector_resolution = Some(sector_resolution);
+		self
+	}
+	/// Set the size of the actors navigating the map
+	pub fn with_actor_size(mut self, actor_size: f32) -> Self {
+		self.actor_size = Some(actor_size);
+		self
+	}
+	/// Use [SectorCostFields] which have already been built
+	pub fn with_cost_fields(mut self, cost_fields: SectorCostFields) -> Self {
+		self.cost_source = CostSource::CostFields(cost_fields);
+		self
+	}
+	/// Load the [SectorCostFields] from a `.ron` file
+	#[cfg(feature = "ron")]
+	pub fn with_ron(mut self, path: &str) -> Self {
+		self.cost_source = CostSource::Ron(path.to_string());
+		self
+	}
+	/// Load the [SectorCostFields] from the contents of a `.ron` file, for
+	/// platforms without a filesystem
+	#[cfg(feature = "ron")]
+	pub fn with_ron_str(mut self, data: &str) -> Self {
+		self.cost_source = CostSource::RonStr(data.to_string());
+		self
+	}
+	/// Load the [CostField]s from a directory of CSV files
+	#[cfg(feature = "csv")]
+	pub fn with_csv_dir(mut self, directory: &str) -> Self {
+		self.cost_source = CostSource::Csv(directory.to_string());
+		self
+	}
+	/// Load the [CostField]s from the contents of the CSV file of each
+	/// sector, for platforms without a filesystem
+	#[cfg(feature = "csv")]
+	pub fn with_csv_data(
+		mut self,
+		sectors: impl IntoIterator<Item = (SectorID, impl AsRef<[u8]>)>,
+	) -> Self {
+		let data = sectors
+			.into_iter()
+			.map(|(sector_id, csv)| (sector_id, csv.as_ref().to_vec()))
+			.collect();
+		self.cost_source = CostSource::CsvData(data);
+		self
+	}
+	/// Derive the [CostField]s from a greyscale heightmap image
+	#[cfg(feature = "heightmap")]
+	pub fn with_heightmap(mut self, file_path: &str) -> Self {
+		self.cost_source = CostSource::Heightmap(file_path.to_string());
+		self
+	}
+	/// Derive the [CostField]s from the bytes of a greyscale heightmap image
+	/// file, for platforms without a filesystem
+	#[cfg(feature = "heightmap")]
+	pub fn with_image_bytes(mut self, bytes: &[u8]) -> Self {
+		self.cost_source = CostSource::HeightmapBytes(bytes.to_vec());
+		self
+	}
+	/// Derive the [CostField]s from 2d meshes, see
+	/// [FlowFieldTilesBundle::from_bevy_2d_meshes]
+	#[cfg(feature = "2d")]
+	pub fn with_2d_meshes(
+		mut self,
+		meshes: Vec<(Mesh, Vec2)>,
+		internal_cost: u8,
+		external_cost: u8,
+	) -> Self {
+		self.cost_source = CostSource::Meshes2d {
+			meshes,
+			internal_cost,
+			external_cost,
+		};
+		self
+	}
+	/// Restrict actors to moving between field cells with the given
+	/// [Connectivity]
+	pub fn with_connectivity(mut self, connectivity: Connectivity) -> Self {
+		self.connectivity = connectivity;
+		self
+	}
+	/// Score routes through the [PortalGraph] with a custom [PortalCostModel]
+	pub fn with_route_cost_model(mut self, model: impl PortalCostModel + 'static) -> Self {
+		self.route_cost_model = Some(RouteCostModel::new(model));
+		self
+	}
+	/// Lay the bundle out on a [CoordinateSystem] other than the default
+	#[cfg(any(feature = "2d", feature = "3d"))]
+	pub fn with_coordinate_system(mut self, coordinate_system: CoordinateSystem) -> Self {
+		self.coordinate_system = coordinate_system;
+		self
+	}
+	/// Split boundary windows longer than `max_portal_window` cells into
+	/// several portals, see [SectorCostFields::set_max_portal_window]
+	pub fn with_max_portal_window(mut self, max_portal_window: usize) -> Self {
+		self.max_portal_window = Some(max_portal_window);
+		self
+	}
+	/// Set the distance of moving between portals either side of a sector
+	/// boundary in the [PortalGraph], see
+	/// [PortalGraph::new_with_boundary_distance]
+	pub fn with_portal_boundary_distance(mut self, distance: i32) -> Self {
+		self.portal_boundary_distance = Some(distance);
+		self
+	}
+	/// Get the distance of moving between portals either side of a sector
+	/// boundary the [PortalGraph] is built with
+	pub(crate) fn get_portal_boundary_distance(&self) -> i32 {
+		self.portal_boundary_distance
+			.unwrap_or(SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE)
+	}
+	/// Find every problem with the options that would prevent a
+	/// [MapDimensions] being created
+	fn validate_dimensions(&self) -> Vec<BuildError> {
+		let mut errors = Vec::new();
+		match self.dimensions {
+			None => errors.push(BuildError::MissingDimensions),
+			Some((length, depth)) if length == 0 || depth == 0 => {
+				errors.push(BuildError::ZeroDimensions { length, depth })
+			}
+			_ => {}
 		}
-		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
-		let route_cache = RouteCache::default();
-		let cache = FlowFieldCache::default();
-		FlowFieldTilesBundle {
-			sector_cost_fields: cost_fields,
-			sector_portals: portals,
-			portal_graph: graph,
+		match self.sector_resolution {
+			None => errors.push(BuildError::MissingSectorResolution),
+			Some(0) => errors.push(BuildError::ZeroSectorResolution),
+			Some(sector_resolution) => {
+				if let Some((length, depth)) = self.dimensions {
+					if length % sector_resolution != 0 || depth % sector_resolution != 0 {
+						errors.push(BuildError::CostFields(
+							ValidationError::IndivisibleDimensions {
+								length,
+								depth,
+								sector_resolution,
+							},
+						));
+					}
+				}
+			}
+		}
+		match self.actor_size {
+			None => errors.push(BuildError::MissingActorSize),
+			Some(actor_size) if actor_size.is_nan() || actor_size < 0.0 => {
+				errors.push(BuildError::NegativeActorSize(actor_size))
+			}
+			Some(actor_size) => {
+				if let Some(sector_resolution) = self.sector_resolution.filter(|r| *r > 0) {
+					// mirrors the limits of the actor scale in MapDimensions::new
+					let actor_scale = (actor_size / (sector_resolution as f32 / 10.0)).ceil();
+					if actor_size >= sector_resolution as f32 || actor_scale >= 10.0 {
+						errors.push(BuildError::ActorTooLarge {
+							actor_size,
+							sector_resolution,
+						});
+					}
+				}
+			}
+		}
+		errors
+	}
+	/// Validate the options and create the [FlowFieldTilesBundle], if
+	/// anything is wrong a [BuildErrorReport] lists every problem found
+	pub fn build(self) -> Result<FlowFieldTilesBundle, BuildErrorReport> {
+		self.build_with_progress(|_, _| {})
+	}
+	/// As with [FlowFieldTilesBundleBuilder::build] but `progress` is called
+	/// with the [BundleBuildStage] being worked on and the fraction of it
+	/// completed so far, see [FlowFieldTilesBundle::spawn_async] to build
+	/// within a task instead
+	pub fn build_with_progress(
+		mut self,
+		mut progress: impl FnMut(BundleBuildStage, f32),
+	) -> Result<FlowFieldTilesBundle, BuildErrorReport> {
+		let (map_dimensions, cost_fields) = self.build_cost_fields(&mut progress)?;
+		let bundle = FlowFieldTilesBundle::from_sector_cost_fields_with_boundary_distance(
 			map_dimensions,
-			route_cache,
-			flow_field_cache: cache,
+			cost_fields,
+			self.get_portal_boundary_distance(),
+			progress,
+		);
+		Ok(self.finish(bundle))
+	}
+	/// Validate the options and create the [MapDimensions] and scaled
+	/// [SectorCostFields], the first [BundleBuildStage] of a build. The
+	/// source of the costs is taken from the builder
+	pub(crate) fn build_cost_fields(
+		&mut self,
+		progress: &mut impl FnMut(BundleBuildStage, f32),
+	) -> Result<(MapDimensions, SectorCostFields), BuildErrorReport> {
+		let errors = self.validate_dimensions();
+		if !errors.is_empty() {
+			return Err(BuildErrorReport { errors });
 		}
+		// validation guarantees each option is present and well formed
+		let (length, depth) = self.dimensions.unwrap();
+		let map_dimensions = MapDimensions::new(
+			length,
+			depth,
+			self.sector_resolution.unwrap(),
+			self.actor_size.unwrap(),
+		);
+		let mut cost_fields = match std::mem::take(&mut self.cost_source) {
+			CostSource::Uniform => Ok(SectorCostFields::new(&map_dimensions)),
+			CostSource::CostFields(mut cost_fields) => {
+				cost_fields.scale_all_costfields(&map_dimensions);
+				Ok(cost_fields)
+			}
+			#[cfg(feature = "ron")]
+			CostSource::Ron(path) => SectorCostFields::try_from_ron(path, &map_dimensions),
+			#[cfg(feature = "ron")]
+			CostSource::RonStr(data) => SectorCostFields::try_from_ron_str(&data, &map_dimensions),
+			#[cfg(feature = "csv")]
+			CostSource::Csv(directory) => SectorCostFields::try_from_csv_dir(&map_dimensions, directory),
+			#[cfg(feature = "csv")]
+			CostSource::CsvData(data) => SectorCostFields::try_from_csv_readers(
+				&map_dimensions,
+				data.iter()
+					.map(|(sector_id, csv)| (*sector_id, csv.as_slice())),
+			),
+			#[cfg(feature = "heightmap")]
+			CostSource::Heightmap(path) => {
+				SectorCostFields::try_from_heightmap_with_progress(&map_dimensions, path, |f| {
+					progress(BundleBuildStage::CostFields, f)
+				})
+			}
+			#[cfg(feature = "heightmap")]
+			CostSource::HeightmapBytes(bytes) => {
+				SectorCostFields::try_from_image_bytes_with_progress(&map_dimensions, &bytes, |f| {
+					progress(BundleBuildStage::CostFields, f)
+				})
+			}
+			#[cfg(feature = "2d")]
+			CostSource::Meshes2d {
+				meshes,
+				internal_cost,
+				external_cost,
+			} => Ok(SectorCostFields::from_bevy_2d_meshes_with_progress(
+				&map_dimensions,
+				&meshes
+					.iter()
+					.map(|(mesh, translation)| (mesh, *translation))
+					.collect(),
+				internal_cost,
+				external_cost,
+				|f| progress(BundleBuildStage::CostFields, f),
+			)),
+		}
+		.map_err(|e| BuildErrorReport {
+			errors: vec![BuildError::CostSource(e)],
+		})?;
+		if let Err(errors) = cost_fields.validate(&map_dimensions) {
+			return Err(BuildErrorReport {
+				errors: errors.into_iter().map(BuildError::CostFields).collect(),
+			});
+		}
+		if self.max_portal_window.is_some() {
+			cost_fields.set_max_portal_window(self.max_portal_window);
+			// the portals are about to be built from scratch
+			cost_fields.take_edited_sectors();
+		}
+		progress(BundleBuildStage::CostFields, 1.0);
+		Ok((map_dimensions, cost_fields))
+	}
+	/// Apply the remaining options of the builder to a `bundle` built from
+	/// its [SectorCostFields]
+	pub(crate) fn finish(self, bundle: FlowFieldTilesBundle) -> FlowFieldTilesBundle {
+		let mut bundle = bundle.with_connectivity(self.connectivity);
+		#[cfg(any(feature = "2d", feature = "3d"))]
+		{
+			bundle.coordinate_system = self.coordinate_system;
+		}
+		if let Some(model) = self.route_cost_model {
+			bundle.route_cost_model = model;
+		}
+		bundle
 	}
 }
 
+/// A problem found by [FlowFieldTilesBundleBuilder::build]
+#[derive(Clone, Debug, PartialEq)]
+pub enum BuildError {
+	/// [FlowFieldTilesBundleBuilder::with_dimensions] was never called
+	MissingDimensions,
+	/// [FlowFieldTilesBundleBuilder::with_sector_resolution] was never called
+	MissingSectorResolution,
+	/// [FlowFieldTilesBundleBuilder::with_actor_size] was never called
+	MissingActorSize,
+	/// The map has no area
+	ZeroDimensions {
+		/// Length of the map
+		length: u32,
+		/// Depth of the map
+		depth: u32,
+	},
+	/// Sectors must have a size
+	ZeroSectorResolution,
+	/// The actor size is negative or not a number
+	NegativeActorSize(f32),
+	/// Actors would be as large as an entire sector
+	ActorTooLarge {
+		/// Size of the actors
+		actor_size: f32,
+		/// Size of each sector
+		sector_resolution: u32,
+	},
+	/// The [CostField]s couldn't be read or parsed
+	CostSource(String),
+	/// The [CostField]s don't describe the map
+	CostFields(ValidationError),
+}
+
+impl std::fmt::Display for BuildError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			BuildError::MissingDimensions => write!(f, "The map dimensions have not been set"),
+			BuildError::MissingSectorResolution => {
+				write!(f, "The sector resolution has not been set")
+			}
+			BuildError::MissingActorSize => write!(f, "The actor size has not been set"),
+			BuildError::ZeroDimensions { length, depth } => {
+				write!(f, "Map size ({}, {}) has no area", length, depth)
+			}
+			BuildError::ZeroSectorResolution => {
+				write!(f, "The sector resolution must be greater than zero")
+			}
+			BuildError::NegativeActorSize(actor_size) => {
+				write!(f, "Actor size {} cannot be less than zero", actor_size)
+			}
+			BuildError::ActorTooLarge {
+				actor_size,
+				sector_resolution,
+			} => write!(
+				f,
+				"Actor size {} is too large for the sector resolution {}",
+				actor_size, sector_resolution
+			),
+			BuildError::CostSource(e) => write!(f, "{}", e),
+			BuildError::CostFields(e) => write!(f, "{}", e),
+		}
+	}
+}
+
+impl std::error::Error for BuildError {}
+
+/// Every problem found by [FlowFieldTilesBundleBuilder::build]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildErrorReport {
+	/// The problems found
+	errors: Vec<BuildError>,
+}
+
+impl BuildErrorReport {
+	/// Get the problems found
+	pub fn get_errors(&self) -> &Vec<BuildError> {
+		&self.errors
+	}
+}
+
+impl std::fmt::Display for BuildErrorReport {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(
+			f,
+			"Unable to build FlowFieldTilesBundle, found {} problem(s):",
+			self.errors.len()
+		)?;
+		for error in self.errors.iter() {
+			writeln!(f, "- {}", error)?;
+		}
+		Ok(())
+	}
+}
+
+impl std::error::Error for BuildErrorReport {}
+
 // #[rustfmt::skip]
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use bevy::utils::Duration;
 	#[test]
 	fn valid_map_dimensions() {
 		let _map_dimsions = MapDimensions::new(10, 10, 10, 0.5);
@@ -243,4 +1063,508 @@ mod tests {
 			+ "/assets/sector_cost_fields_continuous_layout.ron";
 		let _ = FlowFieldTilesBundle::from_ron(30, 30, 10, 0.5, &path);
 	}
+	#[test]
+	#[cfg(feature = "ron")]
+	fn new_bundle_from_ron_str() {
+		let data = include_str!("../assets/sector_cost_fields_continuous_layout.ron");
+		let _ = FlowFieldTilesBundle::from_ron_str(30, 30, 10, 0.5, data);
+	}
+	#[test]
+	#[cfg(feature = "csv")]
+	fn builder_from_csv_data() {
+		let mut sectors = Vec::new();
+		for column in 0..3 {
+			for row in 0..3 {
+				let path = format!(
+					"{}/assets/csv/vis_portals/{}_{}.csv",
+					env!("CARGO_MANIFEST_DIR"),
+					column,
+					row
+				);
+				sectors.push((SectorID::new(column, row), std::fs::read(path).unwrap()));
+			}
+		}
+		let bundle = FlowFieldTilesBundle::builder()
+			.with_dimensions(30, 30)
+			.with_sector_resolution(10)
+			.with_actor_size(0.5)
+			.with_csv_data(sectors.clone())
+			.build()
+			.unwrap();
+		let expected = FlowFieldTilesBundle::from_csv(
+			30,
+			30,
+			10,
+			0.5,
+			&(env!("CARGO_MANIFEST_DIR").to_string() + "/assets/csv/vis_portals"),
+		);
+		for (sector_id, field) in expected.get_sector_cost_fields().get_baseline().iter() {
+			assert_eq!(
+				field.get(),
+				bundle.get_sector_cost_fields().get_baseline()[sector_id].get()
+			);
+		}
+		// a missing sector is reported
+		sectors.pop();
+		assert!(FlowFieldTilesBundle::builder()
+			.with_dimensions(30, 30)
+			.with_sector_resolution(10)
+			.with_actor_size(0.5)
+			.with_csv_data(sectors)
+			.build()
+			.is_err());
+	}
+	#[test]
+	fn builder_reports_every_problem() {
+		let report = FlowFieldTilesBundle::builder()
+			.with_dimensions(35, 30)
+			.with_actor_size(-1.0)
+			.build()
+			.err()
+			.unwrap();
+		let expected = vec![
+			BuildError::MissingSectorResolution,
+			BuildError::NegativeActorSize(-1.0),
+		];
+		assert_eq!(&expected, report.get_errors());
+		let report = FlowFieldTilesBundle::builder()
+			.with_dimensions(35, 30)
+			.with_sector_resolution(10)
+			.with_actor_size(12.0)
+			.build()
+			.err()
+			.unwrap();
+		let expected = vec![
+			BuildError::CostFields(ValidationError::IndivisibleDimensions {
+				length: 35,
+				depth: 30,
+				sector_resolution: 10,
+			}),
+			BuildError::ActorTooLarge {
+				actor_size: 12.0,
+				sector_resolution: 10,
+			},
+		];
+		assert_eq!(&expected, report.get_errors());
+	}
+	#[test]
+	fn builder_rejects_mismatched_cost_fields() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let report = FlowFieldTilesBundle::builder()
+			.with_dimensions(30, 20)
+			.with_sector_resolution(10)
+			.with_actor_size(0.5)
+			.with_cost_fields(cost_fields)
+			.build()
+			.err()
+			.unwrap();
+		let expected = vec![
+			BuildError::CostFields(ValidationError::MissingSector(SectorID::new(2, 0))),
+			BuildError::CostFields(ValidationError::MissingSector(SectorID::new(2, 1))),
+		];
+		assert_eq!(&expected, report.get_errors());
+	}
+	#[test]
+	fn builder_on_another_thread() {
+		let builder = FlowFieldTilesBundle::builder()
+			.with_dimensions(30, 30)
+			.with_sector_resolution(10)
+			.with_actor_size(0.5)
+			.with_connectivity(Connectivity::FourWay);
+		let bundle = std::thread::spawn(move || builder.build())
+			.join()
+			.unwrap()
+			.unwrap();
+		assert_eq!(Connectivity::FourWay, bundle.get_connectivity());
+		assert_eq!((30, 30), bundle.get_map_dimensions().get_size());
+		assert_eq!(9, bundle.get_sector_cost_fields().get_scaled().len());
+	}
+	#[test]
+	fn snapshot_and_restore_state() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let snapshot = bundle.clone_state();
+		let sector_id = SectorID::new(1, 1);
+		let field_cell = FieldCell::new(4, 4);
+		let map_dimensions = *bundle.get_map_dimensions();
+		bundle
+			.sector_cost_fields
+			.set_field_cell_value(sector_id, 255, field_cell, &map_dimensions);
+		bundle.restore_state(&snapshot);
+		let result = bundle
+			.get_sector_cost_fields()
+			.get_baseline()
+			.get(&sector_id)
+			.unwrap()
+			.get_field_cell_value(field_cell);
+		assert_eq!(1, result);
+	}
+	#[test]
+	fn snapshot_keeps_modifier_handle_in_charge() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let sector_id = SectorID::new(1, 1);
+		let field_cell = FieldCell::new(4, 4);
+		let map_dimensions = *bundle.get_map_dimensions();
+		let handle = bundle.cost_modifiers.apply_timed_cost_modifier(
+			&mut bundle.sector_cost_fields,
+			&map_dimensions,
+			&[(sector_id, field_cell)],
+			Cost::IMPASSABLE,
+			Duration::from_secs(10),
+		);
+		let mut snapshot = bundle.clone_state();
+		// dropping the handle reverts the modifier in the bundle and the snapshot
+		drop(handle);
+		for state in [&mut bundle, &mut snapshot] {
+			let reverted = state.cost_modifiers.tick(
+				Duration::ZERO,
+				&mut state.sector_cost_fields,
+				&map_dimensions,
+			);
+			assert_eq!(1, reverted);
+			let result = state
+				.get_sector_cost_fields()
+				.get_baseline()
+				.get(&sector_id)
+				.unwrap()
+				.get_field_cell_value(field_cell);
+			assert_eq!(1, result);
+		}
+	}
+	#[test]
+	fn prewarm_route() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let request = RouteMetadata::new(
+			SectorID::new(2, 0),
+			FieldCell::new(4, 4),
+			SectorID::new(0, 2),
+			FieldCell::new(2, 7),
+			Duration::default(),
+		);
+		assert!(!bundle.ready(&request));
+		bundle.prewarm(&[request]);
+		assert!(bundle.ready(&request));
+	}
+	#[test]
+	fn prewarm_route_with_split_portals() {
+		let mut bundle = FlowFieldTilesBundle::builder()
+			.with_dimensions(30, 30)
+			.with_sector_resolution(10)
+			.with_actor_size(0.5)
+			.with_max_portal_window(3)
+			.build()
+			.unwrap();
+		assert!(bundle.sector_cost_fields.get_edited_sectors().is_empty());
+		let portals = bundle
+			.sector_portals
+			.get()
+			.get(&SectorID::new(1, 1))
+			.unwrap();
+		assert_eq!(4, portals.get(&Ordinal::North).len());
+		let request = RouteMetadata::new(
+			SectorID::new(2, 0),
+			FieldCell::new(4, 4),
+			SectorID::new(0, 2),
+			FieldCell::new(2, 7),
+			Duration::default(),
+		);
+		bundle.prewarm(&[request]);
+		assert!(bundle.ready(&request));
+	}
+	#[test]
+	fn prewarm_route_with_stopping_distance() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let target = (SectorID::new(0, 2), FieldCell::new(2, 7));
+		// every corner of the goal sector is within 11 units of the goal
+		let request = RouteMetadata::new(
+			SectorID::new(2, 0),
+			FieldCell::new(4, 4),
+			target.0,
+			target.1,
+			Duration::default(),
+		)
+		.with_stopping_distance(Vec3::new(-12.5, 0.0, 12.5), 11.0);
+		bundle.prewarm(&[request]);
+		assert!(bundle.ready(&request));
+		assert!(bundle
+			.flow_field_cache
+			.get_field(target.0, target.0, target.1)
+			.is_none());
+		let route = bundle
+			.route_cache
+			.get_route(
+				request.get_source_sector(),
+				request.get_source_field_cell(),
+				target.0,
+				target.1,
+			)
+			.unwrap();
+		assert!(!bundle.flow_field_cache.is_route_built(&route));
+	}
+	#[test]
+	fn approach_line_of_sight() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		// a wall inside the sector next to the goal sector
+		for row in 0..=6 {
+			cost_fields.set_field_cell_value(
+				SectorID::new(0, 0),
+				255,
+				FieldCell::new(6, row),
+				&map_dimensions,
+			);
+		}
+		let mut bundle = FlowFieldTilesBundle::from_sector_cost_fields(map_dimensions, cost_fields);
+		let source = (SectorID::new(0, 0), FieldCell::new(2, 5));
+		let target = (SectorID::new(1, 0), FieldCell::new(5, 5));
+		let request =
+			RouteMetadata::new(source.0, source.1, target.0, target.1, Duration::default());
+		bundle.prewarm(&[request]);
+		let cache = &bundle.flow_field_cache;
+		// a clear line through the portal window
+		let clear = FieldCell::new(8, 5);
+		assert!(cache.has_approach_line_of_sight(source.0, clear, target.0, target.1, None));
+		// the wall lies between the cell and the goal
+		let blocked = FieldCell::new(2, 2);
+		assert!(!cache.has_approach_line_of_sight(source.0, blocked, target.0, target.1, None));
+		// the shared transit field itself has no line of sight
+		let route = bundle
+			.route_cache
+			.get_route(source.0, source.1, target.0, target.1)
+			.unwrap();
+		let field = cache
+			.get_field(source.0, target.0, route.get()[0].1)
+			.unwrap();
+		assert!(!has_line_of_sight(field.get_field_cell_value(clear)));
+		// an actor with sight of the goal heads straight for it
+		let trace = cache
+			.trace_route(
+				&route,
+				(source.0, clear),
+				&bundle.map_dimensions,
+				false,
+				None,
+			)
+			.unwrap();
+		assert_eq!(vec![(source.0, clear), target], trace);
+		// removing the fields of the sector removes its line of sight
+		for flow_meta in bundle.flow_field_cache.get_sector_field_metadata(source.0) {
+			bundle.flow_field_cache.remove_field(flow_meta);
+		}
+		assert!(!bundle
+			.flow_field_cache
+			.has_approach_line_of_sight(source.0, clear, target.0, target.1, None));
+		// a faction has line of sight of its own
+		let faction = FactionId::new(1);
+		bundle.prewarm(&[request.with_faction(faction)]);
+		let cache = &bundle.flow_field_cache;
+		assert!(cache.has_approach_line_of_sight(
+			source.0,
+			clear,
+			target.0,
+			target.1,
+			Some(faction)
+		));
+		assert!(!cache.has_approach_line_of_sight(source.0, clear, target.0, target.1, None));
+		let trace = cache
+			.trace_route(
+				&route,
+				(source.0, clear),
+				&bundle.map_dimensions,
+				false,
+				Some(faction),
+			)
+			.unwrap();
+		assert_eq!(vec![(source.0, clear), target], trace);
+		assert!(cache
+			.trace_route(
+				&route,
+				(source.0, clear),
+				&bundle.map_dimensions,
+				false,
+				None
+			)
+			.is_none());
+	}
+	#[test]
+	fn trace_prewarmed_route() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let source = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let target = (SectorID::new(0, 2), FieldCell::new(2, 7));
+		let request =
+			RouteMetadata::new(source.0, source.1, target.0, target.1, Duration::default());
+		bundle.prewarm(&[request]);
+		let route = bundle
+			.route_cache
+			.get_route(source.0, source.1, target.0, target.1)
+			.unwrap();
+		let cells = bundle
+			.flow_field_cache
+			.trace_route(&route, source, &bundle.map_dimensions, false, None)
+			.unwrap();
+		assert_eq!(source, cells[0]);
+		assert_eq!(target, *cells.last().unwrap());
+		// each step moves to an adjacent cell until line of sight is reached
+		for pair in cells[..cells.len() - 1].windows(2) {
+			let a = bundle.map_dimensions.global_cell_from(pair[0].0, pair[0].1);
+			let b = bundle.map_dimensions.global_cell_from(pair[1].0, pair[1].1);
+			assert!(a.0.abs_diff(b.0) <= 1 && a.1.abs_diff(b.1) <= 1);
+		}
+		let simplified = bundle
+			.flow_field_cache
+			.trace_route(&route, source, &bundle.map_dimensions, true, None)
+			.unwrap();
+		assert!(simplified.len() < cells.len());
+		assert_eq!(source, simplified[0]);
+		assert_eq!(target, *simplified.last().unwrap());
+	}
+	#[test]
+	fn expanded_portal_goals() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let source = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let target = (SectorID::new(0, 0), FieldCell::new(2, 2));
+		let request =
+			RouteMetadata::new(source.0, source.1, target.0, target.1, Duration::default());
+		bundle.prewarm(&[request]);
+		let route = bundle
+			.route_cache
+			.get_route(source.0, source.1, target.0, target.1)
+			.unwrap();
+		// the source sector leads west through the full window of its boundary
+		let portal = route.get()[0].1;
+		let goals = bundle
+			.flow_field_cache
+			.get_goal_cells(source.0, target.0, portal)
+			.unwrap();
+		assert_eq!(10, goals.len());
+		assert!(goals.iter().all(|cell| cell.get_column() == 0));
+		let nearest = bundle
+			.flow_field_cache
+			.get_nearest_goal_cell(source.0, target.0, portal, FieldCell::new(6, 8))
+			.unwrap();
+		assert_eq!(FieldCell::new(0, 8), nearest);
+		let goals = bundle
+			.flow_field_cache
+			.get_goal_cells(target.0, target.0, target.1)
+			.unwrap();
+		assert_eq!(&vec![target.1], goals);
+	}
+	#[test]
+	fn share_transit_fields() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let target = (SectorID::new(0, 0), FieldCell::new(2, 2));
+		let first = RouteMetadata::new(
+			SectorID::new(2, 0),
+			FieldCell::new(4, 4),
+			target.0,
+			target.1,
+			Duration::default(),
+		);
+		bundle.prewarm(&[first]);
+		// a second actor in the same sector heads through the same portals
+		let second = RouteMetadata::new(
+			SectorID::new(2, 0),
+			FieldCell::new(6, 6),
+			target.0,
+			target.1,
+			Duration::default(),
+		);
+		let (_goal, route) = find_route_with_fallback(
+			&bundle.portal_graph,
+			&bundle.sector_portals,
+			&bundle.sector_cost_fields,
+			&bundle.map_dimensions,
+			bundle.route_cost_model.get(),
+			&second,
+		)
+		.unwrap();
+		let mut route_from_goal = route.clone();
+		route_from_goal.get_mut().reverse();
+		let mut int_builder = IntegrationBuilder::new(route_from_goal, &bundle.sector_cost_fields);
+		int_builder.expand_field_portals(
+			&bundle.sector_portals,
+			&bundle.sector_cost_fields,
+			&bundle.map_dimensions,
+		);
+		int_builder.set_expanded_portals();
+		bundle
+			.flow_field_cache
+			.share_cached_fields_into(&mut int_builder);
+		// the goal sector is never shared, the transit sectors are
+		assert_eq!(3, int_builder.get_integration_fields().len());
+		assert!(!int_builder.is_field_shared(0));
+		assert!(int_builder.is_field_shared(1));
+		assert!(int_builder.is_field_shared(2));
+		int_builder.calculate_los();
+		int_builder.set_los_pass();
+		int_builder.build_integrated_cost(&bundle.sector_cost_fields);
+		int_builder.set_cost_pass();
+		bundle
+			.flow_field_cache
+			.insert_flow_fields(&int_builder, Duration::from_secs(5));
+		assert!(bundle.flow_field_cache.is_route_built(&route));
+		// reused fields are refreshed so they don't expire before the new route
+		assert!(bundle
+			.flow_field_cache
+			.get()
+			.keys()
+			.all(|meta| meta.get_time_generated() == Stamp::Time(Duration::from_secs(5))));
+	}
+	#[test]
+	fn revalidate_prewarmed_field() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		bundle.flow_field_cache = FlowFieldCache::default().with_revalidation_tolerance(10);
+		let request = RouteMetadata::new(
+			SectorID::new(2, 0),
+			FieldCell::new(4, 4),
+			SectorID::new(0, 2),
+			FieldCell::new(2, 7),
+			Duration::default(),
+		);
+		bundle.prewarm(&[request]);
+		let sector_id = SectorID::new(2, 0);
+		let changed = FieldCell::new(7, 3);
+		let source_field = *bundle
+			.flow_field_cache
+			.get()
+			.keys()
+			.find(|meta| meta.get_sector_id() == sector_id)
+			.unwrap();
+		let mut cost_field = bundle
+			.sector_cost_fields
+			.get_scaled()
+			.get(&sector_id)
+			.unwrap()
+			.clone();
+		cost_field.set_field_cell_value(5, changed);
+		assert!(bundle.flow_field_cache.is_field_still_valid(
+			&source_field,
+			&cost_field,
+			&[changed]
+		));
+		cost_field.set_field_cell_value(255, changed);
+		assert!(!bundle.flow_field_cache.is_field_still_valid(
+			&source_field,
+			&cost_field,
+			&[changed]
+		));
+	}
+	#[test]
+	fn builder_reports_progress() {
+		let mut reports = Vec::new();
+		let bundle = FlowFieldTilesBundle::builder()
+			.with_dimensions(30, 30)
+			.with_sector_resolution(10)
+			.with_actor_size(0.5)
+			.build_with_progress(|stage, fraction| reports.push((stage, fraction)));
+		assert!(bundle.is_ok());
+		// one report per sector as its portals are calculated
+		let portals = reports
+			.iter()
+			.filter(|(stage, _)| *stage == BundleBuildStage::Portals)
+			.count();
+		assert_eq!(9, portals);
+		assert!(reports.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+		assert_eq!(Some(&(BundleBuildStage::PortalGraph, 1.0)), reports.last());
+	}
 }
@@ -0,0 +1,91 @@
+//! Scratch buffers used while building [IntegrationField]s are kept in a
+//! pool local to each thread. Maps which rebuild many sectors a second then
+//! reuse the same allocations rather than churning through new ones, with the
+//! `parallel` feature each rayon worker keeps a pool of its own
+//!
+
+use std::cell::RefCell;
+
+use crate::prelude::*;
+
+/// Number of buckets used by the integrated cost priority queue. A single
+/// step (with any slope cost capped) can increase the integrated cost by at
+/// most the largest [CostField] value so this many buckets can be reused in
+/// a circular fashion
+pub(crate) const INT_COST_BUCKETS: usize = u8::MAX as usize + 1;
+
+/// The most spare [FieldCell] buffers kept by a thread, a buffer returned
+/// to a full pool is dropped
+const MAX_POOLED_CELL_BUFFERS: usize = 32;
+
+thread_local! {
+	/// Buckets of the integrated cost priority queue
+	static COST_BUCKETS: RefCell<Vec<Vec<FieldCell>>> = const { RefCell::new(Vec::new()) };
+	/// Spare lists of [FieldCell]s
+	static CELL_BUFFERS: RefCell<Vec<Vec<FieldCell>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `f` with the [INT_COST_BUCKETS] empty buckets of the integrated cost
+/// priority queue of this thread. The buckets are emptied afterwards, keeping
+/// their capacity for the next build
+pub(crate) fn with_cost_buckets<R>(f: impl FnOnce(&mut [Vec<FieldCell>]) -> R) -> R {
+	// the buckets are taken out of the pool so a nested build on the same
+	// thread allocates its own rather than sharing them
+	let mut buckets = COST_BUCKETS.with(|pool| std::mem::take(&mut *pool.borrow_mut()));
+	buckets.resize_with(INT_COST_BUCKETS, Vec::new);
+	let result = f(&mut buckets);
+	for bucket in buckets.iter_mut() {
+		bucket.clear();
+	}
+	COST_BUCKETS.with(|pool| *pool.borrow_mut() = buckets);
+	result
+}
+
+/// Take an empty list of [FieldCell]s from the pool of this thread,
+/// allocating a new one if the pool is empty
+pub(crate) fn take_cell_buffer() -> Vec<FieldCell> {
+	CELL_BUFFERS
+		.with(|pool| pool.borrow_mut().pop())
+		.unwrap_or_default()
+}
+
+/// Return a list of [FieldCell]s to the pool of this thread so that it can
+/// be reused by [take_cell_buffer]
+pub(crate) fn recycle_cell_buffer(mut buffer: Vec<FieldCell>) {
+	buffer.clear();
+	CELL_BUFFERS.with(|pool| {
+		let mut pool = pool.borrow_mut();
+		if pool.len() < MAX_POOLED_CELL_BUFFERS {
+			pool.push(buffer);
+		}
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn cost_buckets_are_reused() {
+		let capacity = with_cost_buckets(|buckets| {
+			assert_eq!(INT_COST_BUCKETS, buckets.len());
+			buckets[7].push(FieldCell::new(1, 1));
+			buckets[7].capacity()
+		});
+		with_cost_buckets(|buckets| {
+			assert!(buckets.iter().all(|bucket| bucket.is_empty()));
+			assert_eq!(capacity, buckets[7].capacity());
+			// nested use gets buckets of its own
+			with_cost_buckets(|nested| assert_eq!(INT_COST_BUCKETS, nested.len()));
+		});
+	}
+	#[test]
+	fn cell_buffers_are_reused() {
+		let mut buffer = take_cell_buffer();
+		buffer.extend([FieldCell::new(0, 0); 20]);
+		let capacity = buffer.capacity();
+		recycle_cell_buffer(buffer);
+		let buffer = take_cell_buffer();
+		assert!(buffer.is_empty());
+		assert_eq!(capacity, buffer.capacity());
+	}
+}
@@ -44,13 +44,183 @@
 use crate::prelude::*;
 use bevy::reflect::Reflect;
 
+/// The cost of moving through a [FieldCell], a named alternative to the raw
+/// `u8` values of a [CostField]. Any API accepting a cost takes either a
+/// [Cost] or a `u8`
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct Cost(u8);
+
+impl Cost {
+	/// The cheapest cost and the default of every [FieldCell]
+	pub const PASSABLE: Cost = Cost(1);
+	/// A road is as cheap as a cost can be, to make actors prefer roads give
+	/// the surrounding terrain a higher cost such as [Cost::ROUGH]
+	pub const ROAD: Cost = Cost(1);
+	/// Terrain that is slower to cross, such as a forest or shallow water
+	pub const ROUGH: Cost = Cost(100);
+	/// Terrain that should only be crossed if there's no other way, such as
+	/// a swamp or steep slope
+	pub const DIFFICULT: Cost = Cost(200);
+	/// A soft obstacle which slows actors without blocking them, such as a
+	/// crowded doorway or scattered furniture. As the most expensive passable
+	/// cost the integration strongly penalises it, but unlike
+	/// [Cost::IMPASSABLE] a route may still pass through it and it isn't
+	/// expanded by the actor size. Pair it with
+	/// [FlowFieldCache::with_flow_spread] so crowds fan out across a doorway
+	pub const SOFT_IMPASSABLE: Cost = Cost(254);
+	/// The [FieldCell] cannot be pathed through at all
+	pub const IMPASSABLE: Cost = Cost(255);
+	/// Create a [Cost] from a raw `u8` value
+	pub const fn new(value: u8) -> Self {
+		Cost(value)
+	}
+	/// Create a passable [Cost] from a `weight` between `0.0` (the cheapest,
+	/// [Cost::PASSABLE]) and `1.0` (the most expensive passable cost of
+	/// `254`). Weights outside of the range are clamped, a [Cost] from a
+	/// weight is never impassable
+	pub fn from_weight(weight: f32) -> Self {
+		if weight.is_nan() {
+			return Cost::PASSABLE;
+		}
+		let weight = weight.clamp(0.0, 1.0);
+		Cost(1 + (weight * 253.0).round() as u8)
+	}
+	/// Get the raw `u8` value of the cost
+	pub fn get(&self) -> u8 {
+		self.0
+	}
+	/// Whether the [FieldCell] cannot be pathed through
+	pub fn is_impassable(&self) -> bool {
+		*self == Cost::IMPASSABLE
+	}
+}
+
+impl Default for Cost {
+	fn default() -> Self {
+		Cost::PASSABLE
+	}
+}
+
+impl From<u8> for Cost {
+	fn from(value: u8) -> Self {
+		Cost(value)
+	}
+}
+
+impl From<Cost> for u8 {
+	fn from(cost: Cost) -> Self {
+		cost.0
+	}
+}
+
+/// A compact bitset of the impassable [FieldCell]s of a [CostField]. It's
+/// kept in step with the costs whenever a value of the [CostField] is set so
+/// algorithms which only care whether a cell is impassable can test whole
+/// columns and rows of cells with bitwise operations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub struct ImpassableMask {
+	/// Bit `row` of each element is set when the cell at that column and row
+	/// is impassable
+	columns: [u16; FIELD_RESOLUTION],
+	/// Bit `column` of each element is set when the cell at that column and
+	/// row is impassable
+	rows: [u16; FIELD_RESOLUTION],
+}
+
+// each column and row of cells must fit into the bits of a `u16`
+const _: () = assert!(FIELD_RESOLUTION <= u16::BITS as usize);
+
+impl ImpassableMask {
+	/// The bits of a column or row which represent cells within the field
+	pub const ALL: u16 = ((1_u32 << FIELD_RESOLUTION) - 1) as u16;
+	/// Build the mask of a 2D array of costs
+	fn from_costs(costs: &[[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION]) -> Self {
+		let mut mask = ImpassableMask::default();
+		for (column, rows) in costs.iter().enumerate() {
+			for (row, cost) in rows.iter().enumerate() {
+				mask.set(FieldCell::new(column, row), *cost == 255);
+			}
+		}
+		mask
+	}
+	/// Mark whether a [FieldCell] is impassable
+	fn set(&mut self, field_cell: FieldCell, impassable: bool) {
+		let (column, row) = field_cell.get_column_row();
+		if impassable {
+			self.columns[column] |= 1 << row;
+			self.rows[row] |= 1 << column;
+		} else {
+			self.columns[column] &= !(1 << row);
+			self.rows[row] &= !(1 << column);
+		}
+	}
+	/// Whether the [FieldCell] is impassable
+	///
+	/// NB: This will panic if out of bounds
+	pub fn is_impassable(&self, field_cell: FieldCell) -> bool {
+		self.columns[field_cell.get_column()] & (1 << field_cell.get_row()) != 0
+	}
+	/// Get the bits of a column where bit `n` is set if the cell of row `n`
+	/// is impassable
+	///
+	/// NB: This will panic if out of bounds
+	pub fn get_column(&self, column: usize) -> u16 {
+		self.columns[column]
+	}
+	/// Get the bits of a row where bit `n` is set if the cell of column `n`
+	/// is impassable
+	///
+	/// NB: This will panic if out of bounds
+	pub fn get_row(&self, row: usize) -> u16 {
+		self.rows[row]
+	}
+	/// Number of impassable cells
+	pub fn count(&self) -> u32 {
+		self.columns.iter().map(|bits| bits.count_ones()).sum()
+	}
+	/// Whether no cell is impassable
+	pub fn is_empty(&self) -> bool {
+		self.columns.iter().all(|bits| *bits == 0)
+	}
+	/// Whether every cell is impassable
+	pub fn is_full(&self) -> bool {
+		self.columns.iter().all(|bits| *bits == Self::ALL)
+	}
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+	feature = "serde",
+	serde(from = "SerialisedCostField", into = "SerialisedCostField")
+)]
 #[derive(Clone, Reflect)]
-pub struct CostField([[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION]);
+pub struct CostField([[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION], ImpassableMask);
+
+/// The serialised form of a [CostField] is only its costs, the
+/// [ImpassableMask] is rebuilt when it's deserialised
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename = "CostField")]
+struct SerialisedCostField([[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION]);
+
+#[cfg(feature = "serde")]
+impl From<SerialisedCostField> for CostField {
+	fn from(field: SerialisedCostField) -> Self {
+		CostField::from_costs(field.0)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl From<CostField> for SerialisedCostField {
+	fn from(field: CostField) -> Self {
+		SerialisedCostField(field.0)
+	}
+}
 
 impl Default for CostField {
 	fn default() -> Self {
-		CostField([[1; FIELD_RESOLUTION]; FIELD_RESOLUTION])
+		CostField::new_with_cost(Cost::PASSABLE)
 	}
 }
 
@@ -70,12 +240,23 @@ impl Field<u8> for CostField {
 	/// NB: This will panic if out of bounds
 	fn set_field_cell_value(&mut self, value: u8, field_cell: FieldCell) {
 		self.0[field_cell.get_column()][field_cell.get_row()] = value;
+		self.1.set(field_cell, value == 255);
 	}
 }
 impl CostField {
 	/// Create a new [CostField] with all cell values initialised with `cost`
-	pub fn new_with_cost(cost: u8) -> Self {
-		CostField([[cost; FIELD_RESOLUTION]; FIELD_RESOLUTION])
+	pub fn new_with_cost(cost: impl Into<Cost>) -> Self {
+		CostField::from_costs([[cost.into().get(); FIELD_RESOLUTION]; FIELD_RESOLUTION])
+	}
+	/// Create a new [CostField] from a 2D array of costs indexed by column
+	/// then row
+	pub fn from_costs(costs: [[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION]) -> Self {
+		let mask = ImpassableMask::from_costs(&costs);
+		CostField(costs, mask)
+	}
+	/// Get the [ImpassableMask] of the field
+	pub fn impassable_mask(&self) -> &ImpassableMask {
+		&self.1
 	}
 	/// Tests whether two cells can see each other within a sector (one might be boxed in by impassable cost field values)
 	pub fn is_cell_pair_reachable(&self, source: FieldCell, target: FieldCell) -> bool {
@@ -105,6 +286,58 @@ impl CostField {
 		propagation[source.get_column()][source.get_row()] = 0;
 		process_neighbours_distance(self, target, queue, &mut propagation)
 	}
+	/// Finds the cheapest cumulative cost of moving from the `source` to the
+	/// `target` within the field where stepping into a [FieldCell] costs its
+	/// value. Unlike [CostField::get_distance_between_cells] this accounts
+	/// for expensive terrain along the way. Returns [None] if the `target`
+	/// cannot be reached
+	pub fn get_integrated_cost_between_cells(
+		&self,
+		source: &FieldCell,
+		target: &FieldCell,
+	) -> Option<i32> {
+		self.get_integrated_cost_between_cells_with_one_way(source, target, None)
+	}
+	/// As with [CostField::get_integrated_cost_between_cells] but steps
+	/// blocked by the `one_way` field of the sector are never taken
+	pub fn get_integrated_cost_between_cells_with_one_way(
+		&self,
+		source: &FieldCell,
+		target: &FieldCell,
+		one_way: Option<&OneWayField>,
+	) -> Option<i32> {
+		// instance of corner portals overlapping from cramped world
+		if source == target {
+			return Some(self.get_field_cell_value(*target) as i32);
+		}
+		let mut propagation = [[i32::MAX; FIELD_RESOLUTION]; FIELD_RESOLUTION];
+		propagation[source.get_column()][source.get_row()] = 0;
+		let mut queue = std::collections::BinaryHeap::new();
+		queue.push(std::cmp::Reverse((0, *source)));
+		while let Some(std::cmp::Reverse((cumulative_cost, cell))) = queue.pop() {
+			if cell == *target {
+				return Some(cumulative_cost);
+			}
+			// skip stale entries that have since been reached more cheaply
+			if cumulative_cost > propagation[cell.get_column()][cell.get_row()] {
+				continue;
+			}
+			for n in Ordinal::get_orthogonal_cell_neighbours(cell) {
+				let n_cost = self.get_field_cell_value(n);
+				let direction = Ordinal::cell_to_cell_direction(n, cell);
+				// ignore impassable and one-way steps
+				if n_cost != 255 && is_move_allowed(one_way, cell, direction) {
+					let n_cumulative = cumulative_cost + n_cost as i32;
+					let (column, row) = n.get_column_row();
+					if n_cumulative < propagation[column][row] {
+						propagation[column][row] = n_cumulative;
+						queue.push(std::cmp::Reverse((n_cumulative, n)));
+					}
+				}
+			}
+		}
+		None
+	}
 	/// From a `ron` file generate the [CostField]
 	#[cfg(feature = "ron")]
 	pub fn from_ron(path: String) -> Self {
@@ -133,9 +366,8 @@ fn process_neighbours_visibility(
 			if *n == *target {
 				return true;
 			}
-			let cell_cost = cost_field.get_field_cell_value(*n);
 			// ignore impassable cells
-			if cell_cost != 255 {
+			if !cost_field.impassable_mask().is_impassable(*n) {
 				let (column, row) = n.get_column_row();
 				let has_existing_propagation = propagation[column][row];
 				if !has_existing_propagation {
@@ -163,9 +395,8 @@ fn process_neighbours_distance(
 	for (cell, prev_cost) in queue.iter() {
 		let neighbours = Ordinal::get_orthogonal_cell_neighbours(*cell);
 		for n in neighbours {
-			let n_cost = cost_field.get_field_cell_value(n);
 			// ignore impassable
-			if n_cost != 255 {
+			if !cost_field.impassable_mask().is_impassable(n) {
 				// let cumulative_cost = n_cost as i32 + prev_cost;
 				let cumulative_cost = 1_i32 + prev_cost;
 				let (column, row) = n.get_column_row();
@@ -194,6 +425,23 @@ fn process_neighbours_distance(
 mod tests {
 	use super::*;
 	#[test]
+	fn named_costs() {
+		assert_eq!(1, Cost::default().get());
+		assert_eq!(255, u8::from(Cost::IMPASSABLE));
+		assert_eq!(Cost::new(200), Cost::from(200));
+		assert!(Cost::IMPASSABLE.is_impassable());
+		assert_eq!(Cost::PASSABLE, Cost::from_weight(0.0));
+		assert_eq!(Cost::new(128), Cost::from_weight(0.5));
+		assert_eq!(Cost::new(254), Cost::from_weight(1.0));
+		assert_eq!(Cost::new(254), Cost::from_weight(7.0));
+		assert_eq!(Cost::PASSABLE, Cost::from_weight(f32::NAN));
+	}
+	#[test]
+	fn cost_field_from_named_cost() {
+		let cost_field = CostField::new_with_cost(Cost::ROUGH);
+		assert_eq!(100, cost_field.get_field_cell_value(FieldCell::new(3, 3)));
+	}
+	#[test]
 	fn get_cost_field_value() {
 		let mut cost_field = CostField::default();
 		let field_cell = FieldCell::new(9, 9);
@@ -203,6 +451,34 @@ mod tests {
 		assert_eq!(actual, result);
 	}
 	#[test]
+	fn impassable_mask_follows_costs() {
+		let mut cost_field = CostField::default();
+		assert!(cost_field.impassable_mask().is_empty());
+		cost_field.set_field_cell_value(255, FieldCell::new(3, 7));
+		cost_field.set_field_cell_value(255, FieldCell::new(4, 7));
+		let mask = cost_field.impassable_mask();
+		assert!(mask.is_impassable(FieldCell::new(3, 7)));
+		assert_eq!(0b1000_0000, mask.get_column(3));
+		assert_eq!(0b1_1000, mask.get_row(7));
+		assert_eq!(2, mask.count());
+		cost_field.set_field_cell_value(20, FieldCell::new(3, 7));
+		let mask = cost_field.impassable_mask();
+		assert!(!mask.is_impassable(FieldCell::new(3, 7)));
+		assert_eq!(0b1_0000, mask.get_row(7));
+		assert!(CostField::new_with_cost(Cost::IMPASSABLE)
+			.impassable_mask()
+			.is_full());
+	}
+	#[test]
+	#[cfg(feature = "ron")]
+	fn impassable_mask_from_ron() {
+		let mut cost_field = CostField::default();
+		cost_field.set_field_cell_value(255, FieldCell::new(9, 0));
+		let serialised = ron::to_string(&cost_field).unwrap();
+		let result: CostField = ron::from_str(&serialised).unwrap();
+		assert_eq!(cost_field.impassable_mask(), result.impassable_mask());
+	}
+	#[test]
 	#[cfg(feature = "ron")]
 	fn cost_field_file() {
 		let path = env!("CARGO_MANIFEST_DIR").to_string() + "/assets/cost_field.ron";
@@ -312,4 +588,40 @@ mod tests {
 		let result = cost_field.get_distance_between_cells(&source, &target);
 		assert!(result.is_none())
 	}
+	#[test]
+	fn internal_cell_integrated_cost() {
+		//  _____________________________
+		// |S_|50|__|__|__|__|__|__|__|__|
+		// |__|50|__|__|__|__|__|__|__|__|
+		// |__|50|__|__|__|__|__|__|__|__|
+		// |__|50|T_|__|__|__|__|__|__|__|
+		// |__|__|__|__|__|__|__|__|__|__|
+		// |__|__|__|__|__|__|__|__|__|__|
+		// |__|__|__|__|__|__|__|__|__|__|
+		// |__|__|__|__|__|__|__|__|__|__|
+		// |__|__|__|__|__|__|__|__|__|__|
+		// |__|__|__|__|__|__|__|__|__|__|
+		let mut cost_field = CostField::default();
+		for row in 0..4 {
+			cost_field.set_field_cell_value(50, FieldCell::new(1, row));
+		}
+		let source = FieldCell::new(0, 0);
+		let target = FieldCell::new(2, 3);
+		// step count ignores the expensive column
+		let distance = cost_field.get_distance_between_cells(&source, &target);
+		assert_eq!(Some(5), distance);
+		// cheapest route walks around the bottom of the expensive column
+		let result = cost_field.get_integrated_cost_between_cells(&source, &target);
+		assert_eq!(Some(7), result)
+	}
+	#[test]
+	fn internal_cell_integrated_cost_none() {
+		let mut cost_field = CostField::default();
+		cost_field.set_field_cell_value(255, FieldCell::new(1, 0));
+		cost_field.set_field_cell_value(255, FieldCell::new(0, 1));
+		let source = FieldCell::new(0, 0);
+		let target = FieldCell::new(6, 9);
+		let result = cost_field.get_integrated_cost_between_cells(&source, &target);
+		assert!(result.is_none())
+	}
 }
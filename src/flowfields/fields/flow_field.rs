@@ -51,7 +51,7 @@ pub fn convert_ordinal_to_bits_dir(ordinal: Ordinal) -> u8 {
 }
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
-#[derive(Reflect)]
+#[derive(Clone, Reflect)]
 pub struct FlowField([[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION]);
 
 impl Default for FlowField {
@@ -81,6 +81,67 @@ impl FlowField {
 		goals: &[FieldCell],
 		previous_sector_ord_int: Option<(Ordinal, &IntegrationField)>,
 		integration_field: &IntegrationField,
+	) {
+		self.calculate_with_connectivity(
+			goals,
+			previous_sector_ord_int,
+			integration_field,
+			Connectivity::default(),
+		);
+	}
+	/// Calculate the [FlowField] as with [FlowField::calculate] where each
+	/// field cell may only point in a direction permitted by the `connectivity`
+	pub fn calculate_with_connectivity(
+		&mut self,
+		goals: &[FieldCell],
+		previous_sector_ord_int: Option<(Ordinal, &IntegrationField)>,
+		integration_field: &IntegrationField,
+		connectivity: Connectivity,
+	) {
+		self.calculate_with_one_way(
+			goals,
+			previous_sector_ord_int,
+			integration_field,
+			connectivity,
+			None,
+		);
+	}
+	/// Calculate the [FlowField] as with
+	/// [FlowField::calculate_with_connectivity] where no field cell points in
+	/// a direction blocked by the `one_way` field of the sector
+	pub fn calculate_with_one_way(
+		&mut self,
+		goals: &[FieldCell],
+		previous_sector_ord_int: Option<(Ordinal, &IntegrationField)>,
+		integration_field: &IntegrationField,
+		connectivity: Connectivity,
+		one_way: Option<&OneWayField>,
+	) {
+		self.calculate_with_spread(
+			goals,
+			previous_sector_ord_int,
+			integration_field,
+			connectivity,
+			one_way,
+			0,
+		);
+	}
+	/// Calculate the [FlowField] as with [FlowField::calculate_with_one_way]
+	/// where each field cell may point at any neighbour whose integrated
+	/// cost is within `spread` of the cheapest one (and still cheaper than
+	/// the cell itself). Neighbouring cells pick different candidates so
+	/// that a crowd heading through a chokepoint window, such as a doorway,
+	/// fans out across its cells rather than all converging on the single
+	/// cheapest one. A `spread` of `0` always points at the cheapest
+	/// neighbour
+	pub fn calculate_with_spread(
+		&mut self,
+		goals: &[FieldCell],
+		previous_sector_ord_int: Option<(Ordinal, &IntegrationField)>,
+		integration_field: &IntegrationField,
+		connectivity: Connectivity,
+		one_way: Option<&OneWayField>,
+		spread: u32,
 	) {
 		if let Some((ord, prev_field)) = previous_sector_ord_int {
 			// peek into the previous sector to create better flows over the portal goals
@@ -94,7 +155,10 @@ impl FlowField {
 				//TODO if <=, bottom to top aorund a wall can cause a siddeways bump
 				for n in possible_neighbours.iter() {
 					let n_flags = n.1 & INT_FILTER_BITS_FLAGS;
-					if n_flags & INT_BITS_IMPASSABLE != INT_BITS_IMPASSABLE {
+					if n_flags & INT_BITS_IMPASSABLE != INT_BITS_IMPASSABLE
+						&& connectivity.allows(n.0)
+						&& is_move_allowed(one_way, *goal, n.0)
+					{
 						let n_cost = n.1 & INT_FILTER_BITS_COST;
 						if n_cost < cheapest_value {
 							cheapest_value = n_cost;
@@ -113,12 +177,15 @@ impl FlowField {
 				} //TODO this sould never ever be none...
 			}
 		} else {
-			// set goal cells as this is the first flowfield i.e the end goal
+			// set goal cells as this is the first flowfield i.e the end goal,
+			// along with any other goals it has been seeded with
 			let mut goal_value = 0;
 			goal_value |= BITS_HAS_LOS;
 			goal_value |= BITS_GOAL;
 			goal_value |= BITS_PATHABLE;
-			self.set_field_cell_value(goal_value, goals[0]);
+			for goal in goals.iter() {
+				self.set_field_cell_value(goal_value, *goal);
+			}
 		}
 
 		for (i, column) in integration_field.get().iter().enumerate() {
@@ -139,7 +206,7 @@ impl FlowField {
 						// store the cheapest node
 						let mut cheapest_value = u16::MAX as u32;
 						let mut cheapest_neighbour = None;
-						let mut neighbours = Ordinal::get_all_cell_neighbours(field_cell);
+						let mut neighbours = Ordinal::get_cell_neighbours(field_cell, connectivity);
 
 						// find any diagonal cells which are flanked by impassable cells and so
 						// movement between them should be ignored/blocked, i.e
@@ -151,6 +218,14 @@ impl FlowField {
 						for diag in remove_diagonals.iter() {
 							neighbours.retain(|&n| n != *diag);
 						}
+						// never point up a drop or against a conveyor
+						neighbours.retain(|&n| {
+							is_move_allowed(
+								one_way,
+								field_cell,
+								Ordinal::cell_to_cell_direction(n, field_cell),
+							)
+						});
 
 						for n in neighbours.iter() {
 							let neighbour_cost =
@@ -160,6 +235,25 @@ impl FlowField {
 								cheapest_neighbour = Some(n);
 							}
 						}
+						if spread > 0 && cheapest_neighbour.is_some() {
+							let current_cost = current_value & INT_FILTER_BITS_COST;
+							let candidates: Vec<&FieldCell> = neighbours
+								.iter()
+								.filter(|n| {
+									let cost = integration_field.get_field_cell_value(**n)
+										& INT_FILTER_BITS_COST;
+									cost <= cheapest_value.saturating_add(spread)
+										&& cost < current_cost
+								})
+								.collect();
+							if !candidates.is_empty() {
+								// alternate between the candidates so adjacent
+								// cells head for different cells of the window
+								let pick = (field_cell.get_column() + field_cell.get_row())
+									% candidates.len();
+								cheapest_neighbour = Some(candidates[pick]);
+							}
+						}
 						if let Some(target) = cheapest_neighbour {
 							let ord = Ordinal::cell_to_cell_direction(*target, field_cell);
 							let bit_ord = convert_ordinal_to_bits_dir(ord);
@@ -179,6 +273,244 @@ impl FlowField {
 		}
 	}
 }
+/// Build the [IntegrationField] of a single sector seeded from each pathable
+/// `goal_cells`. Returns [None] if none of the goals are pathable
+fn integrate_single_sector(
+	cost_field: &CostField,
+	goal_cells: &[FieldCell],
+) -> Option<(Vec<FieldCell>, IntegrationField)> {
+	let seeds: Vec<(FieldCell, u8)> = goal_cells.iter().map(|goal| (*goal, 0)).collect();
+	integrate_single_sector_seeded(cost_field, &seeds)
+}
+
+/// Build the [IntegrationField] of a single sector seeded from each pathable
+/// goal of the `seeds` with its initial cost. Returns [None] if none of the
+/// goals are pathable
+fn integrate_single_sector_seeded(
+	cost_field: &CostField,
+	seeds: &[(FieldCell, u8)],
+) -> Option<(Vec<FieldCell>, IntegrationField)> {
+	let seeds: Vec<(FieldCell, u8)> = seeds
+		.iter()
+		.filter(|(cell, _)| cost_field.get_field_cell_value(*cell) != 255)
+		.copied()
+		.collect();
+	let (first, _) = seeds.first()?;
+	let mut int_field = IntegrationField::new(first, cost_field);
+	for (goal, initial_cost) in seeds.iter() {
+		int_field.add_seeded_goal(*goal, *initial_cost);
+	}
+	int_field.calculate_field(cost_field);
+	let goals = seeds.iter().map(|(goal, _)| *goal).collect();
+	Some((goals, int_field))
+}
+
+/// Build a [FlowField] directing actors towards the nearest of the
+/// `goal_cells` within a single sector, without any routing or [Portals].
+/// Useful for local behaviours such as moving to a point of interest within
+/// the sector an actor is already in. Impassable goals are ignored and if
+/// none of the goals are pathable the default (unpathable) [FlowField] is
+/// returned
+pub fn build_single_sector_flowfield(
+	cost_field: &CostField,
+	goal_cells: &[FieldCell],
+) -> FlowField {
+	let mut flow_field = FlowField::default();
+	let Some((goals, int_field)) = integrate_single_sector(cost_field, goal_cells) else {
+		return flow_field;
+	};
+	flow_field.calculate(&goals, None, &int_field);
+	let goal_value = BITS_HAS_LOS | BITS_GOAL | BITS_PATHABLE;
+	for goal in goals.iter() {
+		flow_field.set_field_cell_value(goal_value, *goal);
+	}
+	flow_field
+}
+
+/// Build a [FlowField] directing actors within a single sector towards
+/// goals of varying desirability, such as several drop-off points where one
+/// is preferred. Each goal of the `seeds` has an initial cost added to the
+/// cost of reaching it so actors head to the goal which is cheapest once
+/// its initial cost is included, a nearby less preferred goal is still used
+/// when the preferred one is far enough away. Impassable goals are ignored
+/// and if none of the goals are pathable the default (unpathable)
+/// [FlowField] is returned
+pub fn build_single_sector_flowfield_seeded(
+	cost_field: &CostField,
+	seeds: &[(FieldCell, u8)],
+) -> FlowField {
+	let mut flow_field = FlowField::default();
+	let Some((goals, int_field)) = integrate_single_sector_seeded(cost_field, seeds) else {
+		return flow_field;
+	};
+	flow_field.calculate(&goals, None, &int_field);
+	let goal_value = BITS_HAS_LOS | BITS_GOAL | BITS_PATHABLE;
+	for goal in goals.iter() {
+		flow_field.set_field_cell_value(goal_value, *goal);
+	}
+	flow_field
+}
+
+/// Build a [FlowField] directing actors away from the `goal_cells` within a
+/// single sector, such as to flee from a point. Each pathable [FieldCell]
+/// points towards the neighbour which is most expensive to reach from the
+/// goals, cells with no costlier neighbour (the furthest reachable points)
+/// have no direction. Impassable goals are ignored and if none of the goals
+/// are pathable the default (unpathable) [FlowField] is returned
+pub fn build_single_sector_flowfield_inverted(
+	cost_field: &CostField,
+	goal_cells: &[FieldCell],
+) -> FlowField {
+	let mut flow_field = FlowField::default();
+	let Some((_goals, int_field)) = integrate_single_sector(cost_field, goal_cells) else {
+		return flow_field;
+	};
+	let unreached = u16::MAX as u32;
+	for (i, column) in int_field.get().iter().enumerate() {
+		for (j, value) in column.iter().enumerate() {
+			let field_cell = FieldCell::new(i, j);
+			if value & INT_BITS_IMPASSABLE == INT_BITS_IMPASSABLE {
+				flow_field.set_field_cell_value(BITS_ZERO, field_cell);
+				continue;
+			}
+			let mut costliest_value = value & INT_FILTER_BITS_COST;
+			let mut costliest_neighbour = None;
+			let mut neighbours = Ordinal::get_cell_neighbours(field_cell, Connectivity::default());
+			for diag in find_blocked_diagonals(field_cell, &int_field).iter() {
+				neighbours.retain(|&n| n != *diag);
+			}
+			for n in neighbours.iter() {
+				let n_value = int_field.get_field_cell_value(*n);
+				let n_cost = n_value & INT_FILTER_BITS_COST;
+				if n_value & INT_BITS_IMPASSABLE != INT_BITS_IMPASSABLE
+					&& n_cost != unreached
+					&& n_cost > costliest_value
+				{
+					costliest_value = n_cost;
+					costliest_neighbour = Some(n);
+				}
+			}
+			let value = match costliest_neighbour {
+				Some(target) => {
+					let ord = Ordinal::cell_to_cell_direction(*target, field_cell);
+					convert_ordinal_to_bits_dir(ord) | BITS_PATHABLE
+				}
+				None => BITS_PATHABLE,
+			};
+			flow_field.set_field_cell_value(value, field_cell);
+		}
+	}
+	flow_field
+}
+
+/// Placement of a direction arrow over a [FlowField] cell for custom
+/// rendering, an arrow mesh pointing along the positive x-axis can be drawn
+/// with the `translation` and `rotation` (for instance as a [Transform] or
+/// as per-instance data of an instanced draw)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlowArrow {
+	/// The cell the arrow belongs to
+	field_cell: FieldCell,
+	/// The raw [FlowField] value of the cell so that flags (such as
+	/// [is_portal_goal]) can be used to style the arrow
+	value: u8,
+	/// Centre of the cell in world space
+	translation: Vec3,
+	/// Rotation of the arrow from the positive x-axis to the flow direction
+	rotation: Quat,
+}
+
+impl FlowArrow {
+	/// Get the [FieldCell] the arrow belongs to
+	pub fn get_field_cell(&self) -> FieldCell {
+		self.field_cell
+	}
+	/// Get the raw [FlowField] value of the cell
+	pub fn get_value(&self) -> u8 {
+		self.value
+	}
+	/// Get the world space centre of the cell
+	pub fn get_translation(&self) -> Vec3 {
+		self.translation
+	}
+	/// Get the rotation of the arrow
+	pub fn get_rotation(&self) -> Quat {
+		self.rotation
+	}
+	/// Get a [Transform] placing the arrow over its cell
+	pub fn get_transform(&self) -> Transform {
+		Transform::from_translation(self.translation).with_rotation(self.rotation)
+	}
+}
+
+impl FlowField {
+	/// Convert the directional cells of the [FlowField] of a sector into
+	/// [FlowArrow]s in the x-y plane of 2d space. Cells without a direction
+	/// (impassable, the goal and those with line of sight) are skipped
+	#[cfg(feature = "2d")]
+	pub fn get_arrows_2d(
+		&self,
+		sector_id: SectorID,
+		map_dimensions: &MapDimensions,
+	) -> Vec<FlowArrow> {
+		let mut arrows = Vec::new();
+		for (i, column) in self.get().iter().enumerate() {
+			for (j, value) in column.iter().enumerate() {
+				if get_ordinal_from_bits(*value) == Ordinal::Zero {
+					continue;
+				}
+				let field_cell = FieldCell::new(i, j);
+				let Some(position) = map_dimensions.get_xy_from_field_sector(sector_id, field_cell)
+				else {
+					continue;
+				};
+				let direction = get_2d_direction_unit_vector_from_bits(*value);
+				arrows.push(FlowArrow {
+					field_cell,
+					value: *value,
+					translation: position.extend(0.0),
+					rotation: Quat::from_rotation_z(direction.y.atan2(direction.x)),
+				});
+			}
+		}
+		arrows
+	}
+	/// Convert the directional cells of the [FlowField] of a sector into
+	/// [FlowArrow]s across the x-z plane of 3d space. Cells without a
+	/// direction (impassable, the goal and those with line of sight) are
+	/// skipped
+	#[cfg(feature = "3d")]
+	pub fn get_arrows_3d(
+		&self,
+		sector_id: SectorID,
+		map_dimensions: &MapDimensions,
+	) -> Vec<FlowArrow> {
+		let mut arrows = Vec::new();
+		for (i, column) in self.get().iter().enumerate() {
+			for (j, value) in column.iter().enumerate() {
+				if get_ordinal_from_bits(*value) == Ordinal::Zero {
+					continue;
+				}
+				let field_cell = FieldCell::new(i, j);
+				let Some(position) =
+					map_dimensions.get_xyz_from_field_sector(sector_id, field_cell)
+				else {
+					continue;
+				};
+				let direction = get_3d_direction_unit_vector_from_bits(*value);
+				arrows.push(FlowArrow {
+					field_cell,
+					value: *value,
+					translation: position,
+					// rotating about y maps the x-axis to (cos, 0, -sin)
+					rotation: Quat::from_rotation_y((-direction.z).atan2(direction.x)),
+				});
+			}
+		}
+		arrows
+	}
+}
+
 /// Used by a [FlowField] calculation that needs to peek into the previous sectors [IntegrationField] to align portal goal directional bits to the most optimal integration costs
 fn lookup_portal_goal_neighbour_costs_in_previous_sector(
 	portal_goal: &FieldCell,
@@ -329,6 +661,11 @@ fn find_blocked_diagonals(
 	diagonals
 }
 
+/// Get the bits of a pathable cell moving in the direction of `ordinal`
+pub fn get_pathable_bits_from_ordinal(ordinal: Ordinal) -> u8 {
+	convert_ordinal_to_bits_dir(ordinal) | BITS_PATHABLE
+}
+
 /// Indicates that a cell is pathable
 pub fn is_pathable(cell_value: u8) -> bool {
 	cell_value & BITS_PATHABLE == BITS_PATHABLE
@@ -412,6 +749,120 @@ pub fn get_3d_direction_unit_vector_from_bits(cell_value: u8) -> Vec3 {
 		_ => panic!("First 4 bits of cell are not recognised directions"),
 	}
 }
+/// As with [get_2d_direction_unit_vector_from_bits] but the direction is
+/// biased away from impassable neighbours of the `field_cell` which are
+/// within `radius` of the actor, so that an actor whose centre follows the
+/// flow doesn't clip wall corners. The `offset` is the position of the actor
+/// relative to the centre of the `field_cell`, both it and the `radius` are
+/// measured in [FieldCell]s (divide world units by
+/// [MapDimensions::get_field_cell_unit_size]). Neighbours across the
+/// boundary of the `sector_id` are looked up in the sector they lie in, the
+/// cells of missing sectors count as walls while the edge of the map
+/// doesn't. Cells with no direction aren't biased
+pub fn get_2d_direction_avoiding_walls(
+	cell_value: u8,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+	sector_id: SectorID,
+	field_cell: FieldCell,
+	offset: Vec2,
+	radius: f32,
+) -> Vec2 {
+	let flow = get_2d_direction_unit_vector_from_bits(cell_value).normalize_or_zero();
+	if flow == Vec2::ZERO {
+		return flow;
+	}
+	// rows increase southwards, against the y axis
+	let push = wall_repulsion(
+		sector_cost_fields,
+		map_dimensions,
+		(sector_id, field_cell),
+		Vec2::new(offset.x, -offset.y),
+		radius,
+	);
+	(flow + Vec2::new(push.x, -push.y)).normalize_or(flow)
+}
+/// As with [get_3d_direction_unit_vector_from_bits] but the direction is
+/// biased away from impassable neighbours, see
+/// [get_2d_direction_avoiding_walls]. The `offset` is measured across the
+/// x-z plane
+pub fn get_3d_direction_avoiding_walls(
+	cell_value: u8,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+	sector_id: SectorID,
+	field_cell: FieldCell,
+	offset: Vec3,
+	radius: f32,
+) -> Vec3 {
+	let flow = get_3d_direction_unit_vector_from_bits(cell_value).normalize_or_zero();
+	if flow == Vec3::ZERO {
+		return flow;
+	}
+	let push = wall_repulsion(
+		sector_cost_fields,
+		map_dimensions,
+		(sector_id, field_cell),
+		Vec2::new(offset.x, offset.z),
+		radius,
+	);
+	(flow + Vec3::new(push.x, 0.0, push.y)).normalize_or(flow)
+}
+/// Sum of the pushes away from each impassable neighbour of the `cell`
+/// closer than `radius` to the actor at `offset` from the centre of the
+/// cell, with `x` along the columns and `y` along the rows. Neighbours are
+/// found by their global position so walls in neighbouring sectors are
+/// included. Each push is weighted by how far the neighbour intrudes into
+/// the radius
+fn wall_repulsion(
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+	cell: (SectorID, FieldCell),
+	offset: Vec2,
+	radius: f32,
+) -> Vec2 {
+	let mut push = Vec2::ZERO;
+	if radius <= 0.0 {
+		return push;
+	}
+	let (column, row) = map_dimensions.global_cell_from(cell.0, cell.1);
+	let (column, row) = (column as i64, row as i64);
+	let columns = map_dimensions.get_total_field_cell_columns() as i64;
+	let rows = map_dimensions.get_total_field_cell_rows() as i64;
+	for (d_column, d_row) in [
+		(0, -1),
+		(1, -1),
+		(1, 0),
+		(1, 1),
+		(0, 1),
+		(-1, 1),
+		(-1, 0),
+		(-1, -1),
+	] {
+		let (n_column, n_row) = (column + d_column, row + d_row);
+		if n_column < 0 || n_row < 0 || n_column >= columns || n_row >= rows {
+			continue;
+		}
+		let n_cost = get_global_cost(
+			sector_cost_fields,
+			map_dimensions,
+			n_column as usize,
+			n_row as usize,
+		);
+		if n_cost != 255 {
+			continue;
+		}
+		let centre = Vec2::new(d_column as f32, d_row as f32);
+		// nearest point of the impassable cell to the actor
+		let nearest = offset.clamp(centre - Vec2::splat(0.5), centre + Vec2::splat(0.5));
+		let away = offset - nearest;
+		let distance = away.length();
+		if distance > 0.0 && distance < radius {
+			push += away / distance * (radius - distance) / radius;
+		}
+	}
+	push
+}
 
 // #[rustfmt::skip]
 #[cfg(test)]
@@ -423,6 +874,73 @@ mod tests {
 		let v = flow_field.get_field_cell_value(FieldCell::new(0, 0));
 		assert_eq!(BITS_DEFAULT, v);
 	}
+	#[test]
+	fn spread_fans_out_across_doorway() {
+		// a wall along row 5 with a doorway of soft obstacles in columns 3-6
+		let mut cost_field = CostField::default();
+		for column in 0..FIELD_RESOLUTION {
+			let cost = if (3..=6).contains(&column) {
+				Cost::SOFT_IMPASSABLE
+			} else {
+				Cost::IMPASSABLE
+			};
+			cost_field.set_field_cell_value(cost.get(), FieldCell::new(column, 5));
+		}
+		let goals = vec![FieldCell::new(4, 9)];
+		let mut integration_field = IntegrationField::default();
+		integration_field.add_los_corner(goals[0]);
+		integration_field.set_field_cell_value(0, goals[0]);
+		integration_field.calculate_field(&cost_field);
+		// the doorway cells entered from the cells just above it
+		let doorway_targets = |flow_field: &FlowField| {
+			let mut targets = Vec::new();
+			for column in 3..=6 {
+				let field_cell = FieldCell::new(column, 4);
+				let ord = get_ordinal_from_bits(flow_field.get_field_cell_value(field_cell));
+				if let Some(target) = field_cell.neighbour(ord) {
+					if target.get_row() == 5 && !targets.contains(&target) {
+						targets.push(target);
+					}
+				}
+			}
+			targets
+		};
+		let mut flow_field = FlowField::default();
+		flow_field.calculate_with_spread(
+			&goals,
+			None,
+			&integration_field,
+			Connectivity::default(),
+			None,
+			0,
+		);
+		let funnelled = doorway_targets(&flow_field);
+		let mut spread_field = FlowField::default();
+		spread_field.calculate_with_spread(
+			&goals,
+			None,
+			&integration_field,
+			Connectivity::default(),
+			None,
+			2,
+		);
+		let spread = doorway_targets(&spread_field);
+		assert!(spread.len() > funnelled.len());
+		// every cell still heads somewhere cheaper
+		for column in 0..FIELD_RESOLUTION {
+			for row in 0..FIELD_RESOLUTION {
+				let field_cell = FieldCell::new(column, row);
+				let value = spread_field.get_field_cell_value(field_cell);
+				if value & (BITS_PATHABLE | BITS_GOAL | BITS_HAS_LOS) != BITS_PATHABLE {
+					continue;
+				}
+				let target = field_cell.neighbour(get_ordinal_from_bits(value)).unwrap();
+				let cost =
+					|cell| integration_field.get_field_cell_value(cell) & INT_FILTER_BITS_COST;
+				assert!(cost(target) < cost(field_cell));
+			}
+		}
+	}
 	/// Flowfield of a single sector, all far southern cells are goals, verify direct paths from top to bottom
 	#[test]
 	fn calculate_flow_target_south() {
@@ -525,6 +1043,223 @@ mod tests {
 			}
 		}
 	}
+	/// Flowfield of a single sector restricted to four way movement, verify no cell points diagonally
+	#[test]
+	fn calculate_flow_four_way() {
+		let cost_field = CostField::default();
+		let goal = FieldCell::new(5, 5);
+		let mut integration_field = IntegrationField::default();
+		integration_field.add_los_corner(goal);
+		integration_field.set_field_cell_value(INT_BITS_GOAL, goal);
+		integration_field.calculate_field(&cost_field);
+
+		let mut flow_field = FlowField::default();
+		flow_field.calculate_with_connectivity(
+			&[goal],
+			None,
+			&integration_field,
+			Connectivity::FourWay,
+		);
+
+		let orthogonal = [BITS_NORTH, BITS_EAST, BITS_SOUTH, BITS_WEST];
+		for (i, column) in flow_field.get().iter().enumerate() {
+			for (j, row_value) in column.iter().enumerate() {
+				if FieldCell::new(i, j) == goal {
+					continue;
+				}
+				assert!(is_pathable(*row_value));
+				assert!(orthogonal.contains(&(row_value & BITS_DEFAULT)));
+			}
+		}
+		// diagonal from the goal so with eight way movement it would point north-west
+		let value = flow_field.get_field_cell_value(FieldCell::new(6, 6));
+		assert!(value & BITS_DEFAULT == BITS_NORTH || value & BITS_DEFAULT == BITS_WEST);
+	}
+	/// A ledge along row 5 can be dropped off but not climbed, except by a
+	/// ramp in column 9, verify no cell below points up the ledge
+	#[test]
+	fn calculate_flow_one_way() {
+		let cost_field = CostField::default();
+		let mut one_way = OneWayField::default();
+		for column in 0..9 {
+			one_way.block_exit(FieldCell::new(column, 5), Ordinal::North);
+		}
+		let goal = FieldCell::new(5, 0);
+		let mut integration_field = IntegrationField::default();
+		integration_field.add_los_corner(goal);
+		integration_field.set_field_cell_value(INT_BITS_GOAL, goal);
+		integration_field.calculate_field_with_one_way(&cost_field, Some(&one_way));
+
+		let mut flow_field = FlowField::default();
+		flow_field.calculate_with_one_way(
+			&[goal],
+			None,
+			&integration_field,
+			Connectivity::EightWay,
+			Some(&one_way),
+		);
+		let upwards = [BITS_NORTH, BITS_NORTH_EAST, BITS_NORTH_WEST];
+		for column in 0..9 {
+			let value = flow_field.get_field_cell_value(FieldCell::new(column, 5));
+			assert!(is_pathable(value));
+			assert!(!upwards.contains(&(value & BITS_DEFAULT)));
+		}
+		// the detour via the ramp is longer than climbing straight up
+		let below =
+			integration_field.get_field_cell_value(FieldCell::new(2, 5)) & INT_FILTER_BITS_COST;
+		assert!(below > 5);
+		// the ramp can be climbed
+		let ramp = flow_field.get_field_cell_value(FieldCell::new(9, 5));
+		assert!(upwards.contains(&(ramp & BITS_DEFAULT)));
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn arrows_2d() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut flow_field = FlowField::default();
+		flow_field.set_field_cell_value(BITS_PATHABLE + BITS_NORTH_EAST, FieldCell::new(0, 0));
+		flow_field.set_field_cell_value(BITS_ZERO, FieldCell::new(1, 0));
+		flow_field.set_field_cell_value(
+			BITS_GOAL + BITS_HAS_LOS + BITS_PATHABLE,
+			FieldCell::new(2, 0),
+		);
+		let arrows = flow_field.get_arrows_2d(SectorID::new(0, 0), &map_dimensions);
+		// the remaining cells keep the default bits which don't map to a direction
+		assert_eq!(1, arrows.len());
+		let arrow = arrows[0];
+		assert_eq!(FieldCell::new(0, 0), arrow.get_field_cell());
+		assert_eq!(Vec3::new(-14.5, 14.5, 0.0), arrow.get_translation());
+		let pointing = arrow.get_rotation() * Vec3::X;
+		assert!(pointing.abs_diff_eq(Vec3::new(1.0, 1.0, 0.0).normalize(), 0.0001));
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn arrows_3d() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut flow_field = FlowField::default();
+		flow_field.set_field_cell_value(BITS_PATHABLE + BITS_NORTH, FieldCell::new(0, 0));
+		flow_field.set_field_cell_value(BITS_PATHABLE + BITS_SOUTH_WEST, FieldCell::new(9, 9));
+		let arrows = flow_field.get_arrows_3d(SectorID::new(0, 0), &map_dimensions);
+		assert_eq!(2, arrows.len());
+		assert_eq!(Vec3::new(-14.5, 0.0, -14.5), arrows[0].get_translation());
+		let pointing = arrows[0].get_rotation() * Vec3::X;
+		assert!(pointing.abs_diff_eq(Vec3::new(0.0, 0.0, -1.0), 0.0001));
+		let pointing = arrows[1].get_rotation() * Vec3::X;
+		assert!(pointing.abs_diff_eq(Vec3::new(-1.0, 0.0, 1.0).normalize(), 0.0001));
+	}
+	#[test]
+	fn single_sector_flowfield() {
+		let mut cost_field = CostField::default();
+		cost_field.set_field_cell_value(255, FieldCell::new(5, 5));
+		let goal = FieldCell::new(4, 4);
+		let flow_field = build_single_sector_flowfield(&cost_field, &[goal, FieldCell::new(5, 5)]);
+		assert!(is_goal(flow_field.get_field_cell_value(goal)));
+		assert_eq!(0, flow_field.get_field_cell_value(FieldCell::new(5, 5)));
+		let west = flow_field.get_field_cell_value(FieldCell::new(3, 4));
+		assert!(is_pathable(west));
+		assert_eq!(Ordinal::East, get_ordinal_from_bits(west));
+		let corner = flow_field.get_field_cell_value(FieldCell::new(0, 0));
+		assert_eq!(Ordinal::SouthEast, get_ordinal_from_bits(corner));
+		// no pathable goals
+		let flow_field = build_single_sector_flowfield(&cost_field, &[FieldCell::new(5, 5)]);
+		assert_eq!(BITS_DEFAULT, flow_field.get_field_cell_value(goal));
+	}
+	#[test]
+	fn direction_avoiding_walls() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(1, 1);
+		cost_fields.set_field_cell_value(sector_id, 255, FieldCell::new(5, 4), &map_dimensions);
+		let field_cell = FieldCell::new(5, 5);
+		let east = get_pathable_bits_from_ordinal(Ordinal::East);
+		let avoid_2d = |cost_fields: &SectorCostFields, sector_id, field_cell, offset| {
+			get_2d_direction_avoiding_walls(
+				east,
+				cost_fields,
+				&map_dimensions,
+				sector_id,
+				field_cell,
+				offset,
+				0.4,
+			)
+		};
+		// hugging the wall to the north pushes the actor south
+		let offset = Vec2::new(0.0, 0.3);
+		let dir = avoid_2d(&cost_fields, sector_id, field_cell, offset);
+		assert!(dir.x > 0.0 && dir.y < 0.0);
+		assert!((dir.length() - 1.0).abs() < 0.0001);
+		let offset = Vec3::new(0.0, 0.0, -0.3);
+		let dir = get_3d_direction_avoiding_walls(
+			east,
+			&cost_fields,
+			&map_dimensions,
+			sector_id,
+			field_cell,
+			offset,
+			0.4,
+		);
+		assert!(dir.x > 0.0 && dir.z > 0.0);
+		// out of reach of the wall the flow is unchanged
+		let offset = Vec2::new(0.0, -0.3);
+		let dir = avoid_2d(&cost_fields, sector_id, field_cell, offset);
+		assert_eq!(Vec2::X, dir);
+		// a wall just across the northern boundary of the sector
+		cost_fields.set_field_cell_value(
+			SectorID::new(1, 0),
+			255,
+			FieldCell::new(5, 9),
+			&map_dimensions,
+		);
+		let dir = avoid_2d(
+			&cost_fields,
+			sector_id,
+			FieldCell::new(5, 0),
+			Vec2::new(0.0, 0.3),
+		);
+		assert!(dir.x > 0.0 && dir.y < 0.0);
+		// the edge of the map isn't a wall
+		let dir = avoid_2d(
+			&cost_fields,
+			SectorID::new(0, 0),
+			FieldCell::new(5, 0),
+			Vec2::new(0.0, 0.3),
+		);
+		assert_eq!(Vec2::X, dir);
+	}
+	#[test]
+	fn single_sector_flowfield_seeded() {
+		let cost_field = CostField::default();
+		let preferred = FieldCell::new(0, 5);
+		let fallback = FieldCell::new(9, 5);
+		let flow_field =
+			build_single_sector_flowfield_seeded(&cost_field, &[(preferred, 0), (fallback, 6)]);
+		assert!(is_goal(flow_field.get_field_cell_value(preferred)));
+		assert!(is_goal(flow_field.get_field_cell_value(fallback)));
+		// nearer to the fallback yet the preferred goal is still cheaper
+		let middle = flow_field.get_field_cell_value(FieldCell::new(6, 5));
+		assert_eq!(Ordinal::West, get_ordinal_from_bits(middle));
+		// right next to the fallback it wins out
+		let near_fallback = flow_field.get_field_cell_value(FieldCell::new(8, 5));
+		assert_eq!(Ordinal::East, get_ordinal_from_bits(near_fallback));
+	}
+	#[test]
+	fn single_sector_flowfield_inverted() {
+		let cost_field = CostField::default();
+		let goal = FieldCell::new(4, 4);
+		let flow_field = build_single_sector_flowfield_inverted(&cost_field, &[goal]);
+		let west = flow_field.get_field_cell_value(FieldCell::new(3, 4));
+		assert!(is_pathable(west));
+		assert!(matches!(
+			get_ordinal_from_bits(west),
+			Ordinal::NorthWest | Ordinal::SouthWest
+		));
+		let north_west = flow_field.get_field_cell_value(FieldCell::new(2, 2));
+		assert_eq!(Ordinal::NorthWest, get_ordinal_from_bits(north_west));
+		// the furthest cell has nowhere further to go
+		let corner = flow_field.get_field_cell_value(FieldCell::new(9, 9));
+		assert!(is_pathable(corner));
+		assert_eq!(Ordinal::Zero, get_ordinal_from_bits(corner));
+	}
 	//TODO test blocked diag
 	//TODO
 }
@@ -0,0 +1,146 @@
+//! Every bit of a [FlowField] value is used by the crate, the lower 4 bits
+//! hold a direction and the upper 4 bits flag whether a cell is pathable,
+//! has line of sight to the goal, is the goal or is a portal. Games which
+//! want to mark cells with their own information, such as "in a corridor"
+//! or "near the goal", can supply a [FlowFlagHook] to the [FlowFieldCache]
+//! which is run as each [FlowField] is generated. The flags it produces are
+//! stored in a [UserFlagField] alongside the [FlowField] and their meaning is
+//! entirely up to the game
+//!
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Decides the user flags of each [FieldCell] when a [FlowField] is
+/// generated
+pub trait FlowFlagHook: Send + Sync {
+	/// Get the flags of a `field_cell` within the sector `sector_id`. The
+	/// `flow_value` is the value of the cell in the newly generated
+	/// [FlowField] and `integration_field` is the [IntegrationField] it was
+	/// calculated from, use [INT_FILTER_BITS_COST] to read the integrated cost
+	/// of a cell from it. Up to 8 flags can be set, returning `0` sets none
+	fn flags(
+		&self,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+		flow_value: u8,
+		integration_field: &IntegrationField,
+	) -> u8;
+}
+
+/// The user flags of each [FieldCell] of a [FlowField], produced by a
+/// [FlowFlagHook]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Default, Debug, PartialEq, Reflect)]
+pub struct UserFlagField([[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION]);
+
+impl Field<u8> for UserFlagField {
+	/// Get a reference to the field array
+	fn get(&self) -> &[[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION] {
+		&self.0
+	}
+	/// Retrieve a field cell value
+	fn get_field_cell_value(&self, field_cell: FieldCell) -> u8 {
+		self.0[field_cell.get_column()][field_cell.get_row()]
+	}
+	/// Set a field cell to a value
+	fn set_field_cell_value(&mut self, value: u8, field_cell: FieldCell) {
+		self.0[field_cell.get_column()][field_cell.get_row()] = value;
+	}
+}
+
+impl UserFlagField {
+	/// Run the `hook` over every [FieldCell] of a [FlowField] to produce its
+	/// user flags
+	pub fn from_hook(
+		hook: &dyn FlowFlagHook,
+		sector_id: SectorID,
+		flow_field: &FlowField,
+		integration_field: &IntegrationField,
+	) -> Self {
+		let mut flags = UserFlagField::default();
+		for column in 0..FIELD_RESOLUTION {
+			for row in 0..FIELD_RESOLUTION {
+				let field_cell = FieldCell::new(column, row);
+				let value = hook.flags(
+					sector_id,
+					field_cell,
+					flow_field.get_field_cell_value(field_cell),
+					integration_field,
+				);
+				flags.set_field_cell_value(value, field_cell);
+			}
+		}
+		flags
+	}
+	/// Whether every bit of the `flag` is set on the `field_cell`
+	pub fn has_flag(&self, field_cell: FieldCell, flag: u8) -> bool {
+		self.get_field_cell_value(field_cell) & flag == flag
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::utils::Duration;
+
+	/// Flags cells whose integrated cost is within 3 of the goal
+	const NEAR_GOAL: u8 = 0b0000_0001;
+	/// Flags cells with line of sight to the goal
+	const SEES_GOAL: u8 = 0b0000_0010;
+
+	/// Test hook setting both flags
+	struct TestHook;
+
+	impl FlowFlagHook for TestHook {
+		fn flags(
+			&self,
+			_sector_id: SectorID,
+			field_cell: FieldCell,
+			flow_value: u8,
+			integration_field: &IntegrationField,
+		) -> u8 {
+			let cost = integration_field.get_field_cell_value(field_cell) & INT_FILTER_BITS_COST;
+			let mut flags = 0;
+			if cost <= 3 {
+				flags |= NEAR_GOAL;
+			}
+			if has_line_of_sight(flow_value) {
+				flags |= SEES_GOAL;
+			}
+			flags
+		}
+	}
+
+	#[test]
+	fn user_flags_from_hook() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		bundle.flow_field_cache = FlowFieldCache::default().with_flag_hook(TestHook);
+		let goal = FieldCell::new(4, 4);
+		let leg = RouteMetadata::new(
+			SectorID::new(1, 0),
+			FieldCell::new(4, 4),
+			SectorID::new(0, 0),
+			goal,
+			Duration::default(),
+		);
+		bundle.prewarm(&[leg]);
+		let cache = &bundle.flow_field_cache;
+		let flags = cache
+			.get_user_flags(SectorID::new(0, 0), SectorID::new(0, 0), goal)
+			.unwrap();
+		assert!(flags.has_flag(goal, NEAR_GOAL));
+		assert!(!flags.has_flag(FieldCell::new(9, 9), NEAR_GOAL));
+		assert!(flags.has_flag(FieldCell::new(9, 9), SEES_GOAL));
+		// fields removed from the cache take their flags with them
+		let (metadata, _) = cache
+			.get_field_with_metadata(SectorID::new(0, 0), SectorID::new(0, 0), goal)
+			.unwrap();
+		let metadata = *metadata;
+		bundle.flow_field_cache.remove_field(metadata);
+		assert!(bundle
+			.flow_field_cache
+			.get_user_flags(SectorID::new(0, 0), SectorID::new(0, 0), goal)
+			.is_none());
+	}
+}
@@ -0,0 +1,99 @@
+//! A HeightField records the elevation of each field cell of a sector so
+//! that the cost of a step can depend on its direction, climbing a hill
+//! costs more than walking down it. The extra cost of a step is given by
+//! the [SlopeCost] of the [SectorCostFields] from the difference in height
+//! between the two cells and is added to the [CostField] value during
+//! integration, producing flows which prefer to go downhill.
+//!
+//! The field is optional, sectors without one are treated as flat
+//!
+
+use crate::prelude::*;
+use bevy::reflect::Reflect;
+
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Default, Debug, PartialEq, Reflect)]
+pub struct HeightField([[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION]);
+
+impl Field<u8> for HeightField {
+	/// Get a reference to the field array
+	fn get(&self) -> &[[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION] {
+		&self.0
+	}
+	/// Retrieve a field cell value
+	///
+	/// NB: This will panic if out of bounds
+	fn get_field_cell_value(&self, field_cell: FieldCell) -> u8 {
+		self.0[field_cell.get_column()][field_cell.get_row()]
+	}
+	/// Set a field cell to a value
+	///
+	/// NB: This will panic if out of bounds
+	fn set_field_cell_value(&mut self, value: u8, field_cell: FieldCell) {
+		self.0[field_cell.get_column()][field_cell.get_row()] = value;
+	}
+}
+
+impl HeightField {
+	/// Create a new [HeightField] where the height of each [FieldCell] is
+	/// given by `height`, such as by sampling a heightmap
+	pub fn from_fn(height: impl Fn(FieldCell) -> u8) -> Self {
+		let mut field = HeightField::default();
+		for column in 0..FIELD_RESOLUTION {
+			for row in 0..FIELD_RESOLUTION {
+				let field_cell = FieldCell::new(column, row);
+				field.set_field_cell_value(height(field_cell), field_cell);
+			}
+		}
+		field
+	}
+}
+
+/// How much more a step costs for each unit of height it climbs or descends
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Reflect)]
+pub struct SlopeCost {
+	/// Extra cost per unit of height climbed
+	uphill: u32,
+	/// Extra cost per unit of height descended
+	downhill: u32,
+}
+
+impl SlopeCost {
+	/// Create a new instance of [SlopeCost]
+	pub fn new(uphill: u32, downhill: u32) -> Self {
+		SlopeCost { uphill, downhill }
+	}
+	/// Get the extra cost per unit of height climbed
+	pub fn get_uphill(&self) -> u32 {
+		self.uphill
+	}
+	/// Get the extra cost per unit of height descended
+	pub fn get_downhill(&self) -> u32 {
+		self.downhill
+	}
+	/// Get the extra cost of stepping from a cell at height `from` to a cell
+	/// at height `to`
+	pub fn step_cost(&self, from: u8, to: u8) -> u32 {
+		if to > from {
+			(to - from) as u32 * self.uphill
+		} else {
+			(from - to) as u32 * self.downhill
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn slope_step_cost() {
+		let slope = SlopeCost::new(3, 1);
+		assert_eq!(6, slope.step_cost(4, 6));
+		assert_eq!(2, slope.step_cost(6, 4));
+		assert_eq!(0, slope.step_cost(5, 5));
+		let field = HeightField::from_fn(|cell| cell.get_row() as u8);
+		assert_eq!(7, field.get_field_cell_value(FieldCell::new(2, 7)));
+	}
+}
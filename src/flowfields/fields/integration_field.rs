@@ -25,18 +25,82 @@
 //! 3. 3. Add the `CostField` cost to the `IntegrationFields` cost of the current cell (at the corner the wavefront cost assigned was 4, assuming the `CostField` value of the adjacent cell is `1` then the integrated cost becomes `5`)
 //! 4. Wavefront propagates to the next neighbours, find their ordinals and repeat adding their cost value to to the current cells integration cost to produce their cumulative integration cost, and repeat until the entire field is done
 //!
+//! The wavefront is processed in order of integrated cost with a bucketed priority queue, this means that each cell is settled once with its cheapest cost even when `CostField` values vary a lot.
+//!
 //! The end result effectively produces a gradient of high numbers to low numbers, a flow of sorts.
 //!
 //! For Sectors other than the goal the process is effectively the same where boundary portals are treated as corners and wave propagation exapaned.
 //!
 
+use bevy::log::warn;
 use bevy::reflect::Reflect;
+#[cfg(use_rayon)]
+use rayon::prelude::*;
 
+use super::buffer_pool::{self, INT_COST_BUCKETS};
 use crate::prelude::*;
 
+/// Most goals a route can be seeded with, see [GoalSeeds]
+pub const MAX_GOAL_SEEDS: usize = 8;
+
+/// Additional goals within the goal sector of a route, each with the
+/// integrated cost it starts at rather than `0`, such as several drop-off
+/// points where one is preferred. An actor heads to whichever goal is
+/// cheapest once its initial cost is included, see
+/// [IntegrationField::add_seeded_goal]. Up to [MAX_GOAL_SEEDS] goals are
+/// held inline so that the metadata carrying them stays [Copy]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct GoalSeeds {
+	/// `(column, row, initial_cost)` of each goal sorted by cell
+	seeds: [(u8, u8, u8); MAX_GOAL_SEEDS],
+	/// Number of `seeds` in use
+	len: u8,
+}
+
+impl GoalSeeds {
+	/// Create a new instance of [GoalSeeds] from each goal [FieldCell] and
+	/// its initial cost. A goal listed more than once keeps its cheapest
+	/// cost and goals beyond [MAX_GOAL_SEEDS] are ignored
+	pub fn new(seeds: &[(FieldCell, u8)]) -> Self {
+		let mut goal_seeds = GoalSeeds::default();
+		for (cell, initial_cost) in seeds.iter() {
+			let (column, row) = (cell.get_column() as u8, cell.get_row() as u8);
+			let used = &mut goal_seeds.seeds[..goal_seeds.len as usize];
+			if let Some(seed) = used.iter_mut().find(|s| (s.0, s.1) == (column, row)) {
+				seed.2 = seed.2.min(*initial_cost);
+			} else if (goal_seeds.len as usize) < MAX_GOAL_SEEDS {
+				goal_seeds.seeds[goal_seeds.len as usize] = (column, row, *initial_cost);
+				goal_seeds.len += 1;
+			} else {
+				warn!(
+					"Only {} goals can be seeded, ignoring {:?}",
+					MAX_GOAL_SEEDS, cell
+				);
+			}
+		}
+		goal_seeds.seeds[..goal_seeds.len as usize].sort();
+		goal_seeds
+	}
+	/// Iterate over each goal [FieldCell] and its initial cost
+	pub fn iter(&self) -> impl Iterator<Item = (FieldCell, u8)> + '_ {
+		self.seeds[..self.len as usize]
+			.iter()
+			.map(|(column, row, cost)| (FieldCell::new(*column as usize, *row as usize), *cost))
+	}
+	/// Get the number of seeded goals
+	pub fn len(&self) -> usize {
+		self.len as usize
+	}
+	/// Whether no goals are seeded
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+}
+
 /// Grouping of high-level route from goal to actor where the integration
 /// fields get populated when the builder arrives at the front of the queue
-#[derive(Default, Reflect)]
+#[derive(Default, Clone, Reflect)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct IntegrationBuilder {
 	//TODO try avoiding allocating path here and within int_fields
@@ -52,15 +116,62 @@ pub struct IntegrationBuilder {
 	has_los_pass: bool,
 	/// Has the integration cost of the fields been calculated
 	has_cost_pass: bool,
+	/// Which neighbouring [FieldCell]s an actor may move between
+	connectivity: Connectivity,
+	/// Indices of the `integration_fields` which have been copied from an
+	/// identical field that's already cached and so don't need building
+	shared_fields: Vec<usize>,
+	/// Indices of the `integration_fields` whose sectors lie within the
+	/// stopping distance of the goal and so don't need a [FlowField]
+	skipped_fields: Vec<usize>,
+	/// The [OneWayField] of each sector along the route, if it has one
+	#[cfg_attr(feature = "serde", serde(default))]
+	one_way_fields: Vec<Option<OneWayField>>,
+	/// The [HeightField] of each sector along the route, if it isn't flat
+	#[cfg_attr(feature = "serde", serde(default))]
+	height_fields: Vec<Option<HeightField>>,
+	/// Extra cost of a step climbing or descending between heights
+	#[cfg_attr(feature = "serde", serde(default))]
+	slope_cost: SlopeCost,
+	/// The faction the fields are built for
+	#[cfg_attr(feature = "serde", serde(default))]
+	faction: Option<FactionId>,
+	/// The [FieldCell]s of each sector along the route the faction may not
+	/// enter
+	#[cfg_attr(feature = "serde", serde(default))]
+	restricted_cells: Vec<Vec<FieldCell>>,
+	/// The [EventPathRequest] the fields are built for
+	#[cfg_attr(feature = "serde", serde(default))]
+	request_id: Option<RequestId>,
+	/// The [FieldCell]s of the sector next to the goal sector which have Line
+	/// Of Sight through the portal window to the goal
+	#[cfg_attr(feature = "serde", serde(default))]
+	approach_los: Vec<FieldCell>,
+	/// Additional goals of the goal sector and the costs they start at
+	#[cfg_attr(feature = "serde", serde(default))]
+	goal_seeds: GoalSeeds,
 }
 
 impl IntegrationBuilder {
 	/// Create a new instance [IntegrationBuilder] initialised with a `path`
 	pub fn new(path: Route, cost_fields: &SectorCostFields) -> Self {
 		let mut int_fields = Vec::with_capacity(path.get().len());
+		let mut one_way_fields = Vec::with_capacity(path.get().len());
+		let mut height_fields = Vec::with_capacity(path.get().len());
 		for (sector, goal) in path.get().iter() {
-			let cost = cost_fields.get_scaled().get(sector).unwrap();
-			int_fields.push((*sector, Vec::new(), IntegrationField::new(goal, cost)));
+			one_way_fields.push(cost_fields.get_one_way_field(*sector).cloned());
+			height_fields.push(
+				cost_fields
+					.get_slope(*sector)
+					.map(|(heights, _)| heights.clone()),
+			);
+			if let Some(cost) = cost_fields.get_scaled().get(sector) {
+				int_fields.push((*sector, Vec::new(), IntegrationField::new(goal, cost)));
+			} else {
+				// keep the fields aligned with the path, the sector will produce an unusable field
+				warn!("Route passes through non-existent sector {:?}", sector);
+				int_fields.push((*sector, Vec::new(), IntegrationField::default()));
+			}
 		}
 		IntegrationBuilder {
 			path,
@@ -68,8 +179,86 @@ impl IntegrationBuilder {
 			has_expanded_portals: false,
 			has_los_pass: false,
 			has_cost_pass: false,
+			connectivity: Connectivity::default(),
+			shared_fields: Vec::new(),
+			skipped_fields: Vec::new(),
+			one_way_fields,
+			height_fields,
+			slope_cost: cost_fields.get_slope_cost(),
+			faction: None,
+			restricted_cells: Vec::new(),
+			request_id: None,
+			approach_los: Vec::new(),
+			goal_seeds: GoalSeeds::default(),
 		}
 	}
+	/// Build the fields for actors restricted to the given [Connectivity]
+	pub fn with_connectivity(mut self, connectivity: Connectivity) -> Self {
+		self.connectivity = connectivity;
+		self
+	}
+	/// Get the [Connectivity] the fields are built for
+	pub fn get_connectivity(&self) -> Connectivity {
+		self.connectivity
+	}
+	/// Build the fields on behalf of a `faction`, see [RouteMetadata::with_faction]
+	pub fn with_faction(mut self, faction: FactionId) -> Self {
+		self.faction = Some(faction);
+		self
+	}
+	/// Get the faction the fields are built for
+	pub fn get_faction(&self) -> Option<FactionId> {
+		self.faction
+	}
+	/// Tag the fields with the [EventPathRequest] they're built for
+	pub fn with_request_id(mut self, request_id: RequestId) -> Self {
+		self.request_id = Some(request_id);
+		self
+	}
+	/// Get the ID of the [EventPathRequest] the fields are built for
+	pub fn get_request_id(&self) -> Option<RequestId> {
+		self.request_id
+	}
+	/// Seed the goal sector with additional goals, see
+	/// [RouteMetadata::with_goal_seeds]
+	pub fn with_goal_seeds(mut self, goal_seeds: GoalSeeds) -> Self {
+		self.goal_seeds = goal_seeds;
+		self
+	}
+	/// Get the additional goals the goal sector is seeded with
+	pub fn get_goal_seeds(&self) -> GoalSeeds {
+		self.goal_seeds
+	}
+	/// Mark the [FieldCell]s the faction of the builder may not enter as
+	/// impassable in the [IntegrationField] of each sector along the route.
+	/// Must be applied before the portals are expanded, builders without a
+	/// faction are unaffected
+	pub fn apply_zones(&mut self, zones: &FactionZones) {
+		let Some(faction) = self.faction else {
+			return;
+		};
+		self.restricted_cells = Vec::with_capacity(self.integration_fields.len());
+		for (sector_id, _goals, field) in self.integration_fields.iter_mut() {
+			let restricted = zones.get_restricted_cells(faction, *sector_id);
+			for cell in restricted.iter() {
+				field.set_field_cell_value(65535 + INT_BITS_IMPASSABLE, *cell);
+			}
+			self.restricted_cells.push(restricted);
+		}
+	}
+	/// Get the [OneWayField] of the sector at position `i` of the route, if
+	/// it has one
+	pub fn get_one_way_field(&self, i: usize) -> Option<&OneWayField> {
+		self.one_way_fields.get(i).and_then(|field| field.as_ref())
+	}
+	/// Get the [HeightField] and [SlopeCost] of the sector at position `i`
+	/// of the route, if it isn't flat
+	pub fn get_slope(&self, i: usize) -> Option<(&HeightField, SlopeCost)> {
+		self.height_fields
+			.get(i)
+			.and_then(|field| field.as_ref())
+			.map(|heights| (heights, self.slope_cost))
+	}
 	/// Get the series of sectors and connecting portals of the path
 	pub fn get_route(&self) -> &Route {
 		&self.path
@@ -78,6 +267,29 @@ impl IntegrationBuilder {
 	pub fn get_integration_fields(&self) -> &Vec<(SectorID, Vec<FieldCell>, IntegrationField)> {
 		&self.integration_fields
 	}
+	/// Record the estimated bytes of the fields built so far against the
+	/// sectors of the route
+	pub(crate) fn record_memory_usage(&self, usage: &mut MemoryUsage) {
+		for (i, (sector_id, goals, int_field)) in self.integration_fields.iter().enumerate() {
+			let mut size = std::mem::size_of::<(SectorID, Vec<FieldCell>, IntegrationField)>()
+				+ goals.capacity() * std::mem::size_of::<FieldCell>()
+				+ int_field.heap_size();
+			// sectors without one-way movement or slopes hold nothing
+			if let Some(Some(_)) = self.one_way_fields.get(i) {
+				size += std::mem::size_of::<OneWayField>();
+			}
+			if let Some(Some(_)) = self.height_fields.get(i) {
+				size += std::mem::size_of::<HeightField>();
+			}
+			if let Some(restricted) = self.restricted_cells.get(i) {
+				size += restricted.capacity() * std::mem::size_of::<FieldCell>();
+			}
+			if i == 1 {
+				size += self.approach_los.capacity() * std::mem::size_of::<FieldCell>();
+			}
+			usage.add(*sector_id, MemoryCategory::QueuedRoutes, size);
+		}
+	}
 	/// Get the list of fields
 	pub fn get_mut_integration_fields(
 		&mut self,
@@ -109,6 +321,58 @@ impl IntegrationBuilder {
 	pub fn set_cost_pass(&mut self) {
 		self.has_cost_pass = true;
 	}
+	/// Replace the [IntegrationField] at position `i` of the route with an
+	/// already built `field` so that it's skipped by the cost pass and no
+	/// [FlowField] is recalculated for it
+	pub fn share_field(&mut self, i: usize, field: IntegrationField) {
+		if let Some((_, _, int_field)) = self.integration_fields.get_mut(i) {
+			*int_field = field;
+			if !self.shared_fields.contains(&i) {
+				self.shared_fields.push(i);
+			}
+		}
+	}
+	/// Whether the [IntegrationField] at position `i` of the route was
+	/// copied from the cache
+	pub fn is_field_shared(&self, i: usize) -> bool {
+		self.shared_fields.contains(&i)
+	}
+	/// Mark the sector at position `i` of the route as not needing a
+	/// [FlowField]. Its [IntegrationField] is only built if the [FlowField]
+	/// of the next sector along the route depends upon it
+	pub fn skip_field(&mut self, i: usize) {
+		if i < self.integration_fields.len() && !self.skipped_fields.contains(&i) {
+			self.skipped_fields.push(i);
+		}
+	}
+	/// Whether the sector at position `i` of the route doesn't need a
+	/// [FlowField]
+	pub fn is_field_skipped(&self, i: usize) -> bool {
+		self.skipped_fields.contains(&i)
+	}
+	/// Skip every sector of the route which lies entirely within the
+	/// stopping distance of the route `metadata`, see
+	/// [RouteMetadata::is_sector_within_stopping_distance]
+	pub fn skip_fields_within_stopping_distance(
+		&mut self,
+		metadata: &RouteMetadata,
+		map_dimensions: &MapDimensions,
+	) {
+		for i in 0..self.path.get().len() {
+			let sector_id = self.path.get()[i].0;
+			if metadata.is_sector_within_stopping_distance(sector_id, map_dimensions) {
+				self.skip_field(i);
+			}
+		}
+	}
+	/// Whether the cost pass can ignore the [IntegrationField] at position
+	/// `i`, either it was shared or neither it nor the next sector along the
+	/// route need a [FlowField]
+	fn is_cost_pass_skipped(&self, i: usize) -> bool {
+		self.is_field_shared(i)
+			|| (self.is_field_skipped(i)
+				&& (i + 1 >= self.integration_fields.len() || self.is_field_skipped(i + 1)))
+	}
 	/// Portals may represent multiple [FieldCell]s along a boundary, expand
 	/// them within the IntegrationFields to provide multiple goal [FieldCell]s
 	/// for crossing from one sector to another
@@ -118,29 +382,55 @@ impl IntegrationBuilder {
 		sector_cost_fields_scaled: &SectorCostFields,
 		map_dimensions: &MapDimensions,
 	) {
+		#[cfg(feature = "trace")]
+		let _span = bevy::log::info_span!(
+			"expand_integration_portals",
+			sectors = self.integration_fields.len()
+		)
+		.entered();
+		let restricted_cells = &self.restricted_cells;
 		for (i, (sector_id, goals, field)) in self.integration_fields.iter_mut().enumerate() {
 			// first element is always the end target, don't bother with portal expansion,
 			// just store the single end goal in the list
 			if i == 0 {
-				goals.push(self.path.get()[i].1);
-				field.set_field_cell_value(INT_BITS_GOAL, self.path.get()[i].1);
+				let goal = self.path.get()[i].1;
+				goals.push(goal);
+				field.set_field_cell_value(INT_BITS_GOAL, goal);
+				// impassable and restricted cells have already been marked
+				for (seed, initial_cost) in self.goal_seeds.iter() {
+					if seed == goal || field.get_field_cell_value(seed) & INT_BITS_IMPASSABLE != 0 {
+						continue;
+					}
+					goals.push(seed);
+					field.add_seeded_goal(seed, initial_cost);
+				}
 			} else {
 				// portals represent the boundary to another sector, a portal can be spread over
 				// multple field cells, expand the portal to provide multiple goal
 				// targets for moving to another sector
 				let neighbour_sector_id = self.path.get()[i - 1].0;
-				let expanded_goals = sector_portals
-					.get()
-					.get(sector_id)
-					.unwrap()
-					.expand_portal_into_goals(
-						sector_cost_fields_scaled,
-						sector_id,
-						&self.path.get()[i].1, // portal
-						&neighbour_sector_id,
-						map_dimensions,
+				let Some(portals) = sector_portals.get().get(sector_id) else {
+					warn!(
+						"Cannot expand portals of non-existent sector {:?}",
+						sector_id
 					);
+					continue;
+				};
+				let expanded_goals = portals.expand_portal_into_goals(
+					sector_cost_fields_scaled,
+					sector_id,
+					&self.path.get()[i].1, // portal
+					&neighbour_sector_id,
+					map_dimensions,
+				);
 				for g in expanded_goals.iter() {
+					// the faction cannot cross where the portal is restricted
+					if restricted_cells
+						.get(i)
+						.is_some_and(|cells| cells.contains(g))
+					{
+						continue;
+					}
 					// set the goals of the expanded portal, value and the bit flag
 					goals.push(*g);
 					field.set_field_cell_value(INT_BITS_PORTAL, *g)
@@ -151,16 +441,32 @@ impl IntegrationBuilder {
 	/// From the target goal perform a Line Of Sight pass in an expanding
 	/// wavefront to mark any `FieldCell` that can see the goal with the LOS
 	/// flag and mark any LOS corners that can be expanded in the integration
-	/// cost layer
+	/// cost layer. With [Connectivity::FourWay], or when the goal sector has
+	/// one-way movement, only the goal is marked as an actor cannot move
+	/// directly towards it. The same applies when the goal sector is seeded
+	/// with other goals as an actor may prefer one of them
 	pub fn calculate_los(&mut self) {
+		#[cfg(feature = "trace")]
+		let _span = bevy::log::info_span!(
+			"integration_los",
+			goal_sector = ?self.path.get().first().map(|(sector, _)| *sector)
+		)
+		.entered();
+		let connectivity = self.connectivity;
+		let has_one_way = self.get_one_way_field(0).is_some();
+		let is_seeded = !self.goal_seeds.is_empty();
 		let fields = self.get_mut_integration_fields();
 		if let Some((_sector, goals, field)) = fields.first_mut() {
 			field.set_initial_los(goals[0]);
-			field.calculate_sector_goal_los(goals, &goals[0]);
+			if is_seeded {
+				// the seeded goals are already corners of the wavefront
+				field.add_los_corner(goals[0]);
+			} else if connectivity == Connectivity::EightWay && !has_one_way {
+				field.calculate_sector_goal_los(goals, &goals[0]);
+			}
 		}
-		//TODO propagate LOS across sectors
-		//until then set LOS corners in other sectors as the goals (this is
-		// portal goals) for int calc layer
+		// set LOS corners in other sectors as the goals (this is portal goals)
+		// for int calc layer
 		for (_sector, goals, field) in fields.iter_mut() {
 			if field.los_corners.is_empty() {
 				for g in goals {
@@ -168,13 +474,155 @@ impl IntegrationBuilder {
 				}
 			}
 		}
+		if connectivity == Connectivity::EightWay
+			&& !has_one_way
+			&& !is_seeded
+			&& self.get_one_way_field(1).is_none()
+		{
+			self.calculate_approach_los();
+		}
+	}
+	/// Continue Line Of Sight from the goal through the portal window into
+	/// the sector the route enters the goal sector from. The [IntegrationField]
+	/// of that sector is left untouched as it's shared by every route using
+	/// the same portal, instead the cells which can see the goal are recorded
+	/// separately, see [IntegrationBuilder::get_approach_los]. A cell has
+	/// sight of the goal if a straight line to it crosses no impassable cell,
+	/// doesn't squeeze diagonally between two impassable cells and leaves the
+	/// sector through the portal window
+	fn calculate_approach_los(&mut self) {
+		self.approach_los.clear();
+		let (Some((goal_sector, goal_cells, goal_field)), Some((sector_id, window, field))) = (
+			self.integration_fields.first(),
+			self.integration_fields.get(1),
+		) else {
+			return;
+		};
+		let Some(goal) = goal_cells.first() else {
+			return;
+		};
+		let Some(ordinal) = Ordinal::sector_to_sector_direction(*sector_id, *goal_sector) else {
+			return;
+		};
+		// lay the two sectors side by side so that a single line can be drawn
+		// across them, `(column, row)` offsets of the goal sector and sector
+		let res = FIELD_RESOLUTION;
+		let (goal_offset, offset) = match ordinal {
+			Ordinal::North => ((0, res), (0, 0)),
+			Ordinal::East => ((0, 0), (res, 0)),
+			Ordinal::South => ((0, 0), (0, res)),
+			Ordinal::West => ((res, 0), (0, 0)),
+			_ => return,
+		};
+		let shift = |cell: &FieldCell, (column, row): (usize, usize)| {
+			FieldCell::new(cell.get_column() + column, cell.get_row() + row)
+		};
+		// find which sector a combined cell lies in and whether it's impassable
+		let lookup = |cell: &FieldCell| {
+			let local = |(column, row): (usize, usize)| {
+				let c = cell.get_column().checked_sub(column)?;
+				let r = cell.get_row().checked_sub(row)?;
+				(c < res && r < res).then(|| FieldCell::new(c, r))
+			};
+			if let Some(local) = local(offset) {
+				Some((false, local, field.get_field_cell_value(local)))
+			} else {
+				local(goal_offset)
+					.map(|local| (true, local, goal_field.get_field_cell_value(local)))
+			}
+		};
+		let is_impassable = |cell: &FieldCell| {
+			lookup(cell).is_none_or(|(_, _, value)| value & INT_BITS_IMPASSABLE != 0)
+		};
+		let goal = shift(goal, goal_offset);
+		for column in 0..res {
+			for row in 0..res {
+				let cell = FieldCell::new(column, row);
+				if field.get_field_cell_value(cell) & INT_BITS_IMPASSABLE != 0 {
+					continue;
+				}
+				let line = shift(&cell, offset).get_cells_between_points(&goal);
+				let mut exit = None;
+				let is_clear = line.iter().enumerate().all(|(i, step)| {
+					let Some((in_goal_sector, _, value)) = lookup(step) else {
+						return false;
+					};
+					if value & INT_BITS_IMPASSABLE != 0 {
+						return false;
+					}
+					if let Some(previous) = i.checked_sub(1).map(|j| line[j]) {
+						// the last cell before crossing into the goal sector
+						if in_goal_sector && exit.is_none() {
+							exit = lookup(&previous).map(|(_, local, _)| local);
+						}
+						// a diagonal step between two impassable cells is blocked
+						if previous.get_column() != step.get_column()
+							&& previous.get_row() != step.get_row()
+						{
+							let a = FieldCell::new(previous.get_column(), step.get_row());
+							let b = FieldCell::new(step.get_column(), previous.get_row());
+							if is_impassable(&a) && is_impassable(&b) {
+								return false;
+							}
+						}
+					}
+					true
+				});
+				if is_clear && exit.is_some_and(|exit| window.contains(&exit)) {
+					self.approach_los.push(cell);
+				}
+			}
+		}
+	}
+	/// Get the [FieldCell]s of the sector the route enters the goal sector
+	/// from which have Line Of Sight to the goal, an actor in one of them can
+	/// move directly to the goal rather than following the [FlowField]s. Empty
+	/// until the LOS pass has been performed or if the route doesn't leave the
+	/// goal sector
+	pub fn get_approach_los(&self) -> &Vec<FieldCell> {
+		&self.approach_los
 	}
 	/// From identified LOS corners calcualte the integrated cost of unmarked `FieldCell`
 	pub fn build_integrated_cost(&mut self, cost_fields: &SectorCostFields) {
-		for (sector_id, _goals, int_field) in self.get_mut_integration_fields() {
-			let cost_field = cost_fields.get_scaled().get(sector_id).unwrap();
-			//TODO explain using los corners
-			int_field.calculate_field(cost_field);
+		// with LOS resolved the sectors are independent of one another
+		let skipped: Vec<bool> = (0..self.integration_fields.len())
+			.map(|i| self.is_cost_pass_skipped(i))
+			.collect();
+		let one_way_fields = &self.one_way_fields;
+		let height_fields = &self.height_fields;
+		let slope_cost = self.slope_cost;
+		let slope = |i: usize| {
+			height_fields
+				.get(i)
+				.and_then(|field| field.as_ref())
+				.map(|heights| (heights, slope_cost))
+		};
+		#[cfg(use_rayon)]
+		self.integration_fields.par_iter_mut().enumerate().for_each(
+			|(i, (sector_id, _goals, int_field))| {
+				if skipped[i] {
+					return;
+				}
+				#[cfg(feature = "trace")]
+				let _span = bevy::log::info_span!("integrate_sector", sector = ?sector_id).entered();
+				if let Some(cost_field) = cost_fields.get_scaled().get(sector_id) {
+					let one_way = one_way_fields.get(i).and_then(|field| field.as_ref());
+					int_field.calculate_field_with_slope(cost_field, one_way, slope(i));
+				}
+			},
+		);
+		#[cfg(not(use_rayon))]
+		for (i, (sector_id, _goals, int_field)) in self.integration_fields.iter_mut().enumerate() {
+			if skipped[i] {
+				continue;
+			}
+			#[cfg(feature = "trace")]
+			let _span = bevy::log::info_span!("integrate_sector", sector = ?sector_id).entered();
+			if let Some(cost_field) = cost_fields.get_scaled().get(sector_id) {
+				//TODO explain using los corners
+				let one_way = one_way_fields.get(i).and_then(|field| field.as_ref());
+				int_field.calculate_field_with_slope(cost_field, one_way, slope(i));
+			}
 		}
 	}
 }
@@ -235,14 +683,15 @@ impl IntegrationField {
 	/// is set to `0`
 	pub fn new(goal: &FieldCell, cost: &CostField) -> Self {
 		let mut field = IntegrationField::default();
-		for (column, rows) in cost.get().iter().enumerate() {
-			for (row, value) in rows.iter().enumerate() {
-				if *value == u8::MAX {
-					field.set_field_cell_value(
-						65535 + INT_BITS_IMPASSABLE,
-						FieldCell::new(column, row),
-					);
-				}
+		let mask = cost.impassable_mask();
+		for column in 0..FIELD_RESOLUTION {
+			let mut impassable = mask.get_column(column);
+			// visit only the set bits of the column
+			while impassable != 0 {
+				let row = impassable.trailing_zeros() as usize;
+				field
+					.set_field_cell_value(65535 + INT_BITS_IMPASSABLE, FieldCell::new(column, row));
+				impassable &= impassable - 1;
 			}
 		}
 		field.set_field_cell_value(INT_BITS_GOAL, *goal);
@@ -256,6 +705,18 @@ impl IntegrationField {
 	pub fn add_los_corner(&mut self, corner: FieldCell) {
 		self.los_corners.push(corner);
 	}
+	/// Mark `goal` as a goal whose integrated cost starts at `initial_cost`
+	/// rather than `0` and seed the wavefront from it. Goals seeded with a
+	/// higher cost are less desirable, an actor only heads to one if it's
+	/// cheaper to reach once the extra cost is included
+	pub fn add_seeded_goal(&mut self, goal: FieldCell, initial_cost: u8) {
+		self.set_field_cell_value(INT_BITS_GOAL | initial_cost as u32, goal);
+		self.los_corners.push(goal);
+	}
+	/// Bytes allocated by the field beyond its own size
+	pub(crate) fn heap_size(&self) -> usize {
+		self.los_corners.capacity() * std::mem::size_of::<FieldCell>()
+	}
 	/// From the goal of the target sector calcualte LOS
 	pub fn calculate_sector_goal_los(&mut self, active_wavefront: &[FieldCell], goal: &FieldCell) {
 		let wavefront_cost = 1;
@@ -267,12 +728,81 @@ impl IntegrationField {
 	/// cells and calculate the integrated-cost field values from the
 	/// `cost_field`
 	pub fn calculate_field(&mut self, cost_field: &CostField) {
-		// further positions to process, tuple element 0 is the position, element 1 is the integration cost from the previous cell needed to help calculate element 0s cost
-		let mut queue: Vec<(FieldCell, u32)> = Vec::new();
-		for goal in self.los_corners.iter() {
-			queue.push(((*goal), self.get_field_cell_value(*goal)));
+		self.calculate_field_with_one_way(cost_field, None);
+	}
+	/// As with [IntegrationField::calculate_field] but the wavefront only
+	/// expands into a [FieldCell] if the `one_way` field of the sector
+	/// permits an actor to step from it back towards the wavefront
+	pub fn calculate_field_with_one_way(
+		&mut self,
+		cost_field: &CostField,
+		one_way: Option<&OneWayField>,
+	) {
+		self.calculate_field_with_slope(cost_field, one_way, None);
+	}
+	/// As with [IntegrationField::calculate_field_with_one_way] where the
+	/// cost of each step additionally depends on the difference in height
+	/// between the two [FieldCell]s as given by the `slope`
+	pub fn calculate_field_with_slope(
+		&mut self,
+		cost_field: &CostField,
+		one_way: Option<&OneWayField>,
+		slope: Option<(&HeightField, SlopeCost)>,
+	) {
+		process_neighbours(self, cost_field, one_way, slope);
+	}
+	/// Check whether the integrated costs of the field still hold after the
+	/// `changed_cells` of the `cost_field` have been given new values.
+	///
+	/// The integrated cost of a cell is the cheapest integrated cost of its
+	/// neighbours plus its own cost, if this still agrees with the recorded
+	/// cost of each changed cell (to within `tolerance`) then none of the
+	/// other cells are affected and the field doesn't need rebuilding.
+	/// Changes to passability or to the cells the field was seeded from
+	/// always invalidate the field
+	pub fn is_valid_after_change(
+		&self,
+		cost_field: &CostField,
+		changed_cells: &[FieldCell],
+		tolerance: u32,
+	) -> bool {
+		for cell in changed_cells.iter() {
+			let value = self.get_field_cell_value(*cell);
+			let cost = cost_field.get_field_cell_value(*cell);
+			if value & INT_BITS_IMPASSABLE == INT_BITS_IMPASSABLE || cost == u8::MAX {
+				if value & INT_BITS_IMPASSABLE == INT_BITS_IMPASSABLE && cost == u8::MAX {
+					continue;
+				}
+				return false;
+			}
+			// costs within line of sight are not used
+			if value & (INT_BITS_LOS | INT_BITS_GOAL) != 0 {
+				continue;
+			}
+			if self.los_corners.contains(cell) {
+				return false;
+			}
+			let mut cheapest_neighbour = u16::MAX as u32;
+			for n in Ordinal::get_orthogonal_cell_neighbours(*cell) {
+				let n_int = self.get_field_cell_value(n);
+				let is_source = self.los_corners.contains(&n)
+					|| (n_int & INT_BITS_IMPASSABLE != INT_BITS_IMPASSABLE
+						&& n_int & INT_BITS_LOS != INT_BITS_LOS);
+				if is_source {
+					cheapest_neighbour = cheapest_neighbour.min(n_int & INT_FILTER_BITS_COST);
+				}
+			}
+			// unreached areas remain unreached
+			let expected = if cheapest_neighbour == u16::MAX as u32 {
+				u16::MAX as u32
+			} else {
+				cheapest_neighbour + cost as u32
+			};
+			if expected.abs_diff(value & INT_FILTER_BITS_COST) > tolerance {
+				return false;
+			}
 		}
-		process_neighbours(self, queue, cost_field);
+		true
 	}
 }
 //TODO how woudl portals work with a goal
@@ -285,7 +815,7 @@ fn propagate_los(
 	mut wavefront_cost: u32,
 	goal: &FieldCell,
 ) {
-	let mut moved_wavefront: Vec<FieldCell> = Vec::new();
+	let mut moved_wavefront = buffer_pool::take_cell_buffer();
 	for wavefront in active_wavefront.iter() {
 		let neighbours = Ordinal::get_orthogonal_cell_neighbours(*wavefront);
 		for n in neighbours.iter() {
@@ -356,6 +886,7 @@ fn propagate_los(
 	if !moved_wavefront.is_empty() {
 		propagate_los(field, &moved_wavefront, wavefront_cost, goal);
 	}
+	buffer_pool::recycle_cell_buffer(moved_wavefront);
 }
 /// From a Line Of Sight corner extrapolate a line from the goal to the corner
 /// and through to the sector boundary. For any FieldCell between the corner
@@ -588,35 +1119,155 @@ fn check_los_corner_propagation(adj: &FieldCell, goal: &FieldCell) -> FieldCell
 	}
 }
 
-/// Recursively expand the neighbours of a list of [FieldCell] and calculate
-/// their value in the [IntegrationField]
+/// Expand the neighbours of the LOS corners of the [IntegrationField] and
+/// calculate their value in the field.
+///
+/// Cells are processed in order of their integrated cost using a bucketed
+/// priority queue (Dial's algorithm) so that each cell is settled once with
+/// its true minimum cost, rather than being revisited each time a cheaper
+/// wavefront reaches it. The buckets are borrowed from the [buffer_pool] of
+/// the thread. The ring of buckets can only order costs within
+/// [INT_COST_BUCKETS] of each other, if the corners have been
+/// seeded with costs spread further apart a binary heap is used instead
 fn process_neighbours(
 	int_field: &mut IntegrationField,
-	queue: Vec<(FieldCell, u32)>,
 	cost_field: &CostField,
+	one_way: Option<&OneWayField>,
+	slope: Option<(&HeightField, SlopeCost)>,
 ) {
-	let mut next_neighbours = Vec::new();
-	// iterate over the queue calculating neighbour int costs
-	for (cell, prev_int_cost) in queue.iter() {
-		let neighbours = Ordinal::get_orthogonal_cell_neighbours(*cell);
-		// iterate over the neighbours calculating int costs
-		for n in neighbours.iter() {
-			// ensure neighbour isn't impassable
-			let n_int = int_field.get_field_cell_value(*n);
-			if n_int & INT_BITS_IMPASSABLE != INT_BITS_IMPASSABLE
-				&& n_int & INT_BITS_LOS != INT_BITS_LOS
-			{
-				let cell_cost = cost_field.get_field_cell_value(*n) as u32;
-				let int_cost = cell_cost + (prev_int_cost & INT_FILTER_BITS_COST);
-				if int_cost < (n_int & INT_FILTER_BITS_COST) {
-					int_field.set_field_cell_value(int_cost, *n);
-					next_neighbours.push((*n, int_cost));
-				}
+	let seed_costs = int_field
+		.los_corners
+		.iter()
+		.map(|corner| int_field.get_field_cell_value(*corner) & INT_FILTER_BITS_COST);
+	let spread = seed_costs
+		.clone()
+		.max()
+		.zip(seed_costs.min())
+		.map_or(0, |(max, min)| max - min);
+	if spread >= INT_COST_BUCKETS as u32 {
+		process_neighbours_with_heap(int_field, cost_field, one_way, slope);
+	} else {
+		buffer_pool::with_cost_buckets(|buckets| {
+			process_neighbours_with_buckets(int_field, cost_field, one_way, slope, buckets)
+		});
+	}
+}
+
+/// Perform [process_neighbours] with a set of empty `buckets`
+fn process_neighbours_with_buckets(
+	int_field: &mut IntegrationField,
+	cost_field: &CostField,
+	one_way: Option<&OneWayField>,
+	slope: Option<(&HeightField, SlopeCost)>,
+	buckets: &mut [Vec<FieldCell>],
+) {
+	let mut pending = 0;
+	let mut current_cost = u32::MAX;
+	// the wavefront starts from the corners with the integration cost they've been seeded with
+	for corner in int_field.los_corners.iter() {
+		let cost = int_field.get_field_cell_value(*corner) & INT_FILTER_BITS_COST;
+		buckets[cost as usize % INT_COST_BUCKETS].push(*corner);
+		pending += 1;
+		current_cost = current_cost.min(cost);
+	}
+	let mut bucket = buffer_pool::take_cell_buffer();
+	while pending > 0 {
+		std::mem::swap(
+			&mut bucket,
+			&mut buckets[current_cost as usize % INT_COST_BUCKETS],
+		);
+		for cell in bucket.iter() {
+			pending -= 1;
+			// skip stale entries, the cell has since been settled with a cheaper cost
+			if int_field.get_field_cell_value(*cell) & INT_FILTER_BITS_COST != current_cost {
+				continue;
 			}
+			relax_neighbours(
+				int_field,
+				cost_field,
+				one_way,
+				slope,
+				*cell,
+				current_cost,
+				|int_cost, n| {
+					buckets[int_cost as usize % INT_COST_BUCKETS].push(n);
+					pending += 1;
+				},
+			);
+		}
+		bucket.clear();
+		current_cost += 1;
+	}
+	buffer_pool::recycle_cell_buffer(bucket);
+}
+
+/// Perform [process_neighbours] with a binary heap, used when the costs of
+/// the corners are too far apart to be ordered by the ring of buckets
+fn process_neighbours_with_heap(
+	int_field: &mut IntegrationField,
+	cost_field: &CostField,
+	one_way: Option<&OneWayField>,
+	slope: Option<(&HeightField, SlopeCost)>,
+) {
+	let mut queue = std::collections::BinaryHeap::new();
+	for corner in int_field.los_corners.iter() {
+		let cost = int_field.get_field_cell_value(*corner) & INT_FILTER_BITS_COST;
+		queue.push(std::cmp::Reverse((cost, *corner)));
+	}
+	while let Some(std::cmp::Reverse((cost, cell))) = queue.pop() {
+		// skip stale entries, the cell has since been settled with a cheaper cost
+		if int_field.get_field_cell_value(cell) & INT_FILTER_BITS_COST != cost {
+			continue;
 		}
+		relax_neighbours(
+			int_field,
+			cost_field,
+			one_way,
+			slope,
+			cell,
+			cost,
+			|int_cost, n| queue.push(std::cmp::Reverse((int_cost, n))),
+		);
 	}
-	if !next_neighbours.is_empty() {
-		process_neighbours(int_field, next_neighbours, cost_field);
+}
+
+/// Lower the integrated cost of each neighbour of the settled `cell` of
+/// `cost` which can be reached more cheaply through it, every neighbour
+/// lowered is handed to `push` along with its new cost
+fn relax_neighbours(
+	int_field: &mut IntegrationField,
+	cost_field: &CostField,
+	one_way: Option<&OneWayField>,
+	slope: Option<(&HeightField, SlopeCost)>,
+	cell: FieldCell,
+	cost: u32,
+	mut push: impl FnMut(u32, FieldCell),
+) {
+	let neighbours = Ordinal::get_orthogonal_cell_neighbours(cell);
+	// iterate over the neighbours calculating int costs
+	for n in neighbours.iter() {
+		// ensure neighbour isn't impassable
+		let n_int = int_field.get_field_cell_value(*n);
+		// an actor in `n` would step towards `cell`
+		let direction = Ordinal::cell_to_cell_direction(cell, *n);
+		if n_int & INT_BITS_IMPASSABLE != INT_BITS_IMPASSABLE
+			&& n_int & INT_BITS_LOS != INT_BITS_LOS
+			&& is_move_allowed(one_way, *n, direction)
+		{
+			let mut cell_cost = cost_field.get_field_cell_value(*n) as u32;
+			if let Some((heights, slope_cost)) = slope {
+				let from = heights.get_field_cell_value(*n);
+				let to = heights.get_field_cell_value(cell);
+				// a single step must fit within the bucket queue
+				cell_cost =
+					(cell_cost + slope_cost.step_cost(from, to)).min(INT_COST_BUCKETS as u32 - 1);
+			}
+			let int_cost = cell_cost + cost;
+			if int_cost < (n_int & INT_FILTER_BITS_COST) {
+				int_field.set_field_cell_value(int_cost, *n);
+				push(int_cost, *n);
+			}
+		}
 	}
 }
 
@@ -625,6 +1276,116 @@ fn process_neighbours(
 mod tests {
 	use super::*;
 
+	/// Integrate a field from a goal in the middle of the sector
+	fn field_from_goal(cost_field: &CostField) -> IntegrationField {
+		let goal = FieldCell::new(5, 5);
+		let mut field = IntegrationField::new(&goal, cost_field);
+		field.add_los_corner(goal);
+		field.calculate_field(cost_field);
+		field
+	}
+	#[test]
+	fn goal_seeds() {
+		let seeds = GoalSeeds::new(&[
+			(FieldCell::new(5, 5), 9),
+			(FieldCell::new(1, 2), 3),
+			(FieldCell::new(5, 5), 4),
+		]);
+		// sorted by cell with the cheapest cost of a repeated goal
+		let expected = vec![(FieldCell::new(1, 2), 3), (FieldCell::new(5, 5), 4)];
+		assert_eq!(expected, seeds.iter().collect::<Vec<_>>());
+		let reordered = GoalSeeds::new(&[(FieldCell::new(5, 5), 4), (FieldCell::new(1, 2), 3)]);
+		assert_eq!(seeds, reordered);
+		let many: Vec<(FieldCell, u8)> = (0..10).map(|i| (FieldCell::new(i, 0), 0)).collect();
+		assert_eq!(MAX_GOAL_SEEDS, GoalSeeds::new(&many).len());
+	}
+	#[test]
+	fn memory_usage_skips_absent_fields() {
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let route = Route::new(vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 4)),
+			(SectorID::new(1, 0), FieldCell::new(4, 4)),
+		]);
+		let usage_of = |cost_fields: &SectorCostFields, sector_id| {
+			let mut usage = MemoryUsage::new();
+			IntegrationBuilder::new(route.clone(), cost_fields).record_memory_usage(&mut usage);
+			usage.get_bytes(sector_id, MemoryCategory::QueuedRoutes)
+		};
+		let flat = usage_of(&cost_fields, SectorID::new(1, 0));
+		cost_fields.set_one_way(
+			SectorID::new(1, 0),
+			FieldCell::new(4, 4),
+			Ordinal::North,
+			&map_dimensions,
+		);
+		// only the sector with one-way movement pays for its field
+		assert_eq!(
+			flat + std::mem::size_of::<OneWayField>(),
+			usage_of(&cost_fields, SectorID::new(1, 0))
+		);
+		assert_eq!(
+			usage_of(&cost_fields, SectorID::new(0, 0)),
+			usage_of(&SectorCostFields::new(&map_dimensions), SectorID::new(0, 0))
+		);
+	}
+	#[test]
+	fn valid_after_change_within_tolerance() {
+		let mut cost_field = CostField::default();
+		let field = field_from_goal(&cost_field);
+		let changed = FieldCell::new(0, 0);
+		cost_field.set_field_cell_value(2, changed);
+		assert!(!field.is_valid_after_change(&cost_field, &[changed], 0));
+		assert!(field.is_valid_after_change(&cost_field, &[changed], 1));
+	}
+	#[test]
+	fn valid_after_change_unchanged_cost() {
+		let cost_field = CostField::default();
+		let field = field_from_goal(&cost_field);
+		assert!(field.is_valid_after_change(&cost_field, &[FieldCell::new(2, 7)], 0));
+	}
+	#[test]
+	fn seeds_spread_beyond_buckets() {
+		let mut cost_field = CostField::default();
+		// a wall down the middle keeps each seed to its own half
+		for row in 0..FIELD_RESOLUTION {
+			cost_field.set_field_cell_value(255, FieldCell::new(5, row));
+		}
+		let left = FieldCell::new(0, 0);
+		let right = FieldCell::new(9, 9);
+		let mut field = IntegrationField::new(&left, &cost_field);
+		field.add_los_corner(left);
+		field.set_field_cell_value(300, right);
+		field.add_los_corner(right);
+		field.calculate_field(&cost_field);
+		let cost = |cell| field.get_field_cell_value(cell) & INT_FILTER_BITS_COST;
+		assert_eq!(2, cost(FieldCell::new(1, 1)));
+		// the far seed isn't mistaken for a stale entry of a wrapped bucket
+		assert_eq!(302, cost(FieldCell::new(8, 8)));
+	}
+	#[test]
+	fn slope_prefers_downhill() {
+		let cost_field = CostField::default();
+		// the sector rises towards the south
+		let heights = HeightField::from_fn(|cell| cell.get_row() as u8);
+		let goal = FieldCell::new(5, 5);
+		let mut field = IntegrationField::new(&goal, &cost_field);
+		field.add_los_corner(goal);
+		field.calculate_field_with_slope(&cost_field, None, Some((&heights, SlopeCost::new(5, 0))));
+		let cost = |cell| field.get_field_cell_value(cell) & INT_FILTER_BITS_COST;
+		// climbing up from the north costs 5 extra per row, walking down from the south costs nothing extra
+		assert_eq!(30, cost(FieldCell::new(5, 0)));
+		assert_eq!(4, cost(FieldCell::new(5, 9)));
+	}
+	#[test]
+	fn invalid_after_change_impassable() {
+		let mut cost_field = CostField::default();
+		let field = field_from_goal(&cost_field);
+		let changed = FieldCell::new(0, 0);
+		cost_field.set_field_cell_value(255, changed);
+		assert!(!field.is_valid_after_change(&cost_field, &[changed], 100));
+	}
+
 	#[test]
 	fn hori_los_prop_max() {
 		let goal = FieldCell::new(4, 3);
@@ -729,6 +1490,24 @@ mod tests {
 		];
 		assert_eq!(actual, result);
 	}
+	/// Calculate integration field where a band of expensive cells forces the cheapest cost to flow around it
+	#[test]
+	fn varied_cost_field() {
+		let mut cost_field = CostField::default();
+		for row in 0..FIELD_RESOLUTION - 1 {
+			cost_field.set_field_cell_value(50, FieldCell::new(1, row));
+		}
+		let goal = FieldCell::new(0, 0);
+		let mut integration_field = IntegrationField::new(&goal, &cost_field);
+		integration_field.add_los_corner(goal);
+		integration_field.calculate_field(&cost_field);
+		// walking through the band
+		let result = integration_field.get_field_cell_value(FieldCell::new(1, 0)) & INT_FILTER_BITS_COST;
+		assert_eq!(50, result);
+		// walking around the band is cheaper than stepping through it
+		let result = integration_field.get_field_cell_value(FieldCell::new(2, 0)) & INT_FILTER_BITS_COST;
+		assert_eq!(20, result);
+	}
 	// /// Calculate integration field from a custom cost field set
 	// #[test]
 	// fn complex_field() {
@@ -0,0 +1,31 @@
+//! A MetaField contains a 2D array of 8-bit values which are free for a game
+//! to use, such as tagging the territory owner or zone ID of each field
+//! cell. The values sit alongside the [CostField] of a sector and are
+//! serialised with it but they have no influence on pathfinding.
+//!
+
+use crate::prelude::*;
+use bevy::reflect::Reflect;
+
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Default, Reflect)]
+pub struct MetaField([[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION]);
+
+impl Field<u8> for MetaField {
+	/// Get a reference to the field array
+	fn get(&self) -> &[[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION] {
+		&self.0
+	}
+	/// Retrieve a field cell value
+	///
+	/// NB: This will panic if out of bounds
+	fn get_field_cell_value(&self, field_cell: FieldCell) -> u8 {
+		self.0[field_cell.get_column()][field_cell.get_row()]
+	}
+	/// Set a field cell to a value
+	///
+	/// NB: This will panic if out of bounds
+	fn set_field_cell_value(&mut self, value: u8, field_cell: FieldCell) {
+		self.0[field_cell.get_column()][field_cell.get_row()] = value;
+	}
+}
@@ -1,15 +1,27 @@
 //! The kinds of fields used by the algorithm
 //!
 
+mod buffer_pool;
 pub mod cost_field;
 pub mod flow_field;
+pub mod flow_flags;
+pub mod height_field;
 pub mod integration_field;
+#[cfg(feature = "metafield")]
+pub mod meta_field;
+pub mod one_way_field;
+pub mod route_spline;
+mod route_tree;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
 
 use crate::prelude::*;
 use bevy::prelude::*;
 use bevy::utils::Duration;
+#[cfg(use_rayon)]
+use rayon::prelude::*;
+use route_tree::{RouteNodeId, RouteTree};
 
 /// Defines required access to field arrays
 pub trait Field<T> {
@@ -49,6 +61,38 @@ impl FieldCell {
 	pub fn get_row(&self) -> usize {
 		self.0 .1
 	}
+	/// Get the neighbouring [FieldCell] in the direction of `ordinal`.
+	/// Returns [None] if the neighbour would be outside of the field
+	pub fn neighbour(&self, ordinal: Ordinal) -> Option<FieldCell> {
+		Ordinal::get_cell_neighbour(*self, ordinal)
+	}
+	/// Get the [FieldCell] found by stepping `n` cells in the direction of
+	/// `ordinal`. Returns [None] if the cell would be outside of the field
+	pub fn offset(&self, ordinal: Ordinal, n: usize) -> Option<FieldCell> {
+		let (column_step, row_step): (isize, isize) = match ordinal {
+			Ordinal::North => (0, -1),
+			Ordinal::East => (1, 0),
+			Ordinal::South => (0, 1),
+			Ordinal::West => (-1, 0),
+			Ordinal::NorthEast => (1, -1),
+			Ordinal::SouthEast => (1, 1),
+			Ordinal::SouthWest => (-1, 1),
+			Ordinal::NorthWest => (-1, -1),
+			Ordinal::Zero => (0, 0),
+		};
+		let n = isize::try_from(n).ok()?;
+		let column = self
+			.get_column()
+			.checked_add_signed(column_step.checked_mul(n)?)?;
+		let row = self
+			.get_row()
+			.checked_add_signed(row_step.checked_mul(n)?)?;
+		if column < FIELD_RESOLUTION && row < FIELD_RESOLUTION {
+			Some(FieldCell::new(column, row))
+		} else {
+			None
+		}
+	}
 	/// From the position of a `cell_id`, if it sits along a boundary, return the [Ordinal] of that boundary. Note that if the `cell_id` is in a field corner then it'll have two boundaries. Note that if the `cell_id` is not in fact along a boundary then this will panic
 	pub fn get_boundary_ordinal_from_field_cell(&self) -> Vec<Ordinal> {
 		let mut boundaries = Vec::new();
@@ -178,6 +222,143 @@ fn walk_bresenham_steep(col_0: i32, row_0: i32, col_1: i32, row_1: i32) -> Vec<F
 	cells
 }
 
+/// Policy of how a path request should behave when its goal is impassable or
+/// cannot be reached from the source
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum GoalFallback {
+	/// Only ever route to the requested goal
+	#[default]
+	Exact,
+	/// Route to the closest [FieldCell] to the goal which is not impassable
+	NearestPathable,
+	/// Route to the closest [FieldCell] to the goal which can be reached from
+	/// the source
+	NearestReachable,
+}
+
+/// How strongly each frame moves the average throughput of a
+/// [FlowFieldCache] towards the number of routes completed in it
+const THROUGHPUT_SMOOTHING: f32 = 0.2;
+
+/// How long a queued path request waits before it is promoted by one
+/// [PathPriority] level
+pub const PRIORITY_AGING_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Priority of a path request, higher priority requests are built first.
+/// Requests waiting in a queue are gradually promoted (see
+/// [PRIORITY_AGING_INTERVAL]) so that low priority requests are never starved
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum PathPriority {
+	/// Exploratory or speculative requests
+	Background,
+	/// Standard requests
+	#[default]
+	Normal,
+	/// Requests which must be serviced as quickly as possible, such as a
+	/// player commanding their units
+	PlayerCritical,
+}
+
+impl PathPriority {
+	/// Get the rank of the priority after it has been promoted based on how
+	/// long it has `waited` in a queue
+	pub fn get_aged_rank(&self, waited: Duration) -> u128 {
+		let base = match self {
+			PathPriority::Background => 0,
+			PathPriority::Normal => 1,
+			PathPriority::PlayerCritical => 2,
+		};
+		base + waited.as_millis() / PRIORITY_AGING_INTERVAL.as_millis()
+	}
+}
+
+/// How quickly a class of actor moves, in world units per second across
+/// [FieldCell]s of cost `1`. Costs are a measure of distance weighted by
+/// terrain so the speed turns them into times, such as ETAs or budgets
+/// expressed in seconds, rather than treating a cost of `1` as one second.
+/// It can be attached to actors so each class (infantry, cavalry, ...)
+/// carries its own
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct TraversalSpeed(f32);
+
+impl TraversalSpeed {
+	/// Create a new [TraversalSpeed] of `units_per_second`, speeds which
+	/// aren't positive are clamped to the smallest positive speed
+	pub fn new(units_per_second: f32) -> Self {
+		TraversalSpeed(units_per_second.max(f32::EPSILON))
+	}
+	/// Get the speed in world units per second
+	pub fn get_units_per_second(&self) -> f32 {
+		self.0
+	}
+	/// Get how long it takes to cover a straight line `distance` in world
+	/// units at this speed
+	pub fn distance_to_time(&self, distance: f32) -> Duration {
+		Duration::from_secs_f32(distance.max(0.0) / self.0)
+	}
+	/// Get how long it takes to travel an integrated `cost`, each unit of
+	/// cost being one [FieldCell] of cost `1` across
+	pub fn cost_to_time(&self, cost: i32, map_dimensions: &MapDimensions) -> Duration {
+		self.distance_to_time(cost as f32 * map_dimensions.get_field_cell_unit_size())
+	}
+	/// Get the most an actor can spend travelling within `time`, such as for
+	/// a budget expressed in seconds, see [TraversalSpeed::cost_to_time]
+	pub fn time_to_cost(&self, time: Duration, map_dimensions: &MapDimensions) -> i32 {
+		(time.as_secs_f32() * self.0 / map_dimensions.get_field_cell_unit_size()).floor() as i32
+	}
+}
+
+/// When an entry of a cache or queue was generated, either the time elapsed
+/// since app start (less any time paused) or the turn of a turn-based game
+/// (see [FlowFieldTurn]). Stamps of different kinds can't be compared as
+/// times so entries stamped with a turn never expire nor age
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Stamp {
+	/// Time elapsed since app start
+	Time(Duration),
+	/// The turn of a turn-based game
+	Turn(u64),
+}
+
+impl Default for Stamp {
+	fn default() -> Self {
+		Stamp::Time(Duration::ZERO)
+	}
+}
+
+impl From<Duration> for Stamp {
+	fn from(elapsed: Duration) -> Self {
+		Stamp::Time(elapsed)
+	}
+}
+
+impl Stamp {
+	/// Get the time elapsed if the stamp is a [Stamp::Time]
+	pub fn get_time(&self) -> Option<Duration> {
+		match self {
+			Stamp::Time(elapsed) => Some(*elapsed),
+			Stamp::Turn(_) => None,
+		}
+	}
+	/// Get the turn if the stamp is a [Stamp::Turn]
+	pub fn get_turn(&self) -> Option<u64> {
+		match self {
+			Stamp::Time(_) => None,
+			Stamp::Turn(turn) => Some(*turn),
+		}
+	}
+	/// Get the time which has passed since an `earlier` stamp, [None] unless
+	/// both stamps are a [Stamp::Time] as turns have no duration
+	pub fn time_since(&self, earlier: Stamp) -> Option<Duration> {
+		Some(self.get_time()?.saturating_sub(earlier.get_time()?))
+	}
+}
+
 /// Describes the properties of a route
 #[derive(Clone, Copy, Debug, Reflect)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
@@ -191,8 +372,42 @@ pub struct RouteMetadata {
 	/// Field cell of the goal in the target sector
 	target_goal: FieldCell,
 	//? If a game is running for 136 years bad things will start happening here
-	/// Marks the route based on time elapsed since app start, used to enable automatic cleardown of long lived routes that are probably not needed anymore
-	time_generated: Duration,
+	/// Marks the route based on time elapsed since app start (or the turn when playing turn-based), used to enable automatic cleardown of long lived routes that are probably not needed anymore
+	time_generated: Stamp,
+	/// How the goal is chosen if the requested one cannot be used
+	goal_fallback: GoalFallback,
+	/// The sector and field cell the route actually leads to, this differs
+	/// from the target when a [GoalFallback] selected an alternative goal
+	resolved_target: (SectorID, FieldCell),
+	/// How urgently the route should be built
+	priority: PathPriority,
+	/// The world position of the goal and the distance from it at which an
+	/// actor stops, used by ranged actors which don't need to reach the goal
+	stopping: Option<(Vec3, f32)>,
+	/// The most the route may cost, routes which are more expensive are
+	/// rejected rather than built
+	#[cfg_attr(feature = "serde", serde(default))]
+	max_cost: Option<i32>,
+	/// The player the route was searched for, limiting it to the sectors
+	/// they have explored
+	#[cfg_attr(feature = "serde", serde(default))]
+	player: Option<PlayerId>,
+	/// The faction the route was searched for, which may not enter the
+	/// enabled zones of its [FactionZones]. Routes of different factions are
+	/// cached separately
+	#[cfg_attr(feature = "serde", serde(default))]
+	faction: Option<FactionId>,
+	/// The shared goal the route leads to, see [SharedGoals]
+	#[cfg_attr(feature = "serde", serde(default))]
+	shared_goal: Option<GoalId>,
+	/// The [EventPathRequest] the route was built for, it isn't compared so
+	/// identical requests share the same cached route
+	#[cfg_attr(feature = "serde", serde(default))]
+	request_id: Option<RequestId>,
+	/// Additional goals of the goal sector and the costs they start at.
+	/// Routes with different seeds are cached separately
+	#[cfg_attr(feature = "serde", serde(default))]
+	goal_seeds: GoalSeeds,
 }
 // we don't want to compare `time_generated` so manually impl PartialEq
 impl PartialEq for RouteMetadata {
@@ -201,6 +416,9 @@ impl PartialEq for RouteMetadata {
 			&& self.source_field == other.source_field
 			&& self.target_sector == other.target_sector
 			&& self.target_goal == other.target_goal
+			&& self.player == other.player
+			&& self.faction == other.faction
+			&& self.goal_seeds == other.goal_seeds
 	}
 }
 impl Eq for RouteMetadata {}
@@ -212,12 +430,18 @@ impl Ord for RouteMetadata {
 			self.source_field,
 			self.target_sector,
 			self.target_goal,
+			self.player,
+			self.faction,
+			self.goal_seeds,
 		)
 			.cmp(&(
 				other.source_sector,
 				other.source_field,
 				other.target_sector,
 				other.target_goal,
+				other.player,
+				other.faction,
+				other.goal_seeds,
 			))
 	}
 }
@@ -235,16 +459,105 @@ impl RouteMetadata {
 		source_field: FieldCell,
 		target_sector: SectorID,
 		target_goal: FieldCell,
-		time_generated: Duration,
+		time_generated: impl Into<Stamp>,
 	) -> Self {
 		RouteMetadata {
 			source_sector,
 			source_field,
 			target_sector,
 			target_goal,
-			time_generated,
+			time_generated: time_generated.into(),
+			goal_fallback: GoalFallback::default(),
+			resolved_target: (target_sector, target_goal),
+			priority: PathPriority::default(),
+			stopping: None,
+			max_cost: None,
+			player: None,
+			faction: None,
+			shared_goal: None,
+			request_id: None,
+			goal_seeds: GoalSeeds::default(),
 		}
 	}
+	/// Set the [PathPriority] of the route
+	pub fn with_priority(mut self, priority: PathPriority) -> Self {
+		self.priority = priority;
+		self
+	}
+	/// Set the [GoalFallback] policy of the route
+	pub fn with_goal_fallback(mut self, goal_fallback: GoalFallback) -> Self {
+		self.goal_fallback = goal_fallback;
+		self
+	}
+	/// An actor on the route stops once it is within `distance` of the world
+	/// `goal_position`, 2d actors can extend a `Vec2` with a `z` of `0.0`.
+	/// [FlowField]s are not built for sectors which lie entirely within the
+	/// stopping distance of the goal as the actor never needs to enter them
+	pub fn with_stopping_distance(mut self, goal_position: Vec3, distance: f32) -> Self {
+		self.stopping = Some((goal_position, distance));
+		self
+	}
+	/// Only build the route if its cost is no more than `max_cost`, see
+	/// [PortalGraph::find_best_path_within_budget]
+	pub fn with_max_cost(mut self, max_cost: i32) -> Self {
+		self.max_cost = Some(max_cost);
+		self
+	}
+	/// Get the most the route may cost if a budget has been set
+	pub fn get_max_cost(&self) -> Option<i32> {
+		self.max_cost
+	}
+	/// Search the route on behalf of a `player`, see [SectorVisibility]
+	pub fn with_player(mut self, player: PlayerId) -> Self {
+		self.player = Some(player);
+		self
+	}
+	/// Get the player the route was searched for
+	pub fn get_player(&self) -> Option<PlayerId> {
+		self.player
+	}
+	/// Search the route on behalf of a `faction`, see [FactionZones]
+	pub fn with_faction(mut self, faction: FactionId) -> Self {
+		self.faction = Some(faction);
+		self
+	}
+	/// Get the faction the route was searched for
+	pub fn get_faction(&self) -> Option<FactionId> {
+		self.faction
+	}
+	/// Mark the route as leading to a shared goal, when the goal moves the
+	/// route is invalidated and requested again, see [SharedGoals]
+	pub fn with_shared_goal(mut self, goal_id: GoalId) -> Self {
+		self.shared_goal = Some(goal_id);
+		self
+	}
+	/// Get the shared goal the route leads to
+	pub fn get_shared_goal(&self) -> Option<GoalId> {
+		self.shared_goal
+	}
+	/// Tag the route with the [EventPathRequest] it's built for
+	pub fn with_request_id(mut self, request_id: RequestId) -> Self {
+		self.request_id = Some(request_id);
+		self
+	}
+	/// Get the ID of the [EventPathRequest] the route was built for
+	pub fn get_request_id(&self) -> Option<RequestId> {
+		self.request_id
+	}
+	/// Seed the goal sector of the route with additional goals, each with
+	/// the integrated cost it starts at, so that actors head to whichever
+	/// goal is cheapest once its initial cost is included. The route itself
+	/// still leads to the target, the seeds only shape the [FlowField] of
+	/// the sector it ends in. Seeded routes don't have Line Of Sight beyond
+	/// the target, see [IntegrationBuilder::calculate_los]
+	pub fn with_goal_seeds(mut self, goal_seeds: GoalSeeds) -> Self {
+		self.goal_seeds = goal_seeds;
+		self
+	}
+	/// Get the additional goals the goal sector of the route is seeded with
+	pub fn get_goal_seeds(&self) -> GoalSeeds {
+		self.goal_seeds
+	}
 	/// Get the source sector
 	pub fn get_source_sector(&self) -> SectorID {
 		self.source_sector
@@ -262,9 +575,92 @@ impl RouteMetadata {
 		self.target_goal
 	}
 	/// Get when the route was generated
-	pub fn get_time_generated(&self) -> Duration {
+	pub fn get_time_generated(&self) -> Stamp {
 		self.time_generated
 	}
+	/// Get the [GoalFallback] policy of the route
+	pub fn get_goal_fallback(&self) -> GoalFallback {
+		self.goal_fallback
+	}
+	/// Get the sector and field cell the route leads to. This is the target
+	/// unless a [GoalFallback] chose an alternative goal
+	pub fn get_resolved_target(&self) -> (SectorID, FieldCell) {
+		self.resolved_target
+	}
+	/// Record the sector and field cell the route leads to
+	pub fn set_resolved_target(&mut self, sector_id: SectorID, goal: FieldCell) {
+		self.resolved_target = (sector_id, goal);
+	}
+	/// Get the [PathPriority] of the route
+	pub fn get_priority(&self) -> PathPriority {
+		self.priority
+	}
+	/// Get the rank of the route within a queue after its [PathPriority] has
+	/// been aged based on the `elapsed` time, see [PathPriority::get_aged_rank].
+	/// Routes stamped with a [Stamp::Turn] don't age
+	pub fn get_aged_rank(&self, elapsed: impl Into<Stamp>) -> u128 {
+		let waited = elapsed.into().time_since(self.time_generated);
+		self.priority.get_aged_rank(waited.unwrap_or_default())
+	}
+	/// Get the world position of the goal if a stopping distance has been set
+	pub fn get_goal_position(&self) -> Option<Vec3> {
+		self.stopping.map(|(goal_position, _)| goal_position)
+	}
+	/// Get the distance from the goal at which an actor stops if one has
+	/// been set
+	pub fn get_stopping_distance(&self) -> Option<f32> {
+		self.stopping.map(|(_, distance)| distance)
+	}
+	/// Whether the world `position` of an actor is close enough to the goal
+	/// to stop. Always false if no stopping distance has been set
+	pub fn is_within_stopping_distance(&self, position: Vec3) -> bool {
+		self.stopping
+			.is_some_and(|(goal_position, distance)| goal_position.distance(position) <= distance)
+	}
+	/// Get the shortest time it could take an actor at `position` moving at
+	/// `speed` to come within the stopping distance of the goal, measured in
+	/// a straight line so obstacles may make it take longer. [None] if no
+	/// stopping distance has been set
+	pub fn get_time_to_stopping_distance(
+		&self,
+		position: Vec3,
+		speed: TraversalSpeed,
+	) -> Option<Duration> {
+		self.stopping.map(|(goal_position, distance)| {
+			speed.distance_to_time(goal_position.distance(position) - distance)
+		})
+	}
+	/// Whether every part of a sector lies within the stopping distance of
+	/// the goal, an actor stops as soon as it enters such a sector so no
+	/// [FlowField] is needed for it. The distance is measured from the centre
+	/// of the resolved goal [FieldCell] so it applies to both 2d and 3d
+	/// worlds. Always false if no stopping distance has been set
+	pub fn is_sector_within_stopping_distance(
+		&self,
+		sector_id: SectorID,
+		map_dimensions: &MapDimensions,
+	) -> bool {
+		let Some((_, distance)) = self.stopping else {
+			return false;
+		};
+		let cell_size = map_dimensions.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+		let (goal_sector, goal_cell) = self.resolved_target;
+		let (goal_column, goal_row) = map_dimensions.global_cell_from(goal_sector, goal_cell);
+		let goal = Vec2::new(goal_column as f32 + 0.5, goal_row as f32 + 0.5) * cell_size;
+		// the sector is enclosed if its furthest corner is
+		let sector_size = map_dimensions.get_sector_resolution() as f32;
+		let top_left =
+			Vec2::new(sector_id.get_column() as f32, sector_id.get_row() as f32) * sector_size;
+		let furthest = Vec2::new(
+			(goal.x - top_left.x)
+				.abs()
+				.max((goal.x - top_left.x - sector_size).abs()),
+			(goal.y - top_left.y)
+				.abs()
+				.max((goal.y - top_left.y - sector_size).abs()),
+		);
+		furthest.length() <= distance
+	}
 }
 
 /// List of sector-portal (or just the end goal) route describing the sector path an actor should take to move to a destination sector
@@ -285,35 +681,434 @@ impl Route {
 	pub fn new(path: Vec<(SectorID, FieldCell)>) -> Self {
 		Route(path)
 	}
+	/// Whether the route passes through the `sector_id`
+	pub fn contains_sector(&self, sector_id: SectorID) -> bool {
+		self.0.iter().any(|(sector, _)| *sector == sector_id)
+	}
+	/// Get the remaining portion of the route for an actor which has been
+	/// displaced (knockback, teleport etc) to the `field_cell` of a
+	/// `sector_id` that is already on the route. The remainder starts at the
+	/// sector so it can be followed as if it had been requested from the new
+	/// position. If the route passes through the sector more than once the
+	/// pass whose portal (or goal) is closest to the `field_cell` is used,
+	/// favouring the pass closer to the goal on a tie. Returns [None] if the
+	/// sector isn't on the route
+	pub fn splice_from(&self, sector_id: SectorID, field_cell: FieldCell) -> Option<Route> {
+		let distance = |cell: &FieldCell| {
+			cell.get_column().abs_diff(field_cell.get_column())
+				+ cell.get_row().abs_diff(field_cell.get_row())
+		};
+		let (start, _) = self
+			.0
+			.iter()
+			.enumerate()
+			.filter(|(_, (sector, _))| *sector == sector_id)
+			.min_by_key(|(i, (_, cell))| (distance(cell), std::cmp::Reverse(*i)))?;
+		Some(Route(self.0[start..].to_vec()))
+	}
+	/// Get the [FieldCell]s of each sector forming a corridor `width` cells
+	/// wide around the route, such as for keeping the members of a squad
+	/// moving together inside it. The route must be ordered from the actor to
+	/// the goal (as it is within the [RouteCache]).
+	///
+	/// Within each sector the spine of the corridor is made up of the portal
+	/// window the route enters by, the portal window it leaves by (or the
+	/// goal) and the line of sight cells between the two. If an impassable
+	/// [FieldCell] blocks the line of sight the cheapest path between the
+	/// portals is used instead. The spine is then widened by `width / 2`
+	/// cells either side (so a `width` of `3` covers the spine and one cell
+	/// around it) without passing through impassable cells. The sector the
+	/// route starts in only has a spine from its exit portal as the position
+	/// of the actor isn't part of the route
+	pub fn corridor(
+		&self,
+		width: usize,
+		sector_cost_fields: &SectorCostFields,
+	) -> BTreeMap<SectorID, Vec<FieldCell>> {
+		let mut corridor: BTreeMap<SectorID, BTreeSet<FieldCell>> = BTreeMap::new();
+		for (i, (sector_id, exit)) in self.0.iter().enumerate() {
+			let Some(cost_field) = sector_cost_fields.get_scaled().get(sector_id) else {
+				warn!(
+					"Cannot build corridor in non-existent sector {:?}",
+					sector_id
+				);
+				continue;
+			};
+			let mut spine = BTreeSet::new();
+			// window leaving the sector, or just the goal
+			let exit_window = match self.0.get(i + 1) {
+				Some((next_sector, _)) => {
+					portal_window(sector_cost_fields, *sector_id, *next_sector, *exit)
+				}
+				None => vec![*exit],
+			};
+			spine.extend(exit_window.iter().copied());
+			// window entering the sector from the previous one
+			if let Some((previous_sector, previous_exit)) = i.checked_sub(1).map(|j| self.0[j]) {
+				let entry_window = portal_window(
+					sector_cost_fields,
+					previous_sector,
+					*sector_id,
+					previous_exit,
+				);
+				if let Some(ordinal) =
+					Ordinal::sector_to_sector_direction(*sector_id, previous_sector)
+				{
+					let entry_window: Vec<FieldCell> = entry_window
+						.iter()
+						.map(|cell| cell_across_boundary(*cell, ordinal))
+						.collect();
+					let entry = cell_across_boundary(previous_exit, ordinal);
+					spine.extend(entry_window);
+					spine.extend(line_or_cheapest_path(cost_field, entry, *exit));
+				}
+			}
+			let cells = corridor.entry(*sector_id).or_default();
+			cells.extend(widen_spine(cost_field, &spine, width / 2));
+		}
+		corridor
+			.into_iter()
+			.map(|(sector_id, cells)| (sector_id, cells.into_iter().collect()))
+			.collect()
+	}
+	/// Get the estimated cost of reaching each waypoint of the route from
+	/// the `source` position of an actor, such as for previewing the travel
+	/// time of each leg or deciding where to place waypoint markers. The
+	/// route must be ordered from the actor to the goal (as it is within the
+	/// [RouteCache]).
+	///
+	/// The costs are cumulative, each leg adds the cheapest integrated cost
+	/// across its sector plus the cost of stepping over the boundary into
+	/// the next one, so the last value estimates the cost of the whole
+	/// route. Returns [None] if the route doesn't begin in the sector of the
+	/// `source` or a leg can't be walked, such as after the costs changed
+	pub fn get_waypoint_costs(
+		&self,
+		source: (SectorID, FieldCell),
+		sector_cost_fields: &SectorCostFields,
+	) -> Option<Vec<i32>> {
+		let mut costs = Vec::with_capacity(self.0.len());
+		let mut total = 0;
+		let mut entry = source;
+		for (i, (sector_id, waypoint)) in self.0.iter().enumerate() {
+			if *sector_id != entry.0 {
+				return None;
+			}
+			let cost_field = sector_cost_fields.get_scaled().get(sector_id)?;
+			if entry.1 != *waypoint {
+				total += cost_field.get_integrated_cost_between_cells(&entry.1, waypoint)?;
+			}
+			costs.push(total);
+			// step over the boundary into the sector of the next waypoint
+			if let Some((next_sector, _)) = self.0.get(i + 1) {
+				let ordinal = Ordinal::sector_to_sector_direction(*next_sector, *sector_id)?;
+				let next_cell = cell_across_boundary(*waypoint, ordinal);
+				let next_cost_field = sector_cost_fields.get_scaled().get(next_sector)?;
+				total += next_cost_field.get_field_cell_value(next_cell) as i32;
+				entry = (*next_sector, next_cell);
+			}
+		}
+		Some(costs)
+	}
+	/// Get the estimated time it takes an actor of the `speed` to reach each
+	/// waypoint of the route from its `source` position, see
+	/// [Route::get_waypoint_costs] and [TraversalSpeed::cost_to_time]
+	pub fn get_waypoint_times(
+		&self,
+		source: (SectorID, FieldCell),
+		speed: TraversalSpeed,
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) -> Option<Vec<Duration>> {
+		self.get_waypoint_costs(source, sector_cost_fields)
+			.map(|costs| {
+				costs
+					.into_iter()
+					.map(|cost| speed.cost_to_time(cost, map_dimensions))
+					.collect()
+			})
+	}
+}
+
+/// Get the [FieldCell] on the other side of a sector boundary from `cell`
+/// when moving in the `ordinal` direction
+fn cell_across_boundary(cell: FieldCell, ordinal: Ordinal) -> FieldCell {
+	let end = FIELD_RESOLUTION - 1;
+	match ordinal {
+		Ordinal::North => FieldCell::new(cell.get_column(), end),
+		Ordinal::East => FieldCell::new(0, cell.get_row()),
+		Ordinal::South => FieldCell::new(cell.get_column(), 0),
+		Ordinal::West => FieldCell::new(end, cell.get_row()),
+		_ => cell,
+	}
+}
+
+/// Get the [FieldCell]s of the portal window containing the `portal` along
+/// the boundary of `sector_id` which borders `next_sector`, the window
+/// extends along the boundary while both sides of it are pathable
+fn portal_window(
+	sector_cost_fields: &SectorCostFields,
+	sector_id: SectorID,
+	next_sector: SectorID,
+	portal: FieldCell,
+) -> Vec<FieldCell> {
+	let (Some(cost_field), Some(next_cost_field), Some(ordinal)) = (
+		sector_cost_fields.get_scaled().get(&sector_id),
+		sector_cost_fields.get_scaled().get(&next_sector),
+		Ordinal::sector_to_sector_direction(next_sector, sector_id),
+	) else {
+		return vec![portal];
+	};
+	let is_open = |cell: FieldCell| {
+		!cost_field.impassable_mask().is_impassable(cell)
+			&& !next_cost_field
+				.impassable_mask()
+				.is_impassable(cell_across_boundary(cell, ordinal))
+	};
+	let directions = match ordinal {
+		Ordinal::North | Ordinal::South => [Ordinal::West, Ordinal::East],
+		_ => [Ordinal::North, Ordinal::South],
+	};
+	let mut window = vec![portal];
+	for direction in directions {
+		let mut cell = portal;
+		while let Some(next) = cell.neighbour(direction).filter(|n| is_open(*n)) {
+			window.push(next);
+			cell = next;
+		}
+	}
+	window
+}
+
+/// Get the line of sight [FieldCell]s from `source` to `target`, or the
+/// cheapest path between them if an impassable cell blocks the line
+fn line_or_cheapest_path(
+	cost_field: &CostField,
+	source: FieldCell,
+	target: FieldCell,
+) -> Vec<FieldCell> {
+	let line = source.get_cells_between_points(&target);
+	let mask = cost_field.impassable_mask();
+	if line.iter().all(|cell| !mask.is_impassable(*cell)) {
+		return line;
+	}
+	let mut propagation = [[i32::MAX; FIELD_RESOLUTION]; FIELD_RESOLUTION];
+	let mut previous: BTreeMap<FieldCell, FieldCell> = BTreeMap::new();
+	propagation[source.get_column()][source.get_row()] = 0;
+	let mut queue = std::collections::BinaryHeap::new();
+	queue.push(std::cmp::Reverse((0, source)));
+	while let Some(std::cmp::Reverse((cumulative_cost, cell))) = queue.pop() {
+		if cell == target {
+			let mut path = vec![target];
+			while let Some(cell) = previous.get(path.last().unwrap()) {
+				path.push(*cell);
+			}
+			return path;
+		}
+		// skip stale entries that have since been reached more cheaply
+		if cumulative_cost > propagation[cell.get_column()][cell.get_row()] {
+			continue;
+		}
+		for n in Ordinal::get_orthogonal_cell_neighbours(cell) {
+			if !mask.is_impassable(n) {
+				let n_cumulative = cumulative_cost + cost_field.get_field_cell_value(n) as i32;
+				if n_cumulative < propagation[n.get_column()][n.get_row()] {
+					propagation[n.get_column()][n.get_row()] = n_cumulative;
+					previous.insert(n, cell);
+					queue.push(std::cmp::Reverse((n_cumulative, n)));
+				}
+			}
+		}
+	}
+	// unreachable within the sector, the portals alone make up the spine
+	Vec::new()
+}
+
+/// Expand the `spine` by up to `reach` steps in every direction through
+/// pathable [FieldCell]s
+fn widen_spine(
+	cost_field: &CostField,
+	spine: &BTreeSet<FieldCell>,
+	reach: usize,
+) -> BTreeSet<FieldCell> {
+	let mask = cost_field.impassable_mask();
+	let mut cells: BTreeSet<FieldCell> = spine
+		.iter()
+		.filter(|cell| !mask.is_impassable(**cell))
+		.copied()
+		.collect();
+	let mut frontier: Vec<FieldCell> = cells.iter().copied().collect();
+	for _ in 0..reach {
+		let mut next_frontier = Vec::new();
+		for cell in frontier.iter() {
+			for n in Ordinal::get_all_cell_neighbours(*cell) {
+				if !mask.is_impassable(n) && cells.insert(n) {
+					next_frontier.push(n);
+				}
+			}
+		}
+		frontier = next_frontier;
+	}
+	cells
 }
 
-/// Each key makes use of custom Ord and Eq implementations based on comparing `(source_id, target_id, goal_id)` so that RouteMetaData can be used to refer to the high-level route an actor has asked for. The value is a sector-portal (or just the end goal) route. An actor can use this as a fallback if the `field_cache` doesn't yet contain the granular [FlowField] routes or for when [CostField]s have been changed and so [FlowField]s in the cache need to be regenerated
+/// Each key makes use of custom Ord and Eq implementations based on comparing `(source_id, target_id, goal_id)` so that RouteMetaData can be used to refer to the high-level route an actor has asked for. The value is a sector-portal (or just the end goal) route. An actor can use this as a fallback if the `field_cache` doesn't yet contain the granular [FlowField] routes or for when [CostField]s have been changed and so [FlowField]s in the cache need to be regenerated.
+///
+/// Routes are stored in a tree rooted at each goal so that routes sharing
+/// the same final portals (such as actors spread across the map heading to
+/// one goal) only store them once. Each route is rebuilt from the tree when
+/// it's read.
+///
+/// Routes waiting to be promoted into the cache are held by the [RouteQueue]
+/// so that systems reading the cache aren't blocked by path requests
 #[derive(Component, Default, Clone, Reflect)]
 #[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+	feature = "serde",
+	serde(from = "RouteCacheEntries", into = "RouteCacheEntries")
+)]
 pub struct RouteCache {
-	/// A queue of high-level routes which get processed into the `routes` field
-	route_queue: BTreeMap<RouteMetadata, Route>,
+	/// The node of the `tree` each route starts from, [None] for an empty
+	/// route
+	routes: BTreeMap<RouteMetadata, Option<RouteNodeId>>,
+	/// High-level routes describing the path from an actor to an end goal,
+	/// stored as branches of trees rooted at each goal
+	tree: RouteTree,
+	/// The `routes` starting from each node of the `tree`. Lets a [CostField]
+	/// change find the routes it affects by walking only the branches
+	/// through the changed sector
+	#[reflect(ignore)]
+	starts: BTreeMap<RouteNodeId, BTreeSet<RouteMetadata>>,
+	/// The `routes` whose source or target is within each sector, the route
+	/// itself may not pass through the sector when its goal has been moved by
+	/// a [GoalFallback]
+	#[reflect(ignore)]
+	endpoint_index: BTreeMap<SectorID, BTreeSet<RouteMetadata>>,
+	/// The `routes` leading to each shared goal, so that moving a goal finds
+	/// its routes without walking the whole cache
+	#[reflect(ignore)]
+	goal_index: BTreeMap<GoalId, BTreeSet<RouteMetadata>>,
+}
+
+/// The serialised entries of a [RouteCache], the tree and indices are
+/// rebuilt from them when deserialised
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename = "RouteCache")]
+struct RouteCacheEntries {
 	/// High-level routes describing the path from an actor to an end goal
 	routes: BTreeMap<RouteMetadata, Route>,
 }
 
+#[cfg(feature = "serde")]
+impl From<RouteCacheEntries> for RouteCache {
+	fn from(entries: RouteCacheEntries) -> Self {
+		let mut cache = RouteCache::default();
+		for (metadata, route) in entries.routes {
+			cache.insert_route_with_metadata(metadata, route);
+		}
+		cache
+	}
+}
+
+#[cfg(feature = "serde")]
+impl From<RouteCache> for RouteCacheEntries {
+	fn from(cache: RouteCache) -> Self {
+		RouteCacheEntries {
+			routes: cache.get_routes(),
+		}
+	}
+}
+
 impl RouteCache {
-	/// Get a refernce to the map of queued routes
-	pub fn get_queue(&self) -> &BTreeMap<RouteMetadata, Route> {
-		&self.route_queue
+	/// Get the map of routes. Each route is rebuilt from the tree of shared
+	/// routes, use [RouteCache::get_route_from_metadata] to look up a single
+	/// route
+	pub fn get_routes(&self) -> BTreeMap<RouteMetadata, Route> {
+		self.routes
+			.iter()
+			.map(|(metadata, start)| (*metadata, self.tree.get_route(*start)))
+			.collect()
 	}
-	/// Get a mutable reference to the map of queued routes
-	pub fn get_queue_mut(&mut self) -> &mut BTreeMap<RouteMetadata, Route> {
-		&mut self.route_queue
+	/// Get the metadata of every cached route
+	pub fn get_route_metadata(&self) -> impl Iterator<Item = &RouteMetadata> {
+		self.routes.keys()
+	}
+	/// Get the number of cached routes
+	pub fn len(&self) -> usize {
+		self.routes.len()
+	}
+	/// Whether the cache holds no routes
+	pub fn is_empty(&self) -> bool {
+		self.routes.is_empty()
+	}
+	/// Whether a route is cached for the `metadata`
+	pub fn contains_route(&self, metadata: &RouteMetadata) -> bool {
+		self.routes.contains_key(metadata)
 	}
-	/// Get the map of routes
-	pub fn get_routes(&self) -> &BTreeMap<RouteMetadata, Route> {
-		&self.routes
+	/// Get the number of sector-portal (or goal) nodes stored for every
+	/// route, a node shared by several routes is only counted once
+	pub fn get_node_count(&self) -> usize {
+		self.tree.get_node_count()
 	}
-	/// Get a mutable reference to the map of routes
-	pub fn get_mut(&mut self) -> &mut BTreeMap<RouteMetadata, Route> {
-		&mut self.routes
+	/// Get the metadata of every cached route making use of a sector, either
+	/// as its source, target or a sector along the way
+	pub fn get_routes_using_sector(&self, sector_id: SectorID) -> Vec<RouteMetadata> {
+		let mut routes = self
+			.endpoint_index
+			.get(&sector_id)
+			.cloned()
+			.unwrap_or_default();
+		for node in self.tree.get_branches_through_sector(sector_id) {
+			if let Some(starts) = self.starts.get(&node) {
+				routes.extend(starts.iter().copied());
+			}
+		}
+		routes.into_iter().collect()
+	}
+	/// Add a route to the indices, replacing any entry with the same metadata
+	/// so that its latest properties are kept
+	fn index_route(&mut self, metadata: RouteMetadata, start: Option<RouteNodeId>) {
+		if let Some(node) = start {
+			self.starts.entry(node).or_default().replace(metadata);
+		}
+		for sector_id in [metadata.get_source_sector(), metadata.get_target_sector()] {
+			self.endpoint_index
+				.entry(sector_id)
+				.or_default()
+				.replace(metadata);
+		}
+		if let Some(goal_id) = metadata.get_shared_goal() {
+			self.goal_index
+				.entry(goal_id)
+				.or_default()
+				.replace(metadata);
+		}
+	}
+	/// Remove a route from the indices
+	fn unindex_route(&mut self, metadata: &RouteMetadata, start: Option<RouteNodeId>) {
+		if let Some(node) = start {
+			if let Some(routes) = self.starts.get_mut(&node) {
+				routes.remove(metadata);
+				if routes.is_empty() {
+					self.starts.remove(&node);
+				}
+			}
+		}
+		for sector_id in [metadata.get_source_sector(), metadata.get_target_sector()] {
+			if let Some(routes) = self.endpoint_index.get_mut(&sector_id) {
+				routes.remove(metadata);
+				if routes.is_empty() {
+					self.endpoint_index.remove(&sector_id);
+				}
+			}
+		}
+		// a route may lead to several shared goals which occupy the same cell
+		self.goal_index.retain(|_, routes| {
+			routes.remove(metadata);
+			!routes.is_empty()
+		});
 	}
 	/// Get a high-level sector to sector route. Returns [None] if it doesn't exist
 	pub fn get_route(
@@ -322,38 +1117,121 @@ impl RouteCache {
 		source_field: FieldCell,
 		target_sector: SectorID,
 		goal_id: FieldCell,
-	) -> Option<&Route> {
-		let route_data = RouteMetadata {
+	) -> Option<Route> {
+		self.get_route_with_metadata(source_sector, source_field, target_sector, goal_id)
+			.map(|(_, route)| route)
+	}
+	/// Get a high-level sector to sector route. Returns [None] if it doesn't exist
+	pub fn get_route_with_metadata(
+		&self,
+		source_sector: SectorID,
+		source_field: FieldCell,
+		target_sector: SectorID,
+		goal_id: FieldCell,
+	) -> Option<(&RouteMetadata, Route)> {
+		let route_data = RouteMetadata::new(
 			source_sector,
 			source_field,
 			target_sector,
-			target_goal: goal_id,
-			time_generated: Duration::default(),
-		};
-		let route = self.routes.get(&route_data);
-		route
+			goal_id,
+			Duration::default(),
+		);
+		self.get_route_from_metadata(&route_data)
 	}
-	/// Get a high-level sector to sector route. Returns [None] if it doesn't exist
-	pub fn get_route_with_metadata(
+	/// Get the cached route of a request alongside the metadata it was cached
+	/// with. Returns [None] if it doesn't exist
+	pub fn get_route_from_metadata(
+		&self,
+		metadata: &RouteMetadata,
+	) -> Option<(&RouteMetadata, Route)> {
+		self.routes
+			.get_key_value(metadata)
+			.map(|(cached, start)| (cached, self.tree.get_route(*start)))
+	}
+	/// Walk the cached route of a request from its source to the goal
+	/// without building a [Route]. Returns [None] if it doesn't exist
+	pub fn iter_route(
+		&self,
+		metadata: &RouteMetadata,
+	) -> Option<impl Iterator<Item = (SectorID, FieldCell)> + '_> {
+		self.routes
+			.get(metadata)
+			.map(|start| self.tree.iter_route(*start))
+	}
+	/// As with [RouteCache::get_route] for a route searched on behalf of a
+	/// `faction`, see [RouteMetadata::with_faction]
+	pub fn get_faction_route(
 		&self,
 		source_sector: SectorID,
 		source_field: FieldCell,
 		target_sector: SectorID,
 		goal_id: FieldCell,
-	) -> Option<(&RouteMetadata, &Route)> {
-		let route_data = RouteMetadata {
+		faction: FactionId,
+	) -> Option<Route> {
+		let route_data = RouteMetadata::new(
 			source_sector,
 			source_field,
 			target_sector,
-			target_goal: goal_id,
-			time_generated: Duration::default(),
-		};
-		let route = self.routes.get_key_value(&route_data);
-		route
+			goal_id,
+			Duration::default(),
+		)
+		.with_faction(faction);
+		self.get_route_from_metadata(&route_data)
+			.map(|(_, route)| route)
 	}
-	/// Insert a high-level route of sector-portal paths (or just the end goal if local sector pathing) into the `route_cache`
-	pub fn add_to_queue(&mut self, route_data: RouteMetadata, route: Route) {
-		self.route_queue.insert(route_data, route);
+	/// As with [RouteCache::get_route] for a route searched on behalf of a
+	/// `player`, see [RouteMetadata::with_player]
+	pub fn get_player_route(
+		&self,
+		source_sector: SectorID,
+		source_field: FieldCell,
+		target_sector: SectorID,
+		goal_id: FieldCell,
+		player: PlayerId,
+	) -> Option<Route> {
+		let route_data = RouteMetadata::new(
+			source_sector,
+			source_field,
+			target_sector,
+			goal_id,
+			Duration::default(),
+		)
+		.with_player(player);
+		self.get_route_from_metadata(&route_data)
+			.map(|(_, route)| route)
+	}
+	/// Find a cached route to the `target_sector` and `goal_id` which passes
+	/// through the `source_sector` and splice it from the `source_field`, see
+	/// [Route::splice_from]. This lets a displaced actor reuse the remainder
+	/// of a route (and the [FlowField]s already cached for it) rather than
+	/// requesting a new one. The `source_field` must be able to reach the
+	/// portal (or goal) the remainder leads to within the [CostField] of the
+	/// sector. When several routes match the shortest remainder is returned.
+	/// Returns [None] if no cached route can be reused
+	pub fn get_spliced_route(
+		&self,
+		source_sector: SectorID,
+		source_field: FieldCell,
+		target_sector: SectorID,
+		goal_id: FieldCell,
+		sector_cost_fields: &SectorCostFields,
+	) -> Option<Route> {
+		let cost_field = sector_cost_fields.get_scaled().get(&source_sector)?;
+		self.get_routes_using_sector(source_sector)
+			.iter()
+			.filter(|metadata| {
+				metadata.get_target_sector() == target_sector
+					&& metadata.get_target_goal() == goal_id
+			})
+			.filter_map(|metadata| self.get_route_from_metadata(metadata))
+			.filter_map(|(_, route)| route.splice_from(source_sector, source_field))
+			.filter(|spliced| {
+				spliced
+					.get()
+					.first()
+					.is_some_and(|(_, cell)| cost_field.is_cell_pair_reachable(source_field, *cell))
+			})
+			.min_by_key(|spliced| spliced.get().len())
 	}
 	/// Insert a high-level route of sector-portal paths (or just the end goal if local sector pathing) into the `route_cache`
 	pub fn insert_route(
@@ -362,32 +1240,349 @@ impl RouteCache {
 		source_field: FieldCell,
 		target_sector: SectorID,
 		goal_id: FieldCell,
-		elapsed_duration: Duration,
+		elapsed_duration: impl Into<Stamp>,
 		route: Route,
 	) {
-		let route_data = RouteMetadata {
+		let route_data = RouteMetadata::new(
 			source_sector,
 			source_field,
 			target_sector,
-			target_goal: goal_id,
-			time_generated: elapsed_duration,
-		};
-		self.routes.insert(route_data, route);
+			goal_id,
+			elapsed_duration,
+		);
+		self.insert_route_with_metadata(route_data, route);
 	}
 	/// Insert a high-level route of sector-portal paths (or just the end goal if local sector pathing) into the `route_cache` with an already created [RouteMetadata] structure
 	pub fn insert_route_with_metadata(&mut self, route_metadata: RouteMetadata, route: Route) {
-		self.routes.insert(route_metadata, route);
+		// metadata only compares the source and target, remove the old entry
+		// so the properties of the replacement are kept
+		self.remove_route(route_metadata);
+		let start = self.tree.insert(&route);
+		self.index_route(route_metadata, start);
+		self.routes.insert(route_metadata, start);
+	}
+	/// Update the generation time of a cached route so that it doesn't
+	/// expire. Returns false if the route isn't cached
+	pub fn refresh_route(
+		&mut self,
+		route_metadata: RouteMetadata,
+		elapsed: impl Into<Stamp>,
+	) -> bool {
+		// metadata ignores the time when compared so remove the old entry first
+		if let Some((mut metadata, start)) = self.routes.remove_entry(&route_metadata) {
+			metadata.time_generated = elapsed.into();
+			self.index_route(metadata, start);
+			self.routes.insert(metadata, start);
+			true
+		} else {
+			false
+		}
 	}
-	/// Remove a high-level  route of sector-portal paths (or just the end goal if local sector pathing) from the `route_cache`
+	/// Remove a high-level  route of sector-portal paths (or just the end goal if local sector pathing) from the `route_cache`. Only the
+	/// part of the route which isn't shared with another route is discarded
 	pub fn remove_route(&mut self, route_metadata: RouteMetadata) {
-		self.routes.remove(&route_metadata);
+		if let Some((metadata, start)) = self.routes.remove_entry(&route_metadata) {
+			self.unindex_route(&metadata, start);
+			if let Some(start) = start {
+				self.tree.remove(start);
+			}
+		}
+	}
+	/// Remove every route from the cache
+	pub fn clear(&mut self) {
+		self.routes.clear();
+		self.tree.clear();
+		self.starts.clear();
+		self.endpoint_index.clear();
+		self.goal_index.clear();
+	}
+	/// Estimate the bytes held by the routes within each sector, a
+	/// sector-portal (or goal) node shared by several routes is only counted
+	/// once within the sector of the node
+	pub fn memory_usage(&self) -> MemoryUsage {
+		let mut usage = self.tree.memory_usage();
+		let size = entry_size::<RouteMetadata, Option<RouteNodeId>>();
+		for metadata in self.routes.keys() {
+			usage.add(metadata.get_source_sector(), MemoryCategory::Routes, size);
+		}
+		usage
+	}
+	/// Get the metadata of every cached route leading to a shared goal
+	pub fn get_routes_to_goal(&self, goal_id: GoalId) -> Vec<RouteMetadata> {
+		self.goal_index
+			.get(&goal_id)
+			.map(|routes| routes.iter().copied().collect())
+			.unwrap_or_default()
+	}
+	/// Whether the cached route of the `metadata` is indexed as leading to
+	/// the shared goal `goal_id`
+	pub fn is_route_to_goal(&self, goal_id: GoalId, metadata: &RouteMetadata) -> bool {
+		self.goal_index
+			.get(&goal_id)
+			.is_some_and(|routes| routes.contains(metadata))
+	}
+	/// Index the cached route of the `metadata` as leading to the shared goal
+	/// of the `metadata`, such as when a request to a shared goal is served by
+	/// a route which was cached without one. Metadata only compares the
+	/// source and target so the cached entry is otherwise untouched. Returns
+	/// false if the route isn't cached or the `metadata` has no shared goal
+	pub fn tag_shared_goal(&mut self, metadata: &RouteMetadata) -> bool {
+		let Some(goal_id) = metadata.get_shared_goal() else {
+			return false;
+		};
+		let Some((cached, start)) = self.routes.remove_entry(metadata) else {
+			return false;
+		};
+		// the request repeated once the goal moves should follow it
+		let tagged = match cached.get_shared_goal() {
+			Some(_) => cached,
+			None => cached.with_shared_goal(goal_id),
+		};
+		self.unindex_route(&cached, start);
+		self.routes.insert(tagged, start);
+		self.index_route(tagged, start);
+		self.goal_index.entry(goal_id).or_default().insert(tagged);
+		true
+	}
+	/// Remove every cached route leading to a shared goal, such as when the
+	/// goal has moved. Returns the metadata of the removed routes so that
+	/// they can be requested again, see [RouteQueue::remove_routes_to_goal]
+	/// for routes which are still queued
+	pub fn remove_routes_to_goal(&mut self, goal_id: GoalId) -> Vec<RouteMetadata> {
+		let removed = self.get_routes_to_goal(goal_id);
+		for metadata in removed.iter() {
+			self.remove_route(*metadata);
+		}
+		removed
+	}
+}
+
+/// High-level routes which have been searched for but not yet promoted into
+/// the [RouteCache]. Path requests only write to the queue, the routes are
+/// moved into the [RouteCache] (and their [FlowField]s queued in the
+/// [FlowFieldQueue]) by a later step of the pipeline, so systems which only
+/// read the [RouteCache] can run alongside the handling of requests
+#[derive(Component, Default, Clone, Reflect)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct RouteQueue {
+	/// A queue of high-level routes which get processed into the routes of
+	/// the [RouteCache]
+	queue: BTreeMap<RouteMetadata, Route>,
+	/// Requests following a queued route, keyed by the queued route. Actors
+	/// in the same sector heading to the same goal share a single search and
+	/// set of [FlowField]s, each follower is given a copy of the route once
+	/// it leaves the queue
+	#[cfg_attr(feature = "serde", serde(default))]
+	followers: BTreeMap<RouteMetadata, Vec<RouteMetadata>>,
+	/// Requests following a route which was already cached, given a copy of
+	/// it waiting to be inserted into the [RouteCache]. No fields are built
+	/// for them as they're shared with the cached route
+	#[cfg_attr(feature = "serde", serde(default))]
+	copies: BTreeMap<RouteMetadata, Route>,
+	/// Requests to a shared goal served by a route which was cached without
+	/// being indexed under the goal, waiting for the [RouteCache] to record
+	/// it (see [RouteCache::tag_shared_goal])
+	#[cfg_attr(feature = "serde", serde(default))]
+	goal_tags: Vec<RouteMetadata>,
+}
+
+impl RouteQueue {
+	/// Get a refernce to the map of queued routes
+	pub fn get_queue(&self) -> &BTreeMap<RouteMetadata, Route> {
+		&self.queue
+	}
+	/// Get a mutable reference to the map of queued routes
+	pub fn get_queue_mut(&mut self) -> &mut BTreeMap<RouteMetadata, Route> {
+		&mut self.queue
+	}
+	/// Get the requests following a cached route alongside their copy of it,
+	/// waiting to be inserted into the [RouteCache]
+	pub fn get_copies(&self) -> &BTreeMap<RouteMetadata, Route> {
+		&self.copies
+	}
+	/// Iterate over every queued route and every copy of a cached route
+	/// waiting to be inserted into the [RouteCache]
+	pub fn iter(&self) -> impl Iterator<Item = (&RouteMetadata, &Route)> {
+		self.queue.iter().chain(self.copies.iter())
+	}
+	/// Whether there's a route queued, or a copy waiting, for the `metadata`
+	pub fn contains(&self, metadata: &RouteMetadata) -> bool {
+		self.queue.contains_key(metadata) || self.copies.contains_key(metadata)
+	}
+	/// Whether nothing is queued
+	pub fn is_empty(&self) -> bool {
+		self.queue.is_empty() && self.copies.is_empty() && self.goal_tags.is_empty()
+	}
+	/// Insert a high-level route of sector-portal paths (or just the end goal if local sector pathing) into the queue
+	pub fn add_to_queue(&mut self, route_data: RouteMetadata, route: Route) {
+		// metadata only compares the source and target, remove the old entry
+		// so the properties of the replacement are kept
+		self.queue.remove(&route_data);
+		self.queue.insert(route_data, route);
 	}
 	/// Remove a high-level route that has been queued (or just the end goal if
-	/// local sector pathing)
+	/// local sector pathing), any requests following it are dropped too
 	pub fn remove_queued_route(&mut self, route_metadata: RouteMetadata) {
-		self.route_queue.remove(&route_metadata);
+		self.queue.remove(&route_metadata);
+		self.followers.remove(&route_metadata);
+		self.copies.remove(&route_metadata);
+	}
+	/// Remove every queued route and follower
+	pub fn clear(&mut self) {
+		self.queue.clear();
+		self.followers.clear();
+		self.copies.clear();
+		self.goal_tags.clear();
+	}
+	/// Remove every queued route (and the requests following them) leading
+	/// to a shared goal, such as when the goal has moved. Returns the
+	/// metadata of the removed routes so that they can be requested again
+	pub fn remove_routes_to_goal(&mut self, goal_id: GoalId) -> Vec<RouteMetadata> {
+		self.goal_tags
+			.retain(|metadata| metadata.get_shared_goal() != Some(goal_id));
+		let queued: Vec<RouteMetadata> = self
+			.iter()
+			.map(|(metadata, _)| *metadata)
+			.filter(|metadata| metadata.get_shared_goal() == Some(goal_id))
+			.collect();
+		let mut removed = Vec::new();
+		for metadata in queued {
+			removed.append(&mut self.take_followers(&metadata));
+			self.remove_queued_route(metadata);
+			removed.push(metadata);
+		}
+		removed
+	}
+	/// Find a queued or cached route which a request described by the
+	/// `metadata` can follow instead of searching for its own. The route must
+	/// start in the same sector and lead to the same goal (with the same
+	/// [GoalFallback] and stopping distance), and the source [FieldCell] of
+	/// the request must be able to reach the first portal (or goal) of the
+	/// route within the [CostField] of the sector. Returns the metadata of
+	/// the route to follow
+	pub fn find_leader(
+		&self,
+		route_cache: &RouteCache,
+		metadata: &RouteMetadata,
+		sector_cost_fields: &SectorCostFields,
+	) -> Option<RouteMetadata> {
+		let source_sector = metadata.get_source_sector();
+		let cost_field = sector_cost_fields.get_scaled().get(&source_sector)?;
+		let is_same_request = |leader: &RouteMetadata| {
+			leader != metadata
+				&& leader.get_source_sector() == source_sector
+				&& leader.get_target_sector() == metadata.get_target_sector()
+				&& leader.get_target_goal() == metadata.get_target_goal()
+				&& leader.get_goal_fallback() == metadata.get_goal_fallback()
+				&& leader.get_goal_position() == metadata.get_goal_position()
+				&& leader.get_stopping_distance() == metadata.get_stopping_distance()
+				&& leader.get_player() == metadata.get_player()
+				&& leader.get_faction() == metadata.get_faction()
+				&& leader.get_goal_seeds() == metadata.get_goal_seeds()
+				// a budget is only met by a route found within the same or a
+				// smaller budget
+				&& metadata.get_max_cost().is_none_or(|max_cost| {
+					leader
+						.get_max_cost()
+						.is_some_and(|leader_max| leader_max <= max_cost)
+				})
+		};
+		let can_follow = |route: &Route| {
+			route.get().first().is_some_and(|(sector_id, cell)| {
+				*sector_id == source_sector
+					&& cost_field.is_cell_pair_reachable(metadata.get_source_field_cell(), *cell)
+			})
+		};
+		let queued = self
+			.queue
+			.iter()
+			.find(|(leader, route)| is_same_request(leader) && can_follow(route))
+			.map(|(leader, _)| *leader);
+		queued.or_else(|| {
+			route_cache
+				.get_routes_using_sector(source_sector)
+				.into_iter()
+				.find(|leader| {
+					is_same_request(leader)
+						&& route_cache
+							.get_route_from_metadata(leader)
+							.is_some_and(|(_, route)| can_follow(&route))
+				})
+		})
+	}
+	/// Attach the `follower` request to the queued or cached route of the
+	/// `leader`, see [RouteQueue::find_leader]. The follower of a cached
+	/// route is given a copy of it straight away, otherwise the follower is
+	/// given the route when the leader leaves the queue and the leader is
+	/// raised to the [PathPriority] of the follower if it's higher. Either
+	/// way the follower is inserted into the [RouteCache] as the queue is
+	/// promoted. Returns false if the leader doesn't exist
+	pub fn add_follower(
+		&mut self,
+		route_cache: &RouteCache,
+		leader: RouteMetadata,
+		mut follower: RouteMetadata,
+	) -> bool {
+		if self.queue.contains_key(&leader) {
+			self.raise_priority(&leader, follower.get_priority());
+			let followers = self.followers.entry(leader).or_default();
+			followers.retain(|existing| *existing != follower);
+			followers.push(follower);
+			true
+		} else if let Some((cached, route)) = route_cache.get_route_from_metadata(&leader) {
+			let (sector_id, goal) = cached.get_resolved_target();
+			follower.set_resolved_target(sector_id, goal);
+			self.copies.remove(&follower);
+			self.copies.insert(follower, route);
+			true
+		} else {
+			false
+		}
+	}
+	/// Raise the [PathPriority] of a queued route to `priority`, a route
+	/// which already has the same or a higher priority is left alone
+	pub fn raise_priority(&mut self, metadata: &RouteMetadata, priority: PathPriority) {
+		// metadata ignores the priority when compared so the entry is
+		// replaced to update its key
+		if let Some((queued, route)) = self.queue.remove_entry(metadata) {
+			let raised = queued.with_priority(queued.get_priority().max(priority));
+			self.queue.insert(raised, route);
+		}
+	}
+	/// Get the requests following a queued route
+	pub fn get_followers(&self, leader: &RouteMetadata) -> Option<&Vec<RouteMetadata>> {
+		self.followers.get(leader)
+	}
+	/// Remove the requests following a queued route so that they can be
+	/// given the route as it leaves the queue
+	pub fn take_followers(&mut self, leader: &RouteMetadata) -> Vec<RouteMetadata> {
+		self.followers.remove(leader).unwrap_or_default()
+	}
+	/// Remove the copies of cached routes waiting to be inserted into the
+	/// [RouteCache]
+	pub fn take_copies(&mut self) -> BTreeMap<RouteMetadata, Route> {
+		std::mem::take(&mut self.copies)
+	}
+	/// Record that a request to a shared goal was served by a cached route,
+	/// the route is indexed under the goal once the [RouteCache] is next
+	/// written to
+	pub fn tag_shared_goal(&mut self, metadata: RouteMetadata) {
+		let is_tagged = self
+			.goal_tags
+			.iter()
+			.any(|tag| *tag == metadata && tag.get_shared_goal() == metadata.get_shared_goal());
+		if metadata.get_shared_goal().is_some() && !is_tagged {
+			self.goal_tags.push(metadata);
+		}
+	}
+	/// Remove the requests to shared goals waiting for their cached route to
+	/// be indexed under the goal
+	pub fn take_goal_tags(&mut self) -> Vec<RouteMetadata> {
+		std::mem::take(&mut self.goal_tags)
 	}
 }
+
 /// Describes the properties of a [FlowField]
 #[derive(Clone, Copy, Reflect)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
@@ -399,8 +1594,19 @@ pub struct FlowFieldMetadata {
 	/// Portal ID if this field is used in trnasit to another sector
 	portal_id: Option<FieldCell>,
 	//? If a game is running for 136 years bad things will start happening here
-	/// Marks the field based on time elapsed since app start, used to enable automatic cleardown of long lived fields that are probably not needed anymore
-	time_generated: Duration,
+	/// Marks the field based on time elapsed since app start (or the turn when playing turn-based), used to enable automatic cleardown of long lived fields that are probably not needed anymore
+	time_generated: Stamp,
+	/// The faction the field was built for, whose restricted zones it avoids
+	#[cfg_attr(feature = "serde", serde(default))]
+	faction: Option<FactionId>,
+	/// The [EventPathRequest] whose route the field was built for, it isn't
+	/// compared so the field is shared with identical requests
+	#[cfg_attr(feature = "serde", serde(default))]
+	request_id: Option<RequestId>,
+	/// Additional goals the field of the goal sector was seeded with, see
+	/// [RouteMetadata::with_goal_seeds]
+	#[cfg_attr(feature = "serde", serde(default))]
+	goal_seeds: GoalSeeds,
 }
 // we don't want to compare `time_generated` so manually impl PartialEq
 impl PartialEq for FlowFieldMetadata {
@@ -408,16 +1614,27 @@ impl PartialEq for FlowFieldMetadata {
 		self.sector_id == other.sector_id
 			&& self.goal_id == other.goal_id
 			&& self.portal_id == other.portal_id
+			&& self.faction == other.faction
+			&& self.goal_seeds == other.goal_seeds
 	}
 }
 impl Eq for FlowFieldMetadata {}
 impl Ord for FlowFieldMetadata {
 	fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-		(self.sector_id, self.goal_id, self.portal_id).cmp(&(
-			other.sector_id,
-			other.goal_id,
-			other.portal_id,
-		))
+		(
+			self.sector_id,
+			self.goal_id,
+			self.portal_id,
+			self.faction,
+			self.goal_seeds,
+		)
+			.cmp(&(
+				other.sector_id,
+				other.goal_id,
+				other.portal_id,
+				other.faction,
+				other.goal_seeds,
+			))
 	}
 }
 impl PartialOrd for FlowFieldMetadata {
@@ -427,22 +1644,124 @@ impl PartialOrd for FlowFieldMetadata {
 }
 
 impl FlowFieldMetadata {
-	/// Get the sector
-	pub fn get_sector_id(&self) -> SectorID {
-		self.sector_id
-	}
-	/// Get the goal
-	pub fn get_goal_id(&self) -> Option<FieldCell> {
-		self.goal_id
+	/// Create the metadata used to look up the [FlowField] of the
+	/// `current_sector_id` leading towards the `goal_id`. When the current
+	/// sector is the goal sector the `goal_id` is the end goal, otherwise it's
+	/// the portal of the current sector. The generation time is ignored when
+	/// metadata is compared
+	pub fn for_lookup(
+		current_sector_id: SectorID,
+		goal_sector_id: SectorID,
+		goal_id: FieldCell,
+	) -> Self {
+		if current_sector_id == goal_sector_id {
+			FlowFieldMetadata {
+				sector_id: current_sector_id,
+				goal_id: Some(goal_id),
+				portal_id: None,
+				time_generated: Stamp::default(),
+				faction: None,
+				request_id: None,
+				goal_seeds: GoalSeeds::default(),
+			}
+		} else {
+			FlowFieldMetadata {
+				sector_id: current_sector_id,
+				goal_id: None,
+				portal_id: Some(goal_id),
+				time_generated: Stamp::default(),
+				faction: None,
+				request_id: None,
+				goal_seeds: GoalSeeds::default(),
+			}
+		}
+	}
+	/// Get the sector
+	pub fn get_sector_id(&self) -> SectorID {
+		self.sector_id
+	}
+	/// Get the goal
+	pub fn get_goal_id(&self) -> Option<FieldCell> {
+		self.goal_id
 	}
 	/// Get the portal
 	pub fn get_portal_id(&self) -> Option<FieldCell> {
 		self.portal_id
 	}
 	/// Get when the field was generated
-	pub fn get_time_generated(&self) -> Duration {
+	pub fn get_time_generated(&self) -> Stamp {
 		self.time_generated
 	}
+	/// Look up the field built for a `faction`, see [RouteMetadata::with_faction]
+	pub fn with_faction(mut self, faction: FactionId) -> Self {
+		self.faction = Some(faction);
+		self
+	}
+	/// Get the faction the field was built for
+	pub fn get_faction(&self) -> Option<FactionId> {
+		self.faction
+	}
+	/// Look up the field of a goal sector seeded with additional goals, see
+	/// [RouteMetadata::with_goal_seeds]. Fields of transit sectors are never
+	/// seeded so their metadata is unchanged
+	pub fn with_goal_seeds(mut self, goal_seeds: GoalSeeds) -> Self {
+		if self.goal_id.is_some() {
+			self.goal_seeds = goal_seeds;
+		}
+		self
+	}
+	/// Get the additional goals the field was seeded with
+	pub fn get_goal_seeds(&self) -> GoalSeeds {
+		self.goal_seeds
+	}
+	/// Get the ID of the [EventPathRequest] whose route the field was built
+	/// for
+	pub fn get_request_id(&self) -> Option<RequestId> {
+		self.request_id
+	}
+}
+
+/// How far ahead of an actor the [FlowField]s of a queued route should be
+/// built, see [FlowFieldQueue::hint_actor_position]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct PrefetchPolicy {
+	/// Number of sectors beyond the actor's sector along its route whose
+	/// [FlowField]s should be ready before it crosses the boundary
+	sectors_ahead: usize,
+	/// How many [FieldCell]s from the exit portal of its sector an actor is
+	/// treated as approaching the boundary
+	boundary_distance: usize,
+}
+
+impl Default for PrefetchPolicy {
+	fn default() -> Self {
+		PrefetchPolicy {
+			sectors_ahead: 1,
+			boundary_distance: 3,
+		}
+	}
+}
+
+impl PrefetchPolicy {
+	/// Create a new [PrefetchPolicy] where the [FlowField]s of the next
+	/// `sectors_ahead` sectors of a route are prioritised once an actor is
+	/// within `boundary_distance` [FieldCell]s of the exit of its sector
+	pub fn new(sectors_ahead: usize, boundary_distance: usize) -> Self {
+		PrefetchPolicy {
+			sectors_ahead,
+			boundary_distance,
+		}
+	}
+	/// Get the number of sectors ahead of an actor which are prefetched
+	pub fn get_sectors_ahead(&self) -> usize {
+		self.sectors_ahead
+	}
+	/// Get how many [FieldCell]s from the exit of its sector an actor is
+	/// treated as approaching the boundary
+	pub fn get_boundary_distance(&self) -> usize {
+		self.boundary_distance
+	}
 }
 
 /// Each generated [FlowField] is placed into this cache so that multiple actors can read from the same dataset.
@@ -450,40 +1769,133 @@ impl FlowFieldMetadata {
 /// Each entry is given an ID of `(sector_id, goal_id)` and actors can poll the
 /// cache to retrieve the field once it's built and inserted. Note that
 /// `goal_id` can refer to the true end-goal or it can refer to a portal
-/// position when a path spans multiple sectors
-#[derive(Component, Default, Reflect)]
+/// position when a path spans multiple sectors.
+///
+/// The fields are built within the [FlowFieldQueue] and only inserted into
+/// the cache by the final step of the pipeline, so steering systems reading
+/// the cache can run alongside the earlier steps
+#[derive(Component, Default, Clone, Reflect)]
 #[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct FlowFieldCache {
-	/// Routes describing the sector path and [IntegrationField]s where the
-	/// integration and flow fields can be incrementally built
-	queue: BTreeMap<RouteMetadata, IntegrationBuilder>,
-	/// Created FlowFields that actors can use to pathfind
-	flows: BTreeMap<FlowFieldMetadata, FlowField>,
+	/// Created FlowFields that actors can use to pathfind, each is shared
+	/// behind an [Arc] so that snapshots of the cache don't copy the fields
+	flows: BTreeMap<FlowFieldMetadata, Arc<FlowField>>,
+	/// The [IntegrationField]s the `flows` were calculated from alongside
+	/// the goals (the expanded portal window for transit sectors) they were
+	/// seeded from. Used to re-validate a [FlowField] when the [CostField] of
+	/// its sector changes and to share transit fields between routes
+	integrations: BTreeMap<FlowFieldMetadata, (Vec<FieldCell>, IntegrationField)>,
+	/// How far the integrated cost of a changed [FieldCell] may drift before
+	/// the [FlowField] is rebuilt
+	revalidation_tolerance: u32,
+	/// Flags set on the cells of each [FlowField] by the `flag_hook`
+	#[cfg_attr(feature = "serde", serde(default))]
+	user_flags: BTreeMap<FlowFieldMetadata, UserFlagField>,
+	/// Run as each [FlowField] is generated to produce its [UserFlagField]
+	#[reflect(ignore)]
+	#[cfg_attr(feature = "serde", serde(skip))]
+	flag_hook: Option<Arc<dyn FlowFlagHook>>,
+	/// How much more expensive than the cheapest neighbour a [FieldCell] a
+	/// [FlowField] points at may be, see [FlowField::calculate_with_spread]
+	#[cfg_attr(feature = "serde", serde(default))]
+	flow_spread: u32,
+	/// The [FieldCell]s of the sector next to a goal sector which have Line
+	/// Of Sight to the goal, keyed by the goal [FlowField] and the sector, see
+	/// [IntegrationBuilder::get_approach_los]
+	#[cfg_attr(feature = "serde", serde(default))]
+	approach_los: BTreeMap<(FlowFieldMetadata, SectorID), Vec<FieldCell>>,
 }
 
 impl FlowFieldCache {
 	/// Get the map of [FlowField]s
-	pub fn get(&self) -> &BTreeMap<FlowFieldMetadata, FlowField> {
+	pub fn get(&self) -> &BTreeMap<FlowFieldMetadata, Arc<FlowField>> {
 		&self.flows
 	}
-	/// Get a mutable reference to the map of [FlowField]s
-	pub fn get_mut(&mut self) -> &mut BTreeMap<FlowFieldMetadata, FlowField> {
+	/// Get a mutable reference to the map of [FlowField]s, use
+	/// [Arc::make_mut] to edit a field without altering any snapshot sharing
+	/// it
+	pub fn get_mut(&mut self) -> &mut BTreeMap<FlowFieldMetadata, Arc<FlowField>> {
 		&mut self.flows
 	}
-	/// Get a mutable reference to the queue map
-	pub fn get_queue_mut(&mut self) -> &mut BTreeMap<RouteMetadata, IntegrationBuilder> {
-		&mut self.queue
+	/// Set how far the integrated cost of a [FieldCell] may drift after a
+	/// [CostField] change before any [FlowField] using it is rebuilt. A
+	/// `tolerance` of `0` (the default) only keeps fields which are
+	/// unaffected by the change
+	pub fn with_revalidation_tolerance(mut self, tolerance: u32) -> Self {
+		self.revalidation_tolerance = tolerance;
+		self
 	}
-	/// Insert a route into the queue to be built
-	pub fn add_to_queue(
-		&mut self,
-		metadata: RouteMetadata,
-		path: Route,
-		cost_fields: &SectorCostFields,
-	) {
-		let int_builder = IntegrationBuilder::new(path, cost_fields);
-		self.queue.insert(metadata, int_builder);
+	/// Get the tolerance used when re-validating [FlowField]s
+	pub fn get_revalidation_tolerance(&self) -> u32 {
+		self.revalidation_tolerance
+	}
+	/// Let the [FlowField]s generated from now on point at any neighbour
+	/// whose integrated cost is within `spread` of the cheapest, fanning a
+	/// crowd out across a doorway or other chokepoint window rather than
+	/// funnelling it into a single cell. A `spread` of `0` (the default)
+	/// always points at the cheapest neighbour, see
+	/// [FlowField::calculate_with_spread]
+	pub fn with_flow_spread(mut self, spread: u32) -> Self {
+		self.flow_spread = spread;
+		self
+	}
+	/// Get how far the [FlowField]s spread across near-cheapest neighbours
+	pub fn get_flow_spread(&self) -> u32 {
+		self.flow_spread
+	}
+	/// Produce a [UserFlagField] for every [FlowField] generated from now on
+	/// with a custom [FlowFlagHook]
+	pub fn with_flag_hook(mut self, hook: impl FlowFlagHook + 'static) -> Self {
+		self.set_flag_hook(hook);
+		self
+	}
+	/// Set the [FlowFlagHook] of a cache which already exists, [FlowField]s
+	/// generated before it's set have no [UserFlagField]
+	pub fn set_flag_hook(&mut self, hook: impl FlowFlagHook + 'static) {
+		self.flag_hook = Some(Arc::new(hook));
+	}
+	/// Get the [UserFlagField] of a cached [FlowField] based on the
+	/// `sector_id` and `goal_id`. Returns [None] if the field isn't cached or
+	/// was generated without a [FlowFlagHook]
+	pub fn get_user_flags(
+		&self,
+		current_sector_id: SectorID,
+		goal_sector_id: SectorID,
+		goal_id: FieldCell,
+	) -> Option<&UserFlagField> {
+		let flow_meta = FlowFieldMetadata::for_lookup(current_sector_id, goal_sector_id, goal_id);
+		self.user_flags.get(&flow_meta)
+	}
+	/// Estimate the bytes of the cached fields held for each sector, see
+	/// [FlowFieldQueue::memory_usage] for the fields of queued routes
+	pub fn memory_usage(&self) -> MemoryUsage {
+		let mut usage = MemoryUsage::new();
+		for flow_meta in self.flows.keys() {
+			let size = entry_size::<FlowFieldMetadata, FlowField>();
+			usage.add(flow_meta.get_sector_id(), MemoryCategory::FlowFields, size);
+		}
+		for (flow_meta, (goals, int_field)) in self.integrations.iter() {
+			let size = entry_size::<FlowFieldMetadata, (Vec<FieldCell>, IntegrationField)>()
+				+ goals.capacity() * std::mem::size_of::<FieldCell>()
+				+ int_field.heap_size();
+			let sector_id = flow_meta.get_sector_id();
+			usage.add(sector_id, MemoryCategory::IntegrationFields, size);
+		}
+		for flow_meta in self.user_flags.keys() {
+			let size = entry_size::<FlowFieldMetadata, UserFlagField>();
+			usage.add(
+				flow_meta.get_sector_id(),
+				MemoryCategory::UserFlagFields,
+				size,
+			);
+		}
+		for ((_, sector_id), cells) in self.approach_los.iter() {
+			let size = entry_size::<(FlowFieldMetadata, SectorID), Vec<FieldCell>>()
+				+ cells.capacity() * std::mem::size_of::<FieldCell>();
+			usage.add(*sector_id, MemoryCategory::FlowFields, size);
+		}
+		usage
 	}
 	/// Get a [FlowField] based on the `sector_id` and `goal_id`. Returns
 	/// [None] if the cache doesn't contain a record
@@ -493,23 +1905,112 @@ impl FlowFieldCache {
 		goal_sector_id: SectorID,
 		goal_id: FieldCell,
 	) -> Option<&FlowField> {
-		if current_sector_id == goal_sector_id {
-			let flow_meta = FlowFieldMetadata {
-				sector_id: current_sector_id,
-				goal_id: Some(goal_id),
-				portal_id: None,
-				time_generated: Duration::default(),
-			};
-			self.flows.get(&flow_meta)
-		} else {
-			let flow_meta = FlowFieldMetadata {
-				sector_id: current_sector_id,
-				goal_id: None,
-				portal_id: Some(goal_id),
-				time_generated: Duration::default(),
-			};
-			self.flows.get(&flow_meta)
-		}
+		self.get_field_with_metadata(current_sector_id, goal_sector_id, goal_id)
+			.map(|(_, field)| field)
+	}
+	/// As with [FlowFieldCache::get_field] alongside the metadata of the
+	/// [FlowField] so that its generation time can be inspected
+	pub fn get_field_with_metadata(
+		&self,
+		current_sector_id: SectorID,
+		goal_sector_id: SectorID,
+		goal_id: FieldCell,
+	) -> Option<(&FlowFieldMetadata, &FlowField)> {
+		let flow_meta = FlowFieldMetadata::for_lookup(current_sector_id, goal_sector_id, goal_id);
+		self.flows
+			.get_key_value(&flow_meta)
+			.map(|(meta, field)| (meta, field.as_ref()))
+	}
+	/// As with [FlowFieldCache::get_field] for a [FlowField] built on behalf
+	/// of a `faction`, see [RouteMetadata::with_faction]
+	pub fn get_faction_field(
+		&self,
+		current_sector_id: SectorID,
+		goal_sector_id: SectorID,
+		goal_id: FieldCell,
+		faction: FactionId,
+	) -> Option<&FlowField> {
+		let flow_meta = FlowFieldMetadata::for_lookup(current_sector_id, goal_sector_id, goal_id)
+			.with_faction(faction);
+		self.flows.get(&flow_meta).map(Arc::as_ref)
+	}
+	/// As with [FlowFieldCache::get_field] for a route whose goal sector was
+	/// seeded with additional goals, see [RouteMetadata::with_goal_seeds].
+	/// Only the field of the goal sector differs from an unseeded route
+	pub fn get_seeded_field(
+		&self,
+		current_sector_id: SectorID,
+		goal_sector_id: SectorID,
+		goal_id: FieldCell,
+		goal_seeds: GoalSeeds,
+	) -> Option<&FlowField> {
+		let flow_meta = FlowFieldMetadata::for_lookup(current_sector_id, goal_sector_id, goal_id)
+			.with_goal_seeds(goal_seeds);
+		self.flows.get(&flow_meta).map(Arc::as_ref)
+	}
+	/// Whether the `field_cell` of a sector next to the goal sector has Line
+	/// Of Sight through the portal window to the goal of a cached route, such
+	/// that an actor can move directly to the goal rather than following the
+	/// [FlowField] of the sector. The LOS bits of a transit [FlowField] are
+	/// never set as the field is shared with routes leading to other goals.
+	/// Routes of a `faction` (see [RouteMetadata::with_faction]) have their
+	/// own Line Of Sight
+	pub fn has_approach_line_of_sight(
+		&self,
+		current_sector_id: SectorID,
+		field_cell: FieldCell,
+		goal_sector_id: SectorID,
+		goal_id: FieldCell,
+		faction: Option<FactionId>,
+	) -> bool {
+		let mut goal_meta = FlowFieldMetadata::for_lookup(goal_sector_id, goal_sector_id, goal_id);
+		goal_meta.faction = faction;
+		self.approach_los
+			.get(&(goal_meta, current_sector_id))
+			.is_some_and(|cells| cells.contains(&field_cell))
+	}
+	/// Get the [IntegrationField] a cached [FlowField] was calculated from.
+	/// Returns [None] if the field was inserted without one
+	pub fn get_integration_field(
+		&self,
+		flow_meta: &FlowFieldMetadata,
+	) -> Option<&IntegrationField> {
+		self.integrations
+			.get(flow_meta)
+			.map(|(_, int_field)| int_field)
+	}
+	/// Get the goal [FieldCell]s a cached [FlowField] leads to based on the
+	/// `sector_id` and `goal_id`. For a transit sector these are every cell
+	/// of the portal window an actor may cross into the next sector rather
+	/// than just the portal midpoint. Returns [None] if the field isn't cached
+	/// or was inserted without its [IntegrationField]
+	pub fn get_goal_cells(
+		&self,
+		current_sector_id: SectorID,
+		goal_sector_id: SectorID,
+		goal_id: FieldCell,
+	) -> Option<&Vec<FieldCell>> {
+		let flow_meta = FlowFieldMetadata::for_lookup(current_sector_id, goal_sector_id, goal_id);
+		self.integrations.get(&flow_meta).map(|(goals, _)| goals)
+	}
+	/// Get the goal [FieldCell] of a cached [FlowField] closest to the
+	/// `field_cell` of an actor, so that steering near a boundary can aim for
+	/// the nearest cell of the portal window. See [FlowFieldCache::get_goal_cells]
+	pub fn get_nearest_goal_cell(
+		&self,
+		current_sector_id: SectorID,
+		goal_sector_id: SectorID,
+		goal_id: FieldCell,
+		field_cell: FieldCell,
+	) -> Option<FieldCell> {
+		self.get_goal_cells(current_sector_id, goal_sector_id, goal_id)?
+			.iter()
+			.min_by_key(|goal| {
+				let dx = goal.get_column().abs_diff(field_cell.get_column());
+				let dy = goal.get_row().abs_diff(field_cell.get_row());
+				dx * dx + dy * dy
+			})
+			.copied()
 	}
 	/// Insert a [FlowField] into the cache with a sector-goal ID
 	pub fn insert_field(
@@ -517,26 +2018,803 @@ impl FlowFieldCache {
 		sector_id: SectorID,
 		goal_id: Option<FieldCell>,
 		portal_id: Option<FieldCell>,
-		elapsed_duration: Duration,
+		elapsed_duration: impl Into<Stamp>,
 		field: FlowField,
 	) {
 		let flow_meta = FlowFieldMetadata {
 			sector_id,
 			goal_id,
 			portal_id,
-			time_generated: elapsed_duration,
+			time_generated: elapsed_duration.into(),
+			faction: None,
+			request_id: None,
+			goal_seeds: GoalSeeds::default(),
+		};
+		self.insert_field_with_metadata(flow_meta, field);
+	}
+	/// Insert a [FlowField] into the cache with already created [FlowFieldMetadata]
+	fn insert_field_with_metadata(&mut self, flow_meta: FlowFieldMetadata, field: FlowField) {
+		// without its integration a replacement field cannot be re-validated
+		self.integrations.remove(&flow_meta);
+		self.user_flags.remove(&flow_meta);
+		// metadata ignores the time when compared, remove the old entry so the
+		// replacement's generation time is kept
+		self.flows.remove(&flow_meta);
+		self.flows.insert(flow_meta, Arc::new(field));
+	}
+	/// From an [IntegrationBuilder] which has completed its cost pass
+	/// calculate the [FlowField]s of each sector along the route and insert
+	/// them into the cache
+	pub fn insert_flow_fields(
+		&mut self,
+		int_builder: &IntegrationBuilder,
+		elapsed: impl Into<Stamp>,
+	) {
+		self.insert_flow_fields_batch(std::slice::from_ref(int_builder), elapsed);
+	}
+	/// From several [IntegrationBuilder]s which have completed their cost
+	/// pass calculate the [FlowField]s of every sector along their routes and
+	/// insert them into the cache. With the `parallel` feature the sectors
+	/// are calculated concurrently
+	pub fn insert_flow_fields_batch(
+		&mut self,
+		int_builders: &[IntegrationBuilder],
+		elapsed: impl Into<Stamp>,
+	) {
+		let elapsed = elapsed.into();
+		// each sector only needs its own and the previous integration field so
+		// they can be calculated independently. Fields shared from the cache
+		// already exist and transit sectors leading through the same portal
+		// window of several routes only need calculating once
+		let mut windows = Vec::new();
+		let mut sectors: Vec<(&IntegrationBuilder, usize)> = Vec::new();
+		for int_builder in int_builders.iter() {
+			for (i, (sector_id, goals, _)) in
+				int_builder.get_integration_fields().iter().enumerate()
+			{
+				if int_builder.is_field_skipped(i) {
+					continue;
+				}
+				// a shared field may have since been purged, if so it's recalculated
+				if int_builder.is_field_shared(i)
+					&& self.refresh_field(
+						*sector_id,
+						None,
+						Some(int_builder.get_route().get()[i].1),
+						int_builder.get_faction(),
+						elapsed,
+					) {
+					continue;
+				}
+				if i > 0 {
+					let window = (
+						*sector_id,
+						int_builder.get_route().get()[i].1,
+						int_builder.get_faction(),
+						goals,
+					);
+					if windows.contains(&window) {
+						continue;
+					}
+					windows.push(window);
+				}
+				sectors.push((int_builder, i));
+			}
+		}
+		let spread = self.flow_spread;
+		#[cfg(use_rayon)]
+		let sector_flows: Vec<_> = sectors
+			.par_iter()
+			.map(|(int_builder, i)| calculate_sector_flow_field(int_builder, *i, spread, elapsed))
+			.collect();
+		#[cfg(not(use_rayon))]
+		let sector_flows: Vec<_> = sectors
+			.iter()
+			.map(|(int_builder, i)| calculate_sector_flow_field(int_builder, *i, spread, elapsed))
+			.collect();
+		for (flow_meta, flow_field, goals, int_field) in sector_flows.into_iter().flatten() {
+			self.insert_field_with_metadata(flow_meta, flow_field);
+			if let Some(hook) = self.flag_hook.as_ref() {
+				if let Some(flow_field) = self.flows.get(&flow_meta) {
+					let flags = UserFlagField::from_hook(
+						hook.as_ref(),
+						flow_meta.sector_id,
+						flow_field,
+						int_field,
+					);
+					self.user_flags.insert(flow_meta, flags);
+				}
+			}
+			self.integrations
+				.insert(flow_meta, (goals.clone(), int_field.clone()));
+		}
+		for int_builder in int_builders.iter() {
+			let path = int_builder.get_route().get();
+			if let (Some((goal_sector, goal)), Some((sector_id, _))) = (path.first(), path.get(1)) {
+				if int_builder.get_approach_los().is_empty() {
+					continue;
+				}
+				let mut goal_meta =
+					FlowFieldMetadata::for_lookup(*goal_sector, *goal_sector, *goal);
+				goal_meta.faction = int_builder.get_faction();
+				self.approach_los.insert(
+					(goal_meta, *sector_id),
+					int_builder.get_approach_los().clone(),
+				);
+			}
+		}
+	}
+	/// Remove any Line Of Sight into the sector next to a goal recorded for
+	/// the goal [FlowField] of `flow_meta` or which lies within its sector
+	fn remove_approach_los(&mut self, flow_meta: &FlowFieldMetadata) {
+		self.approach_los.retain(|(goal_meta, sector_id), _| {
+			goal_meta != flow_meta && *sector_id != flow_meta.sector_id
+		});
+	}
+	/// Update the generation time of a cached [FlowField] which has been
+	/// reused by a newer route so that it doesn't expire before the route.
+	/// Returns false if the field isn't cached
+	fn refresh_field(
+		&mut self,
+		sector_id: SectorID,
+		goal_id: Option<FieldCell>,
+		portal_id: Option<FieldCell>,
+		faction: Option<FactionId>,
+		elapsed: Stamp,
+	) -> bool {
+		let mut flow_meta = FlowFieldMetadata {
+			sector_id,
+			goal_id,
+			portal_id,
+			time_generated: elapsed,
+			faction,
+			request_id: None,
+			goal_seeds: GoalSeeds::default(),
+		};
+		// metadata ignores the time when compared so remove the old entry first
+		if let Some((old_meta, field)) = self.flows.remove_entry(&flow_meta) {
+			// the field still belongs to the request it was built for
+			flow_meta.request_id = old_meta.request_id;
+			self.flows.insert(flow_meta, field);
+			if let Some(int_field) = self.integrations.remove(&flow_meta) {
+				self.integrations.insert(flow_meta, int_field);
+			}
+			if let Some(flags) = self.user_flags.remove(&flow_meta) {
+				self.user_flags.insert(flow_meta, flags);
+			}
+			true
+		} else {
+			false
+		}
+	}
+	/// Copy any transit sector [IntegrationField] already cached for the same
+	/// sector and portal window into an [IntegrationBuilder] whose portals
+	/// have been expanded, see [FlowFieldQueue::share_cached_fields]
+	pub fn share_cached_fields_into(&self, int_builder: &mut IntegrationBuilder) {
+		share_fields(&self.flows, &self.integrations, int_builder);
+	}
+	/// Whether every [FlowField] along an actor-to-goal `route` exists in the
+	/// cache
+	pub fn is_route_built(&self, route: &Route) -> bool {
+		let Some((target_sector, _)) = route.get().last() else {
+			return false;
+		};
+		route.get().iter().all(|(sector_id, goal_id)| {
+			self.get_field(*sector_id, *target_sector, *goal_id)
+				.is_some()
+		})
+	}
+	/// As with [FlowFieldCache::is_route_built] except that sectors lying
+	/// within the stopping distance of the route `metadata` don't need a
+	/// [FlowField]
+	pub fn is_route_built_for(
+		&self,
+		metadata: &RouteMetadata,
+		route: &Route,
+		map_dimensions: &MapDimensions,
+	) -> bool {
+		let Some((target_sector, _)) = route.get().last() else {
+			return false;
+		};
+		route.get().iter().all(|(sector_id, goal_id)| {
+			let mut flow_meta = FlowFieldMetadata::for_lookup(*sector_id, *target_sector, *goal_id)
+				.with_goal_seeds(metadata.get_goal_seeds());
+			flow_meta.faction = metadata.get_faction();
+			metadata.is_sector_within_stopping_distance(*sector_id, map_dimensions)
+				|| self.flows.contains_key(&flow_meta)
+		})
+	}
+	/// Follow the cached [FlowField]s of a `route` from the `source` sector
+	/// and [FieldCell] until the goal is reached (or a cell with line of sight
+	/// to the goal) producing the list of cells visited. When `simplify` is
+	/// true only the source, the cells where the direction of travel changes
+	/// and the goal are kept. The fields of a route searched on behalf of a
+	/// `faction` are followed when one is given.
+	///
+	/// Returns [None] if a [FlowField] along the route hasn't been built or
+	/// the fields don't lead to the goal
+	pub fn trace_route(
+		&self,
+		route: &Route,
+		source: (SectorID, FieldCell),
+		map_dimensions: &MapDimensions,
+		simplify: bool,
+		faction: Option<FactionId>,
+	) -> Option<Vec<(SectorID, FieldCell)>> {
+		let (target_sector, target_goal) = *route.get().last()?;
+		let mut cells = vec![source];
+		let mut previous_ordinal = None;
+		let mut current = source;
+		let mut route_index = 0;
+		// every cell can only be visited once on the way to the goal
+		let max_steps = map_dimensions.get_total_field_cell_columns()
+			* map_dimensions.get_total_field_cell_rows();
+		for _ in 0..max_steps {
+			if current == (target_sector, target_goal) {
+				return Some(cells);
+			}
+			// the same sector may appear in the route more than once, always
+			// use the next entry of the sector the walk is in
+			route_index += route.get()[route_index..]
+				.iter()
+				.position(|(sector_id, _)| *sector_id == current.0)?;
+			let goal_id = route.get()[route_index].1;
+			let field = match faction {
+				Some(faction) => {
+					self.get_faction_field(current.0, target_sector, goal_id, faction)?
+				}
+				None => self.get_field(current.0, target_sector, goal_id)?,
+			};
+			let value = field.get_field_cell_value(current.1);
+			if has_line_of_sight(value)
+				|| self.has_approach_line_of_sight(
+					current.0,
+					current.1,
+					target_sector,
+					target_goal,
+					faction,
+				) {
+				cells.push((target_sector, target_goal));
+				return Some(cells);
+			}
+			let ordinal = get_ordinal_from_bits(value);
+			let (column, row) = map_dimensions.global_cell_from(current.0, current.1);
+			let (column, row) = match ordinal {
+				Ordinal::North => (Some(column), row.checked_sub(1)),
+				Ordinal::East => (Some(column + 1), Some(row)),
+				Ordinal::South => (Some(column), Some(row + 1)),
+				Ordinal::West => (column.checked_sub(1), Some(row)),
+				Ordinal::NorthEast => (Some(column + 1), row.checked_sub(1)),
+				Ordinal::SouthEast => (Some(column + 1), Some(row + 1)),
+				Ordinal::SouthWest => (column.checked_sub(1), Some(row + 1)),
+				Ordinal::NorthWest => (column.checked_sub(1), row.checked_sub(1)),
+				Ordinal::Zero => return None,
+			};
+			current = map_dimensions.sector_and_cell_from_global(column?, row?)?;
+			if simplify && previous_ordinal == Some(ordinal) {
+				// continuing in a straight line, replace the last point
+				cells.pop();
+			}
+			cells.push(current);
+			previous_ordinal = Some(ordinal);
+		}
+		None
+	}
+	/// Follow the cached [FlowField]s of a `route` from the `source` sector
+	/// and [FieldCell] to produce a list of 2d waypoints, useful for actors
+	/// which move along a polyline rather than reading the fields. See
+	/// [FlowFieldCache::trace_route]
+	#[cfg(feature = "2d")]
+	pub fn get_waypoints_2d(
+		&self,
+		route: &Route,
+		source: (SectorID, FieldCell),
+		map_dimensions: &MapDimensions,
+		simplify: bool,
+		faction: Option<FactionId>,
+	) -> Option<Vec<Vec2>> {
+		self.trace_route(route, source, map_dimensions, simplify, faction)?
+			.iter()
+			.map(|(sector_id, field_cell)| {
+				map_dimensions.get_xy_from_field_sector(*sector_id, *field_cell)
+			})
+			.collect()
+	}
+	/// Follow the cached [FlowField]s of a `route` from the `source` sector
+	/// and [FieldCell] to produce a list of 3d waypoints across the x-z plane,
+	/// useful for actors which move along a polyline rather than reading the
+	/// fields. See [FlowFieldCache::trace_route]
+	#[cfg(feature = "3d")]
+	pub fn get_waypoints_3d(
+		&self,
+		route: &Route,
+		source: (SectorID, FieldCell),
+		map_dimensions: &MapDimensions,
+		simplify: bool,
+		faction: Option<FactionId>,
+	) -> Option<Vec<Vec3>> {
+		self.trace_route(route, source, map_dimensions, simplify, faction)?
+			.iter()
+			.map(|(sector_id, field_cell)| {
+				map_dimensions.get_xyz_from_field_sector(*sector_id, *field_cell)
+			})
+			.collect()
+	}
+	/// Update the generation time of every cached [FlowField] along an
+	/// actor-to-goal `route` so that they don't expire, fields which aren't
+	/// cached are ignored
+	pub fn refresh_route_fields(&mut self, route: &Route, elapsed: impl Into<Stamp>) {
+		let elapsed = elapsed.into();
+		let Some((target_sector, _)) = route.get().last() else {
+			return;
+		};
+		for (sector_id, goal_id) in route.get().iter() {
+			if sector_id == target_sector {
+				self.refresh_field(*sector_id, Some(*goal_id), None, None, elapsed);
+			} else {
+				self.refresh_field(*sector_id, None, Some(*goal_id), None, elapsed);
+			}
+		}
+	}
+	/// Get the metadata of every cached [FlowField] within a sector. Fields
+	/// are ordered by their sector first so only the entries of the sector
+	/// are visited
+	pub fn get_sector_field_metadata(&self, sector_id: SectorID) -> Vec<FlowFieldMetadata> {
+		let first = FlowFieldMetadata {
+			sector_id,
+			goal_id: None,
+			portal_id: None,
+			time_generated: Stamp::default(),
+			faction: None,
+			request_id: None,
+			goal_seeds: GoalSeeds::default(),
 		};
-		self.flows.insert(flow_meta, field);
+		self.flows
+			.range(first..)
+			.map(|(metadata, _)| *metadata)
+			.take_while(|metadata| metadata.sector_id == sector_id)
+			.collect()
 	}
 	/// Remove a [FlowField] from the cache (when it needs regenerating from a
 	/// [CostField] update)
 	pub fn remove_field(&mut self, flow_meta: FlowFieldMetadata) {
 		self.flows.remove(&flow_meta);
+		self.integrations.remove(&flow_meta);
+		self.user_flags.remove(&flow_meta);
+		self.remove_approach_los(&flow_meta);
+	}
+	/// Remove every [FlowField] from the cache, its configuration (such as
+	/// the revalidation tolerance) is kept
+	pub fn clear(&mut self) {
+		self.flows.clear();
+		self.integrations.clear();
+		self.user_flags.clear();
+		self.approach_los.clear();
+	}
+	/// Whether a cached [FlowField] is unaffected by the `changed_cells` of
+	/// the `cost_field` of its sector (within the revalidation tolerance).
+	/// Fields inserted without their [IntegrationField] are never considered
+	/// valid
+	pub fn is_field_still_valid(
+		&self,
+		flow_meta: &FlowFieldMetadata,
+		cost_field: &CostField,
+		changed_cells: &[FieldCell],
+	) -> bool {
+		self.integrations
+			.get(flow_meta)
+			.is_some_and(|(_, int_field)| {
+				int_field.is_valid_after_change(
+					cost_field,
+					changed_cells,
+					self.revalidation_tolerance,
+				)
+			})
+	}
+}
+
+/// Routes whose [IntegrationField]s and [FlowField]s are being incrementally
+/// built. Each route is removed once its fields are complete and the
+/// [FlowField]s are inserted into the [FlowFieldCache], so that the steps of
+/// the pipeline building the fields only write to the queue
+#[derive(Component, Default, Clone, Reflect)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct FlowFieldQueue {
+	/// Routes describing the sector path and [IntegrationField]s where the
+	/// integration and flow fields can be incrementally built
+	queue: BTreeMap<RouteMetadata, IntegrationBuilder>,
+	/// Average number of queued routes completed per frame while the queue
+	/// has work
+	#[cfg_attr(feature = "serde", serde(default))]
+	throughput: f32,
+	/// Which queued routes are prioritised based on where their actors are
+	#[cfg_attr(feature = "serde", serde(default))]
+	prefetch_policy: PrefetchPolicy,
+	/// The latest sector and [FieldCell] of an actor following each queued
+	/// route
+	#[cfg_attr(feature = "serde", serde(default))]
+	position_hints: BTreeMap<RouteMetadata, (SectorID, FieldCell)>,
+	/// The requests served by each route (including the request which
+	/// queued it), they're ready once the route is no longer queued
+	#[cfg_attr(feature = "serde", serde(default))]
+	waiting: BTreeMap<RouteMetadata, Vec<RouteMetadata>>,
+}
+
+impl FlowFieldQueue {
+	/// Get a reference to the queue map
+	pub fn get_queue(&self) -> &BTreeMap<RouteMetadata, IntegrationBuilder> {
+		&self.queue
 	}
-	/// Remove a [RouteMetadata] from the cache integration queue (when it
-	/// needs regenerating from a [CostField] update)
+	/// Get a mutable reference to the queue map
+	pub fn get_queue_mut(&mut self) -> &mut BTreeMap<RouteMetadata, IntegrationBuilder> {
+		&mut self.queue
+	}
+	/// Estimate the bytes of the fields of queued routes built so far held
+	/// for each sector
+	pub fn memory_usage(&self) -> MemoryUsage {
+		let mut usage = MemoryUsage::new();
+		for builder in self.queue.values() {
+			builder.record_memory_usage(&mut usage);
+		}
+		usage
+	}
+	/// Raise the [PathPriority] of a queued route to `priority`, such as when
+	/// a more urgent request follows it, a route which already has the same
+	/// or a higher priority is left alone
+	pub fn raise_priority(&mut self, metadata: &RouteMetadata, priority: PathPriority) {
+		// metadata ignores the priority when compared so the entry is
+		// replaced to update its key
+		if let Some((queued, int_builder)) = self.queue.remove_entry(metadata) {
+			let raised = queued.with_priority(queued.get_priority().max(priority));
+			self.queue.insert(raised, int_builder);
+		}
+	}
+	/// Get the metadata of the queued route which should be built next. Routes
+	/// with an actor about to cross into a sector whose [FlowField] isn't
+	/// in the `flow_cache` come first (see [FlowFieldQueue::is_prefetch_due]),
+	/// otherwise this is the route with the highest [PathPriority] after
+	/// aging based on the `elapsed` time. Routes whose [IntegrationField]s
+	/// are already built are skipped.
+	///
+	/// Routes stamped with a [Stamp::Turn] don't age, so when playing
+	/// turn-based a lower priority route is only built once no route of a
+	/// higher priority is queued and a long queue of higher priority work
+	/// can starve it for the whole turn
+	pub fn get_next_queued(
+		&self,
+		elapsed: impl Into<Stamp>,
+		flow_cache: &FlowFieldCache,
+	) -> Option<RouteMetadata> {
+		self.get_next_queued_batch(elapsed, flow_cache, 1)
+			.into_iter()
+			.next()
+	}
+	/// Get the metadata of up to `count` queued routes in the order they
+	/// should be built, see [FlowFieldQueue::get_next_queued]. Routes of
+	/// the same rank are ordered by their metadata. Used to pick the routes
+	/// built in a frame before building them at once with the `parallel`
+	/// feature
+	pub fn get_next_queued_batch(
+		&self,
+		elapsed: impl Into<Stamp>,
+		flow_cache: &FlowFieldCache,
+		count: usize,
+	) -> Vec<RouteMetadata> {
+		let elapsed = elapsed.into();
+		let mut ranked: Vec<((bool, u128), RouteMetadata)> = self
+			.queue
+			.iter()
+			.filter(|(_, int_builder)| !int_builder.has_cost_pass())
+			.map(|(metadata, _)| {
+				(
+					self.get_queue_rank(metadata, elapsed, flow_cache),
+					*metadata,
+				)
+			})
+			.collect();
+		// stable so that ties keep the order of the queue
+		ranked.sort_by(|(a, _), (b, _)| b.cmp(a));
+		ranked
+			.into_iter()
+			.take(count)
+			.map(|(_, metadata)| metadata)
+			.collect()
+	}
+	/// Get how many queued routes rank at least as highly as `metadata` based
+	/// on the `elapsed` time and so are likely to be built before it
+	pub fn count_queued_ahead(
+		&self,
+		metadata: &RouteMetadata,
+		elapsed: impl Into<Stamp>,
+		flow_cache: &FlowFieldCache,
+	) -> usize {
+		let elapsed = elapsed.into();
+		let rank = self.get_queue_rank(metadata, elapsed, flow_cache);
+		self.queue
+			.keys()
+			.filter(|queued| {
+				*queued != metadata && self.get_queue_rank(queued, elapsed, flow_cache) >= rank
+			})
+			.count()
+	}
+	/// Rank of a queued route, routes due a prefetch outrank all others
+	fn get_queue_rank(
+		&self,
+		metadata: &RouteMetadata,
+		elapsed: Stamp,
+		flow_cache: &FlowFieldCache,
+	) -> (bool, u128) {
+		(
+			self.is_prefetch_due(metadata, flow_cache),
+			metadata.get_aged_rank(elapsed),
+		)
+	}
+	/// Set which queued routes are prioritised based on the positions of
+	/// their actors
+	pub fn with_prefetch_policy(mut self, policy: PrefetchPolicy) -> Self {
+		self.prefetch_policy = policy;
+		self
+	}
+	/// Get the [PrefetchPolicy] of the queue
+	pub fn get_prefetch_policy(&self) -> PrefetchPolicy {
+		self.prefetch_policy
+	}
+	/// Replace the [PrefetchPolicy] of the queue
+	pub fn set_prefetch_policy(&mut self, policy: PrefetchPolicy) {
+		self.prefetch_policy = policy;
+	}
+	/// Record that an actor following the queued route of `metadata` is at
+	/// `field_cell` of `sector_id`. Once the actor approaches the exit of its
+	/// sector the route is built ahead of the rest of the queue if the
+	/// [FlowField]s of the sectors it's about to enter aren't ready, avoiding
+	/// a stall at the boundary. Hints for routes which aren't queued are
+	/// ignored
+	pub fn hint_actor_position(
+		&mut self,
+		metadata: RouteMetadata,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+	) {
+		let queue = &self.queue;
+		self.position_hints
+			.retain(|hinted, _| queue.contains_key(hinted));
+		if self.queue.contains_key(&metadata) {
+			self.position_hints
+				.insert(metadata, (sector_id, field_cell));
+		}
+	}
+	/// Whether an actor of the queued route of `metadata` is within the
+	/// boundary distance of the [PrefetchPolicy] from the exit of its sector
+	/// and the [FlowField] of any of the next sectors along the route isn't
+	/// in the `flow_cache`
+	pub fn is_prefetch_due(&self, metadata: &RouteMetadata, flow_cache: &FlowFieldCache) -> bool {
+		let (Some((sector_id, field_cell)), Some(int_builder)) =
+			(self.position_hints.get(metadata), self.queue.get(metadata))
+		else {
+			return false;
+		};
+		// the route is ordered from the goal back to the actor
+		let path = int_builder.get_route().get();
+		let Some(index) = path.iter().rposition(|(sector, _)| sector == sector_id) else {
+			return false;
+		};
+		// there's no boundary left to cross in the goal sector
+		if index == 0 {
+			return false;
+		}
+		let exit = path[index].1;
+		let distance = exit
+			.get_column()
+			.abs_diff(field_cell.get_column())
+			.max(exit.get_row().abs_diff(field_cell.get_row()));
+		if distance > self.prefetch_policy.boundary_distance {
+			return false;
+		}
+		let goal_sector = path[0].0;
+		let first_ahead = index.saturating_sub(self.prefetch_policy.sectors_ahead);
+		path[first_ahead..index]
+			.iter()
+			.any(|(sector, goal)| flow_cache.get_field(*sector, goal_sector, *goal).is_none())
+	}
+	/// Record how many queued routes had their [FlowField]s `completed`
+	/// during a frame in which the queue had work, updating the average
+	/// throughput of the queue
+	pub fn record_throughput(&mut self, completed: usize) {
+		self.throughput += (completed as f32 - self.throughput) * THROUGHPUT_SMOOTHING;
+	}
+	/// Get the average number of queued routes completed per frame while the
+	/// queue has work, `0.0` if nothing has been built yet
+	pub fn get_throughput(&self) -> f32 {
+		self.throughput
+	}
+	/// Estimate how many frames it will take for a route with `position`
+	/// routes queued ahead of it to be built based on the recent throughput.
+	/// Returns [None] if nothing has been built yet to estimate from
+	pub fn estimate_frames_to_ready(&self, position: usize) -> Option<u32> {
+		if self.throughput <= 0.0 {
+			return None;
+		}
+		Some(((position + 1) as f32 / self.throughput).ceil() as u32)
+	}
+	/// Insert a route into the queue to be built for actors restricted to
+	/// the `connectivity`. Sectors within the stopping distance of the route
+	/// are skipped
+	pub fn add_to_queue(
+		&mut self,
+		metadata: RouteMetadata,
+		path: Route,
+		cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+		connectivity: Connectivity,
+	) {
+		let mut int_builder =
+			IntegrationBuilder::new(path, cost_fields).with_connectivity(connectivity);
+		if let Some(faction) = metadata.get_faction() {
+			int_builder = int_builder.with_faction(faction);
+		}
+		if let Some(request_id) = metadata.get_request_id() {
+			int_builder = int_builder.with_request_id(request_id);
+		}
+		int_builder = int_builder.with_goal_seeds(metadata.get_goal_seeds());
+		int_builder.skip_fields_within_stopping_distance(&metadata, map_dimensions);
+		self.queue.insert(metadata, int_builder);
+	}
+	/// For each queued [IntegrationBuilder] which has expanded its portals
+	/// but not yet performed its cost pass copy in any transit sector
+	/// [IntegrationField] which is already in the `flow_cache` for the same
+	/// sector and portal window, the cost pass and [FlowField] calculation of
+	/// those sectors is then skipped. Routes through a chokepoint toward the
+	/// same exit portal share the fields rather than rebuilding them
+	pub fn share_cached_fields(&mut self, flow_cache: &FlowFieldCache) {
+		for int_builder in self.queue.values_mut() {
+			if !int_builder.has_expanded_portals() || int_builder.has_cost_pass() {
+				continue;
+			}
+			flow_cache.share_cached_fields_into(int_builder);
+		}
+	}
+	/// Remove a [RouteMetadata] from the queue (when it needs regenerating
+	/// from a [CostField] update)
 	pub fn remove_queue_item(&mut self, route_meta: RouteMetadata) {
 		self.queue.remove(&route_meta);
+		// the requests are repeated and wait on their new route instead
+		self.waiting.remove(&route_meta);
+	}
+	/// Record that the `request` is served by the route of `metadata`, such
+	/// as the request which queued the route, a request following it or a
+	/// request answered by the route once it was cached. The request is
+	/// ready once the route isn't queued, see [FlowFieldQueue::take_ready]
+	pub fn add_waiting(&mut self, metadata: RouteMetadata, request: RouteMetadata) {
+		let requests = self.waiting.entry(metadata).or_default();
+		let is_waiting = requests.iter().any(|waiting| {
+			waiting.get_request_id() == request.get_request_id() && *waiting == request
+		});
+		if !is_waiting {
+			requests.push(request);
+		}
+	}
+	/// Whether any request is waiting on a route
+	pub fn has_waiting(&self) -> bool {
+		!self.waiting.is_empty()
+	}
+	/// Remove and return the requests waiting on a route which is no longer
+	/// queued, i.e whose [FlowField]s have been built
+	pub fn take_ready(&mut self) -> Vec<RouteMetadata> {
+		let queue = &self.queue;
+		let built: Vec<RouteMetadata> = self
+			.waiting
+			.keys()
+			.filter(|metadata| !queue.contains_key(*metadata))
+			.copied()
+			.collect();
+		built
+			.iter()
+			.flat_map(|metadata| self.waiting.remove(metadata).unwrap_or_default())
+			.collect()
+	}
+	/// Remove every queued route, the [PrefetchPolicy] is kept
+	pub fn clear(&mut self) {
+		self.queue.clear();
+		self.position_hints.clear();
+		self.waiting.clear();
+	}
+}
+
+/// Copy any cached transit sector [IntegrationField] with the same sector
+/// and portal window as those of the `int_builder` into it
+fn share_fields(
+	flows: &BTreeMap<FlowFieldMetadata, Arc<FlowField>>,
+	integrations: &BTreeMap<FlowFieldMetadata, (Vec<FieldCell>, IntegrationField)>,
+	int_builder: &mut IntegrationBuilder,
+) {
+	let mut shareable = Vec::new();
+	for (i, (sector_id, goals, _)) in int_builder
+		.get_integration_fields()
+		.iter()
+		.enumerate()
+		.skip(1)
+	{
+		let flow_meta = FlowFieldMetadata {
+			sector_id: *sector_id,
+			goal_id: None,
+			portal_id: Some(int_builder.get_route().get()[i].1),
+			time_generated: Stamp::default(),
+			faction: int_builder.get_faction(),
+			request_id: int_builder.get_request_id(),
+			goal_seeds: GoalSeeds::default(),
+		};
+		if let Some((window, int_field)) = integrations.get(&flow_meta) {
+			if window == goals && flows.contains_key(&flow_meta) {
+				shareable.push((i, int_field.clone()));
+			}
+		}
+	}
+	for (i, int_field) in shareable {
+		int_builder.share_field(i, int_field);
+	}
+}
+
+/// Calculate the [FlowField] of the sector at position `i` of the route
+/// within an [IntegrationBuilder]
+fn calculate_sector_flow_field(
+	int_builder: &IntegrationBuilder,
+	i: usize,
+	spread: u32,
+	elapsed: Stamp,
+) -> Option<(
+	FlowFieldMetadata,
+	FlowField,
+	&Vec<FieldCell>,
+	&IntegrationField,
+)> {
+	let sector_int_fields = int_builder.get_integration_fields();
+	let path = int_builder.get_route().get();
+	let (sector_id, goals, int_field) = &sector_int_fields[i];
+	#[cfg(feature = "trace")]
+	let _span = bevy::log::info_span!("build_flow_field", sector = ?sector_id).entered();
+	let connectivity = int_builder.get_connectivity();
+	let one_way = int_builder.get_one_way_field(i);
+	let mut flow_field = FlowField::default();
+	// first element is end target, therefore has no info about previous sector for
+	// direction optimisations
+	if i == 0 {
+		flow_field.calculate_with_spread(goals, None, int_field, connectivity, one_way, spread);
+		let flow_meta = FlowFieldMetadata {
+			sector_id: *sector_id,
+			goal_id: Some(path[i].1),
+			portal_id: None,
+			time_generated: elapsed,
+			faction: int_builder.get_faction(),
+			request_id: int_builder.get_request_id(),
+			goal_seeds: int_builder.get_goal_seeds(),
+		};
+		Some((flow_meta, flow_field, goals, int_field))
+	} else if let Some(dir_prev_sector) =
+		Ordinal::sector_to_sector_direction(sector_int_fields[i - 1].0, *sector_id)
+	{
+		let prev_int_field = &sector_int_fields[i - 1].2;
+		flow_field.calculate_with_spread(
+			goals,
+			Some((dir_prev_sector, prev_int_field)),
+			int_field,
+			connectivity,
+			one_way,
+			spread,
+		);
+		let flow_meta = FlowFieldMetadata {
+			sector_id: *sector_id,
+			goal_id: None,
+			portal_id: Some(path[i].1),
+			time_generated: elapsed,
+			faction: int_builder.get_faction(),
+			request_id: int_builder.get_request_id(),
+			goal_seeds: GoalSeeds::default(),
+		};
+		Some((flow_meta, flow_field, goals, int_field))
+	} else {
+		error!("Route from goal to actor {:?}", path);
+		None
 	}
 }
 
@@ -544,6 +2822,811 @@ impl FlowFieldCache {
 mod tests {
 	use super::*;
 	#[test]
+	fn priority_aging() {
+		let fresh = PathPriority::Background.get_aged_rank(Duration::ZERO);
+		assert!(fresh < PathPriority::Normal.get_aged_rank(Duration::ZERO));
+		let aged = PathPriority::Background.get_aged_rank(PRIORITY_AGING_INTERVAL * 2);
+		assert_eq!(
+			aged,
+			PathPriority::PlayerCritical.get_aged_rank(Duration::ZERO)
+		);
+	}
+	#[test]
+	fn stopping_distance() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let metadata = RouteMetadata::new(
+			SectorID::new(2, 2),
+			FieldCell::new(0, 0),
+			SectorID::new(1, 1),
+			FieldCell::new(4, 4),
+			Duration::ZERO,
+		);
+		assert!(!metadata.is_within_stopping_distance(Vec3::ZERO));
+		assert!(!metadata.is_sector_within_stopping_distance(SectorID::new(1, 1), &map_dimensions));
+		let metadata = metadata.with_stopping_distance(Vec3::ZERO, 8.0);
+		assert!(metadata.is_within_stopping_distance(Vec3::new(0.0, 0.0, 8.0)));
+		assert!(!metadata.is_within_stopping_distance(Vec3::new(6.0, 0.0, 6.0)));
+		// the furthest corner of the goal sector is 6.36 from the centre of the goal
+		assert!(metadata.is_sector_within_stopping_distance(SectorID::new(1, 1), &map_dimensions));
+		assert!(!metadata.is_sector_within_stopping_distance(SectorID::new(2, 1), &map_dimensions));
+	}
+	#[test]
+	fn route_corridor() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let route = Route::new(vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 4)),
+			(SectorID::new(1, 0), FieldCell::new(4, 4)),
+		]);
+		let corridor = route.corridor(3, &cost_fields);
+		assert_eq!(2, corridor.len());
+		// the whole eastern boundary is the exit window, widened by one cell
+		let start = corridor.get(&SectorID::new(0, 0)).unwrap();
+		assert_eq!(20, start.len());
+		assert!(start.contains(&FieldCell::new(8, 0)));
+		// entry window along the west plus the line of sight to the goal
+		let goal = corridor.get(&SectorID::new(1, 0)).unwrap();
+		assert!(goal.contains(&FieldCell::new(1, 9)));
+		assert!(goal.contains(&FieldCell::new(5, 5)));
+		assert!(!goal.contains(&FieldCell::new(6, 4)));
+		assert!(!goal.contains(&FieldCell::new(4, 7)));
+		// a wall blocking line of sight is walked around
+		let sector_id = SectorID::new(1, 0);
+		for row in 2..=6 {
+			cost_fields.set_field_cell_value(
+				sector_id,
+				255,
+				FieldCell::new(2, row),
+				&map_dimensions,
+			);
+		}
+		let goal = route.corridor(1, &cost_fields);
+		let goal = goal.get(&sector_id).unwrap();
+		assert!(goal.contains(&FieldCell::new(4, 4)));
+		assert!(goal.contains(&FieldCell::new(2, 7)) || goal.contains(&FieldCell::new(2, 1)));
+		let cost_field = cost_fields.get_scaled().get(&sector_id).unwrap();
+		assert!(goal
+			.iter()
+			.all(|cell| cost_field.get_field_cell_value(*cell) != 255));
+	}
+	#[test]
+	fn route_waypoint_costs() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let source = (SectorID::new(0, 0), FieldCell::new(2, 5));
+		let route = Route::new(vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 5)),
+			(SectorID::new(1, 0), FieldCell::new(5, 5)),
+		]);
+		// 7 steps to the portal, 1 across the boundary and 5 to the goal
+		assert_eq!(
+			Some(vec![7, 13]),
+			route.get_waypoint_costs(source, &cost_fields)
+		);
+		// expensive terrain on the second leg
+		cost_fields.set_field_cell_value(
+			SectorID::new(1, 0),
+			10,
+			FieldCell::new(3, 5),
+			&map_dimensions,
+		);
+		let costs = route.get_waypoint_costs(source, &cost_fields).unwrap();
+		assert_eq!(7, costs[0]);
+		assert!(costs[1] > 13);
+		// the route must start where the actor is
+		let elsewhere = (SectorID::new(2, 2), FieldCell::new(0, 0));
+		assert!(route.get_waypoint_costs(elsewhere, &cost_fields).is_none());
+	}
+	#[test]
+	fn route_waypoint_times() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let source = (SectorID::new(0, 0), FieldCell::new(2, 5));
+		let route = Route::new(vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 5)),
+			(SectorID::new(1, 0), FieldCell::new(5, 5)),
+		]);
+		// costs of 7 and 13 with cells 1 unit across
+		let infantry = TraversalSpeed::new(2.0);
+		let cavalry = TraversalSpeed::new(4.0);
+		let times = route
+			.get_waypoint_times(source, infantry, &cost_fields, &map_dimensions)
+			.unwrap();
+		assert_eq!(
+			vec![Duration::from_secs_f32(3.5), Duration::from_secs_f32(6.5)],
+			times
+		);
+		let times = route
+			.get_waypoint_times(source, cavalry, &cost_fields, &map_dimensions)
+			.unwrap();
+		assert_eq!(Duration::from_secs_f32(3.25), times[1]);
+		// a budget of 5 seconds
+		let budget = Duration::from_secs(5);
+		assert_eq!(10, infantry.time_to_cost(budget, &map_dimensions));
+		assert_eq!(20, cavalry.time_to_cost(budget, &map_dimensions));
+		// 10 units from a goal which is stopped at from 2 units away
+		let metadata = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(0, 0),
+			SectorID::new(1, 0),
+			FieldCell::new(0, 0),
+			Duration::ZERO,
+		);
+		assert!(metadata
+			.get_time_to_stopping_distance(Vec3::ZERO, infantry)
+			.is_none());
+		let metadata = metadata.with_stopping_distance(Vec3::new(10.0, 0.0, 0.0), 2.0);
+		assert_eq!(
+			Some(Duration::from_secs(4)),
+			metadata.get_time_to_stopping_distance(Vec3::ZERO, infantry)
+		);
+		assert_eq!(
+			Some(Duration::ZERO),
+			metadata.get_time_to_stopping_distance(Vec3::new(9.0, 0.0, 0.0), infantry)
+		);
+	}
+	#[test]
+	fn route_splice() {
+		let route = Route::new(vec![
+			(SectorID::new(2, 0), FieldCell::new(0, 4)),
+			(SectorID::new(1, 0), FieldCell::new(0, 4)),
+			(SectorID::new(0, 0), FieldCell::new(4, 4)),
+		]);
+		let spliced = route
+			.splice_from(SectorID::new(1, 0), FieldCell::new(7, 2))
+			.unwrap();
+		assert_eq!(&route.get()[1..], spliced.get().as_slice());
+		assert!(route
+			.splice_from(SectorID::new(2, 2), FieldCell::new(0, 0))
+			.is_none());
+		// a route passing through a sector twice uses the closest pass
+		let route = Route::new(vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 1)),
+			(SectorID::new(1, 0), FieldCell::new(4, 9)),
+			(SectorID::new(1, 1), FieldCell::new(0, 4)),
+			(SectorID::new(0, 1), FieldCell::new(4, 0)),
+			(SectorID::new(0, 0), FieldCell::new(4, 8)),
+		]);
+		let spliced = route
+			.splice_from(SectorID::new(0, 0), FieldCell::new(8, 1))
+			.unwrap();
+		assert_eq!(5, spliced.get().len());
+		let spliced = route
+			.splice_from(SectorID::new(0, 0), FieldCell::new(3, 7))
+			.unwrap();
+		assert_eq!(1, spliced.get().len());
+	}
+	#[test]
+	fn sector_field_metadata() {
+		let mut cache = FlowFieldCache::default();
+		let field = FlowField::default();
+		let goal = FieldCell::new(4, 4);
+		cache.insert_field(
+			SectorID::new(0, 0),
+			Some(goal),
+			None,
+			Duration::ZERO,
+			field.clone(),
+		);
+		cache.insert_field(
+			SectorID::new(1, 0),
+			None,
+			Some(goal),
+			Duration::ZERO,
+			field.clone(),
+		);
+		cache.insert_field(
+			SectorID::new(1, 0),
+			Some(goal),
+			None,
+			Duration::ZERO,
+			field.clone(),
+		);
+		cache.insert_field(SectorID::new(2, 0), Some(goal), None, Duration::ZERO, field);
+		let metadata = cache.get_sector_field_metadata(SectorID::new(1, 0));
+		assert_eq!(2, metadata.len());
+		assert!(metadata
+			.iter()
+			.all(|m| m.get_sector_id() == SectorID::new(1, 0)));
+		assert!(cache
+			.get_sector_field_metadata(SectorID::new(2, 2))
+			.is_empty());
+	}
+	#[test]
+	fn flow_cache_memory_usage() {
+		let mut cache = FlowFieldCache::default();
+		let goal = FieldCell::new(4, 4);
+		for sector_id in [SectorID::new(0, 0), SectorID::new(1, 0)] {
+			cache.insert_field(
+				sector_id,
+				Some(goal),
+				None,
+				Duration::ZERO,
+				FlowField::default(),
+			);
+		}
+		let usage = cache.memory_usage();
+		let field_size =
+			std::mem::size_of::<FlowFieldMetadata>() + std::mem::size_of::<FlowField>();
+		assert_eq!(field_size, usage.get_sector_total(SectorID::new(1, 0)));
+		assert_eq!(
+			2 * field_size,
+			usage.get_category_total(MemoryCategory::FlowFields)
+		);
+		assert_eq!(0, usage.get_category_total(MemoryCategory::QueuedRoutes));
+	}
+	#[test]
+	fn route_cache_sector_index() {
+		let mut cache = RouteCache::default();
+		let goal = FieldCell::new(4, 4);
+		let metadata = RouteMetadata::new(
+			SectorID::new(2, 0),
+			FieldCell::new(9, 4),
+			SectorID::new(0, 0),
+			goal,
+			Duration::ZERO,
+		);
+		let route = Route::new(vec![
+			(SectorID::new(2, 0), FieldCell::new(0, 4)),
+			(SectorID::new(1, 0), FieldCell::new(0, 4)),
+			(SectorID::new(0, 0), goal),
+		]);
+		cache.insert_route_with_metadata(metadata, route);
+		assert_eq!(
+			vec![metadata],
+			cache.get_routes_using_sector(SectorID::new(1, 0))
+		);
+		// a replacement route through a different sector moves the entry
+		let detour = Route::new(vec![
+			(SectorID::new(2, 0), FieldCell::new(4, 9)),
+			(SectorID::new(2, 1), FieldCell::new(0, 4)),
+			(SectorID::new(1, 1), FieldCell::new(4, 0)),
+			(SectorID::new(0, 0), goal),
+		]);
+		cache.insert_route_with_metadata(metadata, detour);
+		assert!(cache
+			.get_routes_using_sector(SectorID::new(1, 0))
+			.is_empty());
+		assert_eq!(1, cache.get_routes_using_sector(SectorID::new(1, 1)).len());
+		// refreshing keeps the latest generation time
+		let elapsed = Duration::from_secs(5);
+		cache.refresh_route(metadata, elapsed);
+		let indexed = cache.get_routes_using_sector(SectorID::new(0, 0));
+		assert_eq!(Stamp::Time(elapsed), indexed[0].get_time_generated());
+		cache.remove_route(metadata);
+		assert!(cache
+			.get_routes_using_sector(SectorID::new(0, 0))
+			.is_empty());
+	}
+	#[test]
+	fn route_cache_separates_players() {
+		let mut cache = RouteCache::default();
+		let source = (SectorID::new(0, 0), FieldCell::new(5, 5));
+		let target = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let metadata = |player| {
+			RouteMetadata::new(source.0, source.1, target.0, target.1, Duration::ZERO)
+				.with_player(player)
+		};
+		let (first, second) = (PlayerId::new(1), PlayerId::new(2));
+		assert_ne!(metadata(first), metadata(second));
+		// each player has explored a different way to the target
+		let first_route = Route::new(vec![
+			(source.0, FieldCell::new(9, 5)),
+			(SectorID::new(1, 0), FieldCell::new(9, 5)),
+			target,
+		]);
+		let second_route = Route::new(vec![
+			(source.0, FieldCell::new(5, 9)),
+			(SectorID::new(0, 1), FieldCell::new(9, 5)),
+			(SectorID::new(1, 1), FieldCell::new(9, 0)),
+			(SectorID::new(2, 1), FieldCell::new(4, 0)),
+			target,
+		]);
+		cache.insert_route_with_metadata(metadata(first), first_route.clone());
+		cache.insert_route_with_metadata(metadata(second), second_route.clone());
+		assert_eq!(2, cache.len());
+		assert_eq!(
+			first_route.get(),
+			cache
+				.get_player_route(source.0, source.1, target.0, target.1, first)
+				.unwrap()
+				.get()
+		);
+		assert_eq!(
+			second_route.get(),
+			cache
+				.get_player_route(source.0, source.1, target.0, target.1, second)
+				.unwrap()
+				.get()
+		);
+		// nor is either route used by a request without a player
+		assert!(cache
+			.get_route(source.0, source.1, target.0, target.1)
+			.is_none());
+	}
+	#[test]
+	fn route_cache_shares_suffixes() {
+		let mut cache = RouteCache::default();
+		let goal = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let portal = (SectorID::new(1, 0), FieldCell::new(0, 4));
+		let east = RouteMetadata::new(
+			SectorID::new(2, 0),
+			FieldCell::new(5, 5),
+			goal.0,
+			goal.1,
+			Duration::ZERO,
+		);
+		let south = RouteMetadata::new(
+			SectorID::new(1, 1),
+			FieldCell::new(5, 5),
+			goal.0,
+			goal.1,
+			Duration::ZERO,
+		);
+		let east_route = Route::new(vec![
+			(SectorID::new(2, 0), FieldCell::new(0, 4)),
+			portal,
+			goal,
+		]);
+		let south_route = Route::new(vec![
+			(SectorID::new(1, 1), FieldCell::new(2, 0)),
+			portal,
+			goal,
+		]);
+		cache.insert_route_with_metadata(east, east_route.clone());
+		cache.insert_route_with_metadata(south, south_route.clone());
+		// the portal of sector (1, 0) and the goal are stored once
+		assert_eq!(4, cache.get_node_count());
+		assert_eq!(2, cache.len());
+		assert_eq!(
+			east_route.get(),
+			cache.get_route_from_metadata(&east).unwrap().1.get()
+		);
+		assert_eq!(south_route.get(), cache.get_routes()[&south].get());
+		let usage = cache.memory_usage();
+		assert!(usage.get_bytes(portal.0, MemoryCategory::Routes) > 0);
+		// a change to sector (2, 0) only reaches the branch through it
+		assert_eq!(
+			vec![east],
+			cache.get_routes_using_sector(SectorID::new(2, 0))
+		);
+		assert_eq!(2, cache.get_routes_using_sector(portal.0).len());
+		cache.remove_route(east);
+		assert_eq!(3, cache.get_node_count());
+		assert_eq!(
+			south_route.get(),
+			cache.get_route_from_metadata(&south).unwrap().1.get()
+		);
+		assert_eq!(
+			0,
+			cache
+				.memory_usage()
+				.get_bytes(SectorID::new(2, 0), MemoryCategory::Routes)
+		);
+	}
+	#[test]
+	#[cfg(feature = "ron")]
+	fn route_cache_sector_index_deserialised() {
+		let mut cache = RouteCache::default();
+		let goal = FieldCell::new(4, 4);
+		cache.insert_route(
+			SectorID::new(1, 0),
+			FieldCell::new(9, 4),
+			SectorID::new(0, 0),
+			goal,
+			Duration::ZERO,
+			Route::new(vec![
+				(SectorID::new(1, 0), FieldCell::new(0, 4)),
+				(SectorID::new(0, 0), goal),
+			]),
+		);
+		let serialised = ron::to_string(&cache).unwrap();
+		let cache: RouteCache = ron::from_str(&serialised).unwrap();
+		assert_eq!(1, cache.get_routes_using_sector(SectorID::new(1, 0)).len());
+	}
+	#[test]
+	fn route_followers() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		// box in a cell of the source sector
+		for (column, row) in [(7, 0), (7, 1), (8, 1), (9, 1)] {
+			cost_fields.set_field_cell_value(
+				SectorID::new(2, 0),
+				Cost::IMPASSABLE,
+				FieldCell::new(column, row),
+				&map_dimensions,
+			);
+		}
+		let mut cache = RouteCache::default();
+		let mut queue = RouteQueue::default();
+		let goal = FieldCell::new(4, 4);
+		let request = |source: FieldCell| {
+			RouteMetadata::new(
+				SectorID::new(2, 0),
+				source,
+				SectorID::new(0, 0),
+				goal,
+				Duration::ZERO,
+			)
+		};
+		let leader = request(FieldCell::new(5, 5));
+		let route = Route::new(vec![
+			(SectorID::new(2, 0), FieldCell::new(0, 4)),
+			(SectorID::new(1, 0), FieldCell::new(0, 4)),
+			(SectorID::new(0, 0), goal),
+		]);
+		queue.add_to_queue(leader, route.clone());
+		let follower = request(FieldCell::new(2, 8));
+		assert_eq!(
+			Some(leader),
+			queue.find_leader(&cache, &follower, &cost_fields)
+		);
+		assert!(queue.add_follower(&cache, leader, follower));
+		assert_eq!(1, queue.get_followers(&leader).unwrap().len());
+		// a cell which can't reach the exit portal searches for its own route
+		let boxed_in = request(FieldCell::new(9, 0));
+		assert!(queue.find_leader(&cache, &boxed_in, &cost_fields).is_none());
+		// a request doesn't follow itself
+		assert!(queue.find_leader(&cache, &leader, &cost_fields).is_none());
+		// once cached a new follower is given a copy of the route
+		assert_eq!(vec![follower], queue.take_followers(&leader));
+		queue.remove_queued_route(leader);
+		cache.insert_route_with_metadata(leader, route);
+		let late = request(FieldCell::new(3, 3));
+		assert_eq!(Some(leader), queue.find_leader(&cache, &late, &cost_fields));
+		assert!(queue.add_follower(&cache, leader, late));
+		assert!(queue.get_queue().is_empty());
+		assert!(queue.contains(&late));
+		let copies = queue.take_copies();
+		let (copied, _) = copies.first_key_value().unwrap();
+		assert_eq!(leader.get_resolved_target(), copied.get_resolved_target());
+	}
+	#[test]
+	fn follower_raises_leader_priority() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let cache = RouteCache::default();
+		let mut queue = RouteQueue::default();
+		let request = |source: FieldCell, priority: PathPriority| {
+			RouteMetadata::new(
+				SectorID::new(0, 0),
+				source,
+				SectorID::new(0, 0),
+				FieldCell::new(8, 8),
+				Duration::ZERO,
+			)
+			.with_priority(priority)
+		};
+		let leader = request(FieldCell::new(0, 0), PathPriority::Background);
+		let route = Route::new(vec![(SectorID::new(0, 0), FieldCell::new(8, 8))]);
+		queue.add_to_queue(leader, route.clone());
+		let follower = request(FieldCell::new(1, 1), PathPriority::PlayerCritical);
+		assert!(queue.add_follower(&cache, leader, follower));
+		let (queued, _) = queue.get_queue().first_key_value().unwrap();
+		assert_eq!(PathPriority::PlayerCritical, queued.get_priority());
+		// a less urgent follower doesn't lower it
+		let late = request(FieldCell::new(2, 2), PathPriority::Background);
+		assert!(queue.add_follower(&cache, leader, late));
+		let (queued, _) = queue.get_queue().first_key_value().unwrap();
+		assert_eq!(PathPriority::PlayerCritical, queued.get_priority());
+		// the fields of a leader are raised the same way
+		let mut field_queue = FlowFieldQueue::default();
+		field_queue.add_to_queue(
+			leader,
+			route,
+			&cost_fields,
+			&map_dimensions,
+			Connectivity::default(),
+		);
+		field_queue.raise_priority(&leader, PathPriority::Normal);
+		let (queued, _) = field_queue.get_queue().first_key_value().unwrap();
+		assert_eq!(PathPriority::Normal, queued.get_priority());
+	}
+	#[test]
+	fn spliced_route_from_cache() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut cache = RouteCache::default();
+		let target_sector = SectorID::new(0, 0);
+		let goal = FieldCell::new(4, 4);
+		let route = Route::new(vec![
+			(SectorID::new(2, 0), FieldCell::new(0, 4)),
+			(SectorID::new(1, 0), FieldCell::new(0, 4)),
+			(target_sector, goal),
+		]);
+		cache.insert_route(
+			SectorID::new(2, 0),
+			FieldCell::new(9, 4),
+			target_sector,
+			goal,
+			Duration::ZERO,
+			route,
+		);
+		let spliced = cache
+			.get_spliced_route(
+				SectorID::new(1, 0),
+				FieldCell::new(8, 8),
+				target_sector,
+				goal,
+				&cost_fields,
+			)
+			.unwrap();
+		assert_eq!(2, spliced.get().len());
+		// a different goal or a sector off the route can't be spliced
+		assert!(cache
+			.get_spliced_route(
+				SectorID::new(1, 0),
+				FieldCell::new(8, 8),
+				target_sector,
+				FieldCell::new(1, 1),
+				&cost_fields,
+			)
+			.is_none());
+		assert!(cache
+			.get_spliced_route(
+				SectorID::new(1, 1),
+				FieldCell::new(8, 8),
+				target_sector,
+				goal,
+				&cost_fields,
+			)
+			.is_none());
+		// walled off from the portal
+		for row in 0..FIELD_RESOLUTION {
+			cost_fields.set_field_cell_value(
+				SectorID::new(1, 0),
+				Cost::IMPASSABLE,
+				FieldCell::new(5, row),
+				&map_dimensions,
+			);
+		}
+		assert!(cache
+			.get_spliced_route(
+				SectorID::new(1, 0),
+				FieldCell::new(8, 8),
+				target_sector,
+				goal,
+				&cost_fields,
+			)
+			.is_none());
+	}
+	#[test]
+	fn next_queued_by_priority() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut queue = FlowFieldQueue::default();
+		let cache = FlowFieldCache::default();
+		let background = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(0, 0),
+			SectorID::new(0, 0),
+			FieldCell::new(5, 5),
+			Duration::ZERO,
+		)
+		.with_priority(PathPriority::Background);
+		let critical = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(1, 0),
+			SectorID::new(0, 0),
+			FieldCell::new(5, 5),
+			Duration::from_millis(400),
+		)
+		.with_priority(PathPriority::PlayerCritical);
+		let route = Route::new(vec![(SectorID::new(0, 0), FieldCell::new(5, 5))]);
+		queue.add_to_queue(
+			background,
+			route.clone(),
+			&cost_fields,
+			&map_dimensions,
+			Connectivity::default(),
+		);
+		queue.add_to_queue(
+			critical,
+			route.clone(),
+			&cost_fields,
+			&map_dimensions,
+			Connectivity::default(),
+		);
+		let next = queue
+			.get_next_queued(Duration::from_millis(400), &cache)
+			.unwrap();
+		assert_eq!(critical, next);
+		queue.remove_queue_item(critical);
+		// the background request has aged enough to be served before a fresh
+		// critical request
+		let fresh_critical = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(2, 0),
+			SectorID::new(0, 0),
+			FieldCell::new(5, 5),
+			Duration::from_millis(1500),
+		)
+		.with_priority(PathPriority::PlayerCritical);
+		queue.add_to_queue(
+			fresh_critical,
+			route,
+			&cost_fields,
+			&map_dimensions,
+			Connectivity::default(),
+		);
+		let next = queue
+			.get_next_queued(Duration::from_millis(1500), &cache)
+			.unwrap();
+		assert_eq!(background, next);
+	}
+	#[test]
+	fn next_queued_batch_by_priority() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut queue = FlowFieldQueue::default();
+		let cache = FlowFieldCache::default();
+		let route = Route::new(vec![(SectorID::new(0, 0), FieldCell::new(5, 5))]);
+		let priorities = [
+			PathPriority::Background,
+			PathPriority::PlayerCritical,
+			PathPriority::Normal,
+		];
+		let mut queued = vec![];
+		for (i, priority) in priorities.iter().enumerate() {
+			let metadata = RouteMetadata::new(
+				SectorID::new(0, 0),
+				FieldCell::new(i, 0),
+				SectorID::new(0, 0),
+				FieldCell::new(5, 5),
+				Duration::ZERO,
+			)
+			.with_priority(*priority);
+			queue.add_to_queue(
+				metadata,
+				route.clone(),
+				&cost_fields,
+				&map_dimensions,
+				Connectivity::default(),
+			);
+			queued.push(metadata);
+		}
+		let batch = queue.get_next_queued_batch(Duration::ZERO, &cache, 2);
+		assert_eq!(vec![queued[1], queued[2]], batch);
+		let all = queue.get_next_queued_batch(Duration::ZERO, &cache, 5);
+		assert_eq!(vec![queued[1], queued[2], queued[0]], all);
+	}
+	#[test]
+	fn prefetch_actor_near_boundary() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut queue = FlowFieldQueue::default();
+		let cache = FlowFieldCache::default();
+		let background = RouteMetadata::new(
+			SectorID::new(1, 0),
+			FieldCell::new(5, 4),
+			SectorID::new(0, 0),
+			FieldCell::new(5, 5),
+			Duration::ZERO,
+		)
+		.with_priority(PathPriority::Background);
+		let critical = RouteMetadata::new(
+			SectorID::new(2, 2),
+			FieldCell::new(5, 5),
+			SectorID::new(2, 2),
+			FieldCell::new(1, 1),
+			Duration::ZERO,
+		)
+		.with_priority(PathPriority::PlayerCritical);
+		// ordered from the goal back to the actor
+		let route = Route::new(vec![
+			(SectorID::new(0, 0), FieldCell::new(5, 5)),
+			(SectorID::new(1, 0), FieldCell::new(0, 4)),
+		]);
+		queue.add_to_queue(
+			background,
+			route,
+			&cost_fields,
+			&map_dimensions,
+			Connectivity::default(),
+		);
+		queue.add_to_queue(
+			critical,
+			Route::new(vec![(SectorID::new(2, 2), FieldCell::new(1, 1))]),
+			&cost_fields,
+			&map_dimensions,
+			Connectivity::default(),
+		);
+		assert_eq!(
+			Some(critical),
+			queue.get_next_queued(Duration::ZERO, &cache)
+		);
+		// far from the exit of its sector
+		queue.hint_actor_position(background, SectorID::new(1, 0), FieldCell::new(9, 4));
+		assert!(!queue.is_prefetch_due(&background, &cache));
+		assert_eq!(
+			Some(critical),
+			queue.get_next_queued(Duration::ZERO, &cache)
+		);
+		// approaching the boundary with the next sector not yet built
+		queue.hint_actor_position(background, SectorID::new(1, 0), FieldCell::new(2, 4));
+		assert!(queue.is_prefetch_due(&background, &cache));
+		assert_eq!(
+			Some(background),
+			queue.get_next_queued(Duration::ZERO, &cache)
+		);
+		assert_eq!(
+			1,
+			queue.count_queued_ahead(&critical, Duration::ZERO, &cache)
+		);
+		// routes which aren't queued can't be hinted
+		queue.remove_queue_item(background);
+		queue.hint_actor_position(background, SectorID::new(1, 0), FieldCell::new(1, 4));
+		assert!(!queue.is_prefetch_due(&background, &cache));
+	}
+	#[test]
+	fn queue_backpressure() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut queue = FlowFieldQueue::default();
+		let cache = FlowFieldCache::default();
+		let route = Route::new(vec![(SectorID::new(0, 0), FieldCell::new(5, 5))]);
+		for (column, priority) in [
+			(0, PathPriority::Background),
+			(1, PathPriority::Normal),
+			(2, PathPriority::PlayerCritical),
+		] {
+			let metadata = RouteMetadata::new(
+				SectorID::new(0, 0),
+				FieldCell::new(column, 0),
+				SectorID::new(0, 0),
+				FieldCell::new(5, 5),
+				Duration::ZERO,
+			)
+			.with_priority(priority);
+			queue.add_to_queue(
+				metadata,
+				route.clone(),
+				&cost_fields,
+				&map_dimensions,
+				Connectivity::default(),
+			);
+		}
+		let request = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(3, 0),
+			SectorID::new(0, 0),
+			FieldCell::new(5, 5),
+			Duration::ZERO,
+		);
+		// the normal and critical requests are ahead
+		assert_eq!(
+			2,
+			queue.count_queued_ahead(&request, Duration::ZERO, &cache)
+		);
+		// nothing built yet so no estimate
+		assert_eq!(None, queue.estimate_frames_to_ready(2));
+		for _ in 0..50 {
+			queue.record_throughput(1);
+			queue.record_throughput(0);
+		}
+		// roughly one route every two frames
+		assert!((queue.get_throughput() - 0.5).abs() < 0.15);
+		let frames = queue.estimate_frames_to_ready(2).unwrap();
+		assert!((5..=8).contains(&frames));
+	}
+	#[test]
+	fn field_cell_neighbour() {
+		let cell = FieldCell::new(0, 4);
+		assert_eq!(Some(FieldCell::new(0, 3)), cell.neighbour(Ordinal::North));
+		assert_eq!(None, cell.neighbour(Ordinal::West));
+	}
+	#[test]
+	fn field_cell_offset() {
+		let cell = FieldCell::new(2, 4);
+		assert_eq!(
+			Some(FieldCell::new(5, 1)),
+			cell.offset(Ordinal::NorthEast, 3)
+		);
+		assert_eq!(Some(FieldCell::new(0, 4)), cell.offset(Ordinal::West, 2));
+		assert_eq!(None, cell.offset(Ordinal::West, 3));
+		assert_eq!(None, cell.offset(Ordinal::South, 6));
+	}
+	#[test]
 	fn field_cell_line_horizontal() {
 		let source = FieldCell::new(3, 4);
 		let target = FieldCell::new(7, 4);
@@ -0,0 +1,121 @@
+//! A OneWayField records the directions an actor may not step in from each
+//! field cell of a sector, such as back up a ledge it can drop off or
+//! against the belt of a conveyor. Each cell is a bitmask of the orthogonal
+//! [Ordinal]s which are blocked, a diagonal step is blocked if either of its
+//! orthogonal components are.
+//!
+//! The field is optional, sectors without one-way movement don't have one.
+//! When present the integration and flow layers never direct an actor
+//! through a blocked step and the [PortalGraph] omits the edges which would
+//! require one
+//!
+
+use crate::prelude::*;
+use bevy::reflect::Reflect;
+
+/// Bit of a cell blocking movement to the North
+const ONE_WAY_BITS_NORTH: u8 = 0b0001;
+/// Bit of a cell blocking movement to the East
+const ONE_WAY_BITS_EAST: u8 = 0b0010;
+/// Bit of a cell blocking movement to the South
+const ONE_WAY_BITS_SOUTH: u8 = 0b0100;
+/// Bit of a cell blocking movement to the West
+const ONE_WAY_BITS_WEST: u8 = 0b1000;
+
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Default, Debug, PartialEq, Reflect)]
+pub struct OneWayField([[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION]);
+
+impl Field<u8> for OneWayField {
+	/// Get a reference to the field array
+	fn get(&self) -> &[[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION] {
+		&self.0
+	}
+	/// Retrieve a field cell value
+	///
+	/// NB: This will panic if out of bounds
+	fn get_field_cell_value(&self, field_cell: FieldCell) -> u8 {
+		self.0[field_cell.get_column()][field_cell.get_row()]
+	}
+	/// Set a field cell to a value
+	///
+	/// NB: This will panic if out of bounds
+	fn set_field_cell_value(&mut self, value: u8, field_cell: FieldCell) {
+		self.0[field_cell.get_column()][field_cell.get_row()] = value;
+	}
+}
+
+impl OneWayField {
+	/// Prevent an actor stepping from the `field_cell` in the orthogonal
+	/// direction `ordinal`, diagonal ordinals are ignored
+	pub fn block_exit(&mut self, field_cell: FieldCell, ordinal: Ordinal) {
+		let value = self.get_field_cell_value(field_cell) | exit_bits(ordinal);
+		self.set_field_cell_value(value, field_cell);
+	}
+	/// Allow an actor to step from the `field_cell` in the orthogonal
+	/// direction `ordinal` again
+	pub fn allow_exit(&mut self, field_cell: FieldCell, ordinal: Ordinal) {
+		let value = self.get_field_cell_value(field_cell) & !exit_bits(ordinal);
+		self.set_field_cell_value(value, field_cell);
+	}
+	/// Whether an actor may step from the `field_cell` in the direction
+	/// `ordinal`. A diagonal step requires both of its orthogonal
+	/// components to be allowed
+	pub fn allows_move(&self, field_cell: FieldCell, ordinal: Ordinal) -> bool {
+		let blocked = match ordinal {
+			Ordinal::NorthEast => ONE_WAY_BITS_NORTH | ONE_WAY_BITS_EAST,
+			Ordinal::SouthEast => ONE_WAY_BITS_SOUTH | ONE_WAY_BITS_EAST,
+			Ordinal::SouthWest => ONE_WAY_BITS_SOUTH | ONE_WAY_BITS_WEST,
+			Ordinal::NorthWest => ONE_WAY_BITS_NORTH | ONE_WAY_BITS_WEST,
+			orthogonal => exit_bits(orthogonal),
+		};
+		self.get_field_cell_value(field_cell) & blocked == 0
+	}
+	/// Whether no cell of the field has a blocked direction
+	pub fn is_empty(&self) -> bool {
+		self.0.iter().flatten().all(|value| *value == 0)
+	}
+}
+
+/// Get the bit blocking movement in an orthogonal `ordinal`
+fn exit_bits(ordinal: Ordinal) -> u8 {
+	match ordinal {
+		Ordinal::North => ONE_WAY_BITS_NORTH,
+		Ordinal::East => ONE_WAY_BITS_EAST,
+		Ordinal::South => ONE_WAY_BITS_SOUTH,
+		Ordinal::West => ONE_WAY_BITS_WEST,
+		_ => 0,
+	}
+}
+
+/// Whether an actor may step from the `field_cell` in the direction `ordinal`
+/// given the optional `one_way` field of its sector
+pub fn is_move_allowed(
+	one_way: Option<&OneWayField>,
+	field_cell: FieldCell,
+	ordinal: Ordinal,
+) -> bool {
+	one_way.is_none_or(|field| field.allows_move(field_cell, ordinal))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn blocked_exits() {
+		let mut field = OneWayField::default();
+		assert!(field.is_empty());
+		let cell = FieldCell::new(3, 3);
+		field.block_exit(cell, Ordinal::North);
+		assert!(!field.allows_move(cell, Ordinal::North));
+		assert!(!field.allows_move(cell, Ordinal::NorthEast));
+		assert!(!field.allows_move(cell, Ordinal::NorthWest));
+		assert!(field.allows_move(cell, Ordinal::South));
+		assert!(field.allows_move(cell, Ordinal::SouthEast));
+		assert!(field.allows_move(FieldCell::new(3, 4), Ordinal::North));
+		field.allow_exit(cell, Ordinal::North);
+		assert!(field.is_empty());
+		assert!(is_move_allowed(None, cell, Ordinal::North));
+	}
+}
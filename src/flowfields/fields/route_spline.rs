@@ -0,0 +1,222 @@
+//! A [Route] describes which portals an actor passes through but not the
+//! exact way it moves, that is left to the [FlowField]s. For camera paths,
+//! cinematics and projectiles it can be more useful to have a smooth
+//! curve through the world following the same route, so a [Route] can be
+//! converted into a [CubicCurve] passing through the centre of each portal
+//! window it crosses
+//!
+
+#[cfg(any(feature = "2d", feature = "3d"))]
+use super::{cell_across_boundary, portal_window};
+use crate::prelude::*;
+#[cfg(any(feature = "2d", feature = "3d"))]
+use bevy::math::NormedVectorSpace;
+use bevy::prelude::*;
+
+/// The kind of spline to build from a [Route]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum RouteSplineKind {
+	/// A Catmull-Rom spline passing through each portal window, the curve
+	/// is as smooth as possible but may cross a window at a shallow angle
+	#[default]
+	CatmullRom,
+	/// A cubic Bézier curve with one segment between each portal window, the
+	/// curve crosses each window perpendicular to the sector boundary
+	Bezier,
+}
+
+impl Route {
+	/// Build a 2d [CubicCurve] following the route from the `start` position
+	/// of an actor. The curve passes through the centre of each expanded
+	/// portal window the route crosses and ends at the centre of the goal
+	/// [FieldCell]. The domain of the curve is `0.0..=n` where `n` is the
+	/// number of sectors in the route, so `t = 1.0` is the first portal
+	/// window crossed.
+	///
+	/// Returns [None] if the route is empty or a part of it lies outside of
+	/// the world
+	#[cfg(feature = "2d")]
+	pub fn to_spline_xy(
+		&self,
+		start: Vec2,
+		kind: RouteSplineKind,
+		map_dimensions: &MapDimensions,
+		sector_cost_fields: &SectorCostFields,
+	) -> Option<CubicCurve<Vec2>> {
+		let to_world =
+			|sector_id, field_cell| map_dimensions.get_xy_from_field_sector(sector_id, field_cell);
+		// row IDs increase towards negative y
+		let to_normal = |ordinal| match ordinal {
+			Ordinal::North => Vec2::Y,
+			Ordinal::East => Vec2::X,
+			Ordinal::South => Vec2::NEG_Y,
+			_ => Vec2::NEG_X,
+		};
+		let waypoints = self.waypoints(start, sector_cost_fields, to_world, to_normal)?;
+		build_spline(&waypoints, kind)
+	}
+	/// Build a 3d (x-z) [CubicCurve] following the route from the `start`
+	/// position of an actor. The curve passes through the centre of each
+	/// expanded portal window the route crosses and ends at the centre of the
+	/// goal [FieldCell]. The domain of the curve is `0.0..=n` where `n` is
+	/// the number of sectors in the route, so `t = 1.0` is the first portal
+	/// window crossed.
+	///
+	/// The `y` coordinate of each window is defaulted to `0.0` and is
+	/// interpolated from the `y` of `start`.
+	///
+	/// Returns [None] if the route is empty or a part of it lies outside of
+	/// the world
+	#[cfg(feature = "3d")]
+	pub fn to_spline_xyz(
+		&self,
+		start: Vec3,
+		kind: RouteSplineKind,
+		map_dimensions: &MapDimensions,
+		sector_cost_fields: &SectorCostFields,
+	) -> Option<CubicCurve<Vec3>> {
+		let to_world =
+			|sector_id, field_cell| map_dimensions.get_xyz_from_field_sector(sector_id, field_cell);
+		// row IDs increase towards positive z
+		let to_normal = |ordinal| match ordinal {
+			Ordinal::North => Vec3::NEG_Z,
+			Ordinal::East => Vec3::X,
+			Ordinal::South => Vec3::Z,
+			_ => Vec3::NEG_X,
+		};
+		let waypoints = self.waypoints(start, sector_cost_fields, to_world, to_normal)?;
+		build_spline(&waypoints, kind)
+	}
+	/// Get the world position of each point the spline of the route passes
+	/// through, along with the direction of travel across the sector
+	/// boundary for portal windows
+	#[cfg(any(feature = "2d", feature = "3d"))]
+	fn waypoints<P: NormedVectorSpace>(
+		&self,
+		start: P,
+		sector_cost_fields: &SectorCostFields,
+		to_world: impl Fn(SectorID, FieldCell) -> Option<P>,
+		to_normal: impl Fn(Ordinal) -> P,
+	) -> Option<Vec<(P, Option<P>)>> {
+		let (goal_sector, goal) = self.0.last()?;
+		let mut waypoints = vec![(start, None)];
+		for pair in self.0.windows(2) {
+			let (sector_id, portal) = pair[0];
+			let next_sector = pair[1].0;
+			let ordinal = Ordinal::sector_to_sector_direction(next_sector, sector_id)?;
+			// both sides of the window, their mean sits on the boundary
+			let window = portal_window(sector_cost_fields, sector_id, next_sector, portal);
+			let mut sum = P::ZERO;
+			for cell in window.iter() {
+				sum = sum + to_world(sector_id, *cell)?;
+				sum = sum + to_world(next_sector, cell_across_boundary(*cell, ordinal))?;
+			}
+			let centre = sum / (window.len() * 2) as f32;
+			waypoints.push((centre, Some(to_normal(ordinal))));
+		}
+		waypoints.push((to_world(*goal_sector, *goal)?, None));
+		Some(waypoints)
+	}
+}
+
+/// Build a [CubicCurve] through the `waypoints`, each of which can have a
+/// direction the curve should pass through it in
+#[cfg(any(feature = "2d", feature = "3d"))]
+fn build_spline<P: NormedVectorSpace>(
+	waypoints: &[(P, Option<P>)],
+	kind: RouteSplineKind,
+) -> Option<CubicCurve<P>> {
+	match kind {
+		RouteSplineKind::CatmullRom => {
+			let points: Vec<P> = waypoints.iter().map(|(point, _)| *point).collect();
+			CubicCardinalSpline::new_catmull_rom(points).to_curve().ok()
+		}
+		RouteSplineKind::Bezier => {
+			let segments: Vec<[P; 4]> = waypoints
+				.windows(2)
+				.map(|pair| {
+					let (from, from_normal) = pair[0];
+					let (to, to_normal) = pair[1];
+					// handles a third of the way along the segment, aligned
+					// with the boundary normal of a window or aimed along
+					// the segment at the start and goal
+					let reach = from.distance(to) / 3.0;
+					let chord = (to - from) / 3.0;
+					let from_handle = match from_normal {
+						Some(normal) => from + normal * reach,
+						None => from + chord,
+					};
+					let to_handle = match to_normal {
+						Some(normal) => to - normal * reach,
+						None => to - chord,
+					};
+					[from, from_handle, to_handle, to]
+				})
+				.collect();
+			CubicBezier::new(segments).to_curve().ok()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	#[cfg(feature = "2d")]
+	fn route_spline_xy() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let route = Route::new(vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 2)),
+			(SectorID::new(1, 0), FieldCell::new(5, 5)),
+		]);
+		let start = Vec2::new(-12.5, 12.5);
+		let goal = map_dimensions
+			.get_xy_from_field_sector(SectorID::new(1, 0), FieldCell::new(5, 5))
+			.unwrap();
+		for kind in [RouteSplineKind::CatmullRom, RouteSplineKind::Bezier] {
+			let curve = route
+				.to_spline_xy(start, kind, &map_dimensions, &cost_fields)
+				.unwrap();
+			assert_eq!(2, curve.segments().len());
+			assert!(curve.position(0.0).distance(start) < 0.001);
+			// the whole eastern boundary is open so the window centre is
+			// the middle of it
+			assert!(curve.position(1.0).distance(Vec2::new(-5.0, 10.0)) < 0.001);
+			assert!(curve.position(2.0).distance(goal) < 0.001);
+		}
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn route_spline_xyz_crosses_window() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		// narrow the southern boundary of sector (0, 0) to cells 0..=2
+		for column in 3..FIELD_RESOLUTION {
+			cost_fields.set_field_cell_value(
+				SectorID::new(0, 0),
+				255,
+				FieldCell::new(column, 9),
+				&map_dimensions,
+			);
+		}
+		let route = Route::new(vec![
+			(SectorID::new(0, 0), FieldCell::new(1, 9)),
+			(SectorID::new(0, 1), FieldCell::new(8, 8)),
+		]);
+		let start = Vec3::new(-10.5, 0.0, -14.5);
+		let curve = route
+			.to_spline_xyz(
+				start,
+				RouteSplineKind::Bezier,
+				&map_dimensions,
+				&cost_fields,
+			)
+			.unwrap();
+		assert!(curve.position(1.0).distance(Vec3::new(-13.5, 0.0, -5.0)) < 0.001);
+		// the curve leaves the window heading south
+		let velocity = curve.velocity(1.0).normalize();
+		assert!(velocity.distance(Vec3::Z) < 0.001);
+	}
+}
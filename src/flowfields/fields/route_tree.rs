@@ -0,0 +1,277 @@
+//! Routes from many sectors towards the same goal tend to converge, once two
+//! actors pass through the same portal the rest of their routes are
+//! identical. The [RouteCache] stores its routes in a [RouteTree] rooted at
+//! each goal where a route is a branch leading from the portal the actor
+//! starts at down to the goal, so a shared suffix of portals is only stored
+//! once. Removing a route prunes only the nodes which no other route passes
+//! through
+//!
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Index of a node within a [RouteTree]
+pub(crate) type RouteNodeId = usize;
+
+/// A sector and portal (or goal) shared by one or more routes
+#[derive(Clone, Debug, Reflect)]
+struct RouteNode {
+	/// The sector and portal (or goal) of the node
+	entry: (SectorID, FieldCell),
+	/// The next node towards the goal, [None] for the goal itself
+	parent: Option<RouteNodeId>,
+	/// The nodes one step further away from the goal sorted by their entry.
+	/// A node rarely has more than a handful of children so they're kept in
+	/// a compact list and found with a binary search
+	children: Vec<((SectorID, FieldCell), RouteNodeId)>,
+	/// Number of routes passing through the node
+	routes: usize,
+}
+
+/// High-level routes stored as trees rooted at their goals, each route is
+/// referred to by the node it starts from
+#[derive(Clone, Debug, Default, Reflect)]
+pub(crate) struct RouteTree {
+	/// The nodes of every tree, a removed node leaves an empty slot which is
+	/// reused by the next node added
+	nodes: Vec<Option<RouteNode>>,
+	/// Empty slots of `nodes`
+	free: Vec<RouteNodeId>,
+	/// The root node of each goal
+	roots: BTreeMap<(SectorID, FieldCell), RouteNodeId>,
+	/// The nodes within each sector
+	#[reflect(ignore)]
+	sector_nodes: BTreeMap<SectorID, BTreeSet<RouteNodeId>>,
+}
+
+impl RouteTree {
+	/// Add a `route` to the tree, reusing the nodes of any route which shares
+	/// the same suffix. Returns the node the route starts from or [None] if
+	/// the route is empty
+	pub(crate) fn insert(&mut self, route: &Route) -> Option<RouteNodeId> {
+		let mut entries = route.get().iter().rev();
+		let goal = *entries.next()?;
+		let mut node = match self.roots.get(&goal) {
+			Some(root) => *root,
+			None => {
+				let root = self.add_node(goal, None);
+				self.roots.insert(goal, root);
+				root
+			}
+		};
+		self.nodes[node].as_mut()?.routes += 1;
+		for entry in entries {
+			let parent = self.nodes[node].as_ref()?;
+			node = match parent.children.binary_search_by_key(entry, |(e, _)| *e) {
+				Ok(index) => parent.children[index].1,
+				Err(index) => {
+					let child = self.add_node(*entry, Some(node));
+					self.nodes[node]
+						.as_mut()?
+						.children
+						.insert(index, (*entry, child));
+					child
+				}
+			};
+			self.nodes[node].as_mut()?.routes += 1;
+		}
+		Some(node)
+	}
+	/// Store a new node in an empty slot
+	fn add_node(
+		&mut self,
+		entry: (SectorID, FieldCell),
+		parent: Option<RouteNodeId>,
+	) -> RouteNodeId {
+		let node = RouteNode {
+			entry,
+			parent,
+			children: Vec::new(),
+			routes: 0,
+		};
+		let id = match self.free.pop() {
+			Some(id) => {
+				self.nodes[id] = Some(node);
+				id
+			}
+			None => {
+				self.nodes.push(Some(node));
+				self.nodes.len() - 1
+			}
+		};
+		self.sector_nodes.entry(entry.0).or_default().insert(id);
+		id
+	}
+	/// Remove the route starting from the node `start`, any node no longer
+	/// used by another route is pruned
+	pub(crate) fn remove(&mut self, start: RouteNodeId) {
+		let mut current = Some(start);
+		while let Some(id) = current {
+			let Some(node) = self.nodes.get_mut(id).and_then(|node| node.as_mut()) else {
+				return;
+			};
+			node.routes = node.routes.saturating_sub(1);
+			current = node.parent;
+			if node.routes > 0 {
+				continue;
+			}
+			// every route through the children has already been removed
+			let Some(node) = self.nodes[id].take() else {
+				return;
+			};
+			self.free.push(id);
+			if let Some(nodes) = self.sector_nodes.get_mut(&node.entry.0) {
+				nodes.remove(&id);
+				if nodes.is_empty() {
+					self.sector_nodes.remove(&node.entry.0);
+				}
+			}
+			match node.parent.and_then(|parent| self.nodes[parent].as_mut()) {
+				Some(parent) => {
+					if let Ok(index) = parent
+						.children
+						.binary_search_by_key(&node.entry, |(e, _)| *e)
+					{
+						parent.children.remove(index);
+					}
+				}
+				None => {
+					self.roots.remove(&node.entry);
+				}
+			}
+		}
+	}
+	/// Walk the route starting from the node `start` towards the goal
+	/// without allocating, nothing is walked for [None]
+	pub(crate) fn iter_route(
+		&self,
+		start: Option<RouteNodeId>,
+	) -> impl Iterator<Item = (SectorID, FieldCell)> + '_ {
+		let node_of = |id: RouteNodeId| self.nodes.get(id)?.as_ref();
+		std::iter::successors(start.and_then(node_of), move |node| {
+			node.parent.and_then(node_of)
+		})
+		.map(|node| node.entry)
+	}
+	/// Get the route starting from the node `start`, an empty route is
+	/// returned for [None]. The route is sized before it's filled so it
+	/// allocates once
+	pub(crate) fn get_route(&self, start: Option<RouteNodeId>) -> Route {
+		let mut path = Vec::with_capacity(self.iter_route(start).count());
+		path.extend(self.iter_route(start));
+		Route::new(path)
+	}
+	/// Get every node of the branches passing through the `sector_id`, the
+	/// nodes within the sector and those further away from the goal. These
+	/// are the nodes the routes using the sector start from
+	pub(crate) fn get_branches_through_sector(&self, sector_id: SectorID) -> BTreeSet<RouteNodeId> {
+		let mut branches = BTreeSet::new();
+		let mut stack: Vec<RouteNodeId> = self
+			.sector_nodes
+			.get(&sector_id)
+			.map(|nodes| nodes.iter().copied().collect())
+			.unwrap_or_default();
+		while let Some(id) = stack.pop() {
+			// a route passing through the sector more than once nests one
+			// branch within another
+			if !branches.insert(id) {
+				continue;
+			}
+			if let Some(node) = self.nodes[id].as_ref() {
+				stack.extend(node.children.iter().map(|(_, child)| *child));
+			}
+		}
+		branches
+	}
+	/// Get the number of nodes stored
+	pub(crate) fn get_node_count(&self) -> usize {
+		self.nodes.len() - self.free.len()
+	}
+	/// Estimate the bytes held by the nodes within each sector
+	pub(crate) fn memory_usage(&self) -> MemoryUsage {
+		let mut usage = MemoryUsage::new();
+		for node in self.nodes.iter().flatten() {
+			let size = std::mem::size_of::<Option<RouteNode>>()
+				+ node.children.capacity() * entry_size::<(SectorID, FieldCell), RouteNodeId>();
+			usage.add(node.entry.0, MemoryCategory::Routes, size);
+		}
+		usage
+	}
+	/// Remove every node
+	pub(crate) fn clear(&mut self) {
+		self.nodes.clear();
+		self.free.clear();
+		self.roots.clear();
+		self.sector_nodes.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn shared_suffix_stored_once() {
+		let goal = (SectorID::new(2, 0), FieldCell::new(5, 5));
+		let portal = (SectorID::new(1, 0), FieldCell::new(9, 4));
+		let first = Route::new(vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 2)),
+			portal,
+			goal,
+		]);
+		let second = Route::new(vec![
+			(SectorID::new(1, 1), FieldCell::new(3, 0)),
+			portal,
+			goal,
+		]);
+		let mut tree = RouteTree::default();
+		let first_start = tree.insert(&first).unwrap();
+		let second_start = tree.insert(&second).unwrap();
+		// the portal and goal are shared
+		assert_eq!(4, tree.get_node_count());
+		assert_eq!(first.get(), tree.get_route(Some(first_start)).get());
+		assert_eq!(second.get(), tree.get_route(Some(second_start)).get());
+		// both routes pass through the portal, only one through sector (0, 0)
+		let branches = tree.get_branches_through_sector(SectorID::new(1, 0));
+		assert!(branches.contains(&first_start) && branches.contains(&second_start));
+		let branches = tree.get_branches_through_sector(SectorID::new(0, 0));
+		assert_eq!(BTreeSet::from([first_start]), branches);
+		// removing a route prunes only its own branch
+		tree.remove(first_start);
+		assert_eq!(3, tree.get_node_count());
+		assert_eq!(second.get(), tree.get_route(Some(second_start)).get());
+		assert!(tree
+			.get_branches_through_sector(SectorID::new(0, 0))
+			.is_empty());
+		tree.remove(second_start);
+		assert_eq!(0, tree.get_node_count());
+		assert!(tree.get_route(None).get().is_empty());
+	}
+	#[test]
+	fn smaller_than_flat_routes() {
+		// many actors spread across a row of sectors converge on the same
+		// portals down a column towards a single goal
+		let goal = (SectorID::new(0, 9), FieldCell::new(5, 5));
+		let suffix: Vec<(SectorID, FieldCell)> = (1..9)
+			.map(|row| (SectorID::new(0, row), FieldCell::new(4, 9)))
+			.chain(std::iter::once(goal))
+			.collect();
+		let mut tree = RouteTree::default();
+		let mut flat = 0;
+		for column in 0..10 {
+			for cell in 0..10 {
+				let mut path = vec![(SectorID::new(column, 0), FieldCell::new(cell, 0))];
+				path.extend(suffix.iter().copied());
+				let route = Route::new(path);
+				let start = tree.insert(&route);
+				assert_eq!(route.get(), tree.get_route(start).get());
+				assert!(route.get().iter().copied().eq(tree.iter_route(start)));
+				flat += std::mem::size_of::<Route>()
+					+ route.get().len() * std::mem::size_of::<(SectorID, FieldCell)>();
+			}
+		}
+		assert_eq!(100 + suffix.len(), tree.get_node_count());
+		assert!(tree.memory_usage().get_total() < flat);
+	}
+}
@@ -69,6 +69,7 @@ pub mod fields;
 pub mod portal;
 pub mod sectors;
 pub mod utilities;
+pub mod validation;
 
 // #[rustfmt::skip]
 // #[cfg(test)]
@@ -0,0 +1,107 @@
+//! The [PortalGraph] scores candidate routes between portals with a
+//! [PortalCostModel]. By default a route is scored purely on how expensive
+//! it is to traverse the [CostField]s, a custom model can be used to
+//! express other strategic preferences, such as favouring roads or avoiding
+//! territory held by an enemy, without changing the underlying fields
+//!
+
+use std::sync::Arc;
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Scores the steps of an A-Star search through the [PortalGraph].
+///
+/// Each position is expressed as the [SectorID] and [FieldCell] of a portal
+pub trait PortalCostModel: Send + Sync {
+	/// Cost of travelling along an edge from one portal to another where
+	/// `distance` is the cost of traversing the [CostField]s between them
+	fn edge_cost(
+		&self,
+		_from: (SectorID, FieldCell),
+		_to: (SectorID, FieldCell),
+		distance: i32,
+	) -> i32 {
+		distance
+	}
+	/// Cost of arriving at a portal where `weight` is the [CostField] value
+	/// of the portal cell
+	fn node_cost(&self, _node: (SectorID, FieldCell), weight: u8) -> i32 {
+		weight as i32
+	}
+	/// Estimate of the remaining cost from a portal to the `target` portal
+	/// where `distance` is the straight line distance between them in
+	/// [FieldCell]s (see [PortalGraph::get_node_position]). To guarantee the
+	/// best route is found the estimate must never exceed the real cost, a
+	/// model which makes edges cheaper than their `distance` should scale it
+	/// down to match
+	fn heuristic(
+		&self,
+		_node: (SectorID, FieldCell),
+		_target: (SectorID, FieldCell),
+		distance: i32,
+	) -> i32 {
+		distance
+	}
+	/// Whether the search may enter a sector, routes never cross into a
+	/// sector which isn't passable (the sector of the source is always
+	/// allowed)
+	fn is_sector_passable(&self, _sector_id: SectorID) -> bool {
+		true
+	}
+	/// Whether the search may cross a portal, portals which aren't passable
+	/// are skipped as though they didn't exist
+	fn is_portal_passable(&self, _node: (SectorID, FieldCell)) -> bool {
+		true
+	}
+	/// Whether the search may walk between two positions within the same
+	/// sector, such as from one portal of a sector to another. Edges which
+	/// aren't passable are skipped even though the [CostField] connects them
+	fn is_edge_passable(&self, _from: (SectorID, FieldCell), _to: (SectorID, FieldCell)) -> bool {
+		true
+	}
+}
+
+/// Scores routes purely on the cost of traversing the [CostField]s
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultPortalCostModel;
+
+impl PortalCostModel for DefaultPortalCostModel {}
+
+/// Holds the [PortalCostModel] used by a [FlowFieldTilesBundle] when
+/// searching for routes through its [PortalGraph]
+#[derive(Component, Clone)]
+pub struct RouteCostModel(Arc<dyn PortalCostModel>);
+
+impl Default for RouteCostModel {
+	fn default() -> Self {
+		RouteCostModel(Arc::new(DefaultPortalCostModel))
+	}
+}
+
+impl RouteCostModel {
+	/// Create a new instance of [RouteCostModel] from a custom [PortalCostModel]
+	pub fn new(model: impl PortalCostModel + 'static) -> Self {
+		RouteCostModel(Arc::new(model))
+	}
+	/// Get a reference to the [PortalCostModel]
+	pub fn get(&self) -> &dyn PortalCostModel {
+		self.0.as_ref()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn default_model_costs() {
+		let model = RouteCostModel::default();
+		let a = (SectorID::new(0, 0), FieldCell::new(9, 4));
+		let b = (SectorID::new(1, 0), FieldCell::new(0, 4));
+		assert_eq!(7, model.get().edge_cost(a, b, 7));
+		assert_eq!(3, model.get().node_cost(b, 3));
+		assert_eq!(1, model.get().heuristic(a, b, 1));
+		assert!(model.get().is_sector_passable(b.0));
+		assert!(model.get().is_edge_passable(a, a));
+	}
+}
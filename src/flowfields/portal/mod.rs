@@ -1,7 +1,13 @@
 //! A Portal indicates a pathable area from one Sector to another.
 //!
 //! A [portal_graph::PortalGraph] is used to calculate a path between portals (effectively a
-//! high level path of traversing from one sector to another).
+//! high level path of traversing from one sector to another). How those paths are scored can be
+//! customised with a [cost_model::PortalCostModel]. The portals and graph of a map can be
+//! rendered into an SVG with [portal_svg::portals_to_svg].
 
+pub mod cost_model;
+#[cfg(feature = "serde")]
+pub mod portal_data;
 pub mod portal_graph;
+pub mod portal_svg;
 pub mod portals;
@@ -0,0 +1,306 @@
+//! Baked navigation data, the [SectorPortals] of a map along with the
+//! [PortalGraph] built from them, can be saved and loaded as a [PortalData].
+//!
+//! Each [PortalData] records the [PORTAL_DATA_VERSION] it was written with so
+//! that data baked by an older version of the crate can be migrated when
+//! loaded, and data written by a newer version fails with a clear error
+//! rather than deserialising into the wrong shape
+//!
+
+use crate::prelude::*;
+
+/// The version of [PortalData] written by this version of the crate.
+///
+/// * `0` - a whole [FlowFieldTilesBundle] serialised by version `0.12` of the
+///   crate or earlier, before [PortalData] existed, see [PortalData::from_ron]
+/// * `1` - the current shape of [SectorPortals] and [PortalGraph]
+pub const PORTAL_DATA_VERSION: u32 = 1;
+
+/// The [SectorPortals] and [PortalGraph] of a map tagged with the version of
+/// their layout
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct PortalData {
+	/// Version of the layout the data was written with
+	version: u32,
+	/// Portals along the boundaries of each sector
+	sector_portals: SectorPortals,
+	/// Graph of the portals
+	portal_graph: PortalGraph,
+}
+
+impl PortalData {
+	/// Create a new instance of [PortalData] at the current [PORTAL_DATA_VERSION]
+	pub fn new(sector_portals: SectorPortals, portal_graph: PortalGraph) -> Self {
+		PortalData {
+			version: PORTAL_DATA_VERSION,
+			sector_portals,
+			portal_graph,
+		}
+	}
+	/// Get the version of the layout the data was written with
+	pub fn get_version(&self) -> u32 {
+		self.version
+	}
+	/// Get the [SectorPortals]
+	pub fn get_sector_portals(&self) -> &SectorPortals {
+		&self.sector_portals
+	}
+	/// Get the [PortalGraph]
+	pub fn get_portal_graph(&self) -> &PortalGraph {
+		&self.portal_graph
+	}
+	/// Consume the data, returning the [SectorPortals] and [PortalGraph]
+	pub fn into_parts(self) -> (SectorPortals, PortalGraph) {
+		(self.sector_portals, self.portal_graph)
+	}
+	/// Bring the data up to the current [PORTAL_DATA_VERSION] and check that
+	/// it describes every sector of the map.
+	///
+	/// Data from before versioning only has its [SectorPortals] read, the
+	/// [PortalGraph] is rebuilt from them
+	pub fn migrate(
+		mut self,
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) -> Result<Self, PortalDataError> {
+		if self.version > PORTAL_DATA_VERSION {
+			return Err(PortalDataError::NewerVersion {
+				found: self.version,
+				supported: PORTAL_DATA_VERSION,
+			});
+		}
+		self.validate(map_dimensions)?;
+		if self.version == 0 {
+			// the graph of a legacy bundle isn't read, its layout has since
+			// changed, so rebuild it from the portals whose layout hasn't
+			self.portal_graph
+				.rebuild(&self.sector_portals, sector_cost_fields, map_dimensions);
+			self.version = 1;
+		}
+		self.portal_graph.cache_node_positions(map_dimensions);
+		Ok(self)
+	}
+	/// Check that the [SectorPortals] cover exactly the sectors of the map
+	fn validate(&self, map_dimensions: &MapDimensions) -> Result<(), PortalDataError> {
+		let resolution = map_dimensions.get_sector_resolution();
+		let columns = map_dimensions.get_length() / resolution;
+		let rows = map_dimensions.get_depth() / resolution;
+		let portals = self.sector_portals.get();
+		for column in 0..columns {
+			for row in 0..rows {
+				let sector_id = SectorID::new(column, row);
+				if !portals.contains_key(&sector_id) {
+					return Err(PortalDataError::MissingSector(sector_id));
+				}
+			}
+		}
+		if let Some(sector_id) = portals
+			.keys()
+			.find(|id| id.get_column() >= columns || id.get_row() >= rows)
+		{
+			return Err(PortalDataError::UnexpectedSector(*sector_id));
+		}
+		Ok(())
+	}
+	/// Serialise the data into a `ron` string
+	#[cfg(feature = "ron")]
+	pub fn to_ron(&self) -> Result<String, PortalDataError> {
+		ron::to_string(self).map_err(|e| PortalDataError::Serialise(e.to_string()))
+	}
+	/// Deserialise data from a `ron` string. The version is read first so
+	/// that data written by a newer version of the crate is reported as such
+	/// rather than as malformed.
+	///
+	/// Data without a version is read as a [FlowFieldTilesBundle] serialised
+	/// by version `0.12` of the crate or earlier, from which only the
+	/// [SectorPortals] are taken. Call [PortalData::migrate] afterwards to
+	/// bring older data up to date
+	#[cfg(feature = "ron")]
+	pub fn from_ron(data: &str) -> Result<Self, PortalDataError> {
+		let header: PortalDataHeader =
+			ron::from_str(data).map_err(|e| PortalDataError::Malformed(e.to_string()))?;
+		match header.version {
+			0 => {
+				let legacy: LegacyBundle =
+					ron::from_str(data).map_err(|e| PortalDataError::Malformed(e.to_string()))?;
+				Ok(PortalData {
+					version: 0,
+					sector_portals: legacy.sector_portals,
+					portal_graph: PortalGraph::default(),
+				})
+			}
+			version if version > PORTAL_DATA_VERSION => Err(PortalDataError::NewerVersion {
+				found: version,
+				supported: PORTAL_DATA_VERSION,
+			}),
+			_ => ron::from_str(data).map_err(|e| PortalDataError::Malformed(e.to_string())),
+		}
+	}
+}
+
+/// Just the version of a serialised [PortalData], used to check it before
+/// reading the rest. Legacy data has no version and is read as version `0`
+#[cfg(feature = "ron")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "PortalData")]
+struct PortalDataHeader {
+	/// Version of the layout the data was written with
+	#[serde(default)]
+	version: u32,
+}
+
+/// The portals of a [FlowFieldTilesBundle] serialised by version `0.12` of
+/// the crate or earlier, every other field is skipped
+#[cfg(feature = "ron")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "FlowFieldTilesBundle")]
+struct LegacyBundle {
+	/// Portals along the boundaries of each sector
+	sector_portals: SectorPortals,
+}
+
+/// A problem found while loading or migrating [PortalData]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PortalDataError {
+	/// The data was written by a newer version of the crate
+	NewerVersion {
+		/// Version of the data
+		found: u32,
+		/// Latest version this crate can read
+		supported: u32,
+	},
+	/// The data doesn't match the layout of its version
+	Malformed(String),
+	/// A sector of the map has no portals
+	MissingSector(SectorID),
+	/// Portals exist for a sector outside of the map
+	UnexpectedSector(SectorID),
+	/// The data could not be serialised
+	Serialise(String),
+}
+
+impl std::fmt::Display for PortalDataError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			PortalDataError::NewerVersion { found, supported } => write!(
+				f,
+				"Portal data version {} is newer than the supported version {}",
+				found, supported
+			),
+			PortalDataError::Malformed(reason) => {
+				write!(f, "Portal data is malformed: {}", reason)
+			}
+			PortalDataError::MissingSector(sector_id) => {
+				write!(f, "Sector {:?} has no portals", sector_id)
+			}
+			PortalDataError::UnexpectedSector(sector_id) => {
+				write!(f, "Sector {:?} lies outside of the map", sector_id)
+			}
+			PortalDataError::Serialise(reason) => {
+				write!(f, "Portal data could not be serialised: {}", reason)
+			}
+		}
+	}
+}
+
+impl std::error::Error for PortalDataError {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	/// Portals and graph of a small open map
+	fn baked(map_dimensions: &MapDimensions) -> (SectorCostFields, SectorPortals, PortalGraph) {
+		let sector_cost_fields = SectorCostFields::new(map_dimensions);
+		let mut sector_portals = SectorPortals::new(
+			map_dimensions.get_length(),
+			map_dimensions.get_depth(),
+			map_dimensions.get_sector_resolution(),
+		);
+		for (sector_id, _cost_fields) in sector_cost_fields.get_scaled().iter() {
+			let portals = sector_portals.get_mut();
+			match portals.get_mut(sector_id) {
+				Some(portals) => {
+					portals.recalculate_portals(&sector_cost_fields, sector_id, map_dimensions)
+				}
+				None => panic!("Key {:?} not found in Portals", sector_id),
+			}
+		}
+		let portal_graph = PortalGraph::new(&sector_portals, &sector_cost_fields, map_dimensions);
+		(sector_cost_fields, sector_portals, portal_graph)
+	}
+	#[test]
+	#[cfg(feature = "ron")]
+	fn round_trip() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let (sector_cost_fields, sector_portals, portal_graph) = baked(&map_dimensions);
+		let data = PortalData::new(sector_portals, portal_graph);
+		let serialised = data.to_ron().unwrap();
+		let result = PortalData::from_ron(&serialised)
+			.unwrap()
+			.migrate(&sector_cost_fields, &map_dimensions)
+			.unwrap();
+		assert_eq!(PORTAL_DATA_VERSION, result.get_version());
+		assert_eq!(
+			data.get_sector_portals().get().len(),
+			result.get_sector_portals().get().len()
+		);
+	}
+	#[test]
+	#[cfg(feature = "ron")]
+	fn newer_version_is_rejected() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let (_, sector_portals, portal_graph) = baked(&map_dimensions);
+		let serialised = PortalData::new(sector_portals, portal_graph)
+			.to_ron()
+			.unwrap()
+			.replacen("version:1", "version:99", 1);
+		let result = PortalData::from_ron(&serialised).unwrap_err();
+		let actual = PortalDataError::NewerVersion {
+			found: 99,
+			supported: PORTAL_DATA_VERSION,
+		};
+		assert_eq!(actual, result);
+	}
+	#[test]
+	#[cfg(feature = "ron")]
+	fn wrong_shape_is_malformed() {
+		let result = PortalData::from_ron("(version:1,sector_portals:(3))").unwrap_err();
+		assert!(matches!(result, PortalDataError::Malformed(_)));
+	}
+	#[test]
+	#[cfg(feature = "ron")]
+	fn legacy_bundle_is_migrated() {
+		// a bundle serialised by version 0.12 of the crate
+		let data = include_str!("../../../assets/legacy_bundle_0_12.ron");
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let source = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let target = (SectorID::new(1, 0), FieldCell::new(4, 4));
+		let data = PortalData::from_ron(data).unwrap();
+		assert_eq!(0, data.get_version());
+		let portals = data.get_sector_portals().get();
+		let east = portals.get(&SectorID::new(0, 0)).unwrap();
+		assert_eq!(&vec![FieldCell::new(9, 4)], east.get(&Ordinal::East));
+		let result = data.migrate(&sector_cost_fields, &map_dimensions).unwrap();
+		assert_eq!(PORTAL_DATA_VERSION, result.get_version());
+		assert!(result
+			.get_portal_graph()
+			.find_best_path(
+				source,
+				target,
+				result.get_sector_portals(),
+				&sector_cost_fields
+			)
+			.is_some());
+	}
+	#[test]
+	fn missing_sector_is_rejected() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let (sector_cost_fields, mut sector_portals, portal_graph) = baked(&map_dimensions);
+		sector_portals.get_mut().remove(&SectorID::new(1, 1));
+		let result = PortalData::new(sector_portals, portal_graph)
+			.migrate(&sector_cost_fields, &map_dimensions)
+			.unwrap_err();
+		assert_eq!(PortalDataError::MissingSector(SectorID::new(1, 1)), result);
+	}
+}
@@ -6,6 +6,8 @@
 //! the agent immediately starts pathing. In the background the other components of the Flowfields can
 //! calcualte a perfect path which can then supersede using portals to path when it's ready
 
+use std::collections::{BTreeMap, BTreeSet};
+
 use crate::prelude::*;
 use bevy::{
 	prelude::*,
@@ -14,8 +16,9 @@ use bevy::{
 
 /// Used to provide a heuristic for portals that sit next to each other across
 /// a portal boundary. This is used in the a-star calculation for determining
-/// the best portal path to a goal
-const SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE: i32 = 1;
+/// the best portal path to a goal. It's the default distance of a
+/// [PortalGraph], see [PortalGraph::new_with_boundary_distance]
+pub const SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE: i32 = 1;
 
 /// The graph contains a series of [Node] which denotes the Sector and FieldCell of a portal
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
@@ -126,11 +129,28 @@ impl std::hash::Hash for Edge {
 	}
 }
 
+/// Identifies a group of conditional crossings in the [PortalGraph], such as
+/// every drawbridge of a castle, which can be enabled or disabled together
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct ConditionId(u32);
+
+impl ConditionId {
+	/// Create a new instance of [ConditionId]
+	pub fn new(id: u32) -> Self {
+		ConditionId(id)
+	}
+	/// Get the ID
+	pub fn get(&self) -> u32 {
+		self.0
+	}
+}
+
 /// The graph represents all the [Portals] across sectors in a [Node] notation. Each [Node] is then associated with `edges_internal` and `edges_external` which define routes to travel between [Portals].
 ///
 /// The graph can be queried to find the best path of [Portals] from one sector to another
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
-#[derive(Component, Default, Reflect, Debug, Clone)]
+#[derive(Component, Reflect, Debug, Clone)]
 #[reflect(Component)]
 pub struct PortalGraph {
 	/// Points that represent the gateway from one sector to another
@@ -139,6 +159,55 @@ pub struct PortalGraph {
 	edges_internal: HashSet<Edge>,
 	/// A pair of [Node]s that indicate that a [Node] within the current sector can allow passage to another [Node] in a different sector
 	edges_external: HashSet<Edge>,
+	/// Crossings between neighbouring sectors which can only be used while
+	/// their condition is enabled, keyed by the pair of portal nodes of the
+	/// external edge in ascending order
+	#[cfg_attr(feature = "serde", serde(default))]
+	conditional_crossings: BTreeMap<Crossing, ConditionId>,
+	/// Conditions whose crossings cannot currently be used
+	#[reflect(ignore)]
+	#[cfg_attr(feature = "serde", serde(default))]
+	disabled_conditions: BTreeSet<ConditionId>,
+	/// World-space position of each [Node] across the plane of the map, `x`
+	/// eastwards and `y` northwards from the centre of the map, see
+	/// [PortalGraph::cache_node_positions]
+	#[cfg_attr(feature = "serde", serde(default))]
+	node_positions: BTreeMap<(SectorID, FieldCell), Vec2>,
+	/// Size of a [FieldCell] in world units when the node positions were
+	/// cached, used to express the distance between nodes in cells
+	#[cfg_attr(feature = "serde", serde(default))]
+	node_cell_size: f32,
+	/// Distance of the [Edge] between portals either side of a sector
+	/// boundary
+	#[cfg_attr(feature = "serde", serde(default = "default_boundary_distance"))]
+	boundary_distance: i32,
+	/// The sectors which can be crossed into from each sector by an enabled
+	/// external [Edge], see [PortalGraph::preview_sector_route]
+	#[reflect(ignore)]
+	#[cfg_attr(feature = "serde", serde(default))]
+	sector_neighbours: BTreeMap<SectorID, BTreeSet<SectorID>>,
+}
+
+impl Default for PortalGraph {
+	fn default() -> Self {
+		PortalGraph {
+			nodes: HashSet::default(),
+			edges_internal: HashSet::default(),
+			edges_external: HashSet::default(),
+			conditional_crossings: BTreeMap::new(),
+			disabled_conditions: BTreeSet::new(),
+			node_positions: BTreeMap::new(),
+			node_cell_size: 0.0,
+			boundary_distance: SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE,
+			sector_neighbours: BTreeMap::new(),
+		}
+	}
+}
+
+/// Boundary distance of a deserialised [PortalGraph] which didn't record one
+#[cfg(feature = "serde")]
+fn default_boundary_distance() -> i32 {
+	SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE
 }
 // interface methods to the graph
 impl PortalGraph {
@@ -205,12 +274,159 @@ impl PortalGraph {
 		sector_cost_fields: &SectorCostFields,
 		map_dimensions: &MapDimensions,
 	) -> Self {
-		let mut graph = PortalGraph::default();
+		PortalGraph::new_with_boundary_distance(
+			sector_portals,
+			sector_cost_fields,
+			map_dimensions,
+			SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE,
+		)
+	}
+	/// Create a new instance of [PortalGraph] from sector data where moving
+	/// between portals either side of a sector boundary costs
+	/// `boundary_distance` rather than [SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE]
+	pub fn new_with_boundary_distance(
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+		boundary_distance: i32,
+	) -> Self {
+		let mut graph = PortalGraph {
+			boundary_distance,
+			..Default::default()
+		};
 		graph.create_all_nodes(sector_portals, sector_cost_fields);
 		graph.create_all_internal_edges(sector_portals, sector_cost_fields);
 		graph.create_all_external_edges(sector_portals, sector_cost_fields, map_dimensions);
+		graph.cache_node_positions(map_dimensions);
+		graph.cache_sector_neighbours();
 		graph
 	}
+	/// Get the distance of moving between portals either side of a sector
+	/// boundary
+	pub fn get_boundary_distance(&self) -> i32 {
+		self.boundary_distance
+	}
+	/// Set the distance of moving between portals either side of a sector
+	/// boundary, the existing boundary edges are updated in place. Routes
+	/// which have already been found keep the portals they were found with
+	pub fn set_boundary_distance(&mut self, boundary_distance: i32) {
+		if self.boundary_distance == boundary_distance {
+			return;
+		}
+		self.boundary_distance = boundary_distance;
+		self.edges_external = std::mem::take(&mut self.edges_external)
+			.into_iter()
+			.map(|edge| Edge::new(edge.from, edge.to, boundary_distance))
+			.collect();
+	}
+	/// Record the world-space position of each [Node] so that it doesn't have
+	/// to be derived from the [MapDimensions] again whenever it's queried.
+	/// Positions of nodes which no longer exist are dropped. This is done as
+	/// the graph is built and updated, a deserialised graph may need it
+	/// calling once
+	pub fn cache_node_positions(&mut self, map_dimensions: &MapDimensions) {
+		let cell_size = map_dimensions.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+		if self.node_cell_size != cell_size {
+			self.node_positions.clear();
+			self.node_cell_size = cell_size;
+		}
+		let nodes: BTreeSet<(SectorID, FieldCell)> = self
+			.get_nodes()
+			.iter()
+			.map(|node| (*node.get_sector(), *node.get_portal_cell()))
+			.collect();
+		self.node_positions.retain(|key, _| nodes.contains(key));
+		for (sector_id, field_cell) in nodes {
+			if self.node_positions.contains_key(&(sector_id, field_cell)) {
+				continue;
+			}
+			if let Some(position) = map_dimensions.get_xy_from_field_sector(sector_id, field_cell) {
+				self.node_positions
+					.insert((sector_id, field_cell), position);
+			}
+		}
+	}
+	/// Record which sectors can be crossed into from each sector through the
+	/// enabled external edges, so that previewing a route doesn't walk every
+	/// edge of the graph. This is done whenever the edges or the conditions
+	/// of crossings change
+	fn cache_sector_neighbours(&mut self) {
+		let mut neighbours: BTreeMap<SectorID, BTreeSet<SectorID>> = BTreeMap::new();
+		for edge in self.get_edges_external().iter() {
+			let from = (
+				*edge.get_from().get_sector(),
+				*edge.get_from().get_portal_cell(),
+			);
+			let to = (
+				*edge.get_to().get_sector(),
+				*edge.get_to().get_portal_cell(),
+			);
+			if self.is_crossing_enabled(from, to) {
+				neighbours.entry(from.0).or_default().insert(to.0);
+			}
+		}
+		self.sector_neighbours = neighbours;
+	}
+	/// Get the sector and [FieldCell] of every portal node of the graph,
+	/// sorted so that the order is stable
+	pub fn get_portal_nodes(&self) -> Vec<(SectorID, FieldCell)> {
+		let mut nodes: Vec<_> = self
+			.get_nodes()
+			.iter()
+			.map(|node| (*node.get_sector(), *node.get_portal_cell()))
+			.collect();
+		nodes.sort();
+		nodes
+	}
+	/// Get every pair of portals within the same sector that an actor can
+	/// move between, as the sector and [FieldCell] of each portal, sorted so
+	/// that the order is stable
+	pub fn get_internal_connections(&self) -> Vec<((SectorID, FieldCell), (SectorID, FieldCell))> {
+		Self::connections(self.get_edges_internal())
+	}
+	/// Get every pair of portals either side of a sector boundary that an
+	/// actor can move between, as the sector and [FieldCell] of each portal,
+	/// sorted so that the order is stable
+	pub fn get_external_connections(&self) -> Vec<((SectorID, FieldCell), (SectorID, FieldCell))> {
+		Self::connections(self.get_edges_external())
+	}
+	/// Convert a set of [Edge]s into sorted pairs of portals
+	fn connections(edges: &HashSet<Edge>) -> Vec<((SectorID, FieldCell), (SectorID, FieldCell))> {
+		let mut connections: Vec<_> = edges
+			.iter()
+			.map(|edge| {
+				let from = edge.get_from();
+				let to = edge.get_to();
+				(
+					(*from.get_sector(), *from.get_portal_cell()),
+					(*to.get_sector(), *to.get_portal_cell()),
+				)
+			})
+			.collect();
+		connections.sort();
+		connections
+	}
+	/// Recreate every [Node] and [Edge] of the graph from the
+	/// `sector_portals`, the conditional crossings (and whether their
+	/// conditions are enabled) and the boundary distance are kept
+	pub fn rebuild(
+		&mut self,
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) {
+		let mut graph = PortalGraph::new_with_boundary_distance(
+			sector_portals,
+			sector_cost_fields,
+			map_dimensions,
+			self.boundary_distance,
+		);
+		graph.conditional_crossings = std::mem::take(&mut self.conditional_crossings);
+		graph.disabled_conditions = std::mem::take(&mut self.disabled_conditions);
+		graph.remap_conditional_crossings(sector_portals, sector_cost_fields, map_dimensions);
+		graph.cache_sector_neighbours();
+		*self = graph;
+	}
 	/// Add nodes for all sectors to the [PortalGraph]
 	fn create_all_nodes(
 		&mut self,
@@ -229,14 +445,19 @@ impl PortalGraph {
 		sector_id: &SectorID,
 		portals: &Portals,
 	) {
+		let Some(cost_field) = sector_cost_fields.get_scaled().get(sector_id) else {
+			if !sector_cost_fields.is_void(*sector_id) {
+				warn!(
+					"Cannot create portal nodes in non-existent sector {:?}",
+					sector_id
+				);
+			}
+			return;
+		};
 		let ords = [Ordinal::North, Ordinal::East, Ordinal::South, Ordinal::West];
 		for ord in ords.iter() {
 			for cell in portals.get(ord).iter() {
-				let weight = sector_cost_fields
-					.get_scaled()
-					.get(sector_id)
-					.unwrap()
-					.get_field_cell_value(*cell);
+				let weight = cost_field.get_field_cell_value(*cell);
 				let portal_node = Node::new(*sector_id, *cell, weight, *ord);
 				self.add_node(portal_node);
 			}
@@ -251,16 +472,28 @@ impl PortalGraph {
 	) {
 		for (sector_id, portals) in sector_portals.get() {
 			// get the cost field for this sector
-			let cost_field = sector_cost_fields.get_scaled().get(sector_id).unwrap();
+			let Some(cost_field) = sector_cost_fields.get_scaled().get(sector_id) else {
+				if !sector_cost_fields.is_void(*sector_id) {
+					warn!(
+						"Cannot create internal edges in non-existent sector {:?}",
+						sector_id
+					);
+				}
+				continue;
+			};
 			// create edges between portals that can see each other
-			self.create_sector_internal_edges(sector_id, cost_field, portals);
+			let one_way = sector_cost_fields.get_one_way_field(*sector_id);
+			self.create_sector_internal_edges(sector_id, cost_field, one_way, portals);
 		}
 	}
-	/// For the given sector create [Edge]s between any [Portals] within it
+	/// For the given sector create [Edge]s between any [Portals] within it,
+	/// an edge is only created in the directions permitted by the `one_way`
+	/// field of the sector
 	fn create_sector_internal_edges(
 		&mut self,
 		sector_id: &SectorID,
 		cost_field: &CostField,
+		one_way: Option<&OneWayField>,
 		portals: &Portals,
 	) {
 		// create edges between portals that can see each other
@@ -274,7 +507,9 @@ impl PortalGraph {
 		for (i, (source, ord_source)) in cells.iter().enumerate() {
 			for (j, (target, ord_target)) in cells.iter().enumerate() {
 				if i != j {
-					if let Some(distance) = cost_field.get_distance_between_cells(source, target) {
+					if let Some(distance) = cost_field
+						.get_integrated_cost_between_cells_with_one_way(source, target, one_way)
+					{
 						// create the edge
 						let s_weight = cost_field.get_field_cell_value(**source);
 						let source_node = Node::new(*sector_id, **source, s_weight, **ord_source);
@@ -317,16 +552,33 @@ impl PortalGraph {
 		sector_neighbours: &[(Ordinal, SectorID)],
 	) {
 		for (ordinal, neighbour_id) in sector_neighbours.iter() {
-			let cost_field_source = sector_cost_fields.get_scaled().get(sector_id).unwrap();
-			let cost_field_target = sector_cost_fields.get_scaled().get(neighbour_id).unwrap();
+			let (Some(cost_field_source), Some(cost_field_target), Some(neighbour_portals)) = (
+				sector_cost_fields.get_scaled().get(sector_id),
+				sector_cost_fields.get_scaled().get(neighbour_id),
+				sector_portals.get().get(neighbour_id),
+			) else {
+				if !sector_cost_fields.is_void(*sector_id)
+					&& !sector_cost_fields.is_void(*neighbour_id)
+				{
+					warn!(
+						"Cannot create external edges between {:?} and non-existent sector {:?}",
+						sector_id, neighbour_id
+					);
+				}
+				continue;
+			};
 			// get portals along boundary of current sector being worked on
 			//? if a portal overlaps a corner we lose an edge pair as we only look at one ord
 			let boundary_portals = portals.get(ordinal);
 			// get inverse ordinal portals along boundary of the neighbour
-			let neighbour_portals = sector_portals.get().get(neighbour_id).unwrap();
 			let neighbour_boundary_portals = neighbour_portals.get(&ordinal.inverse());
+			let one_way = sector_cost_fields.get_one_way_field(*sector_id);
 			// create edges between the portals
 			for (i, cell) in boundary_portals.iter().enumerate() {
+				// a one-way crossing only has an edge in the permitted direction
+				if !is_move_allowed(one_way, *cell, *ordinal) {
+					continue;
+				}
 				// source of the edge
 				let source_weight = cost_field_source.get_field_cell_value(*cell);
 				let source_node = Node::new(*sector_id, *cell, source_weight, *ordinal);
@@ -341,11 +593,7 @@ impl PortalGraph {
 					ordinal.inverse(),
 				);
 				// add the dge
-				let edge = Edge::new(
-					source_node,
-					target_node,
-					SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE,
-				);
+				let edge = Edge::new(source_node, target_node, self.boundary_distance);
 				self.add_edge_external(edge);
 			}
 		}
@@ -367,13 +615,60 @@ impl PortalGraph {
 		sector_cost_fields: &SectorCostFields,
 		map_dimensions: &MapDimensions,
 	) -> &mut Self {
-		let sectors_to_rebuild =
-			map_dimensions.get_ordinal_and_ids_of_neighbouring_sectors(&changed_sector);
+		let boundaries = map_dimensions
+			.get_sector_grid()
+			.portal_ordinals(&changed_sector);
+		self.update_graph_boundaries(
+			changed_sector,
+			&boundaries,
+			sector_portals,
+			sector_cost_fields,
+			map_dimensions,
+		)
+	}
+	/// Like [PortalGraph::update_graph] but only the nodes along the
+	/// `boundaries` of the changed sector (and the facing boundaries of the
+	/// neighbours across them) are rebuilt, see
+	/// [MapDimensions::get_boundaries_affected_by]. The internal edges of the
+	/// changed sector are always rebuilt as moving between its portals may
+	/// have become more or less expensive. When a change is confined to the
+	/// interior of a sector the neighbouring sectors are left untouched
+	///
+	/// # This must run after any updates to a [Portals]!
+	pub fn update_graph_boundaries(
+		&mut self,
+		changed_sector: SectorID,
+		boundaries: &[Ordinal],
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) -> &mut Self {
+		#[cfg(feature = "trace")]
+		let _span = bevy::log::info_span!("update_portal_graph", sector = ?changed_sector, ?boundaries)
+			.entered();
+		let sectors_to_rebuild: Vec<(Ordinal, SectorID)> = map_dimensions
+			.get_ordinal_and_ids_of_neighbouring_sectors(&changed_sector)
+			.into_iter()
+			.filter(|(ord, _)| boundaries.contains(ord))
+			.collect();
+		// leave the graph untouched rather than half rebuilding it from malformed data
+		let is_known = |sector: &SectorID| {
+			sector_portals.get().contains_key(sector)
+				&& (sector_cost_fields.get_scaled().contains_key(sector)
+					|| sector_cost_fields.is_void(*sector))
+		};
+		if !is_known(&changed_sector) || sectors_to_rebuild.iter().any(|(_, s)| !is_known(s)) {
+			warn!(
+				"Cannot update the PortalGraph around non-existent sector {:?}",
+				changed_sector
+			);
+			return self;
+		}
 		let mut nodes_to_remove = vec![];
 		let original_graph = self.clone();
-		// affected nodes from the changed sector
+		// affected nodes along the changed boundaries of the changed sector
 		for n in original_graph.get_nodes().iter() {
-			if n.is_in_sector(&changed_sector) {
+			if n.is_in_sector(&changed_sector) && boundaries.contains(n.get_side()) {
 				nodes_to_remove.push(n);
 			}
 		}
@@ -390,30 +685,34 @@ impl PortalGraph {
 		for n in nodes_to_remove {
 			self.remove_node(n);
 		}
+		// the cost of moving between any remaining portals of the changed
+		// sector may have changed
+		self.edges_internal
+			.retain(|edge| !edge.get_from().is_in_sector(&changed_sector));
 		// create new nodes in changed sector
 		let portals = sector_portals.get().get(&changed_sector).unwrap();
 		self.create_sector_nodes(sector_cost_fields, &changed_sector, portals);
-		// create nodes in the neighbouring sectors
-		//TODO lets not rebuild all, on 3 sides of neighbours they should be exactly as they are
+		// create nodes in the neighbouring sectors, nodes along their other
+		// boundaries are unaffected and remain as they are
 		for (_ord, sector) in sectors_to_rebuild.iter() {
 			let portals = sector_portals.get().get(sector).unwrap();
 			self.create_sector_nodes(sector_cost_fields, sector, portals);
 		}
-		// create internal edges within the changed sector
-		let cost_field = sector_cost_fields
-			.get_scaled()
-			.get(&changed_sector)
-			.unwrap();
-		self.create_sector_internal_edges(&changed_sector, cost_field, portals);
+		// create internal edges within the changed sector, void sectors have none
+		if let Some(cost_field) = sector_cost_fields.get_scaled().get(&changed_sector) {
+			let one_way = sector_cost_fields.get_one_way_field(changed_sector);
+			self.create_sector_internal_edges(&changed_sector, cost_field, one_way, portals);
+		}
 		// recreate internal edges in the neighbouring sectors
-		//TODO lets not rebuild all, on 3 sides of neighbours they should be exactly as they are
 		for (_ord, sector) in sectors_to_rebuild.iter() {
-			let cost_field = sector_cost_fields.get_scaled().get(sector).unwrap();
+			let Some(cost_field) = sector_cost_fields.get_scaled().get(sector) else {
+				continue;
+			};
 			let portals = sector_portals.get().get(sector).unwrap();
-			self.create_sector_internal_edges(sector, cost_field, portals);
+			let one_way = sector_cost_fields.get_one_way_field(*sector);
+			self.create_sector_internal_edges(sector, cost_field, one_way, portals);
 		}
 		// create external edges from the changed sector to neighbours
-		let portals = sector_portals.get().get(&changed_sector).unwrap();
 		self.create_sector_external_edges(
 			sector_portals,
 			sector_cost_fields,
@@ -433,10 +732,184 @@ impl PortalGraph {
 				&orignal_sector,
 			);
 		}
+		self.remap_conditional_crossings(sector_portals, sector_cost_fields, map_dimensions);
+		self.cache_node_positions(map_dimensions);
+		self.cache_sector_neighbours();
 		self
 	}
 }
 
+/// A crossing between neighbouring sectors as the `(sector, portal)` nodes
+/// at either end of an external edge of the [PortalGraph]
+pub type Crossing = ((SectorID, FieldCell), (SectorID, FieldCell));
+
+// conditional crossings
+impl PortalGraph {
+	/// Get the key of a crossing between two portal nodes
+	fn crossing_key(a: (SectorID, FieldCell), b: (SectorID, FieldCell)) -> Crossing {
+		if a <= b {
+			(a, b)
+		} else {
+			(b, a)
+		}
+	}
+	/// Make the external edge between the portal nodes `a` and `b` (see
+	/// [PortalGraph::get_external_connections]) conditional on the
+	/// `condition`. While the condition is disabled routes cannot use the
+	/// crossing, such as when a drawbridge spanning it is raised. Other
+	/// crossings between the same sectors are unaffected and each crossing
+	/// has its own condition. When the graph is rebuilt from [CostField]
+	/// changes the condition follows the portal whose window covers the
+	/// portal it was set on
+	pub fn set_crossing_condition(
+		&mut self,
+		a: (SectorID, FieldCell),
+		b: (SectorID, FieldCell),
+		condition: ConditionId,
+	) {
+		self.conditional_crossings
+			.insert(PortalGraph::crossing_key(a, b), condition);
+		self.cache_sector_neighbours();
+	}
+	/// Make every current crossing between the neighbouring sectors `a` and
+	/// `b` conditional on the `condition`, see
+	/// [PortalGraph::set_crossing_condition]
+	pub fn set_boundary_condition(&mut self, a: SectorID, b: SectorID, condition: ConditionId) {
+		for (from, to) in self.get_external_connections() {
+			if from.0 == a && to.0 == b {
+				self.set_crossing_condition(from, to, condition);
+			}
+		}
+	}
+	/// Make the crossing between the portal nodes `a` and `b` unconditional
+	pub fn remove_crossing_condition(
+		&mut self,
+		a: (SectorID, FieldCell),
+		b: (SectorID, FieldCell),
+	) {
+		self.conditional_crossings
+			.remove(&PortalGraph::crossing_key(a, b));
+		self.cache_sector_neighbours();
+	}
+	/// Get the condition of the crossing between the portal nodes `a` and `b`
+	pub fn get_crossing_condition(
+		&self,
+		a: (SectorID, FieldCell),
+		b: (SectorID, FieldCell),
+	) -> Option<ConditionId> {
+		self.conditional_crossings
+			.get(&PortalGraph::crossing_key(a, b))
+			.copied()
+	}
+	/// Get every crossing (as a pair of portal nodes) which depends on the
+	/// `condition`
+	pub fn get_condition_crossings(&self, condition: ConditionId) -> Vec<Crossing> {
+		self.conditional_crossings
+			.iter()
+			.filter(|(_, c)| **c == condition)
+			.map(|(crossing, _)| *crossing)
+			.collect()
+	}
+	/// Enable or disable the crossings of a `condition`. Disabled crossings
+	/// are skipped by A-Star, the portals are left as they are so the
+	/// condition can be toggled cheaply. Cached routes aren't affected, send
+	/// an [EventSetCondition] instead to have routes crossing a disabled
+	/// condition invalidated
+	pub fn set_condition_enabled(&mut self, condition: ConditionId, enabled: bool) {
+		if enabled {
+			self.disabled_conditions.remove(&condition);
+		} else {
+			self.disabled_conditions.insert(condition);
+		}
+		self.cache_sector_neighbours();
+	}
+	/// Whether the crossings of a `condition` can be used, conditions are
+	/// enabled by default
+	pub fn is_condition_enabled(&self, condition: ConditionId) -> bool {
+		!self.disabled_conditions.contains(&condition)
+	}
+	/// Whether routes can cross between the portal nodes `a` and `b`
+	pub fn is_crossing_enabled(&self, a: (SectorID, FieldCell), b: (SectorID, FieldCell)) -> bool {
+		self.get_crossing_condition(a, b)
+			.is_none_or(|condition| self.is_condition_enabled(condition))
+	}
+	/// Whether a [Route] crosses a disabled crossing. A [Route] only holds
+	/// one portal of each sector, so a step between two sectors is blocked
+	/// when a disabled crossing between them uses either portal of the step
+	pub fn is_route_blocked(&self, route: &Route) -> bool {
+		let disabled: Vec<&Crossing> = self
+			.conditional_crossings
+			.iter()
+			.filter(|(_, condition)| !self.is_condition_enabled(**condition))
+			.map(|(crossing, _)| crossing)
+			.collect();
+		if disabled.is_empty() {
+			return false;
+		}
+		route.get().windows(2).any(|pair| {
+			pair[0].0 != pair[1].0
+				&& disabled.iter().any(|(a, b)| {
+					let is_between = (a.0 == pair[0].0 && b.0 == pair[1].0)
+						|| (a.0 == pair[1].0 && b.0 == pair[0].0);
+					is_between && [a, b].iter().any(|node| pair.contains(node))
+				})
+		})
+	}
+	/// The portals of a conditional crossing may have moved after the graph
+	/// was updated, a crossing whose edge no longer exists is moved to the
+	/// external edge between the same sectors whose portal window covers
+	/// the old portal. A crossing with no such edge is kept so that it can
+	/// be matched again by a later update
+	fn remap_conditional_crossings(
+		&mut self,
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) {
+		if self.conditional_crossings.is_empty() {
+			return;
+		}
+		let connections: BTreeSet<Crossing> = self
+			.get_external_connections()
+			.into_iter()
+			.map(|(from, to)| PortalGraph::crossing_key(from, to))
+			.collect();
+		let stale: Vec<(Crossing, ConditionId)> = self
+			.conditional_crossings
+			.iter()
+			.filter(|(crossing, _)| !connections.contains(crossing))
+			.map(|(crossing, condition)| (*crossing, *condition))
+			.collect();
+		for ((a, b), condition) in stale {
+			let Some(portals) = sector_portals.get().get(&a.0) else {
+				continue;
+			};
+			let replacement = connections.iter().find(|crossing| {
+				let (from, to) = if crossing.0 .0 == a.0 {
+					(crossing.0, crossing.1)
+				} else {
+					(crossing.1, crossing.0)
+				};
+				from.0 == a.0
+					&& to.0 == b.0 && !self.conditional_crossings.contains_key(crossing)
+					&& portals
+						.expand_portal_into_goals(
+							sector_cost_fields,
+							&from.0,
+							&from.1,
+							&to.0,
+							map_dimensions,
+						)
+						.contains(&a.1)
+			});
+			if let Some(replacement) = replacement.copied() {
+				self.conditional_crossings.remove(&(a, b));
+				self.conditional_crossings.insert(replacement, condition);
+			}
+		}
+	}
+}
+
 /// An edge between [PortalNode]s comes in two varieties.
 ///
 /// Internal means it's an edge to another Portal within the same sector, External means it is a Portal to a neighbouring sector Portal
@@ -494,8 +967,195 @@ impl AStarQueueItem {
 	}
 }
 
+/// Why a path could not be found by [PortalGraph::find_best_path_within_budget]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathSearchError {
+	/// The target cannot be reached from the source
+	NoPath,
+	/// The cheapest path to the target would cost more than the budget
+	PathTooExpensive,
+}
+
+impl std::fmt::Display for PathSearchError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			PathSearchError::NoPath => write!(f, "The target cannot be reached"),
+			PathSearchError::PathTooExpensive => {
+				write!(f, "The cheapest path exceeds the cost budget")
+			}
+		}
+	}
+}
+
+impl std::error::Error for PathSearchError {}
+
 // graph querying
 impl PortalGraph {
+	/// Get the world-space position of a portal across the plane of the map,
+	/// `x` eastwards and `y` northwards from the centre of the map. In 2d
+	/// this is the position of the portal, in 3d see
+	/// [PortalGraph::get_node_position_xyz]. Returns [None] if the portal
+	/// isn't a node of the graph
+	pub fn get_node_position(&self, sector_id: SectorID, field_cell: FieldCell) -> Option<Vec2> {
+		self.node_positions.get(&(sector_id, field_cell)).copied()
+	}
+	/// Get the `x-z` world-space position of a portal with a `y` of `0.0`.
+	/// Returns [None] if the portal isn't a node of the graph
+	#[cfg(feature = "3d")]
+	pub fn get_node_position_xyz(
+		&self,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+	) -> Option<Vec3> {
+		self.get_node_position(sector_id, field_cell)
+			.map(|position| Vec3::new(position.x, 0.0, -position.y))
+	}
+	/// Get the straight line world-space distance between two portals, such
+	/// as for the heuristic of a [PortalCostModel]. Returns [None] if either
+	/// portal isn't a node of the graph
+	pub fn get_node_distance(
+		&self,
+		from: (SectorID, FieldCell),
+		to: (SectorID, FieldCell),
+	) -> Option<f32> {
+		let from = self.get_node_position(from.0, from.1)?;
+		let to = self.get_node_position(to.0, to.1)?;
+		Some(from.distance(to))
+	}
+	/// Get the straight line distance between two portals measured in
+	/// [FieldCell]s from their cached positions. Every step between cells
+	/// costs at least `1` so this never exceeds the cost of travelling
+	/// between them, it is `0` if either position isn't cached or crossing
+	/// a sector boundary is free
+	fn get_node_cell_distance(
+		&self,
+		from: (SectorID, FieldCell),
+		to: (SectorID, FieldCell),
+	) -> i32 {
+		if self.boundary_distance < 1 || self.node_cell_size <= 0.0 {
+			return 0;
+		}
+		self.get_node_distance(from, to).map_or(0, |distance| {
+			(distance / self.node_cell_size).floor() as i32
+		})
+	}
+	/// Find the portal closest to a world-space `position` across the plane
+	/// of the map (see [PortalGraph::get_node_position]), ties are broken by
+	/// the ordering of the portals. Returns [None] if the graph has no nodes
+	pub fn find_nearest_portal(&self, position: Vec2) -> Option<(SectorID, FieldCell)> {
+		let mut nearest = None;
+		let mut nearest_distance = f32::INFINITY;
+		for (portal, node_position) in self.node_positions.iter() {
+			let distance = node_position.distance_squared(position);
+			if distance < nearest_distance {
+				nearest = Some(*portal);
+				nearest_distance = distance;
+			}
+		}
+		nearest
+	}
+	/// Find the portal closest to a 3d world-space `position`, the `y` of
+	/// the position is ignored. Returns [None] if the graph has no nodes
+	#[cfg(feature = "3d")]
+	pub fn find_nearest_portal_xyz(&self, position: Vec3) -> Option<(SectorID, FieldCell)> {
+		self.find_nearest_portal(Vec2::new(position.x, -position.z))
+	}
+	/// Find a cheap preview of the route from the `source` sector to the
+	/// `target` sector, such as for drawing a line under the cursor every
+	/// frame before a path has been requested. Only the sectors are searched,
+	/// an A-Star over the sectors joined by the external edges of the graph
+	/// where each step costs one, so no portal windows are expanded and no
+	/// fields are built. The preview can differ from the [Route] of a path
+	/// request and may pass through a sector whose portals aren't connected
+	/// to one another. Returns the sectors from `source` to `target`, or
+	/// [None] if the `target` can't be reached
+	pub fn preview_sector_route(
+		&self,
+		source: SectorID,
+		target: SectorID,
+	) -> Option<Vec<SectorID>> {
+		if source == target {
+			return Some(vec![source]);
+		}
+		let heuristic = |sector: &SectorID| {
+			sector.get_column().abs_diff(target.get_column())
+				+ sector.get_row().abs_diff(target.get_row())
+		};
+		let mut steps = BTreeMap::from([(source, 0)]);
+		let mut came_from = BTreeMap::new();
+		let mut open = std::collections::BinaryHeap::new();
+		open.push(std::cmp::Reverse((heuristic(&source), 0, source)));
+		while let Some(std::cmp::Reverse((_, step, sector))) = open.pop() {
+			if sector == target {
+				let mut path = vec![target];
+				while let Some(previous) = came_from.get(path.last().unwrap()) {
+					path.push(*previous);
+				}
+				path.reverse();
+				return Some(path);
+			}
+			// skip stale entries that have since been reached in fewer steps
+			if step > steps[&sector] {
+				continue;
+			}
+			for neighbour in self.sector_neighbours.get(&sector).into_iter().flatten() {
+				let n_step = step + 1;
+				if steps
+					.get(neighbour)
+					.is_none_or(|existing| n_step < *existing)
+				{
+					steps.insert(*neighbour, n_step);
+					came_from.insert(*neighbour, sector);
+					open.push(std::cmp::Reverse((
+						n_step + heuristic(neighbour),
+						n_step,
+						*neighbour,
+					)));
+				}
+			}
+		}
+		None
+	}
+	/// As with [PortalGraph::preview_sector_route] as a polyline through the
+	/// world-space centre of each sector across the plane of the map, `x`
+	/// eastwards and `y` northwards from the centre of the map
+	pub fn preview_route_polyline(
+		&self,
+		source: SectorID,
+		target: SectorID,
+		map_dimensions: &MapDimensions,
+	) -> Option<Vec<Vec2>> {
+		let (length, depth) = map_dimensions.get_size();
+		let resolution = map_dimensions.get_sector_resolution() as f32;
+		self.preview_sector_route(source, target).map(|sectors| {
+			sectors
+				.iter()
+				.map(|sector| {
+					Vec2::new(
+						-(length as f32) / 2.0 + (sector.get_column() as f32 + 0.5) * resolution,
+						depth as f32 / 2.0 - (sector.get_row() as f32 + 0.5) * resolution,
+					)
+				})
+				.collect()
+		})
+	}
+	/// As with [PortalGraph::preview_route_polyline] in the `x-z` plane with
+	/// a `y` of `0.0`
+	#[cfg(feature = "3d")]
+	pub fn preview_route_polyline_xyz(
+		&self,
+		source: SectorID,
+		target: SectorID,
+		map_dimensions: &MapDimensions,
+	) -> Option<Vec<Vec3>> {
+		self.preview_route_polyline(source, target, map_dimensions)
+			.map(|points| {
+				points
+					.into_iter()
+					.map(|point| Vec3::new(point.x, 0.0, -point.y))
+					.collect()
+			})
+	}
 	/// From any field cell at a `source` sector find any pathable portals witihn that sector and generate a path from each portal to the target. Compare the results and return the path with the best cost associated with it
 	pub fn find_best_path(
 		&self,
@@ -504,24 +1164,103 @@ impl PortalGraph {
 		sector_portals: &SectorPortals,
 		sector_cost_fields: &SectorCostFields,
 	) -> Option<Vec<(SectorID, FieldCell)>> {
+		self.find_best_path_with_model(
+			source,
+			target,
+			sector_portals,
+			sector_cost_fields,
+			&DefaultPortalCostModel,
+		)
+	}
+	/// As with [PortalGraph::find_best_path] but paths are scored with a
+	/// custom [PortalCostModel]
+	pub fn find_best_path_with_model(
+		&self,
+		source: (SectorID, FieldCell),
+		target: (SectorID, FieldCell),
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+		cost_model: &dyn PortalCostModel,
+	) -> Option<Vec<(SectorID, FieldCell)>> {
+		self.search(
+			source,
+			target,
+			sector_portals,
+			sector_cost_fields,
+			cost_model,
+			None,
+		)
+		.ok()
+	}
+	/// As with [PortalGraph::find_best_path_with_model] but the search is
+	/// abandoned as soon as every path being explored is scored higher than
+	/// `max_cost`, the score being the integrated cost of the path as judged
+	/// by the `cost_model`. Returns [PathSearchError::PathTooExpensive] when
+	/// paths exist but all of them exceed the budget
+	pub fn find_best_path_within_budget(
+		&self,
+		source: (SectorID, FieldCell),
+		target: (SectorID, FieldCell),
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+		cost_model: &dyn PortalCostModel,
+		max_cost: i32,
+	) -> Result<Vec<(SectorID, FieldCell)>, PathSearchError> {
+		self.search(
+			source,
+			target,
+			sector_portals,
+			sector_cost_fields,
+			cost_model,
+			Some(max_cost),
+		)
+	}
+	/// Find the best path from `source` to `target`, optionally abandoning
+	/// any path scored higher than `max_cost`
+	fn search(
+		&self,
+		source: (SectorID, FieldCell),
+		target: (SectorID, FieldCell),
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+		cost_model: &dyn PortalCostModel,
+		max_cost: Option<i32>,
+	) -> Result<Vec<(SectorID, FieldCell)>, PathSearchError> {
+		#[cfg(feature = "trace")]
+		let _span = bevy::log::info_span!(
+			"portal_graph_search",
+			source_sector = ?source.0,
+			target_sector = ?target.0,
+			?max_cost
+		)
+		.entered();
 		let cost_fields_scaled = sector_cost_fields.get_scaled();
 		// find portals reachable by the source actor position
 		let source_sector_id = source.0;
 		let source_field_cell = source.1;
-		let source_weight = sector_cost_fields
-			.get_scaled()
-			.get(&source_sector_id)
-			.unwrap()
-			.get_field_cell_value(source_field_cell);
+		let (Some(source_cost_field), Some(source_sector_portals)) = (
+			cost_fields_scaled.get(&source_sector_id),
+			sector_portals.get().get(&source_sector_id),
+		) else {
+			warn!(
+				"Cannot path from non-existent sector {:?}",
+				source_sector_id
+			);
+			return Err(PathSearchError::NoPath);
+		};
+		let source_weight = source_cost_field.get_field_cell_value(source_field_cell);
+		let source_one_way = sector_cost_fields.get_one_way_field(source_sector_id);
 		let mut source_portals = Vec::new();
-		let portals = sector_portals.get().get(&source_sector_id).unwrap();
 		let ords = [Ordinal::North, Ordinal::South, Ordinal::West, Ordinal::East];
 		for ord in ords.iter() {
-			for cell in portals.get(ord) {
-				let cost_field = cost_fields_scaled.get(&source_sector_id).unwrap();
-				if let Some(source_distance) =
-					cost_field.get_distance_between_cells(&source_field_cell, cell)
-				{
+			for cell in source_sector_portals.get(ord) {
+				let cost_field = source_cost_field;
+				if let Some(source_distance) = cost_field
+					.get_integrated_cost_between_cells_with_one_way(
+						&source_field_cell,
+						cell,
+						source_one_way,
+					) {
 					source_portals.push((*cell, *ord, source_distance));
 				}
 				// if cost_field.is_cell_pair_reachable(source_field_cell, *cell) {
@@ -532,32 +1271,60 @@ impl PortalGraph {
 		// find portals that can reach the target/goal
 		let target_sector_id = target.0;
 		let target_field_cell = target.1;
-		let target_weight = cost_fields_scaled
-			.get(&target_sector_id)
-			.unwrap()
-			.get_field_cell_value(target_field_cell);
+		let (Some(target_cost_field), Some(target_sector_portals)) = (
+			cost_fields_scaled.get(&target_sector_id),
+			sector_portals.get().get(&target_sector_id),
+		) else {
+			warn!("Cannot path to non-existent sector {:?}", target_sector_id);
+			return Err(PathSearchError::NoPath);
+		};
+		let target_weight = target_cost_field.get_field_cell_value(target_field_cell);
+		let target_one_way = sector_cost_fields.get_one_way_field(target_sector_id);
 		let mut target_portals = Vec::new();
-		let portals = sector_portals.get().get(&target_sector_id).unwrap();
 		let ords = [Ordinal::North, Ordinal::South, Ordinal::West, Ordinal::East];
 		for ord in ords.iter() {
-			for cell in portals.get(ord) {
-				let cost_field = cost_fields_scaled.get(&target_sector_id).unwrap();
-				if cost_field.is_cell_pair_reachable(target_field_cell, *cell) {
+			for cell in target_sector_portals.get(ord) {
+				// with one-way movement the goal must be reachable from the portal
+				let is_reachable = match target_one_way {
+					Some(one_way) => target_cost_field
+						.get_integrated_cost_between_cells_with_one_way(
+							cell,
+							&target_field_cell,
+							Some(one_way),
+						)
+						.is_some(),
+					None => target_cost_field.is_cell_pair_reachable(target_field_cell, *cell),
+				};
+				if is_reachable {
 					target_portals.push((*cell, *ord));
 				}
 			}
 		}
+		source_portals.retain(|(cell, _, _)| {
+			cost_model.is_portal_passable((source_sector_id, *cell))
+				&& cost_model.is_edge_passable(source, (source_sector_id, *cell))
+		});
+		target_portals.retain(|(cell, _)| {
+			cost_model.is_portal_passable((target_sector_id, *cell))
+				&& cost_model.is_edge_passable((target_sector_id, *cell), target)
+		});
 		// iterate over the source and target portals to find a series of paths
 		let mut best_path: Option<(i32, Vec<(SectorID, FieldCell)>)> = None;
+		// whether any path was abandoned for exceeding the budget
+		let mut over_budget = false;
 		// if local sector add a cheaper direct route, prevents pathing out of a sector and back in when there are extreme local costs
 		//TODO maybe skip searching for other paths if this is true? improve perf but would a really bad local route be given (maybe only under extreme circumstances tho)
-		if source_sector_id == target_sector_id {
-			if let Some(cost) = cost_fields_scaled
-				.get(&source_sector_id)
-				.unwrap()
-				.get_distance_between_cells(&source_field_cell, &target_field_cell)
-			{
-				best_path = Some((cost, vec![(target_sector_id, target_field_cell)]));
+		if source_sector_id == target_sector_id && cost_model.is_edge_passable(source, target) {
+			if let Some(cost) = source_cost_field.get_integrated_cost_between_cells_with_one_way(
+				&source_field_cell,
+				&target_field_cell,
+				source_one_way,
+			) {
+				if max_cost.is_some_and(|max| cost > max) {
+					over_budget = true;
+				} else {
+					best_path = Some((cost, vec![(target_sector_id, target_field_cell)]));
+				}
 			}
 		}
 		for (source_portal, source_ordinal, source_distance) in source_portals.iter() {
@@ -579,23 +1346,32 @@ impl PortalGraph {
 					source_portal_node,
 					target_portal_node,
 					*source_distance,
+					cost_model,
+					max_cost,
+					&mut over_budget,
 				);
 			}
 		}
-		if let Some((_score, p)) = best_path {
-			Some(p)
-		} else {
-			None
+		match best_path {
+			Some((_score, p)) => Ok(p),
+			None if over_budget => Err(PathSearchError::PathTooExpensive),
+			None => Err(PathSearchError::NoPath),
 		}
 	}
 	/// Find a path from a source [Node] to a target [Node] if it
-	/// exists and return the path with a weighting of how expensive it is
+	/// exists and return the path with a weighting of how expensive it is.
+	/// Paths scored higher than `max_cost` are abandoned and flag
+	/// `over_budget`
+	#[allow(clippy::too_many_arguments)]
 	fn find_path_between_sector_portals(
 		&self,
 		best_path: &mut Option<(i32, Vec<(SectorID, FieldCell)>)>,
 		source_node: Node,
 		target_node: Node,
 		source_distance: i32,
+		cost_model: &dyn PortalCostModel,
+		max_cost: Option<i32>,
+		over_budget: &mut bool,
 	) {
 		let current_best_score = if let Some((score, _)) = best_path {
 			Some(*score)
@@ -607,8 +1383,15 @@ impl PortalGraph {
 			source_node,
 			target_node,
 			source_distance,
+			cost_model,
+			max_cost,
+			over_budget,
 		) {
 			let total_weight = path.0;
+			if max_cost.is_some_and(|max| total_weight > max) {
+				*over_budget = true;
+				return;
+			}
 			let mut p = Vec::new();
 			// extract portal node into a <sector, field_cell> representation
 			for node in path.1 {
@@ -641,19 +1424,31 @@ impl PortalGraph {
 	fn find_edges_external(&self, source: Node) -> Vec<&Edge> {
 		let mut edges = vec![];
 		for edge in self.get_edges_external().iter() {
-			if *edge.get_from() == source && *edge.get_to().get_sector() != *source.get_sector() {
+			if *edge.get_from() == source
+				&& *edge.get_to().get_sector() != *source.get_sector()
+				&& self.is_crossing_enabled(
+					(*source.get_sector(), *source.get_portal_cell()),
+					(
+						*edge.get_to().get_sector(),
+						*edge.get_to().get_portal_cell(),
+					),
+				) {
 				edges.push(edge);
 			}
 		}
 		edges
 	}
 	/// Based on https://github.com/BlondeBurrito/pathfinding_astar
+	#[allow(clippy::too_many_arguments)]
 	fn astar(
 		&self,
 		current_best_score: Option<i32>,
 		source_node: Node,
 		target_node: Node,
 		source_distance: i32,
+		cost_model: &dyn PortalCostModel,
+		max_cost: Option<i32>,
+		over_budget: &mut bool,
 	) -> Option<(i32, Vec<Node>)> {
 		let nodes = self.get_nodes();
 		// ensure nodes data contains start and end points
@@ -667,8 +1462,12 @@ impl PortalGraph {
 			// panic!("Node data does not contain end node {:?}", target_node);
 			return None;
 		}
+		let target = (*target_node.get_sector(), *target_node.get_portal_cell());
 		// retreive the weight of the start point
-		let start_weight: i32 = source_node.get_weight() as i32;
+		let start_weight: i32 = cost_model.node_cost(
+			(*source_node.get_sector(), *source_node.get_portal_cell()),
+			source_node.get_weight(),
+		);
 
 		// Every time we process a new node we add it to a map.
 		// If a node has already been recorded then we replace it if it has a better a-star score (smaller number)
@@ -707,6 +1506,11 @@ impl PortalGraph {
 					return None;
 				}
 			}
+			// likewise once every path being explored exceeds the budget
+			if max_cost.is_some_and(|max| max < current_path.score) {
+				*over_budget = true;
+				return None;
+			}
 			// what edge direction to explore
 			let edge_direction = current_path.edge_direction;
 			// Grab the neighbours with their distances from the current path so we can explore each
@@ -717,12 +1521,33 @@ impl PortalGraph {
 			// Process each new path
 			for n in neighbours.iter() {
 				let distance_traveled_so_far: i32 = current_path.cumulative_distance;
-				let distance_to_this_neighbour: i32 = n.get_distance();
+				let from = (*n.get_from().get_sector(), *n.get_from().get_portal_cell());
+				let to = (*n.get_to().get_sector(), *n.get_to().get_portal_cell());
+				if !cost_model.is_sector_passable(to.0)
+					|| !cost_model.is_portal_passable(to)
+					|| !cost_model.is_edge_passable(from, to)
+				{
+					continue;
+				}
+				let distance_to_this_neighbour: i32 =
+					cost_model.edge_cost(from, to, n.get_distance());
 				// Calculate the total distance from the start to this neighbour node
 				let distance_traveled = distance_traveled_so_far + distance_to_this_neighbour;
-				let node_weight: i32 = n.get_to().get_weight() as i32;
+				let weight = n.get_to().get_weight();
+				let mut node_weight: i32 = cost_model.node_cost(to, weight);
+				// an internal edge is the integrated cost of the cells walked
+				// which already includes entering the portal, only apply the
+				// adjustment of the cost model on top of it
+				if edge_direction == Direction::Internal {
+					node_weight -= weight as i32;
+				}
 				// Now we know the overall distance traveled and the weight of where we're going to we can score it
-				let astar_score = distance_traveled + node_weight;
+				let astar_score = distance_traveled
+					+ node_weight + cost_model.heuristic(
+					to,
+					target,
+					self.get_node_cell_distance(to, target),
+				);
 				// Create a vec of the nodes traversed to get to this `n`
 				let mut previous_nodes_traversed = current_path.node_history.clone();
 				previous_nodes_traversed.push(current_path.current_node);
@@ -839,6 +1664,57 @@ use super::*;
 		assert_eq!(actual, result);
 	}
 	#[test]
+	fn node_positions() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for (sector_id, _cost_fields) in sector_cost_fields.get_scaled().iter() {
+			let portals = sector_portals.get_mut();
+			match portals.get_mut(sector_id) {
+				Some(portals) => portals.recalculate_portals(&sector_cost_fields, sector_id, &map_dimensions),
+				None => panic!("Key {:?} not found in Portals", sector_id),
+			}
+		}
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		// the portal on the eastern boundary of the top-left sector
+		let portal = (SectorID::new(0, 0), FieldCell::new(9, 4));
+		let position = graph.get_node_position(portal.0, portal.1).unwrap();
+		assert_eq!(Vec2::new(-5.5, 10.5), position);
+		assert!(graph.get_node_position(SectorID::new(0, 0), FieldCell::new(4, 4)).is_none());
+		// the neighbouring portal across the boundary is one cell away
+		let neighbour = (SectorID::new(1, 0), FieldCell::new(0, 4));
+		assert_eq!(Some(1.0), graph.get_node_distance(portal, neighbour));
+		assert_eq!(Some(portal), graph.find_nearest_portal(Vec2::new(-6.0, 11.0)));
+		// the search heuristic measures the same distance in cells
+		assert_eq!(1, graph.get_node_cell_distance(portal, neighbour));
+		let far = (SectorID::new(2, 0), FieldCell::new(0, 4));
+		assert_eq!(11, graph.get_node_cell_distance(portal, far));
+	}
+	#[test]
+	fn boundary_distance() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for (sector_id, _cost_fields) in sector_cost_fields.get_scaled().iter() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let distances = |graph: &PortalGraph| -> BTreeSet<i32> {
+			graph.get_edges_external().iter().map(|edge| edge.get_distance()).collect()
+		};
+		let mut graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		assert_eq!(BTreeSet::from([SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE]), distances(&graph));
+		let built = PortalGraph::new_with_boundary_distance(&sector_portals, &sector_cost_fields, &map_dimensions, 7);
+		assert_eq!(BTreeSet::from([7]), distances(&built));
+		// updating the distance in place matches building with it
+		graph.set_boundary_distance(7);
+		assert_eq!(built.get_edges_external(), graph.get_edges_external());
+		assert_eq!(BTreeSet::from([7]), distances(&graph));
+		// and the distance survives rebuilding the graph
+		graph.rebuild(&sector_portals, &sector_cost_fields, &map_dimensions);
+		assert_eq!(7, graph.get_boundary_distance());
+		assert_eq!(BTreeSet::from([7]), distances(&graph));
+	}
+	#[test]
 	fn edge_count_internal() {
 		//init
 		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
@@ -1113,9 +1989,309 @@ use super::*;
 		let target_portal_node = Node::new(target_sector, target_field, target_weight, Ordinal::North);
 
 		let mut best_path: Option<(i32, Vec<(SectorID, FieldCell)>)> = None;
-		graph.find_path_between_sector_portals(&mut best_path, source_portal_node, target_portal_node, 0);
+		graph.find_path_between_sector_portals(&mut best_path, source_portal_node, target_portal_node, 0, &DefaultPortalCostModel, None, &mut false);
 		let actual = vec![(SectorID::new(0, 0), FieldCell::new(4, 9)), (SectorID::new(0, 1), FieldCell::new(4, 0)), (SectorID::new(0, 1), FieldCell::new(4, 9)), (SectorID::new(0, 2), FieldCell::new(4, 0))];
 		
 		assert_eq!(actual, best_path.unwrap().1);
 	}
+	#[test]
+	fn target_weight_counted_once() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let source = Node::new(SectorID::new(0, 0), FieldCell::new(4, 9), 1, Ordinal::South);
+		// the target is reached along an internal edge across sector (0, 1)
+		let target = Node::new(SectorID::new(0, 1), FieldCell::new(4, 9), 1, Ordinal::South);
+		let mut best_path: Option<(i32, Vec<(SectorID, FieldCell)>)> = None;
+		graph.find_path_between_sector_portals(&mut best_path, source, target, 0, &DefaultPortalCostModel, None, &mut false);
+		// one step across the boundary and nine cells of cost 1 crossing the
+		// sector, the last of which is the target itself
+		assert_eq!(10, best_path.unwrap().0);
+	}
+	/// Penalises any route passing through a particular sector
+	struct AvoidSector(SectorID);
+	impl PortalCostModel for AvoidSector {
+		fn node_cost(&self, node: (SectorID, FieldCell), weight: u8) -> i32 {
+			if node.0 == self.0 { 1000 } else { weight as i32 }
+		}
+	}
+	#[test]
+	fn best_path_with_cost_model() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		// route diagonally so that there are two equally short choices
+		let source = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let target = (SectorID::new(1, 1), FieldCell::new(4, 4));
+		for (avoid, expected) in [(SectorID::new(1, 0), SectorID::new(0, 1)), (SectorID::new(0, 1), SectorID::new(1, 0))] {
+			let path = graph.find_best_path_with_model(source, target, &sector_portals, &sector_cost_fields, &AvoidSector(avoid)).unwrap();
+			assert!(path.iter().all(|(sector, _)| *sector != avoid));
+			assert!(path.iter().any(|(sector, _)| *sector == expected));
+		}
+	}
+	#[test]
+	fn conditional_crossing() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let mut graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let drawbridge = ConditionId::new(1);
+		graph.set_boundary_condition(SectorID::new(0, 0), SectorID::new(1, 0), drawbridge);
+		let crossings = graph.get_condition_crossings(drawbridge);
+		assert_eq!(1, crossings.len());
+		let (a, b) = crossings[0];
+		assert_eq!(Some(drawbridge), graph.get_crossing_condition(b, a));
+		let source = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let target = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let direct = graph.find_best_path(source, target, &sector_portals, &sector_cost_fields).unwrap();
+		assert!(direct.iter().any(|(sector, _)| *sector == SectorID::new(1, 0)));
+		assert!(!graph.is_route_blocked(&Route::new(direct.clone())));
+		// raising the drawbridge forces a detour through the southern sectors
+		graph.set_condition_enabled(drawbridge, false);
+		assert!(graph.is_route_blocked(&Route::new(direct)));
+		let detour = graph.find_best_path(source, target, &sector_portals, &sector_cost_fields).unwrap();
+		assert_eq!(SectorID::new(0, 0), detour[0].0);
+		assert_eq!(SectorID::new(0, 1), detour[1].0);
+		assert!(!graph.is_route_blocked(&Route::new(detour)));
+		graph.set_condition_enabled(drawbridge, true);
+		assert!(graph.is_condition_enabled(drawbridge));
+		graph.remove_crossing_condition(a, b);
+		assert!(graph.get_condition_crossings(drawbridge).is_empty());
+	}
+	#[test]
+	fn conditions_per_crossing() {
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let west = SectorID::new(0, 0);
+		// a pillar splits the boundary into a northern and a southern crossing
+		sector_cost_fields.set_field_cell_value(west, 255, FieldCell::new(9, 5), &map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let mut graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let crossing = |graph: &PortalGraph, is_north: bool| {
+			graph
+				.get_external_connections()
+				.into_iter()
+				.find(|(from, _)| from.0 == west && (from.1.get_row() < 5) == is_north)
+				.unwrap()
+		};
+		let (north, south) = (crossing(&graph, true), crossing(&graph, false));
+		let (gate_north, gate_south) = (ConditionId::new(1), ConditionId::new(2));
+		graph.set_crossing_condition(north.0, north.1, gate_north);
+		graph.set_crossing_condition(south.0, south.1, gate_south);
+		// the second condition on the boundary doesn't replace the first
+		assert_eq!(Some(gate_north), graph.get_crossing_condition(north.0, north.1));
+		assert_eq!(Some(gate_south), graph.get_crossing_condition(south.0, south.1));
+		let source = (west, FieldCell::new(4, 1));
+		let target = (SectorID::new(1, 0), FieldCell::new(4, 1));
+		graph.set_condition_enabled(gate_north, false);
+		// the southern crossing can still be used
+		let path = graph.find_best_path(source, target, &sector_portals, &sector_cost_fields).unwrap();
+		assert!(path.iter().all(|node| *node != north.0 && *node != north.1));
+		assert!(graph.is_route_blocked(&Route::new(vec![north.0, target])));
+		assert!(!graph.is_route_blocked(&Route::new(vec![south.0, target])));
+		graph.set_condition_enabled(gate_south, false);
+		assert!(graph.find_best_path(source, target, &sector_portals, &sector_cost_fields).is_none());
+		// narrowing the northern window moves its portal, the condition follows it
+		sector_cost_fields.set_field_cell_value(west, 255, FieldCell::new(9, 3), &map_dimensions);
+		sector_portals.update_portals(west, &sector_cost_fields, &map_dimensions);
+		sector_portals.update_portals(SectorID::new(1, 0), &sector_cost_fields, &map_dimensions);
+		graph.update_graph(west, &sector_portals, &sector_cost_fields, &map_dimensions);
+		let moved = crossing(&graph, true);
+		assert_ne!(north, moved);
+		assert_eq!(Some(gate_north), graph.get_crossing_condition(moved.0, moved.1));
+		assert_eq!(Some(gate_south), graph.get_crossing_condition(south.0, south.1));
+	}
+	#[test]
+	fn one_way_crossing() {
+		let map_dimensions = MapDimensions::new(30, 10, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		// a ledge along the eastern boundary of the first sector can be dropped off but not climbed
+		for row in 0..FIELD_RESOLUTION {
+			sector_cost_fields.set_one_way(SectorID::new(0, 0), FieldCell::new(9, row), Ordinal::East, &map_dimensions);
+		}
+		assert!(sector_cost_fields.get_one_way_field(SectorID::new(0, 0)).is_none());
+		assert!(sector_cost_fields.get_one_way_field(SectorID::new(1, 0)).is_some());
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let west = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let east = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		assert!(graph.find_best_path(west, east, &sector_portals, &sector_cost_fields).is_some());
+		assert!(graph.find_best_path(east, west, &sector_portals, &sector_cost_fields).is_none());
+		// clearing the ledge restores the crossing
+		for row in 0..FIELD_RESOLUTION {
+			sector_cost_fields.clear_one_way(SectorID::new(0, 0), FieldCell::new(9, row), Ordinal::East, &map_dimensions);
+		}
+		assert!(sector_cost_fields.get_one_way_fields().is_empty());
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		assert!(graph.find_best_path(east, west, &sector_portals, &sector_cost_fields).is_some());
+	}
+	#[test]
+	fn best_path_within_budget() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let source = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let target = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let unlimited = graph.find_best_path(source, target, &sector_portals, &sector_cost_fields).unwrap();
+		let within = graph.find_best_path_within_budget(source, target, &sector_portals, &sector_cost_fields, &DefaultPortalCostModel, 1000);
+		assert_eq!(Ok(unlimited), within);
+		let too_far = graph.find_best_path_within_budget(source, target, &sector_portals, &sector_cost_fields, &DefaultPortalCostModel, 5);
+		assert_eq!(Err(PathSearchError::PathTooExpensive), too_far);
+		let missing = (SectorID::new(7, 7), FieldCell::new(4, 4));
+		let no_path = graph.find_best_path_within_budget(source, missing, &sector_portals, &sector_cost_fields, &DefaultPortalCostModel, 1000);
+		assert_eq!(Err(PathSearchError::NoPath), no_path);
+	}
+	#[test]
+	fn preview_route_through_sectors() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let mut graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let route = graph.preview_sector_route(SectorID::new(0, 0), SectorID::new(2, 0)).unwrap();
+		assert_eq!(vec![SectorID::new(0, 0), SectorID::new(1, 0), SectorID::new(2, 0)], route);
+		let polyline = graph.preview_route_polyline(SectorID::new(0, 0), SectorID::new(2, 0), &map_dimensions).unwrap();
+		assert_eq!(vec![Vec2::new(-10.0, 10.0), Vec2::new(0.0, 10.0), Vec2::new(10.0, 10.0)], polyline);
+		// a disabled crossing is avoided until it's enabled again
+		let condition = ConditionId::new(1);
+		graph.set_boundary_condition(SectorID::new(0, 0), SectorID::new(1, 0), condition);
+		graph.set_condition_enabled(condition, false);
+		let route = graph.preview_sector_route(SectorID::new(0, 0), SectorID::new(2, 0)).unwrap();
+		assert_eq!(5, route.len());
+		assert_eq!(SectorID::new(0, 1), route[1]);
+		graph.set_condition_enabled(condition, true);
+		assert_eq!(3, graph.preview_sector_route(SectorID::new(0, 0), SectorID::new(2, 0)).unwrap().len());
+		// a wall down the eastern side of the middle sectors cuts the map in two
+		for row in 0..3 {
+			for cell_row in 0..FIELD_RESOLUTION {
+				sector_cost_fields.set_field_cell_value(SectorID::new(1, row), 255, FieldCell::new(9, cell_row), &map_dimensions);
+			}
+		}
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		graph.rebuild(&sector_portals, &sector_cost_fields, &map_dimensions);
+		assert!(graph.preview_sector_route(SectorID::new(0, 0), SectorID::new(2, 0)).is_none());
+		let route = graph.preview_sector_route(SectorID::new(0, 0), SectorID::new(1, 2)).unwrap();
+		assert_eq!(4, route.len());
+	}
+	#[test]
+	fn best_path_missing_sector() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let source = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let target = (SectorID::new(7, 7), FieldCell::new(4, 4));
+		assert!(graph.find_best_path(source, target, &sector_portals, &sector_cost_fields).is_none());
+		assert!(graph.find_best_path(target, source, &sector_portals, &sector_cost_fields).is_none());
+	}
+	#[test]
+	fn interior_mutation_matches_full_rebuild() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let mut graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		// a wall through the middle of the centre sector which doesn't touch any boundary
+		let mutated_sector_id = SectorID::new(1, 1);
+		let cells: Vec<FieldCell> = (1..9).map(|row| FieldCell::new(5, row)).collect();
+		for cell in cells.iter() {
+			sector_cost_fields.set_field_cell_value(mutated_sector_id, 255, *cell, &map_dimensions);
+		}
+		let boundaries = map_dimensions.get_boundaries_affected_by(&mutated_sector_id, &cells);
+		assert!(boundaries.is_empty());
+		sector_portals.update_portal_boundaries(mutated_sector_id, &boundaries, &sector_cost_fields, &map_dimensions);
+		let neighbour_edges_before: Vec<Edge> = graph.get_edges_internal().iter().filter(|e| !e.get_from().is_in_sector(&mutated_sector_id)).cloned().collect();
+		graph.update_graph_boundaries(mutated_sector_id, &boundaries, &sector_portals, &sector_cost_fields, &map_dimensions);
+		// the graph should be identical to one built from scratch
+		let rebuilt = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		assert_eq!(rebuilt.get_nodes(), graph.get_nodes());
+		assert_eq!(rebuilt.get_edges_external(), graph.get_edges_external());
+		let distances = |g: &PortalGraph| {
+			let mut d: Vec<(SectorID, FieldCell, FieldCell, i32)> = g.get_edges_internal().iter().map(|e| (*e.get_from().get_sector(), *e.get_from().get_portal_cell(), *e.get_to().get_portal_cell(), e.get_distance())).collect();
+			d.sort();
+			d
+		};
+		assert_eq!(distances(&rebuilt), distances(&graph));
+		// the internal edges of the neighbours were left in place
+		for edge in neighbour_edges_before.iter() {
+			assert!(graph.get_edges_internal().contains(edge));
+		}
+	}
+	#[test]
+	fn void_sector_matches_full_rebuild() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let mut graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let void_id = SectorID::new(1, 1);
+		sector_cost_fields.set_void(void_id, &map_dimensions);
+		sector_portals.update_portals(void_id, &sector_cost_fields, &map_dimensions);
+		graph.update_graph(void_id, &sector_portals, &sector_cost_fields, &map_dimensions);
+		let rebuilt = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		assert_eq!(rebuilt.get_nodes(), graph.get_nodes());
+		assert_eq!(rebuilt.get_edges_external(), graph.get_edges_external());
+		assert!(!graph.get_nodes().iter().any(|n| n.is_in_sector(&void_id)));
+		// a path from west to east must go around the void
+		let path = graph.find_best_path((SectorID::new(0, 1), FieldCell::new(4, 4)), (SectorID::new(2, 1), FieldCell::new(4, 4)), &sector_portals, &sector_cost_fields).unwrap();
+		assert!(!path.iter().any(|(sector_id, _)| *sector_id == void_id));
+	}
+	#[test]
+	fn boundary_mutation_matches_full_rebuild() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let mut graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		// split the eastern portal of the centre sector
+		let mutated_sector_id = SectorID::new(1, 1);
+		let cells = vec![FieldCell::new(9, 4), FieldCell::new(4, 4)];
+		for cell in cells.iter() {
+			sector_cost_fields.set_field_cell_value(mutated_sector_id, 255, *cell, &map_dimensions);
+		}
+		let boundaries = map_dimensions.get_boundaries_affected_by(&mutated_sector_id, &cells);
+		assert_eq!(vec![Ordinal::East], boundaries);
+		sector_portals.update_portal_boundaries(mutated_sector_id, &boundaries, &sector_cost_fields, &map_dimensions);
+		assert_eq!(2, sector_portals.get().get(&mutated_sector_id).unwrap().get(&Ordinal::East).len());
+		assert_eq!(2, sector_portals.get().get(&SectorID::new(2, 1)).unwrap().get(&Ordinal::West).len());
+		graph.update_graph_boundaries(mutated_sector_id, &boundaries, &sector_portals, &sector_cost_fields, &map_dimensions);
+		let rebuilt = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		assert_eq!(rebuilt.get_nodes(), graph.get_nodes());
+		assert_eq!(rebuilt.get_edges_internal(), graph.get_edges_internal());
+		assert_eq!(rebuilt.get_edges_external(), graph.get_edges_external());
+	}
 }
@@ -0,0 +1,206 @@
+//! Renders the [SectorPortals] and [PortalGraph] of a map into an SVG image,
+//! handy for spotting portals which don't line up across a sector boundary
+//! and for documenting a map. The image is built as plain text so it can be
+//! produced by tools and tests which don't have a render device.
+//!
+//! Each [FieldCell] is drawn as a square with [FieldCell] `(0, 0)` of sector
+//! `(0, 0)` in the top-left corner:
+//!
+//! * impassable cells are dark grey
+//! * sector boundaries are outlined in black
+//! * portal cells are orange
+//! * internal edges (between portals of the same sector) are blue lines
+//! * external edges (across a sector boundary) are red lines
+//!
+
+use std::fmt::Write;
+
+use crate::prelude::*;
+
+/// Colour of impassable [FieldCell]s
+const SVG_IMPASSABLE: &str = "#404040";
+/// Colour of the portal [FieldCell]s
+const SVG_PORTAL: &str = "#ff9900";
+/// Colour of the edges between portals of the same sector
+const SVG_INTERNAL_EDGE: &str = "#1f5fd6";
+/// Colour of the edges between portals across a sector boundary
+const SVG_EXTERNAL_EDGE: &str = "#d62020";
+
+/// Render the portals and the edges of the [PortalGraph] into an SVG
+/// document where each [FieldCell] is `cell_size` pixels wide
+pub fn portals_to_svg(
+	portal_graph: &PortalGraph,
+	sector_portals: &SectorPortals,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+	cell_size: f32,
+) -> String {
+	let width = map_dimensions.get_total_field_cell_columns() as f32 * cell_size;
+	let height = map_dimensions.get_total_field_cell_rows() as f32 * cell_size;
+	let mut svg = String::new();
+	// writing to a String cannot fail
+	let _ = writeln!(
+		svg,
+		r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+	);
+	let _ = writeln!(
+		svg,
+		r#"<rect width="{width}" height="{height}" fill="white"/>"#
+	);
+	svg.push_str("<g id=\"impassable\">\n");
+	for (sector_id, cost_field) in sector_cost_fields.get_scaled().iter() {
+		for column in 0..FIELD_RESOLUTION {
+			for row in 0..FIELD_RESOLUTION {
+				let field_cell = FieldCell::new(column, row);
+				if cost_field.impassable_mask().is_impassable(field_cell) {
+					write_cell(
+						&mut svg,
+						map_dimensions,
+						(*sector_id, field_cell),
+						cell_size,
+						SVG_IMPASSABLE,
+					);
+				}
+			}
+		}
+	}
+	svg.push_str("</g>\n<g id=\"sectors\" fill=\"none\" stroke=\"black\">\n");
+	let sector_size = FIELD_RESOLUTION as f32 * cell_size;
+	for sector_id in sector_portals.get().keys() {
+		let _ = writeln!(
+			svg,
+			r#"<rect x="{}" y="{}" width="{sector_size}" height="{sector_size}"/>"#,
+			sector_id.get_column() as f32 * sector_size,
+			sector_id.get_row() as f32 * sector_size,
+		);
+	}
+	svg.push_str("</g>\n<g id=\"portals\">\n");
+	let ordinals = [Ordinal::North, Ordinal::East, Ordinal::South, Ordinal::West];
+	for (sector_id, portals) in sector_portals.get().iter() {
+		for ordinal in ordinals.iter() {
+			for field_cell in portals.get(ordinal).iter() {
+				write_cell(
+					&mut svg,
+					map_dimensions,
+					(*sector_id, *field_cell),
+					cell_size,
+					SVG_PORTAL,
+				);
+			}
+		}
+	}
+	let edge_groups = [
+		(
+			"internal_edges",
+			SVG_INTERNAL_EDGE,
+			portal_graph.get_internal_connections(),
+		),
+		(
+			"external_edges",
+			SVG_EXTERNAL_EDGE,
+			portal_graph.get_external_connections(),
+		),
+	];
+	for (id, colour, connections) in edge_groups.iter() {
+		svg.push_str("</g>\n");
+		let _ = writeln!(
+			svg,
+			r#"<g id="{id}" stroke="{colour}" stroke-width="{}">"#,
+			cell_size / 8.0
+		);
+		for (from, to) in connections.iter() {
+			let (x1, y1) = cell_centre(map_dimensions, *from, cell_size);
+			let (x2, y2) = cell_centre(map_dimensions, *to, cell_size);
+			let _ = writeln!(svg, r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}"/>"#);
+		}
+	}
+	svg.push_str("</g>\n</svg>\n");
+	svg
+}
+
+/// As with [portals_to_svg] but the document is written to the file at `path`
+pub fn write_portals_svg(
+	path: impl AsRef<std::path::Path>,
+	portal_graph: &PortalGraph,
+	sector_portals: &SectorPortals,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+	cell_size: f32,
+) -> std::io::Result<()> {
+	let svg = portals_to_svg(
+		portal_graph,
+		sector_portals,
+		sector_cost_fields,
+		map_dimensions,
+		cell_size,
+	);
+	std::fs::write(path, svg)
+}
+
+/// Get the pixel position of the centre of a [FieldCell]
+fn cell_centre(
+	map_dimensions: &MapDimensions,
+	(sector_id, field_cell): (SectorID, FieldCell),
+	cell_size: f32,
+) -> (f32, f32) {
+	let (column, row) = map_dimensions.global_cell_from(sector_id, field_cell);
+	(
+		(column as f32 + 0.5) * cell_size,
+		(row as f32 + 0.5) * cell_size,
+	)
+}
+
+/// Append a square filling a [FieldCell] with the `colour`
+fn write_cell(
+	svg: &mut String,
+	map_dimensions: &MapDimensions,
+	(sector_id, field_cell): (SectorID, FieldCell),
+	cell_size: f32,
+	colour: &str,
+) {
+	let (column, row) = map_dimensions.global_cell_from(sector_id, field_cell);
+	let _ = writeln!(
+		svg,
+		r#"<rect x="{}" y="{}" width="{cell_size}" height="{cell_size}" fill="{colour}"/>"#,
+		column as f32 * cell_size,
+		row as f32 * cell_size,
+	);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn svg_contains_every_portal_and_edge() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		sector_cost_fields.set_field_cell_value(
+			SectorID::new(1, 1),
+			255,
+			FieldCell::new(4, 4),
+			&map_dimensions,
+		);
+		let mut sector_portals = SectorPortals::new(30, 30, 10);
+		for (sector_id, portals) in sector_portals.get_mut().iter_mut() {
+			portals.recalculate_portals(&sector_cost_fields, sector_id, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let svg = portals_to_svg(
+			&graph,
+			&sector_portals,
+			&sector_cost_fields,
+			&map_dimensions,
+			4.0,
+		);
+		assert!(svg.starts_with("<svg"));
+		assert!(svg.trim_end().ends_with("</svg>"));
+		let edges = graph.get_internal_connections().len() + graph.get_external_connections().len();
+		assert!(edges > 0);
+		assert_eq!(edges, svg.matches("<line").count());
+		// 24 portals of the 3x3 sectors, 9 sector outlines, one impassable
+		// cell and the background
+		assert_eq!(24 + 9 + 1 + 1, svg.matches("<rect").count());
+		// the impassable cell is drawn at its global position
+		assert!(svg.contains(r##"<rect x="56" y="56" width="4" height="4" fill="#404040"/>"##));
+	}
+}
@@ -44,6 +44,7 @@
 //! the agent can immediately starts pathing. In the background the other components of the Flowfields can
 //! calcualte a perfect path which can then supersede using portals to path when it's ready
 
+use bevy::log::warn;
 use bevy::reflect::Reflect;
 
 use crate::prelude::*;
@@ -98,13 +99,6 @@ impl Portals {
 			),
 		}
 	}
-	/// Remove all [FieldCell] elements from the lists of [FieldCell]
-	fn clear_all(&mut self) {
-		let ords = [Ordinal::North, Ordinal::South, Ordinal::West, Ordinal::East];
-		for ord in ords {
-			self.clear(ord);
-		}
-	}
 	/// When a sectors [CostField] is updated the portal [FieldCell]s of the sector and
 	/// its neighbours may no longer be valid so they should be recalculated.
 	///
@@ -133,7 +127,30 @@ impl Portals {
 		sector_id: &SectorID,
 		map_dimensions: &MapDimensions,
 	) {
-		self.clear_all();
+		self.recalculate_portal_boundaries(
+			sector_cost_fields,
+			sector_id,
+			map_dimensions,
+			&[Ordinal::North, Ordinal::East, Ordinal::South, Ordinal::West],
+		);
+	}
+	/// Recalculate the portal [FieldCell]s along the `boundaries` of a sector
+	/// only, the portals of any other side are left untouched. See
+	/// [Portals::recalculate_portals]
+	pub fn recalculate_portal_boundaries(
+		&mut self,
+		sector_cost_fields: &SectorCostFields,
+		sector_id: &SectorID,
+		map_dimensions: &MapDimensions,
+		boundaries: &[Ordinal],
+	) {
+		#[cfg(feature = "trace")]
+		let _span = bevy::log::info_span!("recalculate_portals", sector = ?sector_id, ?boundaries)
+			.entered();
+		for ord in boundaries {
+			self.clear(*ord);
+		}
+		let max_window = sector_cost_fields.get_max_portal_window();
 		// there are up to 4 lists of [FieldCell]s for a given sector, in case this sector being
 		// updated is on a boundary we need to determine the valid elements of [Portals] that
 		// should be updated
@@ -141,11 +158,32 @@ impl Portals {
 			map_dimensions.get_ordinal_and_ids_of_neighbouring_sectors(sector_id);
 		// moving in a clockwise fashion around the valid ordinals of the boundary sector movement
 		// we inspect the [CostField] values to calculate the portals along each valid sector side
-		let cost_field = sector_cost_fields
-			.get_scaled()
-			.get(sector_id)
-			.expect("Invalid sector id");
+		// void sectors have no portals
+		let Some(cost_field) = sector_cost_fields.get_scaled().get(sector_id) else {
+			if !sector_cost_fields.is_void(*sector_id) {
+				warn!(
+					"Cannot calculate portals of non-existent sector {:?}",
+					sector_id
+				);
+			}
+			return;
+		};
 		for (ord, adjoining_sector_id) in valid_ordinals_for_this_sector.iter() {
+			if !boundaries.contains(ord) {
+				continue;
+			}
+			if !sector_cost_fields
+				.get_scaled()
+				.contains_key(adjoining_sector_id)
+			{
+				if !sector_cost_fields.is_void(*adjoining_sector_id) {
+					warn!(
+						"Cannot calculate portals of {:?} bordering non-existent sector {:?}",
+						sector_id, adjoining_sector_id
+					);
+				}
+				continue;
+			}
 			match ord {
 				Ordinal::North => {
 					let portal_nodes = self.get_mut(ord);
@@ -156,13 +194,14 @@ impl Portals {
 						.get(adjoining_sector_id)
 						.unwrap();
 					// walk along the side of the field
+					// cells along the side where both fields are pathable
+					let open_side = !(cost_field.impassable_mask().get_row(fixed_row)
+						| adjoining_cost_field
+							.impassable_mask()
+							.get_row(FIELD_RESOLUTION - 1));
 					let mut neighbouring_pathable = Vec::new();
 					for i in column_range {
-						let field_cost =
-							cost_field.get_field_cell_value(FieldCell::new(i, fixed_row));
-						let adjacent_field_cost = adjoining_cost_field
-							.get_field_cell_value(FieldCell::new(i, FIELD_RESOLUTION - 1));
-						if field_cost != 255 && adjacent_field_cost != 255 {
+						if open_side & (1 << i) != 0 {
 							// a pathable point along the edge so we record it to be
 							// published later as a FieldCell
 							neighbouring_pathable.push((i, fixed_row));
@@ -170,15 +209,17 @@ impl Portals {
 							// if a length along the edge was previously calculated then publish
 							// it as FieldCell
 							if !neighbouring_pathable.is_empty() {
-								// find the most centre like cell for this portal window
-								let mut column_index_sum = 0;
-								for (m, _) in neighbouring_pathable.iter() {
-									column_index_sum += m;
+								// find the most centre like cell of each portal along this window
+								let window_start = neighbouring_pathable[0].0;
+								for (segment_start, length) in split_portal_window(
+									window_start,
+									neighbouring_pathable.len(),
+									max_window,
+								) {
+									let portal_midpoint_column = segment_start + (length - 1) / 2;
+									portal_nodes
+										.push(FieldCell::new(portal_midpoint_column, fixed_row));
 								}
-								let portal_midpoint_column =
-									column_index_sum / neighbouring_pathable.len();
-								portal_nodes
-									.push(FieldCell::new(portal_midpoint_column, fixed_row));
 								// clear the recording list so any other portals along the side can be built
 								neighbouring_pathable.clear();
 							}
@@ -186,13 +227,16 @@ impl Portals {
 					}
 					// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
 					if !neighbouring_pathable.is_empty() {
-						// find the most centre like cell for this portal window
-						let mut column_index_sum = 0;
-						for (i, _) in neighbouring_pathable.iter() {
-							column_index_sum += i;
+						// find the most centre like cell of each portal along this window
+						let window_start = neighbouring_pathable[0].0;
+						for (segment_start, length) in split_portal_window(
+							window_start,
+							neighbouring_pathable.len(),
+							max_window,
+						) {
+							let portal_midpoint_column = segment_start + (length - 1) / 2;
+							portal_nodes.push(FieldCell::new(portal_midpoint_column, fixed_row));
 						}
-						let portal_midpoint_column = column_index_sum / neighbouring_pathable.len();
-						portal_nodes.push(FieldCell::new(portal_midpoint_column, fixed_row));
 						// clear the recording list so any other portals along the side can be built
 						neighbouring_pathable.clear();
 					}
@@ -206,13 +250,12 @@ impl Portals {
 						.get(adjoining_sector_id)
 						.unwrap();
 					// walk along the side of the field
+					// cells along the side where both fields are pathable
+					let open_side = !(cost_field.impassable_mask().get_column(fixed_column)
+						| adjoining_cost_field.impassable_mask().get_column(0));
 					let mut neighbouring_pathable = Vec::new();
 					for j in row_range {
-						let field_cost =
-							cost_field.get_field_cell_value(FieldCell::new(fixed_column, j));
-						let adjacent_field_cost =
-							adjoining_cost_field.get_field_cell_value(FieldCell::new(0, j));
-						if field_cost != 255 && adjacent_field_cost != 255 {
+						if open_side & (1 << j) != 0 {
 							// a pathable point along the edge so we record it to be
 							// published later as a FieldCell
 							neighbouring_pathable.push((fixed_column, j));
@@ -220,15 +263,17 @@ impl Portals {
 							// if a length along the edge was previously calculated then publish
 							// it as FieldCell
 							if !neighbouring_pathable.is_empty() {
-								// find the most centre like cell for this portal window
-								let mut row_index_sum = 0;
-								for (_, n) in neighbouring_pathable.iter() {
-									row_index_sum += n;
+								// find the most centre like cell of each portal along this window
+								let window_start = neighbouring_pathable[0].1;
+								for (segment_start, length) in split_portal_window(
+									window_start,
+									neighbouring_pathable.len(),
+									max_window,
+								) {
+									let portal_midpoint_row = segment_start + (length - 1) / 2;
+									portal_nodes
+										.push(FieldCell::new(fixed_column, portal_midpoint_row));
 								}
-								let portal_midpoint_row =
-									row_index_sum / neighbouring_pathable.len();
-								portal_nodes
-									.push(FieldCell::new(fixed_column, portal_midpoint_row));
 								// clear the recording list so any other portals along the side can be built
 								neighbouring_pathable.clear();
 							}
@@ -236,13 +281,16 @@ impl Portals {
 					}
 					// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
 					if !neighbouring_pathable.is_empty() {
-						// find the most centre like cell for this portal window
-						let mut row_index_sum = 0;
-						for (_, n) in neighbouring_pathable.iter() {
-							row_index_sum += n;
+						// find the most centre like cell of each portal along this window
+						let window_start = neighbouring_pathable[0].1;
+						for (segment_start, length) in split_portal_window(
+							window_start,
+							neighbouring_pathable.len(),
+							max_window,
+						) {
+							let portal_midpoint_row = segment_start + (length - 1) / 2;
+							portal_nodes.push(FieldCell::new(fixed_column, portal_midpoint_row));
 						}
-						let portal_midpoint_row = row_index_sum / neighbouring_pathable.len();
-						portal_nodes.push(FieldCell::new(fixed_column, portal_midpoint_row));
 						// clear the recording list so any other portals along the side can be built
 						neighbouring_pathable.clear();
 					}
@@ -256,13 +304,12 @@ impl Portals {
 						.get(adjoining_sector_id)
 						.unwrap();
 					// walk along the side of the field
+					// cells along the side where both fields are pathable
+					let open_side = !(cost_field.impassable_mask().get_row(fixed_row)
+						| adjoining_cost_field.impassable_mask().get_row(0));
 					let mut neighbouring_pathable = Vec::new();
 					for i in column_range {
-						let field_cost =
-							cost_field.get_field_cell_value(FieldCell::new(i, fixed_row));
-						let adjacent_field_cost =
-							adjoining_cost_field.get_field_cell_value(FieldCell::new(i, 0));
-						if field_cost != 255 && adjacent_field_cost != 255 {
+						if open_side & (1 << i) != 0 {
 							// a pathable point along the edge so we record it to be
 							// published later as a FieldCell
 							neighbouring_pathable.push((i, fixed_row));
@@ -270,15 +317,17 @@ impl Portals {
 							// if a length along the edge was previously calculated then publish
 							// it as FieldCell
 							if !neighbouring_pathable.is_empty() {
-								// find the most centre like cell for this portal window
-								let mut column_index_sum = 0;
-								for (m, _) in neighbouring_pathable.iter() {
-									column_index_sum += m;
+								// find the most centre like cell of each portal along this window
+								let window_start = neighbouring_pathable[0].0;
+								for (segment_start, length) in split_portal_window(
+									window_start,
+									neighbouring_pathable.len(),
+									max_window,
+								) {
+									let portal_midpoint_column = segment_start + (length - 1) / 2;
+									portal_nodes
+										.push(FieldCell::new(portal_midpoint_column, fixed_row));
 								}
-								let portal_midpoint_column =
-									column_index_sum / neighbouring_pathable.len();
-								portal_nodes
-									.push(FieldCell::new(portal_midpoint_column, fixed_row));
 								// clear the recording list so any other portals along the side can be built
 								neighbouring_pathable.clear();
 							}
@@ -286,13 +335,16 @@ impl Portals {
 					}
 					// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
 					if !neighbouring_pathable.is_empty() {
-						// find the most centre like cell for this portal window
-						let mut column_index_sum = 0;
-						for (i, _) in neighbouring_pathable.iter() {
-							column_index_sum += i;
+						// find the most centre like cell of each portal along this window
+						let window_start = neighbouring_pathable[0].0;
+						for (segment_start, length) in split_portal_window(
+							window_start,
+							neighbouring_pathable.len(),
+							max_window,
+						) {
+							let portal_midpoint_column = segment_start + (length - 1) / 2;
+							portal_nodes.push(FieldCell::new(portal_midpoint_column, fixed_row));
 						}
-						let portal_midpoint_column = column_index_sum / neighbouring_pathable.len();
-						portal_nodes.push(FieldCell::new(portal_midpoint_column, fixed_row));
 						// clear the recording list so any other portals along the side can be built
 						neighbouring_pathable.clear();
 					}
@@ -306,13 +358,14 @@ impl Portals {
 						.get(adjoining_sector_id)
 						.unwrap();
 					// walk along the side of the field
+					// cells along the side where both fields are pathable
+					let open_side = !(cost_field.impassable_mask().get_column(fixed_column)
+						| adjoining_cost_field
+							.impassable_mask()
+							.get_column(FIELD_RESOLUTION - 1));
 					let mut neighbouring_pathable = Vec::new();
 					for j in row_range {
-						let field_cost =
-							cost_field.get_field_cell_value(FieldCell::new(fixed_column, j));
-						let adjacent_field_cost = adjoining_cost_field
-							.get_field_cell_value(FieldCell::new(FIELD_RESOLUTION - 1, j));
-						if field_cost != 255 && adjacent_field_cost != 255 {
+						if open_side & (1 << j) != 0 {
 							// a pathable point along the edge so we record it to be
 							// published later as a FieldCell
 							neighbouring_pathable.push((fixed_column, j));
@@ -320,15 +373,17 @@ impl Portals {
 							// if a length along the edge was previously calculated then publish
 							// it as FieldCell
 							if !neighbouring_pathable.is_empty() {
-								// find the most centre like cell for this portal window
-								let mut row_index_sum = 0;
-								for (_, n) in neighbouring_pathable.iter() {
-									row_index_sum += n;
+								// find the most centre like cell of each portal along this window
+								let window_start = neighbouring_pathable[0].1;
+								for (segment_start, length) in split_portal_window(
+									window_start,
+									neighbouring_pathable.len(),
+									max_window,
+								) {
+									let portal_midpoint_row = segment_start + (length - 1) / 2;
+									portal_nodes
+										.push(FieldCell::new(fixed_column, portal_midpoint_row));
 								}
-								let portal_midpoint_row =
-									row_index_sum / neighbouring_pathable.len();
-								portal_nodes
-									.push(FieldCell::new(fixed_column, portal_midpoint_row));
 								// clear the recording list so any other portals along the side can be built
 								neighbouring_pathable.clear();
 							}
@@ -336,13 +391,16 @@ impl Portals {
 					}
 					// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
 					if !neighbouring_pathable.is_empty() {
-						// find the most centre like cell for this portal window
-						let mut row_index_sum = 0;
-						for (_, n) in neighbouring_pathable.iter() {
-							row_index_sum += n;
+						// find the most centre like cell of each portal along this window
+						let window_start = neighbouring_pathable[0].1;
+						for (segment_start, length) in split_portal_window(
+							window_start,
+							neighbouring_pathable.len(),
+							max_window,
+						) {
+							let portal_midpoint_row = segment_start + (length - 1) / 2;
+							portal_nodes.push(FieldCell::new(fixed_column, portal_midpoint_row));
 						}
-						let portal_midpoint_row = row_index_sum / neighbouring_pathable.len();
-						portal_nodes.push(FieldCell::new(fixed_column, portal_midpoint_row));
 						// clear the recording list so any other portals along the side can be built
 						neighbouring_pathable.clear();
 					}
@@ -385,11 +443,16 @@ impl Portals {
 		goals.push(*portal_id);
 		// from the portal walk either left/right or up/down depending on the ordinal
 		// until an impassable cost field value is found
-		let this_cost_field = sector_cost_fields.get_scaled().get(sector_id).unwrap();
-		let adjoining_cost_field = sector_cost_fields
-			.get_scaled()
-			.get(neighbour_sector_id)
-			.unwrap();
+		let (Some(this_cost_field), Some(adjoining_cost_field)) = (
+			sector_cost_fields.get_scaled().get(sector_id),
+			sector_cost_fields.get_scaled().get(neighbour_sector_id),
+		) else {
+			warn!(
+				"Cannot expand portal {:?} between {:?} and non-existent sector {:?}",
+				portal_id, sector_id, neighbour_sector_id
+			);
+			return goals;
+		};
 		match boundary_ordinal {
 			Ordinal::North => {
 				// walk left from the portal
@@ -537,10 +600,53 @@ impl Portals {
 				boundary_ordinal
 			),
 		}
+		// a long window may be split into several portals, each owns only its
+		// own segment of the window
+		if let Some(max_window) = sector_cost_fields.get_max_portal_window() {
+			if goals.len() > max_window {
+				let along = |cell: &FieldCell| match boundary_ordinal {
+					Ordinal::North | Ordinal::South => cell.get_column(),
+					_ => cell.get_row(),
+				};
+				let window_start = goals.iter().map(along).min().unwrap();
+				let portal_index = along(portal_id);
+				if let Some((segment_start, length)) =
+					split_portal_window(window_start, goals.len(), Some(max_window))
+						.into_iter()
+						.find(|(start, length)| (*start..*start + *length).contains(&portal_index))
+				{
+					goals.retain(|cell| {
+						(segment_start..segment_start + length).contains(&along(cell))
+					});
+				}
+			}
+		}
 		goals
 	}
 }
 
+/// Divide a window of `length` pathable [FieldCell]s along a boundary,
+/// beginning at `window_start`, into the `(start, length)` segments which are
+/// each represented by a portal. Windows no longer than `max_window` have a
+/// single segment
+fn split_portal_window(
+	window_start: usize,
+	length: usize,
+	max_window: Option<usize>,
+) -> Vec<(usize, usize)> {
+	let segments = match max_window {
+		Some(max) if max > 0 => length.div_ceil(max),
+		_ => 1,
+	};
+	(0..segments)
+		.map(|k| {
+			let start = window_start + k * length / segments;
+			let end = window_start + (k + 1) * length / segments;
+			(start, end - start)
+		})
+		.collect()
+}
+
 #[cfg(test)]
 mod tests {
 	use crate::flowfields::sectors::sector_portals::SectorPortals;
@@ -1018,4 +1124,49 @@ mod tests {
 		];
 		assert_eq!(actual, goals);
 	}
+	#[test]
+	fn split_long_portal_windows() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		sector_cost_fields.set_max_portal_window(Some(4));
+		let mut sector_portals = SectorPortals::new(
+			map_dimensions.get_length(),
+			map_dimensions.get_depth(),
+			map_dimensions.get_sector_resolution(),
+		);
+		for (id, portals) in sector_portals.get_mut().iter_mut() {
+			portals.recalculate_portals(&sector_cost_fields, id, &map_dimensions)
+		}
+		let sector_id = SectorID::new(1, 1);
+		let neighbour_sector_id = SectorID::new(1, 0);
+		let portals = sector_portals.get().get(&sector_id).unwrap();
+		let actual = vec![
+			FieldCell::new(1, 0),
+			FieldCell::new(4, 0),
+			FieldCell::new(7, 0),
+		];
+		assert_eq!(&actual, portals.get(&Ordinal::North));
+		// the facing boundary is split in the same way
+		let neighbour_portals = sector_portals.get().get(&neighbour_sector_id).unwrap();
+		let actual = vec![
+			FieldCell::new(1, 9),
+			FieldCell::new(4, 9),
+			FieldCell::new(7, 9),
+		];
+		assert_eq!(&actual, neighbour_portals.get(&Ordinal::South));
+		// each portal only expands over its own segment of the window
+		let goals = portals.expand_portal_into_goals(
+			&sector_cost_fields,
+			&sector_id,
+			&FieldCell::new(4, 0),
+			&neighbour_sector_id,
+			&map_dimensions,
+		);
+		let actual = vec![
+			FieldCell::new(4, 0),
+			FieldCell::new(3, 0),
+			FieldCell::new(5, 0),
+		];
+		assert_eq!(actual, goals);
+	}
 }
@@ -4,9 +4,19 @@
 //!
 
 pub mod sector_cost;
+pub mod sector_grid;
+pub mod sector_navmesh;
 pub mod sector_portals;
+pub mod sector_visibility;
+pub mod sector_zones;
+#[cfg(feature = "tiled")]
+pub mod tiled;
 
 use crate::prelude::*;
+#[cfg(feature = "large_world")]
+use bevy::math::DVec2;
+#[cfg(all(feature = "3d", feature = "large_world"))]
+use bevy::math::DVec3;
 use bevy::prelude::*;
 
 /// Unique ID of a sector
@@ -155,6 +165,21 @@ impl MapDimensions {
 	pub fn get_actor_scale(&self) -> u32 {
 		self.actor_scale
 	}
+	/// Get the [SectorGrid] describing the layout of sectors across the map
+	pub fn get_sector_grid(&self) -> SectorGrid {
+		SectorGrid::from_dimensions(
+			self.get_length(),
+			self.get_depth(),
+			self.get_sector_resolution(),
+		)
+	}
+	/// Whether a single sector covers the whole map. Such a map has no
+	/// [Portals] so the [PortalGraph] is skipped and path requests are
+	/// answered with a [FlowField] built directly within the sector
+	pub fn is_single_sector(&self) -> bool {
+		let grid = self.get_sector_grid();
+		grid.get_columns() == 1 && grid.get_rows() == 1
+	}
 	/// Based on `map_length` and resolution calculate the number of [`FieldCell`] columns across all sectors
 	pub fn get_total_field_cell_columns(&self) -> usize {
 		(self.get_length() / self.get_sector_resolution()) as usize * FIELD_RESOLUTION
@@ -163,6 +188,34 @@ impl MapDimensions {
 	pub fn get_total_field_cell_rows(&self) -> usize {
 		(self.get_depth() / self.get_sector_resolution()) as usize * FIELD_RESOLUTION
 	}
+	/// Convert a sector and [FieldCell] into a global `(column, row)` index
+	/// across the [FieldCell]s of all sectors where `(0, 0)` is the top-left
+	/// cell of the map
+	pub fn global_cell_from(&self, sector_id: SectorID, field_cell: FieldCell) -> (u32, u32) {
+		(
+			sector_id.get_column() * FIELD_RESOLUTION as u32 + field_cell.get_column() as u32,
+			sector_id.get_row() * FIELD_RESOLUTION as u32 + field_cell.get_row() as u32,
+		)
+	}
+	/// Convert a global `(column, row)` [FieldCell] index into the sector and
+	/// [FieldCell] it refers to. Returns [None] if the index is outside of the
+	/// map
+	pub fn sector_and_cell_from_global(
+		&self,
+		column: u32,
+		row: u32,
+	) -> Option<(SectorID, FieldCell)> {
+		if column as usize >= self.get_total_field_cell_columns()
+			|| row as usize >= self.get_total_field_cell_rows()
+		{
+			return None;
+		}
+		let resolution = FIELD_RESOLUTION as u32;
+		Some((
+			SectorID::new(column / resolution, row / resolution),
+			FieldCell::new((column % resolution) as usize, (row % resolution) as usize),
+		))
+	}
 	/// Based on the sector resolution calculate the `f32` size of a [`FieldCell`]
 	pub fn get_field_cell_unit_size(&self) -> f32 {
 		(self.get_sector_resolution() as usize / FIELD_RESOLUTION) as f32
@@ -174,36 +227,44 @@ impl MapDimensions {
 	/// `pixel_scale` refers to the dimensions of your map sprites, not that their `x` and `y` dimensions must be the same, i.e a square shape
 	#[cfg(feature = "2d")]
 	pub fn get_sector_id_from_xy(&self, position: Vec2) -> Option<SectorID> {
-		if position.x < -((self.get_length() / 2) as f32)
-			|| position.x > (self.get_length() / 2) as f32
-			|| position.y < -((self.get_depth() / 2) as f32)
-			|| position.y > (self.get_depth() / 2) as f32
+		#[cfg(feature = "large_world")]
 		{
-			error!("Position is out of bounds of MapDimensions, x {}, y {}, cannot calculate SectorID. Is the actor outside of the map or trying to request route outside of it?", position.x, position.y);
-			//TODO use Result instead
-			return None;
-		}
-		let x_sector_count = self.get_length() / self.get_sector_resolution();
-		let y_sector_count = self.get_depth() / self.get_sector_resolution();
-		// The 2D world is centred at origin (0, 0). The sector grid has an origin in the top
-		// left at 2D world coords of (-map_x * pixel_scale / 2, 0, map_y * pixel_scale / 2).
-		// To translate the 2D world
-		// coords into a new coordinate system with a (0, 0) origin in the top left we add
-		// half the map dimension to each psition coordinatem
-		let x_origin = position.x + (self.get_length() / 2) as f32;
-		let y_origin = (self.get_depth() / 2) as f32 - position.y;
-		// the grid IDs follow a (column, row) convention, by dividing the repositioned dimension
-		// by the sector grid sizes and rounding down we determine the sector indices
-		let mut column = (x_origin / (self.get_sector_resolution() as f32)).floor() as u32;
-		let mut row = (y_origin / (self.get_sector_resolution() as f32)).floor() as u32;
-		// safety for x-y being at the exact limits of map size
-		if column >= x_sector_count {
-			column = x_sector_count - 1;
+			self.get_sector_and_field_cell_from_dxy(position.as_dvec2())
+				.map(|(sector_id, _)| sector_id)
 		}
-		if row >= y_sector_count {
-			row = y_sector_count - 1;
+		#[cfg(not(feature = "large_world"))]
+		{
+			if position.x < -((self.get_length() / 2) as f32)
+				|| position.x > (self.get_length() / 2) as f32
+				|| position.y < -((self.get_depth() / 2) as f32)
+				|| position.y > (self.get_depth() / 2) as f32
+			{
+				error!("Position is out of bounds of MapDimensions, x {}, y {}, cannot calculate SectorID. Is the actor outside of the map or trying to request route outside of it?", position.x, position.y);
+				//TODO use Result instead
+				return None;
+			}
+			let x_sector_count = self.get_length() / self.get_sector_resolution();
+			let y_sector_count = self.get_depth() / self.get_sector_resolution();
+			// The 2D world is centred at origin (0, 0). The sector grid has an origin in the top
+			// left at 2D world coords of (-map_x * pixel_scale / 2, 0, map_y * pixel_scale / 2).
+			// To translate the 2D world
+			// coords into a new coordinate system with a (0, 0) origin in the top left we add
+			// half the map dimension to each psition coordinatem
+			let x_origin = position.x + (self.get_length() / 2) as f32;
+			let y_origin = (self.get_depth() / 2) as f32 - position.y;
+			// the grid IDs follow a (column, row) convention, by dividing the repositioned dimension
+			// by the sector grid sizes and rounding down we determine the sector indices
+			let mut column = (x_origin / (self.get_sector_resolution() as f32)).floor() as u32;
+			let mut row = (y_origin / (self.get_sector_resolution() as f32)).floor() as u32;
+			// safety for x-y being at the exact limits of map size
+			if column >= x_sector_count {
+				column = x_sector_count - 1;
+			}
+			if row >= y_sector_count {
+				row = y_sector_count - 1;
+			}
+			Some(SectorID::new(column, row))
 		}
-		Some(SectorID::new(column, row))
 	}
 
 	/// Get the `(x,y)` coordinates of the top left corner of a sector in real space
@@ -224,60 +285,74 @@ impl MapDimensions {
 		&self,
 		position: Vec2,
 	) -> Option<(SectorID, FieldCell)> {
-		if let Some(sector_id) = self.get_sector_id_from_xy(position) {
-			let sector_corner_origin = self.get_sector_corner_xy(sector_id);
-			let pixel_sector_field_ratio =
-				self.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
-			let field_id_0 =
-				((position.x - sector_corner_origin.x) / pixel_sector_field_ratio).floor() as usize;
-			let field_id_1 = ((-position.y + sector_corner_origin.y) / pixel_sector_field_ratio)
-				.floor() as usize;
-			let field_id = FieldCell::new(field_id_0, field_id_1);
-			return Some((sector_id, field_id));
+		#[cfg(feature = "large_world")]
+		{
+			self.get_sector_and_field_cell_from_dxy(position.as_dvec2())
+		}
+		#[cfg(not(feature = "large_world"))]
+		{
+			if let Some(sector_id) = self.get_sector_id_from_xy(position) {
+				let sector_corner_origin = self.get_sector_corner_xy(sector_id);
+				let pixel_sector_field_ratio =
+					self.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+				let field_id_0 = ((position.x - sector_corner_origin.x) / pixel_sector_field_ratio)
+					.floor() as usize;
+				let field_id_1 = ((-position.y + sector_corner_origin.y) / pixel_sector_field_ratio)
+					.floor() as usize;
+				let field_id = FieldCell::new(field_id_0, field_id_1);
+				return Some((sector_id, field_id));
+			}
+			None
 		}
-		None
 	}
 	/// From a field cell within a Sector retrieve the 2d Vec2 of its
 	/// position. If the position sits outside of the world then [None] is
 	/// returned
-	#[cfg(feature = "2d")]
 	pub fn get_xy_from_field_sector(&self, sector: SectorID, field: FieldCell) -> Option<Vec2> {
-		// the sector grid always begins in the top left
-		// from real-space origin of (0,0) find the position of SectorID(0,0) in real space
-		let sector_grid_origin_offset = {
-			Vec2::new(
-				self.get_length() as f32 / -2.0,
-				self.get_depth() as f32 / 2.0,
-			)
-		};
-		// the sector grid starts top left at (0,0), based on the sector we want find its origin
-		// with how many units make up a sector and and sector mXn ID
-		// NB: use a negative Y here, as row ID goes from 0..n it's approaching the negative Y of real space
-		let sector_origin = Vec2::new(
-			(sector.get_column() * self.get_sector_resolution()) as f32,
-			(sector.get_row() * self.get_sector_resolution()) as f32 * -1.0,
-		);
-		// now we know the real-space coordinates of the top left corner of the sector
-		let xy_of_sector_top_left = sector_grid_origin_offset + sector_origin;
+		#[cfg(feature = "large_world")]
+		{
+			self.get_dxy_from_field_sector(sector, field)
+				.map(|position| position.as_vec2())
+		}
+		#[cfg(not(feature = "large_world"))]
+		{
+			// the sector grid always begins in the top left
+			// from real-space origin of (0,0) find the position of SectorID(0,0) in real space
+			let sector_grid_origin_offset = {
+				Vec2::new(
+					self.get_length() as f32 / -2.0,
+					self.get_depth() as f32 / 2.0,
+				)
+			};
+			// the sector grid starts top left at (0,0), based on the sector we want find its origin
+			// with how many units make up a sector and and sector mXn ID
+			// NB: use a negative Y here, as row ID goes from 0..n it's approaching the negative Y of real space
+			let sector_origin = Vec2::new(
+				(sector.get_column() * self.get_sector_resolution()) as f32,
+				(sector.get_row() * self.get_sector_resolution()) as f32 * -1.0,
+			);
+			// now we know the real-space coordinates of the top left corner of the sector
+			let xy_of_sector_top_left = sector_grid_origin_offset + sector_origin;
 
-		// determine the unit size of a field cell
-		let cell_size = self.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
-		// from a cell origin of (0, 0) find the cell position relative to the field grid
-		// NB: we add half of the cell size to each coord to obtain the centre position of the cell
-		// NB: use negative Y here, as row ID goes form 0..n it's approaching negative Y of real-space
-		let cell_position = Vec2::new(
-			field.get_column() as f32 * cell_size + cell_size / 2.0,
-			(field.get_row() as f32 * cell_size + cell_size / 2.0) * -1.0,
-		);
+			// determine the unit size of a field cell
+			let cell_size = self.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+			// from a cell origin of (0, 0) find the cell position relative to the field grid
+			// NB: we add half of the cell size to each coord to obtain the centre position of the cell
+			// NB: use negative Y here, as row ID goes form 0..n it's approaching negative Y of real-space
+			let cell_position = Vec2::new(
+				field.get_column() as f32 * cell_size + cell_size / 2.0,
+				(field.get_row() as f32 * cell_size + cell_size / 2.0) * -1.0,
+			);
 
-		let real_space_pos = xy_of_sector_top_left + cell_position;
-		// ensure not outside world
-		if real_space_pos.x.abs() > self.get_length() as f32 / 2.0
-			|| real_space_pos.y.abs() > self.get_depth() as f32 / 2.0
-		{
-			None
-		} else {
-			Some(real_space_pos)
+			let real_space_pos = xy_of_sector_top_left + cell_position;
+			// ensure not outside world
+			if real_space_pos.x.abs() > self.get_length() as f32 / 2.0
+				|| real_space_pos.y.abs() > self.get_depth() as f32 / 2.0
+			{
+				None
+			} else {
+				Some(real_space_pos)
+			}
 		}
 	}
 
@@ -288,43 +363,51 @@ impl MapDimensions {
 	/// The `y` coordinate is defaulted to `0.0`.
 	#[cfg(feature = "3d")]
 	pub fn get_xyz_from_field_sector(&self, sector: SectorID, field: FieldCell) -> Option<Vec3> {
-		// the sector grid always begins in the top left
-		// from real-space origin of (0,0,0) find the position of SectorID(0,0) in real space
-		let sector_grid_origin_offset = {
-			Vec3::new(
-				self.get_length() as f32 / -2.0,
+		#[cfg(feature = "large_world")]
+		{
+			self.get_dxyz_from_field_sector(sector, field)
+				.map(|position| position.as_vec3())
+		}
+		#[cfg(not(feature = "large_world"))]
+		{
+			// the sector grid always begins in the top left
+			// from real-space origin of (0,0,0) find the position of SectorID(0,0) in real space
+			let sector_grid_origin_offset = {
+				Vec3::new(
+					self.get_length() as f32 / -2.0,
+					0.0,
+					self.get_depth() as f32 / -2.0,
+				)
+			};
+			// the sector grid starts top left at (0,0), based on the sector we want find its origin
+			// with how many units make up a sector and and sector mXn ID
+			let sector_origin = Vec3::new(
+				(sector.get_column() * self.get_sector_resolution()) as f32,
 				0.0,
-				self.get_depth() as f32 / -2.0,
-			)
-		};
-		// the sector grid starts top left at (0,0), based on the sector we want find its origin
-		// with how many units make up a sector and and sector mXn ID
-		let sector_origin = Vec3::new(
-			(sector.get_column() * self.get_sector_resolution()) as f32,
-			0.0,
-			(sector.get_row() * self.get_sector_resolution()) as f32,
-		);
-		// now we know the real-space coordinates of the top left corner of the sector
-		let xyz_of_sector_top_left = sector_grid_origin_offset + sector_origin;
+				(sector.get_row() * self.get_sector_resolution()) as f32,
+			);
+			// now we know the real-space coordinates of the top left corner of the sector
+			let xyz_of_sector_top_left = sector_grid_origin_offset + sector_origin;
 
-		// determine the unit size of a field cell
-		let cell_size = self.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
-		// from a cell origin of (0, 0) find the cell position relative to the field grid
-		// NB: we add half of the cell size to each coord to obtain the centre position of the cell
-		let cell_position = Vec3::new(
-			field.get_column() as f32 * cell_size + cell_size / 2.0,
-			0.0,
-			field.get_row() as f32 * cell_size + cell_size / 2.0,
-		);
+			// determine the unit size of a field cell
+			let cell_size = self.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+			// from a cell origin of (0, 0) find the cell position relative to the field grid
+			// NB: we add half of the cell size to each coord to obtain the centre position of the cell
+			let cell_position = Vec3::new(
+				field.get_column() as f32 * cell_size + cell_size / 2.0,
+				0.0,
+				field.get_row() as f32 * cell_size + cell_size / 2.0,
+			);
 
-		let real_space_pos = xyz_of_sector_top_left + cell_position;
-		// ensure not outside world
-		if real_space_pos.x.abs() > self.get_length() as f32 / 2.0
-			|| real_space_pos.z.abs() > self.get_depth() as f32 / 2.0
-		{
-			None
-		} else {
-			Some(real_space_pos)
+			let real_space_pos = xyz_of_sector_top_left + cell_position;
+			// ensure not outside world
+			if real_space_pos.x.abs() > self.get_length() as f32 / 2.0
+				|| real_space_pos.z.abs() > self.get_depth() as f32 / 2.0
+			{
+				None
+			} else {
+				Some(real_space_pos)
+			}
 		}
 	}
 
@@ -332,36 +415,44 @@ impl MapDimensions {
 	/// the sector ID that point resides in
 	#[cfg(feature = "3d")]
 	pub fn get_sector_id_from_xyz(&self, position: Vec3) -> Option<SectorID> {
-		if position.x < -((self.get_length() / 2) as f32)
-			|| position.x > (self.get_length() / 2) as f32
-			|| position.z < -((self.get_depth() / 2) as f32)
-			|| position.z > (self.get_depth() / 2) as f32
+		#[cfg(feature = "large_world")]
 		{
-			error!("Position is out of bounds of MapDimensions, x {}, y {}, cannot calculate SectorID. Is the actor outside of the map or trying to request route outside of it?", position.x, position.y);
-			//TODO use Result instead
-			return None;
-		}
-		let x_sector_count = self.get_length() / self.get_sector_resolution();
-		let z_sector_count = self.get_depth() / self.get_sector_resolution();
-		// The 3D world is centred at origin (0, 0, 0). The sector grid has an origin in the top
-		// left at 2D world coords of (-map_x / 2, 0, map_z / 2).
-		// To translate the 3D world
-		// coords into a new coordinate system with a (0, 0, 0) origin in the top left we add
-		// half the map dimension to each psition coordinatem
-		let x_origin = position.x + (self.get_length() / 2) as f32;
-		let z_origin = (self.get_depth() / 2) as f32 + position.z;
-		// the grid IDs follow a (column, row) convention, by dividing the repositioned dimension
-		// by the sector grid sizes and rounding down we determine the sector indices
-		let mut column = (x_origin / (self.get_sector_resolution() as f32)).floor() as u32;
-		let mut row = (z_origin / (self.get_sector_resolution() as f32)).floor() as u32;
-		// safety for x-z being at the exact limits of map size
-		if column >= x_sector_count {
-			column = x_sector_count - 1;
+			self.get_sector_and_field_cell_from_dxyz(position.as_dvec3())
+				.map(|(sector_id, _)| sector_id)
 		}
-		if row >= z_sector_count {
-			row = z_sector_count - 1;
+		#[cfg(not(feature = "large_world"))]
+		{
+			if position.x < -((self.get_length() / 2) as f32)
+				|| position.x > (self.get_length() / 2) as f32
+				|| position.z < -((self.get_depth() / 2) as f32)
+				|| position.z > (self.get_depth() / 2) as f32
+			{
+				error!("Position is out of bounds of MapDimensions, x {}, y {}, cannot calculate SectorID. Is the actor outside of the map or trying to request route outside of it?", position.x, position.y);
+				//TODO use Result instead
+				return None;
+			}
+			let x_sector_count = self.get_length() / self.get_sector_resolution();
+			let z_sector_count = self.get_depth() / self.get_sector_resolution();
+			// The 3D world is centred at origin (0, 0, 0). The sector grid has an origin in the top
+			// left at 2D world coords of (-map_x / 2, 0, map_z / 2).
+			// To translate the 3D world
+			// coords into a new coordinate system with a (0, 0, 0) origin in the top left we add
+			// half the map dimension to each psition coordinatem
+			let x_origin = position.x + (self.get_length() / 2) as f32;
+			let z_origin = (self.get_depth() / 2) as f32 + position.z;
+			// the grid IDs follow a (column, row) convention, by dividing the repositioned dimension
+			// by the sector grid sizes and rounding down we determine the sector indices
+			let mut column = (x_origin / (self.get_sector_resolution() as f32)).floor() as u32;
+			let mut row = (z_origin / (self.get_sector_resolution() as f32)).floor() as u32;
+			// safety for x-z being at the exact limits of map size
+			if column >= x_sector_count {
+				column = x_sector_count - 1;
+			}
+			if row >= z_sector_count {
+				row = z_sector_count - 1;
+			}
+			Some(SectorID::new(column, row))
 		}
-		Some(SectorID::new(column, row))
 	}
 
 	/// Calculate the `x, y, z` coordinates at the top-left corner of a sector based on map dimensions
@@ -382,29 +473,128 @@ impl MapDimensions {
 		&self,
 		position: Vec3,
 	) -> Option<(SectorID, FieldCell)> {
-		if let Some(sector_id) = self.get_sector_id_from_xyz(position) {
-			let sector_corner_origin = self.get_sector_corner_xyz(sector_id);
-			let resolution_by_field_dimension =
-				self.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
-			let field_id_0 = ((position.x - sector_corner_origin.x) / resolution_by_field_dimension)
-				.floor() as usize;
-			let field_id_1 = ((position.z - sector_corner_origin.z) / resolution_by_field_dimension)
-				.floor() as usize;
-			let field_id = FieldCell::new(field_id_0, field_id_1);
-			return Some((sector_id, field_id));
+		#[cfg(feature = "large_world")]
+		{
+			self.get_sector_and_field_cell_from_dxyz(position.as_dvec3())
+		}
+		#[cfg(not(feature = "large_world"))]
+		{
+			if let Some(sector_id) = self.get_sector_id_from_xyz(position) {
+				let sector_corner_origin = self.get_sector_corner_xyz(sector_id);
+				let resolution_by_field_dimension =
+					self.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+				let field_id_0 = ((position.x - sector_corner_origin.x)
+					/ resolution_by_field_dimension)
+					.floor() as usize;
+				let field_id_1 = ((position.z - sector_corner_origin.z)
+					/ resolution_by_field_dimension)
+					.floor() as usize;
+				let field_id = FieldCell::new(field_id_0, field_id_1);
+				return Some((sector_id, field_id));
+			}
+			None
 		}
-		None
 	}
 
+	/// From a position measured from the top left corner of the map, `x`
+	/// along its length and `y` along its depth, find the sector and field
+	/// cell it resides in. The global [FieldCell] is found first so that
+	/// the sector and cell always agree, even at the far edges of a huge map
+	#[cfg(feature = "large_world")]
+	fn get_sector_and_field_cell_from_offset(
+		&self,
+		x: f64,
+		y: f64,
+	) -> Option<(SectorID, FieldCell)> {
+		if !(0.0..=self.get_length() as f64).contains(&x)
+			|| !(0.0..=self.get_depth() as f64).contains(&y)
+		{
+			error!("Position is out of bounds of MapDimensions, x {}, y {}, cannot calculate SectorID. Is the actor outside of the map or trying to request route outside of it?", x, y);
+			return None;
+		}
+		let cell_size = self.get_sector_resolution() as f64 / FIELD_RESOLUTION as f64;
+		// safety for positions at the exact limits of map size
+		let column =
+			((x / cell_size).floor() as usize).min(self.get_total_field_cell_columns() - 1);
+		let row = ((y / cell_size).floor() as usize).min(self.get_total_field_cell_rows() - 1);
+		self.sector_and_cell_from_global(column as u32, row as u32)
+	}
+	/// Find the offset of the centre of a [FieldCell] from the top left
+	/// corner of the map, see [MapDimensions::get_sector_and_field_cell_from_offset].
+	/// Returns [None] if the cell is outside of the map
+	#[cfg(feature = "large_world")]
+	fn get_offset_from_field_sector(
+		&self,
+		sector: SectorID,
+		field: FieldCell,
+	) -> Option<(f64, f64)> {
+		if field.get_column() >= FIELD_RESOLUTION || field.get_row() >= FIELD_RESOLUTION {
+			return None;
+		}
+		let (column, row) = self.global_cell_from(sector, field);
+		if column as usize >= self.get_total_field_cell_columns()
+			|| row as usize >= self.get_total_field_cell_rows()
+		{
+			return None;
+		}
+		let cell_size = self.get_sector_resolution() as f64 / FIELD_RESOLUTION as f64;
+		Some((
+			(column as f64 + 0.5) * cell_size,
+			(row as f64 + 0.5) * cell_size,
+		))
+	}
+	/// As with [MapDimensions::get_sector_and_field_cell_from_xy] from an
+	/// `f64` position, so that positions far from the origin of a huge map
+	/// are bucketed accurately
+	#[cfg(all(feature = "2d", feature = "large_world"))]
+	pub fn get_sector_and_field_cell_from_dxy(
+		&self,
+		position: DVec2,
+	) -> Option<(SectorID, FieldCell)> {
+		// the sector grid begins in the top left and rows approach negative y
+		self.get_sector_and_field_cell_from_offset(
+			position.x + self.get_length() as f64 / 2.0,
+			self.get_depth() as f64 / 2.0 - position.y,
+		)
+	}
+	/// As with [MapDimensions::get_xy_from_field_sector] as an `f64` position
+	#[cfg(feature = "large_world")]
+	pub fn get_dxy_from_field_sector(&self, sector: SectorID, field: FieldCell) -> Option<DVec2> {
+		let (x, y) = self.get_offset_from_field_sector(sector, field)?;
+		Some(DVec2::new(
+			x - self.get_length() as f64 / 2.0,
+			self.get_depth() as f64 / 2.0 - y,
+		))
+	}
+	/// As with [MapDimensions::get_sector_and_field_cell_from_xyz] from an
+	/// `f64` position, so that positions far from the origin of a huge map
+	/// are bucketed accurately
+	#[cfg(all(feature = "3d", feature = "large_world"))]
+	pub fn get_sector_and_field_cell_from_dxyz(
+		&self,
+		position: DVec3,
+	) -> Option<(SectorID, FieldCell)> {
+		// the sector grid begins in the top left and rows approach positive z
+		self.get_sector_and_field_cell_from_offset(
+			position.x + self.get_length() as f64 / 2.0,
+			position.z + self.get_depth() as f64 / 2.0,
+		)
+	}
+	/// As with [MapDimensions::get_xyz_from_field_sector] as an `f64`
+	/// position, the `y` coordinate is defaulted to `0.0`
+	#[cfg(all(feature = "3d", feature = "large_world"))]
+	pub fn get_dxyz_from_field_sector(&self, sector: SectorID, field: FieldCell) -> Option<DVec3> {
+		let (x, z) = self.get_offset_from_field_sector(sector, field)?;
+		Some(DVec3::new(
+			x - self.get_length() as f64 / 2.0,
+			0.0,
+			z - self.get_depth() as f64 / 2.0,
+		))
+	}
 	/// A sector has up to four neighbours. Based on the ID of the sector and the dimensions
 	/// of the map retrieve the IDs neighbouring sectors
 	pub fn get_ids_of_neighbouring_sectors(self, sector_id: &SectorID) -> Vec<SectorID> {
-		Ordinal::get_sector_neighbours(
-			sector_id,
-			self.get_length(),
-			self.get_depth(),
-			self.get_sector_resolution(),
-		)
+		self.get_sector_grid().orthogonal_neighbours(sector_id)
 	}
 
 	/// A sector has up to four neighbours. Based on the ID of the sector and the dimensions
@@ -414,12 +604,8 @@ impl MapDimensions {
 		&self,
 		sector_id: &SectorID,
 	) -> Vec<(Ordinal, SectorID)> {
-		Ordinal::get_sector_neighbours_with_ordinal(
-			sector_id,
-			self.get_length(),
-			self.get_depth(),
-			self.get_sector_resolution(),
-		)
+		self.get_sector_grid()
+			.orthogonal_neighbours_with_ordinal(sector_id)
 	}
 	/// From an [Ordinal] get the ID of a neighbouring sector. Returns [None]
 	/// if the sector would be out of bounds
@@ -428,90 +614,42 @@ impl MapDimensions {
 		ordinal: Ordinal,
 		sector_id: &SectorID,
 	) -> Option<SectorID> {
-		match ordinal {
-			Ordinal::North => sector_id
-				.get_row()
-				.checked_sub(1)
-				.map(|row| SectorID::new(sector_id.get_column(), row)),
-			Ordinal::East => {
-				if sector_id.get_column() + 1 < self.get_length() / self.get_sector_resolution() - 1
-				{
-					Some(SectorID::new(
-						sector_id.get_column() + 1,
-						sector_id.get_row(),
-					))
-				} else {
-					None
-				}
-			}
-			Ordinal::South => {
-				if sector_id.get_row() + 1 < self.get_depth() / self.get_sector_resolution() - 1 {
-					Some(SectorID::new(
-						sector_id.get_column(),
-						sector_id.get_row() + 1,
-					))
-				} else {
-					None
-				}
-			}
-			Ordinal::West => sector_id
-				.get_column()
-				.checked_sub(1)
-				.map(|column| SectorID::new(column, sector_id.get_row())),
-			Ordinal::NorthEast => {
-				if let Some(row) = sector_id.get_row().checked_sub(1) {
-					if sector_id.get_column() + 1
-						< self.get_length() / self.get_sector_resolution() - 1
-					{
-						Some(SectorID::new(sector_id.get_column() + 1, row))
-					} else {
-						None
-					}
-				} else {
-					None
-				}
-			}
-			Ordinal::SouthEast => {
-				if sector_id.get_row() + 1 < self.get_depth() / self.get_sector_resolution() - 1 {
-					if sector_id.get_column() + 1
-						< self.get_length() / self.get_sector_resolution() - 1
-					{
-						Some(SectorID::new(
-							sector_id.get_column() + 1,
-							sector_id.get_row() + 1,
-						))
-					} else {
-						None
-					}
-				} else {
-					None
-				}
-			}
-			Ordinal::SouthWest => {
-				if sector_id.get_row() + 1 < self.get_depth() / self.get_sector_resolution() - 1 {
-					sector_id
-						.get_column()
-						.checked_sub(1)
-						.map(|column| SectorID::new(column, sector_id.get_row() + 1))
-				} else {
-					None
-				}
-			}
-			Ordinal::NorthWest => {
-				if let Some(row) = sector_id.get_row().checked_sub(1) {
-					sector_id
-						.get_column()
-						.checked_sub(1)
-						.map(|column| SectorID::new(column, row))
-				} else {
-					None
-				}
-			}
-			Ordinal::Zero => {
-				error!("`get_sector_id_from_ordinal` should never be called with `Ordinal::Zero`");
-				None
-			}
+		if ordinal == Ordinal::Zero {
+			error!("`get_sector_id_from_ordinal` should never be called with `Ordinal::Zero`");
+			return None;
 		}
+		self.get_sector_grid().neighbour(sector_id, ordinal)
+	}
+	/// Get the [Ordinal] boundaries of `sector_id` whose [Portals] may have
+	/// been affected by a change to the cost of the `field_cells`. A cell
+	/// affects a boundary when it lies on it or, once scaled by the actor
+	/// size, close enough that impassable costs can be expanded onto it.
+	/// Boundaries along the edge of the map are never included
+	pub fn get_boundaries_affected_by(
+		&self,
+		sector_id: &SectorID,
+		field_cells: &[FieldCell],
+	) -> Vec<Ordinal> {
+		// scaling expands impassable cells by up to `actor_scale` cells
+		let margin = if self.get_actor_scale() > 1 {
+			self.get_actor_scale() as usize
+		} else {
+			0
+		};
+		let end = FIELD_RESOLUTION - 1;
+		self.get_sector_grid()
+			.portal_ordinals(sector_id)
+			.into_iter()
+			.filter(|ordinal| {
+				field_cells.iter().any(|cell| match ordinal {
+					Ordinal::North => cell.get_row() <= margin,
+					Ordinal::East => cell.get_column() + margin >= end,
+					Ordinal::South => cell.get_row() + margin >= end,
+					Ordinal::West => cell.get_column() <= margin,
+					_ => false,
+				})
+			})
+			.collect()
 	}
 	// /// From a list of meshes find the maximum and minimum x-y dimensions across all meshes to represent the size of the world as an MxN set of Flowfields
 	// #[cfg(feature = "2d")]
@@ -572,6 +710,21 @@ impl MapDimensions {
 mod tests {
 	use super::*;
 	#[test]
+	fn global_cell_round_trip() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_id = SectorID::new(1, 2);
+		let field_cell = FieldCell::new(3, 7);
+		let global = map_dimensions.global_cell_from(sector_id, field_cell);
+		assert_eq!((13, 27), global);
+		let result = map_dimensions.sector_and_cell_from_global(global.0, global.1);
+		assert_eq!(Some((sector_id, field_cell)), result);
+	}
+	#[test]
+	fn global_cell_out_of_bounds() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		assert_eq!(None, map_dimensions.sector_and_cell_from_global(30, 0));
+	}
+	#[test]
 	fn sector_costfields_top_left_sector_id_from_xyz() {
 		let map_dimensions = MapDimensions::new(20, 20, 10, 1.0);
 		let position = Vec3::new(-5.0, 0.0, -5.0);
@@ -856,6 +1009,48 @@ mod tests {
 		assert_eq!(actual, result.unwrap());
 	}
 	#[test]
+	fn sector_id_ordinal_last_column_and_row() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_id = SectorID::new(1, 1);
+		let east = map_dimensions.get_sector_id_from_ordinal(Ordinal::East, &sector_id);
+		assert_eq!(Some(SectorID::new(2, 1)), east);
+		let south = map_dimensions.get_sector_id_from_ordinal(Ordinal::South, &sector_id);
+		assert_eq!(Some(SectorID::new(1, 2)), south);
+		let south_east = map_dimensions.get_sector_id_from_ordinal(Ordinal::SouthEast, &sector_id);
+		assert_eq!(Some(SectorID::new(2, 2)), south_east);
+		let edge = SectorID::new(2, 2);
+		assert!(map_dimensions
+			.get_sector_id_from_ordinal(Ordinal::East, &edge)
+			.is_none());
+	}
+	#[test]
+	fn boundaries_affected_by_cells() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let centre = SectorID::new(1, 1);
+		let interior = [FieldCell::new(4, 4), FieldCell::new(5, 8)];
+		assert!(map_dimensions
+			.get_boundaries_affected_by(&centre, &interior)
+			.is_empty());
+		let corner = [FieldCell::new(9, 0)];
+		assert_eq!(
+			vec![Ordinal::North, Ordinal::East],
+			map_dimensions.get_boundaries_affected_by(&centre, &corner)
+		);
+		// boundaries along the edge of the map have no portals
+		let top_left = SectorID::new(0, 0);
+		let cells = [FieldCell::new(0, 0), FieldCell::new(9, 9)];
+		assert_eq!(
+			vec![Ordinal::East, Ordinal::South],
+			map_dimensions.get_boundaries_affected_by(&top_left, &cells)
+		);
+		// larger actors expand costs towards the boundaries
+		let map_dimensions = MapDimensions::new(30, 30, 10, 2.0);
+		assert_eq!(
+			vec![Ordinal::South],
+			map_dimensions.get_boundaries_affected_by(&centre, &[FieldCell::new(4, 7)])
+		);
+	}
+	#[test]
 	fn sector_id_ordinal_oob() {
 		let map_dimensions = MapDimensions::new(300, 300, 10, 0.5);
 		let sector_id = SectorID::new(1, 0);
@@ -910,4 +1105,35 @@ mod tests {
 	// 	let actual_size = (30, 20);
 	// 	assert_eq!(actual_size, result_size);
 	// }
+	#[test]
+	#[cfg(all(feature = "3d", feature = "large_world"))]
+	fn large_world_xyz() {
+		let map_dimensions = MapDimensions::new(20_000_000, 20_000_000, 1000, 1.0);
+		// an f32 would round this onto the next cell
+		let position = DVec3::new(9_999_899.99, 0.0, -9_999_999.5);
+		let result = map_dimensions.get_sector_and_field_cell_from_dxyz(position);
+		let actual = Some((SectorID::new(19_999, 0), FieldCell::new(8, 0)));
+		assert_eq!(actual, result);
+		let centre = map_dimensions
+			.get_dxyz_from_field_sector(SectorID::new(19_999, 0), FieldCell::new(8, 0))
+			.unwrap();
+		assert_eq!(DVec3::new(9_999_850.0, 0.0, -9_999_950.0), centre);
+		assert!(map_dimensions
+			.get_sector_and_field_cell_from_dxyz(DVec3::new(10_000_000.5, 0.0, 0.0))
+			.is_none());
+	}
+	#[test]
+	#[cfg(all(feature = "2d", feature = "large_world"))]
+	fn large_world_xy() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 1.0);
+		let result = map_dimensions.get_sector_and_field_cell_from_dxy(DVec2::new(-1.5, 1.5));
+		let actual = Some((SectorID::new(1, 1), FieldCell::new(3, 3)));
+		assert_eq!(actual, result);
+		let result = map_dimensions.get_sector_and_field_cell_from_xy(Vec2::new(15.0, -15.0));
+		let actual = Some((SectorID::new(2, 2), FieldCell::new(9, 9)));
+		assert_eq!(actual, result);
+		let centre =
+			map_dimensions.get_xy_from_field_sector(SectorID::new(1, 1), FieldCell::new(3, 3));
+		assert_eq!(Some(Vec2::new(-1.5, 1.5)), centre);
+	}
 }
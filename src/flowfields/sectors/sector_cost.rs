@@ -3,10 +3,12 @@
 //!
 //!
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use crate::prelude::*;
 use bevy::prelude::*;
+#[cfg(use_rayon)]
+use rayon::prelude::*;
 
 /// Keys represent unique sector IDs and are in the format of `(column, row)`
 /// when considering a grid of sectors across the map. The sectors begin in the
@@ -21,9 +23,32 @@ use bevy::prelude::*;
 #[reflect(Component)]
 pub struct SectorCostFields {
 	/// Initial costs based on the unit size of each field
-	baseline: BTreeMap<SectorID, CostField>,
+	pub(super) baseline: BTreeMap<SectorID, CostField>,
 	/// Each [FieldCell] containing an impassable `255` value is scaled based on actor size to close off gaps which the actor could not path through
 	scaled: BTreeMap<SectorID, CostField>,
+	/// Sectors modified through a [CostFieldEditGuard] whose portals, graph
+	/// and cached routes are yet to be updated
+	#[reflect(ignore)]
+	#[cfg_attr(feature = "serde", serde(skip))]
+	edited_sectors: Vec<SectorID>,
+	/// Sectors which are entirely impassable and omitted from the `baseline`
+	/// and `scaled` fields to save memory, they're treated as impassable
+	void: BTreeSet<SectorID>,
+	/// User data of each [FieldCell], only sectors which have been given a
+	/// value are stored
+	#[cfg(feature = "metafield")]
+	meta: BTreeMap<SectorID, MetaField>,
+	/// Directions which can't be stepped in from each [FieldCell], only
+	/// sectors with one-way movement are stored
+	one_way: BTreeMap<SectorID, OneWayField>,
+	/// Elevation of each [FieldCell], only sectors which aren't flat are
+	/// stored
+	heights: BTreeMap<SectorID, HeightField>,
+	/// Extra cost of a step climbing or descending between heights
+	slope_cost: SlopeCost,
+	/// Longest window of pathable [FieldCell]s along a sector boundary that
+	/// is represented by a single portal, longer windows are split
+	max_portal_window: Option<usize>,
 }
 
 impl SectorCostFields {
@@ -62,6 +87,10 @@ impl SectorCostFields {
 		&self.baseline
 	}
 	/// Get a mutable reference to the map of the baseline sectors and [CostField]
+	#[deprecated(
+		since = "0.13.0",
+		note = "mutating costs directly does not rescale the sector or invalidate portals, use `SectorCostFields::edit` instead"
+	)]
 	pub fn get_baseline_mut(&mut self) -> &mut BTreeMap<SectorID, CostField> {
 		&mut self.baseline
 	}
@@ -70,6 +99,10 @@ impl SectorCostFields {
 		&self.scaled
 	}
 	/// Get a mutable reference to the map of scaled sectors and [CostField]
+	#[deprecated(
+		since = "0.13.0",
+		note = "mutating costs directly does not rescale the sector or invalidate portals, use `SectorCostFields::edit` instead"
+	)]
 	pub fn get_scaled_mut(&mut self) -> &mut BTreeMap<SectorID, CostField> {
 		&mut self.scaled
 	}
@@ -82,13 +115,13 @@ impl SectorCostFields {
 	pub fn set_field_cell_value(
 		&mut self,
 		sector_id: SectorID,
-		value: u8,
+		value: impl Into<Cost>,
 		field_cell: FieldCell,
 		map_dimensions: &MapDimensions,
 	) {
-		if let Some(cost_field) = self.get_baseline_mut().get_mut(&sector_id) {
-			cost_field.set_field_cell_value(value, field_cell);
-			self.scale_costfield(&sector_id, map_dimensions)
+		if let Some(cost_field) = self.baseline.get_mut(&sector_id) {
+			cost_field.set_field_cell_value(value.into().get(), field_cell);
+			self.rescale_around(sector_id, &[field_cell], map_dimensions)
 		} else {
 			error!(
 				"Cannot mutate CostField in non-existent sector {:?}",
@@ -96,477 +129,896 @@ impl SectorCostFields {
 			);
 		}
 	}
-	/// Iterate over all sectors and scale any impassable [FieldCell] based on `actor_scale`.
-	///
-	/// This can be expensive so should typically be used as part of data initialisation, i.e when loading [SectorCostFields] from a file or within a loading type of operation to a world
-	pub fn scale_all_costfields(&mut self, map_dimensions: &MapDimensions) {
-		let sector_ids: Vec<SectorID> = self.baseline.keys().cloned().collect();
-		for sector_id in sector_ids.iter() {
-			self.scaled.insert(
-				*sector_id,
-				self.get_baseline().get(sector_id).unwrap().clone(),
+	/// Begin a guarded edit of the [CostField] within `sector_id`. Changes are
+	/// made to the baseline field and when the returned [CostFieldEditGuard]
+	/// is dropped the sector is rescaled and marked as edited so that the
+	/// plugin updates the portals, graph and caches of the sector. Returns
+	/// [None] if the sector doesn't exist
+	pub fn edit<'a>(
+		&'a mut self,
+		sector_id: SectorID,
+		map_dimensions: &'a MapDimensions,
+	) -> Option<CostFieldEditGuard<'a>> {
+		if !self.baseline.contains_key(&sector_id) {
+			error!(
+				"Cannot edit CostField in non-existent sector {:?}",
+				sector_id
 			);
+			return None;
 		}
-		for sector_id in sector_ids.iter() {
-			self.scale_costfield(sector_id, map_dimensions);
-		}
+		Some(CostFieldEditGuard {
+			sector_cost_fields: self,
+			sector_id,
+			map_dimensions,
+			changed_cells: Vec::new(),
+		})
 	}
-	/// Inspects a sector for impassable cost values and based on an actor
-	/// scale it expands any impassable costs into any neighbouring orthogonal
-	/// [FieldCell] to close off any gaps so that the actor won't try and path
-	/// through a gap it can't fit
-	fn scale_costfield(&mut self, sector_id: &SectorID, map_dimensions: &MapDimensions) {
-		/// Helper updates a tracker of what cells/sectors have been processed
-		fn update_processed(
-			processed: &mut BTreeMap<SectorID, Vec<FieldCell>>,
-			field_cell: FieldCell,
-			sector_id: &SectorID,
-		) {
-			if let Some(list) = processed.get_mut(sector_id) {
-				list.push(field_cell);
-			} else {
-				processed.insert(*sector_id, vec![field_cell]);
+	/// Get the baseline cost of every [FieldCell] which differs between these
+	/// fields and the `other` fields, as the value from `other`. Applying the
+	/// result with [SectorCostFields::apply_diff] makes these fields match,
+	/// so a server can replicate cost changes to clients by sending only the
+	/// cells that changed since the last snapshot. Sectors which are void in
+	/// either set of fields aren't compared
+	pub fn diff(&self, other: &SectorCostFields) -> Vec<(SectorID, FieldCell, u8)> {
+		let mut changes = Vec::new();
+		for (sector_id, cost_field) in self.baseline.iter() {
+			let Some(other_field) = other.baseline.get(sector_id) else {
+				continue;
+			};
+			for column in 0..FIELD_RESOLUTION {
+				for row in 0..FIELD_RESOLUTION {
+					let field_cell = FieldCell::new(column, row);
+					let value = other_field.get_field_cell_value(field_cell);
+					if cost_field.get_field_cell_value(field_cell) != value {
+						changes.push((*sector_id, field_cell, value));
+					}
+				}
 			}
 		}
-		/// Helper that adds tracking data of what final cells to update in the scaled fields
-		fn add_to_be_marked(
-			marks_as_impassable: &mut BTreeMap<SectorID, Vec<FieldCell>>,
-			processed: &BTreeMap<SectorID, Vec<FieldCell>>,
-		) {
-			for (sector, cell_list) in processed.iter() {
-				if let Some(list) = marks_as_impassable.get_mut(sector) {
-					list.extend(cell_list);
-				} else {
-					marks_as_impassable.insert(*sector, cell_list.clone());
+		changes
+	}
+	/// Set the baseline cost of each `(sector, field_cell, cost)` of a
+	/// `diff` produced by [SectorCostFields::diff]. Each sector is changed
+	/// through a [CostFieldEditGuard] so it's rescaled and marked as edited,
+	/// the plugin then updates its portals, the [PortalGraph] and invalidates
+	/// any cached routes and [FlowField]s using it
+	pub fn apply_diff(
+		&mut self,
+		diff: &[(SectorID, FieldCell, u8)],
+		map_dimensions: &MapDimensions,
+	) {
+		let mut sectors: BTreeMap<SectorID, Vec<(FieldCell, u8)>> = BTreeMap::new();
+		for (sector_id, field_cell, value) in diff.iter() {
+			sectors
+				.entry(*sector_id)
+				.or_default()
+				.push((*field_cell, *value));
+		}
+		for (sector_id, cells) in sectors {
+			if let Some(mut guard) = self.edit(sector_id, map_dimensions) {
+				for (field_cell, value) in cells {
+					guard.set_field_cell_value(field_cell, value);
 				}
 			}
 		}
-
-		if map_dimensions.get_actor_scale() == 1 {
-			self.scaled.insert(
-				*sector_id,
-				self.get_baseline().get(sector_id).unwrap().clone(),
+	}
+	/// Get a reference to the map of sectors and [MetaField]
+	#[cfg(feature = "metafield")]
+	pub fn get_meta_fields(&self) -> &BTreeMap<SectorID, MetaField> {
+		&self.meta
+	}
+	/// Get the user data of a [FieldCell] within `sector_id`, cells which
+	/// have never been given a value are `0`. Returns [None] if the sector
+	/// doesn't exist
+	#[cfg(feature = "metafield")]
+	pub fn get_field_cell_meta(&self, sector_id: SectorID, field_cell: FieldCell) -> Option<u8> {
+		if !self.baseline.contains_key(&sector_id) {
+			return None;
+		}
+		Some(
+			self.meta
+				.get(&sector_id)
+				.map_or(0, |meta| meta.get_field_cell_value(field_cell)),
+		)
+	}
+	/// Set the user data of a [FieldCell] within `sector_id`. This has no
+	/// effect on pathfinding so the sector is not rescaled or marked as edited
+	#[cfg(feature = "metafield")]
+	pub fn set_field_cell_meta(&mut self, sector_id: SectorID, value: u8, field_cell: FieldCell) {
+		if self.baseline.contains_key(&sector_id) {
+			self.meta
+				.entry(sector_id)
+				.or_default()
+				.set_field_cell_value(value, field_cell);
+		} else {
+			error!(
+				"Cannot mutate MetaField in non-existent sector {:?}",
+				sector_id
+			);
+		}
+	}
+	/// Get a reference to the map of sectors and [OneWayField]
+	pub fn get_one_way_fields(&self) -> &BTreeMap<SectorID, OneWayField> {
+		&self.one_way
+	}
+	/// Get the [OneWayField] of `sector_id`, [None] if the sector has no
+	/// one-way movement
+	pub fn get_one_way_field(&self, sector_id: SectorID) -> Option<&OneWayField> {
+		self.one_way.get(&sector_id)
+	}
+	/// Make the step from a [FieldCell] to its neighbour in the orthogonal
+	/// direction `ordinal` one-way, such as dropping off a ledge. Actors may
+	/// still step from the [FieldCell] to the neighbour but never back, the
+	/// neighbour may lie in the adjoining sector. The sectors are marked as
+	/// edited so that the plugin updates the portals, graph and caches
+	pub fn set_one_way(
+		&mut self,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+		ordinal: Ordinal,
+		map_dimensions: &MapDimensions,
+	) {
+		self.set_return_blocked(sector_id, field_cell, ordinal, true, map_dimensions);
+	}
+	/// Allow actors to step back from the neighbour of a [FieldCell] in the
+	/// direction `ordinal` again, see [SectorCostFields::set_one_way]
+	pub fn clear_one_way(
+		&mut self,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+		ordinal: Ordinal,
+		map_dimensions: &MapDimensions,
+	) {
+		self.set_return_blocked(sector_id, field_cell, ordinal, false, map_dimensions);
+	}
+	/// Block or allow the step back to a [FieldCell] from its neighbour in
+	/// the direction `ordinal`
+	fn set_return_blocked(
+		&mut self,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+		ordinal: Ordinal,
+		blocked: bool,
+		map_dimensions: &MapDimensions,
+	) {
+		let (column, row) = map_dimensions.global_cell_from(sector_id, field_cell);
+		let neighbour = match ordinal {
+			Ordinal::North => row.checked_sub(1).map(|row| (column, row)),
+			Ordinal::East => Some((column + 1, row)),
+			Ordinal::South => Some((column, row + 1)),
+			Ordinal::West => column.checked_sub(1).map(|column| (column, row)),
+			_ => {
+				error!("One-way movement must be orthogonal, not {:?}", ordinal);
+				return;
+			}
+		};
+		let Some((neighbour_sector, neighbour_cell)) = neighbour
+			.and_then(|(column, row)| map_dimensions.sector_and_cell_from_global(column, row))
+		else {
+			error!(
+				"Cannot make movement one-way off the edge of the map from {:?} {:?}",
+				sector_id, field_cell
+			);
+			return;
+		};
+		if !self.baseline.contains_key(&neighbour_sector) {
+			error!(
+				"Cannot mutate OneWayField in non-existent sector {:?}",
+				neighbour_sector
 			);
+			return;
+		}
+		let field = self.one_way.entry(neighbour_sector).or_default();
+		if blocked {
+			field.block_exit(neighbour_cell, ordinal.inverse());
 		} else {
-			// identify all impassable cells
-			let mut impassable_indices = Vec::new();
-			let cost_field = self.get_baseline_mut().get(sector_id).unwrap();
-			let field_array = cost_field.get();
-			for (column, rows) in field_array.iter().enumerate() {
-				for (row, cost) in rows.iter().enumerate() {
-					if *cost == 255 {
-						impassable_indices.push((column, row));
-					}
+			field.allow_exit(neighbour_cell, ordinal.inverse());
+		}
+		if field.is_empty() {
+			self.one_way.remove(&neighbour_sector);
+		}
+		self.mark_edited(sector_id);
+		self.mark_edited(neighbour_sector);
+	}
+	/// Get a reference to the map of sectors and [HeightField]
+	pub fn get_height_fields(&self) -> &BTreeMap<SectorID, HeightField> {
+		&self.heights
+	}
+	/// Get the [HeightField] of `sector_id`, [None] if the sector is flat
+	pub fn get_height_field(&self, sector_id: SectorID) -> Option<&HeightField> {
+		self.heights.get(&sector_id)
+	}
+	/// Set the elevation of every [FieldCell] within `sector_id`, the
+	/// sector is marked as edited so that the plugin updates the caches
+	pub fn set_height_field(&mut self, sector_id: SectorID, height_field: HeightField) {
+		if self.baseline.contains_key(&sector_id) {
+			self.heights.insert(sector_id, height_field);
+			self.mark_edited(sector_id);
+		} else {
+			error!(
+				"Cannot set HeightField of non-existent sector {:?}",
+				sector_id
+			);
+		}
+	}
+	/// Set the elevation of a [FieldCell] within `sector_id`, the sector is
+	/// marked as edited so that the plugin updates the caches
+	pub fn set_field_cell_height(
+		&mut self,
+		sector_id: SectorID,
+		height: u8,
+		field_cell: FieldCell,
+	) {
+		if self.baseline.contains_key(&sector_id) {
+			self.heights
+				.entry(sector_id)
+				.or_default()
+				.set_field_cell_value(height, field_cell);
+			self.mark_edited(sector_id);
+		} else {
+			error!(
+				"Cannot mutate HeightField in non-existent sector {:?}",
+				sector_id
+			);
+		}
+	}
+	/// Get the extra cost of a step climbing or descending between heights
+	pub fn get_slope_cost(&self) -> SlopeCost {
+		self.slope_cost
+	}
+	/// Set the extra cost of a step climbing or descending between heights,
+	/// every sector with a [HeightField] is marked as edited so that the
+	/// plugin updates the caches
+	pub fn set_slope_cost(&mut self, slope_cost: SlopeCost) {
+		self.slope_cost = slope_cost;
+		let sectors: Vec<SectorID> = self.heights.keys().copied().collect();
+		for sector_id in sectors {
+			self.mark_edited(sector_id);
+		}
+	}
+	/// Get the elevation and [SlopeCost] to integrate `sector_id` with, if
+	/// the sector isn't flat and slopes have a cost
+	pub fn get_slope(&self, sector_id: SectorID) -> Option<(&HeightField, SlopeCost)> {
+		if self.slope_cost == SlopeCost::default() {
+			return None;
+		}
+		self.get_height_field(sector_id)
+			.map(|heights| (heights, self.slope_cost))
+	}
+	/// Get the longest window of pathable [FieldCell]s along a sector
+	/// boundary that is represented by a single portal
+	pub fn get_max_portal_window(&self) -> Option<usize> {
+		self.max_portal_window
+	}
+	/// Split boundary windows longer than `max_portal_window` cells into
+	/// several portals of near equal length, so that routes across wide
+	/// openings aren't all funnelled through the midpoint. [None] restores a
+	/// single portal per window. Every sector is marked as edited so that the
+	/// plugin rebuilds the portals and the [PortalGraph]
+	pub fn set_max_portal_window(&mut self, max_portal_window: Option<usize>) {
+		self.max_portal_window = max_portal_window.filter(|max| *max > 0);
+		let sectors: Vec<SectorID> = self.scaled.keys().copied().collect();
+		for sector_id in sectors {
+			self.mark_edited(sector_id);
+		}
+	}
+	/// Get the sectors which are void, i.e entirely impassable and omitted from
+	/// the baseline and scaled [CostField]s
+	pub fn get_void_sectors(&self) -> &BTreeSet<SectorID> {
+		&self.void
+	}
+	/// Whether `sector_id` is void
+	pub fn is_void(&self, sector_id: SectorID) -> bool {
+		self.void.contains(&sector_id)
+	}
+	/// Estimate the bytes of the fields held for each sector
+	pub fn memory_usage(&self) -> MemoryUsage {
+		let mut usage = MemoryUsage::new();
+		let cost_size = entry_size::<SectorID, CostField>();
+		for sector_id in self.baseline.keys() {
+			usage.add(*sector_id, MemoryCategory::BaselineCostFields, cost_size);
+		}
+		for sector_id in self.scaled.keys() {
+			usage.add(*sector_id, MemoryCategory::ScaledCostFields, cost_size);
+		}
+		#[cfg(feature = "metafield")]
+		for sector_id in self.meta.keys() {
+			let size = entry_size::<SectorID, MetaField>();
+			usage.add(*sector_id, MemoryCategory::MetaFields, size);
+		}
+		for sector_id in self.one_way.keys() {
+			let size = entry_size::<SectorID, OneWayField>();
+			usage.add(*sector_id, MemoryCategory::OneWayFields, size);
+		}
+		for sector_id in self.heights.keys() {
+			let size = entry_size::<SectorID, HeightField>();
+			usage.add(*sector_id, MemoryCategory::HeightFields, size);
+		}
+		usage
+	}
+	/// Whether an actor can stand on a [FieldCell], i.e its scaled cost is
+	/// not impassable. Cells of void or non-existent sectors are not pathable
+	pub fn is_pathable(&self, sector_id: SectorID, field_cell: FieldCell) -> bool {
+		self.scaled
+			.get(&sector_id)
+			.is_some_and(|field| !field.impassable_mask().is_impassable(field_cell))
+	}
+	/// Whether nothing impassable lies along a straight line between the
+	/// `source` and `target` cells, such as for checking if a shot or the
+	/// vision of an actor is blocked. The line is walked with
+	/// [FieldCell::get_cells_between_points] across the [FieldCell]s of the
+	/// whole map so it may cross any number of sector boundaries. The
+	/// baseline costs are used so the result doesn't depend on the size of
+	/// actors, cells of void or non-existent sectors block the line
+	pub fn has_line_of_sight(
+		&self,
+		source: (SectorID, FieldCell),
+		target: (SectorID, FieldCell),
+		map_dimensions: &MapDimensions,
+	) -> bool {
+		// cells indexed across the whole map rather than within a sector
+		let (source_column, source_row) = map_dimensions.global_cell_from(source.0, source.1);
+		let (target_column, target_row) = map_dimensions.global_cell_from(target.0, target.1);
+		let start = FieldCell::new(source_column as usize, source_row as usize);
+		let end = FieldCell::new(target_column as usize, target_row as usize);
+		start.get_cells_between_points(&end).iter().all(|global| {
+			map_dimensions
+				.sector_and_cell_from_global(global.get_column() as u32, global.get_row() as u32)
+				.is_some_and(|(sector_id, field_cell)| {
+					self.baseline
+						.get(&sector_id)
+						.is_some_and(|field| !field.impassable_mask().is_impassable(field_cell))
+				})
+		})
+	}
+	/// Whether nothing impassable lies along a straight line between two 2d
+	/// positions, see [SectorCostFields::has_line_of_sight]. A position
+	/// outside of the map has no line of sight
+	#[cfg(feature = "2d")]
+	pub fn has_line_of_sight_xy(
+		&self,
+		from: Vec2,
+		to: Vec2,
+		map_dimensions: &MapDimensions,
+	) -> bool {
+		let (Some(source), Some(target)) = (
+			map_dimensions.get_sector_and_field_cell_from_xy(from),
+			map_dimensions.get_sector_and_field_cell_from_xy(to),
+		) else {
+			return false;
+		};
+		self.has_line_of_sight(source, target, map_dimensions)
+	}
+	/// Whether nothing impassable lies along a straight line between two 3d
+	/// positions, see [SectorCostFields::has_line_of_sight]. A position
+	/// outside of the map has no line of sight
+	#[cfg(feature = "3d")]
+	pub fn has_line_of_sight_xyz(
+		&self,
+		from: Vec3,
+		to: Vec3,
+		map_dimensions: &MapDimensions,
+	) -> bool {
+		let (Some(source), Some(target)) = (
+			map_dimensions.get_sector_and_field_cell_from_xyz(from),
+			map_dimensions.get_sector_and_field_cell_from_xyz(to),
+		) else {
+			return false;
+		};
+		self.has_line_of_sight(source, target, map_dimensions)
+	}
+	/// Mark a sector as void, its [CostField]s (and user data) are removed and
+	/// every query, the portals and the [PortalGraph] treat it as impassable.
+	/// The sector is marked as edited so that the plugin updates the portals,
+	/// graph and caches around it
+	pub fn set_void(&mut self, sector_id: SectorID, map_dimensions: &MapDimensions) {
+		if !map_dimensions.get_sector_grid().contains(&sector_id) {
+			error!("Cannot void non-existent sector {:?}", sector_id);
+			return;
+		}
+		self.baseline.remove(&sector_id);
+		self.scaled.remove(&sector_id);
+		#[cfg(feature = "metafield")]
+		self.meta.remove(&sector_id);
+		self.one_way.remove(&sector_id);
+		self.heights.remove(&sector_id);
+		self.void.insert(sector_id);
+		self.rescale_neighbours(sector_id, map_dimensions);
+		self.mark_edited(sector_id);
+	}
+	/// Replace a void sector with a `cost_field`, the sector is marked as
+	/// edited so that the plugin updates the portals, graph and caches around
+	/// it
+	pub fn fill_void(
+		&mut self,
+		sector_id: SectorID,
+		cost_field: CostField,
+		map_dimensions: &MapDimensions,
+	) {
+		if !self.void.remove(&sector_id) {
+			error!("Cannot fill sector {:?} which isn't void", sector_id);
+			return;
+		}
+		self.baseline.insert(sector_id, cost_field.clone());
+		self.scaled.insert(sector_id, cost_field);
+		self.scale_costfield(&sector_id, map_dimensions);
+		self.rescale_neighbours(sector_id, map_dimensions);
+		self.mark_edited(sector_id);
+	}
+	/// Mark every sector whose [CostField] is entirely impassable as void.
+	/// This is useful after loading a mostly empty map (such as a space
+	/// station) from a file, it should be used before the portals and graph
+	/// are built
+	pub fn void_impassable_sectors(&mut self) {
+		let impassable: Vec<SectorID> = self
+			.baseline
+			.iter()
+			.filter(|(_, field)| field.impassable_mask().is_full())
+			.map(|(sector_id, _)| *sector_id)
+			.collect();
+		for sector_id in impassable {
+			self.baseline.remove(&sector_id);
+			self.scaled.remove(&sector_id);
+			#[cfg(feature = "metafield")]
+			self.meta.remove(&sector_id);
+			self.void.insert(sector_id);
+		}
+	}
+	/// Rescale the neighbours of a sector, including those diagonally
+	/// adjacent, the scaling of their boundaries depends on the costs along
+	/// the edges of the sector and whether it is void
+	fn rescale_neighbours(&mut self, sector_id: SectorID, map_dimensions: &MapDimensions) {
+		let grid = map_dimensions.get_sector_grid();
+		for ordinal in [
+			Ordinal::North,
+			Ordinal::NorthEast,
+			Ordinal::East,
+			Ordinal::SouthEast,
+			Ordinal::South,
+			Ordinal::SouthWest,
+			Ordinal::West,
+			Ordinal::NorthWest,
+		] {
+			if let Some(neighbour) = grid.neighbour(&sector_id, ordinal) {
+				if self.baseline.contains_key(&neighbour) {
+					self.scale_costfield(&neighbour, map_dimensions);
 				}
 			}
-			// For each impassable cell walk along the neighbouring ordinals
-			// and close any small gaps in the scaled field
-			let mut marks_as_impassable: BTreeMap<SectorID, Vec<FieldCell>> = BTreeMap::new();
-			for (column, row) in impassable_indices.iter() {
-				// North
-				let mut processed: BTreeMap<SectorID, Vec<FieldCell>> = BTreeMap::new();
-				'ord: for i in 1..=map_dimensions.get_actor_scale() as usize {
-					if let Some(n_row) = row.checked_sub(i) {
-						let field_cell = FieldCell::new(*column, n_row);
-						update_processed(&mut processed, field_cell, sector_id);
-						let value = self
-							.get_baseline()
-							.get(sector_id)
-							.unwrap()
-							.get_field_cell_value(field_cell);
-						// hit impassable before exceeding scale therefore
-						// gap too small for pathing
-						if value == 255 {
-							add_to_be_marked(&mut marks_as_impassable, &processed);
-							// marks_as_impassable.extend(&processed);
-							break 'ord;
-						}
-					} else {
-						// based on actor scale figure out how many possible neighbouring sectors might need to be explored
-						let neighbours = {
-							let mut n = Vec::new();
-							for factor in
-								0..=(map_dimensions.get_actor_scale() / FIELD_RESOLUTION as u32)
-							{
-								if let Some(n_sector) = map_dimensions.get_sector_id_from_ordinal(
-									Ordinal::North,
-									&SectorID::new(
-										sector_id.get_column(),
-										sector_id.get_row() - factor,
-									),
-								) {
-									n.push(n_sector);
-								}
-							}
-							n
-						};
-						if neighbours.is_empty() {
-							// hit the edge of the world so actor can't fit through the gap
-							add_to_be_marked(&mut marks_as_impassable, &processed);
-							break 'ord;
-						}
-						// work through neighbours to see if a big enough gap exists
-						for (count, n_sector) in neighbours.iter().enumerate() {
-							// adjust sizing to step through neightbour sector
-							// uses field resolution to account for previously
-							// covered sectors in this list
-							'inner: for x in 0..=map_dimensions.get_actor_scale() as usize
-								- i - (count * FIELD_RESOLUTION)
-							{
-								if let Some(n_row) = 9_usize.checked_sub(x) {
-									let field_cell = FieldCell::new(*column, n_row);
-									update_processed(&mut processed, field_cell, n_sector);
-									let value = self
-										.get_baseline()
-										.get(n_sector)
-										.unwrap_or_else(|| panic!("Could not get baseline costfield {:?}, this can indicates that sector_resolution and/or actor_size are not set correctly", n_sector))
-										.get_field_cell_value(field_cell);
-									// hit impassable before exceeding scale therefore
-									// gap too small for pathing
-									if value == 255 {
-										add_to_be_marked(&mut marks_as_impassable, &processed);
-										break 'ord;
-									}
-								} else {
-									// hit the edge of the world while there's scale left to check
-									// therefore actor cannot fit through the gap
-									if count + 1 == neighbours.len() {
-										add_to_be_marked(&mut marks_as_impassable, &processed);
-										break 'ord;
-									} else {
-										break 'inner;
-									}
-								}
-							}
-						}
-					}
-				}
-				processed.clear();
-				// East
-				'ord: for i in 1..=map_dimensions.get_actor_scale() as usize {
-					if column + i < FIELD_RESOLUTION {
-						let field_cell = FieldCell::new(column + i, *row);
-						update_processed(&mut processed, field_cell, sector_id);
-						let value = self
-							.get_baseline()
-							.get(sector_id)
-							.unwrap()
-							.get_field_cell_value(field_cell);
-						// hit impassable before exceeding scale therefore
-						// gap too small for pathing
-						if value == 255 {
-							add_to_be_marked(&mut marks_as_impassable, &processed);
-							// marks_as_impassable.extend(&processed);
-							break 'ord;
-						}
-					} else {
-						// based on actor scale figure out how many possible neighbouring sectors might need to be explored
-						let neighbours = {
-							let mut n = Vec::new();
-							for factor in
-								0..=(map_dimensions.get_actor_scale() / FIELD_RESOLUTION as u32)
-							{
-								if let Some(n_sector) = map_dimensions.get_sector_id_from_ordinal(
-									Ordinal::East,
-									&SectorID::new(
-										sector_id.get_column() + factor,
-										sector_id.get_row(),
-									),
-								) {
-									n.push(n_sector);
-								}
-							}
-							n
-						};
-						if neighbours.is_empty() {
-							// hit the edge of the world so actor can't fit through the gap
-							add_to_be_marked(&mut marks_as_impassable, &processed);
-							break 'ord;
-						}
-						// work through neighbours to see if a big enough gap exists
-						for (count, n_sector) in neighbours.iter().enumerate() {
-							// adjust sizing to step through neightbour sector
-							// uses field resolution to account for previously
-							// covered sectors in this list
-							'inner: for x in 0..=map_dimensions.get_actor_scale() as usize
-								- i - (count * FIELD_RESOLUTION)
-							{
-								if x < FIELD_RESOLUTION {
-									let field_cell = FieldCell::new(x, *row);
-									update_processed(&mut processed, field_cell, n_sector);
-									let value = self
-										.get_baseline()
-										.get(n_sector)
-										.unwrap()
-										.get_field_cell_value(field_cell);
-									// hit impassable before exceeding scale therefore
-									// gap too small for pathing
-									if value == 255 {
-										add_to_be_marked(&mut marks_as_impassable, &processed);
-										break 'ord;
-									}
-								} else {
-									// hit the edge of the world while there's scale left to check
-									// therefore actor cannot fit through the gap
-									if count + 1 == neighbours.len() {
-										add_to_be_marked(&mut marks_as_impassable, &processed);
-										break 'ord;
-									} else {
-										break 'inner;
-									}
-								}
-							}
-						}
+		}
+	}
+	/// Get the sectors which have been edited with a [CostFieldEditGuard] but
+	/// not yet had their portals, graph and caches updated
+	pub fn get_edited_sectors(&self) -> &Vec<SectorID> {
+		&self.edited_sectors
+	}
+	/// Record that `sector_id` has been edited, each sector is listed once
+	/// however many times it's edited before the plugin updates the caches
+	fn mark_edited(&mut self, sector_id: SectorID) {
+		if !self.edited_sectors.contains(&sector_id) {
+			self.edited_sectors.push(sector_id);
+		}
+	}
+	/// Remove and return the list of edited sectors
+	pub fn take_edited_sectors(&mut self) -> Vec<SectorID> {
+		std::mem::take(&mut self.edited_sectors)
+	}
+	/// Check that the [SectorCostFields] match the `map_dimensions`, i.e every
+	/// sector of the map has a baseline and scaled [CostField] and there are no
+	/// fields for sectors outside of the map. This is useful after loading
+	/// data from files (for instance a `.ron` file generated for a different
+	/// map size) to reject malformed data before it's used
+	pub fn validate(&self, map_dimensions: &MapDimensions) -> Result<(), Vec<ValidationError>> {
+		let mut errors = Vec::new();
+		let length = map_dimensions.get_length();
+		let depth = map_dimensions.get_depth();
+		let sector_resolution = map_dimensions.get_sector_resolution();
+		if sector_resolution == 0
+			|| length % sector_resolution != 0
+			|| depth % sector_resolution != 0
+		{
+			errors.push(ValidationError::IndivisibleDimensions {
+				length,
+				depth,
+				sector_resolution,
+			});
+			return Err(errors);
+		}
+		let column_count = length / sector_resolution;
+		let row_count = depth / sector_resolution;
+		for m in 0..column_count {
+			for n in 0..row_count {
+				let sector_id = SectorID::new(m, n);
+				if self.void.contains(&sector_id) {
+					if self.baseline.contains_key(&sector_id) {
+						errors.push(ValidationError::VoidSectorHasField(sector_id));
 					}
+				} else if !self.baseline.contains_key(&sector_id) {
+					errors.push(ValidationError::MissingSector(sector_id));
+				} else if !self.scaled.contains_key(&sector_id) {
+					errors.push(ValidationError::MissingScaledSector(sector_id));
 				}
-				processed.clear();
-				// South
-				'ord: for i in 1..=map_dimensions.get_actor_scale() as usize {
-					if row + i < FIELD_RESOLUTION {
-						let field_cell = FieldCell::new(*column, *row + i);
-						update_processed(&mut processed, field_cell, sector_id);
-						let value = self
-							.get_baseline()
-							.get(sector_id)
-							.unwrap()
-							.get_field_cell_value(field_cell);
-						// hit impassable before exceeding scale therefore
-						// gap too small for pathing
-						if value == 255 {
-							add_to_be_marked(&mut marks_as_impassable, &processed);
-							// marks_as_impassable.extend(&processed);
-							break 'ord;
-						}
-					} else {
-						// based on actor scale figure out how many possible neighbouring sectors might need to be explored
-						let neighbours = {
-							let mut n = Vec::new();
-							for factor in
-								0..=(map_dimensions.get_actor_scale() / FIELD_RESOLUTION as u32)
-							{
-								if let Some(n_sector) = map_dimensions.get_sector_id_from_ordinal(
-									Ordinal::South,
-									&SectorID::new(
-										sector_id.get_column(),
-										sector_id.get_row() + factor,
-									),
-								) {
-									n.push(n_sector);
-								}
-							}
-							n
-						};
-						if neighbours.is_empty() {
-							// hit the edge of the world so actor can't fit through the gap
-							add_to_be_marked(&mut marks_as_impassable, &processed);
-							break 'ord;
-						}
-						// work through neighbours to see if a big enough gap exists
-						for (count, n_sector) in neighbours.iter().enumerate() {
-							// adjust sizing to step through neightbour sector
-							// uses field resolution to account for previously
-							// covered sectors in this list
-							'inner: for x in 0..=map_dimensions.get_actor_scale() as usize
-								- i - (count * FIELD_RESOLUTION)
-							{
-								if x < FIELD_RESOLUTION {
-									let field_cell = FieldCell::new(*column, x);
-									update_processed(&mut processed, field_cell, n_sector);
-									let value = self
-										.get_baseline()
-										.get(n_sector)
-										.unwrap()
-										.get_field_cell_value(field_cell);
-									// hit impassable before exceeding scale therefore
-									// gap too small for pathing
-									if value == 255 {
-										add_to_be_marked(&mut marks_as_impassable, &processed);
-										break 'ord;
-									}
-								} else {
-									// hit the edge of the world while there's scale left to check
-									// therefore actor cannot fit through the gap
-									if count + 1 == neighbours.len() {
-										add_to_be_marked(&mut marks_as_impassable, &processed);
-										break 'ord;
-									} else {
-										break 'inner;
-									}
-								}
-							}
-						}
+			}
+		}
+		for sector_id in self.baseline.keys() {
+			if sector_id.get_column() >= column_count || sector_id.get_row() >= row_count {
+				errors.push(ValidationError::UnexpectedSector(*sector_id));
+			}
+		}
+		if errors.is_empty() {
+			Ok(())
+		} else {
+			Err(errors)
+		}
+	}
+	/// Iterate over all sectors and scale any impassable [FieldCell] based on `actor_scale`.
+	///
+	/// This can be expensive so should typically be used as part of data initialisation, i.e when loading [SectorCostFields] from a file or within a loading type of operation to a world
+	pub fn scale_all_costfields(&mut self, map_dimensions: &MapDimensions) {
+		self.scale_all_costfields_with_progress(map_dimensions, &mut |_| {});
+	}
+	/// Scale every sector as with [SectorCostFields::scale_all_costfields],
+	/// a row of sectors at a time, reporting the number of sectors scaled so
+	/// far to `progress` after each row. With the `parallel` feature the
+	/// sectors of each row are scaled in parallel
+	fn scale_all_costfields_with_progress(
+		&mut self,
+		map_dimensions: &MapDimensions,
+		progress: &mut dyn FnMut(usize),
+	) {
+		let sector_ids: Vec<SectorID> = self.baseline.keys().cloned().collect();
+		let scaled = build_sectors(
+			&sector_ids,
+			map_dimensions.get_sector_grid().get_columns() as usize,
+			|sector_id| self.calc_scaled_costfield(&sector_id, map_dimensions),
+			progress,
+		);
+		for (sector_id, field) in scaled {
+			if let Some(field) = field {
+				self.scaled.insert(sector_id, field);
+			}
+		}
+	}
+	/// Whether the baseline [FieldCell] is impassable where the [FieldCell]s
+	/// of void sectors are impassable
+	fn is_baseline_impassable(&self, sector_id: &SectorID, field_cell: FieldCell) -> bool {
+		if self.void.contains(sector_id) {
+			return true;
+		}
+		self.get_baseline()
+			.get(sector_id)
+			.unwrap_or_else(|| panic!("Could not get baseline costfield {:?}, this can indicates that sector_resolution and/or actor_size are not set correctly", sector_id))
+			.impassable_mask()
+			.is_impassable(field_cell)
+	}
+	/// Rescale `sector_id` after the cost of the `field_cells` changed. With
+	/// an actor scale greater than `1` a change close to the sector boundary
+	/// can open or close a gap in a neighbour so they're rescaled too
+	fn rescale_around(
+		&mut self,
+		sector_id: SectorID,
+		field_cells: &[FieldCell],
+		map_dimensions: &MapDimensions,
+	) {
+		self.scale_costfield(&sector_id, map_dimensions);
+		let margin = map_dimensions.get_actor_scale().saturating_sub(1) as usize;
+		let end = FIELD_RESOLUTION - 1;
+		let near_boundary = field_cells.iter().any(|cell| {
+			cell.get_column() < margin
+				|| cell.get_row() < margin
+				|| cell.get_column() + margin > end
+				|| cell.get_row() + margin > end
+		});
+		if near_boundary {
+			self.rescale_neighbours(sector_id, map_dimensions);
+		}
+	}
+	/// Rescale a sector, see [SectorCostFields::calc_scaled_costfield]
+	fn scale_costfield(&mut self, sector_id: &SectorID, map_dimensions: &MapDimensions) {
+		if let Some(scaled) = self.calc_scaled_costfield(sector_id, map_dimensions) {
+			self.scaled.insert(*sector_id, scaled);
+		}
+	}
+	/// Build the scaled [CostField] of a sector so that any gap between
+	/// impassable cells (or the edge of the world) that an actor cannot fit
+	/// through is closed off, ensuring the actor won't try to path through it.
+	///
+	/// An actor is treated as a square `actor_scale` cells across. A window
+	/// of the baseline costs is stitched together from the sector and the
+	/// edges of its neighbours and a distance transform finds, for each cell
+	/// of the window, the size of the largest open square extending from it.
+	/// A pathable cell of the sector stays pathable only if a square of at
+	/// least `actor_scale` covers it, i.e the actor can stand on it.
+	///
+	/// Squares covering a cell which can't slide orthogonally into one another
+	/// are pinched between impassable cells which only meet diagonally, such
+	/// as two pillars set corner to corner, and the actor cannot pass through
+	/// the gap between them. Those cells are closed off too.
+	///
+	/// Returns [None] if the sector has no baseline [CostField]
+	fn calc_scaled_costfield(
+		&self,
+		sector_id: &SectorID,
+		map_dimensions: &MapDimensions,
+	) -> Option<CostField> {
+		let baseline = self.baseline.get(sector_id)?;
+		let mut scaled = baseline.clone();
+		let scale = map_dimensions.get_actor_scale() as usize;
+		if scale > 1 {
+			let margin = scale - 1;
+			let clearance = self.get_clearance_window(sector_id, margin, map_dimensions);
+			for column in 0..FIELD_RESOLUTION {
+				for row in 0..FIELD_RESOLUTION {
+					let field_cell = FieldCell::new(column, row);
+					if scaled.impassable_mask().is_impassable(field_cell) {
+						continue;
 					}
-				}
-				processed.clear();
-				// West
-				'ord: for i in 1..=map_dimensions.get_actor_scale() as usize {
-					if let Some(n_column) = column.checked_sub(i) {
-						let field_cell = FieldCell::new(n_column, *row);
-						update_processed(&mut processed, field_cell, sector_id);
-						let value = self
-							.get_baseline()
-							.get(sector_id)
-							.unwrap()
-							.get_field_cell_value(field_cell);
-						// hit impassable before exceeding scale therefore
-						// gap too small for pathing
-						if value == 255 {
-							add_to_be_marked(&mut marks_as_impassable, &processed);
-							// marks_as_impassable.extend(&processed);
-							break 'ord;
-						}
-					} else {
-						// based on actor scale figure out how many possible neighbouring sectors might need to be explored
-						let neighbours = {
-							let mut n = Vec::new();
-							for factor in
-								0..=(map_dimensions.get_actor_scale() / FIELD_RESOLUTION as u32)
-							{
-								if let Some(n_sector) = map_dimensions.get_sector_id_from_ordinal(
-									Ordinal::West,
-									&SectorID::new(
-										sector_id.get_column() - factor,
-										sector_id.get_row(),
-									),
-								) {
-									n.push(n_sector);
-								}
-							}
-							n
-						};
-						if neighbours.is_empty() {
-							// hit the edge of the world so actor can't fit through the gap
-							add_to_be_marked(&mut marks_as_impassable, &processed);
-							break 'ord;
-						}
-						// work through neighbours to see if a big enough gap exists
-						for (count, n_sector) in neighbours.iter().enumerate() {
-							// adjust sizing to step through neightbour sector
-							// uses field resolution to account for previously
-							// covered sectors in this list
-							'inner: for x in 0..=map_dimensions.get_actor_scale() as usize
-								- i - (count * FIELD_RESOLUTION)
-							{
-								if let Some(n_column) = 9_usize.checked_sub(x) {
-									let field_cell = FieldCell::new(n_column, *row);
-									update_processed(&mut processed, field_cell, n_sector);
-									let value = self
-										.get_baseline()
-										.get(n_sector)
-										.unwrap()
-										.get_field_cell_value(field_cell);
-									// hit impassable before exceeding scale therefore
-									// gap too small for pathing
-									if value == 255 {
-										add_to_be_marked(&mut marks_as_impassable, &processed);
-										break 'ord;
-									}
-								} else {
-									// hit the edge of the world while there's scale left to check
-									// therefore actor cannot fit through the gap
-									if count + 1 == neighbours.len() {
-										add_to_be_marked(&mut marks_as_impassable, &processed);
-										break 'ord;
-									} else {
-										break 'inner;
-									}
-								}
-							}
-						}
+					// any square of the actor's size containing the cell
+					// extends from within `margin` cells above and left of it
+					let placements: Vec<Vec<bool>> = (0..scale)
+						.map(|x| {
+							(0..scale)
+								.map(|y| clearance[column + margin - x][row + margin - y] >= scale)
+								.collect()
+						})
+						.collect();
+					if count_placement_groups(&placements) != 1 {
+						scaled.set_field_cell_value(255, field_cell);
 					}
 				}
-				processed.clear();
 			}
-			// mark any cells
-			for (sector, cells) in marks_as_impassable.iter() {
-				for cell in cells.iter() {
-					self.get_scaled_mut()
-						.get_mut(sector)
-						.unwrap()
-						.set_field_cell_value(255, *cell)
+		}
+		Some(scaled)
+	}
+	/// Stitch together the baseline costs of a sector and `margin` cells of
+	/// its neighbours, where void sectors and the edge of the world are
+	/// impassable, and calculate the distance transform of the open space.
+	/// Each `[column][row]` of the window holds the size of the largest open
+	/// square with its top left corner at that cell, sector cell `(0, 0)` is
+	/// found at `[margin][margin]`
+	fn get_clearance_window(
+		&self,
+		sector_id: &SectorID,
+		margin: usize,
+		map_dimensions: &MapDimensions,
+	) -> Vec<Vec<usize>> {
+		let size = FIELD_RESOLUTION + 2 * margin;
+		let grid = map_dimensions.get_sector_grid();
+		let world_columns = grid.get_columns() as usize * FIELD_RESOLUTION;
+		let world_rows = grid.get_rows() as usize * FIELD_RESOLUTION;
+		let origin_column = sector_id.get_column() as usize * FIELD_RESOLUTION;
+		let origin_row = sector_id.get_row() as usize * FIELD_RESOLUTION;
+		let is_open = |x: usize, y: usize| {
+			let (Some(column), Some(row)) = (
+				(origin_column + x).checked_sub(margin),
+				(origin_row + y).checked_sub(margin),
+			) else {
+				return false;
+			};
+			if column >= world_columns || row >= world_rows {
+				return false;
+			}
+			let sector = SectorID::new(
+				(column / FIELD_RESOLUTION) as u32,
+				(row / FIELD_RESOLUTION) as u32,
+			);
+			let field_cell = FieldCell::new(column % FIELD_RESOLUTION, row % FIELD_RESOLUTION);
+			!self.is_baseline_impassable(&sector, field_cell)
+		};
+		// a single pass from the bottom right corner, each open cell extends
+		// the smallest of the squares to its east, south and south-east
+		let mut clearance = vec![vec![0; size + 1]; size + 1];
+		for x in (0..size).rev() {
+			for y in (0..size).rev() {
+				if is_open(x, y) {
+					clearance[x][y] = 1 + clearance[x + 1][y]
+						.min(clearance[x][y + 1])
+						.min(clearance[x + 1][y + 1]);
 				}
 			}
 		}
+		clearance
 	}
 	/// From a `ron` file generate the [SectorCostFields]
 	#[cfg(feature = "ron")]
 	pub fn from_ron(path: String, map_dimensions: &MapDimensions) -> Self {
-		let file = std::fs::File::open(path).expect("Failed opening CostField file");
-		let mut fields: SectorCostFields = match ron::de::from_reader(file) {
-			Ok(fields) => fields,
-			Err(e) => panic!("Failed deserializing SectorCostFields: {}", e),
-		};
+		SectorCostFields::try_from_ron(path, map_dimensions).unwrap_or_else(|e| panic!("{}", e))
+	}
+	/// As with [SectorCostFields::from_ron] but a file which can't be opened
+	/// or deserialized is reported rather than panicking
+	#[cfg(feature = "ron")]
+	pub fn try_from_ron(path: String, map_dimensions: &MapDimensions) -> Result<Self, String> {
+		let data = std::fs::read_to_string(&path)
+			.map_err(|e| format!("Failed opening CostField file {}: {}", path, e))?;
+		SectorCostFields::try_from_ron_str(&data, map_dimensions)
+	}
+	/// From the contents of a `ron` file generate the [SectorCostFields],
+	/// for platforms without a filesystem such as the web the data can be
+	/// embedded with `include_str!` or fetched as an asset
+	#[cfg(feature = "ron")]
+	pub fn from_ron_str(data: &str, map_dimensions: &MapDimensions) -> Self {
+		SectorCostFields::try_from_ron_str(data, map_dimensions).unwrap_or_else(|e| panic!("{}", e))
+	}
+	/// As with [SectorCostFields::from_ron_str] but data which can't be
+	/// deserialized is reported rather than panicking
+	#[cfg(feature = "ron")]
+	pub fn try_from_ron_str(data: &str, map_dimensions: &MapDimensions) -> Result<Self, String> {
+		let mut fields: SectorCostFields = ron::de::from_str(data)
+			.map_err(|e| format!("Failed deserializing SectorCostFields: {}", e))?;
 		fields.scale_all_costfields(map_dimensions);
-		fields
+		Ok(fields)
 	}
 	/// From a directory containing a series of CSV files generate the [SectorCostFields]
 	#[cfg(feature = "csv")]
 	pub fn from_csv_dir(map_dimensions: &MapDimensions, directory: String) -> Self {
+		SectorCostFields::try_from_csv_dir(map_dimensions, directory)
+			.unwrap_or_else(|e| panic!("{}", e))
+	}
+	/// As with [SectorCostFields::from_csv_dir] but a directory or file which
+	/// can't be read or parsed is reported rather than panicking
+	#[cfg(feature = "csv")]
+	pub fn try_from_csv_dir(
+		map_dimensions: &MapDimensions,
+		directory: String,
+	) -> Result<Self, String> {
 		let required_files_count = (map_dimensions.get_length() * map_dimensions.get_depth())
 			as usize / (map_dimensions.get_sector_resolution().pow(2))
 			as usize;
-		let files = std::fs::read_dir(directory)
-			.expect("Unable to read csv directory")
+		let files = std::fs::read_dir(&directory)
+			.map_err(|e| format!("Unable to read csv directory {}: {}", directory, e))?
 			.map(|res| {
 				res.map(|e| {
 					(
-						e.path().into_os_string().into_string().unwrap(),
-						e.file_name().into_string().unwrap(),
+						e.path().to_string_lossy().into_owned(),
+						e.file_name().to_string_lossy().into_owned(),
 					)
 				})
 			})
 			.collect::<Result<Vec<_>, std::io::Error>>()
-			.expect("Failed to filter for CSV files");
+			.map_err(|e| format!("Failed to filter for CSV files: {}", e))?;
 		let mut csvs = Vec::new();
 		for (file_path, file_name) in files {
 			if file_path.ends_with(".csv") {
-				let sector_id_str = file_name.trim_end_matches(".csv").split_once('_').unwrap();
-				let sector_id = SectorID::new(
-					sector_id_str
-						.0
-						.parse::<u32>()
-						.expect("Failed to parse sector ID from csv file name"),
-					sector_id_str
-						.1
-						.parse::<u32>()
-						.expect("Failed to parse sector ID from csv file name"),
-				);
+				let sector_id = file_name
+					.trim_end_matches(".csv")
+					.split_once('_')
+					.and_then(|(column, row)| {
+						Some(SectorID::new(column.parse().ok()?, row.parse().ok()?))
+					})
+					.ok_or(format!(
+						"Failed to parse sector ID from csv file name {}",
+						file_name
+					))?;
 				csvs.push((file_path, sector_id));
 			}
 		}
 		if csvs.len() != required_files_count {
-			panic!(
+			return Err(format!(
 				"Found {} CSVs, expected {}",
 				csvs.len(),
 				required_files_count
-			);
+			));
 		}
-		let mut sector_cost_fields = SectorCostFields::default();
+		let mut readers = Vec::with_capacity(csvs.len());
 		for (csv_file, sector_id) in csvs.iter() {
-			let data = std::fs::File::open(csv_file).expect("Failed opening csv");
+			let data = std::fs::File::open(csv_file)
+				.map_err(|e| format!("Failed opening csv {}: {}", csv_file, e))?;
+			readers.push((*sector_id, data));
+		}
+		SectorCostFields::try_from_csv_readers(map_dimensions, readers)
+	}
+	/// Generate the [SectorCostFields] from the CSV [CostField] of each
+	/// sector read from any source, such as byte slices embedded with
+	/// `include_bytes!` on platforms without a filesystem
+	#[cfg(feature = "csv")]
+	pub fn from_csv_readers<R: std::io::Read>(
+		map_dimensions: &MapDimensions,
+		readers: impl IntoIterator<Item = (SectorID, R)>,
+	) -> Self {
+		SectorCostFields::try_from_csv_readers(map_dimensions, readers)
+			.unwrap_or_else(|e| panic!("{}", e))
+	}
+	/// As with [SectorCostFields::from_csv_readers] but data which can't be
+	/// read or parsed, or which doesn't cover every sector, is reported
+	/// rather than panicking
+	#[cfg(feature = "csv")]
+	pub fn try_from_csv_readers<R: std::io::Read>(
+		map_dimensions: &MapDimensions,
+		readers: impl IntoIterator<Item = (SectorID, R)>,
+	) -> Result<Self, String> {
+		let required_files_count = (map_dimensions.get_length() * map_dimensions.get_depth())
+			as usize / (map_dimensions.get_sector_resolution().pow(2))
+			as usize;
+		let mut sector_cost_fields = SectorCostFields::default();
+		for (sector_id, data) in readers {
 			let mut rdr = csv::ReaderBuilder::new()
 				.has_headers(false)
 				.from_reader(data);
 			let mut cost_field = CostField::default();
 			for (row, record) in rdr.records().enumerate() {
-				for (column, value) in record.unwrap().iter().enumerate() {
-					let value_u8: u8 = value.parse().expect("CSV expects u8 values");
+				let record =
+					record.map_err(|e| format!("Failed reading csv of {:?}: {}", sector_id, e))?;
+				for (column, value) in record.iter().enumerate() {
+					let value_u8: u8 = value.parse().map_err(|_| {
+						format!("CSV expects u8 values, found {} in {:?}", value, sector_id)
+					})?;
 					cost_field.set_field_cell_value(value_u8, FieldCell::new(column, row));
 				}
 			}
-			sector_cost_fields
-				.get_baseline_mut()
-				.insert(*sector_id, cost_field);
+			sector_cost_fields.baseline.insert(sector_id, cost_field);
+		}
+		if sector_cost_fields.baseline.len() != required_files_count {
+			return Err(format!(
+				"Found {} CSVs, expected {}",
+				sector_cost_fields.baseline.len(),
+				required_files_count
+			));
 		}
 		sector_cost_fields.scale_all_costfields(map_dimensions);
-		sector_cost_fields
+		Ok(sector_cost_fields)
 	}
 	/// Create a [SectorCostFields] from a greyscale image where each pixel
 	/// represents the cost of a [FieldCell]
 	#[cfg(feature = "heightmap")]
 	pub fn from_heightmap(map_dimensions: &MapDimensions, path: String) -> Self {
+		SectorCostFields::from_heightmap_with_progress(map_dimensions, path, |_| {})
+	}
+	/// As with [SectorCostFields::from_heightmap] but `progress` is called
+	/// with the fraction (`0.0` to `1.0`) of the work completed after each row
+	/// of sectors is converted and scaled, so that a loading screen can show
+	/// a progress bar. With the `parallel` feature the sectors of each row are
+	/// converted in parallel
+	#[cfg(feature = "heightmap")]
+	pub fn from_heightmap_with_progress(
+		map_dimensions: &MapDimensions,
+		path: String,
+		progress: impl FnMut(f32),
+	) -> Self {
+		SectorCostFields::try_from_heightmap_with_progress(map_dimensions, path, progress)
+			.unwrap_or_else(|e| panic!("{}", e))
+	}
+	/// As with [SectorCostFields::from_heightmap_with_progress] but an image
+	/// which can't be opened or doesn't match the [MapDimensions] is reported
+	/// rather than panicking
+	#[cfg(feature = "heightmap")]
+	pub fn try_from_heightmap_with_progress(
+		map_dimensions: &MapDimensions,
+		path: String,
+		progress: impl FnMut(f32),
+	) -> Result<Self, String> {
 		use photon_rs::native::open_image;
-		let img = open_image(&path).expect("Failed to open heightmap");
+		let img = open_image(&path).map_err(|e| format!("Failed to open heightmap: {:?}", e))?;
+		SectorCostFields::try_from_image(map_dimensions, img, progress)
+	}
+	/// As with [SectorCostFields::from_heightmap] but the image is decoded
+	/// from the bytes of an image file, such as those embedded with
+	/// `include_bytes!` on platforms without a filesystem
+	#[cfg(feature = "heightmap")]
+	pub fn from_image_bytes(map_dimensions: &MapDimensions, bytes: &[u8]) -> Self {
+		SectorCostFields::try_from_image_bytes_with_progress(map_dimensions, bytes, |_| {})
+			.unwrap_or_else(|e| panic!("{}", e))
+	}
+	/// As with [SectorCostFields::from_heightmap_with_progress] but the image
+	/// is decoded from the bytes of an image file, an image which can't be
+	/// decoded or doesn't match the [MapDimensions] is reported rather than
+	/// panicking
+	#[cfg(feature = "heightmap")]
+	pub fn try_from_image_bytes_with_progress(
+		map_dimensions: &MapDimensions,
+		bytes: &[u8],
+		progress: impl FnMut(f32),
+	) -> Result<Self, String> {
+		use photon_rs::native::open_image_from_bytes;
+		let img = open_image_from_bytes(bytes)
+			.map_err(|e| format!("Failed to decode heightmap: {:?}", e))?;
+		SectorCostFields::try_from_image(map_dimensions, img, progress)
+	}
+	/// Convert the pixels of a greyscale heightmap into [SectorCostFields]
+	#[cfg(feature = "heightmap")]
+	fn try_from_image(
+		map_dimensions: &MapDimensions,
+		img: photon_rs::PhotonImage,
+		mut progress: impl FnMut(f32),
+	) -> Result<Self, String> {
 		let img_width = img.get_width();
 		let img_height = img.get_height();
 		// ensure the size of the heightmap actually represents the number of FieldCells required by the MapDimensions
@@ -574,69 +1026,64 @@ impl SectorCostFields {
 			map_dimensions.get_length() / map_dimensions.get_sector_resolution();
 		let required_px_width = hori_sector_count * FIELD_RESOLUTION as u32;
 		if img_width != required_px_width {
-			panic!(
+			return Err(format!(
 				"Heightmap has incorrect width, expected width of {} pixels, found {}",
 				required_px_width, img_width
-			);
+			));
 		}
 		let vert_sector_count = map_dimensions.get_depth() / map_dimensions.get_sector_resolution();
 		let required_px_height = vert_sector_count * FIELD_RESOLUTION as u32;
 		if img_height != required_px_height {
-			panic!(
+			return Err(format!(
 				"Heightmap has incorrect height, expected hieght of {} pixels, found {}",
 				required_px_height, img_height
-			);
+			));
 		}
-		// init the fields so we already have the required sectors inserted
-		let mut sector_cost_fields = SectorCostFields::new(map_dimensions);
-		// iter over the pixels in chunks creating CostFields
-		let raw_pixels = img.get_raw_pixels();
 		// raw pixels are arranged from the top left of the image and come in sets of either 3 or 4 (if alpha channel is inlcuded).
 		// Each sequential set corresponds to Red, Green, Blue, (Alpha).
-		// We want to convert these into a vector of tuples which can represent each field cell
-		let len_if_alpha = img_height * img_height * 4;
-		let chunk_size = {
-			if len_if_alpha as usize == raw_pixels.len() {
-				4
-			} else {
-				3
-			}
+		let raw_pixels = img.get_raw_pixels();
+		let len_if_alpha = img_width as usize * img_height as usize * 4;
+		let channels = if len_if_alpha == raw_pixels.len() {
+			4
+		} else {
+			3
 		};
-		let mut pixels_rgb: Vec<(u8, u8, u8)> = Vec::new();
-		for rgb in raw_pixels.chunks(chunk_size) {
-			let mut as_tuple = vec![(rgb[0], rgb[1], rgb[2])];
-			pixels_rgb.append(&mut as_tuple);
-		}
-		// By chunking the list of pixel RGBAs based on the width of the image
-		// we can iterate on the rows
-		for (line_number, rgba_slice) in pixels_rgb.chunks(img_width as usize).enumerate() {
-			let sector_row = line_number / FIELD_RESOLUTION;
-			// chunk each row by resolution to give slices of pixels for each sector column
-			for (sector_column, rgba_slice_slice) in rgba_slice.chunks(FIELD_RESOLUTION).enumerate()
-			{
-				let sector_id = SectorID::new(sector_column as u32, sector_row as u32);
-				let field = sector_cost_fields
-					.get_baseline_mut()
-					.get_mut(&sector_id)
-					.unwrap();
-				// iter over the pixels in the row of the particular sector
-				for (field_column, px) in rgba_slice_slice.iter().enumerate() {
-					// calc row in the field
-					let field_row = line_number - (FIELD_RESOLUTION * sector_row);
-					let field_cell = FieldCell::new(field_column, field_row);
-					// black (0, 0, 0, 255)
-					// white (255, 255, 255, 255)
-					// careful of u8 overflow
-					let colour_avg = (px.0 as f32 + px.1 as f32 + px.2 as f32) / 3.0;
-					let value = (255 - colour_avg as u8).clamp(1, 255);
-					field.set_field_cell_value(value, field_cell);
+		// each sector reads its own block of pixels so they can be converted
+		// independently
+		let sector_ids = sector_ids_of(map_dimensions);
+		let total = sector_ids.len() * 2;
+		let baseline = build_sectors(
+			&sector_ids,
+			hori_sector_count as usize,
+			|sector_id| {
+				let mut field = CostField::default();
+				for field_column in 0..FIELD_RESOLUTION {
+					for field_row in 0..FIELD_RESOLUTION {
+						let field_cell = FieldCell::new(field_column, field_row);
+						let (x, y) = map_dimensions.global_cell_from(sector_id, field_cell);
+						let index = (y as usize * img_width as usize + x as usize) * channels;
+						let px = &raw_pixels[index..index + 3];
+						// black (0, 0, 0, 255)
+						// white (255, 255, 255, 255)
+						// careful of u8 overflow
+						let colour_avg = (px[0] as f32 + px[1] as f32 + px[2] as f32) / 3.0;
+						let value = (255 - colour_avg as u8).clamp(1, 255);
+						field.set_field_cell_value(value, field_cell);
+					}
 				}
-			}
-		}
+				field
+			},
+			&mut |done| progress(done as f32 / total as f32),
+		);
+		let mut sector_cost_fields = SectorCostFields::default();
+		sector_cost_fields.baseline.extend(baseline);
 		// now that costs are popualated calcualte the scaled fields that will
 		// be used in the algorithm
-		sector_cost_fields.scale_all_costfields(map_dimensions);
-		sector_cost_fields
+		let converted = sector_ids.len();
+		sector_cost_fields.scale_all_costfields_with_progress(map_dimensions, &mut |done| {
+			progress((converted + done) as f32 / total as f32)
+		});
+		Ok(sector_cost_fields)
 	}
 	/// From a list of meshes extract the outer edges of each mesh and project an (MxN) FieldCell representation of edges over the dimensions. The projections undergo two tests to see if a FieldCell sits inside a mesh (thereby being marked as pathable):
 	/// - The top-left vertex of each field cell is tested for mesh edge intersections, a horizontal line is taken from the vertex point to max-x and if the line intersects mesh edges an odd number of times, or touches an edge an even number of times, then it is marked as potentially being within the mesh
@@ -648,9 +1095,27 @@ impl SectorCostFields {
 		internal_cost: u8,
 		external_cost: u8,
 	) -> Self {
-		// init the fields so we already have the required sectors inserted
-		let mut sector_cost_fields = SectorCostFields::new_with_cost(map_dimensions, external_cost);
-
+		SectorCostFields::from_bevy_2d_meshes_with_progress(
+			map_dimensions,
+			meshes,
+			internal_cost,
+			external_cost,
+			|_| {},
+		)
+	}
+	/// As with [SectorCostFields::from_bevy_2d_meshes] but `progress` is
+	/// called with the fraction (`0.0` to `1.0`) of the work completed after
+	/// each row of sectors is tested against the meshes and scaled, so that a
+	/// loading screen can show a progress bar. With the `parallel` feature the
+	/// sectors of each row are tested in parallel
+	#[cfg(feature = "2d")]
+	pub fn from_bevy_2d_meshes_with_progress(
+		map_dimensions: &MapDimensions,
+		meshes: &Vec<(&Mesh, Vec2)>,
+		internal_cost: u8,
+		external_cost: u8,
+		mut progress: impl FnMut(f32),
+	) -> Self {
 		// Treat each FieldCell as its own polygon
 		// to find if one polygon (A) is within another (B):
 		// 1) Take a vertex of A (a corner of a FieldCell) and project a line
@@ -667,132 +1132,360 @@ impl SectorCostFields {
 		// If no intersections are found then A is inside B.
 
 		// store all mesh outer edges for field cell checks later
-		let mut outer_edges = vec![];
-		for (mesh, translation) in meshes {
-			if let Some(mesh_vertices) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
-				let vertex_points = mesh_vertices.as_float3().unwrap();
-				// build each edge of each triangle in the mesh represented by index points
-				let edge_indices = retrieve_mesh_edges(mesh, vertex_points);
-				if !edge_indices.is_empty() {
-					// collect edges that only belong to a single triangle (this means ignore internal edges, we only want the edges outlining the mesh), if any MeshEdge appears more than once we remove all occurances of it
-					let copy = edge_indices.clone();
-					for edge in edge_indices {
-						let mut occurances = 0;
-						for c in &copy {
-							if edge == *c {
-								occurances += 1;
-							}
-						}
-						if occurances == 1 {
-							// found outer edge
-							// store edge line
-							let start = vertex_points[edge.0];
-							let end = vertex_points[edge.1];
-							//NB: vertex points are relative to mesh so include
-							// translation of the mesh to find global position
-							let line = EdgeLine::build(
-								Vec2::new(start[0] + translation.x, start[1] + translation.y),
-								Vec2::new(end[0] + translation.x, end[1] + translation.y),
-							);
-							outer_edges.push(line);
-						}
+		let outer_edges: Vec<EdgeLine> = get_mesh_outlines(meshes).into_iter().flatten().collect();
+		// each sector tests its own FieldCells against the mesh edges so they
+		// can be tested independently
+		let sector_ids = sector_ids_of(map_dimensions);
+		let total = sector_ids.len() * 2;
+		let baseline = build_sectors(
+			&sector_ids,
+			map_dimensions.get_sector_grid().get_columns() as usize,
+			|sector_id| {
+				// create a list of candiate row-col which are likely to be
+				// within the mesh therefore pathable
+				let mut candidates: Vec<(usize, usize)> =
+					calc_field_cell_mesh_candidates(map_dimensions, sector_id, &outer_edges);
+				// to test whether an entire field cell is within the mesh we need to take each edge of the candidate field cells and test that none of them intersect with any mesh edges
+				let failed_candidates: Vec<(usize, usize)> =
+					identify_field_cells_that_intersect_mesh(
+						map_dimensions,
+						&candidates,
+						&outer_edges,
+					);
+				// from candidates and failed candidates identify the cells which are pathable
+				candidates.retain(|c| !failed_candidates.contains(c));
+				let mut field = CostField::new_with_cost(external_cost);
+				for (row, col) in candidates {
+					if let Some((_, field_cell)) =
+						map_dimensions.sector_and_cell_from_global(col as u32, row as u32)
+					{
+						field.set_field_cell_value(internal_cost, field_cell);
 					}
 				}
-			}
-		}
-		// with the external edges of the mesh known we can
-		// test to see if the field cell vertex intercepts any edge
-		// an odd number of times to mark it as a candiate that
-		// could be inside the mesh
-
-		// convert FieldCell ID notation of origin top-left
-		// into an f32 form where the origin is the center of
-		// the world
-		// iterate over all FieldCells, left to right, top to bottom
-
-		// create a list of candiate row-col which are likely to be within the
-		// mesh therefore pathable
-		let mut candidates: Vec<(usize, usize)> =
-			calc_field_cell_mesh_candidates(map_dimensions, &outer_edges);
-		// to test whether an entire field cell is within the mesh we need to take each edge of the candidate field cells and test that none of them intersect with any mesh edges
-		let failed_candidates: Vec<(usize, usize)> =
-			identify_field_cells_that_intersect_mesh(map_dimensions, &candidates, &outer_edges);
-		// from candidates and failed candidates identify the cells which are pathable
-		for cell in failed_candidates.iter() {
-			candidates.retain(|&c| c != *cell);
-		}
-		// candidates are now the pathable ones, determine how they are represented
-		// in Sector and FieldCell notation to update the CostFields
+				field
+			},
+			&mut |done| progress(done as f32 / total as f32),
+		);
+		let mut sector_cost_fields = SectorCostFields::default();
+		sector_cost_fields.baseline.extend(baseline);
+		let tested = sector_ids.len();
+		sector_cost_fields.scale_all_costfields_with_progress(map_dimensions, &mut |done| {
+			progress((tested + done) as f32 / total as f32)
+		});
+		sector_cost_fields
+	}
+	/// From a list of meshes describing pathable areas sample a grid of
+	/// points within every [FieldCell] to find how much of the cell is
+	/// covered by the meshes. Cells wholly covered are given the
+	/// `internal_cost` and cells with a coverage below the threshold of the
+	/// [MeshSupersampling] are given the `external_cost`. Partially covered
+	/// cells in between are given a cost interpolated between the two (but
+	/// never impassable) so that thin or diagonal obstacle edges don't waste
+	/// the space around them
+	#[cfg(feature = "2d")]
+	pub fn from_bevy_2d_meshes_supersampled(
+		map_dimensions: &MapDimensions,
+		meshes: &Vec<(&Mesh, Vec2)>,
+		internal_cost: u8,
+		external_cost: u8,
+		supersampling: MeshSupersampling,
+	) -> Self {
+		let mut sector_cost_fields = SectorCostFields::new_with_cost(map_dimensions, external_cost);
+		let mesh_edges = get_mesh_outlines(meshes);
+		let samples = supersampling.get_samples_per_axis().max(1);
 		let field_cell_unit_size = map_dimensions.get_field_cell_unit_size();
+		let sample_size = field_cell_unit_size / samples as f32;
 		let offset_x = map_dimensions.get_length() as f32 / 2.0;
 		let offset_y = map_dimensions.get_depth() as f32 / 2.0;
-		for (row, col) in candidates {
-			let x = col as f32 * field_cell_unit_size - offset_x + (field_cell_unit_size / 2.0);
-			let y = row as f32 * -field_cell_unit_size + offset_y - (field_cell_unit_size / 2.0);
-			let position = Vec2::new(x, y);
-			if let Some((sector, field_cell)) =
-				map_dimensions.get_sector_and_field_cell_from_xy(position)
-			{
-				sector_cost_fields.set_field_cell_value(
-					sector,
-					internal_cost,
-					field_cell,
-					map_dimensions,
+		for row in 0..map_dimensions.get_total_field_cell_rows() {
+			for col in 0..map_dimensions.get_total_field_cell_columns() {
+				// top left corner of the field cell
+				let x1 = col as f32 * field_cell_unit_size - offset_x;
+				let y1 = row as f32 * -field_cell_unit_size + offset_y;
+				let mut covered = 0;
+				for i in 0..samples {
+					for j in 0..samples {
+						let point = Vec2::new(
+							x1 + (i as f32 + 0.5) * sample_size,
+							y1 - (j as f32 + 0.5) * sample_size,
+						);
+						if mesh_edges.iter().any(|edges| {
+							is_point_within_polygon(point, edges, WindingRule::EvenOdd)
+						}) {
+							covered += 1;
+						}
+					}
+				}
+				let coverage = covered as f32 / (samples * samples) as f32;
+				if coverage < supersampling.get_coverage_threshold() || covered == 0 {
+					continue;
+				}
+				let cost = internal_cost as f32
+					+ (1.0 - coverage) * (external_cost as f32 - internal_cost as f32);
+				let cost = (cost.round() as u8).min(254);
+				let position = Vec2::new(
+					x1 + field_cell_unit_size / 2.0,
+					y1 - field_cell_unit_size / 2.0,
 				);
+				if let Some((sector, field_cell)) =
+					map_dimensions.get_sector_and_field_cell_from_xy(position)
+				{
+					sector_cost_fields.set_field_cell_value(
+						sector,
+						cost,
+						field_cell,
+						map_dimensions,
+					);
+				}
 			}
 		}
 		sector_cost_fields.scale_all_costfields(map_dimensions);
 		sector_cost_fields
 	}
 }
-/// From a triple floating point representation of a mesh retreive a list of the edges as index pairs
-fn retrieve_mesh_edges(mesh: &&Mesh, vertex_points: &[[f32; 3]]) -> Vec<MeshTriEdge<usize>> {
-	use bevy::render::mesh::PrimitiveTopology;
-	let indices = mesh.indices().unwrap();
-	let indices_slice: Vec<usize> = indices.iter().collect();
-	let mut edge_indices = vec![];
-	match mesh.primitive_topology() {
-		PrimitiveTopology::TriangleList => {
-			for i in indices_slice.chunks(3) {
-				edge_indices.push(MeshTriEdge(i[0], i[1]));
-				edge_indices.push(MeshTriEdge(i[1], i[2]));
-				edge_indices.push(MeshTriEdge(i[2], i[0]));
+
+/// Controls the fidelity of creating [CostField]s from meshes with
+/// [SectorCostFields::from_bevy_2d_meshes_supersampled]
+#[cfg(feature = "2d")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshSupersampling {
+	/// Number of sample points along each axis of a [FieldCell], `4` means
+	/// a grid of `4x4` points are tested
+	samples_per_axis: u32,
+	/// The fraction of a [FieldCell] (`0.0` to `1.0`) that must be covered
+	/// by a mesh for it to be pathable
+	coverage_threshold: f32,
+}
+
+#[cfg(feature = "2d")]
+impl Default for MeshSupersampling {
+	fn default() -> Self {
+		MeshSupersampling {
+			samples_per_axis: 4,
+			coverage_threshold: 0.5,
+		}
+	}
+}
+
+#[cfg(feature = "2d")]
+impl MeshSupersampling {
+	/// Create a new instance of [MeshSupersampling]
+	pub fn new(samples_per_axis: u32, coverage_threshold: f32) -> Self {
+		MeshSupersampling {
+			samples_per_axis,
+			coverage_threshold,
+		}
+	}
+	/// Get the number of sample points along each axis of a [FieldCell]
+	pub fn get_samples_per_axis(&self) -> u32 {
+		self.samples_per_axis
+	}
+	/// Get the fraction of a [FieldCell] that must be covered by a mesh for
+	/// it to be pathable
+	pub fn get_coverage_threshold(&self) -> f32 {
+		self.coverage_threshold
+	}
+}
+
+/// A problem found by [SectorCostFields::validate]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+	/// The map can't be split into a whole number of sectors
+	IndivisibleDimensions {
+		/// Length of the map
+		length: u32,
+		/// Depth of the map
+		depth: u32,
+		/// Size of each sector
+		sector_resolution: u32,
+	},
+	/// A sector of the map has no [CostField]
+	MissingSector(SectorID),
+	/// A sector has a baseline [CostField] but no scaled one
+	MissingScaledSector(SectorID),
+	/// A [CostField] exists for a sector outside of the map
+	UnexpectedSector(SectorID),
+	/// A sector marked as void also has a [CostField]
+	VoidSectorHasField(SectorID),
+}
+
+impl std::fmt::Display for ValidationError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ValidationError::IndivisibleDimensions {
+				length,
+				depth,
+				sector_resolution,
+			} => write!(
+				f,
+				"Map size ({}, {}) is not divisible by the sector resolution {}",
+				length, depth, sector_resolution
+			),
+			ValidationError::MissingSector(sector_id) => {
+				write!(f, "Sector {:?} has no CostField", sector_id)
+			}
+			ValidationError::MissingScaledSector(sector_id) => {
+				write!(f, "Sector {:?} has not been scaled", sector_id)
+			}
+			ValidationError::UnexpectedSector(sector_id) => {
+				write!(f, "Sector {:?} lies outside of the map", sector_id)
+			}
+			ValidationError::VoidSectorHasField(sector_id) => {
+				write!(f, "Void sector {:?} has a CostField", sector_id)
+			}
+		}
+	}
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Guards a mutable borrow of a sectors [CostField]. Every changed
+/// [FieldCell] is recorded and when the guard is dropped the sector is
+/// rescaled and marked as edited so the plugin can update its portals, the
+/// [PortalGraph] and clean any cached routes which pass through it
+pub struct CostFieldEditGuard<'a> {
+	/// The fields being edited
+	sector_cost_fields: &'a mut SectorCostFields,
+	/// The sector being edited
+	sector_id: SectorID,
+	/// Dimensions of the map used to rescale the sector
+	map_dimensions: &'a MapDimensions,
+	/// Cells which have had their cost changed
+	changed_cells: Vec<FieldCell>,
+}
+
+impl CostFieldEditGuard<'_> {
+	/// Get the baseline cost of a [FieldCell] in the sector
+	pub fn get_field_cell_value(&self, field_cell: FieldCell) -> u8 {
+		self.sector_cost_fields
+			.baseline
+			.get(&self.sector_id)
+			.unwrap()
+			.get_field_cell_value(field_cell)
+	}
+	/// Set the cost of a [FieldCell] in the sector
+	pub fn set_field_cell_value(&mut self, field_cell: FieldCell, value: impl Into<Cost>) {
+		let value = value.into().get();
+		let cost_field = self
+			.sector_cost_fields
+			.baseline
+			.get_mut(&self.sector_id)
+			.unwrap();
+		if cost_field.get_field_cell_value(field_cell) != value {
+			cost_field.set_field_cell_value(value, field_cell);
+			if !self.changed_cells.contains(&field_cell) {
+				self.changed_cells.push(field_cell);
 			}
 		}
-		PrimitiveTopology::TriangleStrip => {
-			if let Some(triangle_count) = vertex_points.len().checked_sub(2) {
-				for n in 0..triangle_count {
-					if n % 2 == 0 {
-						edge_indices.push(MeshTriEdge(indices_slice[n], indices_slice[n + 1]));
-						edge_indices.push(MeshTriEdge(indices_slice[n + 1], indices_slice[n + 2]));
-						edge_indices.push(MeshTriEdge(indices_slice[n + 2], indices_slice[n]));
-					} else {
-						edge_indices.push(MeshTriEdge(indices_slice[n + 1], indices_slice[n]));
-						edge_indices.push(MeshTriEdge(indices_slice[n], indices_slice[n + 2]));
-						edge_indices.push(MeshTriEdge(indices_slice[n + 2], indices_slice[n + 1]));
+	}
+	/// Get the [FieldCell]s which have been changed so far
+	pub fn get_changed_cells(&self) -> &Vec<FieldCell> {
+		&self.changed_cells
+	}
+}
+
+/// Count the groups of actor `placements` covering a cell which can slide
+/// orthogonally into one another, `0` means the actor cannot stand on the
+/// cell and more than `1` means the cell is pinched diagonally
+fn count_placement_groups(placements: &[Vec<bool>]) -> usize {
+	let size = placements.len();
+	let mut visited = vec![vec![false; size]; size];
+	let mut groups = 0;
+	for x in 0..size {
+		for y in 0..size {
+			if !placements[x][y] || visited[x][y] {
+				continue;
+			}
+			groups += 1;
+			visited[x][y] = true;
+			let mut stack = vec![(x, y)];
+			while let Some((x, y)) = stack.pop() {
+				let neighbours = [
+					(x.checked_sub(1), Some(y)),
+					(Some(x + 1), Some(y)),
+					(Some(x), y.checked_sub(1)),
+					(Some(x), Some(y + 1)),
+				];
+				for (nx, ny) in neighbours {
+					let (Some(nx), Some(ny)) = (nx, ny) else {
+						continue;
+					};
+					if nx < size && ny < size && placements[nx][ny] && !visited[nx][ny] {
+						visited[nx][ny] = true;
+						stack.push((nx, ny));
 					}
 				}
-			} else {
-				warn!("A TriangleStrip mesh has insufficient vertices");
 			}
 		}
-		_ => {
-			warn!("Mesh topology must be of TriangleList or TriangleStrip for use with Flowfields");
+	}
+	groups
+}
+/// Get the ID of every sector of the map, ordered a row of sectors at a time
+#[cfg(any(feature = "2d", feature = "heightmap"))]
+fn sector_ids_of(map_dimensions: &MapDimensions) -> Vec<SectorID> {
+	let grid = map_dimensions.get_sector_grid();
+	let mut sector_ids = Vec::new();
+	for row in 0..grid.get_rows() {
+		for column in 0..grid.get_columns() {
+			sector_ids.push(SectorID::new(column, row));
 		}
 	}
-	edge_indices
+	sector_ids
+}
+/// Build a value for each of the `sector_ids` in batches of `batch_size`
+/// (typically a row of sectors), reporting the number of sectors built so far
+/// to `progress` after each batch. With the `parallel` feature the sectors of
+/// each batch are built in parallel
+fn build_sectors<T: Send>(
+	sector_ids: &[SectorID],
+	batch_size: usize,
+	build: impl Fn(SectorID) -> T + Sync,
+	progress: &mut dyn FnMut(usize),
+) -> Vec<(SectorID, T)> {
+	let mut built = Vec::with_capacity(sector_ids.len());
+	for batch in sector_ids.chunks(batch_size.max(1)) {
+		#[cfg(use_rayon)]
+		let values: Vec<(SectorID, T)> = batch
+			.par_iter()
+			.map(|sector_id| (*sector_id, build(*sector_id)))
+			.collect();
+		#[cfg(not(use_rayon))]
+		let values: Vec<(SectorID, T)> = batch
+			.iter()
+			.map(|sector_id| (*sector_id, build(*sector_id)))
+			.collect();
+		built.extend(values);
+		progress(built.len());
+	}
+	built
 }
-/// Using a list of outer mesh edges iterate over every [FieldCell] and draw a horiontal line from the top-left vertex position of a [FieldCell] box/square and count the number of times the line intersects an outer mesh edge. If the line intersects an edge an odd number of times then it means that the [FieldCell] is probably within the mesh. An even number of intersections means it passes into and out of the mesh and therefore must be a [FieldCell] that sits outside of the mesh edges
+impl Drop for CostFieldEditGuard<'_> {
+	fn drop(&mut self) {
+		if self.changed_cells.is_empty() {
+			return;
+		}
+		self.sector_cost_fields.rescale_around(
+			self.sector_id,
+			&self.changed_cells,
+			self.map_dimensions,
+		);
+		self.sector_cost_fields.mark_edited(self.sector_id);
+	}
+}
+/// Using a list of outer mesh edges iterate over every [FieldCell] of a sector and draw a horiontal line from the top-left vertex position of a [FieldCell] box/square and count the number of times the line intersects an outer mesh edge. If the line intersects an edge an odd number of times then it means that the [FieldCell] is probably within the mesh. An even number of intersections means it passes into and out of the mesh and therefore must be a [FieldCell] that sits outside of the mesh edges
 fn calc_field_cell_mesh_candidates(
 	map_dimensions: &MapDimensions,
+	sector_id: SectorID,
 	outer_edges: &Vec<EdgeLine>,
 ) -> Vec<(usize, usize)> {
-	let columns = map_dimensions.get_total_field_cell_columns();
-	let rows = map_dimensions.get_total_field_cell_rows();
+	// global row-col of the top left FieldCell of the sector
+	let (first_col, first_row) = map_dimensions.global_cell_from(sector_id, FieldCell::new(0, 0));
+	let columns = first_col as usize..first_col as usize + FIELD_RESOLUTION;
+	let rows = first_row as usize..first_row as usize + FIELD_RESOLUTION;
 	let field_cell_unit_size = map_dimensions.get_field_cell_unit_size();
 	let mut candidates: Vec<(usize, usize)> = vec![];
-	for row in 0..rows {
-		for col in 0..columns {
+	for row in rows {
+		for col in columns.clone() {
 			// find coord of top left field cell corner
 			let x1 = col as f32 * field_cell_unit_size - (map_dimensions.get_length() as f32 / 2.0);
 			let y1 = row as f32 * -field_cell_unit_size + (map_dimensions.get_depth() as f32 / 2.0);
@@ -800,7 +1493,7 @@ fn calc_field_cell_mesh_candidates(
 			//TODO what happens when two meshes are next to each other but a field cell overlaps their boundary -> treated as impassable currently
 
 			// create a horizontal edge with constant y
-			let hori = EdgeLine::build(
+			let hori = EdgeLine::new(
 				Vec2::new(x1, y1),
 				Vec2::new(map_dimensions.get_length() as f32 / 2.0, y1),
 			);
@@ -861,13 +1554,13 @@ fn identify_field_cells_that_intersect_mesh(
 			*row as f32 * -field_cell_unit_size + offset_y - field_cell_unit_size,
 		);
 		// edge: left up-down
-		let edge_lud = EdgeLine::build(tl, bl);
+		let edge_lud = EdgeLine::new(tl, bl);
 		// edge: right up-down
-		let edge_rud = EdgeLine::build(tr, br);
+		let edge_rud = EdgeLine::new(tr, br);
 		// edge: bottom left-right
-		let edge_blr = EdgeLine::build(bl, br);
+		let edge_blr = EdgeLine::new(bl, br);
 		// edge: top left-right
-		let edge_tlr = EdgeLine::build(tl, tr);
+		let edge_tlr = EdgeLine::new(tl, tr);
 		// look for intersections
 		let field_edges = [edge_lud, edge_rud, edge_blr, edge_tlr];
 		for edge in outer_edges {
@@ -891,109 +1584,298 @@ fn identify_field_cells_that_intersect_mesh(
 	failed_candidates
 }
 
-/// Represents two points that form the edge between mech vertices
-#[derive(Clone, Debug)]
-struct MeshTriEdge<T: PartialEq>(T, T);
-// custom impl so we can test whether two edges are teh same but with start and end coords swapped
-impl<T: PartialEq> PartialEq for MeshTriEdge<T> {
-	fn eq(&self, other: &Self) -> bool {
-		(self.0 == other.0 && self.1 == other.1) || (self.0 == other.1 && self.1 == other.0)
-	}
-}
-
-/// Defines whether an intersection has occured
-#[derive(PartialEq, Debug)]
-enum Intersection {
-	/// Indicates that an edge meets and passes through another edge
-	Intersect,
-	/// Indicates that edges only touch one another, this is a special case of intersection
-	Touch,
-	/// Edge does not intersect
-	None,
-}
-
-/// Represents the start and end coordinates of a line in space
-#[derive(Debug, PartialEq)]
-struct EdgeLine {
-	/// Where the line starts
-	start: Vec2,
-	/// Where the line ends
-	end: Vec2,
-}
-
-impl EdgeLine {
-	/// Create an [`EdgeLine`] from two positions
-	fn build(start: Vec2, end: Vec2) -> Self {
-		EdgeLine { start, end }
-	}
-	/// Finds whether two edges intersect/touch
-	fn does_intersect(&self, other: &EdgeLine) -> Intersection {
-		//https://stackoverflow.com/questions/563198/how-do-you-detect-where-two-line-segments-intersect/565282#565282 (Ronald Goldman, published in Graphics Gems, page 304)
-		let self_segment = self.end - self.start;
-		let other_segment = other.end - other.start;
-
-		let cross_segment = self_segment.perp_dot(other_segment);
-		if cross_segment == 0.0 {
-			// find whether paralell or collinear
-			if (other.start - self.start).perp_dot(self_segment) == 0.0 {
-				// collinear, check if they overlap
-				let t_0 =
-					(other.start - self.start).dot(self_segment) / (self_segment.dot(self_segment));
-				let t_1 = t_0 + other_segment.dot(self_segment) / (self_segment.dot(self_segment));
-
-				// if other_segment.dot(self_segment) < 0.0 {
-				// 	if (t_0 <= 0.0 || t_0 >= 1.0) && (t_1 <= 0.0 || t_1 >= 1.0) {
-				// 		// overlap
-				// 	} else {
-				// 		// disjoint
-				// 		Intersection::None
-				// 	}
-				// } else {
-
-				if (0.0..=1.0).contains(&t_0) && (0.0..=1.0).contains(&t_1) {
-					// overlap
-					Intersection::Touch
-				} else {
-					// disjoint
-					Intersection::None
-				}
-			// }
-			} else {
-				// parallel, non-intersecting
-				Intersection::None
-			}
-		} else {
-			// may intersect, check if intersection point is on both segments
-			let u = (other.start - self.start).perp_dot(self_segment) / cross_segment;
-			let t = (other.start - self.start).perp_dot(other_segment) / cross_segment;
-			if (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&t) {
-				// special case where an edge only touches the start/end of another edge
-				let point = self.start + t * self_segment; //, other.start + u * other_segment);
-											   //TODO? floating precision can throw off touch check
-				if (point - other.start).length_squared() < f32::EPSILON
-					|| (point - other.end).length_squared() < f32::EPSILON
-				{
-					Intersection::Touch
-				} else {
-					Intersection::Intersect
-				}
-			} else {
-				Intersection::None
-			}
-		}
-	}
-}
-
 // #[rustfmt::skip]
 #[cfg(test)]
 mod tests {
-	use bevy::render::{
-		mesh::{Indices, PrimitiveTopology},
-		render_asset::RenderAssetUsages,
-	};
-
 	use super::*;
 	#[test]
+	fn memory_usage_per_sector() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let hill = SectorID::new(1, 1);
+		sector_cost_fields.set_height_field(hill, HeightField::default());
+		let usage = sector_cost_fields.memory_usage();
+		let cost_size = std::mem::size_of::<SectorID>() + std::mem::size_of::<CostField>();
+		let height_size = std::mem::size_of::<SectorID>() + std::mem::size_of::<HeightField>();
+		assert_eq!(2 * cost_size, usage.get_sector_total(SectorID::new(0, 0)));
+		assert_eq!(2 * cost_size + height_size, usage.get_sector_total(hill));
+		assert_eq!(18 * cost_size + height_size, usage.get_total());
+		assert_eq!(
+			height_size,
+			usage.get_category_total(MemoryCategory::HeightFields)
+		);
+	}
+	#[test]
+	fn diff_and_apply() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let client = SectorCostFields::new(&map_dimensions);
+		let mut server = client.clone();
+		server.set_field_cell_value(
+			SectorID::new(1, 1),
+			255,
+			FieldCell::new(4, 4),
+			&map_dimensions,
+		);
+		server.set_field_cell_value(
+			SectorID::new(2, 0),
+			7,
+			FieldCell::new(0, 9),
+			&map_dimensions,
+		);
+		let diff = client.diff(&server);
+		let actual = vec![
+			(SectorID::new(1, 1), FieldCell::new(4, 4), 255),
+			(SectorID::new(2, 0), FieldCell::new(0, 9), 7),
+		];
+		assert_eq!(actual, diff);
+		let mut client = client;
+		client.apply_diff(&diff, &map_dimensions);
+		assert!(client.diff(&server).is_empty());
+		assert_eq!(
+			server
+				.get_scaled()
+				.get(&SectorID::new(1, 1))
+				.unwrap()
+				.get_field_cell_value(FieldCell::new(3, 4)),
+			client
+				.get_scaled()
+				.get(&SectorID::new(1, 1))
+				.unwrap()
+				.get_field_cell_value(FieldCell::new(3, 4))
+		);
+		assert_eq!(
+			vec![SectorID::new(1, 1), SectorID::new(2, 0)],
+			client.take_edited_sectors()
+		);
+	}
+	#[test]
+	fn line_of_sight_across_sectors() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let source = (SectorID::new(0, 0), FieldCell::new(2, 5));
+		let target = (SectorID::new(2, 0), FieldCell::new(7, 5));
+		assert!(sector_cost_fields.has_line_of_sight(source, target, &map_dimensions));
+		// a wall in the middle sector blocks the line
+		sector_cost_fields.set_field_cell_value(
+			SectorID::new(1, 0),
+			255,
+			FieldCell::new(4, 5),
+			&map_dimensions,
+		);
+		assert!(!sector_cost_fields.has_line_of_sight(source, target, &map_dimensions));
+		assert!(!sector_cost_fields.has_line_of_sight(target, source, &map_dimensions));
+		// a line just above the wall is clear
+		let above = (SectorID::new(2, 0), FieldCell::new(7, 4));
+		let source = (SectorID::new(0, 0), FieldCell::new(2, 4));
+		assert!(sector_cost_fields.has_line_of_sight(source, above, &map_dimensions));
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn supersampled_mesh_partial_cells() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		// pathable area spans from the left of the map to half way across column 15
+		let mesh = Mesh::from(Rectangle::new(15.5, 30.0));
+		let meshes = vec![(&mesh, Vec2::new(-7.25, 0.0))];
+		let result = SectorCostFields::from_bevy_2d_meshes_supersampled(
+			&map_dimensions,
+			&meshes,
+			1,
+			255,
+			MeshSupersampling::new(4, 0.5),
+		);
+		let sector_id = SectorID::new(1, 1);
+		let baseline = result.get_baseline().get(&sector_id).unwrap();
+		assert_eq!(1, baseline.get_field_cell_value(FieldCell::new(4, 3)));
+		assert_eq!(128, baseline.get_field_cell_value(FieldCell::new(5, 3)));
+		assert_eq!(255, baseline.get_field_cell_value(FieldCell::new(6, 3)));
+		// a stricter threshold treats the half covered cells as impassable
+		let result = SectorCostFields::from_bevy_2d_meshes_supersampled(
+			&map_dimensions,
+			&meshes,
+			1,
+			255,
+			MeshSupersampling::new(4, 0.75),
+		);
+		let baseline = result.get_baseline().get(&sector_id).unwrap();
+		assert_eq!(255, baseline.get_field_cell_value(FieldCell::new(5, 3)));
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn mesh_build_reports_progress() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		// pathable area covers the left half of the map
+		let mesh = Mesh::from(Rectangle::new(15.0, 30.0));
+		let meshes = vec![(&mesh, Vec2::new(-7.5, 0.0))];
+		let mut reports = Vec::new();
+		let result = SectorCostFields::from_bevy_2d_meshes_with_progress(
+			&map_dimensions,
+			&meshes,
+			1,
+			255,
+			|fraction| reports.push(fraction),
+		);
+		// a report for each row of sectors being tested and then scaled
+		assert_eq!(6, reports.len());
+		assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+		assert_eq!(1.0, *reports.last().unwrap());
+		let sector_id = SectorID::new(1, 2);
+		let scaled = result.get_scaled().get(&sector_id).unwrap();
+		assert_eq!(1, scaled.get_field_cell_value(FieldCell::new(3, 5)));
+		assert_eq!(255, scaled.get_field_cell_value(FieldCell::new(6, 5)));
+		assert_eq!(9, result.get_scaled().len());
+	}
+	#[test]
+	fn edit_guard_rescales_and_marks_sector() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 2.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(1, 1);
+		{
+			let mut guard = sector_cost_fields.edit(sector_id, &map_dimensions).unwrap();
+			guard.set_field_cell_value(FieldCell::new(4, 4), 255);
+			guard.set_field_cell_value(FieldCell::new(4, 6), 255);
+			assert_eq!(2, guard.get_changed_cells().len());
+		}
+		// gap between the impassable cells is too small for the actor
+		let scaled = sector_cost_fields
+			.get_scaled()
+			.get(&sector_id)
+			.unwrap()
+			.get_field_cell_value(FieldCell::new(4, 5));
+		assert_eq!(255, scaled);
+		assert_eq!(vec![sector_id], sector_cost_fields.take_edited_sectors());
+		assert!(sector_cost_fields.get_edited_sectors().is_empty());
+	}
+	#[test]
+	fn repeated_edits_mark_sector_once() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(1, 1);
+		sector_cost_fields.set_field_cell_height(sector_id, 3, FieldCell::new(2, 2));
+		sector_cost_fields.set_field_cell_height(sector_id, 5, FieldCell::new(2, 3));
+		sector_cost_fields.set_height_field(sector_id, HeightField::default());
+		{
+			let mut guard = sector_cost_fields.edit(sector_id, &map_dimensions).unwrap();
+			guard.set_field_cell_value(FieldCell::new(4, 4), 255);
+		}
+		assert_eq!(vec![sector_id], sector_cost_fields.take_edited_sectors());
+	}
+	#[test]
+	#[cfg(feature = "metafield")]
+	fn meta_alongside_cost() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(1, 2);
+		let field_cell = FieldCell::new(3, 4);
+		assert_eq!(
+			Some(0),
+			sector_cost_fields.get_field_cell_meta(sector_id, field_cell)
+		);
+		sector_cost_fields.set_field_cell_meta(sector_id, 7, field_cell);
+		assert_eq!(
+			Some(7),
+			sector_cost_fields.get_field_cell_meta(sector_id, field_cell)
+		);
+		assert_eq!(1, sector_cost_fields.get_meta_fields().len());
+		// costs are untouched
+		let cost = sector_cost_fields
+			.get_baseline()
+			.get(&sector_id)
+			.unwrap()
+			.get_field_cell_value(field_cell);
+		assert_eq!(1, cost);
+		assert!(sector_cost_fields.get_edited_sectors().is_empty());
+		// unknown sectors have no data
+		assert_eq!(
+			None,
+			sector_cost_fields.get_field_cell_meta(SectorID::new(5, 5), field_cell)
+		);
+	}
+	#[test]
+	fn validate_against_dimensions() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		assert_eq!(Ok(()), sector_cost_fields.validate(&map_dimensions));
+		// fields built for a smaller map are missing sectors
+		let smaller = SectorCostFields::new(&MapDimensions::new(20, 30, 10, 0.5));
+		let result = smaller.validate(&map_dimensions);
+		let actual = vec![
+			ValidationError::MissingSector(SectorID::new(2, 0)),
+			ValidationError::MissingSector(SectorID::new(2, 1)),
+			ValidationError::MissingSector(SectorID::new(2, 2)),
+		];
+		assert_eq!(Err(actual), result);
+		// and a larger map has sectors which don't belong
+		let larger = SectorCostFields::new(&MapDimensions::new(30, 40, 10, 0.5));
+		let result = larger.validate(&map_dimensions).unwrap_err();
+		assert_eq!(3, result.len());
+		assert!(result.contains(&ValidationError::UnexpectedSector(SectorID::new(0, 3))));
+	}
+	#[test]
+	fn void_sectors() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let void_id = SectorID::new(1, 1);
+		sector_cost_fields.set_void(void_id, &map_dimensions);
+		assert!(sector_cost_fields.is_void(void_id));
+		assert!(!sector_cost_fields.get_baseline().contains_key(&void_id));
+		assert!(!sector_cost_fields.get_scaled().contains_key(&void_id));
+		assert_eq!(&vec![void_id], sector_cost_fields.get_edited_sectors());
+		assert_eq!(Ok(()), sector_cost_fields.validate(&map_dimensions));
+		sector_cost_fields.fill_void(void_id, CostField::default(), &map_dimensions);
+		assert!(!sector_cost_fields.is_void(void_id));
+		assert!(sector_cost_fields.get_scaled().contains_key(&void_id));
+		// fully impassable sectors can be omitted after loading
+		let mut guard = sector_cost_fields.edit(void_id, &map_dimensions).unwrap();
+		for column in 0..FIELD_RESOLUTION {
+			for row in 0..FIELD_RESOLUTION {
+				guard.set_field_cell_value(FieldCell::new(column, row), Cost::IMPASSABLE);
+			}
+		}
+		drop(guard);
+		sector_cost_fields.void_impassable_sectors();
+		assert_eq!(1, sector_cost_fields.get_void_sectors().len());
+		assert_eq!(8, sector_cost_fields.get_baseline().len());
+	}
+	#[test]
+	fn pathable_cells() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(0, 0);
+		let wall = FieldCell::new(3, 3);
+		sector_cost_fields.set_field_cell_value(sector_id, 255, wall, &map_dimensions);
+		assert!(!sector_cost_fields.is_pathable(sector_id, wall));
+		assert!(sector_cost_fields.is_pathable(sector_id, FieldCell::new(3, 4)));
+		// void sectors and those outside of the map can't be stood on
+		let void_id = SectorID::new(1, 1);
+		sector_cost_fields.set_void(void_id, &map_dimensions);
+		assert!(!sector_cost_fields.is_pathable(void_id, FieldCell::new(3, 4)));
+		assert!(!sector_cost_fields.is_pathable(SectorID::new(5, 5), FieldCell::new(3, 4)));
+	}
+	#[test]
+	fn void_sector_scales_neighbours() {
+		// an actor 3 cells wide can't squeeze between the map edge and a void
+		let map_dimensions = MapDimensions::new(30, 30, 10, 1.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(0, 1);
+		let mut guard = sector_cost_fields.edit(sector_id, &map_dimensions).unwrap();
+		guard.set_field_cell_value(FieldCell::new(8, 5), Cost::IMPASSABLE);
+		drop(guard);
+		sector_cost_fields.set_void(SectorID::new(1, 1), &map_dimensions);
+		let scaled = sector_cost_fields.get_scaled().get(&sector_id).unwrap();
+		assert_eq!(255, scaled.get_field_cell_value(FieldCell::new(9, 5)));
+	}
+	#[test]
+	fn edit_guard_without_changes() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(0, 0);
+		{
+			let mut guard = sector_cost_fields.edit(sector_id, &map_dimensions).unwrap();
+			guard.set_field_cell_value(FieldCell::new(4, 4), 1);
+		}
+		assert!(sector_cost_fields.get_edited_sectors().is_empty());
+	}
+	#[test]
 	#[cfg(feature = "ron")]
 	fn sector_cost_fields_file_ron() {
 		let map_dimensions = MapDimensions::new(1920, 1920, 640, 16.0);
@@ -1332,90 +2214,106 @@ mod tests {
 		assert_eq!(actual, result);
 	}
 	#[test]
-	fn intersect_para() {
-		let edge1 = EdgeLine::build(Vec2::new(0.0, 0.0), Vec2::new(3.0, 3.0));
-		let edge2 = EdgeLine::build(Vec2::new(-1.0, 0.0), Vec2::new(2.0, 3.0));
-		assert_eq!(edge1.does_intersect(&edge2), Intersection::None);
+	fn scale_neighbour_after_edit() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 1.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		// the gap straddles the boundary, editing the western sector closes
+		// the cell in the eastern one
+		let east = SectorID::new(1, 0);
+		cost_fields.set_field_cell_value(east, 255, FieldCell::new(1, 4), &map_dimensions);
+		let west = SectorID::new(0, 0);
+		cost_fields.set_field_cell_value(west, 255, FieldCell::new(9, 4), &map_dimensions);
+		let result = cost_fields
+			.get_scaled()
+			.get(&east)
+			.unwrap()
+			.get_field_cell_value(FieldCell::new(0, 4));
+		assert_eq!(255, result);
 	}
 	#[test]
-	fn intersect_yes() {
-		let edge1 = EdgeLine::build(Vec2::new(0.0, 0.0), Vec2::new(3.0, 3.0));
-		let edge2 = EdgeLine::build(Vec2::new(-1.0, 5.0), Vec2::new(3.0, 2.0));
-		assert_eq!(edge1.does_intersect(&edge2), Intersection::Intersect);
+	fn scale_reopens_gap() {
+		let map_dimensions = MapDimensions::new(10, 10, 10, 1.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(0, 0);
+		cost_fields.set_field_cell_value(sector_id, 255, FieldCell::new(3, 3), &map_dimensions);
+		cost_fields.set_field_cell_value(sector_id, 255, FieldCell::new(3, 1), &map_dimensions);
+		let inspect_field = FieldCell::new(3, 2);
+		let scaled = cost_fields.get_scaled().get(&sector_id).unwrap();
+		assert_eq!(255, scaled.get_field_cell_value(inspect_field));
+		// removing one side of the gap opens it again
+		cost_fields.set_field_cell_value(sector_id, 1, FieldCell::new(3, 3), &map_dimensions);
+		let scaled = cost_fields.get_scaled().get(&sector_id).unwrap();
+		assert_eq!(1, scaled.get_field_cell_value(inspect_field));
+		assert_eq!(1, scaled.get_field_cell_value(FieldCell::new(3, 3)));
 	}
 	#[test]
-	fn intersect_yes_but_oob() {
-		let edge1 = EdgeLine::build(Vec2::new(0.0, 0.0), Vec2::new(3.0, 3.0));
-		let edge2 = EdgeLine::build(Vec2::new(-1.0, 5.0), Vec2::new(-0.5, 1.25));
-		assert_eq!(edge1.does_intersect(&edge2), Intersection::None);
+	fn scale_three_closes_narrow_corridor() {
+		let map_dimensions = MapDimensions::new(10, 10, 10, 2.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(0, 0);
+		// walls along columns 2 and 5 leave a corridor 2 cells wide
+		for row in 0..FIELD_RESOLUTION {
+			for column in [2, 5] {
+				cost_fields.set_field_cell_value(
+					sector_id,
+					255,
+					FieldCell::new(column, row),
+					&map_dimensions,
+				);
+			}
+		}
+		let scaled = cost_fields.get_scaled().get(&sector_id).unwrap();
+		for row in 0..FIELD_RESOLUTION {
+			assert_eq!(255, scaled.get_field_cell_value(FieldCell::new(3, row)));
+			assert_eq!(255, scaled.get_field_cell_value(FieldCell::new(4, row)));
+			// the open space beyond the walls is wide enough
+			assert_eq!(1, scaled.get_field_cell_value(FieldCell::new(7, row)));
+		}
+		// squeezed between the wall and the edge of the world
+		assert_eq!(255, scaled.get_field_cell_value(FieldCell::new(0, 0)));
 	}
-	// #[test]
-	// fn intersect_no() {
-	// 	let edge1 = EdgeLine::build(Vec2::new(0.0, 0.0), Vec2::new(3.0, 3.0));
-	// 	let edge2 = EdgeLine::build(Vec2::new(-1.0, 0.0), Vec2::new(2.0, 3.0));
-	// 	assert!(!edge1.does_intersect(&edge2))
-	// }
 	#[test]
-	fn mesh_edges_triangle_list() {
-		let mesh = Mesh::new(
-			PrimitiveTopology::TriangleList,
-			RenderAssetUsages::default(),
-		)
-		.with_inserted_attribute(
-			Mesh::ATTRIBUTE_POSITION,
-			vec![
-				[-960.0, 640.0, 0.0],
-				[-960.0, 960.0, 0.0],
-				[700.0, 960.0, 0.0],
-				[900.0, 800.0, 0.0],
-				[700.0, 640.0, 0.0],
-			],
-		)
-		.with_inserted_indices(Indices::U32(vec![0, 1, 2, 2, 3, 4, 4, 2, 0]));
-		let mesh_vertices = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
-		let vertex_points = mesh_vertices.as_float3().unwrap();
-		let result = retrieve_mesh_edges(&&mesh, vertex_points);
-		let actual = vec![
-			MeshTriEdge(0, 1),
-			MeshTriEdge(1, 2),
-			MeshTriEdge(2, 0),
-			MeshTriEdge(2, 3),
-			MeshTriEdge(3, 4),
-			MeshTriEdge(4, 2),
-			MeshTriEdge(4, 2),
-			MeshTriEdge(2, 0),
-			MeshTriEdge(0, 4),
-		];
-		assert_eq!(actual, result);
+	fn scale_closes_diagonal_gap() {
+		let map_dimensions = MapDimensions::new(10, 10, 10, 1.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(0, 0);
+		// pillars set corner to corner with a single diagonal cell between
+		cost_fields.set_field_cell_value(sector_id, 255, FieldCell::new(3, 3), &map_dimensions);
+		cost_fields.set_field_cell_value(sector_id, 255, FieldCell::new(5, 5), &map_dimensions);
+		let scaled = cost_fields.get_scaled().get(&sector_id).unwrap();
+		assert_eq!(255, scaled.get_field_cell_value(FieldCell::new(4, 4)));
+		// cells either side of the gap can still be stood on
+		assert_eq!(1, scaled.get_field_cell_value(FieldCell::new(5, 4)));
+		assert_eq!(1, scaled.get_field_cell_value(FieldCell::new(4, 5)));
+		// a small actor can slip through
+		let map_dimensions = MapDimensions::new(10, 10, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		cost_fields.set_field_cell_value(sector_id, 255, FieldCell::new(3, 3), &map_dimensions);
+		cost_fields.set_field_cell_value(sector_id, 255, FieldCell::new(5, 5), &map_dimensions);
+		let scaled = cost_fields.get_scaled().get(&sector_id).unwrap();
+		assert_eq!(1, scaled.get_field_cell_value(FieldCell::new(4, 4)));
 	}
 	#[test]
-	fn mesh_edges_triangle_strip() {
-		let mesh = Mesh::new(
-			PrimitiveTopology::TriangleStrip,
-			RenderAssetUsages::default(),
-		)
-		.with_inserted_attribute(
-			Mesh::ATTRIBUTE_POSITION,
-			vec![
-				[-192.0, 640.0, 0.0],
-				[-192.0, -640.0, 0.0],
-				[192.0, 640.0, 0.0],
-				[192.0, -640.0, 0.0],
-			],
-		)
-		.with_inserted_indices(Indices::U32(vec![0, 1, 2, 3]));
-		let mesh_vertices = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
-		let vertex_points = mesh_vertices.as_float3().unwrap();
-		let result = retrieve_mesh_edges(&&mesh, vertex_points);
-		let actual = vec![
-			MeshTriEdge(0, 1),
-			MeshTriEdge(1, 2),
-			MeshTriEdge(2, 0),
-			MeshTriEdge(2, 1),
-			MeshTriEdge(1, 3),
-			MeshTriEdge(3, 2),
-		];
-		assert_eq!(actual, result);
+	fn scale_closes_diagonal_wall() {
+		let map_dimensions = MapDimensions::new(10, 10, 10, 1.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(0, 0);
+		// a diagonal wall of pillars from the top right to the bottom left
+		// with gaps a large actor can't fit through
+		for i in (0..FIELD_RESOLUTION).step_by(2) {
+			cost_fields.set_field_cell_value(
+				sector_id,
+				255,
+				FieldCell::new(9 - i, i),
+				&map_dimensions,
+			);
+		}
+		// every gap along the wall is closed
+		let scaled = cost_fields.get_scaled().get(&sector_id).unwrap();
+		for i in (1..FIELD_RESOLUTION).step_by(2) {
+			let gap = FieldCell::new(9 - i, i);
+			assert_eq!(255, scaled.get_field_cell_value(gap));
+		}
 	}
 	/// Using simple edgelines verify which field cell candidates intersect it once
 	#[test]
@@ -1427,12 +2325,13 @@ mod tests {
 		let map_dimensions = MapDimensions::new(length, depth, sector_resolution, actor_size);
 		// simple square in top left of map dim
 		let outer_edges = vec![
-			EdgeLine::build(Vec2::new(-960.0, 864.0), Vec2::new(-960.0, 960.0)),
-			EdgeLine::build(Vec2::new(-864.0, 864.0), Vec2::new(-896.0, 960.0)),
-			EdgeLine::build(Vec2::new(-960.0, 864.0), Vec2::new(-864.0, 864.0)),
-			EdgeLine::build(Vec2::new(-960.0, 960.0), Vec2::new(-864.0, 960.0)),
+			EdgeLine::new(Vec2::new(-960.0, 864.0), Vec2::new(-960.0, 960.0)),
+			EdgeLine::new(Vec2::new(-864.0, 864.0), Vec2::new(-896.0, 960.0)),
+			EdgeLine::new(Vec2::new(-960.0, 864.0), Vec2::new(-864.0, 864.0)),
+			EdgeLine::new(Vec2::new(-960.0, 960.0), Vec2::new(-864.0, 960.0)),
 		];
-		let candidates = calc_field_cell_mesh_candidates(&map_dimensions, &outer_edges);
+		let candidates =
+			calc_field_cell_mesh_candidates(&map_dimensions, SectorID::new(0, 0), &outer_edges);
 		let actual = vec![(1, 1), (1, 2), (2, 1), (2, 2)];
 		assert_eq!(actual, candidates);
 	}
@@ -1445,10 +2344,10 @@ mod tests {
 		let map_dimensions = MapDimensions::new(length, depth, sector_resolution, actor_size);
 		// simple square in top left of map dim
 		let outer_edges = vec![
-			EdgeLine::build(Vec2::new(-960.0, 864.0), Vec2::new(-960.0, 960.0)),
-			EdgeLine::build(Vec2::new(-864.0, 864.0), Vec2::new(-896.0, 960.0)),
-			EdgeLine::build(Vec2::new(-960.0, 864.0), Vec2::new(-864.0, 864.0)),
-			EdgeLine::build(Vec2::new(-960.0, 960.0), Vec2::new(-864.0, 960.0)),
+			EdgeLine::new(Vec2::new(-960.0, 864.0), Vec2::new(-960.0, 960.0)),
+			EdgeLine::new(Vec2::new(-864.0, 864.0), Vec2::new(-896.0, 960.0)),
+			EdgeLine::new(Vec2::new(-960.0, 864.0), Vec2::new(-864.0, 864.0)),
+			EdgeLine::new(Vec2::new(-960.0, 960.0), Vec2::new(-864.0, 960.0)),
 		];
 		let candidates = vec![(1, 1), (1, 2), (2, 1), (2, 2)];
 		let failed =
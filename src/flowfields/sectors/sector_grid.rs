@@ -0,0 +1,267 @@
+//! The layout of sectors across a map. All neighbour lookups between sectors
+//! are resolved by a [SectorGrid] so that the bounds checks of each [Ordinal]
+//! live in a single place
+//!
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// The number of sector columns and rows making up a map. Sectors are indexed
+/// from the top-left `(0, 0)` through to `(columns - 1, rows - 1)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub struct SectorGrid {
+	/// Number of sectors along the x-axis
+	columns: u32,
+	/// Number of sectors along the y-axis (2d) or z-axis (3d)
+	rows: u32,
+}
+
+impl SectorGrid {
+	/// Create a new instance of [SectorGrid] with the number of sector
+	/// `columns` and `rows`
+	pub fn new(columns: u32, rows: u32) -> Self {
+		SectorGrid { columns, rows }
+	}
+	/// Create a [SectorGrid] from the `length` and `depth` of a map and the
+	/// `sector_resolution` used to divide it
+	pub fn from_dimensions(length: u32, depth: u32, sector_resolution: u32) -> Self {
+		SectorGrid {
+			columns: length / sector_resolution,
+			rows: depth / sector_resolution,
+		}
+	}
+	/// Get the number of sector columns
+	pub fn get_columns(&self) -> u32 {
+		self.columns
+	}
+	/// Get the number of sector rows
+	pub fn get_rows(&self) -> u32 {
+		self.rows
+	}
+	/// Whether the `sector_id` is within the grid
+	pub fn contains(&self, sector_id: &SectorID) -> bool {
+		sector_id.get_column() < self.columns && sector_id.get_row() < self.rows
+	}
+	/// Get the ID of the sector found in the [Ordinal] direction from
+	/// `sector_id`. Returns [None] if the neighbour would be out of bounds or
+	/// the `ordinal` is [Ordinal::Zero]
+	pub fn neighbour(&self, sector_id: &SectorID, ordinal: Ordinal) -> Option<SectorID> {
+		let (column_offset, row_offset) = match ordinal {
+			Ordinal::North => (0, -1),
+			Ordinal::East => (1, 0),
+			Ordinal::South => (0, 1),
+			Ordinal::West => (-1, 0),
+			Ordinal::NorthEast => (1, -1),
+			Ordinal::SouthEast => (1, 1),
+			Ordinal::SouthWest => (-1, 1),
+			Ordinal::NorthWest => (-1, -1),
+			Ordinal::Zero => return None,
+		};
+		let column = sector_id.get_column().checked_add_signed(column_offset)?;
+		let row = sector_id.get_row().checked_add_signed(row_offset)?;
+		let neighbour = SectorID::new(column, row);
+		if self.contains(&neighbour) {
+			Some(neighbour)
+		} else {
+			None
+		}
+	}
+	/// Get the orthogonal neighbours of `sector_id` along with the [Ordinal]
+	/// direction they're found in, ordered North, East, South, West
+	pub fn orthogonal_neighbours_with_ordinal(
+		&self,
+		sector_id: &SectorID,
+	) -> Vec<(Ordinal, SectorID)> {
+		[Ordinal::North, Ordinal::East, Ordinal::South, Ordinal::West]
+			.into_iter()
+			.filter_map(|ordinal| {
+				self.neighbour(sector_id, ordinal)
+					.map(|neighbour| (ordinal, neighbour))
+			})
+			.collect()
+	}
+	/// Get the orthogonal neighbours of `sector_id`, ordered North, East,
+	/// South, West
+	pub fn orthogonal_neighbours(&self, sector_id: &SectorID) -> Vec<SectorID> {
+		self.orthogonal_neighbours_with_ordinal(sector_id)
+			.into_iter()
+			.map(|(_, neighbour)| neighbour)
+			.collect()
+	}
+	/// Get the [Ordinal] directions of the boundaries of `sector_id` which
+	/// border another sector and so can support [Portals]
+	pub fn portal_ordinals(&self, sector_id: &SectorID) -> Vec<Ordinal> {
+		self.orthogonal_neighbours_with_ordinal(sector_id)
+			.into_iter()
+			.map(|(ordinal, _)| ordinal)
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Every direction a neighbour can be found in
+	const ORDINALS: [Ordinal; 8] = [
+		Ordinal::North,
+		Ordinal::East,
+		Ordinal::South,
+		Ordinal::West,
+		Ordinal::NorthEast,
+		Ordinal::SouthEast,
+		Ordinal::SouthWest,
+		Ordinal::NorthWest,
+	];
+
+	/// Grid sizes covering single sectors, single rows/columns and
+	/// non-square maps
+	fn grids() -> Vec<SectorGrid> {
+		vec![
+			SectorGrid::new(1, 1),
+			SectorGrid::new(1, 5),
+			SectorGrid::new(5, 1),
+			SectorGrid::new(2, 2),
+			SectorGrid::new(3, 3),
+			SectorGrid::new(4, 7),
+			SectorGrid::new(30, 30),
+		]
+	}
+
+	/// Column and row offset of an [Ordinal] used as an independent oracle
+	fn offset(ordinal: Ordinal) -> (i64, i64) {
+		let column = match ordinal {
+			Ordinal::East | Ordinal::NorthEast | Ordinal::SouthEast => 1,
+			Ordinal::West | Ordinal::NorthWest | Ordinal::SouthWest => -1,
+			_ => 0,
+		};
+		let row = match ordinal {
+			Ordinal::North | Ordinal::NorthEast | Ordinal::NorthWest => -1,
+			Ordinal::South | Ordinal::SouthEast | Ordinal::SouthWest => 1,
+			_ => 0,
+		};
+		(column, row)
+	}
+
+	#[test]
+	fn from_dimensions() {
+		let grid = SectorGrid::from_dimensions(300, 550, 10);
+		assert_eq!(30, grid.get_columns());
+		assert_eq!(55, grid.get_rows());
+	}
+	#[test]
+	fn neighbour_east_at_second_to_last_column() {
+		let grid = SectorGrid::new(3, 3);
+		let result = grid.neighbour(&SectorID::new(1, 1), Ordinal::East);
+		assert_eq!(Some(SectorID::new(2, 1)), result);
+		let result = grid.neighbour(&SectorID::new(1, 1), Ordinal::SouthEast);
+		assert_eq!(Some(SectorID::new(2, 2)), result);
+	}
+	#[test]
+	fn neighbour_zero() {
+		let grid = SectorGrid::new(3, 3);
+		assert!(grid
+			.neighbour(&SectorID::new(1, 1), Ordinal::Zero)
+			.is_none());
+	}
+	#[test]
+	fn neighbour_from_outside_grid() {
+		let grid = SectorGrid::new(3, 3);
+		assert!(grid
+			.neighbour(&SectorID::new(u32::MAX, 1), Ordinal::East)
+			.is_none());
+		assert!(grid
+			.neighbour(&SectorID::new(5, 5), Ordinal::North)
+			.is_none());
+	}
+	#[test]
+	fn every_sector_every_ordinal() {
+		for grid in grids() {
+			for column in 0..grid.get_columns() {
+				for row in 0..grid.get_rows() {
+					let sector_id = SectorID::new(column, row);
+					for ordinal in ORDINALS {
+						let (dc, dr) = offset(ordinal);
+						let c = column as i64 + dc;
+						let r = row as i64 + dr;
+						let in_bounds = c >= 0
+							&& r >= 0 && c < grid.get_columns() as i64
+							&& r < grid.get_rows() as i64;
+						let result = grid.neighbour(&sector_id, ordinal);
+						if in_bounds {
+							assert_eq!(
+								Some(SectorID::new(c as u32, r as u32)),
+								result,
+								"{:?} of {:?} in {:?}",
+								ordinal,
+								sector_id,
+								grid
+							);
+						} else {
+							assert!(
+								result.is_none(),
+								"{:?} of {:?} in {:?}",
+								ordinal,
+								sector_id,
+								grid
+							);
+						}
+					}
+				}
+			}
+		}
+	}
+	#[test]
+	fn neighbour_inverse_returns_to_sector() {
+		for grid in grids() {
+			for column in 0..grid.get_columns() {
+				for row in 0..grid.get_rows() {
+					let sector_id = SectorID::new(column, row);
+					for ordinal in ORDINALS {
+						if let Some(neighbour) = grid.neighbour(&sector_id, ordinal) {
+							assert!(grid.contains(&neighbour));
+							assert_eq!(
+								Some(sector_id),
+								grid.neighbour(&neighbour, ordinal.inverse())
+							);
+						}
+					}
+				}
+			}
+		}
+	}
+	#[test]
+	fn orthogonal_neighbours_match_neighbour() {
+		for grid in grids() {
+			for column in 0..grid.get_columns() {
+				for row in 0..grid.get_rows() {
+					let sector_id = SectorID::new(column, row);
+					let expected: Vec<(Ordinal, SectorID)> =
+						[Ordinal::North, Ordinal::East, Ordinal::South, Ordinal::West]
+							.into_iter()
+							.filter_map(|o| grid.neighbour(&sector_id, o).map(|n| (o, n)))
+							.collect();
+					let with_ordinal = grid.orthogonal_neighbours_with_ordinal(&sector_id);
+					assert_eq!(expected, with_ordinal);
+					let ids: Vec<SectorID> = expected.iter().map(|(_, n)| *n).collect();
+					assert_eq!(ids, grid.orthogonal_neighbours(&sector_id));
+					let ordinals: Vec<Ordinal> = expected.iter().map(|(o, _)| *o).collect();
+					assert_eq!(ordinals, grid.portal_ordinals(&sector_id));
+					// sectors bordered on every side have four neighbours
+					let interior = column > 0
+						&& row > 0 && column + 1 < grid.get_columns()
+						&& row + 1 < grid.get_rows();
+					if interior {
+						assert_eq!(4, with_ordinal.len());
+					}
+				}
+			}
+		}
+	}
+	#[test]
+	fn single_sector_has_no_neighbours() {
+		let grid = SectorGrid::new(1, 1);
+		assert!(grid.orthogonal_neighbours(&SectorID::new(0, 0)).is_empty());
+		assert!(grid.portal_ordinals(&SectorID::new(0, 0)).is_empty());
+	}
+}
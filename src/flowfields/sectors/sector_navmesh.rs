@@ -0,0 +1,343 @@
+//! Some tooling, such as off-the-shelf AI crates, expects a polygonal
+//! navmesh rather than a grid. The scaled [CostField]s can be exported as a
+//! [NavMesh] where runs of pathable [FieldCell]s sharing the same cost are
+//! greedily merged into rectangles within each sector. Polygons are linked
+//! to those they share an edge with, including across sector boundaries
+//! where the portals lie, so the whole walkable area is stitched together
+//!
+
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+use bevy::{
+	prelude::*,
+	render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+};
+
+/// A rectangle of pathable [FieldCell]s within a sector which share the same
+/// cost
+#[derive(Clone, Debug, PartialEq)]
+pub struct NavMeshPolygon {
+	/// The sector the polygon lies in
+	sector_id: SectorID,
+	/// The top-left [FieldCell] covered by the polygon
+	min: FieldCell,
+	/// The bottom-right [FieldCell] covered by the polygon
+	max: FieldCell,
+	/// The scaled cost of every [FieldCell] covered by the polygon
+	cost: u8,
+	/// Indices into the [NavMesh] vertices of the top-left, bottom-left,
+	/// bottom-right and top-right corners
+	vertices: [u32; 4],
+	/// Indices of the polygons which share an edge with this one
+	neighbours: Vec<usize>,
+}
+
+impl NavMeshPolygon {
+	/// Get the sector the polygon lies in
+	pub fn get_sector_id(&self) -> SectorID {
+		self.sector_id
+	}
+	/// Get the top-left and bottom-right [FieldCell]s covered by the polygon
+	pub fn get_field_cells(&self) -> (FieldCell, FieldCell) {
+		(self.min, self.max)
+	}
+	/// Get the scaled cost of every [FieldCell] covered by the polygon
+	pub fn get_cost(&self) -> u8 {
+		self.cost
+	}
+	/// Get the indices into [NavMesh::get_vertices] of the corners, wound
+	/// counter-clockwise from the top-left
+	pub fn get_vertices(&self) -> [u32; 4] {
+		self.vertices
+	}
+	/// Get the indices of the polygons which share an edge with this one
+	pub fn get_neighbours(&self) -> &[usize] {
+		&self.neighbours
+	}
+	/// Get the global `(column, row)` bounds of the polygon in [FieldCell]
+	/// units, the maximums are exclusive
+	fn global_bounds(&self) -> ((u32, u32), (u32, u32)) {
+		let resolution = FIELD_RESOLUTION as u32;
+		let column = self.sector_id.get_column() * resolution;
+		let row = self.sector_id.get_row() * resolution;
+		(
+			(
+				column + self.min.get_column() as u32,
+				row + self.min.get_row() as u32,
+			),
+			(
+				column + self.max.get_column() as u32 + 1,
+				row + self.max.get_row() as u32 + 1,
+			),
+		)
+	}
+	/// Whether two polygons share an edge of non-zero length
+	fn is_adjacent(&self, other: &NavMeshPolygon) -> bool {
+		let ((a_min_x, a_min_y), (a_max_x, a_max_y)) = self.global_bounds();
+		let ((b_min_x, b_min_y), (b_max_x, b_max_y)) = other.global_bounds();
+		let overlap_x = a_min_x.max(b_min_x) < a_max_x.min(b_max_x);
+		let overlap_y = a_min_y.max(b_min_y) < a_max_y.min(b_max_y);
+		((a_max_x == b_min_x || b_max_x == a_min_x) && overlap_y)
+			|| ((a_max_y == b_min_y || b_max_y == a_min_y) && overlap_x)
+	}
+}
+
+/// The pathable area of a map as a set of polygons, see
+/// [SectorCostFields::to_navmesh]
+///
+/// Vertices are in 2d `x-y` world space, neighbouring polygons may meet at a
+/// T-junction rather than sharing both vertices of an edge
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NavMesh {
+	/// Corners of the polygons
+	vertices: Vec<Vec2>,
+	/// Polygons covering the pathable area
+	polygons: Vec<NavMeshPolygon>,
+}
+
+impl NavMesh {
+	/// Get the corners of the polygons in 2d `x-y` world space
+	pub fn get_vertices(&self) -> &[Vec2] {
+		&self.vertices
+	}
+	/// Get the corners of the polygons in 3d `x-z` world space with a `y` of
+	/// `0.0`
+	pub fn get_vertices_xyz(&self) -> Vec<Vec3> {
+		self.vertices
+			.iter()
+			.map(|v| Vec3::new(v.x, 0.0, -v.y))
+			.collect()
+	}
+	/// Get the polygons covering the pathable area
+	pub fn get_polygons(&self) -> &[NavMeshPolygon] {
+		&self.polygons
+	}
+	/// Get the index of the polygon covering a [FieldCell]
+	pub fn get_polygon_at(&self, sector_id: SectorID, field_cell: FieldCell) -> Option<usize> {
+		self.polygons.iter().position(|polygon| {
+			polygon.sector_id == sector_id
+				&& (polygon.min.get_column()..=polygon.max.get_column())
+					.contains(&field_cell.get_column())
+				&& (polygon.min.get_row()..=polygon.max.get_row()).contains(&field_cell.get_row())
+		})
+	}
+	/// Get the vertex indices of two counter-clockwise triangles per polygon
+	pub fn get_triangle_indices(&self) -> Vec<u32> {
+		self.polygons
+			.iter()
+			.flat_map(|polygon| {
+				let [a, b, c, d] = polygon.vertices;
+				[a, b, c, a, c, d]
+			})
+			.collect()
+	}
+	/// Build a triangle [Mesh] of the pathable area in 2d `x-y` world space
+	pub fn to_mesh_xy(&self) -> Mesh {
+		let positions: Vec<[f32; 3]> = self.vertices.iter().map(|v| [v.x, v.y, 0.0]).collect();
+		self.build_mesh(positions, [0.0, 0.0, 1.0])
+	}
+	/// Build a triangle [Mesh] of the pathable area in 3d `x-z` world space
+	pub fn to_mesh_xyz(&self) -> Mesh {
+		let positions: Vec<[f32; 3]> = self
+			.get_vertices_xyz()
+			.iter()
+			.map(|v| v.to_array())
+			.collect();
+		self.build_mesh(positions, [0.0, 1.0, 0.0])
+	}
+	/// Build a triangle [Mesh] from the `positions` of each vertex facing
+	/// along the `normal`
+	fn build_mesh(&self, positions: Vec<[f32; 3]>, normal: [f32; 3]) -> Mesh {
+		let normals = vec![normal; positions.len()];
+		Mesh::new(
+			PrimitiveTopology::TriangleList,
+			RenderAssetUsages::default(),
+		)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+		.with_inserted_indices(Indices::U32(self.get_triangle_indices()))
+	}
+}
+
+impl SectorCostFields {
+	/// Export the pathable area of the scaled [CostField]s as a [NavMesh].
+	/// Within each sector pathable [FieldCell]s of equal cost are greedily
+	/// merged into rectangles, scanning rows from the top-left. Void sectors
+	/// contribute no polygons
+	pub fn to_navmesh(&self, map_dimensions: &MapDimensions) -> NavMesh {
+		let cell_size = map_dimensions.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+		let origin = Vec2::new(
+			map_dimensions.get_length() as f32 / -2.0,
+			map_dimensions.get_depth() as f32 / 2.0,
+		);
+		let mut navmesh = NavMesh::default();
+		// corners are shared between polygons, keyed by global corner index
+		let mut corners: BTreeMap<(u32, u32), u32> = BTreeMap::new();
+		let mut by_sector: BTreeMap<SectorID, Vec<usize>> = BTreeMap::new();
+		for (sector_id, field) in self.get_scaled().iter() {
+			for (min, max, cost) in merge_rectangles(field) {
+				let mut polygon = NavMeshPolygon {
+					sector_id: *sector_id,
+					min,
+					max,
+					cost,
+					vertices: [0; 4],
+					neighbours: Vec::new(),
+				};
+				let ((min_x, min_y), (max_x, max_y)) = polygon.global_bounds();
+				let corner_ids = [
+					(min_x, min_y),
+					(min_x, max_y),
+					(max_x, max_y),
+					(max_x, min_y),
+				];
+				for (i, corner) in corner_ids.into_iter().enumerate() {
+					polygon.vertices[i] = *corners.entry(corner).or_insert_with(|| {
+						navmesh.vertices.push(
+							origin + Vec2::new(corner.0 as f32, -(corner.1 as f32)) * cell_size,
+						);
+						navmesh.vertices.len() as u32 - 1
+					});
+				}
+				by_sector
+					.entry(*sector_id)
+					.or_default()
+					.push(navmesh.polygons.len());
+				navmesh.polygons.push(polygon);
+			}
+		}
+		// polygons can only touch others in the same or an orthogonal sector
+		for index in 0..navmesh.polygons.len() {
+			let sector_id = navmesh.polygons[index].sector_id;
+			let mut sectors = map_dimensions.get_ids_of_neighbouring_sectors(&sector_id);
+			sectors.push(sector_id);
+			let mut neighbours = Vec::new();
+			for other in sectors.iter().filter_map(|s| by_sector.get(s)).flatten() {
+				if *other != index && navmesh.polygons[index].is_adjacent(&navmesh.polygons[*other])
+				{
+					neighbours.push(*other);
+				}
+			}
+			neighbours.sort();
+			navmesh.polygons[index].neighbours = neighbours;
+		}
+		navmesh
+	}
+}
+
+/// Greedily merge the pathable [FieldCell]s of a [CostField] into rectangles
+/// of equal cost, returning the top-left and bottom-right cells and cost of
+/// each
+fn merge_rectangles(field: &CostField) -> Vec<(FieldCell, FieldCell, u8)> {
+	let mut covered = [[false; FIELD_RESOLUTION]; FIELD_RESOLUTION];
+	let mut rectangles = Vec::new();
+	let cost_at = |column, row| field.get_field_cell_value(FieldCell::new(column, row));
+	for row in 0..FIELD_RESOLUTION {
+		for column in 0..FIELD_RESOLUTION {
+			let cost = cost_at(column, row);
+			if covered[column][row] || cost == 255 {
+				continue;
+			}
+			let is_free =
+				|c: usize, r: usize, covered: &[[bool; FIELD_RESOLUTION]; FIELD_RESOLUTION]| {
+					!covered[c][r] && cost_at(c, r) == cost
+				};
+			let mut max_column = column;
+			while max_column + 1 < FIELD_RESOLUTION && is_free(max_column + 1, row, &covered) {
+				max_column += 1;
+			}
+			let mut max_row = row;
+			while max_row + 1 < FIELD_RESOLUTION
+				&& (column..=max_column).all(|c| is_free(c, max_row + 1, &covered))
+			{
+				max_row += 1;
+			}
+			for cells in covered.iter_mut().take(max_column + 1).skip(column) {
+				cells[row..=max_row].fill(true);
+			}
+			rectangles.push((
+				FieldCell::new(column, row),
+				FieldCell::new(max_column, max_row),
+				cost,
+			));
+		}
+	}
+	rectangles
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn open_map_navmesh() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let navmesh = cost_fields.to_navmesh(&map_dimensions);
+		// one polygon per sector sharing a 4x4 grid of corners
+		assert_eq!(9, navmesh.get_polygons().len());
+		assert_eq!(16, navmesh.get_vertices().len());
+		let centre = navmesh
+			.get_polygon_at(SectorID::new(1, 1), FieldCell::new(5, 5))
+			.unwrap();
+		assert_eq!(4, navmesh.get_polygons()[centre].get_neighbours().len());
+		let corner = navmesh
+			.get_polygon_at(SectorID::new(0, 0), FieldCell::new(0, 0))
+			.unwrap();
+		assert_eq!(2, navmesh.get_polygons()[corner].get_neighbours().len());
+		let [top_left, _, bottom_right, _] = navmesh.get_polygons()[corner].get_vertices();
+		assert_eq!(
+			Vec2::new(-15.0, 15.0),
+			navmesh.get_vertices()[top_left as usize]
+		);
+		assert_eq!(
+			Vec2::new(-5.0, 5.0),
+			navmesh.get_vertices()[bottom_right as usize]
+		);
+		assert_eq!(9 * 6, navmesh.get_triangle_indices().len());
+	}
+	#[test]
+	fn navmesh_splits_on_walls_and_costs() {
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(0, 0);
+		// a wall down column 4 and a marsh in column 0
+		for row in 0..FIELD_RESOLUTION {
+			cost_fields.set_field_cell_value(
+				sector_id,
+				255,
+				FieldCell::new(4, row),
+				&map_dimensions,
+			);
+			cost_fields.set_field_cell_value(sector_id, 5, FieldCell::new(0, row), &map_dimensions);
+		}
+		let navmesh = cost_fields.to_navmesh(&map_dimensions);
+		assert_eq!(4, navmesh.get_polygons().len());
+		assert!(navmesh
+			.get_polygon_at(sector_id, FieldCell::new(4, 3))
+			.is_none());
+		let marsh = navmesh
+			.get_polygon_at(sector_id, FieldCell::new(0, 3))
+			.unwrap();
+		let west = navmesh
+			.get_polygon_at(sector_id, FieldCell::new(2, 3))
+			.unwrap();
+		let east = navmesh
+			.get_polygon_at(sector_id, FieldCell::new(7, 3))
+			.unwrap();
+		let beyond = navmesh
+			.get_polygon_at(SectorID::new(1, 0), FieldCell::new(0, 3))
+			.unwrap();
+		assert_eq!(5, navmesh.get_polygons()[marsh].get_cost());
+		assert_eq!(&[west], navmesh.get_polygons()[marsh].get_neighbours());
+		// the wall separates the two halves of the sector
+		assert!(!navmesh.get_polygons()[west]
+			.get_neighbours()
+			.contains(&east));
+		// stitched across the sector boundary
+		assert_eq!(&[east], navmesh.get_polygons()[beyond].get_neighbours());
+		let mesh = navmesh.to_mesh_xyz();
+		assert_eq!(navmesh.get_vertices().len(), mesh.count_vertices());
+	}
+}
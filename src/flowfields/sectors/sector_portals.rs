@@ -49,11 +49,44 @@ impl SectorPortals {
 		let mut changed = map_dimensions.get_ids_of_neighbouring_sectors(&changed_cost_field_id);
 		changed.push(changed_cost_field_id);
 		for id in changed.iter() {
-			self.get_mut().get_mut(id).unwrap().recalculate_portals(
-				sector_cost_fields,
-				id,
-				map_dimensions,
-			);
+			if let Some(portals) = self.get_mut().get_mut(id) {
+				portals.recalculate_portals(sector_cost_fields, id, map_dimensions);
+			} else {
+				warn!("Cannot update Portals of non-existent sector {:?}", id);
+			}
+		}
+		self
+	}
+	/// When the changed [FieldCell]s of a [CostField] only affect some of the
+	/// `boundaries` of the sector (see [MapDimensions::get_boundaries_affected_by])
+	/// then only the [Portals] along those boundaries, and the facing
+	/// boundaries of the neighbouring sectors, need to be recalculated
+	pub fn update_portal_boundaries(
+		&mut self,
+		changed_cost_field_id: SectorID,
+		boundaries: &[Ordinal],
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) -> &mut Self {
+		let mut changed = vec![(changed_cost_field_id, boundaries.to_vec())];
+		for (ordinal, neighbour) in
+			map_dimensions.get_ordinal_and_ids_of_neighbouring_sectors(&changed_cost_field_id)
+		{
+			if boundaries.contains(&ordinal) {
+				changed.push((neighbour, vec![ordinal.inverse()]));
+			}
+		}
+		for (id, sides) in changed.iter() {
+			if let Some(portals) = self.get_mut().get_mut(id) {
+				portals.recalculate_portal_boundaries(
+					sector_cost_fields,
+					id,
+					map_dimensions,
+					sides,
+				);
+			} else {
+				warn!("Cannot update Portals of non-existent sector {:?}", id);
+			}
 		}
 		self
 	}
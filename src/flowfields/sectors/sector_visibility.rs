@@ -0,0 +1,239 @@
+//! In games with a fog of war units should only path through the parts of
+//! the map their player has explored. A [SectorVisibility] component on a
+//! bundle records which sectors each [PlayerId] has explored and path
+//! requests made on behalf of a player (see
+//! [EventPathRequest::with_player]) are searched with a
+//! [VisibilityCostModel] which blocks, or penalises, the unexplored sectors
+//! according to the [UnexploredPolicy]
+//!
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Identifies a player whose knowledge of the map limits where their units
+/// may path
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct PlayerId(u32);
+
+impl PlayerId {
+	/// Create a new instance of [PlayerId]
+	pub fn new(id: u32) -> Self {
+		PlayerId(id)
+	}
+	/// Get the ID
+	pub fn get(&self) -> u32 {
+		self.0
+	}
+}
+
+/// How the route search treats sectors a player hasn't explored
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum UnexploredPolicy {
+	/// Routes never enter an unexplored sector
+	#[default]
+	Blocked,
+	/// Routes may enter an unexplored sector but each portal within it
+	/// costs this much more, so explored paths are preferred
+	Penalty(i32),
+}
+
+/// Records which sectors of a bundle each [PlayerId] has explored
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SectorVisibility {
+	/// The explored sectors of each player
+	explored: BTreeMap<PlayerId, BTreeSet<SectorID>>,
+	/// How unexplored sectors are treated by the route search
+	policy: UnexploredPolicy,
+}
+
+impl SectorVisibility {
+	/// Create a new instance of [SectorVisibility] where no player has
+	/// explored any sectors
+	pub fn new(policy: UnexploredPolicy) -> Self {
+		SectorVisibility {
+			explored: BTreeMap::new(),
+			policy,
+		}
+	}
+	/// Get how unexplored sectors are treated by the route search
+	pub fn get_policy(&self) -> UnexploredPolicy {
+		self.policy
+	}
+	/// Set how unexplored sectors are treated by the route search
+	pub fn set_policy(&mut self, policy: UnexploredPolicy) {
+		self.policy = policy;
+	}
+	/// Mark a sector as explored by the `player`. Returns true if the sector
+	/// was previously unexplored. To invalidate routes which may now find a
+	/// better path send an [EventRevealSector] instead
+	pub fn reveal(&mut self, player: PlayerId, sector_id: SectorID) -> bool {
+		self.explored.entry(player).or_default().insert(sector_id)
+	}
+	/// Mark a sector as unexplored by the `player`, such as when resetting a
+	/// map. Cached routes through the sector aren't affected
+	pub fn conceal(&mut self, player: PlayerId, sector_id: SectorID) {
+		if let Some(explored) = self.explored.get_mut(&player) {
+			explored.remove(&sector_id);
+		}
+	}
+	/// Whether the `player` has explored a sector
+	pub fn is_explored(&self, player: PlayerId, sector_id: SectorID) -> bool {
+		self.explored
+			.get(&player)
+			.is_some_and(|explored| explored.contains(&sector_id))
+	}
+	/// Get the sectors explored by the `player`
+	pub fn get_explored(&self, player: PlayerId) -> Option<&BTreeSet<SectorID>> {
+		self.explored.get(&player)
+	}
+}
+
+/// Wraps a [PortalCostModel] so that the route search of a player treats
+/// the sectors they haven't explored according to the [UnexploredPolicy] of
+/// the [SectorVisibility]
+pub struct VisibilityCostModel<'a> {
+	/// The model scoring the explored sectors
+	inner: &'a dyn PortalCostModel,
+	/// The sectors explored by each player
+	visibility: &'a SectorVisibility,
+	/// The player the route is searched for
+	player: PlayerId,
+}
+
+impl<'a> VisibilityCostModel<'a> {
+	/// Create a new instance of [VisibilityCostModel] searching on behalf of
+	/// the `player`
+	pub fn new(
+		inner: &'a dyn PortalCostModel,
+		visibility: &'a SectorVisibility,
+		player: PlayerId,
+	) -> Self {
+		VisibilityCostModel {
+			inner,
+			visibility,
+			player,
+		}
+	}
+}
+
+impl PortalCostModel for VisibilityCostModel<'_> {
+	fn edge_cost(
+		&self,
+		from: (SectorID, FieldCell),
+		to: (SectorID, FieldCell),
+		distance: i32,
+	) -> i32 {
+		self.inner.edge_cost(from, to, distance)
+	}
+	fn node_cost(&self, node: (SectorID, FieldCell), weight: u8) -> i32 {
+		let cost = self.inner.node_cost(node, weight);
+		match self.visibility.get_policy() {
+			UnexploredPolicy::Penalty(penalty)
+				if !self.visibility.is_explored(self.player, node.0) =>
+			{
+				cost + penalty
+			}
+			_ => cost,
+		}
+	}
+	fn heuristic(
+		&self,
+		node: (SectorID, FieldCell),
+		target: (SectorID, FieldCell),
+		distance: i32,
+	) -> i32 {
+		self.inner.heuristic(node, target, distance)
+	}
+	fn is_sector_passable(&self, sector_id: SectorID) -> bool {
+		let explored = match self.visibility.get_policy() {
+			UnexploredPolicy::Blocked => self.visibility.is_explored(self.player, sector_id),
+			UnexploredPolicy::Penalty(_) => true,
+		};
+		explored && self.inner.is_sector_passable(sector_id)
+	}
+	fn is_portal_passable(&self, node: (SectorID, FieldCell)) -> bool {
+		self.inner.is_portal_passable(node)
+	}
+	fn is_edge_passable(&self, from: (SectorID, FieldCell), to: (SectorID, FieldCell)) -> bool {
+		self.inner.is_edge_passable(from, to)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Reveal the top row of a 30x30 map to the `player`
+	fn reveal_top_row(visibility: &mut SectorVisibility, player: PlayerId) {
+		for column in 0..3 {
+			visibility.reveal(player, SectorID::new(column, 0));
+		}
+	}
+
+	#[test]
+	fn unexplored_sectors_blocked() {
+		let bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let graph = bundle.get_portal_graph();
+		let portals = bundle.get_sector_portals();
+		let cost_fields = bundle.get_sector_cost_fields();
+		let player = PlayerId::new(1);
+		let mut visibility = SectorVisibility::new(UnexploredPolicy::Blocked);
+		reveal_top_row(&mut visibility, player);
+		assert!(visibility.is_explored(player, SectorID::new(1, 0)));
+		assert!(!visibility.is_explored(PlayerId::new(2), SectorID::new(1, 0)));
+		let model = VisibilityCostModel::new(&DefaultPortalCostModel, &visibility, player);
+		let source = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let across = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let path = graph
+			.find_best_path_with_model(source, across, portals, cost_fields, &model)
+			.unwrap();
+		assert!(path.iter().all(|(sector_id, _)| sector_id.get_row() == 0));
+		// the bottom of the map is unknown to the player
+		let below = (SectorID::new(0, 2), FieldCell::new(4, 4));
+		assert!(graph
+			.find_best_path_with_model(source, below, portals, cost_fields, &model)
+			.is_none());
+		visibility.reveal(player, SectorID::new(0, 1));
+		visibility.reveal(player, SectorID::new(0, 2));
+		let model = VisibilityCostModel::new(&DefaultPortalCostModel, &visibility, player);
+		assert!(graph
+			.find_best_path_with_model(source, below, portals, cost_fields, &model)
+			.is_some());
+	}
+	#[test]
+	fn unexplored_sectors_penalised() {
+		let bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let graph = bundle.get_portal_graph();
+		let portals = bundle.get_sector_portals();
+		let cost_fields = bundle.get_sector_cost_fields();
+		let player = PlayerId::new(1);
+		let mut visibility = SectorVisibility::new(UnexploredPolicy::Penalty(1000));
+		reveal_top_row(&mut visibility, player);
+		let model = VisibilityCostModel::new(&DefaultPortalCostModel, &visibility, player);
+		let source = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let below = (SectorID::new(0, 2), FieldCell::new(4, 4));
+		assert!(graph
+			.find_best_path_with_model(source, below, portals, cost_fields, &model)
+			.is_some());
+		// a detour through explored sectors is preferred over a shorter path
+		// through the unknown
+		visibility.conceal(player, SectorID::new(1, 0));
+		for column in 0..3 {
+			visibility.reveal(player, SectorID::new(column, 1));
+		}
+		let model = VisibilityCostModel::new(&DefaultPortalCostModel, &visibility, player);
+		let across = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let path = graph
+			.find_best_path_with_model(source, across, portals, cost_fields, &model)
+			.unwrap();
+		assert!(path
+			.iter()
+			.all(|(sector_id, _)| *sector_id != SectorID::new(1, 0)));
+	}
+}
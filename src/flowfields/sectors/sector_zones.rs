@@ -0,0 +1,362 @@
+//! Parts of a map may be off limits to some teams, such as the base of one
+//! faction which its enemies cannot enter until its gates are opened. A
+//! [FactionZones] component on a bundle records the restricted zones of each
+//! [FactionId] and path requests made on behalf of a faction (see
+//! [EventPathRequest::with_faction]) treat the [FieldCell]s of its enabled
+//! zones as impassable. The route search skips restricted portals, and
+//! walks within a sector which restricted cells cut off, with a
+//! [ZoneCostModel] and the [IntegrationField]s of the route mark the
+//! restricted cells as impassable
+//!
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Identifies a team or faction whose units may be kept out of parts of the
+/// map
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct FactionId(u32);
+
+impl FactionId {
+	/// Create a new instance of [FactionId]
+	pub fn new(id: u32) -> Self {
+		FactionId(id)
+	}
+	/// Get the ID
+	pub fn get(&self) -> u32 {
+		self.0
+	}
+}
+
+/// Identifies a restricted zone of a [FactionZones]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct ZoneId(u32);
+
+impl ZoneId {
+	/// Create a new instance of [ZoneId]
+	pub fn new(id: u32) -> Self {
+		ZoneId(id)
+	}
+	/// Get the ID
+	pub fn get(&self) -> u32 {
+		self.0
+	}
+}
+
+/// A set of [FieldCell]s a faction may not enter while the zone is enabled
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
+struct RestrictedZone {
+	/// The faction kept out of the zone
+	faction: FactionId,
+	/// The restricted cells of each sector
+	cells: BTreeMap<SectorID, BTreeSet<FieldCell>>,
+	/// Whether the restriction currently applies
+	enabled: bool,
+}
+
+/// Records the zones of a bundle which each [FactionId] may not enter
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct FactionZones {
+	/// The restricted zones
+	zones: BTreeMap<ZoneId, RestrictedZone>,
+}
+
+impl FactionZones {
+	/// Create a new instance of [FactionZones] without any restrictions
+	pub fn new() -> Self {
+		FactionZones::default()
+	}
+	/// Keep the `faction` out of the `cells`, replacing any existing zone
+	/// with the same ID. The zone starts enabled
+	pub fn add_zone(
+		&mut self,
+		zone_id: ZoneId,
+		faction: FactionId,
+		cells: impl IntoIterator<Item = (SectorID, FieldCell)>,
+	) {
+		let mut zone = RestrictedZone {
+			faction,
+			cells: BTreeMap::new(),
+			enabled: true,
+		};
+		for (sector_id, field_cell) in cells {
+			zone.cells.entry(sector_id).or_default().insert(field_cell);
+		}
+		self.zones.insert(zone_id, zone);
+	}
+	/// Keep the `faction` out of every [FieldCell] of the `sectors`,
+	/// replacing any existing zone with the same ID. The zone starts enabled
+	pub fn add_sector_zone(
+		&mut self,
+		zone_id: ZoneId,
+		faction: FactionId,
+		sectors: impl IntoIterator<Item = SectorID>,
+	) {
+		let cells = sectors.into_iter().flat_map(|sector_id| {
+			(0..FIELD_RESOLUTION).flat_map(move |column| {
+				(0..FIELD_RESOLUTION).map(move |row| (sector_id, FieldCell::new(column, row)))
+			})
+		});
+		self.add_zone(zone_id, faction, cells);
+	}
+	/// Remove a zone, returning whether it existed. To invalidate the routes
+	/// it affects disable it with an [EventToggleZone] first
+	pub fn remove_zone(&mut self, zone_id: ZoneId) -> bool {
+		self.zones.remove(&zone_id).is_some()
+	}
+	/// Enable or disable a zone. Returns true if the state of the zone
+	/// changed. To invalidate the routes it affects send an
+	/// [EventToggleZone] instead
+	pub fn set_zone_enabled(&mut self, zone_id: ZoneId, enabled: bool) -> bool {
+		match self.zones.get_mut(&zone_id) {
+			Some(zone) if zone.enabled != enabled => {
+				zone.enabled = enabled;
+				true
+			}
+			_ => false,
+		}
+	}
+	/// Whether a zone exists and is enabled
+	pub fn is_zone_enabled(&self, zone_id: ZoneId) -> bool {
+		self.zones.get(&zone_id).is_some_and(|zone| zone.enabled)
+	}
+	/// Get the faction kept out of a zone
+	pub fn get_zone_faction(&self, zone_id: ZoneId) -> Option<FactionId> {
+		self.zones.get(&zone_id).map(|zone| zone.faction)
+	}
+	/// Get the sectors containing the cells of a zone
+	pub fn get_zone_sectors(&self, zone_id: ZoneId) -> Vec<SectorID> {
+		self.zones
+			.get(&zone_id)
+			.map(|zone| zone.cells.keys().copied().collect())
+			.unwrap_or_default()
+	}
+	/// Iterate over the enabled zones of a `faction`
+	fn enabled_zones(&self, faction: FactionId) -> impl Iterator<Item = &RestrictedZone> {
+		self.zones
+			.values()
+			.filter(move |zone| zone.enabled && zone.faction == faction)
+	}
+	/// Whether the `faction` may not enter a [FieldCell]
+	pub fn is_cell_restricted(
+		&self,
+		faction: FactionId,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+	) -> bool {
+		self.enabled_zones(faction).any(|zone| {
+			zone.cells
+				.get(&sector_id)
+				.is_some_and(|cells| cells.contains(&field_cell))
+		})
+	}
+	/// Whether the `faction` may not enter any [FieldCell] of a sector
+	pub fn is_sector_restricted(&self, faction: FactionId, sector_id: SectorID) -> bool {
+		self.get_restricted_cells(faction, sector_id).len() == FIELD_RESOLUTION * FIELD_RESOLUTION
+	}
+	/// Get the [FieldCell]s of a sector the `faction` may not enter
+	pub fn get_restricted_cells(&self, faction: FactionId, sector_id: SectorID) -> Vec<FieldCell> {
+		let mut restricted = BTreeSet::new();
+		for zone in self.enabled_zones(faction) {
+			if let Some(cells) = zone.cells.get(&sector_id) {
+				restricted.extend(cells.iter().copied());
+			}
+		}
+		restricted.into_iter().collect()
+	}
+}
+
+/// Wraps a [PortalCostModel] so that the route search of a faction never
+/// crosses a portal, enters a sector, or walks between the portals of a
+/// sector across cells restricted by its [FactionZones]
+pub struct ZoneCostModel<'a> {
+	/// The model scoring the unrestricted portals
+	inner: &'a dyn PortalCostModel,
+	/// The restricted zones of each faction
+	zones: &'a FactionZones,
+	/// The faction the route is searched for
+	faction: FactionId,
+	/// The scaled [CostField] of each sector containing restricted cells
+	/// with those cells marked impassable
+	masked: BTreeMap<SectorID, CostField>,
+}
+
+impl<'a> ZoneCostModel<'a> {
+	/// Create a new instance of [ZoneCostModel] searching on behalf of the
+	/// `faction`, the `sector_cost_fields` decide whether the restricted
+	/// cells of a sector cut its portals off from one another
+	pub fn new(
+		inner: &'a dyn PortalCostModel,
+		zones: &'a FactionZones,
+		faction: FactionId,
+		sector_cost_fields: &SectorCostFields,
+	) -> Self {
+		let mut masked = BTreeMap::new();
+		let sectors: BTreeSet<SectorID> = zones
+			.enabled_zones(faction)
+			.flat_map(|zone| zone.cells.keys().copied())
+			.collect();
+		for sector_id in sectors {
+			if let Some(cost_field) = sector_cost_fields.get_scaled().get(&sector_id) {
+				let mut cost_field = cost_field.clone();
+				for field_cell in zones.get_restricted_cells(faction, sector_id) {
+					cost_field.set_field_cell_value(255, field_cell);
+				}
+				masked.insert(sector_id, cost_field);
+			}
+		}
+		ZoneCostModel {
+			inner,
+			zones,
+			faction,
+			masked,
+		}
+	}
+}
+
+impl PortalCostModel for ZoneCostModel<'_> {
+	fn edge_cost(
+		&self,
+		from: (SectorID, FieldCell),
+		to: (SectorID, FieldCell),
+		distance: i32,
+	) -> i32 {
+		self.inner.edge_cost(from, to, distance)
+	}
+	fn node_cost(&self, node: (SectorID, FieldCell), weight: u8) -> i32 {
+		self.inner.node_cost(node, weight)
+	}
+	fn heuristic(
+		&self,
+		node: (SectorID, FieldCell),
+		target: (SectorID, FieldCell),
+		distance: i32,
+	) -> i32 {
+		self.inner.heuristic(node, target, distance)
+	}
+	fn is_sector_passable(&self, sector_id: SectorID) -> bool {
+		!self.zones.is_sector_restricted(self.faction, sector_id)
+			&& self.inner.is_sector_passable(sector_id)
+	}
+	fn is_portal_passable(&self, node: (SectorID, FieldCell)) -> bool {
+		!self.zones.is_cell_restricted(self.faction, node.0, node.1)
+			&& self.inner.is_portal_passable(node)
+	}
+	fn is_edge_passable(&self, from: (SectorID, FieldCell), to: (SectorID, FieldCell)) -> bool {
+		let is_connected = from.0 != to.0
+			|| self
+				.masked
+				.get(&from.0)
+				.is_none_or(|cost_field| cost_field.is_cell_pair_reachable(from.1, to.1));
+		is_connected && self.inner.is_edge_passable(from, to)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn zone_restricts_only_its_faction() {
+		let mut zones = FactionZones::new();
+		let attackers = FactionId::new(1);
+		let zone = ZoneId::new(7);
+		zones.add_sector_zone(zone, attackers, [SectorID::new(1, 1)]);
+		assert!(zones.is_sector_restricted(attackers, SectorID::new(1, 1)));
+		assert!(!zones.is_sector_restricted(FactionId::new(2), SectorID::new(1, 1)));
+		assert!(!zones.is_sector_restricted(attackers, SectorID::new(0, 1)));
+		assert!(zones.set_zone_enabled(zone, false));
+		assert!(!zones.set_zone_enabled(zone, false));
+		assert!(!zones.is_cell_restricted(attackers, SectorID::new(1, 1), FieldCell::new(4, 4)));
+		assert_eq!(vec![SectorID::new(1, 1)], zones.get_zone_sectors(zone));
+	}
+	#[test]
+	fn route_avoids_restricted_sectors() {
+		let bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let graph = bundle.get_portal_graph();
+		let portals = bundle.get_sector_portals();
+		let cost_fields = bundle.get_sector_cost_fields();
+		let faction = FactionId::new(1);
+		let mut zones = FactionZones::new();
+		zones.add_sector_zone(ZoneId::new(0), faction, [SectorID::new(1, 0)]);
+		let model = ZoneCostModel::new(&DefaultPortalCostModel, &zones, faction, cost_fields);
+		let source = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let across = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let path = graph
+			.find_best_path_with_model(source, across, portals, cost_fields, &model)
+			.unwrap();
+		assert!(path
+			.iter()
+			.all(|(sector_id, _)| *sector_id != SectorID::new(1, 0)));
+		// other factions take the direct path
+		let model = ZoneCostModel::new(
+			&DefaultPortalCostModel,
+			&zones,
+			FactionId::new(2),
+			cost_fields,
+		);
+		let path = graph
+			.find_best_path_with_model(source, across, portals, cost_fields, &model)
+			.unwrap();
+		assert!(path.iter().all(|(sector_id, _)| sector_id.get_row() == 0));
+	}
+	#[test]
+	fn route_avoids_restricted_portals() {
+		let bundle = FlowFieldTilesBundle::new(20, 10, 10, 0.5);
+		let graph = bundle.get_portal_graph();
+		let portals = bundle.get_sector_portals();
+		let cost_fields = bundle.get_sector_cost_fields();
+		let faction = FactionId::new(1);
+		// a gate across the only boundary between the sectors
+		let mut zones = FactionZones::new();
+		let gate = (0..FIELD_RESOLUTION).map(|row| (SectorID::new(1, 0), FieldCell::new(0, row)));
+		zones.add_zone(ZoneId::new(0), faction, gate);
+		let model = ZoneCostModel::new(&DefaultPortalCostModel, &zones, faction, cost_fields);
+		let source = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let target = (SectorID::new(1, 0), FieldCell::new(4, 4));
+		assert!(graph
+			.find_best_path_with_model(source, target, portals, cost_fields, &model)
+			.is_none());
+		zones.set_zone_enabled(ZoneId::new(0), false);
+		let model = ZoneCostModel::new(&DefaultPortalCostModel, &zones, faction, cost_fields);
+		assert!(graph
+			.find_best_path_with_model(source, target, portals, cost_fields, &model)
+			.is_some());
+	}
+	#[test]
+	fn route_avoids_gate_within_transit_sector() {
+		let bundle = FlowFieldTilesBundle::new(30, 10, 10, 0.5);
+		let graph = bundle.get_portal_graph();
+		let portals = bundle.get_sector_portals();
+		let cost_fields = bundle.get_sector_cost_fields();
+		let faction = FactionId::new(1);
+		// a gate splitting the middle sector, away from any of its portals
+		let mut zones = FactionZones::new();
+		let gate = (0..FIELD_RESOLUTION).map(|row| (SectorID::new(1, 0), FieldCell::new(5, row)));
+		zones.add_zone(ZoneId::new(0), faction, gate);
+		let source = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let target = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let model = ZoneCostModel::new(&DefaultPortalCostModel, &zones, faction, cost_fields);
+		assert!(graph
+			.find_best_path_with_model(source, target, portals, cost_fields, &model)
+			.is_none());
+		// nor can the faction walk from one side of the gate to the other
+		let west = (SectorID::new(1, 0), FieldCell::new(2, 4));
+		let east = (SectorID::new(1, 0), FieldCell::new(8, 4));
+		assert!(graph
+			.find_best_path_with_model(west, east, portals, cost_fields, &model)
+			.is_none());
+		zones.set_zone_enabled(ZoneId::new(0), false);
+		let model = ZoneCostModel::new(&DefaultPortalCostModel, &zones, faction, cost_fields);
+		assert!(graph
+			.find_best_path_with_model(source, target, portals, cost_fields, &model)
+			.is_some());
+	}
+}
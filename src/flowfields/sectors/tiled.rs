@@ -0,0 +1,528 @@
+//! Reading [CostField]s from a [Tiled](https://www.mapeditor.org/) `.tmx`
+//! map. The tiles of a map correspond to the [FieldCell]s of every sector,
+//! so a map of `30x30` tiles suits [MapDimensions] with `3x3` sectors.
+//!
+//! A tile layer converts the global ID of each tile into a cost with a
+//! [TiledCostMapping]. An object layer marks every [FieldCell] whose centre
+//! lies within one of its rectangles, ellipses, polygons or tile objects as
+//! impassable, unless the object has an integer `cost` custom property in
+//! which case that cost is used instead. The XML is parsed with `roxmltree`
+//!
+
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Tiled stores whether a tile is flipped or rotated in the upper bits of
+/// its global ID
+const TILED_GID_MASK: u32 = 0x0FFF_FFFF;
+
+/// Describes how the global ID of each tile of a Tiled tile layer is
+/// converted into a cost
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledCostMapping {
+	/// Cost of each tile by its global ID (the `firstgid` of its tileset plus
+	/// its ID within the tileset)
+	tile_costs: BTreeMap<u32, Cost>,
+	/// Cost of a tile which isn't in `tile_costs`
+	default_cost: Cost,
+	/// Cost of a cell with no tile
+	empty_cost: Cost,
+}
+
+impl Default for TiledCostMapping {
+	fn default() -> Self {
+		TiledCostMapping {
+			tile_costs: BTreeMap::new(),
+			default_cost: Cost::PASSABLE,
+			empty_cost: Cost::PASSABLE,
+		}
+	}
+}
+
+impl TiledCostMapping {
+	/// Create a new instance of [TiledCostMapping] where every tile is
+	/// passable
+	pub fn new() -> Self {
+		TiledCostMapping::default()
+	}
+	/// Set the cost of tiles with the global ID `gid`
+	pub fn with_tile_cost(mut self, gid: u32, cost: impl Into<Cost>) -> Self {
+		self.tile_costs.insert(gid, cost.into());
+		self
+	}
+	/// Set the cost of tiles which haven't been given a cost with
+	/// [TiledCostMapping::with_tile_cost]
+	pub fn with_default_cost(mut self, cost: impl Into<Cost>) -> Self {
+		self.default_cost = cost.into();
+		self
+	}
+	/// Set the cost of cells without a tile
+	pub fn with_empty_cost(mut self, cost: impl Into<Cost>) -> Self {
+		self.empty_cost = cost.into();
+		self
+	}
+	/// Get the cost of a tile from its global ID, any flip flags of the ID
+	/// are ignored
+	pub fn get_tile_cost(&self, gid: u32) -> Cost {
+		let gid = gid & TILED_GID_MASK;
+		if gid == 0 {
+			self.empty_cost
+		} else {
+			*self.tile_costs.get(&gid).unwrap_or(&self.default_cost)
+		}
+	}
+}
+
+impl SectorCostFields {
+	/// From a Tiled `.tmx` file generate the [SectorCostFields] where the
+	/// costs are read from the tile or object layer called `layer_name`. The
+	/// map must have one tile per [FieldCell] and tile layers must be saved
+	/// with the `CSV` or `XML` encoding
+	pub fn from_tiled(
+		path: String,
+		map_dimensions: &MapDimensions,
+		layer_name: &str,
+		mapping: &TiledCostMapping,
+	) -> Self {
+		let contents = std::fs::read_to_string(path).expect("Failed opening Tiled map file");
+		SectorCostFields::from_tiled_str(&contents, map_dimensions, layer_name, mapping)
+	}
+	/// As with [SectorCostFields::from_tiled] from the `contents` of a `.tmx`
+	/// file which has already been read, panics if the map can't be read. See
+	/// [SectorCostFields::try_from_tiled_str] to handle the error instead
+	pub fn from_tiled_str(
+		contents: &str,
+		map_dimensions: &MapDimensions,
+		layer_name: &str,
+		mapping: &TiledCostMapping,
+	) -> Self {
+		SectorCostFields::try_from_tiled_str(contents, map_dimensions, layer_name, mapping)
+			.unwrap_or_else(|e| panic!("{}", e))
+	}
+	/// As with [SectorCostFields::from_tiled_str] but returns a [TiledError]
+	/// if the map is malformed or doesn't suit the `map_dimensions`
+	pub fn try_from_tiled_str(
+		contents: &str,
+		map_dimensions: &MapDimensions,
+		layer_name: &str,
+		mapping: &TiledCostMapping,
+	) -> Result<Self, TiledError> {
+		let document =
+			roxmltree::Document::parse(contents).map_err(|e| TiledError::Parse(e.to_string()))?;
+		let map = document.root_element();
+		if !map.has_tag_name("map") {
+			return Err(TiledError::MissingMap);
+		}
+		if map.attribute("infinite") == Some("1") {
+			return Err(TiledError::Infinite);
+		}
+		let width = get_number::<usize>(map, "width")?;
+		let height = get_number::<usize>(map, "height")?;
+		let tile_width = get_number::<f32>(map, "tilewidth")?;
+		let tile_height = get_number::<f32>(map, "tileheight")?;
+		let columns = map_dimensions.get_total_field_cell_columns();
+		let rows = map_dimensions.get_total_field_cell_rows();
+		if width != columns || height != rows {
+			return Err(TiledError::IncorrectSize {
+				expected: (columns, rows),
+				found: (width, height),
+			});
+		}
+		let mut sector_cost_fields = SectorCostFields::new(map_dimensions);
+		let is_layer = |e: &roxmltree::Node| {
+			(e.has_tag_name("layer") || e.has_tag_name("objectgroup"))
+				&& e.attribute("name") == Some(layer_name)
+		};
+		let Some(layer) = map.descendants().find(is_layer) else {
+			return Err(TiledError::MissingLayer(layer_name.to_string()));
+		};
+		let mut costs: Vec<((usize, usize), Cost)> = Vec::new();
+		if layer.has_tag_name("layer") {
+			for (i, gid) in read_tile_gids(layer)?.into_iter().enumerate() {
+				costs.push(((i % width, i / width), mapping.get_tile_cost(gid)));
+			}
+			if costs.len() != width * height {
+				return Err(TiledError::TileCount {
+					layer: layer_name.to_string(),
+					found: costs.len(),
+					expected: width * height,
+				});
+			}
+		} else {
+			for object in layer.children().filter(|e| e.has_tag_name("object")) {
+				let Some(polygon) = object_polygon(object) else {
+					continue;
+				};
+				let edges: Vec<EdgeLine> = polygon
+					.iter()
+					.zip(polygon.iter().cycle().skip(1))
+					.map(|(a, b)| EdgeLine::new(Vec2::new(a.0, a.1), Vec2::new(b.0, b.1)))
+					.collect();
+				let cost = object
+					.descendants()
+					.find(|e| e.has_tag_name("property") && e.attribute("name") == Some("cost"))
+					.and_then(|property| property.attribute("value"))
+					.and_then(|value| value.parse::<u8>().ok())
+					.map_or(Cost::IMPASSABLE, Cost::new);
+				for row in 0..height {
+					for column in 0..width {
+						let centre = Vec2::new(
+							(column as f32 + 0.5) * tile_width,
+							(row as f32 + 0.5) * tile_height,
+						);
+						if is_point_within_polygon(centre, &edges, WindingRule::EvenOdd) {
+							costs.push(((column, row), cost));
+						}
+					}
+				}
+			}
+		}
+		for ((column, row), cost) in costs {
+			if let Some((sector_id, field_cell)) =
+				map_dimensions.sector_and_cell_from_global(column as u32, row as u32)
+			{
+				if let Some(field) = sector_cost_fields.baseline.get_mut(&sector_id) {
+					field.set_field_cell_value(cost.get(), field_cell);
+				}
+			}
+		}
+		sector_cost_fields.scale_all_costfields(map_dimensions);
+		Ok(sector_cost_fields)
+	}
+}
+
+/// A problem found while reading a Tiled map with
+/// [SectorCostFields::try_from_tiled_str]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TiledError {
+	/// The contents are not valid XML
+	Parse(String),
+	/// The root element of the document isn't a map
+	MissingMap,
+	/// The map is infinite which is not supported
+	Infinite,
+	/// An element lacks a required numeric attribute
+	MissingAttribute {
+		/// Name of the element
+		element: String,
+		/// Name of the attribute
+		attribute: String,
+	},
+	/// The map doesn't have one tile per [FieldCell]
+	IncorrectSize {
+		/// Expected `(columns, rows)` of tiles
+		expected: (usize, usize),
+		/// `(columns, rows)` of tiles in the map
+		found: (usize, usize),
+	},
+	/// There is no tile or object layer with the name
+	MissingLayer(String),
+	/// A tile layer has no data
+	MissingData,
+	/// A tile ID of a CSV tile layer isn't a number
+	InvalidTileId(String),
+	/// A tile layer is compressed or encoded with base64
+	UnsupportedEncoding(String),
+	/// A tile layer doesn't have one tile per [FieldCell]
+	TileCount {
+		/// Name of the layer
+		layer: String,
+		/// Number of tiles in the layer
+		found: usize,
+		/// Number of tiles in the map
+		expected: usize,
+	},
+}
+
+impl std::fmt::Display for TiledError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			TiledError::Parse(reason) => write!(f, "Tiled map is not valid XML: {}", reason),
+			TiledError::MissingMap => write!(f, "Tiled file doesn't contain a map"),
+			TiledError::Infinite => write!(f, "Infinite Tiled maps are not supported"),
+			TiledError::MissingAttribute { element, attribute } => write!(
+				f,
+				"Tiled {} is missing a valid {} attribute",
+				element, attribute
+			),
+			TiledError::IncorrectSize { expected, found } => write!(
+				f,
+				"Tiled map has incorrect size, expected {}x{} tiles, found {}x{}",
+				expected.0, expected.1, found.0, found.1
+			),
+			TiledError::MissingLayer(name) => {
+				write!(f, "Tiled map has no tile or object layer named {}", name)
+			}
+			TiledError::MissingData => write!(f, "Tiled layer has no data"),
+			TiledError::InvalidTileId(id) => write!(f, "Invalid tile ID {} in Tiled layer", id),
+			TiledError::UnsupportedEncoding(encoding) => write!(
+				f,
+				"Tiled layer encoding {} is not supported, save the map with CSV encoding",
+				encoding
+			),
+			TiledError::TileCount {
+				layer,
+				found,
+				expected,
+			} => write!(
+				f,
+				"Tiled layer {} has {} tiles, expected {}",
+				layer, found, expected
+			),
+		}
+	}
+}
+
+impl std::error::Error for TiledError {}
+
+/// Get the numeric value of a required attribute of an `element`
+fn get_number<T: std::str::FromStr>(
+	element: roxmltree::Node,
+	attribute: &str,
+) -> Result<T, TiledError> {
+	element
+		.attribute(attribute)
+		.and_then(|v| v.parse().ok())
+		.ok_or_else(|| TiledError::MissingAttribute {
+			element: element.tag_name().name().to_string(),
+			attribute: attribute.to_string(),
+		})
+}
+
+/// Read the global tile IDs of a tile layer from the top-left to the
+/// bottom-right of the map
+fn read_tile_gids(layer: roxmltree::Node) -> Result<Vec<u32>, TiledError> {
+	let Some(data) = layer.children().find(|e| e.has_tag_name("data")) else {
+		return Err(TiledError::MissingData);
+	};
+	match data.attribute("encoding") {
+		Some("csv") => data
+			.text()
+			.unwrap_or_default()
+			.split(',')
+			.map(str::trim)
+			.filter(|v| !v.is_empty())
+			.map(|v| {
+				v.parse::<u32>()
+					.map_err(|_| TiledError::InvalidTileId(v.to_string()))
+			})
+			.collect(),
+		None => Ok(data
+			.children()
+			.filter(|e| e.has_tag_name("tile"))
+			.map(|tile| {
+				tile.attribute("gid")
+					.and_then(|gid| gid.parse::<u32>().ok())
+					.unwrap_or(0)
+			})
+			.collect()),
+		Some(encoding) => Err(TiledError::UnsupportedEncoding(encoding.to_string())),
+	}
+}
+
+/// Get the outline of a rectangle, ellipse, polygon or tile object in map
+/// pixels. Returns [None] for shapes without an area such as points and
+/// polylines
+fn object_polygon(object: roxmltree::Node) -> Option<Vec<(f32, f32)>> {
+	let number = |key: &str| {
+		object
+			.attribute(key)
+			.and_then(|v| v.parse::<f32>().ok())
+			.unwrap_or(0.0)
+	};
+	let (x, y, width, height) = (number("x"), number("y"), number("width"), number("height"));
+	let has_child = |name: &str| object.children().any(|e| e.has_tag_name(name));
+	let local: Vec<(f32, f32)> =
+		if let Some(polygon) = object.children().find(|e| e.has_tag_name("polygon")) {
+			polygon
+				.attribute("points")?
+				.split_whitespace()
+				.filter_map(|point| {
+					let (px, py) = point.split_once(',')?;
+					Some((px.parse().ok()?, py.parse().ok()?))
+				})
+				.collect()
+		} else if has_child("point") || has_child("polyline") || width <= 0.0 || height <= 0.0 {
+			return None;
+		} else if has_child("ellipse") {
+			let (rx, ry) = (width / 2.0, height / 2.0);
+			(0..32)
+				.map(|i| {
+					let angle = i as f32 / 32.0 * std::f32::consts::TAU;
+					(rx + rx * angle.cos(), ry + ry * angle.sin())
+				})
+				.collect()
+		} else if object.attribute("gid").is_some() {
+			// tile objects are positioned by their bottom-left corner
+			vec![(0.0, -height), (width, -height), (width, 0.0), (0.0, 0.0)]
+		} else {
+			vec![(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)]
+		};
+	if local.len() < 3 {
+		return None;
+	}
+	// objects rotate clockwise (in degrees) around their position
+	let (sin, cos) = number("rotation").to_radians().sin_cos();
+	Some(
+		local
+			.into_iter()
+			.map(|(px, py)| (x + px * cos - py * sin, y + px * sin + py * cos))
+			.collect(),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A `20x10` tile map with a tile layer and an object layer
+	const MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="20" height="10" tilewidth="16" tileheight="16" infinite="0">
+ <tileset firstgid="1" source="terrain.tsx"/>
+ <layer id="1" name="costs" width="20" height="10">
+  <data encoding="csv">
+1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,
+1,2,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,
+1,1,3,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,
+1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,
+1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,
+1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,
+1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,
+1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,
+1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,
+0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,2147483650
+</data>
+ </layer>
+ <objectgroup id="2" name="walls">
+  <object id="1" name="wall" x="160" y="0" width="16" height="48"/>
+  <object id="2" x="32" y="96">
+   <polygon points="0,0 48,0 0,48"/>
+  </object>
+  <object id="3" x="240" y="128" width="32" height="16">
+   <properties>
+    <property name="cost" type="int" value="40"/>
+   </properties>
+  </object>
+  <object id="4" x="0" y="0">
+   <point/>
+  </object>
+ </objectgroup>
+</map>
+"#;
+
+	#[test]
+	fn tiled_tile_layer() {
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let mapping = TiledCostMapping::new()
+			.with_tile_cost(2, 255)
+			.with_tile_cost(3, 20)
+			.with_empty_cost(50);
+		let fields = SectorCostFields::from_tiled_str(MAP, &map_dimensions, "costs", &mapping);
+		let baseline = fields.get_baseline().get(&SectorID::new(0, 0)).unwrap();
+		assert_eq!(1, baseline.get_field_cell_value(FieldCell::new(0, 0)));
+		assert_eq!(255, baseline.get_field_cell_value(FieldCell::new(1, 1)));
+		assert_eq!(20, baseline.get_field_cell_value(FieldCell::new(2, 2)));
+		assert_eq!(50, baseline.get_field_cell_value(FieldCell::new(0, 9)));
+		// flip flags are ignored
+		let baseline = fields.get_baseline().get(&SectorID::new(1, 0)).unwrap();
+		assert_eq!(255, baseline.get_field_cell_value(FieldCell::new(9, 9)));
+	}
+	#[test]
+	fn tiled_object_layer() {
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let mapping = TiledCostMapping::new();
+		let fields = SectorCostFields::from_tiled_str(MAP, &map_dimensions, "walls", &mapping);
+		let west = fields.get_baseline().get(&SectorID::new(0, 0)).unwrap();
+		let east = fields.get_baseline().get(&SectorID::new(1, 0)).unwrap();
+		// rectangle over column 10, rows 0-2
+		for row in 0..3 {
+			assert_eq!(255, east.get_field_cell_value(FieldCell::new(0, row)));
+		}
+		assert_eq!(1, east.get_field_cell_value(FieldCell::new(0, 3)));
+		// triangle with its right angle at column 2, row 6
+		assert_eq!(255, west.get_field_cell_value(FieldCell::new(2, 6)));
+		assert_eq!(255, west.get_field_cell_value(FieldCell::new(3, 6)));
+		assert_eq!(255, west.get_field_cell_value(FieldCell::new(2, 7)));
+		assert_eq!(1, west.get_field_cell_value(FieldCell::new(4, 8)));
+		// an object with a cost property
+		assert_eq!(40, east.get_field_cell_value(FieldCell::new(5, 8)));
+		assert_eq!(40, east.get_field_cell_value(FieldCell::new(6, 8)));
+		// points have no area
+		assert_eq!(1, west.get_field_cell_value(FieldCell::new(0, 0)));
+	}
+	#[test]
+	fn tiled_xml_encoding() {
+		let mut tiles = String::new();
+		for i in 0..200 {
+			if i == 21 {
+				tiles.push_str("<tile gid=\"2\"/>");
+			} else {
+				tiles.push_str("<tile/>");
+			}
+		}
+		let map = format!(
+			"<map width=\"20\" height=\"10\" tilewidth=\"8\" tileheight=\"8\"><layer name=\"costs\"><data>{}</data></layer></map>",
+			tiles
+		);
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let mapping = TiledCostMapping::new().with_tile_cost(2, 255);
+		let fields = SectorCostFields::from_tiled_str(&map, &map_dimensions, "costs", &mapping);
+		let baseline = fields.get_baseline().get(&SectorID::new(0, 0)).unwrap();
+		assert_eq!(255, baseline.get_field_cell_value(FieldCell::new(1, 1)));
+		assert_eq!(1, baseline.get_field_cell_value(FieldCell::new(0, 0)));
+	}
+	#[test]
+	fn tiled_tile_object_anchor() {
+		let map = r#"<map width="20" height="10" tilewidth="16" tileheight="16">
+ <objectgroup name="props">
+  <object id="1" gid="5" x="32" y="32" width="16" height="16"/>
+ </objectgroup>
+</map>"#;
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let fields = SectorCostFields::try_from_tiled_str(
+			map,
+			&map_dimensions,
+			"props",
+			&TiledCostMapping::new(),
+		)
+		.unwrap();
+		let baseline = fields.get_baseline().get(&SectorID::new(0, 0)).unwrap();
+		// a tile object at y=32 occupies the row above it
+		assert_eq!(255, baseline.get_field_cell_value(FieldCell::new(2, 1)));
+		assert_eq!(1, baseline.get_field_cell_value(FieldCell::new(2, 2)));
+	}
+	#[test]
+	fn tiled_errors() {
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let mapping = TiledCostMapping::new();
+		let result = SectorCostFields::try_from_tiled_str(
+			"<map width=\"20\"",
+			&map_dimensions,
+			"costs",
+			&mapping,
+		);
+		assert!(matches!(result.err(), Some(TiledError::Parse(_))));
+		let result = SectorCostFields::try_from_tiled_str(MAP, &map_dimensions, "roads", &mapping);
+		assert_eq!(
+			Some(TiledError::MissingLayer("roads".to_string())),
+			result.err()
+		);
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let result = SectorCostFields::try_from_tiled_str(MAP, &map_dimensions, "costs", &mapping);
+		assert_eq!(
+			Some(TiledError::IncorrectSize {
+				expected: (30, 30),
+				found: (20, 10)
+			}),
+			result.err()
+		);
+	}
+	#[test]
+	#[should_panic]
+	fn tiled_incorrect_size() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		SectorCostFields::from_tiled_str(MAP, &map_dimensions, "costs", &TiledCostMapping::new());
+	}
+}
@@ -0,0 +1,559 @@
+//! Tools for building [CostField]s from shapes, such as the outlines of
+//! meshes or polygons drawn in a level editor. Shapes are described by
+//! [EdgeLine]s and positions are in the `x-y` plane of a 2d world, for a 3d
+//! world the position `(x, y, z)` is equivalent to `(x, -z)`.
+//!
+//! A polygon can be rasterised into the [FieldCell]s it covers and those
+//! cells given a cost:
+//!
+//! ```rust
+//! use bevy::prelude::*;
+//! use bevy_flowfield_tiles_plugin::prelude::*;
+//!
+//! let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+//! let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+//! let square = [
+//!     EdgeLine::new(Vec2::new(-2.0, -2.0), Vec2::new(2.0, -2.0)),
+//!     EdgeLine::new(Vec2::new(2.0, -2.0), Vec2::new(2.0, 2.0)),
+//!     EdgeLine::new(Vec2::new(2.0, 2.0), Vec2::new(-2.0, 2.0)),
+//!     EdgeLine::new(Vec2::new(-2.0, 2.0), Vec2::new(-2.0, -2.0)),
+//! ];
+//! for (sector_id, field_cell) in rasterise_polygon(&map_dimensions, &square, WindingRule::EvenOdd) {
+//!     sector_cost_fields.set_field_cell_value(sector_id, 255, field_cell, &map_dimensions);
+//! }
+//! ```
+//!
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Defines whether an intersection has occured
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Intersection {
+	/// Indicates that an edge meets and passes through another edge
+	Intersect,
+	/// Indicates that edges only touch one another, this is a special case of intersection
+	Touch,
+	/// Edge does not intersect
+	None,
+}
+
+/// Represents the start and end coordinates of a line in space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdgeLine {
+	/// Where the line starts
+	start: Vec2,
+	/// Where the line ends
+	end: Vec2,
+}
+
+impl EdgeLine {
+	/// Create an [EdgeLine] from two positions
+	pub fn new(start: Vec2, end: Vec2) -> Self {
+		EdgeLine { start, end }
+	}
+	/// Get where the line starts
+	pub fn get_start(&self) -> Vec2 {
+		self.start
+	}
+	/// Get where the line ends
+	pub fn get_end(&self) -> Vec2 {
+		self.end
+	}
+	/// Find whether two edges intersect, touch (meet only at an end point
+	/// or overlap while collinear) or don't meet at all
+	pub fn does_intersect(&self, other: &EdgeLine) -> Intersection {
+		//https://stackoverflow.com/questions/563198/how-do-you-detect-where-two-line-segments-intersect/565282#565282 (Ronald Goldman, published in Graphics Gems, page 304)
+		let self_segment = self.end - self.start;
+		let other_segment = other.end - other.start;
+
+		let cross_segment = self_segment.perp_dot(other_segment);
+		if cross_segment == 0.0 {
+			// find whether paralell or collinear
+			if (other.start - self.start).perp_dot(self_segment) == 0.0 {
+				// collinear, check if they overlap
+				let t_0 =
+					(other.start - self.start).dot(self_segment) / (self_segment.dot(self_segment));
+				let t_1 = t_0 + other_segment.dot(self_segment) / (self_segment.dot(self_segment));
+
+				// if other_segment.dot(self_segment) < 0.0 {
+				// 	if (t_0 <= 0.0 || t_0 >= 1.0) && (t_1 <= 0.0 || t_1 >= 1.0) {
+				// 		// overlap
+				// 	} else {
+				// 		// disjoint
+				// 		Intersection::None
+				// 	}
+				// } else {
+
+				if (0.0..=1.0).contains(&t_0) && (0.0..=1.0).contains(&t_1) {
+					// overlap
+					Intersection::Touch
+				} else {
+					// disjoint
+					Intersection::None
+				}
+			// }
+			} else {
+				// parallel, non-intersecting
+				Intersection::None
+			}
+		} else {
+			// may intersect, check if intersection point is on both segments
+			let u = (other.start - self.start).perp_dot(self_segment) / cross_segment;
+			let t = (other.start - self.start).perp_dot(other_segment) / cross_segment;
+			if (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&t) {
+				// special case where an edge only touches the start/end of another edge
+				let point = self.start + t * self_segment; //, other.start + u * other_segment);
+											   //TODO? floating precision can throw off touch check
+				if (point - other.start).length_squared() < f32::EPSILON
+					|| (point - other.end).length_squared() < f32::EPSILON
+				{
+					Intersection::Touch
+				} else {
+					Intersection::Intersect
+				}
+			} else {
+				Intersection::None
+			}
+		}
+	}
+}
+
+/// Decides which points are inside of a polygon whose outline overlaps
+/// itself (or is made of several outlines, such as a shape with a hole)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum WindingRule {
+	/// A point is inside if a ray cast from it crosses the outline an odd
+	/// number of times, overlapping regions and holes are outside
+	#[default]
+	EvenOdd,
+	/// A point is inside if the outline winds around it a non-zero number of
+	/// times, overlapping regions are inside while holes must wind in the
+	/// opposite direction to the outline around them
+	NonZero,
+}
+
+/// Whether a `point` lies within the polygon outlined by `edges`. A
+/// horizontal ray is cast from the point and each edge it crosses is counted
+/// according to the `winding` rule
+pub fn is_point_within_polygon(point: Vec2, edges: &[EdgeLine], winding: WindingRule) -> bool {
+	let mut crossings = 0;
+	let mut winding_number = 0;
+	for edge in edges {
+		let (a, b) = (edge.start, edge.end);
+		if (a.y > point.y) != (b.y > point.y) {
+			let crossing_x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+			if point.x < crossing_x {
+				crossings += 1;
+				// edges heading up wind anticlockwise around the point
+				winding_number += if b.y > a.y { 1 } else { -1 };
+			}
+		}
+	}
+	match winding {
+		WindingRule::EvenOdd => crossings % 2 == 1,
+		WindingRule::NonZero => winding_number != 0,
+	}
+}
+
+/// Get the four edges outlining a [FieldCell] in the order top, right,
+/// bottom and left
+pub fn get_field_cell_outline(
+	map_dimensions: &MapDimensions,
+	sector_id: SectorID,
+	field_cell: FieldCell,
+) -> [EdgeLine; 4] {
+	let (column, row) = map_dimensions.global_cell_from(sector_id, field_cell);
+	let size = map_dimensions.get_field_cell_unit_size();
+	let left = column as f32 * size - map_dimensions.get_length() as f32 / 2.0;
+	let top = row as f32 * -size + map_dimensions.get_depth() as f32 / 2.0;
+	let top_left = Vec2::new(left, top);
+	let top_right = Vec2::new(left + size, top);
+	let bottom_right = Vec2::new(left + size, top - size);
+	let bottom_left = Vec2::new(left, top - size);
+	[
+		EdgeLine::new(top_left, top_right),
+		EdgeLine::new(top_right, bottom_right),
+		EdgeLine::new(bottom_right, bottom_left),
+		EdgeLine::new(bottom_left, top_left),
+	]
+}
+
+/// Whether the whole of a [FieldCell] lies within the polygon outlined by
+/// `edges`, i.e its centre is inside the polygon and none of its sides cross
+/// or touch the outline
+pub fn is_field_cell_within_polygon(
+	map_dimensions: &MapDimensions,
+	sector_id: SectorID,
+	field_cell: FieldCell,
+	edges: &[EdgeLine],
+	winding: WindingRule,
+) -> bool {
+	let outline = get_field_cell_outline(map_dimensions, sector_id, field_cell);
+	let centre = (outline[0].start + outline[1].end) / 2.0;
+	if !is_point_within_polygon(centre, edges, winding) {
+		return false;
+	}
+	outline.iter().all(|side| {
+		edges
+			.iter()
+			.all(|edge| edge.does_intersect(side) == Intersection::None)
+	})
+}
+
+/// Find every [FieldCell] whose centre lies within the polygon outlined by
+/// `edges`. Only the cells within the bounds of the polygon are tested so
+/// small shapes are cheap to rasterise, use [is_field_cell_within_polygon]
+/// to exclude the cells only partially covered
+pub fn rasterise_polygon(
+	map_dimensions: &MapDimensions,
+	edges: &[EdgeLine],
+	winding: WindingRule,
+) -> Vec<(SectorID, FieldCell)> {
+	let mut cells = Vec::new();
+	if edges.is_empty() {
+		return cells;
+	}
+	let mut min = Vec2::MAX;
+	let mut max = Vec2::MIN;
+	for edge in edges {
+		min = min.min(edge.start).min(edge.end);
+		max = max.max(edge.start).max(edge.end);
+	}
+	let size = map_dimensions.get_field_cell_unit_size();
+	let offset_x = map_dimensions.get_length() as f32 / 2.0;
+	let offset_y = map_dimensions.get_depth() as f32 / 2.0;
+	// global columns increase with x and rows increase with -y
+	let to_index =
+		|value: f32, limit: usize| (value / size).floor().clamp(0.0, limit as f32) as usize;
+	let columns = map_dimensions.get_total_field_cell_columns();
+	let rows = map_dimensions.get_total_field_cell_rows();
+	let first_column = to_index(min.x + offset_x, columns);
+	let last_column = to_index(max.x + offset_x, columns - 1);
+	let first_row = to_index(offset_y - max.y, rows);
+	let last_row = to_index(offset_y - min.y, rows - 1);
+	for row in first_row..=last_row {
+		for column in first_column..=last_column {
+			let centre = Vec2::new(
+				(column as f32 + 0.5) * size - offset_x,
+				offset_y - (row as f32 + 0.5) * size,
+			);
+			if is_point_within_polygon(centre, edges, winding) {
+				if let Some(cell) =
+					map_dimensions.sector_and_cell_from_global(column as u32, row as u32)
+				{
+					cells.push(cell);
+				}
+			}
+		}
+	}
+	cells
+}
+
+/// For each mesh find the edges which outline it, translated into world
+/// space by the accompanying translation. Edges shared by two triangles are
+/// internal to the mesh and discarded. The mesh must use a
+/// [bevy::render::mesh::PrimitiveTopology::TriangleList] or
+/// [bevy::render::mesh::PrimitiveTopology::TriangleStrip]
+#[cfg(feature = "2d")]
+pub fn get_mesh_outlines(meshes: &[(&Mesh, Vec2)]) -> Vec<Vec<EdgeLine>> {
+	let mut mesh_edges = vec![];
+	for (mesh, translation) in meshes {
+		let mut outer_edges = vec![];
+		if let Some(mesh_vertices) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+			let vertex_points = mesh_vertices.as_float3().unwrap();
+			// build each edge of each triangle in the mesh represented by index points
+			let edge_indices = retrieve_mesh_edges(mesh, vertex_points);
+			if !edge_indices.is_empty() {
+				// collect edges that only belong to a single triangle (this means ignore internal edges, we only want the edges outlining the mesh), if any MeshEdge appears more than once we remove all occurances of it
+				let copy = edge_indices.clone();
+				for edge in edge_indices {
+					let mut occurances = 0;
+					for c in &copy {
+						if edge == *c {
+							occurances += 1;
+						}
+					}
+					if occurances == 1 {
+						// found outer edge
+						// store edge line
+						let start = vertex_points[edge.0];
+						let end = vertex_points[edge.1];
+						//NB: vertex points are relative to mesh so include
+						// translation of the mesh to find global position
+						let line = EdgeLine::new(
+							Vec2::new(start[0] + translation.x, start[1] + translation.y),
+							Vec2::new(end[0] + translation.x, end[1] + translation.y),
+						);
+						outer_edges.push(line);
+					}
+				}
+			}
+		}
+		mesh_edges.push(outer_edges);
+	}
+	mesh_edges
+}
+/// Represents two points that form the edge between mech vertices
+#[cfg(feature = "2d")]
+#[derive(Clone, Debug)]
+struct MeshTriEdge<T: PartialEq>(T, T);
+// custom impl so we can test whether two edges are teh same but with start and end coords swapped
+#[cfg(feature = "2d")]
+impl<T: PartialEq> PartialEq for MeshTriEdge<T> {
+	fn eq(&self, other: &Self) -> bool {
+		(self.0 == other.0 && self.1 == other.1) || (self.0 == other.1 && self.1 == other.0)
+	}
+}
+/// From a triple floating point representation of a mesh retreive a list of the edges as index pairs
+#[cfg(feature = "2d")]
+fn retrieve_mesh_edges(mesh: &&Mesh, vertex_points: &[[f32; 3]]) -> Vec<MeshTriEdge<usize>> {
+	use bevy::render::mesh::PrimitiveTopology;
+	let indices = mesh.indices().unwrap();
+	let indices_slice: Vec<usize> = indices.iter().collect();
+	let mut edge_indices = vec![];
+	match mesh.primitive_topology() {
+		PrimitiveTopology::TriangleList => {
+			for i in indices_slice.chunks(3) {
+				edge_indices.push(MeshTriEdge(i[0], i[1]));
+				edge_indices.push(MeshTriEdge(i[1], i[2]));
+				edge_indices.push(MeshTriEdge(i[2], i[0]));
+			}
+		}
+		PrimitiveTopology::TriangleStrip => {
+			if let Some(triangle_count) = vertex_points.len().checked_sub(2) {
+				for n in 0..triangle_count {
+					if n % 2 == 0 {
+						edge_indices.push(MeshTriEdge(indices_slice[n], indices_slice[n + 1]));
+						edge_indices.push(MeshTriEdge(indices_slice[n + 1], indices_slice[n + 2]));
+						edge_indices.push(MeshTriEdge(indices_slice[n + 2], indices_slice[n]));
+					} else {
+						edge_indices.push(MeshTriEdge(indices_slice[n + 1], indices_slice[n]));
+						edge_indices.push(MeshTriEdge(indices_slice[n], indices_slice[n + 2]));
+						edge_indices.push(MeshTriEdge(indices_slice[n + 2], indices_slice[n + 1]));
+					}
+				}
+			} else {
+				warn!("A TriangleStrip mesh has insufficient vertices");
+			}
+		}
+		_ => {
+			warn!("Mesh topology must be of TriangleList or TriangleStrip for use with Flowfields");
+		}
+	}
+	edge_indices
+}
+
+#[cfg(test)]
+mod tests {
+	#[cfg(feature = "2d")]
+	use bevy::render::{
+		mesh::{Indices, PrimitiveTopology},
+		render_asset::RenderAssetUsages,
+	};
+
+	use super::*;
+	/// Square outline between `min` and `max`, wound anticlockwise
+	fn square(min: Vec2, max: Vec2) -> Vec<EdgeLine> {
+		vec![
+			EdgeLine::new(min, Vec2::new(max.x, min.y)),
+			EdgeLine::new(Vec2::new(max.x, min.y), max),
+			EdgeLine::new(max, Vec2::new(min.x, max.y)),
+			EdgeLine::new(Vec2::new(min.x, max.y), min),
+		]
+	}
+	#[test]
+	fn point_within_polygon_winding() {
+		// two overlapping squares wound the same way
+		let mut edges = square(Vec2::new(-4.0, -4.0), Vec2::new(2.0, 2.0));
+		edges.extend(square(Vec2::new(-2.0, -2.0), Vec2::new(4.0, 4.0)));
+		let overlap = Vec2::ZERO;
+		let single = Vec2::new(-3.0, -3.0);
+		let outside = Vec2::new(3.0, -3.0);
+		assert!(!is_point_within_polygon(
+			overlap,
+			&edges,
+			WindingRule::EvenOdd
+		));
+		assert!(is_point_within_polygon(
+			overlap,
+			&edges,
+			WindingRule::NonZero
+		));
+		assert!(is_point_within_polygon(
+			single,
+			&edges,
+			WindingRule::EvenOdd
+		));
+		assert!(is_point_within_polygon(
+			single,
+			&edges,
+			WindingRule::NonZero
+		));
+		assert!(!is_point_within_polygon(
+			outside,
+			&edges,
+			WindingRule::EvenOdd
+		));
+		assert!(!is_point_within_polygon(
+			outside,
+			&edges,
+			WindingRule::NonZero
+		));
+	}
+	#[test]
+	fn field_cell_outline() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let outline =
+			get_field_cell_outline(&map_dimensions, SectorID::new(1, 1), FieldCell::new(0, 0));
+		assert_eq!(Vec2::new(-5.0, 5.0), outline[0].get_start());
+		assert_eq!(Vec2::new(-4.0, 4.0), outline[1].get_end());
+	}
+	#[test]
+	fn field_cell_within_polygon() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let edges = square(Vec2::new(-5.0, 2.5), Vec2::new(-2.5, 5.0));
+		let sector_id = SectorID::new(1, 1);
+		let winding = WindingRule::EvenOdd;
+		assert!(is_field_cell_within_polygon(
+			&map_dimensions,
+			sector_id,
+			FieldCell::new(1, 1),
+			&edges,
+			winding
+		));
+		// only half of the cell is covered
+		assert!(!is_field_cell_within_polygon(
+			&map_dimensions,
+			sector_id,
+			FieldCell::new(2, 1),
+			&edges,
+			winding
+		));
+		// sides touch the outline
+		assert!(!is_field_cell_within_polygon(
+			&map_dimensions,
+			sector_id,
+			FieldCell::new(0, 0),
+			&edges,
+			winding
+		));
+		assert!(!is_field_cell_within_polygon(
+			&map_dimensions,
+			sector_id,
+			FieldCell::new(5, 5),
+			&edges,
+			winding
+		));
+	}
+	#[test]
+	fn rasterise_square() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		// straddles the corner of four sectors
+		let edges = square(Vec2::new(-6.0, -6.0), Vec2::new(-4.0, -4.0));
+		let mut cells = rasterise_polygon(&map_dimensions, &edges, WindingRule::EvenOdd);
+		cells.sort();
+		let expected = vec![
+			(SectorID::new(0, 1), FieldCell::new(9, 9)),
+			(SectorID::new(0, 2), FieldCell::new(9, 0)),
+			(SectorID::new(1, 1), FieldCell::new(0, 9)),
+			(SectorID::new(1, 2), FieldCell::new(0, 0)),
+		];
+		assert_eq!(expected, cells);
+		// clipped by the edge of the world
+		let edges = square(Vec2::new(13.0, 13.0), Vec2::new(17.0, 17.0));
+		let cells = rasterise_polygon(&map_dimensions, &edges, WindingRule::EvenOdd);
+		assert_eq!(4, cells.len());
+		assert!(cells.contains(&(SectorID::new(2, 0), FieldCell::new(9, 0))));
+		let outside = square(Vec2::new(20.0, 20.0), Vec2::new(25.0, 25.0));
+		assert!(rasterise_polygon(&map_dimensions, &outside, WindingRule::EvenOdd).is_empty());
+	}
+	#[test]
+	fn intersect_para() {
+		let edge1 = EdgeLine::new(Vec2::new(0.0, 0.0), Vec2::new(3.0, 3.0));
+		let edge2 = EdgeLine::new(Vec2::new(-1.0, 0.0), Vec2::new(2.0, 3.0));
+		assert_eq!(edge1.does_intersect(&edge2), Intersection::None);
+	}
+	#[test]
+	fn intersect_yes() {
+		let edge1 = EdgeLine::new(Vec2::new(0.0, 0.0), Vec2::new(3.0, 3.0));
+		let edge2 = EdgeLine::new(Vec2::new(-1.0, 5.0), Vec2::new(3.0, 2.0));
+		assert_eq!(edge1.does_intersect(&edge2), Intersection::Intersect);
+	}
+	#[test]
+	fn intersect_yes_but_oob() {
+		let edge1 = EdgeLine::new(Vec2::new(0.0, 0.0), Vec2::new(3.0, 3.0));
+		let edge2 = EdgeLine::new(Vec2::new(-1.0, 5.0), Vec2::new(-0.5, 1.25));
+		assert_eq!(edge1.does_intersect(&edge2), Intersection::None);
+	}
+	// #[test]
+	// fn intersect_no() {
+	// 	let edge1 = EdgeLine::new(Vec2::new(0.0, 0.0), Vec2::new(3.0, 3.0));
+	// 	let edge2 = EdgeLine::new(Vec2::new(-1.0, 0.0), Vec2::new(2.0, 3.0));
+	// 	assert!(!edge1.does_intersect(&edge2))
+	// }
+	#[test]
+	#[cfg(feature = "2d")]
+	fn mesh_edges_triangle_list() {
+		let mesh = Mesh::new(
+			PrimitiveTopology::TriangleList,
+			RenderAssetUsages::default(),
+		)
+		.with_inserted_attribute(
+			Mesh::ATTRIBUTE_POSITION,
+			vec![
+				[-960.0, 640.0, 0.0],
+				[-960.0, 960.0, 0.0],
+				[700.0, 960.0, 0.0],
+				[900.0, 800.0, 0.0],
+				[700.0, 640.0, 0.0],
+			],
+		)
+		.with_inserted_indices(Indices::U32(vec![0, 1, 2, 2, 3, 4, 4, 2, 0]));
+		let mesh_vertices = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
+		let vertex_points = mesh_vertices.as_float3().unwrap();
+		let result = retrieve_mesh_edges(&&mesh, vertex_points);
+		let actual = vec![
+			MeshTriEdge(0, 1),
+			MeshTriEdge(1, 2),
+			MeshTriEdge(2, 0),
+			MeshTriEdge(2, 3),
+			MeshTriEdge(3, 4),
+			MeshTriEdge(4, 2),
+			MeshTriEdge(4, 2),
+			MeshTriEdge(2, 0),
+			MeshTriEdge(0, 4),
+		];
+		assert_eq!(actual, result);
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn mesh_edges_triangle_strip() {
+		let mesh = Mesh::new(
+			PrimitiveTopology::TriangleStrip,
+			RenderAssetUsages::default(),
+		)
+		.with_inserted_attribute(
+			Mesh::ATTRIBUTE_POSITION,
+			vec![
+				[-192.0, 640.0, 0.0],
+				[-192.0, -640.0, 0.0],
+				[192.0, 640.0, 0.0],
+				[192.0, -640.0, 0.0],
+			],
+		)
+		.with_inserted_indices(Indices::U32(vec![0, 1, 2, 3]));
+		let mesh_vertices = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
+		let vertex_points = mesh_vertices.as_float3().unwrap();
+		let result = retrieve_mesh_edges(&&mesh, vertex_points);
+		let actual = vec![
+			MeshTriEdge(0, 1),
+			MeshTriEdge(1, 2),
+			MeshTriEdge(2, 0),
+			MeshTriEdge(2, 1),
+			MeshTriEdge(1, 3),
+			MeshTriEdge(3, 2),
+		];
+		assert_eq!(actual, result);
+	}
+}
@@ -0,0 +1,134 @@
+//! Navigation data can make up a sizable part of the memory budget of a game
+//! targeting consoles or the browser. [SectorCostFields::memory_usage],
+//! [FlowFieldCache::memory_usage], [FlowFieldQueue::memory_usage] and
+//! [RouteCache::memory_usage] estimate the bytes held by each sector of a
+//! bundle, broken down by the kind of data, so that cache policies can be
+//! tuned against a budget.
+//!
+//! The estimates count the size of each stored field, its key and any heap
+//! allocations it owns. The bookkeeping of the collections themselves isn't
+//! included so the real figure is slightly higher
+//!
+
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+
+/// The kinds of navigation data memory is accounted against
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MemoryCategory {
+	/// The baseline [CostField]s of [SectorCostFields]
+	BaselineCostFields,
+	/// The [CostField]s of [SectorCostFields] scaled to the actor size
+	ScaledCostFields,
+	/// The `MetaField`s of [SectorCostFields]
+	MetaFields,
+	/// The [OneWayField]s of [SectorCostFields]
+	OneWayFields,
+	/// The [HeightField]s of [SectorCostFields]
+	HeightFields,
+	/// The [FlowField]s of a [FlowFieldCache]
+	FlowFields,
+	/// The [IntegrationField]s a [FlowFieldCache] keeps to re-validate and
+	/// share its [FlowField]s
+	IntegrationFields,
+	/// The [UserFlagField]s of a [FlowFieldCache]
+	UserFlagFields,
+	/// The partially built fields of the routes queued in a [FlowFieldQueue]
+	QueuedRoutes,
+	/// The high-level routes of a [RouteCache]
+	Routes,
+}
+
+/// Estimated bytes of navigation data held by each sector
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+	/// Bytes of each [MemoryCategory] within each sector
+	sectors: BTreeMap<SectorID, BTreeMap<MemoryCategory, usize>>,
+}
+
+impl MemoryUsage {
+	/// Create a new instance of [MemoryUsage] without any bytes recorded
+	pub fn new() -> Self {
+		MemoryUsage::default()
+	}
+	/// Record `bytes` of a `category` held by a sector
+	pub fn add(&mut self, sector_id: SectorID, category: MemoryCategory, bytes: usize) {
+		*self
+			.sectors
+			.entry(sector_id)
+			.or_default()
+			.entry(category)
+			.or_default() += bytes;
+	}
+	/// Add every byte recorded by `other`, such as combining the usage of
+	/// [SectorCostFields] and a [FlowFieldCache]
+	pub fn merge(&mut self, other: &MemoryUsage) {
+		for (sector_id, categories) in other.sectors.iter() {
+			for (category, bytes) in categories.iter() {
+				self.add(*sector_id, *category, *bytes);
+			}
+		}
+	}
+	/// Get the bytes of each [MemoryCategory] held by each sector
+	pub fn get(&self) -> &BTreeMap<SectorID, BTreeMap<MemoryCategory, usize>> {
+		&self.sectors
+	}
+	/// Get the bytes of a `category` held by a sector
+	pub fn get_bytes(&self, sector_id: SectorID, category: MemoryCategory) -> usize {
+		self.sectors
+			.get(&sector_id)
+			.and_then(|categories| categories.get(&category))
+			.copied()
+			.unwrap_or_default()
+	}
+	/// Get the bytes held by a sector
+	pub fn get_sector_total(&self, sector_id: SectorID) -> usize {
+		self.sectors
+			.get(&sector_id)
+			.map(|categories| categories.values().sum())
+			.unwrap_or_default()
+	}
+	/// Get the bytes of a `category` held across every sector
+	pub fn get_category_total(&self, category: MemoryCategory) -> usize {
+		self.sectors
+			.values()
+			.filter_map(|categories| categories.get(&category))
+			.sum()
+	}
+	/// Get the bytes held across every sector
+	pub fn get_total(&self) -> usize {
+		self.sectors
+			.values()
+			.flat_map(|categories| categories.values())
+			.sum()
+	}
+}
+
+/// Estimated bytes of a map entry, its key and value
+pub(crate) fn entry_size<K, V>() -> usize {
+	std::mem::size_of::<K>() + std::mem::size_of::<V>()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn totals() {
+		let mut usage = MemoryUsage::new();
+		usage.add(SectorID::new(0, 0), MemoryCategory::FlowFields, 100);
+		usage.add(SectorID::new(0, 0), MemoryCategory::FlowFields, 50);
+		usage.add(SectorID::new(0, 0), MemoryCategory::QueuedRoutes, 10);
+		let mut other = MemoryUsage::new();
+		other.add(SectorID::new(1, 0), MemoryCategory::FlowFields, 20);
+		usage.merge(&other);
+		assert_eq!(
+			150,
+			usage.get_bytes(SectorID::new(0, 0), MemoryCategory::FlowFields)
+		);
+		assert_eq!(160, usage.get_sector_total(SectorID::new(0, 0)));
+		assert_eq!(170, usage.get_category_total(MemoryCategory::FlowFields));
+		assert_eq!(180, usage.get_total());
+		assert_eq!(0, usage.get_sector_total(SectorID::new(5, 5)));
+	}
+}
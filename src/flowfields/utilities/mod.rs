@@ -1,6 +1,9 @@
 //! Useful structures and tools used by the fields
 //!
 
+pub mod geometry;
+pub mod memory;
+
 use crate::prelude::*;
 use bevy::prelude::*;
 
@@ -25,7 +28,41 @@ pub enum Ordinal {
 	Zero,
 }
 
+/// Defines which neighbouring field cells an actor may move between
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Component, Debug, PartialEq, Clone, Copy, Reflect, Default, Eq, Hash)]
+#[reflect(Component)]
+pub enum Connectivity {
+	/// Movement is restricted to the orthogonal directions, Line Of Sight
+	/// is not calculated as an actor cannot move diagonally towards the goal
+	FourWay,
+	/// Movement is allowed in the orthogonal and diagonal directions
+	#[default]
+	EightWay,
+}
+
+impl Connectivity {
+	/// Whether movement in the direction of `ordinal` is permitted
+	pub fn allows(&self, ordinal: Ordinal) -> bool {
+		match self {
+			Connectivity::FourWay => matches!(
+				ordinal,
+				Ordinal::North | Ordinal::East | Ordinal::South | Ordinal::West
+			),
+			Connectivity::EightWay => ordinal != Ordinal::Zero,
+		}
+	}
+}
+
 impl Ordinal {
+	/// Based on a field cells `(column, row)` position find the neighbours
+	/// which can be moved to under the `connectivity`
+	pub fn get_cell_neighbours(cell_id: FieldCell, connectivity: Connectivity) -> Vec<FieldCell> {
+		match connectivity {
+			Connectivity::FourWay => Ordinal::get_orthogonal_cell_neighbours(cell_id),
+			Connectivity::EightWay => Ordinal::get_all_cell_neighbours(cell_id),
+		}
+	}
 	/// Based on a field cells `(column, row)` position find its neighbours based on FIELD_RESOLUTION limits (up to 4)
 	pub fn get_orthogonal_cell_neighbours(cell_id: FieldCell) -> Vec<FieldCell> {
 		let row = cell_id.get_row();
@@ -224,6 +261,17 @@ impl Ordinal {
 		}
 		neighbours
 	}
+	/// Based on a field cells `(column, row)` position find the neighbours
+	/// which can be moved to under the `connectivity` and the Ordinal they
+	/// are found in
+	pub fn get_cell_neighbours_with_ordinal(
+		cell_id: FieldCell,
+		connectivity: Connectivity,
+	) -> Vec<(Ordinal, FieldCell)> {
+		let mut neighbours = Ordinal::get_all_cell_neighbours_with_ordinal(cell_id);
+		neighbours.retain(|(ordinal, _)| connectivity.allows(*ordinal));
+		neighbours
+	}
 	/// Based on a sectors `(column, row)` position find its neighbours based on map size limits (up to 4)
 	/// ```txt
 	/// top left                     // top right
@@ -274,34 +322,8 @@ impl Ordinal {
 		map_depth: u32,
 		sector_resolution: u32,
 	) -> Vec<SectorID> {
-		let mut neighbours = Vec::new();
-		let sector_column_limit = map_length / sector_resolution - 1;
-		let sector_row_limit = map_depth / sector_resolution - 1;
-		if sector_id.get_row() > 0 {
-			neighbours.push(SectorID::new(
-				sector_id.get_column(),
-				sector_id.get_row() - 1,
-			)); // northern sector coords
-		}
-		if sector_id.get_column() < sector_column_limit {
-			neighbours.push(SectorID::new(
-				sector_id.get_column() + 1,
-				sector_id.get_row(),
-			)); // eastern sector coords
-		}
-		if sector_id.get_row() < sector_row_limit {
-			neighbours.push(SectorID::new(
-				sector_id.get_column(),
-				sector_id.get_row() + 1,
-			)); // southern sector coords
-		}
-		if sector_id.get_column() > 0 {
-			neighbours.push(SectorID::new(
-				sector_id.get_column() - 1,
-				sector_id.get_row(),
-			)); // western sector coords
-		}
-		neighbours
+		SectorGrid::from_dimensions(map_length, map_depth, sector_resolution)
+			.orthogonal_neighbours(sector_id)
 	}
 	/// Based on a sectors `(column, row)` position find the [Ordinal] directions for its boundaries that can support [crate::prelude::Portals]
 	pub fn get_sector_portal_ordinals(
@@ -310,22 +332,8 @@ impl Ordinal {
 		map_depth: u32,
 		sector_resolution: u32,
 	) -> Vec<Ordinal> {
-		let mut neighbours = Vec::new();
-		let sector_column_limit = map_length / sector_resolution - 1;
-		let sector_row_limit = map_depth / sector_resolution - 1;
-		if sector_id.get_row() > 0 {
-			neighbours.push(Ordinal::North); // northern sector coords
-		}
-		if sector_id.get_column() < sector_column_limit {
-			neighbours.push(Ordinal::East); // eastern sector coords
-		}
-		if sector_id.get_row() < sector_row_limit {
-			neighbours.push(Ordinal::South); // southern sector coords
-		}
-		if sector_id.get_column() > 0 {
-			neighbours.push(Ordinal::West); // western sector coords
-		}
-		neighbours
+		SectorGrid::from_dimensions(map_length, map_depth, sector_resolution)
+			.portal_ordinals(sector_id)
 	}
 	/// Based on a sectors `(column, row)` position find its neighbours based on map size limits (up to 4) and include the [Ordinal] direction in the result
 	/// ```txt
@@ -377,34 +385,8 @@ impl Ordinal {
 		map_z_dimension: u32,
 		sector_resolution: u32,
 	) -> Vec<(Ordinal, SectorID)> {
-		let mut neighbours = Vec::new();
-		let sector_x_column_limit = map_x_dimension / sector_resolution - 1;
-		let sector_z_row_limit = map_z_dimension / sector_resolution - 1;
-		if sector_id.get_row() > 0 {
-			neighbours.push((
-				Ordinal::North,
-				SectorID::new(sector_id.get_column(), sector_id.get_row() - 1),
-			)); // northern sector coords
-		}
-		if sector_id.get_column() < sector_x_column_limit {
-			neighbours.push((
-				Ordinal::East,
-				SectorID::new(sector_id.get_column() + 1, sector_id.get_row()),
-			)); // eastern sector coords
-		}
-		if sector_id.get_row() < sector_z_row_limit {
-			neighbours.push((
-				Ordinal::South,
-				SectorID::new(sector_id.get_column(), sector_id.get_row() + 1),
-			)); // southern sector coords
-		}
-		if sector_id.get_column() > 0 {
-			neighbours.push((
-				Ordinal::West,
-				SectorID::new(sector_id.get_column() - 1, sector_id.get_row()),
-			)); // western sector coords
-		}
-		neighbours
+		SectorGrid::from_dimensions(map_x_dimension, map_z_dimension, sector_resolution)
+			.orthogonal_neighbours_with_ordinal(sector_id)
 	}
 	/// Returns the opposite [Ordinal] of the current
 	pub fn inverse(&self) -> Ordinal {
@@ -739,4 +721,16 @@ mod tests {
 		];
 		assert_eq!(actual, result)
 	}
+	#[test]
+	fn neighbours_four_way() {
+		let field = FieldCell::new(0, 0);
+		let result = Ordinal::get_cell_neighbours_with_ordinal(field, Connectivity::FourWay);
+		let actual = vec![
+			(Ordinal::East, FieldCell::new(1, 0)),
+			(Ordinal::South, FieldCell::new(0, 1)),
+		];
+		assert_eq!(actual, result);
+		let result = Ordinal::get_cell_neighbours(field, Connectivity::EightWay);
+		assert_eq!(3, result.len());
+	}
 }
@@ -0,0 +1,252 @@
+//! Checks that the navigation data of a bundle is consistent, such as every
+//! portal having a partner on the other side of its sector boundary and the
+//! [PortalGraph] only referring to portals which still exist. A broken
+//! invariant usually surfaces much later as an odd route, so checking the
+//! data after each change pinpoints the edit which caused it.
+//!
+//! [validate_navigation] can be called directly, for instance from a test
+//! of a map pipeline, and with the `validation` feature the plugin checks
+//! each bundle whenever its data changes and logs any problem found
+//!
+
+use std::collections::BTreeSet;
+
+use crate::prelude::*;
+
+/// A broken invariant of the navigation data of a bundle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NavigationViolation {
+	/// A sector has a baseline [CostField] but no scaled one
+	MissingScaledField(SectorID),
+	/// A sector has a scaled [CostField] but no baseline one
+	MissingBaselineField(SectorID),
+	/// A sector of the map has no [Portals]
+	MissingPortals(SectorID),
+	/// A portal has no partner portal in the neighbouring sector
+	UnmatchedPortal {
+		/// Sector of the portal
+		sector_id: SectorID,
+		/// [FieldCell] of the portal
+		field_cell: FieldCell,
+		/// The boundary of the sector the portal lies on
+		ordinal: Ordinal,
+	},
+	/// A portal has no node in the [PortalGraph]
+	MissingNode(SectorID, FieldCell),
+	/// A node of the [PortalGraph] doesn't correspond to a current portal
+	StaleNode(SectorID, FieldCell),
+	/// An edge of the [PortalGraph] refers to a node which isn't in the graph
+	DanglingEdge {
+		/// Start of the edge
+		from: (SectorID, FieldCell),
+		/// End of the edge
+		to: (SectorID, FieldCell),
+	},
+	/// An edge of the [PortalGraph] joins portals of sectors which aren't
+	/// the same (for an internal edge) or aren't neighbours (for an external
+	/// edge)
+	MisplacedEdge {
+		/// Start of the edge
+		from: (SectorID, FieldCell),
+		/// End of the edge
+		to: (SectorID, FieldCell),
+	},
+}
+
+impl std::fmt::Display for NavigationViolation {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			NavigationViolation::MissingScaledField(sector_id) => {
+				write!(f, "Sector {:?} has no scaled CostField", sector_id)
+			}
+			NavigationViolation::MissingBaselineField(sector_id) => {
+				write!(f, "Sector {:?} has no baseline CostField", sector_id)
+			}
+			NavigationViolation::MissingPortals(sector_id) => {
+				write!(f, "Sector {:?} has no Portals", sector_id)
+			}
+			NavigationViolation::UnmatchedPortal {
+				sector_id,
+				field_cell,
+				ordinal,
+			} => write!(
+				f,
+				"Portal {:?} on the {:?} boundary of sector {:?} has no partner in the neighbouring sector",
+				field_cell, ordinal, sector_id
+			),
+			NavigationViolation::MissingNode(sector_id, field_cell) => write!(
+				f,
+				"Portal {:?} of sector {:?} has no node in the PortalGraph",
+				field_cell, sector_id
+			),
+			NavigationViolation::StaleNode(sector_id, field_cell) => write!(
+				f,
+				"PortalGraph node {:?} of sector {:?} is not a current portal",
+				field_cell, sector_id
+			),
+			NavigationViolation::DanglingEdge { from, to } => write!(
+				f,
+				"PortalGraph edge from {:?} to {:?} refers to a node which isn't in the graph",
+				from, to
+			),
+			NavigationViolation::MisplacedEdge { from, to } => write!(
+				f,
+				"PortalGraph edge from {:?} to {:?} joins the wrong sectors",
+				from, to
+			),
+		}
+	}
+}
+
+impl std::error::Error for NavigationViolation {}
+
+/// Check the invariants of the navigation data of a bundle, returning every
+/// violation found
+pub fn validate_navigation(
+	sector_cost_fields: &SectorCostFields,
+	sector_portals: &SectorPortals,
+	portal_graph: &PortalGraph,
+	map_dimensions: &MapDimensions,
+) -> Vec<NavigationViolation> {
+	let mut violations = Vec::new();
+	let baseline = sector_cost_fields.get_baseline();
+	let scaled = sector_cost_fields.get_scaled();
+	for sector_id in baseline.keys() {
+		if !scaled.contains_key(sector_id) {
+			violations.push(NavigationViolation::MissingScaledField(*sector_id));
+		}
+	}
+	for sector_id in scaled.keys() {
+		if !baseline.contains_key(sector_id) {
+			violations.push(NavigationViolation::MissingBaselineField(*sector_id));
+		}
+	}
+	// every portal has a partner mirrored across its boundary
+	let ordinals = [Ordinal::North, Ordinal::East, Ordinal::South, Ordinal::West];
+	let mut portal_cells = BTreeSet::new();
+	let grid = map_dimensions.get_sector_grid();
+	let sector_ids = (0..grid.get_columns())
+		.flat_map(|m| (0..grid.get_rows()).map(move |n| SectorID::new(m, n)));
+	for sector_id in sector_ids {
+		let Some(portals) = sector_portals.get().get(&sector_id) else {
+			violations.push(NavigationViolation::MissingPortals(sector_id));
+			continue;
+		};
+		for ordinal in ordinals.iter() {
+			for field_cell in portals.get(ordinal).iter() {
+				portal_cells.insert((sector_id, *field_cell));
+				let is_matched = map_dimensions
+					.get_sector_id_from_ordinal(*ordinal, &sector_id)
+					.and_then(|neighbour| sector_portals.get().get(&neighbour))
+					.is_some_and(|neighbour_portals| {
+						neighbour_portals
+							.get(&ordinal.inverse())
+							.contains(&mirror(*field_cell, *ordinal))
+					});
+				if !is_matched {
+					violations.push(NavigationViolation::UnmatchedPortal {
+						sector_id,
+						field_cell: *field_cell,
+						ordinal: *ordinal,
+					});
+				}
+			}
+		}
+	}
+	// the graph matches the portals
+	let nodes: BTreeSet<(SectorID, FieldCell)> =
+		portal_graph.get_portal_nodes().into_iter().collect();
+	for (sector_id, field_cell) in portal_cells.difference(&nodes) {
+		violations.push(NavigationViolation::MissingNode(*sector_id, *field_cell));
+	}
+	for (sector_id, field_cell) in nodes.difference(&portal_cells) {
+		violations.push(NavigationViolation::StaleNode(*sector_id, *field_cell));
+	}
+	let internal = portal_graph.get_internal_connections();
+	let external = portal_graph.get_external_connections();
+	for (is_internal, connections) in [(true, internal), (false, external)] {
+		for (from, to) in connections {
+			if !nodes.contains(&from) || !nodes.contains(&to) {
+				violations.push(NavigationViolation::DanglingEdge { from, to });
+				continue;
+			}
+			let is_placed = if is_internal {
+				from.0 == to.0
+			} else {
+				map_dimensions
+					.get_ids_of_neighbouring_sectors(&from.0)
+					.contains(&to.0)
+			};
+			if !is_placed {
+				violations.push(NavigationViolation::MisplacedEdge { from, to });
+			}
+		}
+	}
+	violations
+}
+
+/// Get the [FieldCell] on the other side of a sector boundary from a portal
+/// on the `ordinal` boundary of its sector
+fn mirror(field_cell: FieldCell, ordinal: Ordinal) -> FieldCell {
+	let (column, row) = field_cell.get_column_row();
+	match ordinal {
+		Ordinal::North | Ordinal::South => FieldCell::new(column, FIELD_RESOLUTION - 1 - row),
+		_ => FieldCell::new(FIELD_RESOLUTION - 1 - column, row),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	#[cfg(feature = "ron")]
+	fn consistent_bundle_has_no_violations() {
+		let path = env!("CARGO_MANIFEST_DIR").to_string()
+			+ "/assets/sector_cost_fields_continuous_layout.ron";
+		let bundle = FlowFieldTilesBundle::from_ron(30, 30, 10, 0.5, &path);
+		let violations = validate_navigation(
+			bundle.get_sector_cost_fields(),
+			bundle.get_sector_portals(),
+			bundle.get_portal_graph(),
+			bundle.get_map_dimensions(),
+		);
+		assert_eq!(Vec::<NavigationViolation>::new(), violations);
+	}
+	#[test]
+	fn stale_graph_is_reported() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(30, 30, 10);
+		for (sector_id, portals) in sector_portals.get_mut().iter_mut() {
+			portals.recalculate_portals(&sector_cost_fields, sector_id, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		// wall off part of a boundary without updating the portals or graph
+		let sector_id = SectorID::new(0, 0);
+		for row in 0..5 {
+			sector_cost_fields.set_field_cell_value(
+				sector_id,
+				255,
+				FieldCell::new(9, row),
+				&map_dimensions,
+			);
+		}
+		for sector_id in [SectorID::new(0, 0), SectorID::new(1, 0)] {
+			if let Some(portals) = sector_portals.get_mut().get_mut(&sector_id) {
+				portals.recalculate_portals(&sector_cost_fields, &sector_id, &map_dimensions);
+			}
+		}
+		let violations = validate_navigation(
+			&sector_cost_fields,
+			&sector_portals,
+			&graph,
+			&map_dimensions,
+		);
+		assert!(violations
+			.iter()
+			.any(|v| matches!(v, NavigationViolation::StaleNode(..))));
+		assert!(violations
+			.iter()
+			.any(|v| matches!(v, NavigationViolation::MissingNode(..))));
+	}
+}
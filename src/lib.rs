@@ -1,8 +1,11 @@
 //! This is a plugin for Bevy game engine to setup and handle the logic for calculating pathfinding FlowFields
 //!
 
+pub mod bench_fixtures;
 pub mod bundle;
 pub mod flowfields;
 pub mod plugin;
+#[cfg(any(test, feature = "scenario"))]
+pub mod scenario;
 
 pub mod prelude;
@@ -0,0 +1,386 @@
+//! Logic for spreading a group of actors heading to the same goal across
+//! distinct nearby [FieldCell]s so that they don't all fight over the goal
+//! on arrival
+//!
+
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Records which actor occupies which arrival slot within a
+/// [FlowFieldTilesBundle]
+#[derive(Component, Default, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct ArrivalSlots {
+	/// The `(sector, field_cell)` slot assigned to each actor
+	slots: BTreeMap<Entity, (SectorID, FieldCell)>,
+}
+
+impl ArrivalSlots {
+	/// Get the map of actors and their slots
+	pub fn get(&self) -> &BTreeMap<Entity, (SectorID, FieldCell)> {
+		&self.slots
+	}
+	/// Get the slot of an `actor` if it has one
+	pub fn get_slot(&self, actor: Entity) -> Option<(SectorID, FieldCell)> {
+		self.slots.get(&actor).copied()
+	}
+	/// Is the [FieldCell] of a sector assigned to an actor
+	pub fn is_occupied(&self, sector_id: SectorID, field_cell: FieldCell) -> bool {
+		self.slots
+			.values()
+			.any(|slot| *slot == (sector_id, field_cell))
+	}
+	/// Free the slot of an `actor`
+	pub fn release(&mut self, actor: Entity) {
+		self.slots.remove(&actor);
+	}
+	/// Assign each of the `actors` a distinct pathable [FieldCell] around
+	/// the `goal`. Cells are searched in an expanding spiral from the goal and
+	/// only cells which are reachable from the goal and not already occupied
+	/// are used, so the first actor receives the goal itself if it's free.
+	/// Any slots previously held by the `actors` are released first.
+	///
+	/// Returns the actors paired with their slot, if the area around the goal
+	/// cannot fit every actor then the remaining actors are not assigned a
+	/// slot
+	pub fn assign(
+		&mut self,
+		goal: (SectorID, FieldCell),
+		actors: &[Entity],
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) -> Vec<(Entity, (SectorID, FieldCell))> {
+		for actor in actors.iter() {
+			self.release(*actor);
+		}
+		let mut assigned = Vec::new();
+		let columns = map_dimensions.get_total_field_cell_columns() as i64;
+		let rows = map_dimensions.get_total_field_cell_rows() as i64;
+		let (goal_column, goal_row) = map_dimensions.global_cell_from(goal.0, goal.1);
+		let (goal_column, goal_row) = (goal_column as i64, goal_row as i64);
+		if goal_column >= columns || goal_row >= rows {
+			return assigned;
+		}
+		let reachable = flood_reachable(goal, sector_cost_fields, map_dimensions);
+		let mut remaining = actors.iter();
+		let Some(mut actor) = remaining.next() else {
+			return assigned;
+		};
+		for radius in 0..columns.max(rows) {
+			for (column, row) in get_spiral_ring(goal_column, goal_row, radius) {
+				if column < 0 || row < 0 || column >= columns || row >= rows {
+					continue;
+				}
+				if !reachable[(row * columns + column) as usize] {
+					continue;
+				}
+				let Some((sector_id, field_cell)) =
+					map_dimensions.sector_and_cell_from_global(column as u32, row as u32)
+				else {
+					continue;
+				};
+				let is_pathable = sector_cost_fields
+					.get_scaled()
+					.get(&sector_id)
+					.is_some_and(|cost_field| cost_field.get_field_cell_value(field_cell) != 255);
+				if !is_pathable || self.is_occupied(sector_id, field_cell) {
+					continue;
+				}
+				self.slots.insert(*actor, (sector_id, field_cell));
+				assigned.push((*actor, (sector_id, field_cell)));
+				match remaining.next() {
+					Some(next) => actor = next,
+					None => return assigned,
+				}
+			}
+		}
+		assigned
+	}
+}
+
+/// Get the global `(column, row)` cells forming a square ring of `radius`
+/// around a centre cell, ordered by how close they are to the centre so that
+/// an expanding search fills in a rounded spiral
+fn get_spiral_ring(column: i64, row: i64, radius: i64) -> Vec<(i64, i64)> {
+	if radius == 0 {
+		return vec![(column, row)];
+	}
+	let mut ring = Vec::with_capacity((radius * 8) as usize);
+	for offset in -radius..=radius {
+		// top and bottom edges
+		ring.push((column + offset, row - radius));
+		ring.push((column + offset, row + radius));
+	}
+	for offset in (-radius + 1)..radius {
+		// left and right edges
+		ring.push((column - radius, row + offset));
+		ring.push((column + radius, row + offset));
+	}
+	ring.sort_by_key(|(c, r)| (c - column).pow(2) + (r - row).pow(2));
+	ring
+}
+
+/// The `(sector, field_cell)` slot of an actor keyed by the bundle entity
+/// and the shared goal of its group
+pub type SlotsByGoal = BTreeMap<(Entity, (SectorID, FieldCell)), (SectorID, FieldCell)>;
+
+/// The arrival slots an actor has been assigned, keyed by the bundle entity
+/// each slot belongs to and the shared goal of its group. The actor should
+/// treat the slot as its goal rather than the shared goal of its group.
+///
+/// An actor holds at most one slot per bundle, being assigned a new slot in
+/// a bundle replaces the previous one. Removing this component (or
+/// despawning the actor) releases the slots so that they can be assigned to
+/// other actors
+#[derive(Component, Default, Clone, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct ArrivalSlot {
+	/// The cell the actor should settle in keyed by `(nav_world, goal)`
+	slots: SlotsByGoal,
+}
+
+impl ArrivalSlot {
+	/// Get the map of `(nav_world, goal)` and the slot assigned for it
+	pub fn get(&self) -> &SlotsByGoal {
+		&self.slots
+	}
+	/// Get the `(sector, field_cell)` slot assigned within the bundle of the
+	/// `nav_world` entity for the shared `goal`
+	pub fn get_slot(
+		&self,
+		nav_world: Entity,
+		goal: (SectorID, FieldCell),
+	) -> Option<(SectorID, FieldCell)> {
+		self.slots.get(&(nav_world, goal)).copied()
+	}
+	/// Record the `slot` assigned within the bundle of the `nav_world` entity
+	/// for the shared `goal`, replacing any slot previously held in that
+	/// bundle
+	pub fn insert(
+		&mut self,
+		nav_world: Entity,
+		goal: (SectorID, FieldCell),
+		slot: (SectorID, FieldCell),
+	) {
+		self.slots.retain(|(nav, _), _| *nav != nav_world);
+		self.slots.insert((nav_world, goal), slot);
+	}
+}
+
+/// A request for a group of actors to path to the same goal where each actor
+/// is given its own [ArrivalSlot] near the goal and a path request is sent
+/// on its behalf to that slot
+#[derive(Event, Clone)]
+pub struct EventArrivalSlotsRequest {
+	/// The sector of the shared goal
+	target_sector: SectorID,
+	/// The field cell of the shared goal
+	target_goal: FieldCell,
+	/// Each actor alongside its starting sector and field cell
+	actors: Vec<(Entity, SectorID, FieldCell)>,
+	/// The bundle entity to path within, if [None] then slots are assigned
+	/// in every bundle
+	nav_world: Option<Entity>,
+	/// How urgently the path requests should be serviced
+	priority: PathPriority,
+}
+
+impl EventArrivalSlotsRequest {
+	/// Create a new request for actors to gather at the target
+	pub fn new(target_sector: SectorID, target_goal: FieldCell) -> Self {
+		EventArrivalSlotsRequest {
+			target_sector,
+			target_goal,
+			actors: Vec::new(),
+			nav_world: None,
+			priority: PathPriority::default(),
+		}
+	}
+	/// Add an `actor` starting from the source sector and field cell to the
+	/// group, actors listed first are given the slots closest to the goal
+	pub fn with_actor(
+		mut self,
+		actor: Entity,
+		source_sector: SectorID,
+		source_field_cell: FieldCell,
+	) -> Self {
+		self.actors.push((actor, source_sector, source_field_cell));
+		self
+	}
+	/// Only assign slots within the bundle of the `nav_world` entity
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	/// Set the [PathPriority] of the path requests sent for each actor
+	pub fn with_priority(mut self, priority: PathPriority) -> Self {
+		self.priority = priority;
+		self
+	}
+}
+
+/// Assign [ArrivalSlot]s to the actors of each [EventArrivalSlotsRequest]
+/// and request a path for each actor to its slot
+#[cfg(not(tarpaulin_include))]
+pub fn assign_arrival_slots(
+	mut events: EventReader<EventArrivalSlotsRequest>,
+	mut query: Query<(Entity, &mut ArrivalSlots, &SectorCostFields, &MapDimensions)>,
+	mut actor_q: Query<&mut ArrivalSlot>,
+	mut commands: Commands,
+	mut event_path_request: EventWriter<EventPathRequest>,
+) {
+	// an actor may be given slots in several bundles or by several events,
+	// collect them so that one doesn't overwrite another
+	let mut actor_slots: BTreeMap<Entity, ArrivalSlot> = BTreeMap::new();
+	for event in events.read() {
+		let goal = (event.target_sector, event.target_goal);
+		let actors: Vec<Entity> = event.actors.iter().map(|(actor, _, _)| *actor).collect();
+		for (entity, mut arrival_slots, sector_cost_fields, map_dimensions) in query.iter_mut() {
+			if event.nav_world.is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			let assigned = arrival_slots.assign(goal, &actors, sector_cost_fields, map_dimensions);
+			if assigned.len() < actors.len() {
+				warn!(
+					"Only {} of {} actors could be given an arrival slot around {:?}",
+					assigned.len(),
+					actors.len(),
+					goal
+				);
+			}
+			for (actor, slot) in assigned.iter() {
+				let Some((_, source_sector, source_field_cell)) =
+					event.actors.iter().find(|(a, _, _)| a == actor)
+				else {
+					continue;
+				};
+				actor_slots
+					.entry(*actor)
+					.or_insert_with(|| actor_q.get(*actor).cloned().unwrap_or_default())
+					.insert(entity, goal, *slot);
+				event_path_request.send(
+					EventPathRequest::new(*source_sector, *source_field_cell, slot.0, slot.1)
+						.with_nav_world(entity)
+						.with_priority(event.priority),
+				);
+			}
+		}
+	}
+	for (actor, slots) in actor_slots {
+		if let Ok(mut arrival_slot) = actor_q.get_mut(actor) {
+			*arrival_slot = slots;
+		} else if let Some(mut actor_commands) = commands.get_entity(actor) {
+			actor_commands.try_insert(slots);
+		}
+	}
+}
+
+/// Free the slots of actors which have had their [ArrivalSlot] removed or
+/// have been despawned
+#[cfg(not(tarpaulin_include))]
+pub fn release_arrival_slots(
+	mut removed: RemovedComponents<ArrivalSlot>,
+	mut query: Query<&mut ArrivalSlots>,
+) {
+	for actor in removed.read() {
+		for mut arrival_slots in query.iter_mut() {
+			if arrival_slots.get_slot(actor).is_some() {
+				arrival_slots.release(actor);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn assign_distinct_slots() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut arrival_slots = ArrivalSlots::default();
+		let goal = (SectorID::new(1, 1), FieldCell::new(5, 5));
+		let actors: Vec<Entity> = (0..5).map(Entity::from_raw).collect();
+		let result = arrival_slots.assign(goal, &actors, &cost_fields, &map_dimensions);
+		assert_eq!(5, result.len());
+		assert_eq!((actors[0], goal), result[0]);
+		// the goal and its orthogonal neighbours are the closest cells
+		let mut cells: Vec<FieldCell> = result.iter().map(|(_, (_, cell))| *cell).collect();
+		cells.sort();
+		let actual = vec![
+			FieldCell::new(4, 5),
+			FieldCell::new(5, 4),
+			FieldCell::new(5, 5),
+			FieldCell::new(5, 6),
+			FieldCell::new(6, 5),
+		];
+		assert_eq!(actual, cells);
+	}
+	#[test]
+	fn slots_keyed_by_nav_world_and_goal() {
+		let mut app = App::new();
+		app.add_event::<EventArrivalSlotsRequest>()
+			.add_event::<EventPathRequest>()
+			.add_systems(Update, assign_arrival_slots);
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let bundles: Vec<Entity> = (0..2)
+			.map(|_| {
+				app.world_mut()
+					.spawn((ArrivalSlots::default(), cost_fields.clone(), map_dimensions))
+					.id()
+			})
+			.collect();
+		let actor = app.world_mut().spawn_empty().id();
+		let goal = (SectorID::new(1, 1), FieldCell::new(5, 5));
+		// both bundles share the goal coordinates
+		app.world_mut()
+			.send_event(EventArrivalSlotsRequest::new(goal.0, goal.1).with_actor(
+				actor,
+				SectorID::new(0, 0),
+				FieldCell::new(0, 0),
+			));
+		app.update();
+		let arrival_slot = app.world().get::<ArrivalSlot>(actor).unwrap();
+		assert_eq!(2, arrival_slot.get().len());
+		for bundle in bundles.iter() {
+			assert_eq!(Some(goal), arrival_slot.get_slot(*bundle, goal));
+		}
+		// a new goal in one bundle replaces only the slot of that bundle
+		let other_goal = (SectorID::new(0, 0), FieldCell::new(2, 2));
+		app.world_mut().send_event(
+			EventArrivalSlotsRequest::new(other_goal.0, other_goal.1)
+				.with_actor(actor, SectorID::new(0, 0), FieldCell::new(0, 0))
+				.with_nav_world(bundles[1]),
+		);
+		app.update();
+		let arrival_slot = app.world().get::<ArrivalSlot>(actor).unwrap();
+		assert_eq!(Some(goal), arrival_slot.get_slot(bundles[0], goal));
+		assert_eq!(None, arrival_slot.get_slot(bundles[1], goal));
+		assert_eq!(
+			Some(other_goal),
+			arrival_slot.get_slot(bundles[1], other_goal)
+		);
+	}
+	#[test]
+	fn assign_skips_impassable_and_occupied() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(0, 0);
+		cost_fields.set_field_cell_value(sector_id, 255, FieldCell::new(1, 0), &map_dimensions);
+		let mut arrival_slots = ArrivalSlots::default();
+		let goal = (sector_id, FieldCell::new(0, 0));
+		let first = Entity::from_raw(1);
+		arrival_slots.assign(goal, &[first], &cost_fields, &map_dimensions);
+		let second = Entity::from_raw(2);
+		let result = arrival_slots.assign(goal, &[second], &cost_fields, &map_dimensions);
+		assert_eq!(vec![(second, (sector_id, FieldCell::new(0, 1)))], result);
+		// releasing the goal allows it to be reused
+		arrival_slots.release(first);
+		let third = Entity::from_raw(3);
+		let result = arrival_slots.assign(goal, &[third], &cost_fields, &map_dimensions);
+		assert_eq!(vec![(third, goal)], result);
+	}
+}
@@ -0,0 +1,326 @@
+//! Builds a [FlowFieldTilesBundle] within a task of the
+//! [AsyncComputeTaskPool] so that loading a large map (scaling its
+//! [CostField]s, calculating the portals and building the [PortalGraph])
+//! doesn't block the app for several seconds. The progress of the task is
+//! published each frame and the components are inserted once it finishes.
+//! On wasm tasks share the main thread with the app, so the task hands the
+//! frame back between chunks of work rather than running to completion
+//!
+
+use std::sync::{Arc, Mutex};
+#[cfg(target_arch = "wasm32")]
+use std::{
+	future::Future,
+	pin::Pin,
+	task::{Context, Poll, Waker},
+};
+
+use crate::prelude::*;
+use bevy::{
+	prelude::*,
+	tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+
+/// The stages of building a [FlowFieldTilesBundle], in the order they happen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Reflect)]
+pub enum BundleBuildStage {
+	/// Loading and scaling the [CostField]s
+	#[default]
+	CostFields,
+	/// Calculating the [Portals] of each sector
+	Portals,
+	/// Building the [PortalGraph]
+	PortalGraph,
+}
+
+/// How many sectors have their portals calculated before a task on wasm
+/// hands the frame back to the app
+const SECTORS_PER_CHUNK: usize = 64;
+
+/// The [BundleBuildStage] a task is working on and the fraction of it done,
+/// shared between the task and the app
+#[derive(Clone, Default)]
+struct SharedProgress {
+	/// The current stage and fraction
+	progress: Arc<Mutex<(BundleBuildStage, f32)>>,
+	/// Wakes a task waiting for the next frame
+	#[cfg(target_arch = "wasm32")]
+	waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl SharedProgress {
+	/// Get the current stage and fraction
+	fn get(&self) -> (BundleBuildStage, f32) {
+		self.progress
+			.lock()
+			.map(|progress| *progress)
+			.unwrap_or_default()
+	}
+	/// Record the current stage and fraction
+	fn set(&self, stage: BundleBuildStage, fraction: f32) {
+		if let Ok(mut progress) = self.progress.lock() {
+			*progress = (stage, fraction);
+		}
+	}
+	/// Wait until the app next polls the task, on wasm this ends the chunk
+	/// of work being done within the current frame. Elsewhere the task has
+	/// its own thread and carries straight on
+	async fn next_frame(&self) {
+		#[cfg(target_arch = "wasm32")]
+		NextFrame {
+			waker: self.waker.clone(),
+			is_waiting: false,
+		}
+		.await;
+	}
+	/// Resume a task waiting for the next frame
+	fn wake_task(&self) {
+		#[cfg(target_arch = "wasm32")]
+		if let Some(waker) = self.waker.lock().ok().and_then(|mut waker| waker.take()) {
+			waker.wake();
+		}
+	}
+}
+
+/// Resolves once [SharedProgress::wake_task] is called from a later frame
+#[cfg(target_arch = "wasm32")]
+struct NextFrame {
+	/// Shared with the app so that it can wake the task
+	waker: Arc<Mutex<Option<Waker>>>,
+	/// Whether the waker has been handed over
+	is_waiting: bool,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Future for NextFrame {
+	type Output = ();
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+		if self.is_waiting {
+			return Poll::Ready(());
+		}
+		self.is_waiting = true;
+		if let Ok(mut waker) = self.waker.lock() {
+			*waker = Some(cx.waker().clone());
+		}
+		Poll::Pending
+	}
+}
+
+/// Build the bundle of the `builder` a chunk of work at a time, publishing
+/// the progress to `progress` and waiting for the next frame between chunks
+async fn build_in_chunks(
+	mut builder: FlowFieldTilesBundleBuilder,
+	progress: SharedProgress,
+) -> Result<FlowFieldTilesBundle, BuildErrorReport> {
+	let (map_dimensions, cost_fields) =
+		builder.build_cost_fields(&mut |stage, fraction| progress.set(stage, fraction))?;
+	progress.next_frame().await;
+	let mut portals = SectorPortals::new(
+		map_dimensions.get_length(),
+		map_dimensions.get_depth(),
+		map_dimensions.get_sector_resolution(),
+	);
+	// a single sector has no neighbours to build portals or a graph for
+	let boundary_distance = builder.get_portal_boundary_distance();
+	let graph = if map_dimensions.is_single_sector() {
+		let mut graph = PortalGraph::default();
+		graph.set_boundary_distance(boundary_distance);
+		graph
+	} else {
+		let sector_count = cost_fields.get_scaled().len();
+		for (i, sector_id) in cost_fields.get_scaled().keys().enumerate() {
+			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+			progress.set(
+				BundleBuildStage::Portals,
+				(i + 1) as f32 / sector_count as f32,
+			);
+			if (i + 1) % SECTORS_PER_CHUNK == 0 {
+				progress.next_frame().await;
+			}
+		}
+		progress.set(BundleBuildStage::PortalGraph, 0.0);
+		progress.next_frame().await;
+		PortalGraph::new_with_boundary_distance(
+			&portals,
+			&cost_fields,
+			&map_dimensions,
+			boundary_distance,
+		)
+	};
+	progress.set(BundleBuildStage::PortalGraph, 1.0);
+	let bundle = FlowFieldTilesBundle::from_parts(map_dimensions, cost_fields, portals, graph);
+	Ok(builder.finish(bundle))
+}
+
+/// Placed on an entity spawned with [FlowFieldTilesBundle::spawn_async]
+/// while its [FlowFieldTilesBundle] is being built. Once the task finishes
+/// the component is removed and either the bundle is inserted and an
+/// [EventBundleBuilt] sent, or an [EventBundleBuildFailed] is sent
+#[derive(Component)]
+pub struct BundleBuildTask {
+	/// The task building the bundle
+	task: Task<Result<FlowFieldTilesBundle, BuildErrorReport>>,
+	/// Progress updated by the task
+	progress: SharedProgress,
+	/// The progress which was last published
+	reported: Option<(BundleBuildStage, f32)>,
+}
+
+impl BundleBuildTask {
+	/// Begin building a bundle from the `builder` within a task
+	pub fn new(builder: FlowFieldTilesBundleBuilder) -> Self {
+		let progress = SharedProgress::default();
+		let task_progress = progress.clone();
+		let task = AsyncComputeTaskPool::get().spawn(build_in_chunks(builder, task_progress));
+		BundleBuildTask {
+			task,
+			progress,
+			reported: None,
+		}
+	}
+	/// Get the [BundleBuildStage] being worked on and the fraction of it done
+	pub fn get_progress(&self) -> (BundleBuildStage, f32) {
+		self.progress.get()
+	}
+}
+
+/// Sent each frame the progress of a [BundleBuildTask] has advanced
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EventBundleBuildProgress {
+	/// The entity the bundle is being built for
+	nav_world: Entity,
+	/// The stage being worked on
+	stage: BundleBuildStage,
+	/// Fraction of the stage done, between `0.0` and `1.0`
+	fraction: f32,
+}
+
+impl EventBundleBuildProgress {
+	/// Get the entity the bundle is being built for
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+	/// Get the stage being worked on
+	pub fn get_stage(&self) -> BundleBuildStage {
+		self.stage
+	}
+	/// Get the fraction of the stage done, between `0.0` and `1.0`
+	pub fn get_fraction(&self) -> f32 {
+		self.fraction
+	}
+}
+
+/// Sent once the [FlowFieldTilesBundle] of a [BundleBuildTask] has been
+/// inserted into its entity
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EventBundleBuilt {
+	/// The entity the bundle was inserted into
+	nav_world: Entity,
+}
+
+impl EventBundleBuilt {
+	/// Get the entity the bundle was inserted into
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+}
+
+/// Sent when the [FlowFieldTilesBundleBuilder] of a [BundleBuildTask] was
+/// rejected, nothing is inserted into the entity
+#[derive(Event, Clone, Debug)]
+pub struct EventBundleBuildFailed {
+	/// The entity the bundle was being built for
+	nav_world: Entity,
+	/// Every problem found with the builder
+	report: BuildErrorReport,
+}
+
+impl EventBundleBuildFailed {
+	/// Get the entity the bundle was being built for
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+	/// Get every problem found with the builder
+	pub fn get_report(&self) -> &BuildErrorReport {
+		&self.report
+	}
+}
+
+/// Publish the progress of each [BundleBuildTask] and insert the bundles of
+/// those which have finished
+#[cfg(not(tarpaulin_include))]
+pub fn poll_bundle_build_tasks(
+	mut commands: Commands,
+	mut tasks: Query<(Entity, &mut BundleBuildTask)>,
+	mut event_progress: EventWriter<EventBundleBuildProgress>,
+	mut event_built: EventWriter<EventBundleBuilt>,
+	mut event_failed: EventWriter<EventBundleBuildFailed>,
+) {
+	for (entity, mut build) in tasks.iter_mut() {
+		build.progress.wake_task();
+		let progress = build.get_progress();
+		if build.reported != Some(progress) {
+			build.reported = Some(progress);
+			event_progress.send(EventBundleBuildProgress {
+				nav_world: entity,
+				stage: progress.0,
+				fraction: progress.1,
+			});
+		}
+		let Some(result) = block_on(future::poll_once(&mut build.task)) else {
+			continue;
+		};
+		let mut entity_commands = commands.entity(entity);
+		entity_commands.remove::<BundleBuildTask>();
+		match result {
+			Ok(bundle) => {
+				// the task may finish between two polls of its progress
+				let done = (BundleBuildStage::PortalGraph, 1.0);
+				if build.reported != Some(done) {
+					event_progress.send(EventBundleBuildProgress {
+						nav_world: entity,
+						stage: done.0,
+						fraction: done.1,
+					});
+				}
+				entity_commands.insert(bundle);
+				event_built.send(EventBundleBuilt { nav_world: entity });
+			}
+			Err(report) => {
+				error!("Unable to build the bundle of {:?}: {}", entity, report);
+				event_failed.send(EventBundleBuildFailed {
+					nav_world: entity,
+					report,
+				});
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn build_task_reports_completion() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin::default()));
+		let builder = FlowFieldTilesBundle::builder()
+			.with_dimensions(30, 30)
+			.with_sector_resolution(10)
+			.with_actor_size(0.5);
+		let entity = FlowFieldTilesBundle::spawn_async(&mut app.world_mut().commands(), builder);
+		for _ in 0..100 {
+			app.update();
+			if app.world().get::<MapDimensions>(entity).is_some() {
+				break;
+			}
+		}
+		assert!(app.world().get::<BundleBuildTask>(entity).is_none());
+		let events = app.world().resource::<Events<EventBundleBuildProgress>>();
+		let last = events.get_cursor().read(events).last().copied().unwrap();
+		assert_eq!(entity, last.get_nav_world());
+		assert_eq!(BundleBuildStage::PortalGraph, last.get_stage());
+		assert_eq!(1.0, last.get_fraction());
+	}
+}
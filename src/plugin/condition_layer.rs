@@ -0,0 +1,135 @@
+//! Crossings between sectors can be made conditional on game state, such as
+//! a drawbridge which is raised and lowered. Toggling a condition with an
+//! [EventSetCondition] updates the [PortalGraph] without recomputing any
+//! portals and invalidates the cached routes which cross a disabled
+//! condition so that replacements are requested
+//!
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Enable or disable the crossings of a [ConditionId], see
+/// [PortalGraph::set_crossing_condition]
+#[derive(Event, Clone, Copy)]
+pub struct EventSetCondition {
+	/// The condition to change
+	condition: ConditionId,
+	/// Whether the crossings of the condition can be used
+	enabled: bool,
+	/// The bundle entity to update, if [None] then every bundle is updated
+	nav_world: Option<Entity>,
+}
+
+impl EventSetCondition {
+	/// Create a new instance of [EventSetCondition]
+	pub fn new(condition: ConditionId, enabled: bool) -> Self {
+		EventSetCondition {
+			condition,
+			enabled,
+			nav_world: None,
+		}
+	}
+	/// Only update the bundle `nav_world`
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	/// Get the condition
+	pub fn get_condition(&self) -> ConditionId {
+		self.condition
+	}
+	/// Get whether the condition is being enabled
+	pub fn is_enabled(&self) -> bool {
+		self.enabled
+	}
+	/// Get the bundle entity to update
+	pub fn get_nav_world(&self) -> Option<Entity> {
+		self.nav_world
+	}
+}
+
+/// Apply [EventSetCondition]s to the [PortalGraph] of each bundle. Routes
+/// which cross a newly disabled condition are removed from the caches,
+/// announced with an [EventRouteInvalidated] and requested again. Enabling
+/// a condition leaves cached routes as they are, new requests may then
+/// cross it
+#[cfg(not(tarpaulin_include))]
+pub fn process_condition_changes(
+	mut events: EventReader<EventSetCondition>,
+	mut query: Query<(
+		Entity,
+		&mut PortalGraph,
+		&mut RouteQueue,
+		&mut RouteCache,
+		&mut FlowFieldQueue,
+	)>,
+	mut event_path_request: EventWriter<EventPathRequest>,
+	mut event_route_invalidated: EventWriter<EventRouteInvalidated>,
+) {
+	for event in events.read() {
+		for (entity, mut graph, mut route_queue, mut route_cache, mut flow_queue) in
+			query.iter_mut()
+		{
+			if event.nav_world.is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			let was_enabled = graph.is_condition_enabled(event.condition);
+			if was_enabled == event.enabled {
+				continue;
+			}
+			graph.set_condition_enabled(event.condition, event.enabled);
+			if event.enabled {
+				continue;
+			}
+			for metadata in blocked_routes(route_queue.iter(), &graph) {
+				route_queue.remove_queued_route(metadata);
+			}
+			let queued: Vec<(&RouteMetadata, &Route)> = flow_queue
+				.get_queue()
+				.iter()
+				.map(|(metadata, builder)| (metadata, builder.get_route()))
+				.collect();
+			for metadata in blocked_routes(queued.into_iter(), &graph) {
+				flow_queue.remove_queue_item(metadata);
+			}
+			// only routes through the sectors of a crossing can use it
+			let mut candidates = Vec::new();
+			for ((sector_id, _), _) in graph.get_condition_crossings(event.condition) {
+				for metadata in route_cache.get_routes_using_sector(sector_id) {
+					if !candidates.contains(&metadata) {
+						candidates.push(metadata);
+					}
+				}
+			}
+			let routes: Vec<(&RouteMetadata, Route)> = candidates
+				.iter()
+				.filter_map(|metadata| route_cache.get_route_from_metadata(metadata))
+				.collect();
+			let to_purge = blocked_routes(
+				routes.iter().map(|(metadata, route)| (*metadata, route)),
+				&graph,
+			);
+			for metadata in to_purge.iter() {
+				route_cache.remove_route(*metadata);
+				invalidate_route(
+					metadata,
+					entity,
+					&mut event_route_invalidated,
+					&mut event_path_request,
+				);
+			}
+		}
+	}
+}
+
+/// Get the metadata of the `routes` which cross a disabled condition of the
+/// `graph`
+fn blocked_routes<'a>(
+	routes: impl Iterator<Item = (&'a RouteMetadata, &'a Route)>,
+	graph: &PortalGraph,
+) -> Vec<RouteMetadata> {
+	routes
+		.filter(|(_, route)| graph.is_route_blocked(route))
+		.map(|(metadata, _)| *metadata)
+		.collect()
+}
@@ -0,0 +1,228 @@
+//! Tunables of the plugin systems gathered into the [FlowFieldConfig]
+//! resource so that games can adjust them at runtime. The defaults match the
+//! behaviour of the plugin before the values were configurable.
+//!
+//! Some values can't be overridden at runtime. [FIELD_RESOLUTION] is fixed
+//! at compile time because fields are fixed-size arrays, and
+//! [PRIORITY_AGING_INTERVAL] is part of how queued routes are ranked
+//!
+
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::*;
+
+/// Runtime tunables read by the plugin systems
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct FlowFieldConfig {
+	/// How long a route stays cached before it's purged
+	route_lifetime: Duration,
+	/// How long a [FlowField] (including flee fields) stays cached before
+	/// it's purged
+	field_lifetime: Duration,
+	/// How old the route of a patrol leg may get before it's refreshed
+	patrol_refresh_interval: Duration,
+	/// How long to wait for the route of a patrol leg before requesting it
+	/// again
+	patrol_retry_interval: Duration,
+	/// Costs sent with an [EventUpdateCostfieldsCell] at or above this value
+	/// are made impassable
+	impassable_threshold: u8,
+	/// How many queued routes have their [IntegrationField]s built each
+	/// frame. With the `parallel` feature the routes of a frame are built
+	/// concurrently
+	integrations_per_frame: usize,
+	/// The [PrefetchPolicy] given to the [FlowFieldQueue] of each bundle,
+	/// including how close an actor is to the exit portal of its sector
+	/// before the next sector is prioritised. If [None] each cache keeps its
+	/// own policy
+	prefetch_policy: Option<PrefetchPolicy>,
+	/// The [GoalFallback] of the replacement request of a route whose goal
+	/// was made impassable, see [EventGoalBlocked]. Routes which already had
+	/// a fallback other than [GoalFallback::Exact] keep their own
+	blocked_goal_fallback: GoalFallback,
+	/// Distance of moving between portals either side of a sector boundary
+	/// given to the [PortalGraph] of each bundle. If [None] each graph keeps
+	/// its own distance
+	portal_boundary_distance: Option<i32>,
+}
+
+impl Default for FlowFieldConfig {
+	fn default() -> Self {
+		FlowFieldConfig {
+			route_lifetime: Duration::from_secs(900),
+			field_lifetime: Duration::from_secs(900),
+			patrol_refresh_interval: Duration::from_secs(60),
+			patrol_retry_interval: Duration::from_secs(5),
+			impassable_threshold: 255,
+			integrations_per_frame: 1,
+			prefetch_policy: None,
+			blocked_goal_fallback: GoalFallback::NearestPathable,
+			portal_boundary_distance: None,
+		}
+	}
+}
+
+impl FlowFieldConfig {
+	/// Set how long a route stays cached before it's purged
+	pub fn with_route_lifetime(mut self, lifetime: Duration) -> Self {
+		self.route_lifetime = lifetime;
+		self
+	}
+	/// Get how long a route stays cached before it's purged
+	pub fn get_route_lifetime(&self) -> Duration {
+		self.route_lifetime
+	}
+	/// Set how long a [FlowField] stays cached before it's purged
+	pub fn with_field_lifetime(mut self, lifetime: Duration) -> Self {
+		self.field_lifetime = lifetime;
+		self
+	}
+	/// Get how long a [FlowField] stays cached before it's purged
+	pub fn get_field_lifetime(&self) -> Duration {
+		self.field_lifetime
+	}
+	/// Set how old the route of a patrol leg may get before it's refreshed
+	/// and how long to wait for a requested leg before requesting it again
+	pub fn with_patrol_intervals(mut self, refresh: Duration, retry: Duration) -> Self {
+		self.patrol_refresh_interval = refresh;
+		self.patrol_retry_interval = retry;
+		self
+	}
+	/// Get how old the route of a patrol leg may get before it's refreshed
+	pub fn get_patrol_refresh_interval(&self) -> Duration {
+		self.patrol_refresh_interval
+	}
+	/// Get how long to wait for the route of a patrol leg before requesting
+	/// it again
+	pub fn get_patrol_retry_interval(&self) -> Duration {
+		self.patrol_retry_interval
+	}
+	/// Set the cost at or above which updated cells are made impassable
+	pub fn with_impassable_threshold(mut self, threshold: u8) -> Self {
+		self.impassable_threshold = threshold;
+		self
+	}
+	/// Get the cost at or above which updated cells are made impassable
+	pub fn get_impassable_threshold(&self) -> u8 {
+		self.impassable_threshold
+	}
+	/// Get the [Cost] a cell is updated to, costs at or above the impassable
+	/// threshold become [Cost::IMPASSABLE]
+	pub fn apply_impassable_threshold(&self, cost: u8) -> Cost {
+		if cost >= self.impassable_threshold {
+			Cost::IMPASSABLE
+		} else {
+			Cost::from(cost)
+		}
+	}
+	/// Set how many queued routes have their [IntegrationField]s built each
+	/// frame, at least one is always built. With the `parallel` feature
+	/// raise it to the number of routes worth building concurrently
+	pub fn with_integrations_per_frame(mut self, budget: usize) -> Self {
+		self.integrations_per_frame = budget.max(1);
+		self
+	}
+	/// Get how many queued routes have their [IntegrationField]s built each
+	/// frame
+	pub fn get_integrations_per_frame(&self) -> usize {
+		self.integrations_per_frame
+	}
+	/// Set the [PrefetchPolicy] given to the [FlowFieldQueue] of each bundle
+	pub fn with_prefetch_policy(mut self, policy: PrefetchPolicy) -> Self {
+		self.prefetch_policy = Some(policy);
+		self
+	}
+	/// Get the [PrefetchPolicy] given to the [FlowFieldQueue] of each bundle
+	pub fn get_prefetch_policy(&self) -> Option<PrefetchPolicy> {
+		self.prefetch_policy
+	}
+	/// Set the [GoalFallback] used to reroute a route whose goal was made
+	/// impassable, [GoalFallback::Exact] lets the new request be rejected
+	/// with an [EventPathGoalImpassable] instead
+	pub fn with_blocked_goal_fallback(mut self, goal_fallback: GoalFallback) -> Self {
+		self.blocked_goal_fallback = goal_fallback;
+		self
+	}
+	/// Get the [GoalFallback] used to reroute a route whose goal was made
+	/// impassable
+	pub fn get_blocked_goal_fallback(&self) -> GoalFallback {
+		self.blocked_goal_fallback
+	}
+	/// Set the distance of moving between portals either side of a sector
+	/// boundary given to the [PortalGraph] of each bundle, see
+	/// [SECTOR_BOUNDARY_PORTAL_PORTAL_DISTANCE] for the default
+	pub fn with_portal_boundary_distance(mut self, distance: i32) -> Self {
+		self.portal_boundary_distance = Some(distance);
+		self
+	}
+	/// Get the distance of moving between portals either side of a sector
+	/// boundary given to the [PortalGraph] of each bundle
+	pub fn get_portal_boundary_distance(&self) -> Option<i32> {
+		self.portal_boundary_distance
+	}
+}
+
+/// Give the [FlowFieldQueue] of each bundle the [PrefetchPolicy] and the
+/// [PortalGraph] the portal boundary distance of the [FlowFieldConfig] when
+/// either the config changes or a bundle is spawned
+#[cfg(not(tarpaulin_include))]
+pub fn apply_flow_field_config(
+	config: Res<FlowFieldConfig>,
+	mut queues: Query<&mut FlowFieldQueue>,
+	mut graphs: Query<&mut PortalGraph>,
+) {
+	if let Some(policy) = config.get_prefetch_policy() {
+		for mut queue in queues.iter_mut() {
+			if (config.is_changed() || queue.is_added()) && queue.get_prefetch_policy() != policy {
+				queue.set_prefetch_policy(policy);
+			}
+		}
+	}
+	if let Some(distance) = config.get_portal_boundary_distance() {
+		for mut graph in graphs.iter_mut() {
+			if (config.is_changed() || graph.is_added())
+				&& graph.get_boundary_distance() != distance
+			{
+				graph.set_boundary_distance(distance);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn impassable_threshold() {
+		let config = FlowFieldConfig::default();
+		assert_eq!(Cost::from(254), config.apply_impassable_threshold(254));
+		let config = config.with_impassable_threshold(200);
+		assert_eq!(Cost::IMPASSABLE, config.apply_impassable_threshold(200));
+		assert_eq!(Cost::from(199), config.apply_impassable_threshold(199));
+	}
+	#[test]
+	fn portal_boundary_distance_applied_to_graphs() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin::default()));
+		app.insert_resource(FlowFieldConfig::default().with_portal_boundary_distance(4));
+		let entity = app
+			.world_mut()
+			.spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5))
+			.id();
+		app.update();
+		let graph = app.world().get::<PortalGraph>(entity).unwrap();
+		assert_eq!(4, graph.get_boundary_distance());
+		// a builder sets the distance of a single bundle
+		let bundle = FlowFieldTilesBundleBuilder::default()
+			.with_dimensions(30, 30)
+			.with_sector_resolution(10)
+			.with_actor_size(0.5)
+			.with_portal_boundary_distance(3)
+			.build()
+			.unwrap();
+		assert_eq!(3, bundle.get_portal_graph().get_boundary_distance());
+	}
+}
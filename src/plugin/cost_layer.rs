@@ -3,6 +3,8 @@
 //! by the cost change
 //!
 
+use std::collections::{BTreeMap, BTreeSet};
+
 use crate::prelude::*;
 use bevy::prelude::*;
 
@@ -15,18 +17,27 @@ pub struct EventUpdateCostfieldsCell {
 	sector: SectorID,
 	/// The value the field cell should be assigned
 	cell_value: u8,
+	/// The bundle entity to update, if [None] then every bundle is updated
+	nav_world: Option<Entity>,
 }
 
 impl EventUpdateCostfieldsCell {
 	/// Create a new instance of [EventUpdateCostfieldsCell]
 	#[cfg(not(tarpaulin_include))]
-	pub fn new(cell: FieldCell, sector: SectorID, cell_value: u8) -> Self {
+	pub fn new(cell: FieldCell, sector: SectorID, cell_value: impl Into<Cost>) -> Self {
 		EventUpdateCostfieldsCell {
 			cell,
 			sector,
-			cell_value,
+			cell_value: cell_value.into().get(),
+			nav_world: None,
 		}
 	}
+	/// Only apply the update to the bundle of the `nav_world` entity
+	#[cfg(not(tarpaulin_include))]
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
 	#[cfg(not(tarpaulin_include))]
 	pub fn get_cell(&self) -> FieldCell {
 		self.cell
@@ -39,159 +50,1293 @@ impl EventUpdateCostfieldsCell {
 	pub fn get_cost_value(&self) -> u8 {
 		self.cell_value
 	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_nav_world(&self) -> Option<Entity> {
+		self.nav_world
+	}
+}
+
+/// The plane of the world a bundle is laid out on. With both the `2d` and
+/// `3d` features enabled each bundle can select its own, such as a 3d world
+/// alongside a 2d minimap. Defaults to [CoordinateSystem::Xyz] when the `3d`
+/// feature is enabled
+#[cfg(any(feature = "2d", feature = "3d"))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub enum CoordinateSystem {
+	/// Positions are `x, y` in a 2d world
+	#[cfg(feature = "2d")]
+	#[cfg_attr(not(feature = "3d"), default)]
+	Xy,
+	/// Positions are `x, z` in a 3d world, `y` is ignored
+	#[cfg(feature = "3d")]
+	#[default]
+	Xyz,
+}
+
+/// A position in world-space which can be translated into a [SectorID] and
+/// [FieldCell]
+#[cfg(any(feature = "2d", feature = "3d"))]
+#[derive(Clone, Copy, Debug)]
+pub enum WorldPosition {
+	/// A 2d `x, y` position
+	#[cfg(feature = "2d")]
+	Xy(Vec2),
+	/// A 3d `x, y, z` position, `y` is ignored
+	#[cfg(feature = "3d")]
+	Xyz(Vec3),
+}
+
+#[cfg(any(feature = "2d", feature = "3d"))]
+impl WorldPosition {
+	/// Create a position from the `translation` of an entity laid out in the
+	/// [CoordinateSystem] of a bundle
+	pub fn from_translation(translation: Vec3, coordinate_system: CoordinateSystem) -> Self {
+		match coordinate_system {
+			#[cfg(feature = "2d")]
+			CoordinateSystem::Xy => WorldPosition::Xy(translation.truncate()),
+			#[cfg(feature = "3d")]
+			CoordinateSystem::Xyz => WorldPosition::Xyz(translation),
+		}
+	}
+	/// Get the sector and field cell that the position resides in. Returns
+	/// [None] if the position is outside of the map
+	pub fn get_sector_and_field_cell(
+		&self,
+		map_dimensions: &MapDimensions,
+	) -> Option<(SectorID, FieldCell)> {
+		match *self {
+			#[cfg(feature = "2d")]
+			WorldPosition::Xy(position) => map_dimensions.get_sector_and_field_cell_from_xy(position),
+			#[cfg(feature = "3d")]
+			WorldPosition::Xyz(position) => map_dimensions.get_sector_and_field_cell_from_xyz(position),
+		}
+	}
+	/// Get the two coordinates of the position which lie on the plane of the
+	/// map, `x, y` in 2d and `x, z` in 3d
+	fn get_planar(&self) -> Vec2 {
+		match *self {
+			#[cfg(feature = "2d")]
+			WorldPosition::Xy(position) => position,
+			#[cfg(feature = "3d")]
+			WorldPosition::Xyz(position) => position.xz(),
+		}
+	}
+	/// Create a position of the same kind from planar coordinates
+	fn with_planar(&self, planar: Vec2) -> Self {
+		match *self {
+			#[cfg(feature = "2d")]
+			WorldPosition::Xy(_) => WorldPosition::Xy(planar),
+			#[cfg(feature = "3d")]
+			WorldPosition::Xyz(position) => WorldPosition::Xyz(Vec3::new(planar.x, position.y, planar.y)),
+		}
+	}
+	/// Whether the position lies within the bounds of the map
+	pub fn is_within(&self, map_dimensions: &MapDimensions) -> bool {
+		let half_extents = get_half_extents(map_dimensions);
+		let planar = self.get_planar();
+		planar.x.abs() <= half_extents.x && planar.y.abs() <= half_extents.y
+	}
+}
+
+/// Half of the length and depth of the map
+#[cfg(any(feature = "2d", feature = "3d"))]
+fn get_half_extents(map_dimensions: &MapDimensions) -> Vec2 {
+	Vec2::new(
+		map_dimensions.get_length() as f32 / 2.0,
+		map_dimensions.get_depth() as f32 / 2.0,
+	)
+}
+
+/// Find every sector and field cell overlapped by an axis-aligned bounding
+/// box described by two opposite corners. Any part of the box outside of the
+/// map is ignored, if the box lies entirely outside of the map then the list
+/// is empty
+#[cfg(any(feature = "2d", feature = "3d"))]
+pub fn get_field_cells_in_aabb(
+	min: WorldPosition,
+	max: WorldPosition,
+	map_dimensions: &MapDimensions,
+) -> Vec<(SectorID, FieldCell)> {
+	let mut cells = Vec::new();
+	let half_extents = get_half_extents(map_dimensions);
+	let lower = min.get_planar().min(max.get_planar());
+	let upper = min.get_planar().max(max.get_planar());
+	if upper.x < -half_extents.x
+		|| lower.x > half_extents.x
+		|| upper.y < -half_extents.y
+		|| lower.y > half_extents.y
+	{
+		return cells;
+	}
+	let clamped_min = min.with_planar(lower.clamp(-half_extents, half_extents));
+	let clamped_max = max.with_planar(upper.clamp(-half_extents, half_extents));
+	let (Some(a), Some(b)) = (
+		clamped_min.get_sector_and_field_cell(map_dimensions),
+		clamped_max.get_sector_and_field_cell(map_dimensions),
+	) else {
+		return cells;
+	};
+	// convert to global cell indices across all sectors, a position on the
+	// far edge of the map can land one cell beyond the grid so restrict it
+	let max_column = map_dimensions.get_total_field_cell_columns() as u32 - 1;
+	let max_row = map_dimensions.get_total_field_cell_rows() as u32 - 1;
+	let (a_column, a_row) = map_dimensions.global_cell_from(a.0, a.1);
+	let (b_column, b_row) = map_dimensions.global_cell_from(b.0, b.1);
+	let (a_column, b_column) = (a_column.min(max_column), b_column.min(max_column));
+	let (a_row, b_row) = (a_row.min(max_row), b_row.min(max_row));
+	for row in a_row.min(b_row)..=a_row.max(b_row) {
+		for column in a_column.min(b_column)..=a_column.max(b_column) {
+			if let Some(cell) = map_dimensions.sector_and_cell_from_global(column, row) {
+				cells.push(cell);
+			}
+		}
+	}
+	cells
+}
+
+/// Update the cost of the [FieldCell] found at a world-space position
+#[cfg(any(feature = "2d", feature = "3d"))]
+#[derive(Event)]
+pub struct EventUpdateCostAtPosition {
+	/// Position of the [FieldCell] to update
+	position: WorldPosition,
+	/// The value the field cell should be assigned
+	cell_value: u8,
+	/// The bundle entity to update, if [None] then every bundle is updated
+	nav_world: Option<Entity>,
+}
+
+#[cfg(any(feature = "2d", feature = "3d"))]
+impl EventUpdateCostAtPosition {
+	/// Create a new instance of [EventUpdateCostAtPosition]
+	#[cfg(not(tarpaulin_include))]
+	pub fn new(position: WorldPosition, cell_value: impl Into<Cost>) -> Self {
+		EventUpdateCostAtPosition {
+			position,
+			cell_value: cell_value.into().get(),
+			nav_world: None,
+		}
+	}
+	/// Only apply the update to the bundle of the `nav_world` entity
+	#[cfg(not(tarpaulin_include))]
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_position(&self) -> WorldPosition {
+		self.position
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_cost_value(&self) -> u8 {
+		self.cell_value
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_nav_world(&self) -> Option<Entity> {
+		self.nav_world
+	}
+}
+
+/// Update the cost of every [FieldCell] overlapped by a world-space
+/// axis-aligned bounding box
+#[cfg(any(feature = "2d", feature = "3d"))]
+#[derive(Event)]
+pub struct EventUpdateCostInAabb {
+	/// One corner of the box
+	min: WorldPosition,
+	/// The opposite corner of the box
+	max: WorldPosition,
+	/// The value the field cells should be assigned
+	cell_value: u8,
+	/// The bundle entity to update, if [None] then every bundle is updated
+	nav_world: Option<Entity>,
+}
+
+#[cfg(any(feature = "2d", feature = "3d"))]
+impl EventUpdateCostInAabb {
+	/// Create a new instance of [EventUpdateCostInAabb]
+	#[cfg(not(tarpaulin_include))]
+	pub fn new(min: WorldPosition, max: WorldPosition, cell_value: impl Into<Cost>) -> Self {
+		EventUpdateCostInAabb {
+			min,
+			max,
+			cell_value: cell_value.into().get(),
+			nav_world: None,
+		}
+	}
+	/// Only apply the update to the bundle of the `nav_world` entity
+	#[cfg(not(tarpaulin_include))]
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_min(&self) -> WorldPosition {
+		self.min
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_max(&self) -> WorldPosition {
+		self.max
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_cost_value(&self) -> u8 {
+		self.cell_value
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_nav_world(&self) -> Option<Entity> {
+		self.nav_world
+	}
+}
+
+/// Translate world-space cost updates into [EventUpdateCostfieldsCell] for
+/// each [FieldCell] they cover
+#[cfg(any(feature = "2d", feature = "3d"))]
+#[cfg(not(tarpaulin_include))]
+pub fn process_world_space_cost_updates(
+	mut events_position: EventReader<EventUpdateCostAtPosition>,
+	mut events_aabb: EventReader<EventUpdateCostInAabb>,
+	query: Query<(Entity, &MapDimensions)>,
+	mut event_update: EventWriter<EventUpdateCostfieldsCell>,
+) {
+	let events_position: Vec<&EventUpdateCostAtPosition> = events_position.read().collect();
+	let events_aabb: Vec<&EventUpdateCostInAabb> = events_aabb.read().collect();
+	for (entity, map_dimensions) in query.iter() {
+		for event in events_position.iter() {
+			if event.get_nav_world().is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			let position = event.get_position();
+			if !position.is_within(map_dimensions) {
+				warn!(
+					"Cost update at {:?} is outside of the map, ignoring it",
+					position
+				);
+				continue;
+			}
+			if let Some((sector, cell)) = position.get_sector_and_field_cell(map_dimensions) {
+				event_update.send(
+					EventUpdateCostfieldsCell::new(cell, sector, event.get_cost_value())
+						.with_nav_world(entity),
+				);
+			}
+		}
+		for event in events_aabb.iter() {
+			if event.get_nav_world().is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			let (min, max) = (event.get_min(), event.get_max());
+			if !min.is_within(map_dimensions) || !max.is_within(map_dimensions) {
+				warn!(
+					"Cost update within {:?} to {:?} extends outside of the map, only cells inside the map will be updated",
+					min, max
+				);
+			}
+			for (sector, cell) in get_field_cells_in_aabb(min, max, map_dimensions) {
+				event_update.send(
+					EventUpdateCostfieldsCell::new(cell, sector, event.get_cost_value())
+						.with_nav_world(entity),
+				);
+			}
+		}
+	}
 }
 
-/// Read [EventUpdateCostfieldsCell] and update the values within [CostField]
+/// Read [EventUpdateCostfieldsCell] and update the values within [CostField],
+/// see [apply_cost_updates]
 #[cfg(not(tarpaulin_include))]
 pub fn process_costfields_updates(
 	mut events: EventReader<EventUpdateCostfieldsCell>,
 	mut query: Query<(
+		Entity,
 		&mut PortalGraph,
 		&mut SectorPortals,
 		&mut SectorCostFields,
 		&MapDimensions,
 	)>,
 	mut event_cache_clean: EventWriter<EventCleanCaches>,
+	config: Res<FlowFieldConfig>,
 ) {
-	// coalesce events to avoid processing duplicates
-	let mut coalesced_sectors = Vec::new();
+	let mut updates = BTreeMap::<Entity, Vec<(SectorID, FieldCell, Cost)>>::new();
 	for event in events.read() {
-		let field_cell = event.get_cell();
-		let sector_id = event.get_sector();
-		let cost = event.get_cost_value();
-		for (_portal_graph, mut sector_portals, mut sector_cost_fields, dimensions) in
-			query.iter_mut()
+		for (entity, ..) in query.iter() {
+			if event.get_nav_world().is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			let update = (
+				event.get_sector(),
+				event.get_cell(),
+				config.apply_impassable_threshold(event.get_cost_value()),
+			);
+			updates.entry(entity).or_default().push(update);
+		}
+	}
+	for (entity, bundle_updates) in updates {
+		if let Ok((_, mut portal_graph, mut sector_portals, mut sector_cost_fields, dimensions)) =
+			query.get_mut(entity)
 		{
-			sector_cost_fields.set_field_cell_value(sector_id, cost, field_cell, dimensions);
-			// update the portals of the sector and around it
-			sector_portals.update_portals(sector_id, sector_cost_fields.as_ref(), dimensions);
+			let changed = apply_cost_updates(
+				&bundle_updates,
+				&mut portal_graph,
+				&mut sector_portals,
+				&mut sector_cost_fields,
+				dimensions,
+			);
+			for (sector_id, cells) in changed {
+				event_cache_clean.send(EventCleanCaches(sector_id, entity, Some(cells)));
+			}
 		}
-		if !coalesced_sectors.contains(&sector_id) {
-			coalesced_sectors.push(sector_id);
+	}
+}
+
+/// Set the cost of each `(sector, field_cell, cost)` of the `updates` in the
+/// [SectorCostFields] then rebuild the [SectorPortals] and [PortalGraph]
+/// around the changed sectors. Only the boundaries of a sector near the
+/// changed cells are recalculated, see [MapDimensions::get_boundaries_affected_by],
+/// and of those the graph is only rebuilt along the boundaries whose portals
+/// have actually changed. A boundary whose portals keep the same
+/// [FieldCell]s and costs keeps its graph nodes so that cached data keyed by
+/// them stays valid.
+///
+/// Returns each sector whose scaled [CostField] changed alongside the
+/// [FieldCell]s which differ so that cached routes and [FlowField]s using
+/// them can be re-validated. Scaling by the actor size can close off cells
+/// around an updated one, including cells of neighbouring sectors, so these
+/// are found by comparing the scaled fields from before and after the update
+pub fn apply_cost_updates(
+	updates: &[(SectorID, FieldCell, Cost)],
+	portal_graph: &mut PortalGraph,
+	sector_portals: &mut SectorPortals,
+	sector_cost_fields: &mut SectorCostFields,
+	map_dimensions: &MapDimensions,
+) -> Vec<(SectorID, Vec<FieldCell>)> {
+	// coalesce updates to avoid processing duplicates
+	let mut coalesced_sectors: Vec<(SectorID, Vec<FieldCell>)> = Vec::new();
+	for (sector_id, field_cell, _) in updates.iter() {
+		if let Some((_, cells)) = coalesced_sectors.iter_mut().find(|(s, _)| s == sector_id) {
+			if !cells.contains(field_cell) {
+				cells.push(*field_cell);
+			}
+		} else {
+			coalesced_sectors.push((*sector_id, vec![*field_cell]));
 		}
 	}
-	for sector_id in coalesced_sectors.iter() {
-		debug!("Rebuilding fields of {:?}", sector_id.get());
-		for (mut portal_graph, sector_portals, sector_cost_fields, dimensions) in query.iter_mut() {
-			// update the graph
+	let scaled_before =
+		get_scaled_fields_around(&coalesced_sectors, sector_cost_fields, map_dimensions);
+	// a single sector map has no portals or graph to rebuild
+	if map_dimensions.is_single_sector() {
+		for (sector_id, field_cell, cost) in updates.iter() {
+			sector_cost_fields.set_field_cell_value(*sector_id, *cost, *field_cell, map_dimensions);
+		}
+		return get_changed_scaled_cells(scaled_before, sector_cost_fields);
+	}
+	// only the boundaries of a sector near the changed cells can have
+	// different portals, record them before the change to compare against
+	let mut affected_boundaries = Vec::with_capacity(coalesced_sectors.len());
+	for (sector_id, cells) in coalesced_sectors.iter() {
+		let boundaries: Vec<(Ordinal, BoundaryPortals)> = map_dimensions
+			.get_boundaries_affected_by(sector_id, cells)
+			.into_iter()
+			.map(|ordinal| {
+				let portals = get_boundary_portals(
+					*sector_id,
+					ordinal,
+					sector_portals,
+					sector_cost_fields,
+					map_dimensions,
+				);
+				(ordinal, portals)
+			})
+			.collect();
+		affected_boundaries.push(boundaries);
+	}
+	for (sector_id, field_cell, cost) in updates.iter() {
+		sector_cost_fields.set_field_cell_value(*sector_id, *cost, *field_cell, map_dimensions);
+	}
+	// all portals need updating before the graph is
+	for ((sector_id, _), boundaries) in coalesced_sectors.iter().zip(affected_boundaries.iter()) {
+		let ordinals: Vec<Ordinal> = boundaries.iter().map(|(ordinal, _)| *ordinal).collect();
+		sector_portals.update_portal_boundaries(
+			*sector_id,
+			&ordinals,
+			sector_cost_fields,
+			map_dimensions,
+		);
+	}
+	for ((sector_id, _), boundaries) in coalesced_sectors.iter().zip(affected_boundaries) {
+		let changed_boundaries = get_changed_boundaries(
+			*sector_id,
+			boundaries,
+			sector_portals,
+			sector_cost_fields,
+			map_dimensions,
+		);
+		debug!(
+			"Rebuilding fields of {:?} along {:?}",
+			sector_id.get(),
+			changed_boundaries
+		);
+		portal_graph.update_graph_boundaries(
+			*sector_id,
+			&changed_boundaries,
+			sector_portals,
+			sector_cost_fields,
+			map_dimensions,
+		);
+	}
+	get_changed_scaled_cells(scaled_before, sector_cost_fields)
+}
+
+/// Get a copy of the scaled [CostField]s which updating cells of the
+/// `sectors` may change, the sectors themselves and when actors are scaled
+/// their neighbours as well
+fn get_scaled_fields_around(
+	sectors: &[(SectorID, Vec<FieldCell>)],
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+) -> BTreeMap<SectorID, CostField> {
+	let grid = map_dimensions.get_sector_grid();
+	let mut around = BTreeSet::new();
+	for (sector_id, _) in sectors.iter() {
+		around.insert(*sector_id);
+		if map_dimensions.get_actor_scale() > 1 {
+			for ordinal in [
+				Ordinal::North,
+				Ordinal::NorthEast,
+				Ordinal::East,
+				Ordinal::SouthEast,
+				Ordinal::South,
+				Ordinal::SouthWest,
+				Ordinal::West,
+				Ordinal::NorthWest,
+			] {
+				around.extend(grid.neighbour(sector_id, ordinal));
+			}
+		}
+	}
+	around
+		.into_iter()
+		.filter_map(|sector_id| {
+			let scaled = sector_cost_fields.get_scaled().get(&sector_id)?;
+			Some((sector_id, scaled.clone()))
+		})
+		.collect()
+}
+
+/// Compare the scaled [CostField]s recorded before an update with the
+/// current ones, returning each sector which changed with the [FieldCell]s
+/// that differ. A sector which has since been made void is returned with
+/// every [FieldCell]
+fn get_changed_scaled_cells(
+	scaled_before: BTreeMap<SectorID, CostField>,
+	sector_cost_fields: &SectorCostFields,
+) -> Vec<(SectorID, Vec<FieldCell>)> {
+	let mut changed = Vec::new();
+	for (sector_id, before) in scaled_before {
+		let after = sector_cost_fields.get_scaled().get(&sector_id);
+		let mut cells = Vec::new();
+		for column in 0..FIELD_RESOLUTION {
+			for row in 0..FIELD_RESOLUTION {
+				let field_cell = FieldCell::new(column, row);
+				let value = before.get_field_cell_value(field_cell);
+				if after.is_none_or(|after| after.get_field_cell_value(field_cell) != value) {
+					cells.push(field_cell);
+				}
+			}
+		}
+		if !cells.is_empty() {
+			changed.push((sector_id, cells));
+		}
+	}
+	changed
+}
+
+/// The portal [FieldCell]s along a boundary of a sector and the facing
+/// boundary of its neighbour, each with its scaled cost
+type BoundaryPortals = Vec<(SectorID, FieldCell, u8)>;
+
+/// Get the portal [FieldCell]s along the `ordinal` boundary of a sector and
+/// the facing boundary of its neighbour, each with its scaled cost which is
+/// the weight of its [PortalGraph] node
+fn get_boundary_portals(
+	sector_id: SectorID,
+	ordinal: Ordinal,
+	sector_portals: &SectorPortals,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+) -> BoundaryPortals {
+	let mut sides = vec![(sector_id, ordinal)];
+	if let Some(neighbour) = map_dimensions.get_sector_id_from_ordinal(ordinal, &sector_id) {
+		sides.push((neighbour, ordinal.inverse()));
+	}
+	let mut boundary_portals = Vec::new();
+	for (id, side) in sides {
+		let (Some(portals), Some(cost_field)) = (
+			sector_portals.get().get(&id),
+			sector_cost_fields.get_scaled().get(&id),
+		) else {
+			continue;
+		};
+		for cell in portals.get(&side).iter() {
+			boundary_portals.push((id, *cell, cost_field.get_field_cell_value(*cell)));
+		}
+	}
+	boundary_portals
+}
+
+/// Filter the `boundaries` of a sector, each recorded with its portals from
+/// before a change, down to those whose portals differ now that the
+/// [SectorPortals] have been recalculated
+fn get_changed_boundaries(
+	sector_id: SectorID,
+	boundaries: Vec<(Ordinal, BoundaryPortals)>,
+	sector_portals: &SectorPortals,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+) -> Vec<Ordinal> {
+	boundaries
+		.into_iter()
+		.filter(|(ordinal, previous)| {
+			*previous
+				!= get_boundary_portals(
+					sector_id,
+					*ordinal,
+					sector_portals,
+					sector_cost_fields,
+					map_dimensions,
+				)
+		})
+		.map(|(ordinal, _)| ordinal)
+		.collect()
+}
+
+/// Update the portals, [PortalGraph] and caches of any sectors which have been
+/// modified with a [CostFieldEditGuard]
+#[cfg(not(tarpaulin_include))]
+pub fn process_edited_costfields(
+	mut query: Query<(
+		Entity,
+		&mut PortalGraph,
+		&mut SectorPortals,
+		&mut SectorCostFields,
+		&MapDimensions,
+	)>,
+	mut event_cache_clean: EventWriter<EventCleanCaches>,
+) {
+	for (entity, mut portal_graph, mut sector_portals, mut sector_cost_fields, dimensions) in
+		query.iter_mut()
+	{
+		// avoid triggering change detection when there's nothing to do
+		if sector_cost_fields.get_edited_sectors().is_empty() {
+			continue;
+		}
+		let edited_sectors = sector_cost_fields.take_edited_sectors();
+		if dimensions.is_single_sector() {
+			for sector_id in edited_sectors.iter() {
+				event_cache_clean.send(EventCleanCaches(*sector_id, entity, None));
+			}
+			continue;
+		}
+		for sector_id in edited_sectors.iter() {
+			// update the portals of the sector and around it
+			sector_portals.update_portals(*sector_id, sector_cost_fields.as_ref(), dimensions);
+		}
+		for sector_id in edited_sectors.iter() {
+			debug!("Rebuilding fields of edited {:?}", sector_id.get());
 			portal_graph.update_graph(
 				*sector_id,
 				sector_portals.as_ref(),
 				sector_cost_fields.as_ref(),
 				dimensions,
 			);
+			// the edited cells aren't tracked so cached fields can't be re-validated
+			event_cache_clean.send(EventCleanCaches(*sector_id, entity, None));
 		}
-		event_cache_clean.send(EventCleanCaches(*sector_id));
 	}
 }
 
-/// For the given sector any route or [FlowField] making use of it needs to have the cached entry removed and a new request made to regenerate the route
+/// For the given sector of a bundle entity any route or [FlowField] making
+/// use of it needs to be re-validated against the [FieldCell]s that changed
+/// (when they're known) and any invalid cached entry removed and a new
+/// request made to regenerate the route
 #[derive(Event)]
-pub struct EventCleanCaches(SectorID);
+pub struct EventCleanCaches(SectorID, Entity, Option<Vec<FieldCell>>);
+
+impl EventCleanCaches {
+	/// Get the sector whose [CostField] changed
+	pub fn get_sector(&self) -> SectorID {
+		self.0
+	}
+	/// Get the bundle entity the sector belongs to
+	pub fn get_nav_world(&self) -> Entity {
+		self.1
+	}
+}
+
+/// Emitted when a cached route is removed because a [CostField] change has
+/// made it invalid. A new path request is made automatically but actors
+/// following the old route can use this to react straight away, such as by
+/// stopping or falling back to local steering until the new route is ready
+#[derive(Event, Clone, Copy)]
+pub struct EventRouteInvalidated {
+	/// The invalidated route
+	metadata: RouteMetadata,
+	/// The bundle entity the route belonged to
+	nav_world: Entity,
+}
 
-/// Lookup any cached data records making use of sectors that have had their [CostField] adjusted and remove them from the cache
+impl EventRouteInvalidated {
+	/// Get the [RouteMetadata] of the invalidated route
+	pub fn get_metadata(&self) -> RouteMetadata {
+		self.metadata
+	}
+	/// Get the ID of the [EventPathRequest] the route was built for, the
+	/// repeated request keeps the same ID
+	pub fn get_request_id(&self) -> Option<RequestId> {
+		self.metadata.get_request_id()
+	}
+	/// Get the bundle entity the route belonged to
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+}
+
+/// Emitted when a [CostField] change makes the goal of a cached route
+/// impassable, such as a building placed on top of it. The route is removed
+/// and requested again with the blocked goal fallback of the
+/// [FlowFieldConfig] so that actors aren't left walking into the obstacle
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EventGoalBlocked {
+	/// The route whose goal was blocked
+	metadata: RouteMetadata,
+	/// The bundle entity the route belonged to
+	nav_world: Entity,
+}
+
+impl EventGoalBlocked {
+	/// Get the [RouteMetadata] of the route whose goal was blocked
+	pub fn get_metadata(&self) -> RouteMetadata {
+		self.metadata
+	}
+	/// Get the sector and [FieldCell] of the blocked goal
+	pub fn get_blocked_goal(&self) -> (SectorID, FieldCell) {
+		self.metadata.get_resolved_target()
+	}
+	/// Get the ID of the [EventPathRequest] the route was built for, the
+	/// repeated request keeps the same ID
+	pub fn get_request_id(&self) -> Option<RequestId> {
+		self.metadata.get_request_id()
+	}
+	/// Get the bundle entity the route belonged to
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+}
+
+/// Find the cached and queued routes, along with the queued fields, leading
+/// to a goal within any of the `affected` sectors which is no longer pathable
+fn find_blocked_goals(
+	route_cache: &RouteCache,
+	route_queue: &RouteQueue,
+	flow_queue: &FlowFieldQueue,
+	affected: &BTreeMap<SectorID, Option<Vec<FieldCell>>>,
+	sector_cost_fields: &SectorCostFields,
+) -> BTreeSet<RouteMetadata> {
+	let cached = affected
+		.keys()
+		.flat_map(|id| route_cache.get_routes_using_sector(*id));
+	let queued = route_queue
+		.iter()
+		.map(|(metadata, _)| *metadata)
+		.chain(flow_queue.get_queue().keys().copied());
+	cached
+		.chain(queued)
+		.filter(|metadata| {
+			// scaling by the actor size can block a goal next to the changed
+			// cells so every goal within the sector is checked
+			let (goal_sector, goal) = metadata.get_resolved_target();
+			affected.contains_key(&goal_sector)
+				&& !sector_cost_fields.is_pathable(goal_sector, goal)
+		})
+		.collect()
+}
+
+/// Collect the queued routes which make use of any of the `affected`
+/// sectors, indexed by sector. The queue only needs walking once however
+/// many sectors changed within a frame
+fn index_routes_by_sector<'a>(
+	routes: impl Iterator<Item = (&'a RouteMetadata, &'a Route)>,
+	affected: &BTreeMap<SectorID, Option<Vec<FieldCell>>>,
+) -> BTreeMap<SectorID, BTreeSet<RouteMetadata>> {
+	let mut index: BTreeMap<SectorID, BTreeSet<RouteMetadata>> = BTreeMap::new();
+	for (metadata, route) in routes {
+		let endpoints = [metadata.get_source_sector(), metadata.get_target_sector()];
+		let sectors = endpoints
+			.into_iter()
+			.chain(route.get().iter().map(|(sector_id, _)| *sector_id));
+		for sector_id in sectors {
+			if affected.contains_key(&sector_id) {
+				index.entry(sector_id).or_default().insert(*metadata);
+			}
+		}
+	}
+	index
+}
+
+/// Lookup any cached data records making use of sectors that have had their [CostField] adjusted and remove them from the cache. A [FlowField] is kept when its integrated costs are unaffected by the changed cells, along with any route which only uses kept fields within the sector.
+///
+/// Every clean event of a frame is coalesced first so that a burst of edits
+/// walks each queue at most once, and only the cached entries which use an
+/// affected sector are then re-validated. Cached and queued routes whose
+/// goal has been made impassable are announced with an [EventGoalBlocked]
 #[cfg(not(tarpaulin_include))]
 pub fn clean_cache(
 	mut events: EventReader<EventCleanCaches>,
-	mut q_flow: Query<&mut FlowFieldCache>,
-	mut q_route: Query<&mut RouteCache>,
+	mut query: Query<(
+		Entity,
+		&mut FlowFieldQueue,
+		&mut FlowFieldCache,
+		&mut RouteQueue,
+		&mut RouteCache,
+		&SectorCostFields,
+		&MapDimensions,
+	)>,
 	mut event_path_request: EventWriter<EventPathRequest>,
+	mut event_route_invalidated: EventWriter<EventRouteInvalidated>,
+	mut event_goal_blocked: EventWriter<EventGoalBlocked>,
+	mut stats: Option<ResMut<FlowFieldStats>>,
+	config: Res<FlowFieldConfig>,
 ) {
-	let mut sectors = Vec::new();
-	for event in events.read() {
-		sectors.push(event.0);
-	}
-	if !sectors.is_empty() {
-		for mut flow_cache in q_flow.iter_mut() {
-			// purge invalid queued integratrion fields
-			let mut to_purge = Vec::new();
-			let map = flow_cache.get_queue_mut();
-			for id in sectors.iter() {
-				'next: for (metadata, builder) in map.iter() {
-					let path = builder.get_route().get();
-					for (route_sector, _) in path.iter() {
-						if *id == *route_sector {
-							to_purge.push(*metadata);
-							continue 'next;
+	// coalesce the changed cells of each sector, if any event doesn't know
+	// which cells changed then the whole sector is invalidated
+	let mut entity_sectors: BTreeMap<Entity, BTreeMap<SectorID, Option<Vec<FieldCell>>>> =
+		BTreeMap::new();
+	for EventCleanCaches(sector_id, entity, cells) in events.read() {
+		let sectors = entity_sectors.entry(*entity).or_default();
+		match sectors.get_mut(sector_id) {
+			Some(existing) => match (existing.as_mut(), cells) {
+				(Some(existing), Some(cells)) => {
+					for cell in cells.iter() {
+						if !existing.contains(cell) {
+							existing.push(*cell);
 						}
 					}
 				}
+				_ => *existing = None,
+			},
+			None => {
+				sectors.insert(*sector_id, cells.clone());
 			}
-			for purge_me in to_purge.iter() {
-				flow_cache.remove_queue_item(*purge_me);
-			}
-			// purge invalid flow fields
-			let mut to_purge = Vec::new();
-			let map = flow_cache.get_mut();
-			for id in sectors.iter() {
-				for metadata in map.keys() {
-					if *id == metadata.get_sector_id() {
-						to_purge.push(*metadata);
-					}
+		}
+	}
+	for (entity, affected) in entity_sectors.iter() {
+		// only the caches of the bundle which changed are cleaned
+		let Ok((
+			_,
+			mut flow_queue,
+			mut flow_cache,
+			mut route_queue,
+			mut route_cache,
+			sector_cost_fields,
+			map_dimensions,
+		)) = query.get_mut(*entity)
+		else {
+			continue;
+		};
+		// fields leading to a blocked goal can never be used again, queued
+		// work towards it is found before the queues are purged
+		let blocked = find_blocked_goals(
+			&route_cache,
+			&route_queue,
+			&flow_queue,
+			affected,
+			sector_cost_fields,
+		);
+		// purge invalid queued integratrion fields
+		let to_purge: Vec<RouteMetadata> = flow_queue
+			.get_queue()
+			.iter()
+			.filter(|(_, builder)| {
+				builder
+					.get_route()
+					.get()
+					.iter()
+					.any(|(sector_id, _)| affected.contains_key(sector_id))
+			})
+			.map(|(metadata, _)| *metadata)
+			.collect();
+		for purge_me in to_purge.iter() {
+			flow_queue.remove_queue_item(*purge_me);
+		}
+		for metadata in blocked.iter() {
+			let (goal_sector, goal) = metadata.get_resolved_target();
+			for field in flow_cache.get_sector_field_metadata(goal_sector) {
+				if field.get_goal_id() == Some(goal) {
+					flow_cache.remove_field(field);
 				}
 			}
-			for purge_me in to_purge.iter() {
-				flow_cache.remove_field(*purge_me);
-			}
 		}
-		for mut route_cache in q_route.iter_mut() {
-			// purge queued routes
-			let mut to_purge = Vec::new();
-			let map = route_cache.get_queue_mut();
-			for id in sectors.iter() {
-				'next: for (metadata, route) in map.iter() {
-					if *id == metadata.get_source_sector() {
-						to_purge.push(*metadata);
-						continue 'next;
-					}
-					if *id == metadata.get_target_sector() {
-						to_purge.push(*metadata);
-						continue 'next;
-					}
-					for (route_sector, _) in route.get().iter() {
-						if *id == *route_sector {
-							to_purge.push(*metadata);
-							continue 'next;
-						}
-					}
+		// purge invalid flow fields, keeping those which are unaffected by the change
+		for (id, cells) in affected.iter() {
+			// a sector which has been made void has no valid fields, nor can
+			// re-validation account for one-way movement or slopes
+			let cost_field = sector_cost_fields.get_scaled().get(id).filter(|_| {
+				sector_cost_fields.get_one_way_field(*id).is_none()
+					&& sector_cost_fields.get_slope(*id).is_none()
+			});
+			for metadata in flow_cache.get_sector_field_metadata(*id) {
+				let is_valid = cost_field
+					.zip(cells.as_ref())
+					.is_some_and(|(cost_field, cells)| {
+						flow_cache.is_field_still_valid(&metadata, cost_field, cells)
+					});
+				if !is_valid {
+					flow_cache.remove_field(metadata);
 				}
 			}
-			for purge_me in to_purge.iter() {
-				route_cache.remove_queued_route(*purge_me);
-			}
-			// purge invalid routes
-			let mut to_purge = Vec::new();
-			let map = route_cache.get_mut();
-			for id in sectors.iter() {
-				'next: for (metadata, route) in map.iter() {
-					if *id == metadata.get_source_sector() {
-						to_purge.push(*metadata);
-						continue 'next;
-					}
-					if *id == metadata.get_target_sector() {
-						to_purge.push(*metadata);
-						continue 'next;
-					}
-					for (route_sector, _) in route.get().iter() {
-						if *id == *route_sector {
-							to_purge.push(*metadata);
-							continue 'next;
-						}
-					}
+		}
+		// purge queued routes
+		let queued = index_routes_by_sector(route_queue.iter(), affected);
+		for metadata in queued.values().flatten() {
+			route_queue.remove_queued_route(*metadata);
+		}
+		// purge invalid routes, a route can be kept if its fields within the
+		// sector survived re-validation
+		let mut to_purge = BTreeSet::new();
+		for id in affected.keys() {
+			for metadata in route_cache.get_routes_using_sector(*id).iter() {
+				if to_purge.contains(metadata) || blocked.contains(metadata) {
+					continue;
+				}
+				let Some(route) = route_cache.iter_route(metadata) else {
+					continue;
+				};
+				let target_sector = metadata.get_target_sector();
+				// sectors within the stopping distance never had a field
+				let mut in_sector = route
+					.filter(|(route_sector, _)| *id == *route_sector)
+					.peekable();
+				let is_valid = in_sector.peek().is_some()
+					&& in_sector.all(|(sector_id, goal_id)| {
+						metadata.is_sector_within_stopping_distance(sector_id, map_dimensions)
+							|| flow_cache
+								.get_field(sector_id, target_sector, goal_id)
+								.is_some()
+					});
+				if !is_valid {
+					to_purge.insert(*metadata);
 				}
 			}
-			for purge_me in to_purge.iter() {
-				route_cache.remove_route(*purge_me);
-			}
-			// send events to regenerate routes
-			for metadata in to_purge.iter() {
-				event_path_request.send(EventPathRequest::new(
-					metadata.get_source_sector(),
-					metadata.get_source_field_cell(),
-					metadata.get_target_sector(),
-					metadata.get_target_goal(),
-				));
+		}
+		for purge_me in to_purge.iter().chain(blocked.iter()) {
+			route_cache.remove_route(*purge_me);
+		}
+		if let Some(stats) = stats.as_mut() {
+			let rebuilds = (to_purge.len() + blocked.len()) as u64;
+			stats.record(|c| c.record_cost_edit_rebuilds(rebuilds));
+		}
+		// reroute towards a new goal chosen by the configured policy, unless
+		// the route already had a policy of its own
+		for metadata in blocked.iter() {
+			event_goal_blocked.send(EventGoalBlocked {
+				metadata: *metadata,
+				nav_world: *entity,
+			});
+			event_route_invalidated.send(EventRouteInvalidated {
+				metadata: *metadata,
+				nav_world: *entity,
+			});
+			let mut request = repeat_request(metadata, *entity);
+			if metadata.get_goal_fallback() == GoalFallback::Exact {
+				request = request.with_goal_fallback(config.get_blocked_goal_fallback());
 			}
+			event_path_request.send(request);
 		}
+		// notify actors and send events to regenerate routes
+		for metadata in to_purge.iter() {
+			invalidate_route(
+				metadata,
+				*entity,
+				&mut event_route_invalidated,
+				&mut event_path_request,
+			);
+		}
+	}
+}
+
+/// Notify actors that a removed route of the bundle `entity` is no longer
+/// valid and request a replacement with the same properties
+pub(crate) fn invalidate_route(
+	metadata: &RouteMetadata,
+	entity: Entity,
+	event_route_invalidated: &mut EventWriter<EventRouteInvalidated>,
+	event_path_request: &mut EventWriter<EventPathRequest>,
+) {
+	event_route_invalidated.send(EventRouteInvalidated {
+		metadata: *metadata,
+		nav_world: entity,
+	});
+	event_path_request.send(repeat_request(metadata, entity));
+}
+
+/// Build an [EventPathRequest] which repeats the request described by the
+/// `metadata` within the bundle `entity`
+pub(crate) fn repeat_request(metadata: &RouteMetadata, entity: Entity) -> EventPathRequest {
+	let mut request = EventPathRequest::new(
+		metadata.get_source_sector(),
+		metadata.get_source_field_cell(),
+		metadata.get_target_sector(),
+		metadata.get_target_goal(),
+	)
+	.with_goal_fallback(metadata.get_goal_fallback())
+	.with_priority(metadata.get_priority())
+	.with_nav_world(entity);
+	if let (Some(goal_position), Some(distance)) = (
+		metadata.get_goal_position(),
+		metadata.get_stopping_distance(),
+	) {
+		request = request.with_stopping_distance(goal_position, distance);
+	}
+	if let Some(max_cost) = metadata.get_max_cost() {
+		request = request.with_max_cost(max_cost);
+	}
+	if let Some(player) = metadata.get_player() {
+		request = request.with_player(player);
+	}
+	if let Some(faction) = metadata.get_faction() {
+		request = request.with_faction(faction);
+	}
+	if let Some(goal_id) = metadata.get_shared_goal() {
+		request = request.with_shared_goal(goal_id);
+	}
+	if let Some(request_id) = metadata.get_request_id() {
+		request = request.with_request_id(request_id);
+	}
+	let goal_seeds = metadata.get_goal_seeds();
+	if !goal_seeds.is_empty() {
+		let seeds: Vec<(FieldCell, u8)> = goal_seeds.iter().collect();
+		request = request.with_goal_seeds(&seeds);
+	}
+	request
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::utils::Duration;
+	#[test]
+	#[cfg(feature = "2d")]
+	fn aabb_cells_xy() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let min = WorldPosition::Xy(Vec2::new(-5.5, 5.5));
+		let max = WorldPosition::Xy(Vec2::new(-3.5, 4.5));
+		let result = get_field_cells_in_aabb(min, max, &map_dimensions);
+		let actual = vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 9)),
+			(SectorID::new(1, 0), FieldCell::new(0, 9)),
+			(SectorID::new(1, 0), FieldCell::new(1, 9)),
+			(SectorID::new(0, 1), FieldCell::new(9, 0)),
+			(SectorID::new(1, 1), FieldCell::new(0, 0)),
+			(SectorID::new(1, 1), FieldCell::new(1, 0)),
+		];
+		assert_eq!(actual, result);
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn aabb_cells_partially_outside_xyz() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let min = WorldPosition::Xyz(Vec3::new(13.5, 0.0, 13.5));
+		let max = WorldPosition::Xyz(Vec3::new(100.0, 0.0, 100.0));
+		let result = get_field_cells_in_aabb(min, max, &map_dimensions);
+		let actual = vec![
+			(SectorID::new(2, 2), FieldCell::new(8, 8)),
+			(SectorID::new(2, 2), FieldCell::new(9, 8)),
+			(SectorID::new(2, 2), FieldCell::new(8, 9)),
+			(SectorID::new(2, 2), FieldCell::new(9, 9)),
+		];
+		assert_eq!(actual, result);
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn aabb_cells_outside_xyz() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let min = WorldPosition::Xyz(Vec3::new(50.0, 0.0, 50.0));
+		let max = WorldPosition::Xyz(Vec3::new(100.0, 0.0, 100.0));
+		let result = get_field_cells_in_aabb(min, max, &map_dimensions);
+		assert!(result.is_empty());
+	}
+	#[test]
+	fn apply_cost_updates_splits_portal() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(
+			map_dimensions.get_length(),
+			map_dimensions.get_depth(),
+			map_dimensions.get_sector_resolution(),
+		);
+		let sectors: Vec<SectorID> = sector_cost_fields.get_scaled().keys().copied().collect();
+		for sector_id in sectors {
+			sector_portals.update_portals(sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let mut portal_graph =
+			PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let sector_id = SectorID::new(0, 0);
+		let wall = FieldCell::new(4, 9);
+		// duplicate updates of a cell are coalesced
+		let updates = [
+			(sector_id, wall, Cost::IMPASSABLE),
+			(sector_id, wall, Cost::IMPASSABLE),
+		];
+		let changed = apply_cost_updates(
+			&updates,
+			&mut portal_graph,
+			&mut sector_portals,
+			&mut sector_cost_fields,
+			&map_dimensions,
+		);
+		assert_eq!(vec![(sector_id, vec![wall])], changed);
+		assert!(!sector_cost_fields.is_pathable(sector_id, wall));
+		// the wall splits the southern portal in two
+		let south = sector_portals
+			.get()
+			.get(&sector_id)
+			.unwrap()
+			.get(&Ordinal::South);
+		assert_eq!(2, south.len());
+		let path = portal_graph.find_best_path(
+			(sector_id, FieldCell::new(0, 0)),
+			(SectorID::new(0, 1), FieldCell::new(9, 9)),
+			&sector_portals,
+			&sector_cost_fields,
+		);
+		assert!(path.is_some());
+	}
+	#[test]
+	fn apply_cost_updates_reports_scaled_cells() {
+		// actors are two cells across
+		let map_dimensions = MapDimensions::new(20, 20, 10, 2.0);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let east = SectorID::new(1, 0);
+		sector_cost_fields.set_field_cell_value(
+			east,
+			Cost::IMPASSABLE,
+			FieldCell::new(1, 4),
+			&map_dimensions,
+		);
+		let mut sector_portals = SectorPortals::new(
+			map_dimensions.get_length(),
+			map_dimensions.get_depth(),
+			map_dimensions.get_sector_resolution(),
+		);
+		let sectors: Vec<SectorID> = sector_cost_fields.get_scaled().keys().copied().collect();
+		for sector_id in sectors {
+			sector_portals.update_portals(sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let mut portal_graph =
+			PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let west = SectorID::new(0, 0);
+		let wall = FieldCell::new(9, 4);
+		let changed = apply_cost_updates(
+			&[(west, wall, Cost::IMPASSABLE)],
+			&mut portal_graph,
+			&mut sector_portals,
+			&mut sector_cost_fields,
+			&map_dimensions,
+		);
+		// the wall leaves a gap across the boundary too narrow for an actor,
+		// closing off a cell of the neighbouring sector
+		let gap = FieldCell::new(0, 4);
+		assert_eq!(vec![(west, vec![wall]), (east, vec![gap])], changed);
+		assert!(!sector_cost_fields.is_pathable(east, gap));
+	}
+	#[test]
+	fn unchanged_portal_boundaries_skipped() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(
+			map_dimensions.get_length(),
+			map_dimensions.get_depth(),
+			map_dimensions.get_sector_resolution(),
+		);
+		let sectors: Vec<SectorID> = sector_cost_fields.get_scaled().keys().copied().collect();
+		for sector_id in sectors {
+			sector_portals.update_portals(sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let sector_id = SectorID::new(0, 0);
+		let south = |sector_portals: &SectorPortals, sector_cost_fields: &SectorCostFields| {
+			vec![(
+				Ordinal::South,
+				get_boundary_portals(
+					sector_id,
+					Ordinal::South,
+					sector_portals,
+					sector_cost_fields,
+					&map_dimensions,
+				),
+			)]
+		};
+		// a costlier cell along the boundary which isn't a portal
+		let before = south(&sector_portals, &sector_cost_fields);
+		assert_eq!(2, before[0].1.len());
+		sector_cost_fields.set_field_cell_value(
+			sector_id,
+			5,
+			FieldCell::new(8, 9),
+			&map_dimensions,
+		);
+		sector_portals.update_portal_boundaries(
+			sector_id,
+			&[Ordinal::South],
+			&sector_cost_fields,
+			&map_dimensions,
+		);
+		let changed = get_changed_boundaries(
+			sector_id,
+			before,
+			&sector_portals,
+			&sector_cost_fields,
+			&map_dimensions,
+		);
+		assert!(changed.is_empty());
+		// a costlier portal changes the weight of its node
+		let portal = sector_portals
+			.get()
+			.get(&sector_id)
+			.unwrap()
+			.get(&Ordinal::South)[0];
+		let before = south(&sector_portals, &sector_cost_fields);
+		sector_cost_fields.set_field_cell_value(sector_id, 5, portal, &map_dimensions);
+		let changed = get_changed_boundaries(
+			sector_id,
+			before,
+			&sector_portals,
+			&sector_cost_fields,
+			&map_dimensions,
+		);
+		assert_eq!(vec![Ordinal::South], changed);
+		// a graph updated around an unchanged boundary can still be pathed
+		let mut portal_graph =
+			PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let updates = [(sector_id, FieldCell::new(7, 9), Cost::new(3))];
+		apply_cost_updates(
+			&updates,
+			&mut portal_graph,
+			&mut sector_portals,
+			&mut sector_cost_fields,
+			&map_dimensions,
+		);
+		let path = portal_graph.find_best_path(
+			(sector_id, FieldCell::new(0, 0)),
+			(SectorID::new(0, 1), FieldCell::new(9, 9)),
+			&sector_portals,
+			&sector_cost_fields,
+		);
+		assert!(path.is_some());
+	}
+	#[test]
+	fn queued_routes_indexed_by_affected_sector() {
+		let mut queue = RouteQueue::default();
+		let goal = FieldCell::new(4, 4);
+		let route = Route::new(vec![
+			(SectorID::new(2, 0), FieldCell::new(0, 4)),
+			(SectorID::new(1, 0), FieldCell::new(0, 4)),
+			(SectorID::new(0, 0), goal),
+		]);
+		let metadata = RouteMetadata::new(
+			SectorID::new(2, 0),
+			FieldCell::new(9, 4),
+			SectorID::new(0, 0),
+			goal,
+			Duration::ZERO,
+		);
+		queue.add_to_queue(metadata, route);
+		let local = Route::new(vec![(SectorID::new(2, 2), goal)]);
+		let metadata = RouteMetadata::new(
+			SectorID::new(2, 2),
+			FieldCell::new(0, 0),
+			SectorID::new(2, 2),
+			goal,
+			Duration::ZERO,
+		);
+		queue.add_to_queue(metadata, local);
+		let mut affected = BTreeMap::new();
+		affected.insert(SectorID::new(1, 0), None);
+		affected.insert(SectorID::new(0, 2), None);
+		let index = index_routes_by_sector(queue.iter(), &affected);
+		assert_eq!(1, index.len());
+		let metadata = index.get(&SectorID::new(1, 0)).unwrap();
+		assert_eq!(1, metadata.len());
+		assert_eq!(
+			SectorID::new(2, 0),
+			metadata.first().unwrap().get_source_sector()
+		);
+	}
+	#[test]
+	fn blocked_goals_found() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut cache = RouteCache::default();
+		let goal_sector = SectorID::new(0, 0);
+		let goal = FieldCell::new(4, 4);
+		let route = Route::new(vec![
+			(SectorID::new(1, 0), FieldCell::new(0, 4)),
+			(goal_sector, goal),
+		]);
+		let metadata = RouteMetadata::new(
+			SectorID::new(1, 0),
+			FieldCell::new(9, 4),
+			goal_sector,
+			goal,
+			Duration::ZERO,
+		);
+		cache.insert_route_with_metadata(metadata, route.clone());
+		// requests still waiting to be built towards the goal
+		let mut route_queue = RouteQueue::default();
+		let queued_route = RouteMetadata::new(
+			SectorID::new(2, 0),
+			FieldCell::new(4, 4),
+			goal_sector,
+			goal,
+			Duration::ZERO,
+		);
+		route_queue
+			.get_queue_mut()
+			.insert(queued_route, route.clone());
+		let mut flow_queue = FlowFieldQueue::default();
+		let queued_field = RouteMetadata::new(
+			SectorID::new(1, 1),
+			FieldCell::new(4, 4),
+			goal_sector,
+			goal,
+			Duration::ZERO,
+		);
+		flow_queue.get_queue_mut().insert(
+			queued_field,
+			IntegrationBuilder::new(route, &sector_cost_fields),
+		);
+		let mut affected = BTreeMap::new();
+		affected.insert(goal_sector, Some(vec![goal]));
+		let find = |affected: &BTreeMap<SectorID, Option<Vec<FieldCell>>>,
+		            sector_cost_fields: &SectorCostFields| {
+			find_blocked_goals(
+				&cache,
+				&route_queue,
+				&flow_queue,
+				affected,
+				sector_cost_fields,
+			)
+		};
+		assert!(find(&affected, &sector_cost_fields).is_empty());
+		sector_cost_fields.set_field_cell_value(goal_sector, 255, goal, &map_dimensions);
+		let blocked = find(&affected, &sector_cost_fields);
+		assert_eq!(3, blocked.len());
+		assert!(blocked.contains(&metadata));
+		assert!(blocked.contains(&queued_route));
+		assert!(blocked.contains(&queued_field));
+		// a change along the way doesn't block the goal
+		let mut affected = BTreeMap::new();
+		affected.insert(SectorID::new(1, 0), None);
+		assert!(find(&affected, &sector_cost_fields).is_empty());
 	}
 }
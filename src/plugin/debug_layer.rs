@@ -0,0 +1,308 @@
+//! Renders the [CostField], [IntegrationField] or [FlowField] of a sector
+//! into a colour-mapped [Image] as a lightweight alternative to drawing
+//! Gizmos. The [Image] can be shown on a sprite, a mesh material or a UI node
+//! and each pixel corresponds to one [FieldCell] with the top-left pixel being
+//! [FieldCell] `(0, 0)`
+//!
+
+use crate::prelude::*;
+use bevy::{
+	asset::RenderAssetUsages,
+	image::ImageSampler,
+	prelude::*,
+	render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+/// The field of a sector which a [FieldDebugTexture] displays
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum DebugField {
+	/// The [CostField] of the sector, cheap cells are green, expensive cells
+	/// are red and impassable cells are black
+	Cost,
+	/// The [IntegrationField] of the sector leading to the goal (or portal)
+	/// of a cached [FlowField], cells closer to the goal are brighter and
+	/// impassable or unreached cells are black
+	Integration {
+		/// The sector of the end goal of the route
+		goal_sector: SectorID,
+		/// The goal or portal within the sector, see [FlowFieldCache::get_field]
+		goal_id: FieldCell,
+	},
+	/// A cached [FlowField] of the sector, the hue of a cell shows its
+	/// direction, cells with line of sight to the goal are white and
+	/// impassable cells are black
+	Flow {
+		/// The sector of the end goal of the route
+		goal_sector: SectorID,
+		/// The goal or portal within the sector, see [FlowFieldCache::get_field]
+		goal_id: FieldCell,
+	},
+}
+
+/// Keeps an [Image] of a field of a sector up to date. The [Image] is only
+/// redrawn when the underlying field changes
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct FieldDebugTexture {
+	/// The bundle entity the field belongs to
+	nav_world: Entity,
+	/// The sector to display
+	sector_id: SectorID,
+	/// Which field of the sector to display
+	field: DebugField,
+	/// The image the field is drawn into
+	image: Handle<Image>,
+	/// When the field was last drawn based on the generation time of the
+	/// [FlowField], a [CostField] is drawn at the default [Stamp]
+	drawn: Option<Stamp>,
+}
+
+impl FieldDebugTexture {
+	/// Create a new blank [Image] within `images` which will display the
+	/// `field` of a sector within the bundle of the `nav_world` entity
+	pub fn new(
+		nav_world: Entity,
+		sector_id: SectorID,
+		field: DebugField,
+		images: &mut Assets<Image>,
+	) -> Self {
+		FieldDebugTexture {
+			nav_world,
+			sector_id,
+			field,
+			image: images.add(new_field_image()),
+			drawn: None,
+		}
+	}
+	/// Get the bundle entity the field belongs to
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+	/// Get the sector being displayed
+	pub fn get_sector_id(&self) -> SectorID {
+		self.sector_id
+	}
+	/// Get which field of the sector is displayed
+	pub fn get_field(&self) -> DebugField {
+		self.field
+	}
+	/// Get the handle of the [Image] the field is drawn into
+	pub fn get_image(&self) -> Handle<Image> {
+		self.image.clone()
+	}
+}
+
+/// Create a transparent [Image] with a pixel for every [FieldCell] of a
+/// sector. Nearest sampling is used so the cells stay crisp when scaled up
+pub fn new_field_image() -> Image {
+	let mut image = Image::new_fill(
+		Extent3d {
+			width: FIELD_RESOLUTION as u32,
+			height: FIELD_RESOLUTION as u32,
+			depth_or_array_layers: 1,
+		},
+		TextureDimension::D2,
+		&[0, 0, 0, 0],
+		TextureFormat::Rgba8UnormSrgb,
+		RenderAssetUsages::default(),
+	);
+	image.sampler = ImageSampler::nearest();
+	image
+}
+
+/// Write the `rgba` colour of a [FieldCell] into an [Image] created with
+/// [new_field_image]
+fn set_pixel(image: &mut Image, field_cell: FieldCell, rgba: [u8; 4]) {
+	let i = (field_cell.get_row() * FIELD_RESOLUTION + field_cell.get_column()) * 4;
+	if let Some(pixel) = image.data.get_mut(i..i + 4) {
+		pixel.copy_from_slice(&rgba);
+	}
+}
+
+/// Get the colour of a [CostField] value, a cost of `1` is green shading
+/// towards red at `254` while impassable cells are black
+pub fn get_cost_colour(cost: u8) -> [u8; 4] {
+	if cost == 255 {
+		return [0, 0, 0, 255];
+	}
+	let t = cost.saturating_sub(1) as f32 / 253.0;
+	[(t * 255.0) as u8, ((1.0 - t) * 255.0) as u8, 0, 255]
+}
+
+/// Get the colour of an [IntegrationField] value where `max_cost` is the
+/// most expensive reachable cell of the field. The goal is white fading
+/// towards blue with distance while impassable or unreached cells are black
+pub fn get_integration_colour(value: u32, max_cost: u32) -> [u8; 4] {
+	let cost = value & INT_FILTER_BITS_COST;
+	if value & INT_BITS_IMPASSABLE == INT_BITS_IMPASSABLE || cost == u16::MAX as u32 {
+		return [0, 0, 0, 255];
+	}
+	let t = if max_cost == 0 {
+		0.0
+	} else {
+		cost as f32 / max_cost as f32
+	};
+	let shade = ((1.0 - t) * 255.0) as u8;
+	[shade, shade, 255, 255]
+}
+
+/// Get the colour of a [FlowField] value, the hue shows the direction of
+/// movement, cells with line of sight to the goal are white and cells
+/// without a direction are black
+pub fn get_flow_colour(value: u8) -> [u8; 4] {
+	if has_line_of_sight(value) {
+		return [255, 255, 255, 255];
+	}
+	if get_ordinal_from_bits(value) == Ordinal::Zero {
+		return [0, 0, 0, 255];
+	}
+	let direction = get_2d_direction_unit_vector_from_bits(value);
+	let hue = direction
+		.y
+		.atan2(direction.x)
+		.to_degrees()
+		.rem_euclid(360.0);
+	Color::hsl(hue, 0.8, 0.5).to_srgba().to_u8_array()
+}
+
+/// Draw a [CostField] into an [Image] created with [new_field_image]
+pub fn draw_cost_field(cost_field: &CostField, image: &mut Image) {
+	for (column, rows) in cost_field.get().iter().enumerate() {
+		for (row, value) in rows.iter().enumerate() {
+			set_pixel(image, FieldCell::new(column, row), get_cost_colour(*value));
+		}
+	}
+}
+
+/// Draw an [IntegrationField] into an [Image] created with
+/// [new_field_image]
+pub fn draw_integration_field(integration_field: &IntegrationField, image: &mut Image) {
+	let max_cost = integration_field
+		.get()
+		.iter()
+		.flatten()
+		.filter(|value| *value & INT_BITS_IMPASSABLE != INT_BITS_IMPASSABLE)
+		.map(|value| value & INT_FILTER_BITS_COST)
+		.filter(|cost| *cost != u16::MAX as u32)
+		.max()
+		.unwrap_or(0);
+	for (column, rows) in integration_field.get().iter().enumerate() {
+		for (row, value) in rows.iter().enumerate() {
+			set_pixel(
+				image,
+				FieldCell::new(column, row),
+				get_integration_colour(*value, max_cost),
+			);
+		}
+	}
+}
+
+/// Draw a [FlowField] into an [Image] created with [new_field_image]
+pub fn draw_flow_field(flow_field: &FlowField, image: &mut Image) {
+	for (column, rows) in flow_field.get().iter().enumerate() {
+		for (row, value) in rows.iter().enumerate() {
+			set_pixel(image, FieldCell::new(column, row), get_flow_colour(*value));
+		}
+	}
+}
+
+/// Redraw the [Image] of each [FieldDebugTexture] whose field has changed
+/// since it was last drawn
+#[cfg(not(tarpaulin_include))]
+pub fn update_field_debug_textures(
+	mut textures: Query<&mut FieldDebugTexture>,
+	bundles: Query<(Ref<SectorCostFields>, &FlowFieldCache)>,
+	images: Option<ResMut<Assets<Image>>>,
+) {
+	let Some(mut images) = images else {
+		return;
+	};
+	for mut texture in textures.iter_mut() {
+		let Ok((cost_fields, cache)) = bundles.get(texture.nav_world) else {
+			continue;
+		};
+		let sector_id = texture.sector_id;
+		match texture.field {
+			DebugField::Cost => {
+				if texture.drawn.is_some() && !cost_fields.is_changed() {
+					continue;
+				}
+				let Some(cost_field) = cost_fields.get_scaled().get(&sector_id) else {
+					continue;
+				};
+				if let Some(image) = images.get_mut(&texture.image) {
+					draw_cost_field(cost_field, image);
+				}
+				texture.drawn = Some(Stamp::default());
+			}
+			DebugField::Integration {
+				goal_sector,
+				goal_id,
+			} => {
+				let Some((flow_meta, _)) =
+					cache.get_field_with_metadata(sector_id, goal_sector, goal_id)
+				else {
+					continue;
+				};
+				if texture.drawn == Some(flow_meta.get_time_generated()) {
+					continue;
+				}
+				let Some(int_field) = cache.get_integration_field(flow_meta) else {
+					continue;
+				};
+				if let Some(image) = images.get_mut(&texture.image) {
+					draw_integration_field(int_field, image);
+				}
+				texture.drawn = Some(flow_meta.get_time_generated());
+			}
+			DebugField::Flow {
+				goal_sector,
+				goal_id,
+			} => {
+				let Some((flow_meta, flow_field)) =
+					cache.get_field_with_metadata(sector_id, goal_sector, goal_id)
+				else {
+					continue;
+				};
+				if texture.drawn == Some(flow_meta.get_time_generated()) {
+					continue;
+				}
+				if let Some(image) = images.get_mut(&texture.image) {
+					draw_flow_field(flow_field, image);
+				}
+				texture.drawn = Some(flow_meta.get_time_generated());
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn cost_colours() {
+		assert_eq!([0, 255, 0, 255], get_cost_colour(1));
+		assert_eq!([255, 0, 0, 255], get_cost_colour(254));
+		assert_eq!([0, 0, 0, 255], get_cost_colour(255));
+	}
+	#[test]
+	fn draw_cost_into_image() {
+		let mut cost_field = CostField::default();
+		cost_field.set_field_cell_value(255, FieldCell::new(3, 1));
+		let mut image = new_field_image();
+		draw_cost_field(&cost_field, &mut image);
+		let i = (FIELD_RESOLUTION + 3) * 4;
+		assert_eq!(&[0, 0, 0, 255], &image.data[i..i + 4]);
+		assert_eq!(&[0, 255, 0, 255], &image.data[0..4]);
+	}
+	#[test]
+	fn flow_colours() {
+		assert_eq!([0, 0, 0, 255], get_flow_colour(0));
+		// line of sight
+		assert_eq!([255, 255, 255, 255], get_flow_colour(0b0011_0000));
+		// east and west are opposite hues
+		let east = get_flow_colour(0b0001_0010);
+		let west = get_flow_colour(0b0001_1000);
+		assert_ne!(east, west);
+	}
+}
@@ -0,0 +1,462 @@
+//! Flee fields direct actors away from a point of danger (such as an
+//! explosion) rather than towards a goal. The integrated cost is calculated
+//! outwards from the danger across every sector within a configurable
+//! distance and each [FieldCell] points towards its neighbour which is most
+//! expensive to reach from the danger, so actors retreat along the same
+//! kind of [FlowField]s used for pathing
+//!
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+/// Describes the properties of a set of flee [FlowField]s
+#[derive(Clone, Copy, Debug, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct FleeFieldMetadata {
+	/// Sector containing the danger
+	danger_sector: SectorID,
+	/// [FieldCell] of the danger
+	danger_cell: FieldCell,
+	/// How many [FieldCell]s away from the danger the fields extend
+	radius: u32,
+	/// Marks the fields based on time elapsed since app start (or the turn when playing turn-based), used to enable automatic cleardown of long lived fields that are probably not needed anymore
+	time_generated: Stamp,
+}
+// we don't want to compare `time_generated` so manually impl PartialEq
+impl PartialEq for FleeFieldMetadata {
+	fn eq(&self, other: &Self) -> bool {
+		self.danger_sector == other.danger_sector
+			&& self.danger_cell == other.danger_cell
+			&& self.radius == other.radius
+	}
+}
+impl Eq for FleeFieldMetadata {}
+impl Ord for FleeFieldMetadata {
+	fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+		(self.danger_sector, self.danger_cell, self.radius).cmp(&(
+			other.danger_sector,
+			other.danger_cell,
+			other.radius,
+		))
+	}
+}
+impl PartialOrd for FleeFieldMetadata {
+	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl FleeFieldMetadata {
+	/// Create a new instance of [FleeFieldMetadata]
+	pub fn new(
+		danger_sector: SectorID,
+		danger_cell: FieldCell,
+		radius: u32,
+		time_generated: impl Into<Stamp>,
+	) -> Self {
+		FleeFieldMetadata {
+			danger_sector,
+			danger_cell,
+			radius,
+			time_generated: time_generated.into(),
+		}
+	}
+	/// Get the sector containing the danger
+	pub fn get_danger_sector(&self) -> SectorID {
+		self.danger_sector
+	}
+	/// Get the [FieldCell] of the danger
+	pub fn get_danger_cell(&self) -> FieldCell {
+		self.danger_cell
+	}
+	/// Get how many [FieldCell]s away from the danger the fields extend
+	pub fn get_radius(&self) -> u32 {
+		self.radius
+	}
+	/// Get when the fields were generated
+	pub fn get_time_generated(&self) -> Stamp {
+		self.time_generated
+	}
+}
+
+/// Cache of flee [FlowField]s, each danger has a [FlowField] for every
+/// sector within its radius
+#[derive(Component, Default, Clone, Reflect)]
+#[reflect(Component)]
+pub struct FleeFieldCache {
+	/// The fields of each sector around a danger
+	fields: BTreeMap<FleeFieldMetadata, BTreeMap<SectorID, FlowField>>,
+}
+
+impl FleeFieldCache {
+	/// Get a reference to the map of dangers and their fields
+	pub fn get(&self) -> &BTreeMap<FleeFieldMetadata, BTreeMap<SectorID, FlowField>> {
+		&self.fields
+	}
+	/// Get the flee [FlowField] of `sector_id` around a danger. Returns
+	/// [None] if the fields haven't been generated or the sector is outside
+	/// of the radius
+	pub fn get_field(
+		&self,
+		danger_sector: SectorID,
+		danger_cell: FieldCell,
+		radius: u32,
+		sector_id: SectorID,
+	) -> Option<&FlowField> {
+		let metadata = FleeFieldMetadata::new(danger_sector, danger_cell, radius, Duration::ZERO);
+		self.fields.get(&metadata)?.get(&sector_id)
+	}
+	/// Insert the `fields` of a danger, replacing any existing fields
+	pub fn insert_fields(
+		&mut self,
+		metadata: FleeFieldMetadata,
+		fields: BTreeMap<SectorID, FlowField>,
+	) {
+		// metadata doesn't compare the time, remove the old entry so the new
+		// time is kept
+		self.fields.remove(&metadata);
+		self.fields.insert(metadata, fields);
+	}
+	/// Remove the fields of a danger
+	pub fn remove_fields(&mut self, metadata: FleeFieldMetadata) {
+		self.fields.remove(&metadata);
+	}
+	/// Remove the fields of every danger
+	pub fn clear(&mut self) {
+		self.fields.clear();
+	}
+}
+
+/// Request [FlowField]s directing actors away from a danger up to `radius`
+/// [FieldCell]s from it, the fields are stored in the [FleeFieldCache]
+#[derive(Event, Clone, Copy)]
+pub struct EventFleeRequest {
+	/// Sector containing the danger
+	danger_sector: SectorID,
+	/// [FieldCell] of the danger
+	danger_cell: FieldCell,
+	/// How many [FieldCell]s away from the danger the fields extend
+	radius: u32,
+	/// The bundle entity to generate the fields within, if [None] then
+	/// fields are generated in every bundle
+	nav_world: Option<Entity>,
+}
+
+impl EventFleeRequest {
+	/// Create a new instance of [EventFleeRequest]
+	pub fn new(danger_sector: SectorID, danger_cell: FieldCell, radius: u32) -> Self {
+		EventFleeRequest {
+			danger_sector,
+			danger_cell,
+			radius,
+			nav_world: None,
+		}
+	}
+	/// Only generate the fields within the bundle of the `nav_world` entity
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	/// Get the sector containing the danger
+	pub fn get_danger_sector(&self) -> SectorID {
+		self.danger_sector
+	}
+	/// Get the [FieldCell] of the danger
+	pub fn get_danger_cell(&self) -> FieldCell {
+		self.danger_cell
+	}
+	/// Get how many [FieldCell]s away from the danger the fields extend
+	pub fn get_radius(&self) -> u32 {
+		self.radius
+	}
+	/// Get the bundle entity the request applies to
+	pub fn get_nav_world(&self) -> Option<Entity> {
+		self.nav_world
+	}
+}
+
+/// Build the flee [FlowField]s of each sector within `radius` [FieldCell]s
+/// (measured along columns and rows) of the `danger`. The integrated cost is
+/// calculated outwards from the danger and each pathable [FieldCell] points
+/// to its neighbour with the highest integrated cost, [FieldCell]s with no
+/// costlier neighbour (the edge of the radius, or areas the danger can't
+/// reach) are pathable with no direction, meaning the actor is safe
+pub fn build_flee_fields(
+	danger: (SectorID, FieldCell),
+	radius: u32,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+) -> BTreeMap<SectorID, FlowField> {
+	let mut fields = BTreeMap::new();
+	let columns = map_dimensions.get_total_field_cell_columns() as i64;
+	let rows = map_dimensions.get_total_field_cell_rows() as i64;
+	let (danger_column, danger_row) = map_dimensions.global_cell_from(danger.0, danger.1);
+	let (danger_column, danger_row) = (danger_column as i64, danger_row as i64);
+	if danger_column >= columns || danger_row >= rows {
+		return fields;
+	}
+	// bounds of the area around the danger
+	let radius = radius as i64;
+	let min_column = (danger_column - radius).max(0);
+	let max_column = (danger_column + radius).min(columns - 1);
+	let min_row = (danger_row - radius).max(0);
+	let max_row = (danger_row + radius).min(rows - 1);
+	let is_within = |column: i64, row: i64| {
+		(min_column..=max_column).contains(&column) && (min_row..=max_row).contains(&row)
+	};
+	let cost = |column: i64, row: i64| {
+		get_global_cost(
+			sector_cost_fields,
+			map_dimensions,
+			column as usize,
+			row as usize,
+		)
+	};
+	// integrate outwards from the danger
+	let integrated = integrate_global_costs(
+		(danger_column as usize, danger_row as usize),
+		u32::MAX,
+		sector_cost_fields,
+		map_dimensions,
+		|column, row| is_within(column as i64, row as i64),
+		|_, _, _| true,
+	);
+	let integrated_at = |column: i64, row: i64| {
+		integrated
+			.get(&(column as usize, row as usize))
+			.copied()
+			.unwrap_or(u32::MAX)
+	};
+	// point each cell away from the danger
+	let ordinals = [
+		Ordinal::North,
+		Ordinal::East,
+		Ordinal::South,
+		Ordinal::West,
+		Ordinal::NorthEast,
+		Ordinal::SouthEast,
+		Ordinal::SouthWest,
+		Ordinal::NorthWest,
+	];
+	let offset = |ordinal: Ordinal| -> (i64, i64) {
+		match ordinal {
+			Ordinal::North => (0, -1),
+			Ordinal::East => (1, 0),
+			Ordinal::South => (0, 1),
+			Ordinal::West => (-1, 0),
+			Ordinal::NorthEast => (1, -1),
+			Ordinal::SouthEast => (1, 1),
+			Ordinal::SouthWest => (-1, 1),
+			Ordinal::NorthWest => (-1, -1),
+			Ordinal::Zero => (0, 0),
+		}
+	};
+	let is_passable = |column: i64, row: i64| is_within(column, row) && cost(column, row) != 255;
+	for row in min_row..=max_row {
+		for column in min_column..=max_column {
+			let Some((sector_id, field_cell)) =
+				map_dimensions.sector_and_cell_from_global(column as u32, row as u32)
+			else {
+				continue;
+			};
+			let value = if cost(column, row) == 255 {
+				convert_ordinal_to_bits_dir(Ordinal::Zero)
+			} else {
+				let current = integrated_at(column, row);
+				let mut costliest = (current, Ordinal::Zero);
+				if current != u32::MAX {
+					for ordinal in ordinals {
+						let (column_step, row_step) = offset(ordinal);
+						let (n_column, n_row) = (column + column_step, row + row_step);
+						if !is_passable(n_column, n_row) {
+							continue;
+						}
+						// don't squeeze diagonally between two impassable cells
+						if column_step != 0
+							&& row_step != 0 && !is_passable(column + column_step, row)
+							&& !is_passable(column, row + row_step)
+						{
+							continue;
+						}
+						let n_integrated = integrated_at(n_column, n_row);
+						if n_integrated != u32::MAX && n_integrated > costliest.0 {
+							costliest = (n_integrated, ordinal);
+						}
+					}
+				}
+				get_pathable_bits_from_ordinal(costliest.1)
+			};
+			fields
+				.entry(sector_id)
+				.or_insert_with(FlowField::default)
+				.set_field_cell_value(value, field_cell);
+		}
+	}
+	fields
+}
+
+/// Generate the flee [FlowField]s of each [EventFleeRequest], a request for
+/// fields which already exist refreshes their generation time
+#[cfg(not(tarpaulin_include))]
+pub fn process_flee_requests(
+	mut events: EventReader<EventFleeRequest>,
+	mut query: Query<(
+		Entity,
+		&mut FleeFieldCache,
+		&SectorCostFields,
+		&MapDimensions,
+	)>,
+	time: Res<Time>,
+	turn: Option<Res<FlowFieldTurn>>,
+	state: Option<Res<FlowFieldSystemState>>,
+) {
+	let elapsed = get_navigation_elapsed(&time, turn.as_deref(), state.as_deref());
+	for event in events.read() {
+		for (entity, mut cache, sector_cost_fields, map_dimensions) in query.iter_mut() {
+			if event.get_nav_world().is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			let metadata = FleeFieldMetadata::new(
+				event.get_danger_sector(),
+				event.get_danger_cell(),
+				event.get_radius(),
+				elapsed,
+			);
+			let fields = match cache.fields.remove(&metadata) {
+				Some(fields) => fields,
+				None => build_flee_fields(
+					(event.get_danger_sector(), event.get_danger_cell()),
+					event.get_radius(),
+					sector_cost_fields,
+					map_dimensions,
+				),
+			};
+			cache.insert_fields(metadata, fields);
+		}
+	}
+}
+
+/// Rebuild any flee [FlowField]s covering a sector whose [CostField] has
+/// changed
+#[cfg(not(tarpaulin_include))]
+pub fn rebuild_changed_flee_fields(
+	mut events: EventReader<EventCleanCaches>,
+	mut query: Query<(&mut FleeFieldCache, &SectorCostFields, &MapDimensions)>,
+) {
+	// coalesce events so each set of fields is rebuilt once
+	let mut changed: BTreeSet<(Entity, SectorID)> = BTreeSet::new();
+	for event in events.read() {
+		changed.insert((event.get_nav_world(), event.get_sector()));
+	}
+	for (entity, sector_id) in changed {
+		let Ok((mut cache, sector_cost_fields, map_dimensions)) = query.get_mut(entity) else {
+			continue;
+		};
+		let affected: Vec<FleeFieldMetadata> = cache
+			.get()
+			.iter()
+			.filter(|(_, fields)| fields.contains_key(&sector_id))
+			.map(|(metadata, _)| *metadata)
+			.collect();
+		for metadata in affected {
+			let fields = build_flee_fields(
+				(metadata.get_danger_sector(), metadata.get_danger_cell()),
+				metadata.get_radius(),
+				sector_cost_fields,
+				map_dimensions,
+			);
+			cache.insert_fields(metadata, fields);
+		}
+	}
+}
+
+/// Purge any flee [FlowField]s older than the field lifetime of the
+/// [FlowFieldConfig] (15 minutes by default), fields are kept while playing
+/// turn-based (see [FlowFieldTurn])
+#[cfg(not(tarpaulin_include))]
+pub fn cleanup_old_flee_fields(
+	mut query: Query<&mut FleeFieldCache>,
+	time: Res<Time>,
+	state: Option<Res<FlowFieldSystemState>>,
+	config: Res<FlowFieldConfig>,
+) {
+	for mut cache in query.iter_mut() {
+		let elapsed = get_navigation_elapsed(&time, None, state.as_deref());
+		cache.fields.retain(|metadata, _| {
+			elapsed
+				.time_since(metadata.get_time_generated())
+				.is_none_or(|age| age <= config.get_field_lifetime())
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn flee_fields_point_away() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let danger = (SectorID::new(1, 1), FieldCell::new(0, 5));
+		let fields = build_flee_fields(danger, 3, &sector_cost_fields, &map_dimensions);
+		// the radius crosses into the western sector
+		assert_eq!(2, fields.len());
+		let east = fields
+			.get(&SectorID::new(1, 1))
+			.unwrap()
+			.get_field_cell_value(FieldCell::new(1, 5));
+		assert!(is_pathable(east));
+		assert!(matches!(
+			get_ordinal_from_bits(east),
+			Ordinal::NorthEast | Ordinal::SouthEast
+		));
+		let west = fields
+			.get(&SectorID::new(0, 1))
+			.unwrap()
+			.get_field_cell_value(FieldCell::new(8, 5));
+		assert!(matches!(
+			get_ordinal_from_bits(west),
+			Ordinal::NorthWest | Ordinal::SouthWest
+		));
+		// the edge of the radius is safe
+		let edge = fields
+			.get(&SectorID::new(1, 1))
+			.unwrap()
+			.get_field_cell_value(FieldCell::new(3, 8));
+		assert!(is_pathable(edge));
+		assert_eq!(Ordinal::Zero, get_ordinal_from_bits(edge));
+		// outside of the radius the fields are untouched
+		let outside = fields
+			.get(&SectorID::new(1, 1))
+			.unwrap()
+			.get_field_cell_value(FieldCell::new(5, 5));
+		assert!(!is_pathable(outside));
+	}
+	#[test]
+	fn flee_cache_ignores_time() {
+		let mut cache = FleeFieldCache::default();
+		let sector_id = SectorID::new(0, 0);
+		let cell = FieldCell::new(4, 4);
+		let mut fields = BTreeMap::new();
+		fields.insert(sector_id, FlowField::default());
+		cache.insert_fields(
+			FleeFieldMetadata::new(sector_id, cell, 2, Duration::from_secs(1)),
+			fields.clone(),
+		);
+		cache.insert_fields(
+			FleeFieldMetadata::new(sector_id, cell, 2, Duration::from_secs(5)),
+			fields,
+		);
+		assert_eq!(1, cache.get().len());
+		let metadata = cache.get().keys().next().unwrap();
+		assert_eq!(
+			Stamp::Time(Duration::from_secs(5)),
+			metadata.get_time_generated()
+		);
+		assert!(cache.get_field(sector_id, cell, 2, sector_id).is_some());
+		assert!(cache.get_field(sector_id, cell, 3, sector_id).is_none());
+	}
+}
@@ -1,12 +1,40 @@
 //! Logic relating to [FlowField] generation
 //!
 
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use crate::prelude::*;
 use bevy::prelude::*;
+use bevy::utils::Duration;
+#[cfg(use_rayon)]
+use rayon::prelude::*;
+
+/// An opaque ID given to each [EventPathRequest] when it's created. It's
+/// carried by the [RouteMetadata] and [FlowFieldMetadata] built for the
+/// request and by the events sent in response, so that gameplay can match a
+/// response to its request even when several actors request identical paths
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct RequestId(u64);
+
+impl RequestId {
+	/// Generate an ID which is unique for the lifetime of the app
+	pub(crate) fn next() -> Self {
+		/// The ID handed to the next request
+		static NEXT: AtomicU64 = AtomicU64::new(1);
+		RequestId(NEXT.fetch_add(1, Ordering::Relaxed))
+	}
+	/// Get the raw value of the ID, such as for logging
+	pub fn get(&self) -> u64 {
+		self.0
+	}
+}
 
 /// A request to queue up an attempt at generating a Route and a series of
 /// [FlowField]s describing a path from the source to target
-#[derive(Event)]
+#[derive(Event, Clone, Copy)]
 pub struct EventPathRequest {
 	/// The starting sector of the request
 	source_sector: SectorID,
@@ -16,6 +44,29 @@ pub struct EventPathRequest {
 	target_sector: SectorID,
 	/// The field cell in the target sector to find a path to
 	target_goal: FieldCell,
+	/// How to select an alternative goal if the target cannot be used
+	goal_fallback: GoalFallback,
+	/// The bundle entity to path within, if [None] then a route is
+	/// generated in every bundle
+	nav_world: Option<Entity>,
+	/// How urgently the request should be serviced
+	priority: PathPriority,
+	/// The world position of the goal and the distance from it at which the
+	/// actor stops
+	stopping: Option<(Vec3, f32)>,
+	/// The most the route may cost
+	max_cost: Option<i32>,
+	/// The player whose explored sectors the route is limited to
+	player: Option<PlayerId>,
+	/// The faction whose restricted zones the route avoids
+	faction: Option<FactionId>,
+	/// The shared goal the route leads to, its position within the
+	/// [SharedGoals] of the bundle replaces the target
+	shared_goal: Option<GoalId>,
+	/// Correlates the request with the routes and events produced for it
+	request_id: RequestId,
+	/// Additional goals of the goal sector and the costs they start at
+	goal_seeds: GoalSeeds,
 }
 
 impl EventPathRequest {
@@ -30,81 +81,739 @@ impl EventPathRequest {
 			source_field_cell,
 			target_sector,
 			target_goal,
+			goal_fallback: GoalFallback::default(),
+			nav_world: None,
+			priority: PathPriority::default(),
+			stopping: None,
+			max_cost: None,
+			player: None,
+			faction: None,
+			shared_goal: None,
+			request_id: RequestId::next(),
+			goal_seeds: GoalSeeds::default(),
+		}
+	}
+	/// Create a request for a path to a shared goal registered in the
+	/// [SharedGoals] of each bundle, bundles which don't know the goal are
+	/// skipped
+	pub fn to_shared_goal(
+		source_sector: SectorID,
+		source_field_cell: FieldCell,
+		goal_id: GoalId,
+	) -> Self {
+		EventPathRequest::new(
+			source_sector,
+			source_field_cell,
+			SectorID::default(),
+			FieldCell::default(),
+		)
+		.with_shared_goal(goal_id)
+	}
+	/// Set the [PathPriority] of the request, higher priority requests are
+	/// processed first
+	pub fn with_priority(mut self, priority: PathPriority) -> Self {
+		self.priority = priority;
+		self
+	}
+	/// Only generate a route within the bundle of the `nav_world` entity
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	/// Set the [GoalFallback] policy used when the target is impassable or
+	/// unreachable
+	pub fn with_goal_fallback(mut self, goal_fallback: GoalFallback) -> Self {
+		self.goal_fallback = goal_fallback;
+		self
+	}
+	/// The actor stops once it is within `distance` of the world
+	/// `goal_position`, see [RouteMetadata::with_stopping_distance]
+	pub fn with_stopping_distance(mut self, goal_position: Vec3, distance: f32) -> Self {
+		self.stopping = Some((goal_position, distance));
+		self
+	}
+	/// Only build the route if its cost is no more than `max_cost`, more
+	/// expensive routes are rejected with an [EventPathTooExpensive] before
+	/// any fields are built for them
+	pub fn with_max_cost(mut self, max_cost: i32) -> Self {
+		self.max_cost = Some(max_cost);
+		self
+	}
+	/// Only build the route if an actor of the `speed` can travel it within
+	/// `max_time`, the time is converted into a cost budget, see
+	/// [EventPathRequest::with_max_cost] and [TraversalSpeed::time_to_cost]
+	pub fn with_max_time(
+		self,
+		max_time: Duration,
+		speed: TraversalSpeed,
+		map_dimensions: &MapDimensions,
+	) -> Self {
+		self.with_max_cost(speed.time_to_cost(max_time, map_dimensions))
+	}
+	/// Path on behalf of a `player`, if the bundle has a [SectorVisibility]
+	/// the route is limited to the sectors the player has explored. The
+	/// route is cached separately for each player, look it up with
+	/// [RouteCache::get_player_route]
+	pub fn with_player(mut self, player: PlayerId) -> Self {
+		self.player = Some(player);
+		self
+	}
+	/// Path on behalf of a `faction`, if the bundle has [FactionZones] the
+	/// route and its [FlowField]s avoid the enabled zones of the faction.
+	/// The fields are cached separately for each faction, look them up with
+	/// [RouteCache::get_faction_route] and [FlowFieldCache::get_faction_field]
+	pub fn with_faction(mut self, faction: FactionId) -> Self {
+		self.faction = Some(faction);
+		self
+	}
+	/// Path to a shared goal, see [EventPathRequest::to_shared_goal]
+	pub fn with_shared_goal(mut self, goal_id: GoalId) -> Self {
+		self.shared_goal = Some(goal_id);
+		self
+	}
+	/// Get the shared goal the request leads to
+	pub fn get_shared_goal(&self) -> Option<GoalId> {
+		self.shared_goal
+	}
+	/// Seed the goal sector with additional goals, each `(field_cell,
+	/// initial_cost)` of the `seeds` starting at its cost rather than `0`,
+	/// so that actors head to whichever goal is cheapest once its initial
+	/// cost is included. Seeded goals must lie within the sector the route
+	/// ends in, see [RouteMetadata::with_goal_seeds]
+	pub fn with_goal_seeds(mut self, seeds: &[(FieldCell, u8)]) -> Self {
+		self.goal_seeds = GoalSeeds::new(seeds);
+		self
+	}
+	/// Get the additional goals the goal sector is seeded with
+	pub fn get_goal_seeds(&self) -> GoalSeeds {
+		self.goal_seeds
+	}
+	/// Get the ID correlating the request with the [RouteMetadata] and events
+	/// produced for it
+	pub fn get_request_id(&self) -> RequestId {
+		self.request_id
+	}
+	/// Reuse the ID of an earlier request, such as when a route is requested
+	/// again after being invalidated
+	pub(crate) fn with_request_id(mut self, request_id: RequestId) -> Self {
+		self.request_id = request_id;
+		self
+	}
+}
+
+/// A request for a path from the current position of the `requester` entity.
+/// The plugin reads the [GlobalTransform] of the entity, converts it into the
+/// source sector and [FieldCell] of each bundle and sends an
+/// [EventPathRequest] on behalf of the caller. Requests from entities
+/// without a [GlobalTransform] or positioned outside of the map are ignored
+#[cfg(any(feature = "2d", feature = "3d"))]
+#[derive(Event, Clone, Copy)]
+pub struct EventPathRequestFromEntity {
+	/// The entity requesting a path, its [GlobalTransform] is the source
+	requester: Entity,
+	/// How the translation of the requester is converted into a
+	/// [WorldPosition], if [None] the [CoordinateSystem] of each bundle is used
+	coordinate_system: Option<CoordinateSystem>,
+	/// The path request, its source is filled in from the requester
+	request: EventPathRequest,
+}
+
+#[cfg(any(feature = "2d", feature = "3d"))]
+impl EventPathRequestFromEntity {
+	/// Create a request from the position of the `requester` in the
+	/// [CoordinateSystem] of each bundle
+	pub fn new(requester: Entity, target_sector: SectorID, target_goal: FieldCell) -> Self {
+		EventPathRequestFromEntity {
+			requester,
+			coordinate_system: None,
+			request: EventPathRequest::new(
+				SectorID::default(),
+				FieldCell::default(),
+				target_sector,
+				target_goal,
+			),
+		}
+	}
+	/// Create a request from the `x, y` position of the `requester` in a 2d
+	/// world
+	#[cfg(feature = "2d")]
+	pub fn new_xy(requester: Entity, target_sector: SectorID, target_goal: FieldCell) -> Self {
+		EventPathRequestFromEntity {
+			requester,
+			coordinate_system: Some(CoordinateSystem::Xy),
+			request: EventPathRequest::new(
+				SectorID::default(),
+				FieldCell::default(),
+				target_sector,
+				target_goal,
+			),
+		}
+	}
+	/// Create a request from the `x, z` position of the `requester` in a 3d
+	/// world
+	#[cfg(feature = "3d")]
+	pub fn new_xyz(requester: Entity, target_sector: SectorID, target_goal: FieldCell) -> Self {
+		EventPathRequestFromEntity {
+			requester,
+			coordinate_system: Some(CoordinateSystem::Xyz),
+			request: EventPathRequest::new(
+				SectorID::default(),
+				FieldCell::default(),
+				target_sector,
+				target_goal,
+			),
+		}
+	}
+	/// Set the [PathPriority] of the request, see [EventPathRequest::with_priority]
+	pub fn with_priority(mut self, priority: PathPriority) -> Self {
+		self.request = self.request.with_priority(priority);
+		self
+	}
+	/// Only generate a route within the bundle of the `nav_world` entity. If
+	/// not set the [NavWorld] of the requester is used when it has one
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.request = self.request.with_nav_world(nav_world);
+		self
+	}
+	/// Set the [GoalFallback] policy, see [EventPathRequest::with_goal_fallback]
+	pub fn with_goal_fallback(mut self, goal_fallback: GoalFallback) -> Self {
+		self.request = self.request.with_goal_fallback(goal_fallback);
+		self
+	}
+	/// The actor stops once it is within `distance` of the world
+	/// `goal_position`, see [EventPathRequest::with_stopping_distance]
+	pub fn with_stopping_distance(mut self, goal_position: Vec3, distance: f32) -> Self {
+		self.request = self.request.with_stopping_distance(goal_position, distance);
+		self
+	}
+	/// Set the cost budget of the route, see [EventPathRequest::with_max_cost]
+	pub fn with_max_cost(mut self, max_cost: i32) -> Self {
+		self.request = self.request.with_max_cost(max_cost);
+		self
+	}
+	/// Set the time budget of the route, see [EventPathRequest::with_max_time]
+	pub fn with_max_time(
+		mut self,
+		max_time: Duration,
+		speed: TraversalSpeed,
+		map_dimensions: &MapDimensions,
+	) -> Self {
+		self.request = self.request.with_max_time(max_time, speed, map_dimensions);
+		self
+	}
+	/// Path on behalf of a `player`, see [EventPathRequest::with_player]
+	pub fn with_player(mut self, player: PlayerId) -> Self {
+		self.request = self.request.with_player(player);
+		self
+	}
+	/// Path on behalf of a `faction`, see [EventPathRequest::with_faction]
+	pub fn with_faction(mut self, faction: FactionId) -> Self {
+		self.request = self.request.with_faction(faction);
+		self
+	}
+	/// Seed the goal sector with additional goals, see
+	/// [EventPathRequest::with_goal_seeds]
+	pub fn with_goal_seeds(mut self, seeds: &[(FieldCell, u8)]) -> Self {
+		self.request = self.request.with_goal_seeds(seeds);
+		self
+	}
+	/// Path to a shared goal rather than the target, see
+	/// [EventPathRequest::to_shared_goal]
+	pub fn with_shared_goal(mut self, goal_id: GoalId) -> Self {
+		self.request = self.request.with_shared_goal(goal_id);
+		self
+	}
+	/// Get the entity requesting a path
+	pub fn get_requester(&self) -> Entity {
+		self.requester
+	}
+	/// Get the ID correlating the request with the [RouteMetadata] and events
+	/// produced for it, see [EventPathRequest::get_request_id]
+	pub fn get_request_id(&self) -> RequestId {
+		self.request.get_request_id()
+	}
+	/// Create the [EventPathRequest] from the `translation` of the requester.
+	/// Returns [None] if the translation lies outside of the map
+	pub fn to_path_request(
+		&self,
+		translation: Vec3,
+		map_dimensions: &MapDimensions,
+	) -> Option<EventPathRequest> {
+		self.path_request_in(
+			translation,
+			map_dimensions,
+			self.coordinate_system.unwrap_or_default(),
+		)
+	}
+	/// As with [EventPathRequestFromEntity::to_path_request] where the
+	/// `coordinate_system` of the bundle is used unless the request was
+	/// created for a specific one
+	fn path_request_in(
+		&self,
+		translation: Vec3,
+		map_dimensions: &MapDimensions,
+		coordinate_system: CoordinateSystem,
+	) -> Option<EventPathRequest> {
+		let coordinate_system = self.coordinate_system.unwrap_or(coordinate_system);
+		let position = WorldPosition::from_translation(translation, coordinate_system);
+		if !position.is_within(map_dimensions) {
+			return None;
 		}
+		let (source_sector, source_field_cell) =
+			position.get_sector_and_field_cell(map_dimensions)?;
+		let mut request = self.request;
+		request.source_sector = source_sector;
+		request.source_field_cell = source_field_cell;
+		Some(request)
 	}
 }
 
-/// Process [EventPathRequest] and generate Routes to go into the [RouteCache] queue
+/// Convert each [EventPathRequestFromEntity] into an [EventPathRequest] for
+/// every bundle the requester is positioned within
+#[cfg(any(feature = "2d", feature = "3d"))]
 #[cfg(not(tarpaulin_include))]
+pub fn process_entity_path_requests(
+	mut events: EventReader<EventPathRequestFromEntity>,
+	requesters: Query<(&GlobalTransform, Option<&NavWorld>)>,
+	bundles: Query<(Entity, &MapDimensions, Option<&CoordinateSystem>)>,
+	mut event_path_request: EventWriter<EventPathRequest>,
+) {
+	for event in events.read() {
+		let Ok((transform, nav)) = requesters.get(event.requester) else {
+			warn!(
+				"Path requester {:?} has no GlobalTransform, ignoring the request",
+				event.requester
+			);
+			continue;
+		};
+		let nav_world = event.request.nav_world.or(nav.map(|nav| nav.get()));
+		for (entity, map_dimensions, coordinate_system) in bundles.iter() {
+			if nav_world.is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			if let Some(request) = event.path_request_in(
+				transform.translation(),
+				map_dimensions,
+				coordinate_system.copied().unwrap_or_default(),
+			) {
+				event_path_request.send(request.with_nav_world(entity));
+			} else {
+				warn!(
+					"Path requester {:?} at {:?} is outside of the map, ignoring the request",
+					event.requester,
+					transform.translation()
+				);
+			}
+		}
+	}
+}
+
+/// Sent when an [EventPathRequest] is queued to be built, or joins a route
+/// already queued by a nearby actor, describing how busy the queues are so
+/// that a UI can show a "calculating path" indicator and AI can defer non
+/// urgent requests
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EventPathQueued {
+	/// The bundle entity the route is being built in
+	nav_world: Entity,
+	/// The metadata of the queued route
+	metadata: RouteMetadata,
+	/// How many queued routes are likely to be built before this one
+	position: usize,
+	/// How many routes are queued in total
+	depth: usize,
+	/// Estimated number of frames until the [FlowField]s are built
+	frames_to_ready: Option<u32>,
+}
+
+impl EventPathQueued {
+	/// Get the bundle entity the route is being built in
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+	/// Get the metadata of the queued route
+	pub fn get_metadata(&self) -> RouteMetadata {
+		self.metadata
+	}
+	/// Get the ID of the [EventPathRequest] the route was queued for
+	pub fn get_request_id(&self) -> Option<RequestId> {
+		self.metadata.get_request_id()
+	}
+	/// Get how many queued routes are likely to be built before this one
+	pub fn get_position(&self) -> usize {
+		self.position
+	}
+	/// Get how many routes are queued in total, including this one
+	pub fn get_depth(&self) -> usize {
+		self.depth
+	}
+	/// Get the estimated number of frames until the [FlowField]s of the route
+	/// are built based on the recent throughput of the [FlowFieldQueue], see
+	/// [FlowFieldQueue::estimate_frames_to_ready]. [None] if nothing has been
+	/// built yet to estimate from
+	pub fn get_frames_to_ready(&self) -> Option<u32> {
+		self.frames_to_ready
+	}
+}
+
+/// Sent when the cheapest route of an [EventPathRequest] would cost more than
+/// the budget set with [EventPathRequest::with_max_cost], no fields are
+/// built for it
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EventPathTooExpensive {
+	/// The bundle entity the route was searched for in
+	nav_world: Entity,
+	/// The metadata of the rejected route
+	metadata: RouteMetadata,
+}
+
+impl EventPathTooExpensive {
+	/// Get the bundle entity the route was searched for in
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+	/// Get the metadata of the rejected route
+	pub fn get_metadata(&self) -> RouteMetadata {
+		self.metadata
+	}
+	/// Get the ID of the rejected [EventPathRequest]
+	pub fn get_request_id(&self) -> Option<RequestId> {
+		self.metadata.get_request_id()
+	}
+}
+
+/// Sent when the goal of an [EventPathRequest] with the [GoalFallback::Exact]
+/// policy is impassable, either when the request is received or because the
+/// costs changed while its route was queued. No fields are built for it
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EventPathGoalImpassable {
+	/// The bundle entity the route was requested in
+	nav_world: Entity,
+	/// The metadata of the rejected route
+	metadata: RouteMetadata,
+}
+
+impl EventPathGoalImpassable {
+	/// Get the bundle entity the route was requested in
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+	/// Get the metadata of the rejected route
+	pub fn get_metadata(&self) -> RouteMetadata {
+		self.metadata
+	}
+	/// Get the ID of the rejected [EventPathRequest]
+	pub fn get_request_id(&self) -> Option<RequestId> {
+		self.metadata.get_request_id()
+	}
+}
+
+/// Sent once the route of an [EventPathRequest] is in the [RouteCache] and
+/// its [FlowField]s are cached. Every request is sent its own event carrying
+/// its [RequestId], including requests answered by a route which was
+/// already cached and requests following the route of a nearby actor
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EventPathReady {
+	/// The bundle entity the route was built in
+	nav_world: Entity,
+	/// The metadata of the request
+	metadata: RouteMetadata,
+}
+
+impl EventPathReady {
+	/// Get the bundle entity the route was built in
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+	/// Get the metadata of the request
+	pub fn get_metadata(&self) -> RouteMetadata {
+		self.metadata
+	}
+	/// Get the ID of the [EventPathRequest] which is ready
+	pub fn get_request_id(&self) -> Option<RequestId> {
+		self.metadata.get_request_id()
+	}
+}
+
+/// A hint of where an actor following a queued route is, used to build the
+/// [FlowField]s of the sectors it's about to enter ahead of the rest of the
+/// queue. Send it each time the actor moves while waiting on its route, see
+/// [FlowFieldQueue::hint_actor_position]
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EventActorPositionHint {
+	/// The metadata of the queued route the actor is following
+	metadata: RouteMetadata,
+	/// The sector the actor is in
+	sector_id: SectorID,
+	/// The field cell the actor is in
+	field_cell: FieldCell,
+	/// The bundle entity the route is queued in, if [None] then every bundle
+	/// is hinted
+	nav_world: Option<Entity>,
+}
+
+impl EventActorPositionHint {
+	/// Create a new hint that an actor following the route of `metadata` is
+	/// at `field_cell` of `sector_id`
+	pub fn new(metadata: RouteMetadata, sector_id: SectorID, field_cell: FieldCell) -> Self {
+		EventActorPositionHint {
+			metadata,
+			sector_id,
+			field_cell,
+			nav_world: None,
+		}
+	}
+	/// Only hint the route within the bundle `nav_world`
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+}
+
+/// Record each [EventActorPositionHint] in the [FlowFieldQueue] of its bundle
+#[cfg(not(tarpaulin_include))]
+pub fn process_position_hints(
+	mut events: EventReader<EventActorPositionHint>,
+	mut queue_q: Query<(Entity, &mut FlowFieldQueue)>,
+) {
+	for event in events.read() {
+		for (entity, mut f_queue) in &mut queue_q {
+			if event.nav_world.is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			f_queue.hint_actor_position(event.metadata, event.sector_id, event.field_cell);
+		}
+	}
+}
+
+/// Process [EventPathRequest] and generate Routes to go into the [RouteQueue].
+/// The [RouteCache] is only read so that cached routes can be reused
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn event_insert_route_queue(
 	mut events: EventReader<EventPathRequest>,
 	mut cache_q: Query<(
-		&mut RouteCache,
+		Entity,
+		&mut RouteQueue,
+		&RouteCache,
 		&PortalGraph,
 		&SectorPortals,
 		&SectorCostFields,
+		&MapDimensions,
+		&RouteCostModel,
 	)>,
+	mut flow_cache_q: Query<(&mut FlowFieldQueue, &FlowFieldCache)>,
+	visibility_q: Query<&SectorVisibility>,
+	zones_q: Query<&FactionZones>,
+	goals_q: Query<&SharedGoals>,
 	time: Res<Time>,
+	turn: Option<Res<FlowFieldTurn>>,
+	state: Option<Res<FlowFieldSystemState>>,
+	mut pending: Local<Vec<(EventPathRequest, Stamp)>>,
+	mut stats: Option<ResMut<FlowFieldStats>>,
+	mut event_path_queued: EventWriter<EventPathQueued>,
+	mut event_too_expensive: EventWriter<EventPathTooExpensive>,
+	mut event_goal_impassable: EventWriter<EventPathGoalImpassable>,
 ) {
+	let elapsed = get_navigation_elapsed(&time, turn.as_deref(), state.as_deref());
+	// buffer requests so they aren't lost while higher priority requests are
+	// serviced ahead of them
+	for event in events.read() {
+		pending.push((*event, elapsed));
+	}
 	// several actors may send requests at once, instead of stepping through the events one at time
 	// blitz thorugh duplicates so only a fresh request gets processed each tick - this is critical to perf
 	let mut is_duplicate = true;
 	while is_duplicate {
-		if let Some(event) = events.read().next() {
-			for (mut cache, graph, sector_portals, sector_cost_fields_scaled) in cache_q.iter_mut()
+		if let Some(event) = take_next_request(&mut pending, elapsed) {
+			for (
+				entity,
+				mut r_queue,
+				r_cache,
+				graph,
+				sector_portals,
+				sector_cost_fields_scaled,
+				map_dimensions,
+				cost_model,
+			) in cache_q.iter_mut()
 			{
-				// ignore requests to an impassable goal
-				if let Some(goal_sector) = sector_cost_fields_scaled
-					.get_scaled()
-					.get(&event.target_sector)
-				{
-					let target_cost = goal_sector.get_field_cell_value(event.target_goal);
-					if target_cost == 255 {
+				// only path within the targeted bundle
+				if event.nav_world.is_some_and(|nav| nav != entity) {
+					continue;
+				}
+				// a shared goal targets wherever the goal currently is within
+				// this bundle
+				let mut event = event;
+				if let Some(goal_id) = event.shared_goal {
+					let Some((sector_id, field_cell)) = goals_q
+						.get(entity)
+						.ok()
+						.and_then(|goals| goals.get_goal(goal_id))
+					else {
 						continue;
-					}
+					};
+					event.target_sector = sector_id;
+					event.target_goal = field_cell;
 				}
 				// only run if the cache doesn't contain the route already
-				let rm = RouteMetadata::new(
+				let mut rm = RouteMetadata::new(
 					event.source_sector,
 					event.source_field_cell,
 					event.target_sector,
 					event.target_goal,
-					time.elapsed(),
-				);
-				if !cache.get_routes().contains_key(&rm) {
-					is_duplicate = false;
-					if let Some(mut path) = graph.find_best_path(
-						(event.source_sector, event.source_field_cell),
-						(event.target_sector, event.target_goal),
-						sector_portals,
-						sector_cost_fields_scaled,
-					) {
-						if !path.is_empty() {
-							filter_path(&mut path, event.target_goal);
+					elapsed,
+				)
+				.with_goal_fallback(event.goal_fallback)
+				.with_priority(event.priority);
+				if let Some((goal_position, distance)) = event.stopping {
+					rm = rm.with_stopping_distance(goal_position, distance);
+				}
+				if let Some(max_cost) = event.max_cost {
+					rm = rm.with_max_cost(max_cost);
+				}
+				if let Some(player) = event.player {
+					rm = rm.with_player(player);
+				}
+				if let Some(faction) = event.faction {
+					rm = rm.with_faction(faction);
+				}
+				if let Some(goal_id) = event.shared_goal {
+					rm = rm.with_shared_goal(goal_id);
+				}
+				rm = rm
+					.with_goal_seeds(event.goal_seeds)
+					.with_request_id(event.request_id);
+				let zones = zones_q.get(entity).ok();
+				let is_goal_restricted =
+					zones.zip(event.faction).is_some_and(|(zones, faction)| {
+						zones.is_cell_restricted(faction, event.target_sector, event.target_goal)
+					});
+				// fail fast on requests to an impassable goal rather than
+				// searching for a route which can never reach it
+				if event.goal_fallback == GoalFallback::Exact
+					&& (is_goal_restricted
+						|| !sector_cost_fields_scaled
+							.is_pathable(event.target_sector, event.target_goal))
+				{
+					event_goal_impassable.send(EventPathGoalImpassable {
+						nav_world: entity,
+						metadata: rm,
+					});
+					continue;
+				}
+				// a cached route built with a different stopping distance may
+				// be missing fields this request needs
+				let cached = r_cache.get_route_from_metadata(&rm).filter(|(cached, _)| {
+					cached.get_stopping_distance().is_none()
+						|| (cached.get_goal_position(), cached.get_stopping_distance())
+							== (rm.get_goal_position(), rm.get_stopping_distance())
+				});
+				// the cached route may have been built without a budget
+				let mut is_cached = cached.is_some();
+				let mut is_over_budget = false;
+				if let Some(((_, route), max_cost)) = cached.as_ref().zip(rm.get_max_cost()) {
+					let cost = route
+						.get_waypoint_costs(
+							(rm.get_source_sector(), rm.get_source_field_cell()),
+							sector_cost_fields_scaled,
+						)
+						.and_then(|costs| costs.last().copied());
+					match cost {
+						Some(cost) => is_over_budget = cost > max_cost,
+						// a route which can't be walked any more is searched
+						// for again
+						None => is_cached = false,
+					}
+				}
+				if let Some(stats) = stats.as_mut() {
+					stats.record(|c| c.record_route_request(is_cached));
+				}
+				if is_over_budget {
+					event_too_expensive.send(EventPathTooExpensive {
+						nav_world: entity,
+						metadata: rm,
+					});
+					continue;
+				}
+				if is_cached {
+					// metadata ignores the shared goal so a route cached by
+					// another request may not yet be known to lead to it
+					if let Some(goal_id) = rm.get_shared_goal() {
+						if !r_cache.is_route_to_goal(goal_id, &rm) {
+							r_queue.tag_shared_goal(rm);
 						}
-						cache.add_to_queue(rm, Route::new(path));
-					} else {
-						// a portal based route could not be found or the actor
-						// is within the same sector as the goal
-						debug!(
-						"No portal path found, either local sector movement or just doesn't exist"
-					);
-						if let Some(cost_field) = sector_cost_fields_scaled
-							.get_scaled()
-							.get(&event.target_sector)
-						{
-							let vis = cost_field
-								.is_cell_pair_reachable(event.source_field_cell, event.target_goal);
-							// if the two cells are reachable from within the same sector
-							// then there is a local route
-							if vis {
-								cache.add_to_queue(
-									rm,
-									Route::new(vec![(event.target_sector, event.target_goal)]),
-								);
+					}
+					// it's ready once any fields still being built for the
+					// cached route are done
+					if let Ok((mut f_queue, _)) = flow_cache_q.get_mut(entity) {
+						f_queue.add_waiting(rm, rm);
+					}
+					continue;
+				}
+				// an actor near another heading to the same goal can share
+				// its route rather than searching again
+				if let Some(leader) = r_queue.find_leader(r_cache, &rm, sector_cost_fields_scaled) {
+					let is_leader_cached = !r_queue.get_queue().contains_key(&leader);
+					if r_queue.add_follower(r_cache, leader, rm) {
+						// the fields of a cached leader may still be queued,
+						// they're built as urgently as the follower needs
+						if let Ok((mut f_queue, _)) = flow_cache_q.get_mut(entity) {
+							f_queue.raise_priority(&leader, rm.get_priority());
+							// followers of a queued leader wait on it once
+							// it's promoted
+							if is_leader_cached {
+								f_queue.add_waiting(leader, rm);
 							}
 						}
+						let leader =
+							leader.with_priority(leader.get_priority().max(rm.get_priority()));
+						let flow_cache = flow_cache_q.get(entity).ok();
+						event_path_queued.send(queued_event(
+							entity, rm, &leader, &r_queue, flow_cache, elapsed,
+						));
+						continue;
+					}
+				}
+				let flow_cache = flow_cache_q.get(entity).ok();
+				is_duplicate = false;
+				// limit the search to the sectors explored by the player
+				let visibility = visibility_q.get(entity).ok();
+				let fogged = visibility.zip(event.player).map(|(visibility, player)| {
+					VisibilityCostModel::new(cost_model.get(), visibility, player)
+				});
+				let visible_model: &dyn PortalCostModel = match fogged.as_ref() {
+					Some(fogged) => fogged,
+					None => cost_model.get(),
+				};
+				// and keep it out of the restricted zones of its faction
+				let zoned = zones.zip(event.faction).map(|(zones, faction)| {
+					ZoneCostModel::new(visible_model, zones, faction, sector_cost_fields_scaled)
+				});
+				let search_model: &dyn PortalCostModel = match zoned.as_ref() {
+					Some(zoned) => zoned,
+					None => visible_model,
+				};
+				match find_route_with_fallback(
+					graph,
+					sector_portals,
+					sector_cost_fields_scaled,
+					map_dimensions,
+					search_model,
+					&rm,
+				) {
+					Ok((goal, route)) => {
+						rm.set_resolved_target(goal.0, goal.1);
+						r_queue.add_to_queue(rm, route);
+						event_path_queued
+							.send(queued_event(entity, rm, &rm, &r_queue, flow_cache, elapsed));
 					}
+					Err(PathSearchError::PathTooExpensive) => {
+						event_too_expensive.send(EventPathTooExpensive {
+							nav_world: entity,
+							metadata: rm,
+						});
+					}
+					Err(PathSearchError::NoPath) => {}
 				}
 			}
 		} else {
@@ -113,6 +822,367 @@ pub fn event_insert_route_queue(
 	}
 }
 
+/// Describe the queue position of the `metadata` of a request, which is
+/// built once the `queued` route (itself, or the route it follows) is built
+fn queued_event(
+	nav_world: Entity,
+	metadata: RouteMetadata,
+	queued: &RouteMetadata,
+	route_queue: &RouteQueue,
+	flow_cache: Option<(&FlowFieldQueue, &FlowFieldCache)>,
+	elapsed: Stamp,
+) -> EventPathQueued {
+	// queued routes are promoted into the flow field queue each frame, so
+	// both queues compete for the same builds
+	let rank = queued.get_aged_rank(elapsed);
+	let mut position = route_queue
+		.get_queue()
+		.keys()
+		.filter(|other| *other != queued && other.get_aged_rank(elapsed) >= rank)
+		.count();
+	let mut depth = route_queue.get_queue().len();
+	if let Some((f_queue, f_cache)) = flow_cache {
+		position += f_queue.count_queued_ahead(queued, elapsed, f_cache);
+		depth += f_queue.get_queue().len();
+	}
+	EventPathQueued {
+		nav_world,
+		metadata,
+		position,
+		depth,
+		frames_to_ready: flow_cache
+			.and_then(|(f_queue, _)| f_queue.estimate_frames_to_ready(position)),
+	}
+}
+
+/// Remove the pending request with the highest [PathPriority] once aged by
+/// how long it has been waiting since it arrived at the `elapsed` time, the
+/// earliest request wins a tie. Requests which arrived during a turn (a
+/// [Stamp::Turn]) don't age so they're taken strictly by priority
+fn take_next_request(
+	pending: &mut Vec<(EventPathRequest, Stamp)>,
+	elapsed: Stamp,
+) -> Option<EventPathRequest> {
+	let mut next: Option<(u128, usize)> = None;
+	for (i, (request, arrived)) in pending.iter().enumerate() {
+		let waited = elapsed.time_since(*arrived).unwrap_or_default();
+		let rank = request.priority.get_aged_rank(waited);
+		if next.is_none_or(|(best, _)| rank > best) {
+			next = Some((rank, i));
+		}
+	}
+	next.map(|(_, i)| pending.remove(i).0)
+}
+
+/// Find a high-level [Route] of sector-portals from the `source` to the
+/// `target` where the portals are scored with the `cost_model`. If a
+/// `max_cost` is given routes costing more are rejected with
+/// [PathSearchError::PathTooExpensive]. Returns [PathSearchError::NoPath] if
+/// the target is impassable or cannot be reached
+pub fn find_route(
+	graph: &PortalGraph,
+	sector_portals: &SectorPortals,
+	sector_cost_fields: &SectorCostFields,
+	cost_model: &dyn PortalCostModel,
+	source: (SectorID, FieldCell),
+	target: (SectorID, FieldCell),
+	max_cost: Option<i32>,
+) -> Result<Route, PathSearchError> {
+	let Some(target_cost_field) = sector_cost_fields.get_scaled().get(&target.0) else {
+		return Err(PathSearchError::NoPath);
+	};
+	if target_cost_field.get_field_cell_value(target.1) == 255 {
+		return Err(PathSearchError::NoPath);
+	}
+	let search = match max_cost {
+		Some(max_cost) => graph.find_best_path_within_budget(
+			source,
+			target,
+			sector_portals,
+			sector_cost_fields,
+			cost_model,
+			max_cost,
+		),
+		None => graph
+			.find_best_path_with_model(
+				source,
+				target,
+				sector_portals,
+				sector_cost_fields,
+				cost_model,
+			)
+			.ok_or(PathSearchError::NoPath),
+	};
+	match search {
+		Ok(mut path) => {
+			if !path.is_empty() {
+				filter_path(&mut path, target.1);
+			}
+			Ok(Route::new(path))
+		}
+		Err(PathSearchError::PathTooExpensive) => Err(PathSearchError::PathTooExpensive),
+		Err(PathSearchError::NoPath) => {
+			// a portal based route could not be found or the actor
+			// is within the same sector as the goal
+			debug!("No portal path found, either local sector movement or just doesn't exist");
+			find_local_route(sector_cost_fields, source, target, max_cost)
+		}
+	}
+}
+
+/// Find a [Route] from the `source` to the `target` which never leaves their
+/// sector, such as when a single sector covers the whole map and there is no
+/// [PortalGraph] to search. Returns [PathSearchError::NoPath] if the cells
+/// are in different sectors or can't reach each other
+pub fn find_local_route(
+	sector_cost_fields: &SectorCostFields,
+	source: (SectorID, FieldCell),
+	target: (SectorID, FieldCell),
+	max_cost: Option<i32>,
+) -> Result<Route, PathSearchError> {
+	if source.0 != target.0 {
+		return Err(PathSearchError::NoPath);
+	}
+	let Some(cost_field) = sector_cost_fields.get_scaled().get(&target.0) else {
+		return Err(PathSearchError::NoPath);
+	};
+	if cost_field.get_field_cell_value(target.1) == 255 {
+		return Err(PathSearchError::NoPath);
+	}
+	match cost_field.get_integrated_cost_between_cells(&source.1, &target.1) {
+		Some(cost) if max_cost.is_some_and(|max| cost > max) => {
+			Err(PathSearchError::PathTooExpensive)
+		}
+		Some(_) => Ok(Route::new(vec![target])),
+		None => Err(PathSearchError::NoPath),
+	}
+}
+
+/// Find a [Route] from the source to the target of the `metadata` where its
+/// [GoalFallback] policy decides upon an alternative goal if the target is
+/// impassable or unreachable. Returns the goal that was used alongside the
+/// [Route]. A target which can be reached but only by exceeding the
+/// [RouteMetadata::get_max_cost] budget is rejected rather than replaced
+pub fn find_route_with_fallback(
+	graph: &PortalGraph,
+	sector_portals: &SectorPortals,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+	cost_model: &dyn PortalCostModel,
+	metadata: &RouteMetadata,
+) -> Result<((SectorID, FieldCell), Route), PathSearchError> {
+	let source = (
+		metadata.get_source_sector(),
+		metadata.get_source_field_cell(),
+	);
+	let target = (metadata.get_target_sector(), metadata.get_target_goal());
+	let max_cost = metadata.get_max_cost();
+	// a map covered by a single sector has no portals so the route is
+	// always local to the sector
+	let search = |goal: (SectorID, FieldCell)| {
+		if map_dimensions.is_single_sector() {
+			find_local_route(sector_cost_fields, source, goal, max_cost)
+		} else {
+			find_route(
+				graph,
+				sector_portals,
+				sector_cost_fields,
+				cost_model,
+				source,
+				goal,
+				max_cost,
+			)
+		}
+	};
+	let goal = match metadata.get_goal_fallback() {
+		GoalFallback::Exact => target,
+		GoalFallback::NearestPathable => {
+			find_nearest_cell(target, map_dimensions, |column, row| {
+				get_global_cost(sector_cost_fields, map_dimensions, column, row) != 255
+			})
+			.ok_or(PathSearchError::NoPath)?
+		}
+		GoalFallback::NearestReachable => {
+			match search(target) {
+				Ok(route) => return Ok((target, route)),
+				Err(PathSearchError::PathTooExpensive) => {
+					return Err(PathSearchError::PathTooExpensive)
+				}
+				Err(PathSearchError::NoPath) => {}
+			}
+			let reachable = flood_reachable(source, sector_cost_fields, map_dimensions);
+			let columns = map_dimensions.get_total_field_cell_columns();
+			find_nearest_cell(target, map_dimensions, |column, row| {
+				reachable[row * columns + column]
+			})
+			.ok_or(PathSearchError::NoPath)?
+		}
+	};
+	search(goal).map(|route| (goal, route))
+}
+
+/// Get the scaled cost of a [FieldCell] from its global `(column, row)`
+/// index, a missing sector is treated as impassable
+pub(crate) fn get_global_cost(
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+	column: usize,
+	row: usize,
+) -> u8 {
+	map_dimensions
+		.sector_and_cell_from_global(column as u32, row as u32)
+		.and_then(|(sector_id, field_cell)| {
+			sector_cost_fields
+				.get_scaled()
+				.get(&sector_id)
+				.map(|cost_field| cost_field.get_field_cell_value(field_cell))
+		})
+		.unwrap_or(255)
+}
+
+/// Get the orthogonal neighbours of a global `(column, row)` index which
+/// reside within the map, in the order of north, east, south and west
+pub(crate) fn get_global_neighbours(
+	column: usize,
+	row: usize,
+	map_dimensions: &MapDimensions,
+) -> Vec<(usize, usize)> {
+	let mut neighbours = Vec::with_capacity(4);
+	if row > 0 {
+		neighbours.push((column, row - 1));
+	}
+	if column + 1 < map_dimensions.get_total_field_cell_columns() {
+		neighbours.push((column + 1, row));
+	}
+	if row + 1 < map_dimensions.get_total_field_cell_rows() {
+		neighbours.push((column, row + 1));
+	}
+	if column > 0 {
+		neighbours.push((column - 1, row));
+	}
+	neighbours
+}
+
+/// Integrate the cost of travelling from the global `(column, row)` index of
+/// the `source` across the pathable [FieldCell]s of all sectors, moving
+/// orthogonally. The cost of reaching a cell is the sum of the scaled costs
+/// of the cells moved into (the source is free). Cells for which `is_within`
+/// is false, or which cost more than `budget` to reach, aren't expanded
+/// into. Each cell is passed to `visit` with its cost once its cheapest cost
+/// is known, in order of increasing cost, and the search stops as soon as
+/// `visit` returns false.
+///
+/// Returns the cost of each cell reached, only the cells searched are
+/// recorded so a small budget or area keeps the search cheap on a large map
+pub(crate) fn integrate_global_costs(
+	source: (usize, usize),
+	budget: u32,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+	is_within: impl Fn(usize, usize) -> bool,
+	mut visit: impl FnMut(usize, usize, u32) -> bool,
+) -> BTreeMap<(usize, usize), u32> {
+	let mut integrated = BTreeMap::new();
+	let mut queue = BinaryHeap::new();
+	integrated.insert(source, 0);
+	queue.push(Reverse((0, source)));
+	while let Some(Reverse((cumulative, (column, row)))) = queue.pop() {
+		// skip stale entries that have since been reached more cheaply
+		if integrated
+			.get(&(column, row))
+			.is_some_and(|cheapest| cumulative > *cheapest)
+		{
+			continue;
+		}
+		if !visit(column, row, cumulative) {
+			break;
+		}
+		for neighbour in get_global_neighbours(column, row, map_dimensions) {
+			if !is_within(neighbour.0, neighbour.1) {
+				continue;
+			}
+			let n_cost =
+				get_global_cost(sector_cost_fields, map_dimensions, neighbour.0, neighbour.1);
+			if n_cost == 255 {
+				continue;
+			}
+			let n_cumulative = cumulative + n_cost as u32;
+			if n_cumulative <= budget
+				&& integrated
+					.get(&neighbour)
+					.is_none_or(|cheapest| n_cumulative < *cheapest)
+			{
+				integrated.insert(neighbour, n_cumulative);
+				queue.push(Reverse((n_cumulative, neighbour)));
+			}
+		}
+	}
+	integrated
+}
+
+/// Breadth-first search outward from `start` (irrespective of cost) for the
+/// closest [FieldCell] which satisfies the `is_goal` predicate
+fn find_nearest_cell(
+	start: (SectorID, FieldCell),
+	map_dimensions: &MapDimensions,
+	is_goal: impl Fn(usize, usize) -> bool,
+) -> Option<(SectorID, FieldCell)> {
+	let columns = map_dimensions.get_total_field_cell_columns();
+	let rows = map_dimensions.get_total_field_cell_rows();
+	let (start_column, start_row) = map_dimensions.global_cell_from(start.0, start.1);
+	let (start_column, start_row) = (start_column as usize, start_row as usize);
+	if start_column >= columns || start_row >= rows {
+		return None;
+	}
+	let mut visited = vec![false; columns * rows];
+	let mut queue = std::collections::VecDeque::new();
+	visited[start_row * columns + start_column] = true;
+	queue.push_back((start_column, start_row));
+	while let Some((column, row)) = queue.pop_front() {
+		if is_goal(column, row) {
+			return map_dimensions.sector_and_cell_from_global(column as u32, row as u32);
+		}
+		for (n_column, n_row) in get_global_neighbours(column, row, map_dimensions) {
+			if !visited[n_row * columns + n_column] {
+				visited[n_row * columns + n_column] = true;
+				queue.push_back((n_column, n_row));
+			}
+		}
+	}
+	None
+}
+
+/// Flood fill from the `source` across pathable [FieldCell]s of all sectors
+/// to mark which cells can be reached, indexed by `row * columns + column`
+pub(crate) fn flood_reachable(
+	source: (SectorID, FieldCell),
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+) -> Vec<bool> {
+	let columns = map_dimensions.get_total_field_cell_columns();
+	let rows = map_dimensions.get_total_field_cell_rows();
+	let mut reachable = vec![false; columns * rows];
+	let (source_column, source_row) = map_dimensions.global_cell_from(source.0, source.1);
+	let (source_column, source_row) = (source_column as usize, source_row as usize);
+	if source_column >= columns || source_row >= rows {
+		return reachable;
+	}
+	let mut queue = std::collections::VecDeque::new();
+	reachable[source_row * columns + source_column] = true;
+	queue.push_back((source_column, source_row));
+	while let Some((column, row)) = queue.pop_front() {
+		for (n_column, n_row) in get_global_neighbours(column, row, map_dimensions) {
+			if !reachable[n_row * columns + n_column]
+				&& get_global_cost(sector_cost_fields, map_dimensions, n_column, n_row) != 255
+			{
+				reachable[n_row * columns + n_column] = true;
+				queue.push_back((n_column, n_row));
+			}
+		}
+	}
+	reachable
+}
+
 /// Generated portal-portal routes contain two elements for each sector, one
 /// for an actors entry and one for an actors exit, we only need to know
 /// about the elements which an actor would use to exit the sector so we filter
@@ -136,122 +1206,348 @@ pub fn filter_path(path: &mut Vec<(SectorID, FieldCell)>, target_goal: FieldCell
 	*path = path_based_on_portal_exits;
 }
 
-/// Remove items from the queue of the [RouteCache] and promote them as routes
-/// which an actor can use as a high-level pathfinding route while publishing a
-/// new item into the [FlowFieldCache] queue
+/// Remove items from the [RouteQueue] and promote them into the [RouteCache]
+/// as routes which an actor can use as a high-level pathfinding route while
+/// publishing a new item into the [FlowFieldQueue].
+///
+/// The costs may have changed while a route was queued so the goal is checked
+/// again, if it has become impassable the route (and any followers) is
+/// dropped. Requests with the [GoalFallback::Exact] policy are rejected with
+/// an [EventPathGoalImpassable] while the others are requested again so that
+/// their policy can choose a new goal
 #[cfg(not(tarpaulin_include))]
+#[allow(clippy::type_complexity)]
 pub fn process_route_queue(
-	mut cache_q: Query<(&mut RouteCache, &mut FlowFieldCache, &SectorCostFields)>,
+	mut cache_q: Query<(
+		Entity,
+		&mut RouteQueue,
+		&mut RouteCache,
+		&mut FlowFieldQueue,
+		&SectorCostFields,
+		&MapDimensions,
+		Option<&Connectivity>,
+	)>,
+	zones_q: Query<&FactionZones>,
+	mut event_goal_impassable: EventWriter<EventPathGoalImpassable>,
+	mut event_path_request: EventWriter<EventPathRequest>,
+	mut event_path_ready: EventWriter<EventPathReady>,
 ) {
-	for (mut r_cache, mut f_cache, cost_fields) in &mut cache_q {
-		while let Some((metadata, route_to_goal)) = r_cache.get_queue_mut().pop_first() {
-			let mut route_from_goal = route_to_goal.clone();
-			route_from_goal.get_mut().reverse();
-			// store a route from actor to goal so that can actor can use it for high-level pathfinding while the more accurate flowfield representation gets built in the background
-			r_cache.insert_route_with_metadata(metadata, route_to_goal);
-			// add the route from goal to actor into the flowfield cache queue
-			f_cache.add_to_queue(metadata, route_from_goal, cost_fields);
+	for (
+		entity,
+		mut r_queue,
+		mut r_cache,
+		mut f_queue,
+		cost_fields,
+		map_dimensions,
+		connectivity,
+	) in &mut cache_q
+	{
+		// avoid flagging the components as changed when there's nothing to
+		// promote so that readers of the caches aren't woken each frame
+		if !r_queue.is_empty() {
+			let rejected = promote_queued_routes(
+				&mut r_queue,
+				&mut r_cache,
+				&mut f_queue,
+				cost_fields,
+				map_dimensions,
+				// bundles spawned without the component build eight-way fields
+				connectivity.copied().unwrap_or_default(),
+				zones_q.get(entity).ok(),
+			);
+			for request in rejected {
+				if request.get_goal_fallback() == GoalFallback::Exact {
+					event_goal_impassable.send(EventPathGoalImpassable {
+						nav_world: entity,
+						metadata: request,
+					});
+				} else {
+					event_path_request.send(repeat_request(&request, entity));
+				}
+			}
+		}
+		// requests served by routes whose fields are already cached
+		if f_queue.has_waiting() {
+			for request in f_queue.take_ready() {
+				event_path_ready.send(EventPathReady {
+					nav_world: entity,
+					metadata: request,
+				});
+			}
 		}
 	}
 }
 
-/// Inspect the [FlowFieldCache] queue and if the [IntegrationField]s of the
-/// first entry haven't been created then calculate them
+/// Empty the [RouteQueue], each route (and the requests following it) is
+/// inserted into the [RouteCache] and queued in the [FlowFieldQueue] to have
+/// its fields built for actors restricted to the `connectivity`. The fields
+/// of a route searched on behalf of a faction treat the enabled `zones` of
+/// the faction as impassable. Requests which follow a route that was
+/// already cached are inserted without queueing any fields, and cached
+/// routes which served a request to a shared goal are indexed under it.
+/// Each promoted request waits in the [FlowFieldQueue] for the fields of its
+/// route to be built before an [EventPathReady] is sent for it.
+///
+/// Returns the requests whose goal has become impassable since they were
+/// queued, no route is inserted for them
+pub fn promote_queued_routes(
+	r_queue: &mut RouteQueue,
+	r_cache: &mut RouteCache,
+	f_queue: &mut FlowFieldQueue,
+	cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+	connectivity: Connectivity,
+	zones: Option<&FactionZones>,
+) -> Vec<RouteMetadata> {
+	let mut rejected = Vec::new();
+	for (follower, route) in r_queue.take_copies() {
+		r_cache.insert_route_with_metadata(follower, route);
+	}
+	for metadata in r_queue.take_goal_tags() {
+		r_cache.tag_shared_goal(&metadata);
+	}
+	while let Some((metadata, route_to_goal)) = r_queue.get_queue_mut().pop_first() {
+		let (resolved_sector, resolved_goal) = metadata.get_resolved_target();
+		if !cost_fields.is_pathable(resolved_sector, resolved_goal) {
+			rejected.append(&mut r_queue.take_followers(&metadata));
+			rejected.push(metadata);
+			continue;
+		}
+		let mut route_from_goal = route_to_goal.clone();
+		route_from_goal.get_mut().reverse();
+		// requests following this route share it and the fields built for it
+		for mut follower in r_queue.take_followers(&metadata) {
+			follower.set_resolved_target(resolved_sector, resolved_goal);
+			r_cache.insert_route_with_metadata(follower, route_to_goal.clone());
+			f_queue.add_waiting(metadata, follower);
+		}
+		// store a route from actor to goal so that can actor can use it for high-level pathfinding while the more accurate flowfield representation gets built in the background
+		r_cache.insert_route_with_metadata(metadata, route_to_goal);
+		// add the route from goal to actor into the flowfield queue
+		f_queue.add_to_queue(
+			metadata,
+			route_from_goal,
+			cost_fields,
+			map_dimensions,
+			connectivity,
+		);
+		if let Some(zones) = zones {
+			if let Some(int_builder) = f_queue.get_queue_mut().get_mut(&metadata) {
+				int_builder.apply_zones(zones);
+			}
+		}
+		f_queue.add_waiting(metadata, metadata);
+	}
+	rejected
+}
+
+/// Inspect the [FlowFieldQueue] and calculate the [IntegrationField]s of the
+/// most urgent entries, as many as the integration budget of the
+/// [FlowFieldConfig] allows. The batch is chosen by priority and prefetch
+/// (see [FlowFieldQueue::get_next_queued_batch]), with the `parallel`
+/// feature the entries of the batch are then built at once. Once the portals
+/// of an entry have been expanded any transit sector fields which are
+/// already cached are shared rather than rebuilt, the [FlowFieldCache] is
+/// only read
 #[cfg(not(tarpaulin_include))]
 pub fn create_queued_integration_fields(
 	mut cache_q: Query<(
-		&mut FlowFieldCache,
+		&mut FlowFieldQueue,
+		&FlowFieldCache,
 		&SectorPortals,
 		&SectorCostFields,
 		&MapDimensions,
 	)>,
+	time: Res<Time>,
+	turn: Option<Res<FlowFieldTurn>>,
+	state: Option<Res<FlowFieldSystemState>>,
+	config: Res<FlowFieldConfig>,
 ) {
-	for (mut f_cache, sector_portals, sector_cost_fields, map_dimensions) in &mut cache_q {
-		if let Some(mut entry) = f_cache.get_queue_mut().first_entry() {
-			let mut_builder = entry.get_mut();
-			// expand portal goals if not done so
-			if !mut_builder.has_expanded_portals() {
-				mut_builder.expand_field_portals(
+	let elapsed = get_navigation_elapsed(&time, turn.as_deref(), state.as_deref());
+	for (mut f_queue, f_cache, sector_portals, sector_cost_fields, map_dimensions) in &mut cache_q {
+		if f_queue.get_queue().is_empty() {
+			continue;
+		}
+		// the most urgent items in the queue
+		let batch =
+			f_queue.get_next_queued_batch(elapsed, f_cache, config.get_integrations_per_frame());
+		#[cfg(use_rayon)]
+		{
+			f_queue
+				.get_queue_mut()
+				.par_iter_mut()
+				.filter(|(metadata, _)| batch.contains(*metadata))
+				.for_each(|(_, mut_builder)| {
+					expand_integration_portals(
+						mut_builder,
+						sector_portals,
+						sector_cost_fields,
+						map_dimensions,
+					);
+				});
+			f_queue.share_cached_fields(f_cache);
+			f_queue
+				.get_queue_mut()
+				.par_iter_mut()
+				.filter(|(metadata, _)| batch.contains(*metadata))
+				.for_each(|(_, mut_builder)| {
+					build_integration_fields(mut_builder, sector_cost_fields);
+				});
+		}
+		#[cfg(not(use_rayon))]
+		for metadata in batch {
+			if let Some(mut_builder) = f_queue.get_queue_mut().get_mut(&metadata) {
+				expand_integration_portals(
+					mut_builder,
 					sector_portals,
 					sector_cost_fields,
 					map_dimensions,
 				);
-				mut_builder.set_expanded_portals();
-			}
-			// compute line of sight if not done so
-			if !mut_builder.has_los_pass() {
-				mut_builder.calculate_los();
-				mut_builder.set_los_pass();
 			}
-			// if the fields haven't been built then build them
-			if !mut_builder.has_cost_pass() {
-				// let sector_int_fields = build_integration_fields(&sectors_expanded_goals, sector_cost_fields_scaled);
-				mut_builder.build_integrated_cost(sector_cost_fields);
-				mut_builder.set_cost_pass();
+			f_queue.share_cached_fields(f_cache);
+			if let Some(mut_builder) = f_queue.get_queue_mut().get_mut(&metadata) {
+				build_integration_fields(mut_builder, sector_cost_fields);
 			}
 		}
 	}
 }
 
+/// Expand the portal goals of an [IntegrationBuilder] if not done so
+fn expand_integration_portals(
+	mut_builder: &mut IntegrationBuilder,
+	sector_portals: &SectorPortals,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+) {
+	if !mut_builder.has_expanded_portals() {
+		mut_builder.expand_field_portals(sector_portals, sector_cost_fields, map_dimensions);
+		mut_builder.set_expanded_portals();
+	}
+}
+
+/// Perform any outstanding line of sight and cost passes of an
+/// [IntegrationBuilder] whose portals have been expanded
+fn build_integration_fields(
+	mut_builder: &mut IntegrationBuilder,
+	sector_cost_fields: &SectorCostFields,
+) {
+	// compute line of sight if not done so
+	if !mut_builder.has_los_pass() {
+		mut_builder.calculate_los();
+		mut_builder.set_los_pass();
+	}
+	// if the fields haven't been built then build them
+	if !mut_builder.has_cost_pass() {
+		mut_builder.build_integrated_cost(sector_cost_fields);
+		mut_builder.set_cost_pass();
+	}
+}
+
 /// When a queued item has had its [IntegrationField]s built generate the
-/// [FlowField]s for it
+/// [FlowField]s for it, see [insert_completed_flow_fields]. This is the only
+/// system of the pipeline which writes to the [FlowFieldCache]
 #[cfg(not(tarpaulin_include))]
-pub fn create_flow_fields(mut cache_q: Query<&mut FlowFieldCache>, time: Res<Time>) {
-	for mut field_cache in &mut cache_q {
-		if let Some(mut entry) = field_cache.get_queue_mut().first_entry() {
-			// if the integration fields havbe been created then remove form queue and calculate flowfields
-			if entry.get_mut().has_cost_pass() {
-				let int_builder = entry.remove();
-				let sector_int_fields = int_builder.get_integration_fields();
-				let path = int_builder.get_route().get();
-				// build the flow fields
-				for (i, (sector_id, goals, int_field)) in sector_int_fields.iter().enumerate() {
-					let mut flow_field = FlowField::default();
-					// first element is end target, therefore has no info about previous sector for
-					// direction optimisations
-					if i == 0 {
-						flow_field.calculate(goals, None, int_field);
-						field_cache.insert_field(
-							*sector_id,
-							Some(path[i].1),
-							None,
-							time.elapsed(),
-							flow_field,
-						);
-					} else if let Some(dir_prev_sector) =
-						Ordinal::sector_to_sector_direction(sector_int_fields[i - 1].0, *sector_id)
-					{
-						let prev_int_field = &sector_int_fields[i - 1].2;
-						flow_field.calculate(
-							goals,
-							Some((dir_prev_sector, prev_int_field)),
-							int_field,
-						);
-						field_cache.insert_field(
-							*sector_id,
-							None,
-							Some(path[i].1),
-							time.elapsed(),
-							flow_field,
-						);
-					} else {
-						error!("Route from goal to actor {:?}", path);
-					};
-				}
+pub fn create_flow_fields(
+	mut cache_q: Query<(Entity, &mut FlowFieldQueue, &mut FlowFieldCache)>,
+	time: Res<Time>,
+	turn: Option<Res<FlowFieldTurn>>,
+	state: Option<Res<FlowFieldSystemState>>,
+	mut stats: Option<ResMut<FlowFieldStats>>,
+	mut event_path_ready: EventWriter<EventPathReady>,
+) {
+	let elapsed = get_navigation_elapsed(&time, turn.as_deref(), state.as_deref());
+	for (entity, mut field_queue, mut field_cache) in &mut cache_q {
+		if field_queue.get_queue().is_empty() {
+			continue;
+		}
+		let built = insert_completed_flow_fields(&mut field_queue, &mut field_cache, elapsed);
+		if let Some(stats) = stats.as_mut() {
+			for int_builder in built.iter() {
+				stats.record(|c| c.record_built_route(int_builder));
+			}
+		}
+		if !built.is_empty() {
+			for request in field_queue.take_ready() {
+				event_path_ready.send(EventPathReady {
+					nav_world: entity,
+					metadata: request,
+				});
 			}
 		}
 	}
 }
 
-/// Purge any routes older than 15 minutes
+/// Remove any item of the [FlowFieldQueue] which has had its
+/// [IntegrationField]s built and insert the [FlowField]s generated from
+/// them into the [FlowFieldCache], recording the throughput of the queue.
+/// With the `parallel` feature every built item is processed at once and
+/// merged into the cache afterwards, otherwise they're inserted one at a
+/// time.
+///
+/// Returns the [IntegrationBuilder]s the [FlowField]s were generated from
+pub fn insert_completed_flow_fields(
+	field_queue: &mut FlowFieldQueue,
+	field_cache: &mut FlowFieldCache,
+	elapsed: impl Into<Stamp>,
+) -> Vec<IntegrationBuilder> {
+	let elapsed = elapsed.into();
+	#[cfg(use_rayon)]
+	{
+		let built: Vec<RouteMetadata> = field_queue
+			.get_queue()
+			.iter()
+			.filter(|(_, int_builder)| int_builder.has_cost_pass())
+			.map(|(metadata, _)| *metadata)
+			.collect();
+		let builders: Vec<IntegrationBuilder> = built
+			.iter()
+			.filter_map(|metadata| field_queue.get_queue_mut().remove(metadata))
+			.collect();
+		if !field_queue.get_queue().is_empty() || !builders.is_empty() {
+			field_queue.record_throughput(builders.len());
+		}
+		field_cache.insert_flow_fields_batch(&builders, elapsed);
+		builders
+	}
+	#[cfg(not(use_rayon))]
+	{
+		let built: Vec<RouteMetadata> = field_queue
+			.get_queue()
+			.iter()
+			.filter(|(_, int_builder)| int_builder.has_cost_pass())
+			.map(|(metadata, _)| *metadata)
+			.collect();
+		if !field_queue.get_queue().is_empty() {
+			field_queue.record_throughput(built.len());
+		}
+		// if the integration fields have been created then remove from queue and calculate flowfields
+		let builders: Vec<IntegrationBuilder> = built
+			.iter()
+			.filter_map(|metadata| field_queue.get_queue_mut().remove(metadata))
+			.collect();
+		for int_builder in builders.iter() {
+			field_cache.insert_flow_fields(int_builder, elapsed);
+		}
+		builders
+	}
+}
+
+/// Purge any routes older than the route lifetime of the [FlowFieldConfig]
+/// (15 minutes by default), routes are kept while playing turn-based (see
+/// [FlowFieldTurn])
 #[cfg(not(tarpaulin_include))]
-pub fn cleanup_old_routes(mut q_route_cache: Query<&mut RouteCache>, time: Res<Time>) {
+pub fn cleanup_old_routes(
+	mut q_route_cache: Query<&mut RouteCache>,
+	time: Res<Time>,
+	state: Option<Res<FlowFieldSystemState>>,
+	config: Res<FlowFieldConfig>,
+) {
 	for mut cache in q_route_cache.iter_mut() {
 		let mut routes_to_purge = Vec::new();
-		for data in cache.get_mut().keys() {
-			let elapsed = time.elapsed();
-			let diff = elapsed.saturating_sub(data.get_time_generated());
-			if diff.as_secs() > 900 {
+		for data in cache.get_route_metadata() {
+			let elapsed = get_navigation_elapsed(&time, None, state.as_deref());
+			let diff = elapsed.time_since(data.get_time_generated());
+			if diff.is_some_and(|diff| diff > config.get_route_lifetime()) {
 				routes_to_purge.push(*data);
 			}
 		}
@@ -260,15 +1556,22 @@ pub fn cleanup_old_routes(mut q_route_cache: Query<&mut RouteCache>, time: Res<T
 		}
 	}
 }
-/// Purge any [FlowField]s older than 15 minutes
+/// Purge any [FlowField]s older than the field lifetime of the
+/// [FlowFieldConfig] (15 minutes by default), fields are kept while playing
+/// turn-based (see [FlowFieldTurn])
 #[cfg(not(tarpaulin_include))]
-pub fn cleanup_old_flowfields(mut q_flow_cache: Query<&mut FlowFieldCache>, time: Res<Time>) {
+pub fn cleanup_old_flowfields(
+	mut q_flow_cache: Query<&mut FlowFieldCache>,
+	time: Res<Time>,
+	state: Option<Res<FlowFieldSystemState>>,
+	config: Res<FlowFieldConfig>,
+) {
 	for mut cache in q_flow_cache.iter_mut() {
 		let mut routes_to_purge = Vec::new();
-		for data in cache.get_mut().keys() {
-			let elapsed = time.elapsed();
-			let diff = elapsed.saturating_sub(data.get_time_generated());
-			if diff.as_secs() > 900 {
+		for data in cache.get().keys() {
+			let elapsed = get_navigation_elapsed(&time, None, state.as_deref());
+			let diff = elapsed.time_since(data.get_time_generated());
+			if diff.is_some_and(|diff| diff > config.get_field_lifetime()) {
 				routes_to_purge.push(*data);
 			}
 		}
@@ -282,6 +1585,376 @@ pub fn cleanup_old_flowfields(mut q_flow_cache: Query<&mut FlowFieldCache>, time
 mod tests {
 	use super::*;
 
+	#[test]
+	fn promote_and_build_queued_route() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_portals = SectorPortals::new(30, 30, 10);
+		let sector_id = SectorID::new(0, 0);
+		let mut r_queue = RouteQueue::default();
+		let mut r_cache = RouteCache::default();
+		let mut f_queue = FlowFieldQueue::default();
+		let mut f_cache = FlowFieldCache::default();
+		let reachable = RouteMetadata::new(
+			sector_id,
+			FieldCell::new(0, 0),
+			sector_id,
+			FieldCell::new(5, 5),
+			Duration::ZERO,
+		);
+		let walled = RouteMetadata::new(
+			sector_id,
+			FieldCell::new(0, 0),
+			sector_id,
+			FieldCell::new(8, 8),
+			Duration::ZERO,
+		);
+		r_queue.add_to_queue(reachable, Route::new(vec![(sector_id, FieldCell::new(5, 5))]));
+		r_queue.add_to_queue(walled, Route::new(vec![(sector_id, FieldCell::new(8, 8))]));
+		// the goal became impassable while the route was queued
+		cost_fields.set_field_cell_value(sector_id, 255, FieldCell::new(8, 8), &map_dimensions);
+		let rejected = promote_queued_routes(
+			&mut r_queue,
+			&mut r_cache,
+			&mut f_queue,
+			&cost_fields,
+			&map_dimensions,
+			Connectivity::default(),
+			None,
+		);
+		assert_eq!(vec![walled], rejected);
+		assert!(r_queue.get_queue().is_empty());
+		assert_eq!(1, r_cache.len());
+		assert_eq!(1, f_queue.get_queue().len());
+		// nothing is inserted until the integration fields are built
+		assert!(insert_completed_flow_fields(&mut f_queue, &mut f_cache, Duration::ZERO).is_empty());
+		for int_builder in f_queue.get_queue_mut().values_mut() {
+			expand_integration_portals(int_builder, &sector_portals, &cost_fields, &map_dimensions);
+			build_integration_fields(int_builder, &cost_fields);
+		}
+		let built = insert_completed_flow_fields(&mut f_queue, &mut f_cache, Duration::ZERO);
+		assert_eq!(1, built.len());
+		assert!(f_queue.get_queue().is_empty());
+		assert!(f_cache
+			.get_field(sector_id, sector_id, FieldCell::new(5, 5))
+			.is_some());
+	}
+	#[test]
+	fn promote_follower_of_cached_route() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(0, 0);
+		let goal = FieldCell::new(5, 5);
+		let mut r_queue = RouteQueue::default();
+		let mut r_cache = RouteCache::default();
+		let mut f_queue = FlowFieldQueue::default();
+		let leader = RouteMetadata::new(sector_id, FieldCell::new(0, 0), sector_id, goal, Duration::ZERO);
+		r_cache.insert_route_with_metadata(leader, Route::new(vec![(sector_id, goal)]));
+		let follower = RouteMetadata::new(sector_id, FieldCell::new(1, 1), sector_id, goal, Duration::ZERO);
+		assert_eq!(Some(leader), r_queue.find_leader(&r_cache, &follower, &cost_fields));
+		assert!(r_queue.add_follower(&r_cache, leader, follower));
+		// the cache is untouched until the queue is promoted
+		assert_eq!(1, r_cache.len());
+		promote_queued_routes(
+			&mut r_queue,
+			&mut r_cache,
+			&mut f_queue,
+			&cost_fields,
+			&map_dimensions,
+			Connectivity::default(),
+			None,
+		);
+		assert!(r_queue.is_empty());
+		assert_eq!(2, r_cache.len());
+		// the fields of the leader are shared so nothing is built
+		assert!(f_queue.get_queue().is_empty());
+	}
+	#[test]
+	fn faction_fields_avoid_zones() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_portals = SectorPortals::new(30, 30, 10);
+		let sector_id = SectorID::new(0, 0);
+		let faction = FactionId::new(3);
+		let mut zones = FactionZones::new();
+		let wall = (0..9).map(|row| (sector_id, FieldCell::new(3, row)));
+		zones.add_zone(ZoneId::new(0), faction, wall);
+		let mut r_queue = RouteQueue::default();
+		let mut r_cache = RouteCache::default();
+		let mut f_queue = FlowFieldQueue::default();
+		let mut f_cache = FlowFieldCache::default();
+		let goal = FieldCell::new(5, 5);
+		let metadata =
+			RouteMetadata::new(sector_id, FieldCell::new(0, 0), sector_id, goal, Duration::ZERO)
+				.with_faction(faction);
+		r_queue.add_to_queue(metadata, Route::new(vec![(sector_id, goal)]));
+		promote_queued_routes(
+			&mut r_queue,
+			&mut r_cache,
+			&mut f_queue,
+			&cost_fields,
+			&map_dimensions,
+			Connectivity::default(),
+			Some(&zones),
+		);
+		for int_builder in f_queue.get_queue_mut().values_mut() {
+			expand_integration_portals(int_builder, &sector_portals, &cost_fields, &map_dimensions);
+			build_integration_fields(int_builder, &cost_fields);
+		}
+		insert_completed_flow_fields(&mut f_queue, &mut f_cache, Duration::ZERO);
+		assert!(r_cache
+			.get_faction_route(sector_id, FieldCell::new(0, 0), sector_id, goal, faction)
+			.is_some());
+		// the field is only used by the faction
+		assert!(f_cache.get_field(sector_id, sector_id, goal).is_none());
+		assert!(f_cache
+			.get_faction_field(sector_id, sector_id, goal, faction)
+			.is_some());
+		let flow_meta = FlowFieldMetadata::for_lookup(sector_id, sector_id, goal).with_faction(faction);
+		let int_field = f_cache.get_integration_field(&flow_meta).unwrap();
+		let wall_cell = int_field.get_field_cell_value(FieldCell::new(3, 4));
+		assert_eq!(INT_BITS_IMPASSABLE, wall_cell & INT_BITS_IMPASSABLE);
+		// the way around the wall is longer than walking straight through it
+		let cost = int_field.get_field_cell_value(FieldCell::new(0, 0)) & INT_FILTER_BITS_COST;
+		assert!(cost > 5);
+	}
+	#[test]
+	fn request_id_is_propagated() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_portals = SectorPortals::new(30, 30, 10);
+		let sector_id = SectorID::new(0, 0);
+		let goal = FieldCell::new(5, 5);
+		let request = EventPathRequest::new(sector_id, FieldCell::new(0, 0), sector_id, goal);
+		let identical = EventPathRequest::new(sector_id, FieldCell::new(0, 0), sector_id, goal);
+		assert_ne!(request.get_request_id(), identical.get_request_id());
+		let mut r_queue = RouteQueue::default();
+		let mut r_cache = RouteCache::default();
+		let mut f_queue = FlowFieldQueue::default();
+		let mut f_cache = FlowFieldCache::default();
+		let metadata =
+			RouteMetadata::new(sector_id, FieldCell::new(0, 0), sector_id, goal, Duration::ZERO)
+				.with_request_id(request.get_request_id());
+		r_queue.add_to_queue(metadata, Route::new(vec![(sector_id, goal)]));
+		promote_queued_routes(
+			&mut r_queue,
+			&mut r_cache,
+			&mut f_queue,
+			&cost_fields,
+			&map_dimensions,
+			Connectivity::default(),
+			None,
+		);
+		for int_builder in f_queue.get_queue_mut().values_mut() {
+			expand_integration_portals(int_builder, &sector_portals, &cost_fields, &map_dimensions);
+			build_integration_fields(int_builder, &cost_fields);
+		}
+		insert_completed_flow_fields(&mut f_queue, &mut f_cache, Duration::ZERO);
+		let route_meta = r_cache.get_route_metadata().next().unwrap();
+		assert_eq!(Some(request.get_request_id()), route_meta.get_request_id());
+		let (flow_meta, _) = f_cache.get().iter().next().unwrap();
+		assert_eq!(Some(request.get_request_id()), flow_meta.get_request_id());
+		// a route requested again after being invalidated keeps its ID
+		let repeated = repeat_request(route_meta, Entity::PLACEHOLDER);
+		assert_eq!(request.get_request_id(), repeated.get_request_id());
+	}
+	#[test]
+	fn integrate_global_costs_within_budget() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		// a wall across the boundary of sectors (0, 0) and (1, 0)
+		for row in 0..9 {
+			cost_fields.set_field_cell_value(
+				SectorID::new(1, 0),
+				255,
+				FieldCell::new(0, row),
+				&map_dimensions,
+			);
+		}
+		let all = |_, _| true;
+		let integrated =
+			integrate_global_costs((9, 0), 3, &cost_fields, &map_dimensions, all, |_, _, _| true);
+		// only the cells within the budget are recorded and the wall is
+		// never crossed
+		assert_eq!(Some(&0), integrated.get(&(9, 0)));
+		assert_eq!(Some(&3), integrated.get(&(8, 2)));
+		assert_eq!(None, integrated.get(&(10, 0)));
+		assert!(integrated.values().all(|cost| *cost <= 3));
+		// the search stops once the visitor has seen enough
+		let mut visited = Vec::new();
+		integrate_global_costs(
+			(9, 0),
+			u32::MAX,
+			&cost_fields,
+			&map_dimensions,
+			all,
+			|column, row, cost| {
+				visited.push((column, row, cost));
+				visited.len() < 3
+			},
+		);
+		assert_eq!(vec![(9, 0, 0), (8, 0, 1), (9, 1, 1)], visited);
+	}
+	#[test]
+	fn exact_goal_impassable_event() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin::default()));
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let entity = app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5)).id();
+		let source = (SectorID::new(0, 0), FieldCell::new(0, 0));
+		let target = (SectorID::new(2, 2), FieldCell::new(4, 4));
+		app.world_mut()
+			.get_mut::<SectorCostFields>(entity)
+			.unwrap()
+			.set_field_cell_value(target.0, 255, target.1, &map_dimensions);
+		let request = EventPathRequest::new(source.0, source.1, target.0, target.1)
+			.with_goal_fallback(GoalFallback::Exact);
+		app.world_mut().send_event(request);
+		for _ in 0..5 {
+			app.update();
+		}
+		let events = app.world().resource::<Events<EventPathGoalImpassable>>();
+		let rejected: Vec<EventPathGoalImpassable> =
+			events.get_cursor().read(events).copied().collect();
+		assert_eq!(1, rejected.len());
+		assert_eq!(entity, rejected[0].get_nav_world());
+		assert_eq!(Some(request.get_request_id()), rejected[0].get_request_id());
+		assert!(app.world().get::<RouteCache>(entity).unwrap().is_empty());
+		// any other policy picks a nearby goal instead
+		let nearest = EventPathRequest::new(source.0, source.1, target.0, target.1)
+			.with_goal_fallback(GoalFallback::NearestPathable);
+		app.world_mut().send_event(nearest);
+		for _ in 0..5 {
+			app.update();
+		}
+		let events = app.world().resource::<Events<EventPathGoalImpassable>>();
+		assert!(events
+			.get_cursor()
+			.read(events)
+			.all(|event| event.get_request_id() != Some(nearest.get_request_id())));
+		assert_eq!(1, app.world().get::<RouteCache>(entity).unwrap().len());
+	}
+	#[test]
+	fn ready_event_per_request() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin::default()));
+		let entity = app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5)).id();
+		let target = (SectorID::new(2, 2), FieldCell::new(4, 4));
+		let leader = EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(0, 0), target.0, target.1);
+		// a nearby actor shares the route of the leader
+		let follower =
+			EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(1, 1), target.0, target.1);
+		app.world_mut().send_event(leader);
+		app.world_mut().send_event(follower);
+		let is_ready = |app: &App, request: &EventPathRequest| {
+			let events = app.world().resource::<Events<EventPathReady>>();
+			let ready: Vec<EventPathReady> = events
+				.get_cursor()
+				.read(events)
+				.filter(|event| event.get_request_id() == Some(request.get_request_id()))
+				.copied()
+				.collect();
+			assert!(ready.len() <= 1);
+			ready
+				.first()
+				.is_some_and(|event| event.get_nav_world() == entity)
+		};
+		for _ in 0..10 {
+			app.update();
+		}
+		assert!(is_ready(&app, &leader));
+		assert!(is_ready(&app, &follower));
+		// a request answered by the cached route is sent its own event
+		let repeat = EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(0, 0), target.0, target.1);
+		app.world_mut().send_event(repeat);
+		app.update();
+		assert!(is_ready(&app, &repeat));
+		assert!(!app
+			.world()
+			.get::<FlowFieldQueue>(entity)
+			.unwrap()
+			.has_waiting());
+	}
+	#[test]
+	fn seeded_goals_shape_goal_field() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin::default()));
+		let entity = app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5)).id();
+		let target = (SectorID::new(1, 0), FieldCell::new(9, 5));
+		let seed = FieldCell::new(1, 5);
+		let request = EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(5, 5), target.0, target.1)
+			.with_goal_seeds(&[(seed, 0)]);
+		app.world_mut().send_event(request);
+		for _ in 0..10 {
+			app.update();
+		}
+		let route_cache = app.world().get::<RouteCache>(entity).unwrap();
+		let metadata = route_cache.get_route_metadata().next().unwrap();
+		assert_eq!(request.get_goal_seeds(), metadata.get_goal_seeds());
+		let (_, route) = route_cache.get_route_from_metadata(metadata).unwrap();
+		let map_dimensions = app.world().get::<MapDimensions>(entity).unwrap();
+		let flow_cache = app.world().get::<FlowFieldCache>(entity).unwrap();
+		assert!(flow_cache.is_route_built_for(metadata, &route, map_dimensions));
+		// only the field of the goal sector is seeded
+		assert!(flow_cache.get_field(target.0, target.0, target.1).is_none());
+		let field = flow_cache
+			.get_seeded_field(target.0, target.0, target.1, request.get_goal_seeds())
+			.unwrap();
+		assert!(is_goal(field.get_field_cell_value(seed)));
+		assert!(is_goal(field.get_field_cell_value(target.1)));
+		// each cell heads to whichever goal is nearer
+		let near_seed = field.get_field_cell_value(FieldCell::new(3, 5));
+		assert_eq!(Ordinal::West, get_ordinal_from_bits(near_seed));
+		let near_target = field.get_field_cell_value(FieldCell::new(7, 5));
+		assert_eq!(Ordinal::East, get_ordinal_from_bits(near_target));
+	}
+	#[test]
+	fn bundle_without_connectivity_builds_fields() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin::default()));
+		let entity = app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5)).id();
+		// bundles assembled before the component existed lack it
+		app.world_mut().entity_mut(entity).remove::<Connectivity>();
+		app.world_mut().send_event(EventPathRequest::new(
+			SectorID::new(0, 0),
+			FieldCell::new(0, 0),
+			SectorID::new(2, 2),
+			FieldCell::new(4, 4),
+		));
+		for _ in 0..10 {
+			app.update();
+		}
+		assert_eq!(1, app.world().get::<RouteCache>(entity).unwrap().len());
+		assert!(!app.world().get::<FlowFieldCache>(entity).unwrap().get().is_empty());
+	}
+	#[test]
+	fn budget_applies_to_cached_route() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin::default()));
+		let entity = app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5)).id();
+		let source = (SectorID::new(0, 0), FieldCell::new(0, 0));
+		let target = (SectorID::new(2, 2), FieldCell::new(4, 4));
+		let request = EventPathRequest::new(source.0, source.1, target.0, target.1);
+		app.world_mut().send_event(request);
+		for _ in 0..5 {
+			app.update();
+		}
+		assert_eq!(1, app.world().get::<RouteCache>(entity).unwrap().len());
+		assert!(app.world().resource::<Events<EventPathTooExpensive>>().is_empty());
+		// the cached route was built without a budget and costs far more
+		app.world_mut().send_event(request.with_max_cost(10));
+		app.update();
+		let events = app.world().resource::<Events<EventPathTooExpensive>>();
+		assert_eq!(1, events.len());
+		assert!(app.world().get::<RouteQueue>(entity).unwrap().is_empty());
+		// while a generous budget reuses it
+		app.world_mut().send_event(request.with_max_cost(1000));
+		app.update();
+		let events = app.world().resource::<Events<EventPathTooExpensive>>();
+		assert_eq!(1, events.len());
+		assert!(app.world().get::<RouteQueue>(entity).unwrap().is_empty());
+	}
+
 	#[test]
 	fn filter_graph_route() {
 		// path in 3x3 sector grid, moving from top right to bottom left
@@ -338,4 +2011,165 @@ mod tests {
 		assert_eq!(actual, path);
 	}
 
+	#[test]
+	fn next_request_by_priority() {
+		let request = |column, priority| {
+			EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(column, 0), SectorID::new(0, 0), FieldCell::new(5, 5)).with_priority(priority)
+		};
+		let mut pending = vec![
+			(request(0, PathPriority::Background), Stamp::Time(Duration::ZERO)),
+			(request(1, PathPriority::Normal), Stamp::Time(Duration::ZERO)),
+			(request(2, PathPriority::PlayerCritical), Stamp::Time(Duration::ZERO)),
+		];
+		let next = take_next_request(&mut pending, Stamp::Time(Duration::ZERO)).unwrap();
+		assert_eq!(FieldCell::new(2, 0), next.source_field_cell);
+		let next = take_next_request(&mut pending, Stamp::Time(Duration::ZERO)).unwrap();
+		assert_eq!(FieldCell::new(1, 0), next.source_field_cell);
+		// background has waited long enough to beat a fresh critical request
+		pending.push((request(4, PathPriority::PlayerCritical), Stamp::Time(Duration::from_secs(2))));
+		let next = take_next_request(&mut pending, Stamp::Time(Duration::from_secs(2))).unwrap();
+		assert_eq!(FieldCell::new(0, 0), next.source_field_cell);
+	}
+	#[test]
+	fn next_request_by_priority_without_aging_between_turns() {
+		let request = |column, priority| {
+			EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(column, 0), SectorID::new(0, 0), FieldCell::new(5, 5)).with_priority(priority)
+		};
+		let mut pending = vec![
+			(request(0, PathPriority::Background), FlowFieldTurn::new(0).as_stamp()),
+			(request(1, PathPriority::Normal), FlowFieldTurn::new(0).as_stamp()),
+		];
+		// however many turns pass the background request never gets promoted
+		pending.push((request(2, PathPriority::Normal), FlowFieldTurn::new(1000).as_stamp()));
+		let now = FlowFieldTurn::new(1000).as_stamp();
+		let next = take_next_request(&mut pending, now).unwrap();
+		assert_eq!(FieldCell::new(1, 0), next.source_field_cell);
+		let next = take_next_request(&mut pending, now).unwrap();
+		assert_eq!(FieldCell::new(2, 0), next.source_field_cell);
+		let next = take_next_request(&mut pending, now).unwrap();
+		assert_eq!(FieldCell::new(0, 0), next.source_field_cell);
+	}
+
+	/// Build the components needed to find a route with a set of impassable cells
+	fn setup_components(impassable: &[(SectorID, FieldCell)]) -> (PortalGraph, SectorPortals, SectorCostFields, MapDimensions) {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		for (sector_id, field_cell) in impassable {
+			cost_fields.set_field_cell_value(*sector_id, 255, *field_cell, &map_dimensions);
+		}
+		let mut portals = SectorPortals::new(30, 30, 10);
+		for sector_id in cost_fields.get_scaled().keys() {
+			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
+		(graph, portals, cost_fields, map_dimensions)
+	}
+
+	fn fallback_request(source: (SectorID, FieldCell), target: (SectorID, FieldCell), goal_fallback: GoalFallback) -> RouteMetadata {
+		RouteMetadata::new(source.0, source.1, target.0, target.1, Duration::default()).with_goal_fallback(goal_fallback)
+	}
+
+	#[test]
+	fn fallback_exact_impassable() {
+		let target = (SectorID::new(0, 0), FieldCell::new(0, 0));
+		let (graph, portals, cost_fields, map_dimensions) = setup_components(&[target]);
+		let source = (SectorID::new(2, 2), FieldCell::new(5, 5));
+		let result = find_route_with_fallback(&graph, &portals, &cost_fields, &map_dimensions, &DefaultPortalCostModel, &fallback_request(source, target, GoalFallback::Exact));
+		assert_eq!(Err(PathSearchError::NoPath), result.map(|(goal, _)| goal));
+	}
+
+	#[test]
+	fn fallback_nearest_pathable() {
+		let target = (SectorID::new(0, 0), FieldCell::new(0, 0));
+		let (graph, portals, cost_fields, map_dimensions) = setup_components(&[
+			target,
+			(SectorID::new(0, 0), FieldCell::new(1, 0)),
+		]);
+		let source = (SectorID::new(2, 2), FieldCell::new(5, 5));
+		let (goal, route) = find_route_with_fallback(&graph, &portals, &cost_fields, &map_dimensions, &DefaultPortalCostModel, &fallback_request(source, target, GoalFallback::NearestPathable)).unwrap();
+		let actual = (SectorID::new(0, 0), FieldCell::new(0, 1));
+		assert_eq!(actual, goal);
+		assert_eq!(actual, *route.get().last().unwrap());
+	}
+
+	#[test]
+	fn fallback_nearest_reachable() {
+		// surround the target with a ring of impassable cells
+		let sector_id = SectorID::new(1, 1);
+		let mut ring = Vec::new();
+		for column in 4..=6 {
+			for row in 4..=6 {
+				if (column, row) != (5, 5) {
+					ring.push((sector_id, FieldCell::new(column, row)));
+				}
+			}
+		}
+		let (graph, portals, cost_fields, map_dimensions) = setup_components(&ring);
+		let source = (SectorID::new(0, 0), FieldCell::new(0, 0));
+		let target = (sector_id, FieldCell::new(5, 5));
+		let (goal, _route) = find_route_with_fallback(&graph, &portals, &cost_fields, &map_dimensions, &DefaultPortalCostModel, &fallback_request(source, target, GoalFallback::NearestReachable)).unwrap();
+		let actual = (sector_id, FieldCell::new(5, 3));
+		assert_eq!(actual, goal);
+	}
+	#[test]
+	fn single_sector_route_skips_graph() {
+		let bundle = FlowFieldTilesBundle::new(10, 10, 10, 0.5);
+		assert!(bundle.get_map_dimensions().is_single_sector());
+		assert!(bundle.get_portal_graph().get_portal_nodes().is_empty());
+		let source = (SectorID::new(0, 0), FieldCell::new(0, 0));
+		let target = (SectorID::new(0, 0), FieldCell::new(9, 9));
+		let (goal, route) = find_route_with_fallback(bundle.get_portal_graph(), bundle.get_sector_portals(), bundle.get_sector_cost_fields(), bundle.get_map_dimensions(), &DefaultPortalCostModel, &fallback_request(source, target, GoalFallback::Exact)).unwrap();
+		assert_eq!(target, goal);
+		assert_eq!(vec![target], *route.get());
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn entity_request_from_translation() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 1.0);
+		let nav_world = Entity::from_raw(3);
+		let event = EventPathRequestFromEntity::new_xy(
+			Entity::from_raw(7),
+			SectorID::new(0, 0),
+			FieldCell::new(4, 4),
+		)
+		.with_nav_world(nav_world);
+		let request = event
+			.to_path_request(Vec3::new(-1.5, 1.5, 0.0), &map_dimensions)
+			.unwrap();
+		assert_eq!(SectorID::new(1, 1), request.source_sector);
+		assert_eq!(FieldCell::new(3, 3), request.source_field_cell);
+		assert_eq!(Some(nav_world), request.nav_world);
+		assert!(event
+			.to_path_request(Vec3::new(100.0, 0.0, 0.0), &map_dimensions)
+			.is_none());
+	}
+	#[test]
+	#[cfg(all(feature = "2d", feature = "3d"))]
+	fn entity_request_in_bundle_coordinates() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 1.0);
+		let event = EventPathRequestFromEntity::new(
+			Entity::from_raw(7),
+			SectorID::new(0, 0),
+			FieldCell::new(4, 4),
+		);
+		let translation = Vec3::new(-1.5, 1.5, 12.5);
+		let request = event
+			.path_request_in(translation, &map_dimensions, CoordinateSystem::Xy)
+			.unwrap();
+		assert_eq!(SectorID::new(1, 1), request.source_sector);
+		let request = event
+			.path_request_in(translation, &map_dimensions, CoordinateSystem::Xyz)
+			.unwrap();
+		assert_eq!(SectorID::new(1, 2), request.source_sector);
+		// a request created for a coordinate system ignores the bundle's
+		let event = EventPathRequestFromEntity::new_xy(
+			Entity::from_raw(7),
+			SectorID::new(0, 0),
+			FieldCell::new(4, 4),
+		);
+		let request = event
+			.path_request_in(translation, &map_dimensions, CoordinateSystem::Xyz)
+			.unwrap();
+		assert_eq!(SectorID::new(1, 1), request.source_sector);
+	}
 }
@@ -0,0 +1,226 @@
+//! Many actors often head for the same destination, such as every creep of a
+//! tower defence game pathing to the player's base. Registering the
+//! destination as a shared goal in the [SharedGoals] of a bundle lets path
+//! requests refer to it by its [GoalId] (see
+//! [EventPathRequest::to_shared_goal]) rather than its position. The routes
+//! of every actor lead to the same goal [FieldCell] so they share the
+//! [FlowField]s cached for it, and when the goal moves a single
+//! [EventMoveSharedGoal] invalidates and re-requests every route to it
+//!
+
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Identifies a goal shared by many path requests
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct GoalId(u32);
+
+impl GoalId {
+	/// Create a new instance of [GoalId]
+	pub fn new(id: u32) -> Self {
+		GoalId(id)
+	}
+	/// Get the ID
+	pub fn get(&self) -> u32 {
+		self.0
+	}
+}
+
+/// The position of each shared goal of a bundle
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SharedGoals {
+	/// The sector and [FieldCell] of each goal
+	goals: BTreeMap<GoalId, (SectorID, FieldCell)>,
+}
+
+impl SharedGoals {
+	/// Create a new instance of [SharedGoals] without any goals
+	pub fn new() -> Self {
+		SharedGoals::default()
+	}
+	/// Set the position of a goal, returning its previous position. To
+	/// invalidate the routes leading to a goal which has moved send an
+	/// [EventMoveSharedGoal] instead
+	pub fn set_goal(
+		&mut self,
+		goal_id: GoalId,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+	) -> Option<(SectorID, FieldCell)> {
+		self.goals.insert(goal_id, (sector_id, field_cell))
+	}
+	/// Get the position of a goal
+	pub fn get_goal(&self, goal_id: GoalId) -> Option<(SectorID, FieldCell)> {
+		self.goals.get(&goal_id).copied()
+	}
+	/// Remove a goal, returning its position
+	pub fn remove_goal(&mut self, goal_id: GoalId) -> Option<(SectorID, FieldCell)> {
+		self.goals.remove(&goal_id)
+	}
+}
+
+/// Move a shared goal to a new sector and [FieldCell]
+#[derive(Event, Clone, Copy)]
+pub struct EventMoveSharedGoal {
+	/// The goal to move
+	goal_id: GoalId,
+	/// The new sector of the goal
+	sector_id: SectorID,
+	/// The new [FieldCell] of the goal
+	field_cell: FieldCell,
+	/// The bundle entity to update, if [None] then every bundle is updated
+	nav_world: Option<Entity>,
+}
+
+impl EventMoveSharedGoal {
+	/// Create a new instance of [EventMoveSharedGoal]
+	pub fn new(goal_id: GoalId, sector_id: SectorID, field_cell: FieldCell) -> Self {
+		EventMoveSharedGoal {
+			goal_id,
+			sector_id,
+			field_cell,
+			nav_world: None,
+		}
+	}
+	/// Only update the bundle `nav_world`
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	/// Get the goal to move
+	pub fn get_goal_id(&self) -> GoalId {
+		self.goal_id
+	}
+	/// Get the new sector of the goal
+	pub fn get_sector_id(&self) -> SectorID {
+		self.sector_id
+	}
+	/// Get the new [FieldCell] of the goal
+	pub fn get_field_cell(&self) -> FieldCell {
+		self.field_cell
+	}
+	/// Get the bundle entity to update
+	pub fn get_nav_world(&self) -> Option<Entity> {
+		self.nav_world
+	}
+}
+
+/// Apply [EventMoveSharedGoal]s to the [SharedGoals] of each bundle. Every
+/// cached or queued route to a goal which has moved is removed, announced
+/// with an [EventRouteInvalidated] and requested again towards the new
+/// position of the goal
+#[cfg(not(tarpaulin_include))]
+pub fn process_shared_goal_moves(
+	mut events: EventReader<EventMoveSharedGoal>,
+	mut query: Query<(Entity, &mut SharedGoals, &mut RouteQueue, &mut RouteCache)>,
+	mut event_path_request: EventWriter<EventPathRequest>,
+	mut event_route_invalidated: EventWriter<EventRouteInvalidated>,
+) {
+	for event in events.read() {
+		for (entity, mut goals, mut route_queue, mut route_cache) in query.iter_mut() {
+			if event.nav_world.is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			let target = (event.sector_id, event.field_cell);
+			if goals.set_goal(event.goal_id, target.0, target.1) == Some(target) {
+				continue;
+			}
+			let mut removed = route_cache.remove_routes_to_goal(event.goal_id);
+			removed.append(&mut route_queue.remove_routes_to_goal(event.goal_id));
+			for metadata in removed.iter() {
+				invalidate_route(
+					metadata,
+					entity,
+					&mut event_route_invalidated,
+					&mut event_path_request,
+				);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::utils::Duration;
+
+	#[test]
+	fn moving_goal_removes_its_routes() {
+		let goal_id = GoalId::new(1);
+		let mut goals = SharedGoals::new();
+		let base = (SectorID::new(2, 2), FieldCell::new(5, 5));
+		assert_eq!(None, goals.set_goal(goal_id, base.0, base.1));
+		let mut cache = RouteCache::default();
+		let mut queue = RouteQueue::default();
+		let to_goal = |column: usize| {
+			RouteMetadata::new(
+				SectorID::new(0, 0),
+				FieldCell::new(column, 0),
+				base.0,
+				base.1,
+				Duration::ZERO,
+			)
+			.with_shared_goal(goal_id)
+		};
+		let elsewhere = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(0, 0),
+			SectorID::new(1, 1),
+			FieldCell::new(5, 5),
+			Duration::ZERO,
+		);
+		let route = Route::new(vec![(SectorID::new(0, 0), FieldCell::new(9, 4))]);
+		cache.insert_route_with_metadata(to_goal(0), route.clone());
+		cache.insert_route_with_metadata(to_goal(1), route.clone());
+		cache.insert_route_with_metadata(elsewhere, route.clone());
+		queue.add_to_queue(to_goal(2), route);
+		assert_eq!(2, cache.get_routes_to_goal(goal_id).len());
+		let mut removed = cache.remove_routes_to_goal(goal_id);
+		removed.append(&mut queue.remove_routes_to_goal(goal_id));
+		assert_eq!(3, removed.len());
+		assert!(cache.get_routes_to_goal(goal_id).is_empty());
+		assert!(queue.get_queue().is_empty());
+		// routes to other goals are untouched
+		assert_eq!(1, cache.len());
+		// the request repeated for a removed route follows the goal
+		let request = repeat_request(&removed[0], Entity::PLACEHOLDER);
+		assert_eq!(Some(goal_id), request.get_shared_goal());
+	}
+	#[test]
+	fn cached_route_tagged_with_shared_goal() {
+		let goal_id = GoalId::new(1);
+		let plain = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(0, 0),
+			SectorID::new(2, 2),
+			FieldCell::new(5, 5),
+			Duration::ZERO,
+		);
+		let to_goal = plain.with_shared_goal(goal_id);
+		let route = Route::new(vec![(SectorID::new(0, 0), FieldCell::new(9, 4))]);
+		let mut cache = RouteCache::default();
+		cache.insert_route_with_metadata(plain, route);
+		// the request to the goal is served by the route cached without it
+		assert!(cache.get_route_from_metadata(&to_goal).is_some());
+		assert!(!cache.is_route_to_goal(goal_id, &to_goal));
+		let mut queue = RouteQueue::default();
+		queue.tag_shared_goal(to_goal);
+		queue.tag_shared_goal(to_goal);
+		assert!(!queue.is_empty());
+		for metadata in queue.take_goal_tags() {
+			assert!(cache.tag_shared_goal(&metadata));
+		}
+		assert!(queue.is_empty());
+		assert!(cache.is_route_to_goal(goal_id, &plain));
+		// so moving the goal removes it and the repeated request follows it
+		let removed = cache.remove_routes_to_goal(goal_id);
+		assert_eq!(1, removed.len());
+		assert_eq!(Some(goal_id), removed[0].get_shared_goal());
+		assert!(cache.is_empty());
+	}
+}
@@ -2,60 +2,312 @@
 //!
 
 use crate::prelude::*;
-use bevy::prelude::*;
+use bevy::{
+	ecs::schedule::{InternedScheduleLabel, ScheduleLabel},
+	prelude::*,
+};
 
+pub mod arrival_layer;
+pub mod build_layer;
+pub mod condition_layer;
+pub mod config_layer;
 pub mod cost_layer;
+pub mod debug_layer;
+pub mod flee_layer;
 pub mod flow_layer;
+pub mod goal_layer;
+pub mod modifier_layer;
+pub mod patrol_layer;
+pub mod reach_layer;
+pub mod repath_layer;
+pub mod snapshot_layer;
+pub mod spawn_layer;
+pub mod state_layer;
+pub mod stats_layer;
+pub mod stuck_layer;
+pub mod turn_layer;
+#[cfg(feature = "validation")]
+pub mod validation_layer;
+pub mod visibility_layer;
+pub mod zone_layer;
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub enum OrderingSet {
 	Tidy,
 	Calculate,
+	/// Every system of the plugin, including those ordered around the
+	/// [OrderingSet::Tidy] and [OrderingSet::Calculate] sets, so a run
+	/// condition of the set applies to all of them
+	Navigation,
 }
 
-pub struct FlowFieldTilesPlugin;
+/// Registers the types, events and systems used to generate FlowFields.
+///
+/// By default all systems run as part of the [PreUpdate] schedule. For
+/// deterministic lockstep simulations the systems can instead be placed
+/// within [FixedUpdate] (where `Res<Time>` resolves to `Time<Fixed>`) with
+/// [FlowFieldTilesPlugin::new]:
+///
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use bevy_flowfield_tiles_plugin::prelude::*;
+///
+/// App::new().add_plugins(FlowFieldTilesPlugin::new(FixedUpdate));
+/// ```
+///
+/// Events are only read when the chosen schedule runs. A frame without a
+/// fixed tick doesn't run [FixedUpdate], so events written in [Update] may be
+/// dropped before the navigation systems see them, write them from within
+/// [FixedUpdate] as well
+///
+/// To manually step navigation (for instance when resimulating frames in a
+/// rollback scheme) supply a custom [ScheduleLabel] and run it yourself with
+/// `world.run_schedule(MyLabel)`
+///
+/// The systems can be tied to a game state with [FlowFieldTilesPlugin::run_if]:
+///
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use bevy_flowfield_tiles_plugin::prelude::*;
+///
+/// #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+/// enum GameState {
+///     #[default]
+///     Menu,
+///     InGame,
+/// }
+///
+/// App::new()
+///     .add_plugins(DefaultPlugins)
+///     .init_state::<GameState>()
+///     .add_plugins(FlowFieldTilesPlugin::default().run_if(in_state(GameState::InGame)));
+/// ```
+///
+/// To briefly suspend the processing of queued work instead see
+/// [FlowFieldSystemState]
+///
+/// Turn-based games which build fields on demand and never want them to
+/// expire can enable [FlowFieldTilesPlugin::turn_based], see [FlowFieldTurn]
+pub struct FlowFieldTilesPlugin {
+	/// The schedule that the navigation systems are added to
+	schedule: InternedScheduleLabel,
+	/// Apply each run condition of the navigation systems to a schedule
+	#[allow(clippy::type_complexity)]
+	run_conditions: Vec<Box<dyn Fn(&mut App, InternedScheduleLabel) + Send + Sync>>,
+	/// Whether cached entries are kept until cleared and stamped with a
+	/// [FlowFieldTurn] rather than the time elapsed
+	turn_based: bool,
+}
+
+impl Default for FlowFieldTilesPlugin {
+	fn default() -> Self {
+		FlowFieldTilesPlugin::new(PreUpdate)
+	}
+}
+
+impl FlowFieldTilesPlugin {
+	/// Create a new instance of [FlowFieldTilesPlugin] where the systems
+	/// will run in the given `schedule`
+	pub fn new(schedule: impl ScheduleLabel) -> Self {
+		FlowFieldTilesPlugin {
+			schedule: schedule.intern(),
+			run_conditions: Vec::new(),
+			turn_based: false,
+		}
+	}
+	/// Play turn-based, time-based expiry of cached routes and fields is
+	/// disabled and metadata is stamped with the [FlowFieldTurn] resource
+	/// which the game advances. Caches are emptied with [clear_all_caches]
+	pub fn turn_based(mut self) -> Self {
+		self.turn_based = true;
+		self
+	}
+	/// Only run the navigation systems while the `condition` holds, such as
+	/// `in_state(GameState::InGame)`. Events sent while the systems don't
+	/// run are dropped by Bevy after two frames, so requests should only be
+	/// made while the condition holds. Each condition added must hold and
+	/// applies to every system of the plugin, such as the refreshing of the
+	/// [FlowFieldSnapshot] and the polling of bundle build tasks
+	pub fn run_if<M>(
+		mut self,
+		condition: impl Condition<M> + Clone + Send + Sync + 'static,
+	) -> Self {
+		self.run_conditions.push(Box::new(move |app, schedule| {
+			app.configure_sets(schedule, OrderingSet::Navigation.run_if(condition.clone()));
+		}));
+		self
+	}
+}
 
 impl Plugin for FlowFieldTilesPlugin {
 	#[cfg(not(tarpaulin_include))]
 	fn build(&self, app: &mut App) {
 		app.register_type::<Ordinal>()
+			.register_type::<Connectivity>()
 			.register_type::<MapDimensions>()
+			.register_type::<Cost>()
 			.register_type::<CostField>()
 			.register_type::<Portals>()
 			.register_type::<PortalGraph>()
+			.register_type::<ConditionId>()
+			.register_type::<PlayerId>()
+			.register_type::<UnexploredPolicy>()
+			.register_type::<SectorVisibility>()
+			.register_type::<FactionId>()
+			.register_type::<ZoneId>()
+			.register_type::<FactionZones>()
+			.register_type::<GoalId>()
+			.register_type::<SharedGoals>()
 			.register_type::<FlowField>()
 			.register_type::<SectorID>()
 			.register_type::<FieldCell>()
 			.register_type::<RouteMetadata>()
+			.register_type::<RequestId>()
+			.register_type::<GoalFallback>()
+			.register_type::<PathPriority>()
+			.register_type::<TraversalSpeed>()
+			.register_type::<NavWorld>()
+			.register_type::<ArrivalSlots>()
+			.register_type::<ArrivalSlot>()
+			.register_type::<CostModifiers>()
+			.register_type::<SpeculativeEdits>()
+			.register_type::<SpeculationToken>()
+			.register_type::<RepathHysteresis>()
+			.register_type::<RepathTracker>()
+			.register_type::<DebugField>()
+			.register_type::<FieldDebugTexture>()
 			.register_type::<FlowFieldMetadata>()
+			.register_type::<FleeFieldMetadata>()
+			.register_type::<FleeFieldCache>()
+			.register_type::<PatrolRoutes>()
+			.register_type::<PatrolProgress>()
+			.register_type::<StuckDetector>()
+			.register_type::<FlowFieldCounters>()
+			.register_type::<FlowFieldStats>()
+			.register_type::<state_layer::FlowFieldSystemState>()
+			.register_type::<FlowFieldTurn>()
+			.register_type::<FlowFieldConfig>()
+			.register_type::<BundleBuildStage>()
 			.add_event::<cost_layer::EventUpdateCostfieldsCell>()
 			.add_event::<cost_layer::EventCleanCaches>()
+			.add_event::<cost_layer::EventRouteInvalidated>()
+			.add_event::<cost_layer::EventGoalBlocked>()
+			.add_event::<condition_layer::EventSetCondition>()
+			.add_event::<visibility_layer::EventRevealSector>()
+			.add_event::<zone_layer::EventToggleZone>()
+			.add_event::<goal_layer::EventMoveSharedGoal>()
 			.add_event::<flow_layer::EventPathRequest>()
+			.add_event::<flow_layer::EventPathQueued>()
+			.add_event::<flow_layer::EventPathTooExpensive>()
+			.add_event::<flow_layer::EventPathGoalImpassable>()
+			.add_event::<flow_layer::EventPathReady>()
+			.add_event::<flow_layer::EventActorPositionHint>()
+			.add_event::<arrival_layer::EventArrivalSlotsRequest>()
+			.add_event::<flee_layer::EventFleeRequest>()
+			.add_event::<reach_layer::EventReachableRequest>()
+			.add_event::<reach_layer::EventReachableCells>()
+			.add_event::<stuck_layer::EventActorStuck>()
+			.add_event::<build_layer::EventBundleBuildProgress>()
+			.add_event::<build_layer::EventBundleBuilt>()
+			.add_event::<build_layer::EventBundleBuildFailed>()
+			.init_resource::<snapshot_layer::FlowFieldSnapshot>()
+			.init_resource::<stats_layer::FlowFieldStats>()
+			.init_resource::<state_layer::FlowFieldSystemState>()
+			.init_resource::<config_layer::FlowFieldConfig>()
 			.configure_sets(
-				PreUpdate,
-				(OrderingSet::Tidy, OrderingSet::Calculate).chain(),
+				self.schedule,
+				(OrderingSet::Tidy, OrderingSet::Calculate)
+					.chain()
+					.in_set(OrderingSet::Navigation),
 			)
 			.add_systems(
-				PreUpdate,
+				self.schedule,
 				(
 					(
-						flow_layer::cleanup_old_routes,
-						flow_layer::cleanup_old_flowfields,
 						(
+							flow_layer::cleanup_old_routes,
+							flow_layer::cleanup_old_flowfields,
+							flee_layer::cleanup_old_flee_fields,
+						)
+							.run_if(state_layer::is_flow_field_processing_active)
+							.run_if(turn_layer::is_cache_expiry_enabled),
+						patrol_layer::maintain_patrol_routes
+							.before(flow_layer::cleanup_old_routes)
+							.before(flow_layer::cleanup_old_flowfields),
+						arrival_layer::release_arrival_slots,
+						condition_layer::process_condition_changes,
+						visibility_layer::process_sector_reveals,
+						zone_layer::process_zone_toggles,
+						goal_layer::process_shared_goal_moves,
+						(
+							modifier_layer::expire_cost_modifiers,
 							cost_layer::process_costfields_updates,
+							cost_layer::process_edited_costfields,
 							cost_layer::clean_cache,
+							flee_layer::rebuild_changed_flee_fields,
 						)
 							.chain(),
 					)
 						.in_set(OrderingSet::Tidy),
 					(
+						arrival_layer::assign_arrival_slots
+							.before(flow_layer::event_insert_route_queue),
 						flow_layer::event_insert_route_queue,
-						flow_layer::process_route_queue,
-						flow_layer::create_queued_integration_fields,
-						flow_layer::create_flow_fields,
+						(
+							flow_layer::process_route_queue
+								.after(flow_layer::event_insert_route_queue),
+							flow_layer::process_position_hints
+								.after(flow_layer::process_route_queue)
+								.before(flow_layer::create_queued_integration_fields),
+							flow_layer::create_queued_integration_fields,
+							flow_layer::create_flow_fields,
+						)
+							.run_if(state_layer::is_flow_field_processing_active),
+						flee_layer::process_flee_requests,
+						reach_layer::process_reachable_requests,
+						debug_layer::update_field_debug_textures
+							.after(flow_layer::create_flow_fields),
 					)
 						.in_set(OrderingSet::Calculate),
+					snapshot_layer::refresh_flow_field_snapshot.after(OrderingSet::Calculate),
+					stuck_layer::detect_stuck_actors.after(OrderingSet::Calculate),
+					stats_layer::begin_flow_field_stats_frame.before(OrderingSet::Tidy),
+					state_layer::track_paused_time.before(OrderingSet::Tidy),
+					build_layer::poll_bundle_build_tasks.before(OrderingSet::Tidy),
+					config_layer::apply_flow_field_config
+						.after(build_layer::poll_bundle_build_tasks)
+						.before(OrderingSet::Tidy),
+				)
+					.in_set(OrderingSet::Navigation),
+			);
+		if self.turn_based {
+			app.init_resource::<turn_layer::FlowFieldTurn>();
+		}
+		for run_condition in self.run_conditions.iter() {
+			run_condition(app, self.schedule);
+		}
+		#[cfg(any(feature = "2d", feature = "3d"))]
+		app.add_event::<cost_layer::EventUpdateCostAtPosition>()
+			.add_event::<cost_layer::EventUpdateCostInAabb>()
+			.add_event::<flow_layer::EventPathRequestFromEntity>()
+			.add_systems(
+				self.schedule,
+				(
+					cost_layer::process_world_space_cost_updates
+						.before(cost_layer::process_costfields_updates)
+						.in_set(OrderingSet::Tidy),
+					flow_layer::process_entity_path_requests
+						.before(flow_layer::event_insert_route_queue)
+						.in_set(OrderingSet::Calculate),
 				),
 			);
+		#[cfg(feature = "validation")]
+		app.add_systems(
+			self.schedule,
+			validation_layer::validate_changed_bundles
+				.after(OrderingSet::Tidy)
+				.before(OrderingSet::Calculate)
+				.in_set(OrderingSet::Navigation),
+		);
 	}
 }
@@ -0,0 +1,563 @@
+//! Temporary changes to [CostField]s, such as an ice patch slowing an area
+//! for a few seconds, which are automatically reverted when they expire or
+//! when the [CostModifierHandle] returned by
+//! [CostModifiers::apply_timed_cost_modifier] is dropped.
+//!
+//! Speculative changes made with [SpeculativeEdits] are applied straight
+//! away for client-side prediction and kept until they're confirmed or
+//! reverted, such as when a server accepts or rejects a building placement
+//!
+
+use std::{
+	collections::BTreeMap,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
+
+use crate::prelude::*;
+use bevy::{prelude::*, utils::Duration};
+
+/// A temporary cost applied over a region of [FieldCell]s
+#[derive(Clone, Reflect)]
+struct CostModifier {
+	/// Unique ID of the modifier within the bundle
+	id: u64,
+	/// The cells the cost is applied to
+	cells: Vec<(SectorID, FieldCell)>,
+	/// The cost applied to the cells
+	value: u8,
+	/// How long until the modifier expires
+	remaining: Duration,
+	/// Shared with the [CostModifierHandle] (and with any copy of the
+	/// modifier taken when its bundle is cloned), set when the handle is
+	/// dropped without being detached
+	#[reflect(ignore)]
+	reverted: Arc<AtomicBool>,
+}
+
+impl CostModifier {
+	/// Whether the modifier should be reverted
+	fn is_finished(&self) -> bool {
+		self.remaining.is_zero() || self.reverted.load(Ordering::Relaxed)
+	}
+}
+
+/// Returned when a modifier is applied, dropping the handle reverts the
+/// modifier unless it has been detached with [CostModifierHandle::detach]
+#[derive(Debug)]
+pub struct CostModifierHandle {
+	/// Unique ID of the modifier within the bundle
+	id: u64,
+	/// Shared with the modifier, set when the handle is dropped
+	reverted: Arc<AtomicBool>,
+	/// Whether the modifier should instead run until it expires
+	detached: bool,
+}
+
+impl CostModifierHandle {
+	/// Get the ID of the modifier
+	pub fn get_id(&self) -> u64 {
+		self.id
+	}
+	/// Drop the handle without reverting the modifier, it instead lasts until
+	/// its duration expires
+	pub fn detach(mut self) {
+		self.detached = true;
+	}
+}
+
+impl Drop for CostModifierHandle {
+	fn drop(&mut self) {
+		if !self.detached {
+			self.reverted.store(true, Ordering::Relaxed);
+		}
+	}
+}
+
+/// Tracks the temporary cost modifiers of a bundle and the costs they
+/// replaced. Modifiers may overlap, the most recently applied modifier
+/// decides the cost of a [FieldCell] and when it's reverted the cell returns
+/// to the cost of the next most recent modifier or to its original cost.
+///
+/// NB: a cell covered by a modifier is restored to the cost it had when the
+/// first modifier was applied, so changes made to the cell by other means
+/// while it's modified are lost
+#[derive(Component, Default, Clone, Reflect)]
+#[reflect(Component)]
+pub struct CostModifiers {
+	/// The ID given to the next modifier
+	next_id: u64,
+	/// Active modifiers in the order they were applied
+	modifiers: Vec<CostModifier>,
+	/// The cost of each modified cell before any modifier was applied
+	original_costs: BTreeMap<(SectorID, FieldCell), u8>,
+}
+
+impl CostModifiers {
+	/// Get the number of active modifiers
+	pub fn len(&self) -> usize {
+		self.modifiers.len()
+	}
+	/// Whether there are no active modifiers
+	pub fn is_empty(&self) -> bool {
+		self.modifiers.is_empty()
+	}
+	/// Whether the modifier of the `handle` is still applied
+	pub fn is_active(&self, handle: &CostModifierHandle) -> bool {
+		self.modifiers.iter().any(|m| m.id == handle.id)
+	}
+	/// Get the cost a modified [FieldCell] will return to once every modifier
+	/// covering it has been reverted. Returns [None] if the cell isn't
+	/// modified
+	pub fn get_original_cost(&self, sector_id: SectorID, field_cell: FieldCell) -> Option<u8> {
+		self.original_costs.get(&(sector_id, field_cell)).copied()
+	}
+	/// Set the cost of the `region` of [FieldCell]s to `value` for the given
+	/// `duration`. The cells are edited with [SectorCostFields::edit] so the
+	/// plugin updates the portals, graph and caches of each sector, both when
+	/// the modifier is applied and when it's reverted. Cells in sectors
+	/// which don't exist are ignored
+	pub fn apply_timed_cost_modifier(
+		&mut self,
+		sector_cost_fields: &mut SectorCostFields,
+		map_dimensions: &MapDimensions,
+		region: &[(SectorID, FieldCell)],
+		value: impl Into<Cost>,
+		duration: Duration,
+	) -> CostModifierHandle {
+		let value = value.into().get();
+		let mut cells = Vec::with_capacity(region.len());
+		for (sector_id, field_cell) in region.iter() {
+			let Some(cost_field) = sector_cost_fields.get_baseline().get(sector_id) else {
+				continue;
+			};
+			if cells.contains(&(*sector_id, *field_cell)) {
+				continue;
+			}
+			cells.push((*sector_id, *field_cell));
+			self.original_costs
+				.entry((*sector_id, *field_cell))
+				.or_insert_with(|| cost_field.get_field_cell_value(*field_cell));
+		}
+		let costs: Vec<((SectorID, FieldCell), u8)> =
+			cells.iter().map(|cell| (*cell, value)).collect();
+		set_costs(sector_cost_fields, map_dimensions, costs);
+		let id = self.next_id;
+		self.next_id += 1;
+		let reverted = Arc::new(AtomicBool::new(false));
+		self.modifiers.push(CostModifier {
+			id,
+			cells,
+			value,
+			remaining: duration,
+			reverted: reverted.clone(),
+		});
+		CostModifierHandle {
+			id,
+			reverted,
+			detached: false,
+		}
+	}
+	/// Advance the timers of the modifiers by `delta` and revert any which
+	/// have expired or whose handle has been dropped. Returns the number of
+	/// modifiers reverted
+	pub fn tick(
+		&mut self,
+		delta: Duration,
+		sector_cost_fields: &mut SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) -> usize {
+		for modifier in self.modifiers.iter_mut() {
+			modifier.remaining = modifier.remaining.saturating_sub(delta);
+		}
+		let (finished, active): (Vec<CostModifier>, Vec<CostModifier>) = self
+			.modifiers
+			.drain(..)
+			.partition(|modifier| modifier.is_finished());
+		self.modifiers = active;
+		if finished.is_empty() {
+			return 0;
+		}
+		// each cell returns to the most recent remaining modifier covering it
+		// or to its original cost
+		let mut costs = Vec::new();
+		for modifier in finished.iter() {
+			for cell in modifier.cells.iter() {
+				if costs.iter().any(|(c, _)| c == cell) {
+					continue;
+				}
+				let layered = self
+					.modifiers
+					.iter()
+					.rev()
+					.find(|m| m.cells.contains(cell))
+					.map(|m| m.value);
+				let value = match layered {
+					Some(value) => value,
+					None => {
+						let Some(original) = self.original_costs.remove(cell) else {
+							continue;
+						};
+						original
+					}
+				};
+				costs.push((*cell, value));
+			}
+		}
+		set_costs(sector_cost_fields, map_dimensions, costs);
+		finished.len()
+	}
+}
+
+/// Identifies a speculative edit applied with [SpeculativeEdits::apply]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct SpeculationToken(u64);
+
+impl SpeculationToken {
+	/// Get the ID of the token
+	pub fn get(&self) -> u64 {
+		self.0
+	}
+}
+
+/// The costs set by a speculative edit and the costs they replaced
+#[derive(Clone, Reflect)]
+struct SpeculativeEdit {
+	/// Token the edit was applied with
+	token: SpeculationToken,
+	/// The cost of each edited cell before the edit was applied
+	previous: Vec<((SectorID, FieldCell), u8)>,
+	/// The cost the edit set each cell to
+	applied: Vec<((SectorID, FieldCell), u8)>,
+}
+
+/// Tracks the speculative cost changes of a bundle which are waiting to be
+/// confirmed, for instance a client predicting that its building placement
+/// will be accepted by the server. The changes are applied immediately and
+/// [SpeculativeEdits::revert] restores the previous costs if the server
+/// rejects them, only the sectors touched by the edit are invalidated
+#[derive(Component, Default, Clone, Reflect)]
+#[reflect(Component)]
+pub struct SpeculativeEdits {
+	/// The ID given to the next token
+	next_token: u64,
+	/// Unconfirmed edits in the order they were applied
+	pending: Vec<SpeculativeEdit>,
+}
+
+impl SpeculativeEdits {
+	/// Get the number of unconfirmed edits
+	pub fn len(&self) -> usize {
+		self.pending.len()
+	}
+	/// Whether there are no unconfirmed edits
+	pub fn is_empty(&self) -> bool {
+		self.pending.is_empty()
+	}
+	/// Whether the edit of the `token` is waiting to be confirmed or reverted
+	pub fn is_pending(&self, token: SpeculationToken) -> bool {
+		self.pending.iter().any(|edit| edit.token == token)
+	}
+	/// Set the cost of each `(sector, field_cell, cost)` of the `changes`
+	/// and remember the costs they replaced under the returned token. The
+	/// cells are edited with [SectorCostFields::edit] so the plugin updates
+	/// the portals, graph and caches of each sector. Cells in sectors which
+	/// don't exist are ignored
+	pub fn apply(
+		&mut self,
+		sector_cost_fields: &mut SectorCostFields,
+		map_dimensions: &MapDimensions,
+		changes: &[(SectorID, FieldCell, u8)],
+	) -> SpeculationToken {
+		let mut previous: Vec<((SectorID, FieldCell), u8)> = Vec::with_capacity(changes.len());
+		let mut applied: Vec<((SectorID, FieldCell), u8)> = Vec::with_capacity(changes.len());
+		let mut costs = Vec::with_capacity(changes.len());
+		for (sector_id, field_cell, value) in changes.iter() {
+			let Some(cost_field) = sector_cost_fields.get_baseline().get(sector_id) else {
+				continue;
+			};
+			let cell = (*sector_id, *field_cell);
+			if !previous.iter().any(|(c, _)| *c == cell) {
+				previous.push((cell, cost_field.get_field_cell_value(*field_cell)));
+			}
+			// a cell changed more than once ends up with the last cost
+			match applied.iter_mut().find(|(c, _)| *c == cell) {
+				Some((_, cost)) => *cost = *value,
+				None => applied.push((cell, *value)),
+			}
+			costs.push((cell, *value));
+		}
+		set_costs(sector_cost_fields, map_dimensions, costs);
+		let token = SpeculationToken(self.next_token);
+		self.next_token += 1;
+		self.pending.push(SpeculativeEdit {
+			token,
+			previous,
+			applied,
+		});
+		token
+	}
+	/// Keep the changes of the `token` as they are and forget the costs they
+	/// replaced. Earlier pending edits of the same cells no longer restore
+	/// them when reverted, the confirmed cost stands. Returns `false` if the
+	/// token isn't pending
+	pub fn confirm(&mut self, token: SpeculationToken) -> bool {
+		let Some(index) = self.pending.iter().position(|edit| edit.token == token) else {
+			return false;
+		};
+		let edit = self.pending.remove(index);
+		for earlier in self.pending[..index].iter_mut() {
+			earlier
+				.previous
+				.retain(|(cell, _)| !edit.applied.iter().any(|(c, _)| c == cell));
+		}
+		true
+	}
+	/// Restore the costs replaced by the changes of the `token`. A cell which
+	/// a later pending edit has also changed keeps the later cost, and
+	/// reverting the later edit then restores the cost from before either of
+	/// them. A cell which no longer holds the cost set by the `token` has
+	/// been changed since and is left alone. Returns `false` if the token
+	/// isn't pending
+	pub fn revert(
+		&mut self,
+		token: SpeculationToken,
+		sector_cost_fields: &mut SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) -> bool {
+		let Some(index) = self.pending.iter().position(|edit| edit.token == token) else {
+			return false;
+		};
+		let edit = self.pending.remove(index);
+		let mut costs = Vec::with_capacity(edit.previous.len());
+		for (cell, value) in edit.previous {
+			let later = self.pending[index..]
+				.iter_mut()
+				.find_map(|later| later.previous.iter_mut().find(|(c, _)| *c == cell));
+			match later {
+				// the later edit now returns the cell to the cost from before
+				// this one
+				Some((_, later_previous)) => *later_previous = value,
+				None => {
+					let current = sector_cost_fields
+						.get_baseline()
+						.get(&cell.0)
+						.map(|cost_field| cost_field.get_field_cell_value(cell.1));
+					let applied = edit.applied.iter().find(|(c, _)| *c == cell);
+					if applied.map(|(_, cost)| *cost) == current {
+						costs.push((cell, value));
+					}
+				}
+			}
+		}
+		set_costs(sector_cost_fields, map_dimensions, costs);
+		true
+	}
+}
+
+/// Set the cost of each cell, grouping the cells by sector so that each
+/// sector is only rescaled once
+fn set_costs(
+	sector_cost_fields: &mut SectorCostFields,
+	map_dimensions: &MapDimensions,
+	mut costs: Vec<((SectorID, FieldCell), u8)>,
+) {
+	costs.sort_by_key(|((sector_id, _), _)| *sector_id);
+	for sector_costs in costs.chunk_by(|((a, _), _), ((b, _), _)| a == b) {
+		let sector_id = sector_costs[0].0 .0;
+		if let Some(mut guard) = sector_cost_fields.edit(sector_id, map_dimensions) {
+			for ((_, field_cell), value) in sector_costs.iter() {
+				guard.set_field_cell_value(*field_cell, *value);
+			}
+		}
+	}
+}
+
+/// Revert [CostModifiers] which have expired or whose handle has been
+/// dropped
+#[cfg(not(tarpaulin_include))]
+pub fn expire_cost_modifiers(
+	mut query: Query<(&mut CostModifiers, &mut SectorCostFields, &MapDimensions)>,
+	time: Res<Time>,
+) {
+	for (mut modifiers, mut sector_cost_fields, map_dimensions) in query.iter_mut() {
+		if modifiers.is_empty() {
+			continue;
+		}
+		modifiers.tick(time.delta(), &mut sector_cost_fields, map_dimensions);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn expire_restores_cost() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut modifiers = CostModifiers::default();
+		let sector_id = SectorID::new(1, 1);
+		let cell = FieldCell::new(2, 2);
+		let handle = modifiers.apply_timed_cost_modifier(
+			&mut cost_fields,
+			&map_dimensions,
+			&[(sector_id, cell)],
+			Cost::DIFFICULT,
+			Duration::from_secs(10),
+		);
+		let cost = |cost_fields: &SectorCostFields| {
+			cost_fields
+				.get_baseline()
+				.get(&sector_id)
+				.unwrap()
+				.get_field_cell_value(cell)
+		};
+		assert_eq!(200, cost(&cost_fields));
+		assert!(cost_fields.get_edited_sectors().contains(&sector_id));
+		assert_eq!(
+			0,
+			modifiers.tick(Duration::from_secs(5), &mut cost_fields, &map_dimensions)
+		);
+		assert!(modifiers.is_active(&handle));
+		assert_eq!(
+			1,
+			modifiers.tick(Duration::from_secs(5), &mut cost_fields, &map_dimensions)
+		);
+		assert_eq!(1, cost(&cost_fields));
+		assert!(!modifiers.is_active(&handle));
+		assert!(modifiers.get_original_cost(sector_id, cell).is_none());
+	}
+	#[test]
+	fn overlapping_modifiers_layer() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut modifiers = CostModifiers::default();
+		let sector_id = SectorID::new(0, 0);
+		let shared = FieldCell::new(1, 1);
+		let first = modifiers.apply_timed_cost_modifier(
+			&mut cost_fields,
+			&map_dimensions,
+			&[(sector_id, shared), (sector_id, FieldCell::new(0, 1))],
+			Cost::ROUGH,
+			Duration::from_secs(10),
+		);
+		let second = modifiers.apply_timed_cost_modifier(
+			&mut cost_fields,
+			&map_dimensions,
+			&[(sector_id, shared)],
+			Cost::IMPASSABLE,
+			Duration::from_secs(10),
+		);
+		let cost = |cost_fields: &SectorCostFields, cell| {
+			cost_fields
+				.get_baseline()
+				.get(&sector_id)
+				.unwrap()
+				.get_field_cell_value(cell)
+		};
+		assert_eq!(255, cost(&cost_fields, shared));
+		// dropping the first handle leaves the second in charge of the shared cell
+		drop(first);
+		modifiers.tick(Duration::ZERO, &mut cost_fields, &map_dimensions);
+		assert_eq!(255, cost(&cost_fields, shared));
+		assert_eq!(1, cost(&cost_fields, FieldCell::new(0, 1)));
+		// a detached handle runs until the modifier expires
+		second.detach();
+		modifiers.tick(Duration::ZERO, &mut cost_fields, &map_dimensions);
+		assert_eq!(1, modifiers.len());
+		modifiers.tick(Duration::from_secs(10), &mut cost_fields, &map_dimensions);
+		assert_eq!(1, cost(&cost_fields, shared));
+		assert!(modifiers.is_empty());
+	}
+	#[test]
+	fn speculative_edit_revert() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut edits = SpeculativeEdits::default();
+		let sector_id = SectorID::new(1, 0);
+		let shared = FieldCell::new(3, 3);
+		let cost = |cost_fields: &SectorCostFields, cell| {
+			cost_fields
+				.get_baseline()
+				.get(&sector_id)
+				.unwrap()
+				.get_field_cell_value(cell)
+		};
+		let wall = edits.apply(
+			&mut cost_fields,
+			&map_dimensions,
+			&[
+				(sector_id, shared, 255),
+				(sector_id, FieldCell::new(3, 4), 255),
+			],
+		);
+		let road = edits.apply(&mut cost_fields, &map_dimensions, &[(sector_id, shared, 5)]);
+		assert_eq!(vec![sector_id], cost_fields.take_edited_sectors());
+		// the later edit keeps the shared cell while the wall is rejected
+		assert!(edits.revert(wall, &mut cost_fields, &map_dimensions));
+		assert_eq!(5, cost(&cost_fields, shared));
+		assert_eq!(1, cost(&cost_fields, FieldCell::new(3, 4)));
+		assert_eq!(vec![sector_id], cost_fields.take_edited_sectors());
+		// and reverting the road restores the cost from before either edit
+		assert!(edits.revert(road, &mut cost_fields, &map_dimensions));
+		assert_eq!(1, cost(&cost_fields, shared));
+		assert!(!edits.revert(road, &mut cost_fields, &map_dimensions));
+		// a confirmed edit can no longer be reverted
+		let kept = edits.apply(&mut cost_fields, &map_dimensions, &[(sector_id, shared, 9)]);
+		assert!(edits.confirm(kept));
+		assert!(edits.is_empty());
+		assert!(!edits.revert(kept, &mut cost_fields, &map_dimensions));
+		assert_eq!(9, cost(&cost_fields, shared));
+	}
+	#[test]
+	fn speculative_edit_confirmed_over_earlier_edit() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut edits = SpeculativeEdits::default();
+		let sector_id = SectorID::new(0, 0);
+		let shared = FieldCell::new(5, 5);
+		let other = FieldCell::new(5, 6);
+		let cost = |cost_fields: &SectorCostFields, cell| {
+			cost_fields
+				.get_baseline()
+				.get(&sector_id)
+				.unwrap()
+				.get_field_cell_value(cell)
+		};
+		let a = edits.apply(
+			&mut cost_fields,
+			&map_dimensions,
+			&[(sector_id, shared, 255), (sector_id, other, 255)],
+		);
+		let b = edits.apply(&mut cost_fields, &map_dimensions, &[(sector_id, shared, 7)]);
+		assert!(edits.confirm(b));
+		// the confirmed cost of the shared cell survives rejecting the
+		// earlier edit while its other cell is restored
+		assert!(edits.revert(a, &mut cost_fields, &map_dimensions));
+		assert_eq!(7, cost(&cost_fields, shared));
+		assert_eq!(1, cost(&cost_fields, other));
+		assert!(edits.is_empty());
+	}
+	#[test]
+	fn speculative_revert_skips_changed_cells() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut edits = SpeculativeEdits::default();
+		let sector_id = SectorID::new(0, 0);
+		let cell = FieldCell::new(2, 2);
+		let token = edits.apply(&mut cost_fields, &map_dimensions, &[(sector_id, cell, 255)]);
+		// the cell is changed outside of the speculation
+		if let Some(mut guard) = cost_fields.edit(sector_id, &map_dimensions) {
+			guard.set_field_cell_value(cell, 3);
+		}
+		assert!(edits.revert(token, &mut cost_fields, &map_dimensions));
+		let cost = cost_fields
+			.get_baseline()
+			.get(&sector_id)
+			.unwrap()
+			.get_field_cell_value(cell);
+		assert_eq!(3, cost);
+	}
+}
@@ -0,0 +1,321 @@
+//! Patrols loop an actor through a series of goals, `A -> B -> C -> A`.
+//! Rather than issuing a path request every time an actor reaches a goal the
+//! patrol is registered once, the plugin then requests the route of each leg
+//! and keeps the routes and [FlowField]s of every leg cached for as long as
+//! the patrol is registered
+//!
+//! When playing turn-based (see [FlowFieldTurn]) cached routes never expire
+//! so they aren't refreshed, and a leg which isn't cached or queued is
+//! requested again at most once per turn rather than after the retry
+//! interval of the [FlowFieldConfig]
+//!
+
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+/// A looped series of goals
+#[derive(Clone, Debug, Default, Reflect)]
+pub struct Patrol {
+	/// The goals visited in order, after the last goal the patrol returns to
+	/// the first
+	goals: Vec<(SectorID, FieldCell)>,
+	/// When the route of each leg was last requested
+	requested: Vec<Option<Stamp>>,
+}
+
+impl Patrol {
+	/// Create a new instance of [Patrol] from its goals
+	pub fn new(goals: Vec<(SectorID, FieldCell)>) -> Self {
+		let requested = vec![None; goals.len()];
+		Patrol { goals, requested }
+	}
+	/// Get the goals of the patrol
+	pub fn get_goals(&self) -> &Vec<(SectorID, FieldCell)> {
+		&self.goals
+	}
+	/// Get the number of legs, a patrol of fewer than two goals has none
+	pub fn get_leg_count(&self) -> usize {
+		if self.goals.len() < 2 {
+			0
+		} else {
+			self.goals.len()
+		}
+	}
+	/// Get the [RouteMetadata] of a leg, from goal `leg` to the goal after
+	/// it. Returns [None] if the leg doesn't exist
+	pub fn get_leg(&self, leg: usize) -> Option<RouteMetadata> {
+		if leg >= self.get_leg_count() {
+			return None;
+		}
+		let source = self.goals[leg];
+		let target = self.goals[(leg + 1) % self.goals.len()];
+		Some(RouteMetadata::new(
+			source.0,
+			source.1,
+			target.0,
+			target.1,
+			Duration::default(),
+		))
+	}
+}
+
+/// The patrols registered within a [FlowFieldTilesBundle]
+#[derive(Component, Default, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct PatrolRoutes {
+	/// ID to give the next registered patrol
+	next_id: u32,
+	/// Each registered patrol by its ID
+	patrols: BTreeMap<u32, Patrol>,
+}
+
+impl PatrolRoutes {
+	/// Get the map of patrols
+	pub fn get(&self) -> &BTreeMap<u32, Patrol> {
+		&self.patrols
+	}
+	/// Get a patrol by its ID
+	pub fn get_patrol(&self, patrol_id: u32) -> Option<&Patrol> {
+		self.patrols.get(&patrol_id)
+	}
+	/// Register a patrol of `goals` and get its ID. The plugin requests the
+	/// route of each leg and keeps the routes and [FlowField]s cached until
+	/// the patrol is unregistered
+	pub fn register(&mut self, goals: Vec<(SectorID, FieldCell)>) -> u32 {
+		let id = self.next_id;
+		self.next_id += 1;
+		self.patrols.insert(id, Patrol::new(goals));
+		id
+	}
+	/// Remove a patrol, the fields of its legs are then left to expire
+	/// like any other
+	pub fn unregister(&mut self, patrol_id: u32) {
+		self.patrols.remove(&patrol_id);
+	}
+	/// Get the [FlowField] an actor at the `actor_sector` and `actor_cell`
+	/// should follow for the current leg of its [PatrolProgress]. When the
+	/// actor is standing on the goal of the leg its progress moves onto the
+	/// next leg.
+	///
+	/// Returns [None] if the patrol doesn't exist, the fields of the leg
+	/// haven't been built yet or the actor has strayed from the route of the
+	/// leg (in which case it should path back to the start of the leg)
+	pub fn get_current_flow<'a>(
+		&self,
+		progress: &mut PatrolProgress,
+		actor_sector: SectorID,
+		actor_cell: FieldCell,
+		route_cache: &RouteCache,
+		flow_cache: &'a FlowFieldCache,
+	) -> Option<&'a FlowField> {
+		let patrol = self.patrols.get(&progress.patrol)?;
+		let legs = patrol.get_leg_count();
+		if legs == 0 {
+			return None;
+		}
+		progress.leg %= legs;
+		let leg = patrol.get_leg(progress.leg)?;
+		if (actor_sector, actor_cell) == (leg.get_target_sector(), leg.get_target_goal()) {
+			progress.leg = (progress.leg + 1) % legs;
+		}
+		let leg = patrol.get_leg(progress.leg)?;
+		let route = route_cache.get_route(
+			leg.get_source_sector(),
+			leg.get_source_field_cell(),
+			leg.get_target_sector(),
+			leg.get_target_goal(),
+		)?;
+		let (_, goal_id) = route
+			.get()
+			.iter()
+			.find(|(sector_id, _)| *sector_id == actor_sector)?;
+		flow_cache.get_field(actor_sector, leg.get_target_sector(), *goal_id)
+	}
+}
+
+/// Tracks which patrol an actor is following and which leg it's on
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct PatrolProgress {
+	/// ID of the patrol within [PatrolRoutes]
+	patrol: u32,
+	/// The leg the actor is currently travelling
+	leg: usize,
+}
+
+impl PatrolProgress {
+	/// Create a new instance of [PatrolProgress] starting at the first leg
+	pub fn new(patrol: u32) -> Self {
+		PatrolProgress { patrol, leg: 0 }
+	}
+	/// Start the actor on a different leg
+	pub fn with_leg(mut self, leg: usize) -> Self {
+		self.leg = leg;
+		self
+	}
+	/// Get the ID of the patrol
+	pub fn get_patrol(&self) -> u32 {
+		self.patrol
+	}
+	/// Get the leg the actor is travelling
+	pub fn get_leg(&self) -> usize {
+		self.leg
+	}
+}
+
+/// Request the route of any patrol leg which isn't cached and refresh the
+/// generation time of those which are so they don't expire
+#[cfg(not(tarpaulin_include))]
+pub fn maintain_patrol_routes(
+	mut query: Query<(
+		Entity,
+		&mut PatrolRoutes,
+		&mut RouteCache,
+		&mut FlowFieldCache,
+		&RouteQueue,
+		&FlowFieldQueue,
+	)>,
+	mut event_path_request: EventWriter<EventPathRequest>,
+	time: Res<Time>,
+	turn: Option<Res<FlowFieldTurn>>,
+	state: Option<Res<FlowFieldSystemState>>,
+	config: Res<FlowFieldConfig>,
+) {
+	let elapsed = get_navigation_elapsed(&time, turn.as_deref(), state.as_deref());
+	for (entity, mut patrol_routes, mut route_cache, mut flow_cache, route_queue, flow_queue) in
+		query.iter_mut()
+	{
+		let mut to_request = Vec::new();
+		for (id, patrol) in patrol_routes.get().iter() {
+			for leg in 0..patrol.get_leg_count() {
+				let Some(metadata) = patrol.get_leg(leg) else {
+					continue;
+				};
+				if let Some((cached, route)) = route_cache.get_route_from_metadata(&metadata) {
+					let age = elapsed.time_since(cached.get_time_generated());
+					if age.is_some_and(|age| age > config.get_patrol_refresh_interval()) {
+						route_cache.refresh_route(metadata, elapsed);
+						flow_cache.refresh_route_fields(&route, elapsed);
+					}
+					continue;
+				}
+				let is_queued = route_queue.contains(&metadata)
+					|| flow_queue.get_queue().contains_key(&metadata);
+				let is_waiting = patrol.requested[leg].is_some_and(|requested| {
+					match elapsed.time_since(requested) {
+						Some(waited) => waited < config.get_patrol_retry_interval(),
+						// turns have no duration, wait for the next turn
+						None => requested == elapsed,
+					}
+				});
+				if !is_queued && !is_waiting {
+					to_request.push((*id, leg, metadata));
+				}
+			}
+		}
+		for (id, leg, metadata) in to_request {
+			if let Some(patrol) = patrol_routes.patrols.get_mut(&id) {
+				patrol.requested[leg] = Some(elapsed);
+			}
+			event_path_request.send(
+				EventPathRequest::new(
+					metadata.get_source_sector(),
+					metadata.get_source_field_cell(),
+					metadata.get_target_sector(),
+					metadata.get_target_goal(),
+				)
+				.with_nav_world(entity),
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn patrol_legs_loop() {
+		let a = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let b = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let c = (SectorID::new(2, 2), FieldCell::new(4, 4));
+		let patrol = Patrol::new(vec![a, b, c]);
+		assert_eq!(3, patrol.get_leg_count());
+		let last = patrol.get_leg(2).unwrap();
+		assert_eq!(c, (last.get_source_sector(), last.get_source_field_cell()));
+		assert_eq!(a, (last.get_target_sector(), last.get_target_goal()));
+		assert!(patrol.get_leg(3).is_none());
+		assert_eq!(0, Patrol::new(vec![a]).get_leg_count());
+	}
+	#[test]
+	fn patrol_current_flow_advances() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let a = (SectorID::new(0, 0), FieldCell::new(4, 4));
+		let b = (SectorID::new(2, 0), FieldCell::new(4, 4));
+		let mut patrol_routes = PatrolRoutes::default();
+		let id = patrol_routes.register(vec![a, b]);
+		let patrol = patrol_routes.get_patrol(id).unwrap();
+		let legs: Vec<RouteMetadata> = (0..patrol.get_leg_count())
+			.filter_map(|leg| patrol.get_leg(leg))
+			.collect();
+		bundle.prewarm(&legs);
+		let mut progress = PatrolProgress::new(id);
+		let flow = patrol_routes.get_current_flow(
+			&mut progress,
+			SectorID::new(1, 0),
+			FieldCell::new(5, 5),
+			&bundle.route_cache,
+			&bundle.flow_field_cache,
+		);
+		assert!(flow.is_some());
+		assert_eq!(0, progress.get_leg());
+		// reaching the goal of the leg moves onto the return leg
+		let flow = patrol_routes
+			.get_current_flow(
+				&mut progress,
+				b.0,
+				b.1,
+				&bundle.route_cache,
+				&bundle.flow_field_cache,
+			)
+			.unwrap();
+		assert_eq!(1, progress.get_leg());
+		assert!(!is_goal(flow.get_field_cell_value(b.1)));
+		// an actor off the route of the leg has no flow
+		let flow = patrol_routes.get_current_flow(
+			&mut progress,
+			SectorID::new(1, 2),
+			FieldCell::new(5, 5),
+			&bundle.route_cache,
+			&bundle.flow_field_cache,
+		);
+		assert!(flow.is_none());
+		patrol_routes.unregister(id);
+		assert!(patrol_routes.get_patrol(id).is_none());
+	}
+	#[test]
+	fn refresh_pins_route() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let leg = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(4, 4),
+			SectorID::new(2, 0),
+			FieldCell::new(4, 4),
+			Duration::default(),
+		);
+		bundle.prewarm(&[leg]);
+		let elapsed = Duration::from_secs(600);
+		assert!(bundle.route_cache.refresh_route(leg, elapsed));
+		let (_, route) = bundle.route_cache.get_route_from_metadata(&leg).unwrap();
+		bundle
+			.flow_field_cache
+			.refresh_route_fields(&route, elapsed);
+		let (cached, _) = bundle.route_cache.get_route_from_metadata(&leg).unwrap();
+		assert_eq!(Stamp::Time(elapsed), cached.get_time_generated());
+		for metadata in bundle.flow_field_cache.get().keys() {
+			assert_eq!(Stamp::Time(elapsed), metadata.get_time_generated());
+		}
+	}
+}
@@ -0,0 +1,197 @@
+//! Finds every [FieldCell] an actor can reach within a travel cost budget,
+//! such as the movement range of a unit in a turn-based game. The cost is
+//! integrated outwards from the source across sector boundaries and the
+//! expansion stops once the budget is spent
+//!
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Find each pathable [FieldCell] which can be reached from the `source`
+/// for no more than `budget`, along with the cost of reaching it. The cost
+/// of a path is the sum of the costs of the cells moved into (the source is
+/// free) and cells are listed in order of increasing cost. Returns an empty
+/// list if the source is impassable or outside of the map
+pub fn find_reachable_cells(
+	source: (SectorID, FieldCell),
+	budget: u32,
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+) -> Vec<(SectorID, FieldCell, u32)> {
+	let mut reachable = Vec::new();
+	let columns = map_dimensions.get_total_field_cell_columns();
+	let rows = map_dimensions.get_total_field_cell_rows();
+	let (source_column, source_row) = map_dimensions.global_cell_from(source.0, source.1);
+	let (source_column, source_row) = (source_column as usize, source_row as usize);
+	if source_column >= columns
+		|| source_row >= rows
+		|| get_global_cost(
+			sector_cost_fields,
+			map_dimensions,
+			source_column,
+			source_row,
+		) == 255
+	{
+		return reachable;
+	}
+	integrate_global_costs(
+		(source_column, source_row),
+		budget,
+		sector_cost_fields,
+		map_dimensions,
+		|_, _| true,
+		|column, row, cumulative| {
+			if let Some((sector_id, field_cell)) =
+				map_dimensions.sector_and_cell_from_global(column as u32, row as u32)
+			{
+				reachable.push((sector_id, field_cell, cumulative));
+			}
+			true
+		},
+	);
+	reachable
+}
+
+/// Request the [FieldCell]s reachable from a source within a travel cost
+/// budget, the result is sent as an [EventReachableCells] by each bundle
+#[derive(Event, Clone, Copy)]
+pub struct EventReachableRequest {
+	/// The sector the actor is in
+	source_sector: SectorID,
+	/// The [FieldCell] the actor is on
+	source_field_cell: FieldCell,
+	/// The most the actor may spend travelling
+	budget: u32,
+	/// The bundle entity to search within, if [None] then every bundle is
+	/// searched
+	nav_world: Option<Entity>,
+	/// Correlates the request with its [EventReachableCells]
+	request_id: RequestId,
+}
+
+impl EventReachableRequest {
+	/// Create a new instance of [EventReachableRequest]
+	pub fn new(source_sector: SectorID, source_field_cell: FieldCell, budget: u32) -> Self {
+		EventReachableRequest {
+			source_sector,
+			source_field_cell,
+			budget,
+			nav_world: None,
+			request_id: RequestId::next(),
+		}
+	}
+	/// Only search within the bundle of the `nav_world` entity
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	/// Get the sector the actor is in
+	pub fn get_source_sector(&self) -> SectorID {
+		self.source_sector
+	}
+	/// Get the [FieldCell] the actor is on
+	pub fn get_source_field_cell(&self) -> FieldCell {
+		self.source_field_cell
+	}
+	/// Get the most the actor may spend travelling
+	pub fn get_budget(&self) -> u32 {
+		self.budget
+	}
+	/// Get the bundle entity the request applies to
+	pub fn get_nav_world(&self) -> Option<Entity> {
+		self.nav_world
+	}
+	/// Get the ID which the [EventReachableCells] of the request carries
+	pub fn get_request_id(&self) -> RequestId {
+		self.request_id
+	}
+}
+
+/// The [FieldCell]s found for an [EventReachableRequest] within a bundle
+#[derive(Event, Clone, Debug)]
+pub struct EventReachableCells {
+	/// The bundle entity which was searched
+	nav_world: Entity,
+	/// ID of the request
+	request_id: RequestId,
+	/// Each reachable cell and the cost of reaching it
+	cells: Vec<(SectorID, FieldCell, u32)>,
+}
+
+impl EventReachableCells {
+	/// Get the bundle entity which was searched
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+	/// Get the ID of the [EventReachableRequest] this answers
+	pub fn get_request_id(&self) -> RequestId {
+		self.request_id
+	}
+	/// Get each reachable cell and the cost of reaching it, in order of
+	/// increasing cost
+	pub fn get_cells(&self) -> &[(SectorID, FieldCell, u32)] {
+		&self.cells
+	}
+}
+
+/// Find the reachable [FieldCell]s of each [EventReachableRequest]
+#[cfg(not(tarpaulin_include))]
+pub fn process_reachable_requests(
+	mut events: EventReader<EventReachableRequest>,
+	query: Query<(Entity, &SectorCostFields, &MapDimensions)>,
+	mut event_cells: EventWriter<EventReachableCells>,
+) {
+	for event in events.read() {
+		for (entity, sector_cost_fields, map_dimensions) in query.iter() {
+			if event.get_nav_world().is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			let cells = find_reachable_cells(
+				(event.get_source_sector(), event.get_source_field_cell()),
+				event.get_budget(),
+				sector_cost_fields,
+				map_dimensions,
+			);
+			event_cells.send(EventReachableCells {
+				nav_world: entity,
+				request_id: event.get_request_id(),
+				cells,
+			});
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn reachable_within_budget() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		// a wall stops the expansion going north
+		let sector_id = SectorID::new(1, 1);
+		for column in 0..10 {
+			sector_cost_fields.set_field_cell_value(
+				sector_id,
+				255,
+				FieldCell::new(column, 4),
+				&map_dimensions,
+			);
+		}
+		let source = (sector_id, FieldCell::new(0, 5));
+		let cells = find_reachable_cells(source, 2, &sector_cost_fields, &map_dimensions);
+		// the source, 3 cells at a cost of 1 and 6 at a cost of 2
+		assert_eq!(10, cells.len());
+		assert_eq!((sector_id, FieldCell::new(0, 5), 0), cells[0]);
+		// the expansion crosses into the western sector
+		assert!(cells.contains(&(SectorID::new(0, 1), FieldCell::new(8, 5), 2)));
+		assert!(!cells
+			.iter()
+			.any(|(s, c, _)| *s == sector_id && c.get_row() < 5));
+		let costs: Vec<u32> = cells.iter().map(|(_, _, cost)| *cost).collect();
+		assert!(costs.windows(2).all(|pair| pair[0] <= pair[1]));
+		// nothing is reachable from a wall
+		let wall = (sector_id, FieldCell::new(0, 4));
+		assert!(find_reachable_cells(wall, 2, &sector_cost_fields, &map_dimensions).is_empty());
+	}
+}
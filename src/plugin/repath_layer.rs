@@ -0,0 +1,174 @@
+//! Helpers for a steering pipeline/character controller to avoid jittering
+//! when fields are frequently rebuilt. An actor can use a [RepathTracker] to
+//! throttle how often it re-requests a path and to smoothly blend from
+//! following its high-level route to following a freshly built [crate::prelude::FlowField]
+//!
+
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+/// Hysteresis settings shared by actors when deciding whether to send a new
+/// [crate::prelude::EventPathRequest] and how quickly to switch from route-following to
+/// flow-following
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct RepathHysteresis {
+	/// Minimum time between path requests of an actor
+	min_interval: Duration,
+	/// Minimum distance the goal must move before a new path is requested
+	min_goal_movement: f32,
+	/// How long an actor takes to fully switch from following its route to
+	/// following a [crate::prelude::FlowField]
+	blend_duration: Duration,
+}
+
+impl Default for RepathHysteresis {
+	fn default() -> Self {
+		RepathHysteresis {
+			min_interval: Duration::from_millis(500),
+			min_goal_movement: 0.0,
+			blend_duration: Duration::ZERO,
+		}
+	}
+}
+
+impl RepathHysteresis {
+	/// Set the minimum time between path requests of an actor
+	pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+		self.min_interval = min_interval;
+		self
+	}
+	/// Set the minimum distance the goal must move before a new path is
+	/// requested
+	pub fn with_min_goal_movement(mut self, min_goal_movement: f32) -> Self {
+		self.min_goal_movement = min_goal_movement;
+		self
+	}
+	/// Set how long an actor takes to switch from route-following to
+	/// flow-following, a duration of zero switches immediately
+	pub fn with_blend_duration(mut self, blend_duration: Duration) -> Self {
+		self.blend_duration = blend_duration;
+		self
+	}
+	/// Get the minimum time between path requests of an actor
+	pub fn get_min_interval(&self) -> Duration {
+		self.min_interval
+	}
+	/// Get the minimum distance the goal must move before a new path is
+	/// requested
+	pub fn get_min_goal_movement(&self) -> f32 {
+		self.min_goal_movement
+	}
+	/// Get how long an actor takes to switch from route-following to
+	/// flow-following
+	pub fn get_blend_duration(&self) -> Duration {
+		self.blend_duration
+	}
+}
+
+/// Records when an actor last requested a path and when it began following
+/// a [crate::prelude::FlowField] so that [RepathHysteresis] can be applied. Goal positions
+/// are in world space, 2d actors can extend a `Vec2` with a `z` of `0.0`
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct RepathTracker {
+	/// The elapsed time and goal position of the last path request
+	last_request: Option<(Duration, Vec3)>,
+	/// The elapsed time at which the actor began following a [crate::prelude::FlowField]
+	flow_started: Option<Duration>,
+}
+
+impl RepathTracker {
+	/// Whether the actor should send a new path request to `goal` at the
+	/// `elapsed` time. The first request is always allowed, after that the
+	/// minimum interval must have passed and the goal must have moved far
+	/// enough
+	pub fn should_request(
+		&self,
+		hysteresis: &RepathHysteresis,
+		elapsed: Duration,
+		goal: Vec3,
+	) -> bool {
+		match self.last_request {
+			None => true,
+			Some((time, last_goal)) => {
+				elapsed.saturating_sub(time) >= hysteresis.get_min_interval()
+					&& goal.distance(last_goal) >= hysteresis.get_min_goal_movement()
+			}
+		}
+	}
+	/// Record that a path request to `goal` has been sent at the `elapsed`
+	/// time, the actor goes back to following its route until a
+	/// [crate::prelude::FlowField] is available
+	pub fn record_request(&mut self, elapsed: Duration, goal: Vec3) {
+		self.last_request = Some((elapsed, goal));
+		self.flow_started = None;
+	}
+	/// Record that a [crate::prelude::FlowField] has become available at the `elapsed` time,
+	/// repeated calls keep the original time so the blend isn't restarted
+	pub fn record_flow_available(&mut self, elapsed: Duration) {
+		if self.flow_started.is_none() {
+			self.flow_started = Some(elapsed);
+		}
+	}
+	/// Get how much of the actors steering should come from the [crate::prelude::FlowField]
+	/// rather than its route at the `elapsed` time, between `0.0` (follow the
+	/// route) and `1.0` (follow the field)
+	pub fn get_flow_weight(&self, hysteresis: &RepathHysteresis, elapsed: Duration) -> f32 {
+		let Some(started) = self.flow_started else {
+			return 0.0;
+		};
+		let blend = hysteresis.get_blend_duration();
+		if blend.is_zero() {
+			return 1.0;
+		}
+		(elapsed.saturating_sub(started).as_secs_f32() / blend.as_secs_f32()).min(1.0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn request_throttled_by_interval_and_goal_movement() {
+		let hysteresis = RepathHysteresis::default()
+			.with_min_interval(Duration::from_secs(1))
+			.with_min_goal_movement(2.0);
+		let mut tracker = RepathTracker::default();
+		assert!(tracker.should_request(&hysteresis, Duration::ZERO, Vec3::ZERO));
+		tracker.record_request(Duration::ZERO, Vec3::ZERO);
+		// too soon
+		let far_goal = Vec3::new(5.0, 0.0, 0.0);
+		assert!(!tracker.should_request(&hysteresis, Duration::from_millis(500), far_goal));
+		// goal hasn't moved enough
+		let near_goal = Vec3::new(1.0, 0.0, 0.0);
+		assert!(!tracker.should_request(&hysteresis, Duration::from_secs(2), near_goal));
+		assert!(tracker.should_request(&hysteresis, Duration::from_secs(2), far_goal));
+	}
+	#[test]
+	fn flow_weight_blends() {
+		let hysteresis = RepathHysteresis::default().with_blend_duration(Duration::from_secs(2));
+		let mut tracker = RepathTracker::default();
+		tracker.record_request(Duration::ZERO, Vec3::ZERO);
+		assert_eq!(
+			0.0,
+			tracker.get_flow_weight(&hysteresis, Duration::from_secs(1))
+		);
+		tracker.record_flow_available(Duration::from_secs(1));
+		tracker.record_flow_available(Duration::from_secs(2));
+		assert_eq!(
+			0.5,
+			tracker.get_flow_weight(&hysteresis, Duration::from_secs(2))
+		);
+		assert_eq!(
+			1.0,
+			tracker.get_flow_weight(&hysteresis, Duration::from_secs(5))
+		);
+		// a new request returns the actor to its route
+		tracker.record_request(Duration::from_secs(5), Vec3::ONE);
+		assert_eq!(
+			0.0,
+			tracker.get_flow_weight(&hysteresis, Duration::from_secs(6))
+		);
+	}
+}
@@ -0,0 +1,125 @@
+//! A read-only snapshot of the [FlowField]s of every bundle which steering
+//! systems can read from in parallel. Reading the [FlowFieldCache] component
+//! directly ties a steering system to the bundle query, the snapshot is a
+//! [Resource] so that actors can be processed with `par_iter` against it
+//! without any aliasing concerns
+//!
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// The [FlowField]s of a single bundle shared between the cache, the
+/// snapshot and any system holding on to it
+pub type FlowFieldMap = Arc<BTreeMap<FlowFieldMetadata, Arc<FlowField>>>;
+
+/// View of the [FlowField]s of each bundle refreshed once the Calculate set
+/// of the plugin has run. A bundle is only refreshed when its
+/// [FlowFieldCache] has changed, the fields themselves are shared with the
+/// cache behind [Arc]s so a refresh copies the map of pointers rather than
+/// every [FlowField], and cloning the snapshot or the fields of a bundle is
+/// cheap
+#[derive(Resource, Default, Clone)]
+pub struct FlowFieldSnapshot {
+	/// The fields of each bundle entity
+	bundles: BTreeMap<Entity, FlowFieldMap>,
+}
+
+impl FlowFieldSnapshot {
+	/// Get the fields of the bundle of the `nav_world` entity
+	pub fn get(&self, nav_world: Entity) -> Option<&FlowFieldMap> {
+		self.bundles.get(&nav_world)
+	}
+	/// Get the map of bundle entities and their fields
+	pub fn get_bundles(&self) -> &BTreeMap<Entity, FlowFieldMap> {
+		&self.bundles
+	}
+	/// Get a [FlowField] of the bundle of the `nav_world` entity based on the
+	/// `sector_id` and `goal_id`, see [FlowFieldCache::get_field]. Returns
+	/// [None] if the snapshot doesn't contain a record
+	pub fn get_field(
+		&self,
+		nav_world: Entity,
+		current_sector_id: SectorID,
+		goal_sector_id: SectorID,
+		goal_id: FieldCell,
+	) -> Option<&FlowField> {
+		let flow_meta = FlowFieldMetadata::for_lookup(current_sector_id, goal_sector_id, goal_id);
+		self.bundles
+			.get(&nav_world)?
+			.get(&flow_meta)
+			.map(Arc::as_ref)
+	}
+	/// Replace the fields of the bundle of the `nav_world` entity with those
+	/// in the `cache`, the fields are shared rather than copied
+	pub fn update(&mut self, nav_world: Entity, cache: &FlowFieldCache) {
+		self.bundles
+			.insert(nav_world, Arc::new(cache.get().clone()));
+	}
+	/// Remove the fields of any bundle that isn't one of the `nav_worlds`
+	pub fn retain(&mut self, nav_worlds: &[Entity]) {
+		self.bundles.retain(|entity, _| nav_worlds.contains(entity));
+	}
+}
+
+/// Share the fields of each [FlowFieldCache] which has changed with the
+/// [FlowFieldSnapshot]
+#[cfg(not(tarpaulin_include))]
+pub fn refresh_flow_field_snapshot(
+	mut snapshot: ResMut<FlowFieldSnapshot>,
+	caches: Query<(Entity, Ref<FlowFieldCache>)>,
+) {
+	let nav_worlds: Vec<Entity> = caches.iter().map(|(entity, _)| entity).collect();
+	if snapshot.get_bundles().len() != nav_worlds.len()
+		|| snapshot
+			.get_bundles()
+			.keys()
+			.any(|entity| !nav_worlds.contains(entity))
+	{
+		snapshot.retain(&nav_worlds);
+	}
+	for (entity, cache) in caches.iter() {
+		if cache.is_changed() || snapshot.get(entity).is_none() {
+			snapshot.update(entity, &cache);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::utils::Duration;
+	#[test]
+	fn snapshot_copies_fields() {
+		let mut cache = FlowFieldCache::default();
+		let sector_id = SectorID::new(0, 0);
+		let goal = FieldCell::new(4, 4);
+		cache.insert_field(
+			sector_id,
+			Some(goal),
+			None,
+			Duration::default(),
+			FlowField::default(),
+		);
+		let nav_world = Entity::from_raw(7);
+		let mut snapshot = FlowFieldSnapshot::default();
+		snapshot.update(nav_world, &cache);
+		let shared = snapshot.get(nav_world).unwrap().clone();
+		// the snapshot shares the field of the cache rather than copying it
+		let field = cache.get().values().next().unwrap();
+		assert!(Arc::ptr_eq(field, shared.values().next().unwrap()));
+		assert!(snapshot
+			.get_field(nav_world, sector_id, sector_id, goal)
+			.is_some());
+		// later changes to the cache don't alter the snapshot until refreshed
+		cache.remove_field(FlowFieldMetadata::for_lookup(sector_id, sector_id, goal));
+		assert_eq!(1, shared.len());
+		snapshot.update(nav_world, &cache);
+		assert!(snapshot
+			.get_field(nav_world, sector_id, sector_id, goal)
+			.is_none());
+		snapshot.retain(&[]);
+		assert!(snapshot.get_bundles().is_empty());
+	}
+}
@@ -0,0 +1,248 @@
+//! Finds positions to spawn actors at, such as a wave of units appearing
+//! around a base. Positions are picked from the pathable [FieldCell]s of an
+//! area, kept apart from one another and limited to the cells which can
+//! reach a reference point so that no actor is spawned on an island it
+//! can't walk out of.
+//!
+//! Positions are in the `x-y` plane of a 2d world, for a 3d world the
+//! position `(x, y, z)` is equivalent to `(x, -z)`
+//!
+
+use std::collections::BTreeSet;
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// The region of the world spawn positions are picked from
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum SpawnArea {
+	/// An axis-aligned rectangle between its `min` and `max` corners
+	Rect { min: Vec2, max: Vec2 },
+	/// A circle about its `centre`
+	Circle { centre: Vec2, radius: f32 },
+}
+
+impl SpawnArea {
+	/// Get the centre of the area
+	pub fn get_centre(&self) -> Vec2 {
+		match self {
+			SpawnArea::Rect { min, max } => (*min + *max) / 2.0,
+			SpawnArea::Circle { centre, .. } => *centre,
+		}
+	}
+	/// Get the `(min, max)` corners of the rectangle bounding the area
+	pub fn get_bounds(&self) -> (Vec2, Vec2) {
+		match self {
+			SpawnArea::Rect { min, max } => (min.min(*max), min.max(*max)),
+			SpawnArea::Circle { centre, radius } => (
+				*centre - Vec2::splat(*radius),
+				*centre + Vec2::splat(*radius),
+			),
+		}
+	}
+	/// Whether the `position` lies within the area
+	pub fn contains(&self, position: Vec2) -> bool {
+		match self {
+			SpawnArea::Rect { .. } => {
+				let (min, max) = self.get_bounds();
+				position.cmpge(min).all() && position.cmple(max).all()
+			}
+			SpawnArea::Circle { centre, radius } => {
+				position.distance_squared(*centre) <= radius * radius
+			}
+		}
+	}
+}
+
+/// Find up to `count` positions within the `area` to spawn actors at. Each
+/// position is the centre of a pathable [FieldCell] (based on the scaled
+/// [CostField]s so the actor fits), is at least `min_separation` away from
+/// every other position and can walk to the `reference` cell, such as the
+/// goal of the wave or the entrance of a base. Cells closest to the centre
+/// of the area are picked first. Fewer positions are returned if the area
+/// can't fit `count` of them and none if the `reference` is impassable or
+/// outside of the map
+pub fn find_spawn_positions(
+	area: SpawnArea,
+	count: usize,
+	min_separation: f32,
+	reference: (SectorID, FieldCell),
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+) -> Vec<Vec2> {
+	let mut positions: Vec<Vec2> = Vec::new();
+	let columns = map_dimensions.get_total_field_cell_columns();
+	let rows = map_dimensions.get_total_field_cell_rows();
+	if count == 0 {
+		return positions;
+	}
+	let size = map_dimensions.get_field_cell_unit_size();
+	let offset_x = map_dimensions.get_length() as f32 / 2.0;
+	let offset_y = map_dimensions.get_depth() as f32 / 2.0;
+	let centre_of = |column: usize, row: usize| {
+		Vec2::new(
+			(column as f32 + 0.5) * size - offset_x,
+			offset_y - (row as f32 + 0.5) * size,
+		)
+	};
+	// gather the pathable cells within the area, global columns increase
+	// with x and rows increase with -y
+	let (min, max) = area.get_bounds();
+	let to_index =
+		|value: f32, limit: usize| (value / size).floor().clamp(0.0, limit as f32) as usize;
+	let first_column = to_index(min.x + offset_x, columns);
+	let last_column = to_index(max.x + offset_x, columns - 1);
+	let first_row = to_index(offset_y - max.y, rows);
+	let last_row = to_index(offset_y - min.y, rows - 1);
+	let mut candidates = BTreeSet::new();
+	for row in first_row..=last_row {
+		for column in first_column..=last_column {
+			if area.contains(centre_of(column, row))
+				&& get_global_cost(sector_cost_fields, map_dimensions, column, row) != 255
+			{
+				candidates.insert((column, row));
+			}
+		}
+	}
+	if candidates.is_empty() {
+		return positions;
+	}
+	// flood outwards from the reference to find which candidates it is
+	// connected to, stopping early once every candidate has been found
+	let (ref_column, ref_row) = map_dimensions.global_cell_from(reference.0, reference.1);
+	let (ref_column, ref_row) = (ref_column as usize, ref_row as usize);
+	if ref_column >= columns
+		|| ref_row >= rows
+		|| get_global_cost(sector_cost_fields, map_dimensions, ref_column, ref_row) == 255
+	{
+		return positions;
+	}
+	// only the cells searched are recorded so a reference close to the
+	// area doesn't touch the rest of the map
+	let mut connected = Vec::new();
+	integrate_global_costs(
+		(ref_column, ref_row),
+		u32::MAX,
+		sector_cost_fields,
+		map_dimensions,
+		|_, _| true,
+		|column, row, _| {
+			if candidates.contains(&(column, row)) {
+				connected.push((column, row));
+			}
+			connected.len() < candidates.len()
+		},
+	);
+	// greedily pick the cells closest to the centre which keep apart from
+	// those already picked
+	let area_centre = area.get_centre();
+	let mut connected: Vec<Vec2> = connected
+		.iter()
+		.map(|(column, row)| centre_of(*column, *row))
+		.collect();
+	connected.sort_by(|a, b| {
+		a.distance_squared(area_centre)
+			.total_cmp(&b.distance_squared(area_centre))
+			.then(b.y.total_cmp(&a.y))
+			.then(a.x.total_cmp(&b.x))
+	});
+	let separation_squared = min_separation * min_separation;
+	for position in connected {
+		if positions
+			.iter()
+			.all(|picked| picked.distance_squared(position) >= separation_squared)
+		{
+			positions.push(position);
+			if positions.len() == count {
+				break;
+			}
+		}
+	}
+	positions
+}
+
+/// Find up to `count` positions to spawn actors at in a 3d world, see
+/// [find_spawn_positions]. The `area` is described in the `x-y` plane where
+/// `(x, y, z)` is equivalent to `(x, -z)` and the `y` coordinate of each
+/// position is defaulted to `0.0`
+#[cfg(feature = "3d")]
+pub fn find_spawn_positions_xyz(
+	area: SpawnArea,
+	count: usize,
+	min_separation: f32,
+	reference: (SectorID, FieldCell),
+	sector_cost_fields: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+) -> Vec<Vec3> {
+	find_spawn_positions(
+		area,
+		count,
+		min_separation,
+		reference,
+		sector_cost_fields,
+		map_dimensions,
+	)
+	.into_iter()
+	.map(|position| Vec3::new(position.x, 0.0, -position.y))
+	.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn spawn_positions_connected_and_separated() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		// a wall runs north to south through the middle of the map
+		for sector_row in 0..3 {
+			for row in 0..10 {
+				sector_cost_fields.set_field_cell_value(
+					SectorID::new(1, sector_row),
+					255,
+					FieldCell::new(5, row),
+					&map_dimensions,
+				);
+			}
+		}
+		let reference = (SectorID::new(0, 1), FieldCell::new(0, 0));
+		let area = SpawnArea::Circle {
+			centre: Vec2::ZERO,
+			radius: 5.0,
+		};
+		let positions = find_spawn_positions(
+			area,
+			4,
+			2.0,
+			reference,
+			&sector_cost_fields,
+			&map_dimensions,
+		);
+		assert_eq!(4, positions.len());
+		for (i, position) in positions.iter().enumerate() {
+			// only the western side of the wall can reach the reference
+			assert!(position.x < 0.0);
+			assert!(area.contains(*position));
+			for other in positions.iter().skip(i + 1) {
+				assert!(position.distance(*other) >= 2.0);
+			}
+		}
+		// the area can't fit every position asked for
+		let positions = find_spawn_positions(
+			area,
+			1000,
+			2.0,
+			reference,
+			&sector_cost_fields,
+			&map_dimensions,
+		);
+		assert!(positions.len() > 4 && positions.len() < 1000);
+		// nothing can reach a wall
+		let wall = (SectorID::new(1, 1), FieldCell::new(5, 0));
+		assert!(
+			find_spawn_positions(area, 4, 2.0, wall, &sector_cost_fields, &map_dimensions)
+				.is_empty()
+		);
+	}
+}
@@ -0,0 +1,150 @@
+//! Navigation can be suspended, such as while a game is paused, without
+//! losing any work. While [FlowFieldSystemState] is paused the queues of
+//! routes and fields aren't processed, path requests and [CostField] updates
+//! are still accepted so that they're picked up on resume. The time spent
+//! paused is tracked and excluded from the age of cached entries (and from
+//! the time new metadata is stamped with, see [get_navigation_elapsed]) so
+//! they don't all expire at once on resume.
+//!
+//! To instead tie every navigation system to a game state see
+//! [FlowFieldTilesPlugin::run_if]
+//!
+
+use bevy::{prelude::*, utils::Duration};
+
+/// Whether the plugin processes its queues of routes and fields
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct FlowFieldSystemState {
+	/// Whether processing has been suspended
+	paused: bool,
+	/// Total time spent paused
+	paused_time: Duration,
+}
+
+impl FlowFieldSystemState {
+	/// Suspend processing of the queues, queued work is kept
+	pub fn pause(&mut self) {
+		self.paused = true;
+	}
+	/// Resume processing of the queues
+	pub fn resume(&mut self) {
+		self.paused = false;
+	}
+	/// Whether processing has been suspended
+	pub fn is_paused(&self) -> bool {
+		self.paused
+	}
+	/// Get the total time spent paused
+	pub fn get_paused_time(&self) -> Duration {
+		self.paused_time
+	}
+	/// Get the time `elapsed` since app start less the time spent paused
+	pub fn get_unpaused_elapsed(&self, elapsed: Duration) -> Duration {
+		elapsed.saturating_sub(self.paused_time)
+	}
+}
+
+/// Accumulate the time spent paused, runs whether or not the
+/// [FlowFieldSystemState] is paused
+#[cfg(not(tarpaulin_include))]
+pub fn track_paused_time(state: Option<ResMut<FlowFieldSystemState>>, time: Res<Time>) {
+	if let Some(mut state) = state.filter(|state| state.is_paused()) {
+		state.paused_time += time.delta();
+	}
+}
+
+/// Run condition of the systems which process queued work or expire cached
+/// entries, they're skipped while the [FlowFieldSystemState] is paused
+pub fn is_flow_field_processing_active(state: Option<Res<FlowFieldSystemState>>) -> bool {
+	state.is_none_or(|state| !state.is_paused())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::prelude::*;
+	use bevy::time::TimeUpdateStrategy;
+
+	#[test]
+	fn pause_and_resume() {
+		let mut state = FlowFieldSystemState::default();
+		assert!(!state.is_paused());
+		state.pause();
+		assert!(state.is_paused());
+		state.resume();
+		assert!(!state.is_paused());
+	}
+	#[test]
+	fn cached_entries_survive_resume() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin::default()))
+			.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+				200,
+			)))
+			.insert_resource(
+				FlowFieldConfig::default()
+					.with_route_lifetime(Duration::from_secs(2))
+					.with_field_lifetime(Duration::from_secs(2)),
+			);
+		let entity = app
+			.world_mut()
+			.spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5))
+			.id();
+		app.world_mut().send_event(EventPathRequest::new(
+			SectorID::new(0, 0),
+			FieldCell::new(0, 0),
+			SectorID::new(2, 2),
+			FieldCell::new(4, 4),
+		));
+		for _ in 0..5 {
+			app.update();
+		}
+		let is_cached = |app: &App| {
+			!app.world().get::<RouteCache>(entity).unwrap().is_empty()
+				&& !app
+					.world()
+					.get::<FlowFieldCache>(entity)
+					.unwrap()
+					.get()
+					.is_empty()
+		};
+		assert!(is_cached(&app));
+		// pause for longer than the lifetime of the entries
+		app.world_mut()
+			.resource_mut::<FlowFieldSystemState>()
+			.pause();
+		for _ in 0..20 {
+			app.update();
+		}
+		let state = app.world().resource::<FlowFieldSystemState>();
+		assert!(state.get_paused_time() >= Duration::from_secs(4));
+		app.world_mut()
+			.resource_mut::<FlowFieldSystemState>()
+			.resume();
+		app.update();
+		assert!(is_cached(&app));
+		// and they expire once they've been unpaused for their lifetime
+		for _ in 0..15 {
+			app.update();
+		}
+		assert!(!is_cached(&app));
+	}
+	#[test]
+	fn run_condition_gates_every_system() {
+		let mut app = App::new();
+		app.add_plugins((
+			MinimalPlugins,
+			FlowFieldTilesPlugin::default().run_if(|| false),
+		));
+		app.world_mut()
+			.spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5));
+		for _ in 0..3 {
+			app.update();
+		}
+		// the snapshot is refreshed outside of the ordering sets yet still
+		// waits on the run condition
+		let snapshot = app.world().resource::<FlowFieldSnapshot>();
+		assert!(snapshot.get_bundles().is_empty());
+	}
+}
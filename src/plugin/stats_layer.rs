@@ -0,0 +1,249 @@
+//! Counters describing how effectively routes and [FlowField]s are shared
+//! between actors. They can be used to tune the `sector_resolution` of a map
+//! and cache policies based on data rather than guesswork, either by reading
+//! the [FlowFieldStats] resource or by adding the
+//! [FlowFieldDiagnosticsPlugin] to feed them into `bevy_diagnostic`
+//!
+
+use crate::prelude::*;
+use bevy::{
+	diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+	prelude::*,
+};
+
+/// A set of counters recorded by the plugin
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub struct FlowFieldCounters {
+	/// Path requests whose route was already cached
+	route_cache_hits: u64,
+	/// Path requests which needed a new route
+	route_cache_misses: u64,
+	/// Sectors of a built route whose fields were shared from the cache
+	flow_cache_hits: u64,
+	/// Sectors of a built route whose fields had to be calculated
+	flow_cache_misses: u64,
+	/// Routes re-requested because a [CostField] they used was changed
+	cost_edit_rebuilds: u64,
+	/// Routes which have had their [FlowField]s built
+	routes_built: u64,
+}
+
+impl FlowFieldCounters {
+	/// Get the number of path requests whose route was already cached
+	pub fn get_route_cache_hits(&self) -> u64 {
+		self.route_cache_hits
+	}
+	/// Get the number of path requests which needed a new route
+	pub fn get_route_cache_misses(&self) -> u64 {
+		self.route_cache_misses
+	}
+	/// Get the number of sectors of built routes whose [IntegrationField]
+	/// and [FlowField] were shared from the cache rather than calculated
+	pub fn get_flow_cache_hits(&self) -> u64 {
+		self.flow_cache_hits
+	}
+	/// Get the number of sectors of built routes whose fields had to be
+	/// calculated
+	pub fn get_flow_cache_misses(&self) -> u64 {
+		self.flow_cache_misses
+	}
+	/// Get the number of routes re-requested because a [CostField] they used
+	/// was changed
+	pub fn get_cost_edit_rebuilds(&self) -> u64 {
+		self.cost_edit_rebuilds
+	}
+	/// Get the number of routes which have had their [FlowField]s built
+	pub fn get_routes_built(&self) -> u64 {
+		self.routes_built
+	}
+	/// Get the average number of sectors whose fields were calculated for
+	/// each built route, `0.0` if no routes have been built
+	pub fn get_sectors_built_per_request(&self) -> f64 {
+		if self.routes_built == 0 {
+			0.0
+		} else {
+			self.flow_cache_misses as f64 / self.routes_built as f64
+		}
+	}
+	/// Record a path request which was (or wasn't) already cached
+	pub fn record_route_request(&mut self, is_cached: bool) {
+		if is_cached {
+			self.route_cache_hits += 1;
+		} else {
+			self.route_cache_misses += 1;
+		}
+	}
+	/// Record the sectors of a route whose [FlowField]s are about to be built
+	/// from the `int_builder`, sectors skipped within the stopping distance
+	/// of the goal are neither hits nor misses
+	pub fn record_built_route(&mut self, int_builder: &IntegrationBuilder) {
+		self.routes_built += 1;
+		for i in 0..int_builder.get_integration_fields().len() {
+			if int_builder.is_field_shared(i) {
+				self.flow_cache_hits += 1;
+			} else if !int_builder.is_field_skipped(i) {
+				self.flow_cache_misses += 1;
+			}
+		}
+	}
+	/// Record `count` routes re-requested after a [CostField] change
+	pub fn record_cost_edit_rebuilds(&mut self, count: u64) {
+		self.cost_edit_rebuilds += count;
+	}
+}
+
+/// Counters of the most recent run of the plugin's systems alongside the
+/// cumulative counters since the app started (or [FlowFieldStats::reset]
+/// was called)
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource)]
+pub struct FlowFieldStats {
+	/// Counters of the most recent run of the plugin's systems
+	frame: FlowFieldCounters,
+	/// Counters accumulated across every run
+	total: FlowFieldCounters,
+}
+
+impl FlowFieldStats {
+	/// Get the counters of the most recent run of the plugin's systems
+	pub fn get_frame(&self) -> &FlowFieldCounters {
+		&self.frame
+	}
+	/// Get the cumulative counters
+	pub fn get_total(&self) -> &FlowFieldCounters {
+		&self.total
+	}
+	/// Clear both the frame and cumulative counters
+	pub fn reset(&mut self) {
+		*self = FlowFieldStats::default();
+	}
+	/// Clear the frame counters ready for the next run
+	pub fn begin_frame(&mut self) {
+		self.frame = FlowFieldCounters::default();
+	}
+	/// Apply a change to both the frame and cumulative counters
+	pub fn record(&mut self, mut change: impl FnMut(&mut FlowFieldCounters)) {
+		change(&mut self.frame);
+		change(&mut self.total);
+	}
+}
+
+/// Clear the frame counters of [FlowFieldStats] before the plugin's systems
+/// run
+#[cfg(not(tarpaulin_include))]
+pub fn begin_flow_field_stats_frame(stats: Option<ResMut<FlowFieldStats>>) {
+	if let Some(mut stats) = stats {
+		stats.begin_frame();
+	}
+}
+
+/// Path requests whose route was already cached
+pub const ROUTE_CACHE_HITS: DiagnosticPath =
+	DiagnosticPath::const_new("flowfield/route_cache_hits");
+/// Path requests which needed a new route
+pub const ROUTE_CACHE_MISSES: DiagnosticPath =
+	DiagnosticPath::const_new("flowfield/route_cache_misses");
+/// Sectors whose fields were shared from the cache
+pub const FLOW_CACHE_HITS: DiagnosticPath = DiagnosticPath::const_new("flowfield/flow_cache_hits");
+/// Sectors whose fields had to be calculated
+pub const FLOW_CACHE_MISSES: DiagnosticPath =
+	DiagnosticPath::const_new("flowfield/flow_cache_misses");
+/// Routes re-requested because of a [CostField] change
+pub const COST_EDIT_REBUILDS: DiagnosticPath =
+	DiagnosticPath::const_new("flowfield/cost_edit_rebuilds");
+/// Average number of sectors calculated per built route
+pub const SECTORS_BUILT_PER_REQUEST: DiagnosticPath =
+	DiagnosticPath::const_new("flowfield/sectors_built_per_request");
+
+/// Publishes the counters of [FlowFieldStats] as `bevy_diagnostic`
+/// measurements once per frame (in the [Last] schedule) so they can be
+/// logged with `LogDiagnosticsPlugin` or shown by any diagnostics overlay.
+/// Each measurement is the change since the previous frame, so it covers
+/// every run of the navigation systems when they're placed in [FixedUpdate]
+pub struct FlowFieldDiagnosticsPlugin;
+
+impl Plugin for FlowFieldDiagnosticsPlugin {
+	#[cfg(not(tarpaulin_include))]
+	fn build(&self, app: &mut App) {
+		app.register_diagnostic(Diagnostic::new(ROUTE_CACHE_HITS))
+			.register_diagnostic(Diagnostic::new(ROUTE_CACHE_MISSES))
+			.register_diagnostic(Diagnostic::new(FLOW_CACHE_HITS))
+			.register_diagnostic(Diagnostic::new(FLOW_CACHE_MISSES))
+			.register_diagnostic(Diagnostic::new(COST_EDIT_REBUILDS))
+			.register_diagnostic(Diagnostic::new(SECTORS_BUILT_PER_REQUEST))
+			.init_resource::<FlowFieldStats>()
+			.add_systems(Last, publish_flow_field_diagnostics);
+	}
+}
+
+/// Add the change in the cumulative [FlowFieldStats] counters since the
+/// previous frame as diagnostic measurements
+#[cfg(not(tarpaulin_include))]
+fn publish_flow_field_diagnostics(
+	mut diagnostics: Diagnostics,
+	stats: Res<FlowFieldStats>,
+	mut previous: Local<FlowFieldCounters>,
+) {
+	let total = *stats.get_total();
+	let delta = |now: u64, before: u64| now.saturating_sub(before) as f64;
+	diagnostics.add_measurement(&ROUTE_CACHE_HITS, || {
+		delta(total.route_cache_hits, previous.route_cache_hits)
+	});
+	diagnostics.add_measurement(&ROUTE_CACHE_MISSES, || {
+		delta(total.route_cache_misses, previous.route_cache_misses)
+	});
+	diagnostics.add_measurement(&FLOW_CACHE_HITS, || {
+		delta(total.flow_cache_hits, previous.flow_cache_hits)
+	});
+	diagnostics.add_measurement(&FLOW_CACHE_MISSES, || {
+		delta(total.flow_cache_misses, previous.flow_cache_misses)
+	});
+	diagnostics.add_measurement(&COST_EDIT_REBUILDS, || {
+		delta(total.cost_edit_rebuilds, previous.cost_edit_rebuilds)
+	});
+	let routes = delta(total.routes_built, previous.routes_built);
+	if routes > 0.0 {
+		diagnostics.add_measurement(&SECTORS_BUILT_PER_REQUEST, || {
+			delta(total.flow_cache_misses, previous.flow_cache_misses) / routes
+		});
+	}
+	*previous = total;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn record_frame_and_total() {
+		let mut stats = FlowFieldStats::default();
+		stats.record(|c| c.record_route_request(true));
+		stats.record(|c| c.record_route_request(false));
+		stats.begin_frame();
+		stats.record(|c| c.record_route_request(false));
+		stats.record(|c| c.record_cost_edit_rebuilds(2));
+		assert_eq!(0, stats.get_frame().get_route_cache_hits());
+		assert_eq!(1, stats.get_frame().get_route_cache_misses());
+		assert_eq!(1, stats.get_total().get_route_cache_hits());
+		assert_eq!(2, stats.get_total().get_route_cache_misses());
+		assert_eq!(2, stats.get_total().get_cost_edit_rebuilds());
+		stats.reset();
+		assert_eq!(FlowFieldCounters::default(), *stats.get_total());
+	}
+	#[test]
+	fn record_shared_sectors() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let cost_fields = SectorCostFields::new(&map_dimensions);
+		let route = Route::new(vec![
+			(SectorID::new(0, 0), FieldCell::new(4, 4)),
+			(SectorID::new(1, 0), FieldCell::new(0, 4)),
+			(SectorID::new(2, 0), FieldCell::new(0, 4)),
+		]);
+		let mut int_builder = IntegrationBuilder::new(route, &cost_fields);
+		int_builder.share_field(1, IntegrationField::default());
+		let mut counters = FlowFieldCounters::default();
+		counters.record_built_route(&int_builder);
+		assert_eq!(1, counters.get_flow_cache_hits());
+		assert_eq!(2, counters.get_flow_cache_misses());
+		assert_eq!(2.0, counters.get_sectors_built_per_request());
+	}
+}
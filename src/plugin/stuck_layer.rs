@@ -0,0 +1,471 @@
+//! Detects actors which have stopped making progress along their route,
+//! such as an actor wedged against a corner by a physics simulation or one
+//! following a field which points into a wall. An actor reports the route it
+//! is following and the [FieldCell] it's on to its [StuckDetector] and the
+//! plugin sends an [EventActorStuck], with some context about the cell and
+//! the cached data of the route, once no progress has been made for the
+//! timeout of the detector
+//!
+
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+/// How far along a `route` an actor in `sector_id` on `field_cell` is, as
+/// the number of sectors left to traverse after the current one and the
+/// number of [FieldCell] steps taken following the cached `flow_field` of
+/// the current sector to its portal (or goal). Once the actor has line of
+/// sight to the goal the remaining steps are walked in a straight line.
+/// Smaller values are closer to the goal. Returns [None] if the sector isn't
+/// part of the route or the field doesn't lead the actor out of the sector
+pub fn get_route_progress(
+	route: &Route,
+	flow_field: &FlowField,
+	sector_id: SectorID,
+	field_cell: FieldCell,
+) -> Option<(usize, usize)> {
+	let path = route.get();
+	let index = path.iter().rposition(|(sector, _)| *sector == sector_id)?;
+	let target = path[index].1;
+	let mut current = field_cell;
+	// every cell can only be visited once on the way out of the sector
+	for steps in 0..FIELD_RESOLUTION * FIELD_RESOLUTION {
+		let value = flow_field.get_field_cell_value(current);
+		if is_goal(value) || is_portal_goal(value) {
+			return Some((path.len() - 1 - index, steps));
+		}
+		if has_line_of_sight(value) {
+			let remaining = target
+				.get_column()
+				.abs_diff(current.get_column())
+				.max(target.get_row().abs_diff(current.get_row()));
+			return Some((path.len() - 1 - index, steps + remaining));
+		}
+		match get_ordinal_from_bits(value) {
+			Ordinal::Zero => return None,
+			ordinal => current = Ordinal::get_cell_neighbour(current, ordinal)?,
+		}
+	}
+	None
+}
+
+/// Tracks the progress of an actor along its route, add it to an actor and
+/// call [StuckDetector::report] whenever the actor moves (or every frame) so
+/// that an [EventActorStuck] is sent if the actor stops progressing
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct StuckDetector {
+	/// How long an actor can go without progressing before it's stuck
+	timeout: Duration,
+	/// The bundle entity the route belongs to, if [None] then every bundle
+	/// is searched
+	nav_world: Option<Entity>,
+	/// The route the actor is following along with its current sector and
+	/// [FieldCell]
+	report: Option<(RouteMetadata, SectorID, FieldCell)>,
+	/// The closest the actor has been to the goal, see [get_route_progress]
+	best_progress: Option<(usize, usize)>,
+	/// When the actor last made progress
+	last_progress: Option<Duration>,
+	/// The sector and [FieldCell] of the actor when it was last updated
+	last_cell: Option<(SectorID, FieldCell)>,
+	/// Whether the current stall has already been reported
+	is_stuck: bool,
+}
+
+impl Default for StuckDetector {
+	fn default() -> Self {
+		StuckDetector::new(Duration::from_secs(3))
+	}
+}
+
+impl StuckDetector {
+	/// Create a new instance of [StuckDetector] which considers the actor
+	/// stuck after it has made no progress for `timeout`
+	pub fn new(timeout: Duration) -> Self {
+		StuckDetector {
+			timeout,
+			nav_world: None,
+			report: None,
+			best_progress: None,
+			last_progress: None,
+			last_cell: None,
+			is_stuck: false,
+		}
+	}
+	/// Only look for the route within the bundle of the `nav_world` entity
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	/// Get how long an actor can go without progressing before it's stuck
+	pub fn get_timeout(&self) -> Duration {
+		self.timeout
+	}
+	/// Get the bundle entity the route belongs to
+	pub fn get_nav_world(&self) -> Option<Entity> {
+		self.nav_world
+	}
+	/// Get the route, sector and [FieldCell] last reported
+	pub fn get_report(&self) -> Option<(RouteMetadata, SectorID, FieldCell)> {
+		self.report
+	}
+	/// Whether the actor has been reported as stuck and hasn't since made
+	/// progress
+	pub fn is_stuck(&self) -> bool {
+		self.is_stuck
+	}
+	/// Record that the actor following the route of `route_metadata` is in
+	/// `sector_id` on `field_cell`. Following a different route restarts the
+	/// tracking of progress
+	pub fn report(
+		&mut self,
+		route_metadata: RouteMetadata,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+	) {
+		if self
+			.report
+			.is_some_and(|(metadata, _, _)| metadata != route_metadata)
+		{
+			self.reset();
+		}
+		self.report = Some((route_metadata, sector_id, field_cell));
+	}
+	/// Stop tracking the actor, such as when it has arrived at its goal
+	pub fn reset(&mut self) {
+		self.report = None;
+		self.best_progress = None;
+		self.last_progress = None;
+		self.last_cell = None;
+		self.is_stuck = false;
+	}
+	/// Update the detector with the `progress` of the actor at the `elapsed`
+	/// time. When the `progress` is [None] (off the route or no [FlowField]
+	/// is cached to measure it by) only moving onto another [FieldCell] since
+	/// the last update counts as progressing. Returns how long the actor has
+	/// stalled for when it first becomes stuck
+	pub fn update(
+		&mut self,
+		progress: Option<(usize, usize)>,
+		elapsed: Duration,
+	) -> Option<Duration> {
+		let cell = self
+			.report
+			.map(|(_, sector_id, field_cell)| (sector_id, field_cell));
+		let last_cell = std::mem::replace(&mut self.last_cell, cell);
+		let Some(last_progress) = self.last_progress else {
+			// the first update starts the clock
+			self.best_progress = progress;
+			self.last_progress = Some(elapsed);
+			return None;
+		};
+		let is_progressing = match (progress, self.best_progress) {
+			(Some(current), Some(best)) => current < best,
+			(Some(_), None) => true,
+			(None, _) => cell.is_some() && cell != last_cell,
+		};
+		if is_progressing {
+			self.best_progress = progress;
+			self.last_progress = Some(elapsed);
+			self.is_stuck = false;
+			return None;
+		}
+		let stalled = elapsed.saturating_sub(last_progress);
+		if !self.is_stuck && stalled >= self.timeout {
+			self.is_stuck = true;
+			return Some(stalled);
+		}
+		None
+	}
+}
+
+/// Context describing where a stuck actor is, to help decide how to recover
+/// it and to attach to bug reports
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct StuckDiagnostics {
+	/// The scaled cost of the [FieldCell] the actor is on, [None] if the
+	/// sector doesn't exist
+	cell_cost: Option<u8>,
+	/// The value of the [FlowField] at the [FieldCell] the actor is on,
+	/// [None] if no field is cached for the sector
+	flow_value: Option<u8>,
+	/// Whether the route is in the [RouteCache]
+	is_route_cached: bool,
+	/// Whether the sector of the actor is part of the route
+	is_on_route: bool,
+}
+
+impl StuckDiagnostics {
+	/// Get the scaled cost of the [FieldCell] the actor is on
+	pub fn get_cell_cost(&self) -> Option<u8> {
+		self.cell_cost
+	}
+	/// Get the value of the [FlowField] at the [FieldCell] the actor is on
+	pub fn get_flow_value(&self) -> Option<u8> {
+		self.flow_value
+	}
+	/// Whether a [FlowField] is cached for the sector the actor is in
+	pub fn is_field_cached(&self) -> bool {
+		self.flow_value.is_some()
+	}
+	/// Whether the route is in the [RouteCache]
+	pub fn is_route_cached(&self) -> bool {
+		self.is_route_cached
+	}
+	/// Whether the sector of the actor is part of the route
+	pub fn is_on_route(&self) -> bool {
+		self.is_on_route
+	}
+}
+
+/// Sent when an actor with a [StuckDetector] has made no progress along its
+/// route for the timeout of the detector. It's sent once per stall, after
+/// the actor progresses again it can be sent again
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EventActorStuck {
+	/// The stuck actor
+	actor: Entity,
+	/// The bundle entity the route was looked up in
+	nav_world: Entity,
+	/// The route the actor is following
+	route_metadata: RouteMetadata,
+	/// The sector the actor is in
+	sector_id: SectorID,
+	/// The [FieldCell] the actor is on
+	field_cell: FieldCell,
+	/// How long the actor has gone without progressing
+	stalled_for: Duration,
+	/// Context about the cell and the cached data of the route
+	diagnostics: StuckDiagnostics,
+}
+
+impl EventActorStuck {
+	/// Get the stuck actor
+	pub fn get_actor(&self) -> Entity {
+		self.actor
+	}
+	/// Get the bundle entity the route was looked up in
+	pub fn get_nav_world(&self) -> Entity {
+		self.nav_world
+	}
+	/// Get the route the actor is following
+	pub fn get_route_metadata(&self) -> RouteMetadata {
+		self.route_metadata
+	}
+	/// Get the sector the actor is in
+	pub fn get_sector(&self) -> SectorID {
+		self.sector_id
+	}
+	/// Get the [FieldCell] the actor is on
+	pub fn get_field_cell(&self) -> FieldCell {
+		self.field_cell
+	}
+	/// Get how long the actor has gone without progressing
+	pub fn get_stalled_for(&self) -> Duration {
+		self.stalled_for
+	}
+	/// Get the context about the cell and the cached data of the route
+	pub fn get_diagnostics(&self) -> StuckDiagnostics {
+		self.diagnostics
+	}
+}
+
+/// Update the [StuckDetector] of each actor and send an [EventActorStuck]
+/// for those which have stopped progressing along their route
+#[cfg(not(tarpaulin_include))]
+pub fn detect_stuck_actors(
+	mut actors: Query<(Entity, &mut StuckDetector)>,
+	bundles: Query<(Entity, &RouteCache, &FlowFieldCache, &SectorCostFields)>,
+	time: Res<Time>,
+	state: Option<Res<FlowFieldSystemState>>,
+	mut event_stuck: EventWriter<EventActorStuck>,
+) {
+	// actors move in time even when playing turn-based so stalls are timed
+	// rather than counted in turns
+	let elapsed = state.map_or(time.elapsed(), |state| {
+		state.get_unpaused_elapsed(time.elapsed())
+	});
+	for (actor, mut detector) in actors.iter_mut() {
+		let Some((metadata, sector_id, field_cell)) = detector.get_report() else {
+			continue;
+		};
+		let nav_world = detector.get_nav_world();
+		let is_searched = |entity: &Entity| nav_world.is_none_or(|nav| nav == *entity);
+		// prefer the bundle which holds the route
+		let Some((nav_world, route_cache, flow_cache, sector_cost_fields)) = bundles
+			.iter()
+			.filter(|(entity, ..)| is_searched(entity))
+			.find(|(_, route_cache, ..)| route_cache.contains_route(&metadata))
+			.or_else(|| bundles.iter().find(|(entity, ..)| is_searched(entity)))
+		else {
+			continue;
+		};
+		let route = route_cache
+			.get_route_from_metadata(&metadata)
+			.map(|(_, route)| route);
+		let route_goal = route.as_ref().and_then(|route| {
+			route
+				.get()
+				.iter()
+				.rev()
+				.find(|(sector, _)| *sector == sector_id)
+				.map(|(_, goal)| *goal)
+		});
+		let flow_field = route_goal.and_then(|goal| match metadata.get_faction() {
+			Some(faction) => {
+				flow_cache.get_faction_field(sector_id, metadata.get_target_sector(), goal, faction)
+			}
+			None => flow_cache.get_field(sector_id, metadata.get_target_sector(), goal),
+		});
+		let progress = route
+			.as_ref()
+			.zip(flow_field)
+			.and_then(|(route, field)| get_route_progress(route, field, sector_id, field_cell));
+		let Some(stalled_for) = detector.update(progress, elapsed) else {
+			continue;
+		};
+		let flow_value = flow_field.map(|field| field.get_field_cell_value(field_cell));
+		let cell_cost = sector_cost_fields
+			.get_scaled()
+			.get(&sector_id)
+			.map(|cost_field| cost_field.get_field_cell_value(field_cell));
+		let diagnostics = StuckDiagnostics {
+			cell_cost,
+			flow_value,
+			is_route_cached: route.is_some(),
+			is_on_route: route_goal.is_some(),
+		};
+		event_stuck.send(EventActorStuck {
+			actor,
+			nav_world,
+			route_metadata: metadata,
+			sector_id,
+			field_cell,
+			stalled_for,
+			diagnostics,
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn progress_along_route() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let metadata = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(2, 4),
+			SectorID::new(2, 0),
+			FieldCell::new(4, 4),
+			Duration::ZERO,
+		);
+		bundle.prewarm(&[metadata]);
+		let (_, route) = bundle
+			.get_route_cache()
+			.get_route_from_metadata(&metadata)
+			.unwrap();
+		let field_of = |sector_id: SectorID| {
+			let (_, goal) = route
+				.get()
+				.iter()
+				.find(|(sector, _)| *sector == sector_id)
+				.unwrap();
+			bundle
+				.get_flowfield_cache()
+				.get_field(sector_id, SectorID::new(2, 0), *goal)
+				.unwrap()
+		};
+		let progress = |sector_id: SectorID, field_cell: FieldCell| {
+			get_route_progress(&route, field_of(sector_id), sector_id, field_cell)
+		};
+		// steps are walked along the field to the portal on the eastern side
+		let start = progress(SectorID::new(0, 0), FieldCell::new(2, 4));
+		assert_eq!(Some((2, 7)), start);
+		let closer = progress(SectorID::new(0, 0), FieldCell::new(6, 5));
+		assert!(closer.is_some_and(|(sectors, _)| sectors == 2));
+		let next = progress(SectorID::new(1, 0), FieldCell::new(0, 4));
+		assert!(next < closer && closer < start);
+		// within sight of the goal the rest is a straight line
+		let goal_sector = progress(SectorID::new(2, 0), FieldCell::new(0, 4));
+		assert_eq!(Some((0, 4)), goal_sector);
+		assert_eq!(
+			None,
+			get_route_progress(
+				&route,
+				field_of(SectorID::new(0, 0)),
+				SectorID::new(0, 1),
+				FieldCell::new(0, 0)
+			)
+		);
+	}
+	#[test]
+	fn stuck_after_timeout_once_per_stall() {
+		let mut detector = StuckDetector::new(Duration::from_secs(2));
+		assert_eq!(None, detector.update(Some((2, 7)), Duration::ZERO));
+		assert_eq!(None, detector.update(Some((2, 5)), Duration::from_secs(1)));
+		// moving back and forth isn't progress
+		assert_eq!(None, detector.update(Some((2, 6)), Duration::from_secs(2)));
+		assert_eq!(
+			Some(Duration::from_secs(2)),
+			detector.update(Some((2, 5)), Duration::from_secs(3))
+		);
+		assert!(detector.is_stuck());
+		// only reported once
+		assert_eq!(None, detector.update(None, Duration::from_secs(4)));
+		// progress clears the stall
+		assert_eq!(None, detector.update(Some((1, 9)), Duration::from_secs(5)));
+		assert!(!detector.is_stuck());
+		assert_eq!(
+			Some(Duration::from_secs(2)),
+			detector.update(None, Duration::from_secs(7))
+		);
+	}
+	#[test]
+	fn new_route_restarts_tracking() {
+		let mut detector = StuckDetector::default();
+		let route_a = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(0, 0),
+			SectorID::new(2, 0),
+			FieldCell::new(4, 4),
+			Duration::ZERO,
+		);
+		let route_b = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(0, 0),
+			SectorID::new(0, 2),
+			FieldCell::new(4, 4),
+			Duration::ZERO,
+		);
+		detector.report(route_a, SectorID::new(0, 0), FieldCell::new(0, 0));
+		detector.update(Some((2, 9)), Duration::ZERO);
+		detector.update(None, Duration::from_secs(10));
+		assert!(detector.is_stuck());
+		detector.report(route_b, SectorID::new(0, 0), FieldCell::new(0, 0));
+		assert!(!detector.is_stuck());
+		assert_eq!(None, detector.update(None, Duration::from_secs(11)));
+	}
+	#[test]
+	fn moving_between_cells_without_field() {
+		let mut detector = StuckDetector::new(Duration::from_secs(3));
+		let metadata = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(0, 0),
+			SectorID::new(2, 0),
+			FieldCell::new(4, 4),
+			Duration::ZERO,
+		);
+		detector.report(metadata, SectorID::new(0, 0), FieldCell::new(0, 0));
+		assert_eq!(None, detector.update(None, Duration::ZERO));
+		// with no field to measure progress by moving cell counts
+		detector.report(metadata, SectorID::new(0, 0), FieldCell::new(1, 0));
+		assert_eq!(None, detector.update(None, Duration::from_secs(10)));
+		assert!(!detector.is_stuck());
+		assert_eq!(
+			Some(Duration::from_secs(10)),
+			detector.update(None, Duration::from_secs(20))
+		);
+	}
+}
@@ -0,0 +1,153 @@
+//! Turn-based games build their [FlowField]s on demand during a turn and
+//! expect them to stay cached until the game decides otherwise. While the
+//! [FlowFieldTurn] resource exists (see [FlowFieldTilesPlugin::turn_based])
+//! cached routes and fields never expire and the metadata of everything the
+//! plugin generates is stamped with the turn supplied by the game (a
+//! [Stamp::Turn]) rather than the time elapsed, so the same inputs produce
+//! the same caches however long a turn takes. Call [clear_all_caches]
+//! between turns to start afresh
+//!
+//! A turn has no duration so queued requests are not aged. Elsewhere a
+//! waiting request is promoted one [PathPriority] level every
+//! [PRIORITY_AGING_INTERVAL], when playing turn-based both the order in which
+//! [EventPathRequest]s are taken (the earliest request winning a tie) and
+//! the order in which the [FlowFieldQueue] builds routes (see
+//! [FlowFieldQueue::get_next_queued]) are strictly by priority. A lower
+//! priority request therefore waits until no higher priority work is
+//! queued, however many turns that takes
+//!
+
+use bevy::prelude::*;
+
+use crate::prelude::*;
+
+/// The current turn of a turn-based game, advanced by the game itself
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct FlowFieldTurn {
+	/// The turn being played
+	turn: u64,
+}
+
+impl FlowFieldTurn {
+	/// Create a new instance of [FlowFieldTurn] starting at `turn`
+	pub fn new(turn: u64) -> Self {
+		FlowFieldTurn { turn }
+	}
+	/// Get the turn being played
+	pub fn get(&self) -> u64 {
+		self.turn
+	}
+	/// Set the turn being played
+	pub fn set(&mut self, turn: u64) {
+		self.turn = turn;
+	}
+	/// Move on to the next turn
+	pub fn advance(&mut self) {
+		self.turn += 1;
+	}
+	/// Get the turn as the [Stamp] of metadata. Use it to create a
+	/// [RouteMetadata] for [FlowFieldTilesBundle::prewarm] so that fields
+	/// built synchronously match the fields built by the plugin
+	pub fn as_stamp(&self) -> Stamp {
+		Stamp::Turn(self.turn)
+	}
+}
+
+impl From<FlowFieldTurn> for Stamp {
+	fn from(turn: FlowFieldTurn) -> Self {
+		turn.as_stamp()
+	}
+}
+
+/// Get the [Stamp] used to stamp and age metadata, the [FlowFieldTurn]
+/// when playing turn-based or otherwise the time elapsed since app start
+/// less any time the [FlowFieldSystemState] spent paused
+pub fn get_navigation_elapsed(
+	time: &Time,
+	turn: Option<&FlowFieldTurn>,
+	state: Option<&FlowFieldSystemState>,
+) -> Stamp {
+	match turn {
+		Some(turn) => turn.as_stamp(),
+		None => Stamp::Time(state.map_or(time.elapsed(), |state| {
+			state.get_unpaused_elapsed(time.elapsed())
+		})),
+	}
+}
+
+/// Run condition of the systems which expire cached entries, they're
+/// skipped while playing turn-based
+pub fn is_cache_expiry_enabled(turn: Option<Res<FlowFieldTurn>>) -> bool {
+	turn.is_none()
+}
+
+/// Remove every route, [FlowField] and flee field from the caches and
+/// queues of all bundles. Run it as a system or between turns with
+/// `commands.queue(clear_all_caches)`
+pub fn clear_all_caches(world: &mut World) {
+	let mut query = world.query::<(
+		Option<&mut RouteQueue>,
+		Option<&mut RouteCache>,
+		Option<&mut FlowFieldQueue>,
+		Option<&mut FlowFieldCache>,
+		Option<&mut FleeFieldCache>,
+	)>();
+	for (route_queue, route_cache, flow_queue, flow_cache, flee_cache) in query.iter_mut(world) {
+		if let Some(mut route_queue) = route_queue {
+			route_queue.clear();
+		}
+		if let Some(mut flow_queue) = flow_queue {
+			flow_queue.clear();
+		}
+		if let Some(mut route_cache) = route_cache {
+			route_cache.clear();
+		}
+		if let Some(mut flow_cache) = flow_cache {
+			flow_cache.clear();
+		}
+		if let Some(mut flee_cache) = flee_cache {
+			flee_cache.clear();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::utils::Duration;
+
+	#[test]
+	fn turn_stamps_metadata() {
+		let mut turn = FlowFieldTurn::new(3);
+		turn.advance();
+		let time = Time::<()>::default();
+		let elapsed = get_navigation_elapsed(&time, Some(&turn), None);
+		assert_eq!(Some(4), elapsed.get_turn());
+		let start = get_navigation_elapsed(&time, None, None);
+		assert_eq!(Stamp::Time(Duration::ZERO), start);
+		// turns have no duration so they neither age nor expire
+		assert_eq!(None, elapsed.time_since(start));
+		assert_eq!(None, elapsed.time_since(FlowFieldTurn::new(1).as_stamp()));
+	}
+	#[test]
+	fn clear_caches_of_bundles() {
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let metadata = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(4, 4),
+			SectorID::new(2, 2),
+			FieldCell::new(4, 4),
+			FlowFieldTurn::new(1),
+		);
+		bundle.prewarm(&[metadata]);
+		assert!(bundle.ready(&metadata));
+		let mut world = World::new();
+		let entity = world.spawn(bundle).id();
+		clear_all_caches(&mut world);
+		let route_cache = world.get::<RouteCache>(entity).unwrap();
+		assert!(route_cache.is_empty());
+		let flow_cache = world.get::<FlowFieldCache>(entity).unwrap();
+		assert!(flow_cache.get().is_empty());
+	}
+}
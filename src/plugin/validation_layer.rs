@@ -0,0 +1,43 @@
+//! With the `validation` feature each bundle whose navigation data has
+//! changed is checked with [validate_navigation] and any broken invariant is
+//! logged
+//!
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Check the [SectorCostFields], [SectorPortals] and [PortalGraph] of each
+/// bundle that has changed since the last run and log every violation found
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::type_complexity)]
+pub fn validate_changed_bundles(
+	bundles: Query<(
+		Entity,
+		Ref<SectorCostFields>,
+		Ref<SectorPortals>,
+		Ref<PortalGraph>,
+		&MapDimensions,
+	)>,
+) {
+	for (entity, sector_cost_fields, sector_portals, portal_graph, map_dimensions) in bundles.iter()
+	{
+		if !sector_cost_fields.is_changed()
+			&& !sector_portals.is_changed()
+			&& !portal_graph.is_changed()
+		{
+			continue;
+		}
+		let violations = validate_navigation(
+			&sector_cost_fields,
+			&sector_portals,
+			&portal_graph,
+			map_dimensions,
+		);
+		for violation in violations.iter() {
+			error!(
+				"Navigation data of bundle {:?} is invalid: {}",
+				entity, violation
+			);
+		}
+	}
+}
@@ -0,0 +1,97 @@
+//! As a player explores the map new sectors become available to the route
+//! searches of their units. Revealing a sector with an [EventRevealSector]
+//! updates the [SectorVisibility] of a bundle and invalidates the cached
+//! routes of the player which pass beside the sector, so that replacements
+//! may take a shorter path through it
+//!
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Mark a sector as explored by a player, see [SectorVisibility::reveal]
+#[derive(Event, Clone, Copy)]
+pub struct EventRevealSector {
+	/// The player exploring the sector
+	player: PlayerId,
+	/// The sector that has been explored
+	sector_id: SectorID,
+	/// The bundle entity to update, if [None] then every bundle is updated
+	nav_world: Option<Entity>,
+}
+
+impl EventRevealSector {
+	/// Create a new instance of [EventRevealSector]
+	pub fn new(player: PlayerId, sector_id: SectorID) -> Self {
+		EventRevealSector {
+			player,
+			sector_id,
+			nav_world: None,
+		}
+	}
+	/// Only update the bundle `nav_world`
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	/// Get the player exploring the sector
+	pub fn get_player(&self) -> PlayerId {
+		self.player
+	}
+	/// Get the sector that has been explored
+	pub fn get_sector_id(&self) -> SectorID {
+		self.sector_id
+	}
+	/// Get the bundle entity to update
+	pub fn get_nav_world(&self) -> Option<Entity> {
+		self.nav_world
+	}
+}
+
+/// Apply [EventRevealSector]s to the [SectorVisibility] of each bundle. When
+/// a sector is newly explored the cached routes of the player which enter a
+/// neighbour of it are removed, announced with an [EventRouteInvalidated]
+/// and requested again
+#[cfg(not(tarpaulin_include))]
+pub fn process_sector_reveals(
+	mut events: EventReader<EventRevealSector>,
+	mut query: Query<(
+		Entity,
+		&mut SectorVisibility,
+		&mut RouteCache,
+		&MapDimensions,
+	)>,
+	mut event_path_request: EventWriter<EventPathRequest>,
+	mut event_route_invalidated: EventWriter<EventRouteInvalidated>,
+) {
+	for event in events.read() {
+		for (entity, mut visibility, mut route_cache, map_dimensions) in query.iter_mut() {
+			if event.nav_world.is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			if !visibility.reveal(event.player, event.sector_id) {
+				continue;
+			}
+			// only a route beside the sector could be shortened by crossing it
+			let mut sectors = map_dimensions.get_ids_of_neighbouring_sectors(&event.sector_id);
+			sectors.push(event.sector_id);
+			let mut to_purge = Vec::new();
+			for sector_id in sectors {
+				for metadata in route_cache.get_routes_using_sector(sector_id) {
+					if metadata.get_player() == Some(event.player) && !to_purge.contains(&metadata)
+					{
+						to_purge.push(metadata);
+					}
+				}
+			}
+			for metadata in to_purge.iter() {
+				route_cache.remove_route(*metadata);
+				invalidate_route(
+					metadata,
+					entity,
+					&mut event_route_invalidated,
+					&mut event_path_request,
+				);
+			}
+		}
+	}
+}
@@ -0,0 +1,117 @@
+//! Restricted zones are opened and closed at runtime, such as the gates of a
+//! base opening. Toggling a zone with an [EventToggleZone] updates the
+//! [FactionZones] of a bundle and invalidates only the cached routes and
+//! [FlowField]s of the zone's faction which the change affects
+//!
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Enable or disable a restricted zone, see [FactionZones::set_zone_enabled]
+#[derive(Event, Clone, Copy)]
+pub struct EventToggleZone {
+	/// The zone to toggle
+	zone_id: ZoneId,
+	/// Whether the zone should restrict its faction
+	enabled: bool,
+	/// The bundle entity to update, if [None] then every bundle is updated
+	nav_world: Option<Entity>,
+}
+
+impl EventToggleZone {
+	/// Create a new instance of [EventToggleZone]
+	pub fn new(zone_id: ZoneId, enabled: bool) -> Self {
+		EventToggleZone {
+			zone_id,
+			enabled,
+			nav_world: None,
+		}
+	}
+	/// Only update the bundle `nav_world`
+	pub fn with_nav_world(mut self, nav_world: Entity) -> Self {
+		self.nav_world = Some(nav_world);
+		self
+	}
+	/// Get the zone to toggle
+	pub fn get_zone_id(&self) -> ZoneId {
+		self.zone_id
+	}
+	/// Get whether the zone should restrict its faction
+	pub fn is_enabled(&self) -> bool {
+		self.enabled
+	}
+	/// Get the bundle entity to update
+	pub fn get_nav_world(&self) -> Option<Entity> {
+		self.nav_world
+	}
+}
+
+/// Apply [EventToggleZone]s to the [FactionZones] of each bundle. The
+/// [FlowField]s of the zone's faction within the sectors of the zone are
+/// removed. Its cached routes which enter those sectors (or, when the zone
+/// opens, a neighbour of them which may now take a shorter path) are
+/// removed, announced with an [EventRouteInvalidated] and requested again.
+/// Routes of other factions are untouched
+#[cfg(not(tarpaulin_include))]
+pub fn process_zone_toggles(
+	mut events: EventReader<EventToggleZone>,
+	mut query: Query<(
+		Entity,
+		&mut FactionZones,
+		&mut RouteCache,
+		&mut FlowFieldCache,
+		&MapDimensions,
+	)>,
+	mut event_path_request: EventWriter<EventPathRequest>,
+	mut event_route_invalidated: EventWriter<EventRouteInvalidated>,
+) {
+	for event in events.read() {
+		for (entity, mut zones, mut route_cache, mut flow_cache, map_dimensions) in query.iter_mut()
+		{
+			if event.nav_world.is_some_and(|nav| nav != entity) {
+				continue;
+			}
+			let Some(faction) = zones.get_zone_faction(event.zone_id) else {
+				continue;
+			};
+			if !zones.set_zone_enabled(event.zone_id, event.enabled) {
+				continue;
+			}
+			let zone_sectors = zones.get_zone_sectors(event.zone_id);
+			for sector_id in zone_sectors.iter() {
+				for flow_meta in flow_cache.get_sector_field_metadata(*sector_id) {
+					if flow_meta.get_faction() == Some(faction) {
+						flow_cache.remove_field(flow_meta);
+					}
+				}
+			}
+			let mut sectors = zone_sectors.clone();
+			if !event.enabled {
+				for sector_id in zone_sectors.iter() {
+					for neighbour in map_dimensions.get_ids_of_neighbouring_sectors(sector_id) {
+						if !sectors.contains(&neighbour) {
+							sectors.push(neighbour);
+						}
+					}
+				}
+			}
+			let mut to_purge = Vec::new();
+			for sector_id in sectors {
+				for metadata in route_cache.get_routes_using_sector(sector_id) {
+					if metadata.get_faction() == Some(faction) && !to_purge.contains(&metadata) {
+						to_purge.push(metadata);
+					}
+				}
+			}
+			for metadata in to_purge.iter() {
+				route_cache.remove_route(*metadata);
+				invalidate_route(
+					metadata,
+					entity,
+					&mut event_route_invalidated,
+					&mut event_path_request,
+				);
+			}
+		}
+	}
+}
@@ -3,16 +3,43 @@
 
 #[doc(hidden)]
 pub use crate::flowfields::{
-	fields::{cost_field::*, flow_field::*, integration_field::*, *},
+	fields::{
+		cost_field::*, flow_field::*, flow_flags::*, height_field::*, integration_field::*,
+		one_way_field::*, route_spline::*, *,
+	},
+	portal::cost_model::*,
 	portal::portal_graph::*,
+	portal::portal_svg::*,
 	portal::portals::*,
-	sectors::{sector_cost::*, sector_portals::*, *},
-	utilities::*,
+	sectors::{
+		sector_cost::*, sector_grid::*, sector_navmesh::*, sector_portals::*, sector_visibility::*,
+		sector_zones::*, *,
+	},
+	utilities::{geometry::*, memory::*, *},
+	validation::*,
 	*,
 };
 
+#[doc(hidden)]
+#[cfg(feature = "serde")]
+pub use crate::flowfields::portal::portal_data::*;
+
+#[doc(hidden)]
+#[cfg(feature = "metafield")]
+pub use crate::flowfields::fields::meta_field::*;
+
+#[doc(hidden)]
+#[cfg(feature = "tiled")]
+pub use crate::flowfields::sectors::tiled::*;
+
 #[doc(hidden)]
 pub use crate::{
 	bundle::*,
-	plugin::{cost_layer::*, flow_layer::*, *},
+	plugin::{
+		arrival_layer::*, build_layer::*, condition_layer::*, config_layer::*, cost_layer::*,
+		debug_layer::*, flee_layer::*, flow_layer::*, goal_layer::*, modifier_layer::*,
+		patrol_layer::*, reach_layer::*, repath_layer::*, snapshot_layer::*, spawn_layer::*,
+		state_layer::*, stats_layer::*, stuck_layer::*, turn_layer::*, visibility_layer::*,
+		zone_layer::*, *,
+	},
 };
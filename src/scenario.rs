@@ -0,0 +1,478 @@
+//! End-to-end scenarios which run the whole navigation pipeline of the
+//! [FlowFieldTilesPlugin] headless within a minimal [App]. A cost map is
+//! loaded from rows of characters, a path request is sent from the source to
+//! the goal and a simulated actor follows the [FlowField]s one [FieldCell]
+//! per frame. A scenario passes when the actor arrives at the goal within a
+//! number of frames and for no more than a cost bound, so a regression in
+//! any layer (portals, routes, fields or the systems driving them) shows up
+//! as a change in behaviour.
+//!
+//! The crate's own tests run the canonical [Scenario::maze],
+//! [Scenario::island] and [Scenario::chokepoint] maps, games can enable the
+//! `scenario` feature to run the same harness against maps of their own
+//!
+
+use bevy::prelude::*;
+
+use crate::prelude::*;
+
+/// The size of the actor within the worlds of a [Scenario]
+const SCENARIO_ACTOR_SIZE: f32 = 0.5;
+
+/// A cost map alongside a path request whose simulated actor should arrive
+/// at the goal
+#[derive(Clone)]
+pub struct Scenario {
+	/// Name of the scenario, used in failure messages
+	name: String,
+	/// Size of the world
+	map_dimensions: MapDimensions,
+	/// Costs of the world
+	sector_cost_fields: SectorCostFields,
+	/// Where the actor starts
+	source: (SectorID, FieldCell),
+	/// Where the actor should arrive
+	goal: (SectorID, FieldCell),
+	/// The most frames the actor may take to arrive, including the frames
+	/// spent building the route and fields
+	max_frames: u32,
+	/// The most the actor may spend to arrive, the sum of the cost of each
+	/// [FieldCell] it steps into
+	max_cost: i32,
+}
+
+impl Scenario {
+	/// Create a [Scenario] from `rows` of characters, each character being a
+	/// [FieldCell] of the world:
+	///
+	/// * `.` - a cell of cost `1`
+	/// * `#` - an impassable cell
+	/// * `1` to `9` - a cell of that cost
+	/// * `S` - the source of the actor, a cell of cost `1`
+	/// * `G` - the goal of the actor, a cell of cost `1`
+	///
+	/// The number of rows and the length of each must be a multiple of
+	/// [FIELD_RESOLUTION] and each [FieldCell] is one unit across. By default
+	/// the actor may take as many frames and spend as much as there are
+	/// cells in the world.
+	///
+	/// Panics if the rows are ragged, a character is unknown or the map
+	/// doesn't have exactly one source and one goal
+	pub fn from_rows(name: &str, rows: &[&str]) -> Self {
+		let depth = rows.len();
+		let length = rows.first().map_or(0, |row| row.len());
+		assert!(
+			length % FIELD_RESOLUTION == 0 && depth % FIELD_RESOLUTION == 0 && length > 0,
+			"Scenario {} must be a multiple of {} cells in each direction",
+			name,
+			FIELD_RESOLUTION
+		);
+		let map_dimensions = MapDimensions::new(
+			length as u32,
+			depth as u32,
+			FIELD_RESOLUTION as u32,
+			SCENARIO_ACTOR_SIZE,
+		);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut source = None;
+		let mut goal = None;
+		for (row, characters) in rows.iter().enumerate() {
+			assert_eq!(
+				length,
+				characters.len(),
+				"Row {} of scenario {} has a different length",
+				row,
+				name
+			);
+			for (column, character) in characters.chars().enumerate() {
+				let cell = map_dimensions
+					.sector_and_cell_from_global(column as u32, row as u32)
+					.unwrap();
+				let cost = match character {
+					'.' => 1,
+					'#' => 255,
+					'1'..='9' => character.to_digit(10).unwrap() as u8,
+					'S' => {
+						assert!(source.is_none(), "Scenario {} has two sources", name);
+						source = Some(cell);
+						1
+					}
+					'G' => {
+						assert!(goal.is_none(), "Scenario {} has two goals", name);
+						goal = Some(cell);
+						1
+					}
+					_ => panic!("Unknown cell {:?} in scenario {}", character, name),
+				};
+				if cost != 1 {
+					sector_cost_fields.set_field_cell_value(cell.0, cost, cell.1, &map_dimensions);
+				}
+			}
+		}
+		let (Some(source), Some(goal)) = (source, goal) else {
+			panic!("Scenario {} needs a source and a goal", name);
+		};
+		let cells = (length * depth) as u32;
+		Scenario {
+			name: name.to_string(),
+			map_dimensions,
+			sector_cost_fields,
+			source,
+			goal,
+			max_frames: cells,
+			max_cost: cells as i32,
+		}
+	}
+	/// The actor may take no more than `max_frames` to arrive
+	pub fn with_max_frames(mut self, max_frames: u32) -> Self {
+		self.max_frames = max_frames;
+		self
+	}
+	/// The actor may spend no more than `max_cost` to arrive
+	pub fn with_max_cost(mut self, max_cost: i32) -> Self {
+		self.max_cost = max_cost;
+		self
+	}
+	/// Get the name of the scenario
+	pub fn get_name(&self) -> &str {
+		&self.name
+	}
+	/// Get the size of the world
+	pub fn get_map_dimensions(&self) -> &MapDimensions {
+		&self.map_dimensions
+	}
+	/// Get the costs of the world
+	pub fn get_sector_cost_fields(&self) -> &SectorCostFields {
+		&self.sector_cost_fields
+	}
+	/// Get where the actor starts
+	pub fn get_source(&self) -> (SectorID, FieldCell) {
+		self.source
+	}
+	/// Get where the actor should arrive
+	pub fn get_goal(&self) -> (SectorID, FieldCell) {
+		self.goal
+	}
+	/// Get the most frames the actor may take to arrive
+	pub fn get_max_frames(&self) -> u32 {
+		self.max_frames
+	}
+	/// Get the most the actor may spend to arrive
+	pub fn get_max_cost(&self) -> i32 {
+		self.max_cost
+	}
+	/// Corridors which wind back and forth across every row of sectors
+	pub fn maze() -> Self {
+		Scenario::from_rows(
+			"maze",
+			&[
+				"..............................",
+				".S............................",
+				"........#.....................",
+				"........#.....................",
+				"..............................",
+				"###########################...",
+				"..............................",
+				"..............................",
+				"....................#.........",
+				"....................#.........",
+				"..............................",
+				"...###########################",
+				"..............................",
+				"..............................",
+				"............#.................",
+				"............#.................",
+				"..............................",
+				"###########################...",
+				"..............................",
+				"..............................",
+				"......#.......................",
+				"......#.......................",
+				"..............................",
+				"...###########################",
+				"..............................",
+				"..............................",
+				"..............................",
+				"..............................",
+				".G............................",
+				"..............................",
+			],
+		)
+		.with_max_frames(140)
+		.with_max_cost(130)
+	}
+	/// An island surrounded by water whose only way off is a costly bridge
+	/// over a sector boundary
+	pub fn island() -> Self {
+		Scenario::from_rows(
+			"island",
+			&[
+				"..............................",
+				"..............................",
+				"..............................",
+				"..............................",
+				"..............................",
+				"..............................",
+				"..............................",
+				"..............................",
+				"..............................",
+				".........############.........",
+				".........############.........",
+				".........##........##.........",
+				".........##.S......##.........",
+				".........##........##.........",
+				".........##........33.........",
+				".........##........##.........",
+				".........##........##.........",
+				".........##........##.........",
+				".........##........##.........",
+				".........############.........",
+				".........############.........",
+				"..............................",
+				"..............................",
+				"..............................",
+				"..............................",
+				"..............................",
+				"..............................",
+				"..G...........................",
+				"..............................",
+				"..............................",
+			],
+		)
+		.with_max_frames(50)
+		.with_max_cost(45)
+	}
+	/// A wall dividing the world with a single gap, approached through mud
+	/// which is cheaper to walk around
+	pub fn chokepoint() -> Self {
+		Scenario::from_rows(
+			"chokepoint",
+			&[
+				"...............#..............",
+				"...............#..............",
+				"...............#...........G..",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"...........5555#..............",
+				"...........5555#..............",
+				"...........5555#..............",
+				"...........5555#..............",
+				"..............................",
+				"...........5555#..............",
+				"...........5555#..............",
+				"...........5555#..............",
+				"...........5555#..............",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"...............#..............",
+				"..S............#..............",
+				"...............#..............",
+				"...............#..............",
+			],
+		)
+		.with_max_frames(50)
+		.with_max_cost(45)
+	}
+	/// Get every canonical scenario shipped with the crate
+	pub fn canonical() -> Vec<Scenario> {
+		vec![Scenario::maze(), Scenario::island(), Scenario::chokepoint()]
+	}
+	/// Run the scenario, a minimal [App] with the [FlowFieldTilesPlugin] is
+	/// stepped one frame at a time. Once the fields of the route exist the
+	/// actor moves one [FieldCell] each frame in the direction of the
+	/// [FlowField] of its cell, or straight towards the goal from a cell with
+	/// Line Of Sight
+	pub fn run(&self) -> ScenarioOutcome {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin::default()));
+		let bundle = FlowFieldTilesBundle::from_sector_cost_fields(
+			self.map_dimensions,
+			self.sector_cost_fields.clone(),
+		);
+		let nav_world = app.world_mut().spawn(bundle).id();
+		app.world_mut().send_event(EventPathRequest::new(
+			self.source.0,
+			self.source.1,
+			self.goal.0,
+			self.goal.1,
+		));
+		let mut outcome = ScenarioOutcome {
+			arrived: false,
+			frames: 0,
+			cost: 0,
+			path: vec![self.source],
+		};
+		let mut route_index = 0;
+		while outcome.frames < self.max_frames {
+			app.update();
+			outcome.frames += 1;
+			let Some(next) = self.step(app.world(), nav_world, &outcome, &mut route_index) else {
+				continue;
+			};
+			let cost = self
+				.sector_cost_fields
+				.get_scaled()
+				.get(&next.0)
+				.map_or(255, |field| field.get_field_cell_value(next.1));
+			if cost == 255 {
+				// the actor has been led into a wall
+				break;
+			}
+			outcome.cost += cost as i32;
+			outcome.path.push(next);
+			if next == self.goal {
+				outcome.arrived = true;
+				break;
+			}
+		}
+		outcome
+	}
+	/// Find the [FieldCell] the actor moves into from its current position.
+	/// Returns [None] while the route or its fields are still being built
+	fn step(
+		&self,
+		world: &World,
+		nav_world: Entity,
+		outcome: &ScenarioOutcome,
+		route_index: &mut usize,
+	) -> Option<(SectorID, FieldCell)> {
+		let route_cache = world.get::<RouteCache>(nav_world)?;
+		let flow_cache = world.get::<FlowFieldCache>(nav_world)?;
+		let route =
+			route_cache.get_route(self.source.0, self.source.1, self.goal.0, self.goal.1)?;
+		let current = *outcome.path.last()?;
+		// the same sector may appear in the route more than once, always use
+		// the next entry of the sector the actor is in
+		let offset = route.get()[*route_index..]
+			.iter()
+			.position(|(sector_id, _)| *sector_id == current.0)?;
+		let goal_id = route.get()[*route_index + offset].1;
+		let value = flow_cache
+			.get_field(current.0, self.goal.0, goal_id)?
+			.get_field_cell_value(current.1);
+		*route_index += offset;
+		let (column, row) = self.map_dimensions.global_cell_from(current.0, current.1);
+		let (column, row) = if has_line_of_sight(value)
+			|| flow_cache.has_approach_line_of_sight(
+				current.0,
+				current.1,
+				self.goal.0,
+				self.goal.1,
+				None,
+			) {
+			let (goal_column, goal_row) = self
+				.map_dimensions
+				.global_cell_from(self.goal.0, self.goal.1);
+			let line = FieldCell::new(column as usize, row as usize)
+				.get_cells_between_points(&FieldCell::new(goal_column as usize, goal_row as usize));
+			let next = line.get(1)?;
+			(next.get_column() as u32, next.get_row() as u32)
+		} else {
+			let (column, row) = (column as i32, row as i32);
+			let (column, row) = match get_ordinal_from_bits(value) {
+				Ordinal::North => (column, row - 1),
+				Ordinal::East => (column + 1, row),
+				Ordinal::South => (column, row + 1),
+				Ordinal::West => (column - 1, row),
+				Ordinal::NorthEast => (column + 1, row - 1),
+				Ordinal::SouthEast => (column + 1, row + 1),
+				Ordinal::SouthWest => (column - 1, row + 1),
+				Ordinal::NorthWest => (column - 1, row - 1),
+				Ordinal::Zero => return None,
+			};
+			(u32::try_from(column).ok()?, u32::try_from(row).ok()?)
+		};
+		self.map_dimensions.sector_and_cell_from_global(column, row)
+	}
+	/// Run the scenario and panic with a description of what went wrong if
+	/// the actor doesn't arrive within the frame and cost bounds
+	pub fn assert_arrives(&self) {
+		let outcome = self.run();
+		assert!(
+			outcome.arrived,
+			"Actor of scenario {} didn't arrive within {} frames, it stopped at {:?}",
+			self.name,
+			self.max_frames,
+			outcome.path.last()
+		);
+		assert!(
+			outcome.cost <= self.max_cost,
+			"Actor of scenario {} arrived for a cost of {} rather than at most {}",
+			self.name,
+			outcome.cost,
+			self.max_cost
+		);
+	}
+}
+
+/// What happened to the simulated actor of a [Scenario]
+#[derive(Clone, Debug)]
+pub struct ScenarioOutcome {
+	/// Whether the actor reached the goal
+	arrived: bool,
+	/// Number of frames run
+	frames: u32,
+	/// Sum of the cost of each [FieldCell] the actor stepped into
+	cost: i32,
+	/// Each [FieldCell] the actor occupied, starting with the source
+	path: Vec<(SectorID, FieldCell)>,
+}
+
+impl ScenarioOutcome {
+	/// Whether the actor reached the goal
+	pub fn has_arrived(&self) -> bool {
+		self.arrived
+	}
+	/// Get the number of frames run
+	pub fn get_frames(&self) -> u32 {
+		self.frames
+	}
+	/// Get the sum of the cost of each [FieldCell] the actor stepped into
+	pub fn get_cost(&self) -> i32 {
+		self.cost
+	}
+	/// Get each [FieldCell] the actor occupied, starting with the source
+	pub fn get_path(&self) -> &Vec<(SectorID, FieldCell)> {
+		&self.path
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn maze_golden_path() {
+		Scenario::maze().assert_arrives();
+	}
+	#[test]
+	fn island_golden_path() {
+		Scenario::island().assert_arrives();
+	}
+	#[test]
+	fn chokepoint_golden_path() {
+		Scenario::chokepoint().assert_arrives();
+	}
+	#[test]
+	fn blocked_scenario_fails() {
+		let mut rows = vec!["..........".to_string(); 10];
+		rows[0] = ".S........".to_string();
+		rows[5] = "##########".to_string();
+		rows[9] = "........G.".to_string();
+		let rows: Vec<&str> = rows.iter().map(|row| row.as_str()).collect();
+		let outcome = Scenario::from_rows("blocked", &rows)
+			.with_max_frames(20)
+			.run();
+		assert!(!outcome.has_arrived());
+		assert_eq!(20, outcome.get_frames());
+	}
+}